@@ -1,4 +1,9 @@
-use std::{env, path::PathBuf, process::Command};
+use std::{
+    env,
+    path::PathBuf,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 fn main() {
     // Export the current git hash
@@ -11,11 +16,22 @@ fn main() {
         .unwrap_or_else(|| "UNKNW".into());
     println!("cargo:rustc-env=GIT_HASH={}", git_hash);
 
-    if cfg!(all(target_arch = "x86_64", target_feature = "avx")) {
-        println!("cargo:rustc-env=SIMD_ENABLED=SIMD JSON parsing enabled");
-    } else {
-        println!("cargo:rustc-env=SIMD_ENABLED=SIMD JSON parsing unavailable");
-    }
+    // Export the build date, honoring SOURCE_DATE_EPOCH for reproducible builds
+    let epoch = match env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch.parse::<i64>().ok(),
+        Err(_) => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .and_then(|d| i64::try_from(d.as_secs()).ok()),
+    };
+    let build_date = epoch
+        .and_then(|epoch| chrono::DateTime::from_timestamp(epoch, 0))
+        .map(|date| date.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .unwrap_or_else(|| "UNKNW".into());
+    println!("cargo:rustc-env=BUILD_DATE={}", build_date);
+
+    // buildstructor emits cfg(feature = "cargo-clippy")
+    println!("cargo:rustc-check-cfg=cfg(feature, values(\"cargo-clippy\"))");
 
     // Generate C FFI bindings
     let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
@@ -0,0 +1,8 @@
+(module m)
+(defcolumns A (B :byte) (C :binary))
+(defconstraint c () (* C (- A B)))
+(deflookup l (A) (B))
+
+(module n)
+(defcolumns (X :i16) (Y :array [2]))
+(defconstraint x () (- X [Y 1] [Y 2]))
@@ -0,0 +1,9 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+const CONSTRAINTS: &str = include_str!("constraints.lisp");
+
+fuzz_target!(|bytes: &[u8]| {
+    let mut cs = corset::fuzzing::compile(CONSTRAINTS).unwrap();
+    let _ = corset::fuzzing::import_json_trace(bytes, &mut cs);
+});
@@ -0,0 +1,6 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|serialized: &str| {
+    let _ = corset::fuzzing::load_constraints(serialized);
+});
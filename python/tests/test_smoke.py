@@ -0,0 +1,37 @@
+"""Smoke tests of the corset_py module; run with `pytest` once it is installed,
+e.g. with `maturin develop`."""
+import json
+
+import corset_py
+
+SOURCE = """
+(module m)
+(defcolumns A B)
+(definterleaved I (A B))
+(defconstraint double () (vanishes! (- B (* 2 A))))
+"""
+
+
+def write_trace(tmp_path, b):
+    trace = tmp_path / "trace.json"
+    trace.write_text(json.dumps({"m": {"A": [1, 2, 3], "B": b}}))
+    return str(trace)
+
+
+def test_compile():
+    cs = corset_py.compile([SOURCE])
+    assert "m.double" in cs.constraints()
+    assert {"m.A", "m.B", "m.I"} <= set(cs.columns())
+
+
+def test_check(tmp_path):
+    cs = corset_py.compile([SOURCE])
+    assert cs.check(write_trace(tmp_path, [2, 4, 6])) == []
+    assert cs.check(write_trace(tmp_path, [2, 4, 7])) == ["m.double"]
+
+
+def test_compute(tmp_path):
+    cs = corset_py.compile([SOURCE])
+    trace = cs.compute(write_trace(tmp_path, [2, 4, 6]))
+    assert list(trace.column("m.I"))[-6:] == [1, 2, 2, 4, 3, 6]
+    assert trace.column("m.I", raw=True).shape[1] == 32
@@ -0,0 +1,118 @@
+//! Gather in a single directory everything needed to publish a release of a
+//! constraint set: the compiled constraint set, its JSON rendition and the
+//! schema thereof, its reference documentation, its statistics and the build
+//! of corset that produced them, all indexed by a manifest.
+use anyhow::*;
+use log::*;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::{compiler::ConstraintSet, exporters, stats, version::BuildInfo};
+
+/// The name of the manifest indexing the other files of a bundle
+pub(crate) const MANIFEST: &str = "manifest.json";
+
+#[derive(Serialize)]
+pub(crate) struct BundledFile {
+    pub name: String,
+    pub description: &'static str,
+    pub bytes: usize,
+    pub sha256: String,
+}
+
+#[derive(Serialize)]
+pub(crate) struct Manifest {
+    pub corset: BuildInfo,
+    pub files: Vec<BundledFile>,
+}
+
+struct Bundler {
+    out_dir: PathBuf,
+    files: Vec<BundledFile>,
+}
+impl Bundler {
+    fn write(&mut self, name: &str, description: &'static str, content: Vec<u8>) -> Result<()> {
+        let path = self.out_dir.join(name);
+        std::fs::write(&path, &content)
+            .with_context(|| anyhow!("while writing to `{}`", path.display()))?;
+        info!("wrote {}", path.display());
+        self.files.push(BundledFile {
+            name: name.to_owned(),
+            description,
+            bytes: content.len(),
+            sha256: Sha256::digest(&content)
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect(),
+        });
+        Ok(())
+    }
+
+    fn render<F>(&mut self, name: &str, description: &'static str, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Vec<u8>) -> Result<()>,
+    {
+        let mut content = Vec::new();
+        f(&mut content).with_context(|| anyhow!("while rendering `{}`", name))?;
+        self.write(name, description, content)
+    }
+}
+
+/// Write the release artifacts of `cs`, that must be fully expanded, to
+/// `out_dir`, and return the manifest indexing them; the build information of
+/// `cs` defaults to the running corset
+pub(crate) fn bundle(cs: &mut ConstraintSet, out_dir: &Path) -> Result<Manifest> {
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| anyhow!("while creating `{}`", out_dir.display()))?;
+    let build = cs.build_info.get_or_insert_with(BuildInfo::current).clone();
+
+    let mut bundler = Bundler {
+        out_dir: out_dir.to_owned(),
+        files: Vec::new(),
+    };
+    bundler.write(
+        "constraints.bin",
+        "the compiled constraint set",
+        cs.to_binary()?,
+    )?;
+    bundler.render(
+        "constraints.json",
+        "the constraint set in the stable JSON format",
+        |out| exporters::json::render(cs, true, out),
+    )?;
+    bundler.write(
+        "constraints.schema.json",
+        "the JSON Schema of constraints.json",
+        exporters::json::SCHEMA.as_bytes().to_vec(),
+    )?;
+    bundler.render(
+        "constraints.md",
+        "the reference of the modules, columns and constraints",
+        |out| exporters::docs::render_markdown(cs, out),
+    )?;
+    bundler.render(
+        "constraints.html",
+        "the reference of the modules, columns and constraints",
+        |out| exporters::docs::render_html(cs, out),
+    )?;
+    bundler.write(
+        "stats.json",
+        "the number of columns, computed columns, constraints and lookups of every module, along with its shift window",
+        serde_json::to_vec_pretty(&stats::module_stats(cs))?,
+    )?;
+    bundler.write(
+        "version.json",
+        "the build of corset that produced the bundle",
+        serde_json::to_vec_pretty(&build)?,
+    )?;
+
+    let manifest = Manifest {
+        corset: build,
+        files: bundler.files,
+    };
+    let path = out_dir.join(MANIFEST);
+    std::fs::write(&path, serde_json::to_vec_pretty(&manifest)?)
+        .with_context(|| anyhow!("while writing to `{}`", path.display()))?;
+    Ok(manifest)
+}
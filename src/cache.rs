@@ -0,0 +1,225 @@
+//! A cache of the constraint sets compiled from Corset sources, so that
+//! unchanged sources are not compiled again on every invocation.
+//!
+//! The cache works at two levels:
+//!   - every source file, i.e. every module, is parsed only once, its AST
+//!     being cached under the hash of its content;
+//!   - as the symbols are resolved across all the modules at once, the
+//!     constraint set is cached as a whole, before any expansion, keyed on
+//!     the hash of its pre-processed sources, of all the compilation settings
+//!     and of the build of corset.
+//!
+//! Modifying a module thus only requires to parse it again, then to resolve
+//! the symbols of all the modules. The warnings emitted while compiling are
+//! stored alongside the constraint set, to be emitted again whenever it is
+//! reused.
+use anyhow::*;
+use itertools::Itertools;
+use log::*;
+use owo_colors::OwoColorize;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+use crate::compiler::{parser::Ast, CompileSettings, ConstraintSet};
+
+/// Beyond this many compiled constraint sets, the oldest ones are evicted
+/// from the cache
+const MAX_ENTRIES: usize = 16;
+/// Beyond this many parsed modules, the oldest ones are evicted from the
+/// cache
+const MAX_ASTS: usize = 1024;
+const EXTENSION: &str = "bin";
+const AST_EXTENSION: &str = "ast";
+
+/// A compiled constraint set, along with the warnings emitted while compiling
+/// it
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    warnings: Vec<String>,
+    /// the constraint set, in its binary format
+    constraints: Vec<u8>,
+}
+
+/// Feed `bytes` to `hasher`, prefixed by their length so that consecutive
+/// fields can not be confused
+fn feed(hasher: &mut Sha256, bytes: &[u8]) {
+    hasher.update((bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+}
+
+/// A hasher already fed with the identity of this build of corset
+fn build_hasher() -> Sha256 {
+    let mut hasher = Sha256::new();
+    feed(
+        &mut hasher,
+        &serde_json::to_vec(&crate::version::BuildInfo::current()).unwrap_or_default(),
+    );
+    // development builds may share their version and commit
+    if let Some(built) = std::env::current_exe()
+        .and_then(std::fs::metadata)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+    {
+        feed(&mut hasher, &built.as_nanos().to_le_bytes());
+    }
+    hasher
+}
+
+fn hex(hasher: Sha256) -> String {
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub(crate) struct Cache {
+    dir: PathBuf,
+}
+impl Cache {
+    /// A cache stored in `dir` if set, or in the `corset` directory of the
+    /// user cache directory otherwise, if there is one
+    pub(crate) fn new(dir: Option<&str>) -> Option<Cache> {
+        dir.map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("XDG_CACHE_HOME")
+                    .filter(|d| !d.is_empty())
+                    .map(|d| PathBuf::from(d).join("corset"))
+            })
+            .or_else(|| {
+                std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache").join("corset"))
+            })
+            .map(|dir| Cache { dir })
+    }
+
+    /// The key identifying the compilation of `sources` with `settings` by
+    /// this build of corset
+    pub(crate) fn key(sources: &[(String, String)], settings: &CompileSettings) -> String {
+        let mut hasher = build_hasher();
+        // destructured, so that new settings can not be forgotten here
+        let CompileSettings {
+            debug,
+            assumptions,
+            promotion_gap,
+        } = settings;
+        feed(&mut hasher, &[*debug as u8]);
+        for (name, value) in assumptions.iter().sorted() {
+            feed(&mut hasher, name.as_bytes());
+            feed(&mut hasher, &value.to_signed_bytes_le());
+        }
+        feed(
+            &mut hasher,
+            &promotion_gap
+                .map(|g| g as u64 + 1)
+                .unwrap_or(0)
+                .to_le_bytes(),
+        );
+        for (name, content) in sources {
+            feed(&mut hasher, name.as_bytes());
+            feed(&mut hasher, content.as_bytes());
+        }
+        hex(hasher)
+    }
+
+    /// The key identifying the parsing of the source `content` by this build
+    /// of corset
+    fn ast_key(content: &str) -> String {
+        let mut hasher = build_hasher();
+        feed(&mut hasher, content.as_bytes());
+        hex(hasher)
+    }
+
+    fn path(&self, key: &str, extension: &str) -> PathBuf {
+        self.dir.join(key).with_extension(extension)
+    }
+
+    /// The item cached under `key`, if any; an entry that can not be read is
+    /// ignored.
+    fn read<T: DeserializeOwned>(&self, key: &str, extension: &str) -> Option<T> {
+        let path = self.path(key, extension);
+        let bytes = std::fs::read(&path).ok()?;
+        match bincode::deserialize(&bytes) {
+            Result::Ok(x) => Some(x),
+            Err(e) => {
+                debug!("ignoring the cache entry `{}`: {:?}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Store `x` under `key`, then evict the oldest entries of the same kind
+    /// if there are more than `max` of them
+    fn write<T: Serialize>(&self, key: &str, extension: &str, max: usize, x: &T) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| anyhow!("while creating `{}`", self.dir.display()))?;
+        // concurrent invocations must never read a partially written entry
+        let tmp = self.dir.join(format!("{}.{}.tmp", key, std::process::id()));
+        std::fs::write(&tmp, bincode::serialize(x)?)
+            .with_context(|| anyhow!("while writing `{}`", tmp.display()))?;
+        let path = self.path(key, extension);
+        std::fs::rename(&tmp, &path)
+            .with_context(|| anyhow!("while writing `{}`", path.display()))?;
+
+        let entries = std::fs::read_dir(&self.dir)?
+            .filter_map(|e| e.ok())
+            .filter(|e| {
+                e.path()
+                    .extension()
+                    .map(|x| x == extension)
+                    .unwrap_or(false)
+            })
+            .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+            .sorted()
+            .collect::<Vec<_>>();
+        for (_, path) in entries.iter().take(entries.len().saturating_sub(max)) {
+            debug!("evicting `{}` from the cache", path.display());
+            let _ = std::fs::remove_file(path);
+        }
+        Ok(())
+    }
+
+    /// The constraint set cached under `key`, if any, along with the warnings
+    /// emitted when it was compiled
+    pub(crate) fn get(&self, key: &str) -> Option<(ConstraintSet, Vec<String>)> {
+        let entry: Entry = self.read(key, EXTENSION)?;
+        let path = self.path(key, EXTENSION);
+        match ConstraintSet::from_bytes(&entry.constraints, &path.to_string_lossy()) {
+            Result::Ok(cs) => Some((cs, entry.warnings)),
+            Err(e) => {
+                debug!("ignoring the cache entry `{}`: {:?}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Store `cs`, compiled with the `warnings`, under `key`
+    pub(crate) fn put(&self, key: &str, cs: &ConstraintSet, warnings: &[String]) -> Result<()> {
+        self.write(
+            key,
+            EXTENSION,
+            MAX_ENTRIES,
+            &Entry {
+                warnings: warnings.to_vec(),
+                constraints: cs.to_binary()?,
+            },
+        )
+    }
+
+    /// The AST of the source `content`, parsed anew unless it is cached
+    pub(crate) fn parse(&self, name: &str, content: &str) -> Result<Ast> {
+        let key = Cache::ast_key(content);
+        if let Some(ast) = self.read(&key, AST_EXTENSION) {
+            debug!("reusing the cached parsing of {}", name);
+            return Ok(ast);
+        }
+        info!("Parsing {}", name.bright_white().bold());
+        let ast = crate::compiler::parser::parse_source(content)
+            .with_context(|| anyhow!("parsing `{}`", name))?;
+        if let Err(e) = self.write(&key, AST_EXTENSION, MAX_ASTS, &ast) {
+            debug!("failed to cache the parsing of {}: {:?}", name, e);
+        }
+        Ok(ast)
+    }
+}
@@ -1,8 +1,14 @@
 use crate::{
     column::{ColumnSet, Value},
-    compiler::{Constraint, ConstraintSet, Domain, EvalSettings, Expression, Node},
+    compiler::{
+        ColumnRef, Constraint, ConstraintSet, Domain, EvalSettings, Expression, Magma, Node,
+        RawMagma,
+    },
+    compute::LazyComputer,
     pretty::*,
-    structs::Handle,
+    run_stats,
+    structs::{Handle, Span},
+    utils::{ensure_deadline, ensure_not_cancelled, is_cancelled, is_deadline_exceeded},
 };
 use anyhow::*;
 use cached::SizedCache;
@@ -10,7 +16,16 @@ use itertools::Itertools;
 use log::*;
 use owo_colors::OwoColorize;
 use rayon::prelude::*;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    ops::Range,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -23,16 +38,296 @@ enum CheckingError {
     MismatchingLengths(Error),
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct DebugSettings {
+/// How failing constraints are reported
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportStyle {
+    /// failing constraints are not detailed
+    Silent,
+    /// failing constraints are detailed in plain text, e.g. for log files
+    Plain,
+    /// failing constraints are detailed with colors, e.g. for terminals
+    Colored,
+}
+impl ReportStyle {
+    /// Report the failure of the constraint `handle`, defined at `span` if
+    /// it stems from the sources
+    fn print(&self, handle: &Handle, span: Option<&Span>, trace: &str) {
+        let defined_at = span
+            .map(|span| format!(" (defined at {})", span))
+            .unwrap_or_default();
+        match self {
+            ReportStyle::Silent => {}
+            ReportStyle::Plain => println!(
+                "{} failed{}:\n{}\n",
+                handle,
+                defined_at,
+                strip_colors(trace)
+            ),
+            ReportStyle::Colored => println!(
+                "{} failed{}:\n{}\n",
+                handle.to_string().red().bold(),
+                defined_at.bright_white(),
+                trace
+            ),
+        }
+    }
+}
+
+/// Remove the ANSI color escape sequences from `s`
+pub(crate) fn strip_colors(s: &str) -> String {
+    let mut r = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            r.push(c);
+        }
+    }
+    r
+}
+
+/// How many rows of a constraint are checked at once by a thread; the rows
+/// verified so far are recorded in a checkpoint between such chunks
+const CHECKPOINT_ROWS: isize = 1 << 16;
+/// How many rows to check between two checks of the deadline
+const DEADLINE_ROWS: isize = 1 << 12;
+
+/// The names of the failed constraints, i.e. their full handles, per module
+type Failures = BTreeMap<String, BTreeSet<String>>;
+
+/// The progress of a check, that can be saved to resume it later on the same
+/// trace, e.g. after a restart. Constraints are identified by their name.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// the constraints that have been fully checked and hold
+    pub done: BTreeSet<String>,
+    /// the constraints that have been found to fail
+    pub failed: BTreeSet<String>,
+    /// for the constraints being checked, the index of the expression being
+    /// checked and the first row that has not been verified yet
+    pub rows: BTreeMap<String, (usize, isize)>,
+}
+
+type SaveCheckpoint = dyn Fn(&Checkpoint) -> Result<()> + Send + Sync;
+
+/// Keeps track of the progress of a check, and periodically saves it.
+pub struct Checkpointer {
+    progress: Mutex<Checkpoint>,
+    save: Box<SaveCheckpoint>,
+    every: Duration,
+    last_save: Mutex<Instant>,
+}
+impl std::fmt::Debug for Checkpointer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Checkpointer")
+            .field("progress", &self.progress)
+            .field("every", &self.every)
+            .finish()
+    }
+}
+impl Checkpointer {
+    /// Resume the check from `progress`, saving it with `save` at most once
+    /// every `every`
+    #[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+    pub fn new(
+        progress: Checkpoint,
+        every: Duration,
+        save: impl Fn(&Checkpoint) -> Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        Checkpointer {
+            progress: Mutex::new(progress),
+            save: Box::new(save),
+            every,
+            last_save: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// The progress made so far
+    pub fn progress(&self) -> Checkpoint {
+        self.progress.lock().unwrap().clone()
+    }
+
+    /// Save the progress, unconditionally if `force` is set, or only if the
+    /// last save is old enough otherwise. A failing save is not fatal, as it
+    /// would only cost some work when resuming.
+    fn save(&self, force: bool) {
+        let mut last_save = self.last_save.lock().unwrap();
+        if force || last_save.elapsed() >= self.every {
+            let progress = self.progress();
+            if let Err(e) = (self.save)(&progress) {
+                warn!("failed to save checkpoint: {:?}", e);
+            }
+            *last_save = Instant::now();
+        }
+    }
+
+    fn status(&self, name: &str) -> Option<bool> {
+        let progress = self.progress.lock().unwrap();
+        if progress.done.contains(name) {
+            Some(true)
+        } else if progress.failed.contains(name) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// The first row to check for the `part`-th expression of `name`
+    fn resume_row(&self, name: &str, part: usize) -> isize {
+        match self.progress.lock().unwrap().rows.get(name) {
+            Some(&(p, row)) if p == part => row,
+            _ => 0,
+        }
+    }
+
+    /// Whether the `part`-th expression of `name` has already been checked
+    fn part_done(&self, name: &str, part: usize) -> bool {
+        matches!(self.progress.lock().unwrap().rows.get(name), Some(&(p, _)) if p > part)
+    }
+
+    fn rows_verified(&self, name: &str, part: usize, row: isize) {
+        self.progress
+            .lock()
+            .unwrap()
+            .rows
+            .insert(name.to_owned(), (part, row));
+        self.save(false);
+    }
+
+    fn finished(&self, name: &str, ok: bool) {
+        {
+            let mut progress = self.progress.lock().unwrap();
+            progress.rows.remove(name);
+            if ok {
+                progress.done.insert(name.to_owned());
+            } else {
+                progress.failed.insert(name.to_owned());
+            }
+        }
+        self.save(false);
+    }
+}
+
+/// A row on which a constraint does not hold
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Violation {
+    /// the failing constraint
+    pub constraint: String,
+    /// the failing row; for a lookup, the row of the included columns
+    pub row: isize,
+    /// the columns read by the failing expression
+    pub columns: Vec<String>,
+    /// if required, the value on the failing row of every sub-expression of
+    /// the failing expression
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<EvaluatedExpr>,
+}
+
+/// A sub-expression of a failing constraint, and its value on the failing row
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EvaluatedExpr {
+    pub expr: String,
+    /// the value, or `nil` if it could not be computed
+    pub value: String,
+}
+
+/// The rows on which a constraint failed, as listed in a JSON report
+#[derive(Serialize, Debug)]
+pub struct ViolatedConstraint {
+    pub constraint: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<FailingRow>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FailingRow {
+    pub row: isize,
+    pub values: Vec<EvaluatedExpr>,
+}
+
+/// The machine-readable outcome of a check, e.g. for CI systems
+#[derive(Serialize, Debug)]
+pub struct JsonReport {
+    pub success: bool,
+    /// the reason why the check failed, if not only violated constraints
+    pub error: Option<String>,
+    pub violated: Vec<ViolatedConstraint>,
+}
+
+/// The rows on which the constraints failed during a check, saved so that
+/// the inspector may later highlight them
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct FailureReport {
+    pub violations: Vec<Violation>,
+}
+impl FailureReport {
+    pub fn load(path: &str) -> Result<Self> {
+        let content =
+            std::fs::read_to_string(path).with_context(|| anyhow!("while reading `{}`", path))?;
+        serde_json::from_str(&content).with_context(|| anyhow!("while parsing `{}`", path))
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| anyhow!("while writing `{}`", path))
+    }
+
+    /// Gather the violations per constraint, given the `outcome` of the check
+    pub fn json_report(&self, outcome: &Result<()>) -> JsonReport {
+        let mut violated: Vec<ViolatedConstraint> = Vec::new();
+        for v in self.violations.iter().sorted() {
+            let row = FailingRow {
+                row: v.row,
+                values: v.values.clone(),
+            };
+            match violated.last_mut() {
+                Some(last) if last.constraint == v.constraint => {
+                    last.columns.extend(v.columns.iter().cloned());
+                    last.rows.push(row);
+                }
+                _ => violated.push(ViolatedConstraint {
+                    constraint: v.constraint.clone(),
+                    columns: v.columns.clone(),
+                    rows: vec![row],
+                }),
+            }
+        }
+        for c in violated.iter_mut() {
+            c.columns.sort();
+            c.columns.dedup();
+        }
+
+        JsonReport {
+            // with `continue_on_error`, a check may succeed despite violations
+            success: outcome.is_ok() && violated.is_empty(),
+            error: outcome
+                .as_ref()
+                .err()
+                .map(|e| strip_colors(&format!("{:?}", e))),
+            violated,
+        }
+    }
+}
+
+/// Controls how a trace is checked, and how the results are reported; it does
+/// not depend on the terminal state, which is left to the caller to inspect.
+#[derive(Clone, Debug)]
+pub struct CheckConfig {
+    /// whether to display the checking progress on stderr
+    progress: bool,
+    /// how to report the failing constraints
+    report: ReportStyle,
     /// whether to skip reporting s-exps reducing to 0
     unclutter: bool,
     /// whether to dim s-exps reducing to 0
     dim: bool,
     /// whether to stop reporting a constraint on the first failure
     continue_on_error: bool,
-    /// whether to report computation details on failing constraints
-    report: bool,
     /// how many lines to show left of the failure point
     context_span_before: isize,
     /// how many lines to show right of the failure point
@@ -41,18 +336,46 @@ pub struct DebugSettings {
     full_trace: bool,
     /// whether to display the original source code along the compiled form
     src: bool,
+    /// the modules in which reading a column beyond its spilling, i.e. its
+    /// padding value, is a failure
+    strict_padding: Vec<String>,
+    /// where to record the progress of the check, if it must be resumable
+    checkpoint: Option<Arc<Checkpointer>>,
+    /// if set, only evaluate the constraints on these rows; shifted columns
+    /// may still be read outside of the window
+    rows: Option<Range<isize>>,
+    /// where to record the rows on which the constraints fail, if required
+    failures: Option<Arc<Mutex<FailureReport>>>,
+    /// whether to record the values of the sub-expressions of the failing
+    /// constraints along their failing rows
+    record_values: bool,
+    /// whether to fail on columns holding values outside of their magma,
+    /// whether the constraints hold or not
+    strict_magma: bool,
+}
+impl Default for CheckConfig {
+    fn default() -> Self {
+        Self::new()
+    }
 }
-impl DebugSettings {
+impl CheckConfig {
     pub fn new() -> Self {
-        DebugSettings {
+        CheckConfig {
+            progress: false,
+            report: ReportStyle::Silent,
             unclutter: false,
             dim: false,
             continue_on_error: false,
-            report: false,
             context_span_before: 2,
             context_span_after: 2,
             full_trace: false,
             src: false,
+            strict_padding: Vec::new(),
+            checkpoint: None,
+            rows: None,
+            failures: None,
+            record_values: false,
+            strict_magma: false,
         }
     }
     pub fn dim(self, x: bool) -> Self {
@@ -73,9 +396,15 @@ impl DebugSettings {
             ..self
         }
     }
-    pub fn report(self, x: bool) -> Self {
+    pub fn report(self, x: ReportStyle) -> Self {
         Self { report: x, ..self }
     }
+    pub fn progress(self, x: bool) -> Self {
+        Self {
+            progress: x,
+            ..self
+        }
+    }
     pub fn context_span(self, x: isize) -> Self {
         Self {
             context_span_before: x,
@@ -109,6 +438,113 @@ impl DebugSettings {
             ..self
         }
     }
+    pub fn strict_padding(self, modules: Vec<String>) -> Self {
+        Self {
+            strict_padding: modules,
+            ..self
+        }
+    }
+    #[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+    pub fn checkpoint(self, x: Arc<Checkpointer>) -> Self {
+        Self {
+            checkpoint: Some(x),
+            ..self
+        }
+    }
+    pub fn rows(self, x: Option<Range<isize>>) -> Self {
+        Self { rows: x, ..self }
+    }
+    pub fn record_failures(self, x: Arc<Mutex<FailureReport>>) -> Self {
+        Self {
+            failures: Some(x),
+            ..self
+        }
+    }
+    pub fn record_values(self, x: bool) -> Self {
+        Self {
+            record_values: x,
+            ..self
+        }
+    }
+    pub fn strict_magma(self, x: bool) -> Self {
+        Self {
+            strict_magma: x,
+            ..self
+        }
+    }
+
+    /// Record, if required, that `constraint` fails on `row`, where it
+    /// evaluates `exprs`
+    fn record_violation(
+        &self,
+        cs: &ConstraintSet,
+        constraint: &Handle,
+        row: isize,
+        exprs: &[Node],
+    ) {
+        if let Some(failures) = self.failures.as_ref() {
+            let columns = exprs
+                .iter()
+                .flat_map(|e| e.dependencies())
+                .map(|h| cs.handle(&h).to_string())
+                .sorted()
+                .dedup()
+                .collect();
+            let values = if self.record_values {
+                evaluated_subexprs(cs, exprs, row)
+            } else {
+                Vec::new()
+            };
+            failures.lock().unwrap().violations.push(Violation {
+                constraint: constraint.to_string(),
+                row,
+                columns,
+                values,
+            });
+        }
+    }
+
+    /// The rows of `0..l` on which the constraints must be evaluated
+    fn window(&self, l: isize) -> Range<isize> {
+        match self.rows.as_ref() {
+            Some(rows) => rows.start.max(0)..rows.end.min(l),
+            None => 0..l,
+        }
+    }
+}
+
+/// The value on row `i` of all the distinct non-constant sub-expressions of
+/// `exprs`, outermost first
+fn evaluated_subexprs(cs: &ConstraintSet, exprs: &[Node], i: isize) -> Vec<EvaluatedExpr> {
+    fn rec(cs: &ConstraintSet, n: &Node, i: isize, ax: &mut Vec<EvaluatedExpr>) {
+        let expr = n.to_string();
+        if !n.is_constant() && !ax.iter().any(|e| e.expr == expr) {
+            let value = n
+                .eval(
+                    i,
+                    |handle, i, wrap| cs.columns.get(handle, i, wrap),
+                    &mut None,
+                    &Default::default(),
+                )
+                .map(|v| v.pretty())
+                .unwrap_or_else(|| "nil".into());
+            ax.push(EvaluatedExpr { expr, value });
+        }
+        match n.e() {
+            Expression::Funcall { args, .. } | Expression::List(args) => {
+                for a in args {
+                    rec(cs, a, i, ax);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut r = Vec::new();
+    for e in exprs {
+        rec(cs, e, i, &mut r);
+    }
+    r
 }
 
 /// Pretty print an expresion and all its intermediate value for debugging (or
@@ -125,7 +561,7 @@ fn fail(
     expr: &Node,
     i: isize,
     wrap: bool,
-    settings: DebugSettings,
+    settings: &CheckConfig,
 ) -> Result<()> {
     let handles = if settings.full_trace {
         let module = &cs
@@ -232,7 +668,7 @@ fn check_constraint_at(
     wrap: bool,
     fail_on_oob: bool,
     cache: &mut Option<SizedCache<Value, Value>>,
-    settings: DebugSettings,
+    settings: &CheckConfig,
 ) -> Result<()> {
     let r = expr.eval(
         i,
@@ -250,10 +686,19 @@ fn check_constraint_at(
     Ok(())
 }
 
-fn check_inrange(expr: &Node, cs: &ConstraintSet, max: &Value) -> Result<()> {
+fn check_inrange(
+    handle: &Handle,
+    expr: &Node,
+    cs: &ConstraintSet,
+    max: &Value,
+    settings: &CheckConfig,
+) -> Result<()> {
     let l = cs.dependencies_len(expr, false)?;
     if let Some(l) = l {
-        for i in 0..l as isize {
+        for i in settings.window(l as isize) {
+            if i % DEADLINE_ROWS == 0 {
+                ensure_deadline("checking the constraints")?;
+            }
             let r = expr
                 .eval(
                     i,
@@ -263,6 +708,7 @@ fn check_inrange(expr: &Node, cs: &ConstraintSet, max: &Value) -> Result<()> {
                 )
                 .unwrap();
             if r.ge(max) {
+                settings.record_violation(cs, handle, i, std::slice::from_ref(expr));
                 bail!(
                     "{} = {} > {}",
                     expr.to_string().white().bold(),
@@ -282,29 +728,98 @@ fn check_constraint(
     expr: &Node,
     domain: &Option<Domain<isize>>,
     name: &Handle,
-    settings: DebugSettings,
+    part: usize,
+    settings: &CheckConfig,
 ) -> Result<()> {
     let l = cs
         .dependencies_len(expr, true)
         .map_err(CheckingError::MismatchingLengths)?;
     if let Some(l) = l {
-        let mut cache = Some(cached::SizedCache::with_size(200000)); // ~1.60MB cache
         match domain {
             Some(is) => {
-                for i in is.iter() {
-                    check_constraint_at(cs, expr, i, true, true, &mut cache, settings)?;
+                let mut cache = Some(cached::SizedCache::with_size(200000)); // ~1.60MB cache
+                ensure_deadline("checking the constraints")?;
+                let window = settings.window(l as isize);
+                // negative points of the domain are counted from the end
+                for i in is
+                    .iter()
+                    .filter(|i| window.contains(&if *i < 0 { i + l as isize } else { *i }))
+                {
+                    let r = check_constraint_at(cs, expr, i, true, true, &mut cache, settings);
+                    if r.is_err() {
+                        let row = if i < 0 { i + l as isize } else { i };
+                        settings.record_violation(cs, name, row, std::slice::from_ref(expr));
+                    }
+                    r?;
                 }
             }
             None => {
-                for i in 0..l as isize {
-                    let err = check_constraint_at(cs, expr, i, false, false, &mut cache, settings)
+                // row 0 is the padding row, and negative rows the spilling
+                let padding_end = if settings.strict_padding.contains(&name.module) {
+                    padding_end(cs, std::slice::from_ref(expr))
+                } else {
+                    0
+                };
+                let window = settings.window(l as isize);
+                let start = settings
+                    .checkpoint
+                    .as_ref()
+                    .map(|c| c.resume_row(&name.to_string(), part))
+                    .unwrap_or(0)
+                    .max(window.start);
+                // the failing rows, up to the first one unless the check
+                // continues on errors
+                let check_rows = |rows: Range<isize>| -> Result<Vec<_>> {
+                    let mut cache = Some(cached::SizedCache::with_size(200000)); // ~1.60MB cache
+                    let mut failures = Vec::new();
+                    for i in rows {
+                        if i % DEADLINE_ROWS == 0 {
+                            ensure_deadline("checking the constraints")?;
+                        }
+                        let err = if i < padding_end {
+                            fail(cs, expr, i, false, settings)
+                                .map_err(|e| anyhow!("row {} reads into the padding\n{}", i, e))
+                        } else {
+                            check_constraint_at(cs, expr, i, false, false, &mut cache, settings)
+                        }
                         .map_err(|e| CheckingError::FailingConstraint(name.clone(), e.to_string()));
 
-                    if err.is_err() {
-                        if settings.continue_on_error {
-                            eprintln!("{:?}", err);
-                        } else {
-                            bail!(err.err().unwrap());
+                        if err.is_err() {
+                            failures.push((i, err));
+                            if !settings.continue_on_error {
+                                break;
+                            }
+                        }
+                    }
+                    Ok(failures)
+                };
+
+                // large modules are split in chunks of rows checked in
+                // parallel, by waves after which the progress is recorded;
+                // failures are reported in the order of the rows
+                let chunks = (start..window.end)
+                    .step_by(CHECKPOINT_ROWS as usize)
+                    .map(|from| from..(from + CHECKPOINT_ROWS).min(window.end))
+                    .collect::<Vec<_>>();
+                for wave in chunks.chunks(rayon::current_num_threads().max(1)) {
+                    let outcomes = wave
+                        .par_iter()
+                        .map(|rows| check_rows(rows.clone()))
+                        .collect::<Vec<_>>();
+                    for outcome in outcomes {
+                        for (i, err) in outcome? {
+                            settings.record_violation(cs, name, i, std::slice::from_ref(expr));
+                            if settings.continue_on_error {
+                                eprintln!("{:?}", err);
+                            } else {
+                                bail!(err.err().unwrap());
+                            }
+                        }
+                    }
+                    let verified = wave.last().unwrap().end;
+                    if verified < window.end {
+                        if let Some(checkpoint) = settings.checkpoint.as_ref() {
+                            checkpoint.rows_verified(&name.to_string(), part, verified);
                         }
                     }
                 }
@@ -317,11 +832,29 @@ fn check_constraint(
     }
 }
 
+/// The row before which evaluating `exprs` reads a column beyond its spilling,
+/// i.e. falls back to its padding value
+fn padding_end(cs: &ConstraintSet, exprs: &[Node]) -> isize {
+    exprs
+        .iter()
+        .flat_map(|e| e.leaves())
+        .filter_map(|leaf| match leaf.e() {
+            Expression::Column { handle, shift, .. }
+            | Expression::ExoColumn { handle, shift, .. } => {
+                Some(-cs.spilling_for_column(handle).unwrap_or(0) - *shift as isize)
+            }
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
 fn check_lookup(
     cs: &ConstraintSet,
     handle: &Handle,
     parents: &[Node],
     children: &[Node],
+    settings: &CheckConfig,
 ) -> Result<()> {
     // Compute the LC \sum_k (k+1) × x_k[i]
     fn pseudo_rlc(exps: &[Node], i: usize, cs: &ColumnSet) -> Value {
@@ -381,12 +914,36 @@ fn check_lookup(
     let child_module = cs.module_of_exprs(children).unwrap();
     let child_len = cs.iter_len(&child_module);
 
+    if settings.strict_padding.contains(&parent_module) {
+        let end = padding_end(cs, parents);
+        if end > 0 {
+            bail!("parent rows 0..{} read into the padding", end)
+        }
+    }
+    let children_padding_end = if settings.strict_padding.contains(&child_module) {
+        padding_end(cs, children)
+    } else {
+        0
+    };
+
+    ensure_deadline("checking the lookups")?;
     let parent_hashes: HashSet<_> = (0..parent_len)
         .map(|i| pseudo_rlc(parents, i, &cs.columns))
         .collect();
 
-    for i in 0..child_len {
+    // only the children are restricted to the window, as they may look up any
+    // row of the parents
+    let window = settings.window(child_len as isize);
+    for i in window.start as usize..window.end as usize {
+        if i as isize % DEADLINE_ROWS == 0 {
+            ensure_deadline("checking the lookups")?;
+        }
+        if (i as isize) < children_padding_end {
+            settings.record_violation(cs, handle, i as isize, children);
+            bail!("row {} reads into the padding", i);
+        }
         if !parent_hashes.contains(&pseudo_rlc(children, i, &cs.columns)) {
+            settings.record_violation(cs, handle, i as isize, children);
             let pretty_expected_matches = parents
                 .iter()
                 .zip(children.iter().zip(children.iter().map(|e| {
@@ -423,157 +980,663 @@ fn check_lookup(
     Ok(())
 }
 
-pub fn check(
+/// Check the constraints of `todo` in parallel, and return the names of the
+/// failing ones; `checked` counts the constraints checked so far, out of
+/// `total`.
+fn check_constraints(
     cs: &ConstraintSet,
-    only: &Option<Vec<String>>,
-    skip: &[String],
-    settings: DebugSettings,
-) -> Result<()> {
-    if cs.columns.is_empty() {
-        info!("Skipping empty trace");
-        return Ok(());
-    }
-
-    let todo = cs
-        .constraints
-        .iter()
-        .filter(|c| only.as_ref().map(|o| o.contains(&c.name())).unwrap_or(true))
-        .filter(|c| !skip.contains(&c.name()))
-        .collect::<Vec<_>>();
-    if todo.is_empty() {
-        bail!("refusing to check an empty constraint set")
-    }
-
-    let failed = todo
-        .par_iter()
-        .filter_map(|c| {
-            match c {
-                Constraint::Vanishes {
-                    handle: name,
-                    domain,
-                    expr,
-                } => {
-                    if matches!(expr.e(), Expression::Void) {
-                        return None;
-                    }
+    todo: &[&Constraint],
+    settings: &CheckConfig,
+    checked: &AtomicUsize,
+    total: usize,
+) -> Failures {
+    let _phase = run_stats::phase("check");
+    let check_one = |c: &Constraint| -> Option<Handle> {
+        match c {
+            Constraint::Vanishes {
+                handle: name,
+                domain,
+                expr,
+            } => {
+                if matches!(expr.e(), Expression::Void) {
+                    return None;
+                }
 
-                    match expr.as_ref().e() {
-                        Expression::List(es) => {
-                            for e in es {
-                                if let Err(err) = check_constraint(cs, e, domain, name, settings) {
-                                    match err.downcast_ref::<CheckingError>() {
-                                        Some(err) => match err {
-                                            CheckingError::NoColumnsFound(_) => {
-                                                warn!("{}", err);
-                                                break;
-                                            }
-                                            CheckingError::FailingConstraint(handle, trace) => {
-                                                if settings.report {
-                                                    println!(
-                                                        "{} failed:\n{}\n",
-                                                        handle.to_string().red().bold(),
-                                                        trace
-                                                    );
-                                                }
-                                                return Some(name.to_owned());
-                                            }
-                                            CheckingError::MismatchingLengths(err) => {
-                                                error!("{err}");
-                                                return Some(name.to_owned());
-                                            }
-                                        },
-                                        None => {
+                match expr.as_ref().e() {
+                    Expression::List(es) => {
+                        for (k, e) in es.iter().enumerate() {
+                            if settings
+                                .checkpoint
+                                .as_ref()
+                                .map(|c| c.part_done(&name.to_string(), k))
+                                .unwrap_or(false)
+                            {
+                                continue;
+                            }
+                            if let Err(err) = check_constraint(cs, e, domain, name, k, settings) {
+                                match err.downcast_ref::<CheckingError>() {
+                                    Some(err) => match err {
+                                        CheckingError::NoColumnsFound(_) => {
                                             warn!("{}", err);
                                             break;
                                         }
+                                        CheckingError::FailingConstraint(handle, trace) => {
+                                            settings.report.print(
+                                                handle,
+                                                cs.spans.get(handle),
+                                                trace,
+                                            );
+                                            return Some(name.to_owned());
+                                        }
+                                        CheckingError::MismatchingLengths(err) => {
+                                            error!("{err}");
+                                            return Some(name.to_owned());
+                                        }
+                                    },
+                                    None => {
+                                        if !is_deadline_exceeded(&err) {
+                                            warn!("{}", err);
+                                        }
+                                        break;
                                     }
                                 }
                             }
-                            None
+                            if let Some(checkpoint) = settings.checkpoint.as_ref() {
+                                checkpoint.rows_verified(&name.to_string(), k + 1, 0);
+                            }
                         }
-                        _ => {
-                            if let Err(err) = check_constraint(cs, expr, domain, name, settings) {
-                                match err.downcast_ref::<CheckingError>() {
-                                    Some(CheckingError::NoColumnsFound(_)) => {
-                                        warn!("{}", err);
-                                        None
-                                    }
-                                    Some(CheckingError::FailingConstraint(handle, trace)) => {
-                                        if settings.report {
-                                            println!(
-                                                "{} failed:\n{}\n",
-                                                handle.to_string().red().bold(),
-                                                trace
-                                            );
-                                        }
-                                        Some(name.to_owned())
-                                    }
-                                    Some(CheckingError::MismatchingLengths(err)) => {
-                                        error!("{err}");
-                                        return Some(name.to_owned());
-                                    }
-                                    None => {
+                        None
+                    }
+                    _ => {
+                        if let Err(err) = check_constraint(cs, expr, domain, name, 0, settings) {
+                            match err.downcast_ref::<CheckingError>() {
+                                Some(CheckingError::NoColumnsFound(_)) => {
+                                    warn!("{}", err);
+                                    None
+                                }
+                                Some(CheckingError::FailingConstraint(handle, trace)) => {
+                                    settings.report.print(handle, cs.spans.get(handle), trace);
+                                    Some(name.to_owned())
+                                }
+                                Some(CheckingError::MismatchingLengths(err)) => {
+                                    error!("{err}");
+                                    Some(name.to_owned())
+                                }
+                                None => {
+                                    if !is_deadline_exceeded(&err) {
                                         warn!("{}", err);
-                                        None
                                     }
+                                    None
                                 }
-                            } else {
-                                None
                             }
+                        } else {
+                            None
                         }
                     }
                 }
-                Constraint::Lookup {
-                    handle,
-                    including,
-                    included,
-                } => {
-                    if let Err(trace) = check_lookup(cs, handle, including, included) {
-                        if settings.report {
-                            println!("{} failed:\n{:?}\n", handle, trace);
-                        }
+            }
+            Constraint::Lookup {
+                handle,
+                including,
+                included,
+            } => match check_lookup(cs, handle, including, included, settings) {
+                Err(err) if is_deadline_exceeded(&err) => None,
+                Err(trace) => {
+                    settings
+                        .report
+                        .print(handle, cs.spans.get(handle), &format!("{:?}", trace));
+                    Some(handle.to_owned())
+                }
+                _ => None,
+            },
+            Constraint::Permutation {
+                handle: _name,
+                from: _from,
+                to: _to,
+                ..
+            } => {
+                // warn!("Permutation validation not yet implemented");
+                None
+            }
+            Constraint::InRange { handle, exp, max } => {
+                match check_inrange(handle, exp, cs, max, settings) {
+                    Err(err) if is_deadline_exceeded(&err) => None,
+                    Err(trace) => {
+                        settings.report.print(
+                            handle,
+                            cs.spans.get(handle),
+                            &format!("{:?}", trace),
+                        );
                         Some(handle.to_owned())
-                    } else {
-                        None
                     }
+                    _ => None,
                 }
-                Constraint::Permutation {
-                    handle: _name,
-                    from: _from,
-                    to: _to,
-                    ..
-                } => {
-                    // warn!("Permutation validation not yet implemented");
-                    None
-                }
-                Constraint::InRange { handle, exp, max } => {
-                    if let Err(trace) = check_inrange(exp, &cs, max) {
-                        if settings.report {
-                            println!("{} failed:\n{:?}\n", handle, trace);
+            }
+            Constraint::Normalization { .. } => {
+                // We trust ourselves
+                None
+            }
+        }
+    };
+
+    // the modules are checked in parallel, as well as the constraints of
+    // each of them, so that a module with many constraints does not delay
+    // the others
+    let modules = todo
+        .iter()
+        .copied()
+        .into_group_map_by(|c| c.handle().module.clone());
+    modules
+        .into_par_iter()
+        .map(|(module, constraints)| {
+            let failed = constraints
+                .par_iter()
+                .filter_map(|c| {
+                    let name = c.name();
+                    match settings.checkpoint.as_ref().and_then(|x| x.status(&name)) {
+                        Some(true) => return Some(None),
+                        Some(false) => return Some(Some(name)),
+                        None => {}
+                    }
+                    // once the deadline is passed or a cancellation requested,
+                    // the remaining constraints are skipped; those being
+                    // checked finish
+                    if ensure_deadline("checking the constraints").is_err()
+                        || ensure_not_cancelled("checking the constraints").is_err()
+                    {
+                        return None;
+                    }
+                    let failed = check_one(c).map(|h| h.to_string());
+                    if let Some(checkpoint) = settings.checkpoint.as_ref() {
+                        // a constraint interrupted by the deadline is not
+                        // finished
+                        if ensure_deadline("checking the constraints").is_ok() {
+                            checkpoint.finished(&name, failed.is_none());
                         }
-                        Some(handle.to_owned())
-                    } else {
-                        None
+                    }
+                    Some(failed)
+                })
+                .inspect(|_| {
+                    let checked = checked.fetch_add(1, Ordering::Relaxed) + 1;
+                    if settings.progress {
+                        eprint!("\rchecked {}/{} constraints", checked, total);
+                    }
+                })
+                .flatten()
+                .collect::<BTreeSet<_>>();
+            if !failed.is_empty() {
+                debug!(
+                    "{}: {}/{} constraints failed",
+                    module,
+                    failed.len(),
+                    constraints.len()
+                );
+            }
+            (module, failed)
+        })
+        .filter(|(_, failed)| !failed.is_empty())
+        .collect()
+}
+
+/// Warn about what may make the check partial; return false if there is
+/// nothing to check.
+fn check_preamble(cs: &ConstraintSet, todo: usize, settings: &CheckConfig) -> Result<bool> {
+    if cs.columns.is_empty() {
+        info!("Skipping empty trace");
+        return Ok(false);
+    }
+    for read in cs.padding_reads() {
+        warn!("{}", read);
+    }
+    if todo == 0 {
+        bail!("refusing to check an empty constraint set")
+    }
+    if let Some(rows) = settings.rows.as_ref() {
+        warn!(
+            "only checking rows {}..{}; the result is partial",
+            rows.start, rows.end
+        );
+    }
+    Ok(true)
+}
+
+/// How many out-of-magma rows are listed per column
+const MAGMA_ROWS: usize = 10;
+
+/// A column holding values outside of its magma
+#[derive(Debug)]
+pub struct MagmaViolation {
+    pub column: Handle,
+    /// where the column has been defined, if it stems from the sources
+    pub span: Option<Span>,
+    pub magma: Magma,
+    /// the first rows holding an invalid value
+    pub rows: Vec<isize>,
+    /// how many rows hold an invalid value
+    pub count: usize,
+    /// why the value of the first of these rows is invalid
+    pub reason: String,
+}
+
+/// Validate every cell of the filled columns of `cs` against the magma of its
+/// column. Values are validated when imported, but not when computed; and as
+/// they are meaningless elsewhere, the columns of a perspective are only
+/// validated on the rows where it is active.
+pub fn magma_violations(cs: &ConstraintSet) -> Vec<MagmaViolation> {
+    let columns = cs
+        .columns
+        .iter()
+        .filter(|(h, c)| {
+            !matches!(c.t.rm(), RawMagma::None | RawMagma::Any | RawMagma::Native)
+                && c.register.is_some()
+                && cs.columns.backing(h).is_some()
+        })
+        .collect::<Vec<_>>();
+    columns
+        .into_par_iter()
+        .filter_map(|(h, c)| {
+            let selector = c.handle.perspective.as_ref().and_then(|p| {
+                cs.perspectives
+                    .get(&c.handle.module)
+                    .and_then(|ps| ps.get(p))
+            });
+            let get = |handle: &ColumnRef, i, wrap| cs.columns.get(handle, i, wrap);
+            let mut r = MagmaViolation {
+                column: c.handle.clone(),
+                span: c.span.clone(),
+                magma: c.t,
+                rows: Vec::new(),
+                count: 0,
+                reason: String::new(),
+            };
+            for i in 0..cs.columns.len(&h).unwrap_or(0) as isize {
+                if let Some(selector) = selector {
+                    let active = selector
+                        .eval(i, get, &mut None, &Default::default())
+                        .is_some_and(|v| !v.is_zero());
+                    if !active {
+                        continue;
                     }
                 }
-                Constraint::Normalization { .. } => {
-                    // We trust ourselves
-                    None
+                if let Some(Err(err)) = cs.columns.get(&h, i, false).map(|x| c.t.rm().validate(x)) {
+                    if r.count == 0 {
+                        r.reason = err.to_string();
+                    }
+                    if r.rows.len() < MAGMA_ROWS {
+                        r.rows.push(i);
+                    }
+                    r.count += 1;
                 }
             }
+            (r.count > 0).then_some(r)
         })
-        .collect::<HashSet<_>>();
+        .collect::<Vec<_>>()
+        .into_iter()
+        .sorted_by_cached_key(|v| v.column.to_string())
+        .collect()
+}
+
+/// If required, report the columns holding values outside of their magma and
+/// fail, whatever `result`, the outcome of the constraints checking
+fn conclude_magmas(cs: &ConstraintSet, settings: &CheckConfig, result: Result<()>) -> Result<()> {
+    if !settings.strict_magma {
+        return result;
+    }
+    let violations = magma_violations(cs);
+    for v in violations.iter() {
+        error!(
+            "{} ({}{}): {} invalid values, on rows {}{}; {}",
+            v.column.pretty(),
+            v.magma,
+            v.span
+                .as_ref()
+                .map(|span| format!(", defined at {}", span))
+                .unwrap_or_default(),
+            v.count.to_string().red().bold(),
+            v.rows.iter().join(", "),
+            if v.count > v.rows.len() { ", ..." } else { "" },
+            v.reason
+        );
+    }
+    if violations.is_empty() {
+        result
+    } else {
+        let err = format!(
+            "{} columns hold values outside of their magma: {}",
+            violations.len(),
+            violations.iter().map(|v| v.column.pretty()).join(", ")
+        );
+        match result {
+            Result::Ok(()) => bail!(err),
+            Err(e) => Err(e.context(err)),
+        }
+    }
+}
+
+fn render_failures(failed: &Failures) -> String {
+    failed
+        .values()
+        .flatten()
+        .map(|x| x.bold().red().to_string())
+        .join(", ")
+}
+
+/// Report the outcome of the check, given the names of the `failed`
+/// constraints, and the number of constraints `checked` out of `total`
+fn conclude(failed: Failures, settings: &CheckConfig, checked: usize, total: usize) -> Result<()> {
+    if settings.progress {
+        eprintln!();
+    }
+    if let Some(checkpoint) = settings.checkpoint.as_ref() {
+        checkpoint.save(true);
+    }
+    ensure_deadline("checking the constraints")?;
+    if let Err(err) = ensure_not_cancelled("checking the constraints") {
+        // the partial result is reported along the error, so that it is not
+        // hidden by the verbosity level
+        return Err(err.context(format!(
+            "partial result: {}/{} constraints checked, {} failed{}",
+            checked,
+            total,
+            failed.values().map(BTreeSet::len).sum::<usize>(),
+            if failed.is_empty() {
+                String::new()
+            } else {
+                format!(": {}", render_failures(&failed))
+            }
+        )));
+    }
     if failed.is_empty() {
-        info!("Validation successful");
+        if let Some(rows) = settings.rows.as_ref() {
+            info!(
+                "Validation successful on rows {}..{} only",
+                rows.start, rows.end
+            );
+        } else {
+            info!("Validation successful");
+        }
+        Ok(())
+    } else {
+        bail!("constraints failed: {}", render_failures(&failed))
+    }
+}
+
+/// Whether `h` has been imported, or can be computed from imported columns
+fn is_available(cs: &ConstraintSet, h: &ColumnRef, known: &mut HashMap<ColumnRef, bool>) -> bool {
+    if let Some(available) = known.get(h) {
+        return *available;
+    }
+    let available = cs.columns.is_computed(h)
+        || cs
+            .computations
+            .computation_idx_for(h)
+            .map(|i| {
+                cs.computations
+                    .get(i)
+                    .unwrap()
+                    .sources()
+                    .iter()
+                    .all(|s| is_available(cs, s, known))
+            })
+            .unwrap_or(false);
+    known.insert(h.clone(), available);
+    available
+}
+
+/// Among the constraints selected by `only` and `skip`, select those that
+/// can be checked against a trace lacking some columns, i.e. those whose
+/// columns are all either imported or computable from imported ones. The
+/// constraints that can and can not be checked are logged.
+pub fn partial_selection(
+    cs: &ConstraintSet,
+    only: &Option<Vec<String>>,
+    skip: &[String],
+) -> Result<Option<Vec<String>>> {
+    let mut known = HashMap::new();
+    let (checkable, skipped): (Vec<_>, Vec<_>) = cs
+        .selected_constraints(only, skip)
+        .into_iter()
+        .map(|c| {
+            let missing = c
+                .dependencies()
+                .iter()
+                .filter(|h| !is_available(cs, h, &mut known))
+                .map(|h| cs.handle(h).to_owned())
+                .min();
+            (c.name(), missing)
+        })
+        .sorted()
+        .partition(|(_, missing)| missing.is_none());
+
+    info!(
+        "{} constraint(s) checkable against the available columns{}",
+        checkable.len(),
+        checkable
+            .iter()
+            .map(|(name, _)| format!("\n  - {}", name))
+            .join("")
+    );
+    info!(
+        "{} constraint(s) skipped for lack of columns{}",
+        skipped.len(),
+        skipped
+            .iter()
+            .map(|(name, missing)| format!("\n  - {} (no {})", name, missing.as_ref().unwrap()))
+            .join("")
+    );
+    if checkable.is_empty() {
+        bail!("none of the selected constraints can be checked against this trace")
+    }
+    Ok(Some(checkable.into_iter().map(|(name, _)| name).collect()))
+}
+
+pub fn check(
+    cs: &ConstraintSet,
+    only: &Option<Vec<String>>,
+    skip: &[String],
+    settings: CheckConfig,
+) -> Result<()> {
+    let todo = cs.selected_constraints(only, skip);
+    if !check_preamble(cs, todo.len(), &settings)? {
+        return Ok(());
+    }
+
+    let checked = AtomicUsize::new(0);
+    let failed = check_constraints(cs, &todo, &settings, &checked, todo.len());
+    let result = conclude(failed, &settings, checked.into_inner(), todo.len());
+    conclude_magmas(cs, &settings, result)
+}
+
+/// Check the constraints selected by `only` and `skip` against a trace whose
+/// computed columns have not been filled yet; they are computed on demand,
+/// right before the first constraint requiring them is checked.
+///
+/// All the constraints whose dependencies are available are checked in
+/// parallel; then the columns required by the next remaining constraint are
+/// computed, and so on until all the constraints have been checked.
+pub fn check_lazily(
+    cs: &mut ConstraintSet,
+    only: &Option<Vec<String>>,
+    skip: &[String],
+    settings: CheckConfig,
+) -> Result<()> {
+    let mut computer = LazyComputer::default();
+    let mut pending = cs
+        .selected_constraints(only, skip)
+        .into_iter()
+        .map(|c| (computer.pending_for(cs, c), c.clone()))
+        .collect::<Vec<_>>();
+    let total = pending.len();
+    if !check_preamble(cs, total, &settings)? {
+        return Ok(());
+    }
+
+    let checked = AtomicUsize::new(0);
+    let mut failed = Failures::new();
+    loop {
+        let (ready, waiting): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|(required, _)| computer.is_done(required));
+        for (module, names) in check_constraints(
+            cs,
+            &ready.iter().map(|(_, c)| c).collect::<Vec<_>>(),
+            &settings,
+            &checked,
+            total,
+        ) {
+            failed.entry(module).or_default().extend(names);
+        }
+        pending = waiting;
+
+        if let Some((_, next)) = pending.first() {
+            if let Err(err) = computer.materialize(cs, next) {
+                if is_deadline_exceeded(&err) || is_cancelled(&err) {
+                    break;
+                }
+                return Err(err);
+            }
+        } else {
+            break;
+        }
+    }
+
+    let skipped = computer.skipped(cs);
+    if skipped > 0 {
+        info!(
+            "{} computations not run, as no checked constraint depends on them",
+            skipped
+        );
+    }
+    let result = conclude(failed, &settings, checked.into_inner(), total);
+    conclude_magmas(cs, &settings, result)
+}
+
+/// How many differing rows are detailed per constraint by [`differential`]
+const DIFFERENTIAL_ROWS: usize = 10;
+
+/// Compare, over the same trace, the vanishing constraints of `reference`,
+/// as written, with their counterparts in `lowered`, the same constraint set
+/// once expanded as for the exporters, and report all the rows where they
+/// disagree on whether the constraint holds.
+///
+/// The expansions may legitimately scale the value of a constraint, e.g.
+/// when lowering an `if-not-zero` into a product with its condition; only
+/// whether it vanishes is thus compared.
+pub fn differential(
+    reference: &ConstraintSet,
+    lowered: &ConstraintSet,
+    only: &Option<Vec<String>>,
+    skip: &[String],
+) -> Result<()> {
+    let _phase = run_stats::phase("check");
+    fn eval(cs: &ConstraintSet, expr: &Node, i: isize, wrap: bool) -> Option<Value> {
+        if matches!(expr.e(), Expression::Void) {
+            return Some(Value::zero());
+        }
+        expr.eval(
+            i,
+            |handle, i, wrap| cs.columns.get_raw(handle, i, wrap),
+            &mut None,
+            &EvalSettings::new().wrap(wrap),
+        )
+    }
+
+    let lowered_exprs = lowered
+        .constraints
+        .iter()
+        .filter_map(|c| match c {
+            Constraint::Vanishes { handle, expr, .. } => Some((handle, expr.as_ref())),
+            _ => None,
+        })
+        .collect::<std::collections::HashMap<_, _>>();
+    let todo = reference.selected_constraints(only, skip);
+    let differing = todo
+        .par_iter()
+        .map(|c| -> Result<Option<&Handle>> {
+            let Constraint::Vanishes {
+                handle,
+                domain,
+                expr,
+            } = c
+            else {
+                return Ok(None);
+            };
+            let Some(lowered_expr) = lowered_exprs.get(handle) else {
+                debug!("{} has no expanded counterpart", handle.pretty());
+                return Ok(None);
+            };
+            let Some(l) = reference.dependencies_len(expr, true).ok().flatten() else {
+                return Ok(None);
+            };
+
+            // out-of-bounds rows only fail the constraints set on a domain
+            let rows: Box<dyn Iterator<Item = (isize, bool)>> = match domain {
+                Some(is) => Box::new(is.iter().map(|i| (i, true))),
+                None => Box::new((0..l as isize).map(|i| (i, false))),
+            };
+            let mut differing = Vec::new();
+            for (i, wrap) in rows {
+                if i % DEADLINE_ROWS == 0 {
+                    ensure_deadline("comparing the constraints")?;
+                }
+                let before = eval(reference, expr, i, wrap);
+                let after = eval(lowered, lowered_expr, i, wrap);
+                let vanishes = |r: &Option<Value>| r.as_ref().map(|r| r.is_zero()).unwrap_or(!wrap);
+                if vanishes(&before) != vanishes(&after) {
+                    differing.push((i, before, after));
+                }
+            }
+            if differing.is_empty() {
+                return Ok(None);
+            }
+
+            let render = |r: &Option<Value>| {
+                r.as_ref()
+                    .map(|r| r.pretty())
+                    .unwrap_or_else(|| "out of bounds".to_string())
+            };
+            error!(
+                "{} and its expanded form disagree on {} rows:\n{}{}",
+                handle.pretty(),
+                differing.len(),
+                differing
+                    .iter()
+                    .take(DIFFERENTIAL_ROWS)
+                    .map(|(i, before, after)| format!(
+                        "  row {}: {} before expansion, {} after",
+                        i,
+                        render(before),
+                        render(after)
+                    ))
+                    .join("\n"),
+                if differing.len() > DIFFERENTIAL_ROWS {
+                    "\n  ..."
+                } else {
+                    ""
+                }
+            );
+            Ok(Some(handle))
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .fold(Failures::new(), |mut ax, h| {
+            ax.entry(h.module.clone())
+                .or_default()
+                .insert(h.to_string());
+            ax
+        });
+
+    if differing.is_empty() {
+        info!(
+            "{} constraints behave identically once expanded",
+            todo.len()
+        );
         Ok(())
     } else {
         bail!(
-            "constraints failed: {}",
-            failed
-                .into_iter()
-                .map(|x| x.to_string().bold().red().to_string())
-                .collect::<Vec<_>>()
-                .join(", ")
+            "the expansion changed the semantics of: {}",
+            render_failures(&differing)
         )
     }
 }
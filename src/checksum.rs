@@ -0,0 +1,148 @@
+use anyhow::*;
+use itertools::Itertools;
+use log::*;
+use num_bigint::BigUint;
+use owo_colors::OwoColorize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::input;
+
+/// The algorithm used to checksum the columns of expanded traces
+pub const ALGORITHM: &str = "blake3";
+
+/// Incrementally computes the checksum of a column from its values, as they
+/// are written in an expanded trace.
+#[derive(Default)]
+pub struct ColumnHasher(blake3::Hasher);
+impl ColumnHasher {
+    pub fn update(&mut self, value: &str) -> Result<()> {
+        self.0.update(&canonical_encoding(value)?);
+        Ok(())
+    }
+
+    /// Update the checksum with the value `x` of a column, whatever the way
+    /// it is written in the trace
+    pub fn update_value(&mut self, x: &crate::column::Value) -> Result<()> {
+        if let crate::column::Value::ExoNative(_) = x {
+            bail!("exo-native values can not be checksummed")
+        }
+        self.0.update(&pad(&x.to_bi().to_bytes_be().1, x)?);
+        Ok(())
+    }
+
+    /// Update the checksum with a value already in its canonical encoding
+    pub fn update_canonical(&mut self, value: &[u8]) {
+        self.0.update(value);
+    }
+
+    pub fn finalize(self) -> String {
+        self.0.finalize().to_hex().to_string()
+    }
+}
+
+/// The canonical encoding of a value, i.e. its 32-bytes big-endian
+/// representation, that does not depend on how it has been formatted.
+fn canonical_encoding(value: &str) -> Result<[u8; 32]> {
+    let x = value
+        .strip_prefix("0x")
+        .and_then(|hex| BigUint::parse_bytes(hex.as_bytes(), 16))
+        .or_else(|| value.parse::<BigUint>().ok())
+        .with_context(|| anyhow!("invalid value: {}", value.red()))?;
+    pad(&x.to_bytes_be(), value)
+}
+
+/// Left-pad the big-endian `bytes` of `value` to 32 bytes
+fn pad(bytes: &[u8], value: impl std::fmt::Display) -> Result<[u8; 32]> {
+    if bytes.len() > 32 {
+        bail!("{} does not fit in 32 bytes", value.to_string().red())
+    }
+    let mut r = [0; 32];
+    r[32 - bytes.len()..].copy_from_slice(bytes);
+    Ok(r)
+}
+
+pub(crate) fn read_json(tracefile: &str) -> Result<Value> {
+    serde_json::from_reader(input::reader(tracefile)?)
+        .with_context(|| format!("while reading `{}`", tracefile))
+}
+
+/// Recompute the checksums of the columns of the expanded trace `tracefile`,
+/// and ensure that they match the ones it embeds.
+pub fn verify_trace(tracefile: &str) -> Result<()> {
+    let trace = read_json(tracefile)?;
+    let checksums = trace.get("checksums").with_context(|| {
+        anyhow!(
+            "`{}` does not contain checksums; it must be computed with --checksums",
+            tracefile
+        )
+    })?;
+    let algorithm = checksums
+        .get("algorithm")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if algorithm != ALGORITHM {
+        bail!(
+            "unsupported checksum algorithm `{}`; expected `{}`",
+            algorithm.red(),
+            ALGORITHM
+        )
+    }
+    let mut expected = checksums
+        .get("columns")
+        .and_then(Value::as_object)
+        .with_context(|| anyhow!("missing columns checksums"))?
+        .iter()
+        .map(|(column, checksum)| (column.as_str(), checksum.as_str().unwrap_or_default()))
+        .collect::<HashMap<_, _>>();
+    let columns = trace
+        .get("columns")
+        .and_then(Value::as_object)
+        .with_context(|| anyhow!("`{}` does not contain any column", tracefile))?;
+
+    let mut errors = Vec::new();
+    for (column, content) in columns.iter() {
+        let mut hasher = ColumnHasher::default();
+        let values = content
+            .get("values")
+            .with_context(|| anyhow!("no values found for {}", column.bold()))?;
+        if let Some(xs) = values.as_array() {
+            for x in xs {
+                hasher.update(x.as_str().unwrap_or_default())?;
+            }
+        } else {
+            // encoded values are already in their canonical encoding
+            let bytes = crate::import::encoded_values(values)
+                .with_context(|| anyhow!("reading {}", column.bold()))?;
+            hasher.update_canonical(&bytes);
+        }
+        let found = hasher.finalize();
+        match expected.remove(column.as_str()) {
+            Some(checksum) if checksum == found => {
+                trace!("{}: {}", column, found)
+            }
+            Some(checksum) => errors.push(format!(
+                "{}: expected {}, found {}",
+                column.bold(),
+                checksum,
+                found.red()
+            )),
+            None => errors.push(format!("{}: no checksum found", column.bold())),
+        }
+    }
+    for column in expected.keys().sorted() {
+        errors.push(format!("{}: column not found", column.bold()));
+    }
+
+    if errors.is_empty() {
+        info!("{} columns verified", columns.len());
+        Ok(())
+    } else {
+        bail!(
+            "{} corrupted column(s) in `{}`:\n{}",
+            errors.len(),
+            tracefile,
+            errors.join("\n")
+        )
+    }
+}
@@ -2,7 +2,7 @@ use crate::{
     compiler::{ColumnRef, EvalSettings, Intrinsic, Kind, Magma, Node},
     constants, errors,
     pretty::{opcodes, Base, Pretty},
-    structs::Handle,
+    structs::{Handle, Span},
 };
 use anyhow::*;
 use ark_bls12_377::fr::Fr;
@@ -22,6 +22,49 @@ use std::{
 pub type RegisterID = usize;
 pub type ColumnID = usize;
 
+/// A reference to a register of a [`ColumnSet`], either by its ID or by its
+/// handle.
+///
+/// It is deliberately distinct from [`ColumnRef`]: several columns may share
+/// a register, and the IDs and handles of registers do not match those of
+/// their columns. A column is turned into its register with
+/// [`ColumnSet::register_ref_of`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RegisterRef {
+    ID(RegisterID),
+    Handle(Handle),
+}
+impl From<RegisterID> for RegisterRef {
+    fn from(id: RegisterID) -> Self {
+        RegisterRef::ID(id)
+    }
+}
+impl From<Handle> for RegisterRef {
+    fn from(handle: Handle) -> Self {
+        RegisterRef::Handle(handle)
+    }
+}
+impl std::fmt::Display for RegisterRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegisterRef::ID(id) => write!(f, "reg#{}", id),
+            RegisterRef::Handle(handle) => write!(f, "{}", handle),
+        }
+    }
+}
+impl Pretty for RegisterRef {
+    fn pretty(&self) -> String {
+        match self {
+            RegisterRef::ID(_) => self.to_string(),
+            RegisterRef::Handle(handle) => handle.pretty(),
+        }
+    }
+
+    fn pretty_with_base(&self, _base: Base) -> String {
+        self.pretty()
+    }
+}
+
 static POW_2_256: OnceLock<BigInt> = OnceLock::new();
 fn clamp_bi(bi: &mut BigInt) {
     // TODO: adapt to field size
@@ -35,6 +78,10 @@ fn clamp_bi(bi: &mut BigInt) {
     assert!(bi.sign() != Sign::Minus);
 }
 
+#[allow(
+    clippy::derive_ord_xor_partial_ord,
+    clippy::derived_hash_with_manual_eq
+)]
 #[derive(Debug, Clone, Eq, Ord, Hash, Serialize, Deserialize)]
 pub enum Value {
     BigInt(BigInt),
@@ -57,7 +104,8 @@ fn get_limbs(x: &Fr) -> &[u64; 4] {
 
 impl From<FrDef> for Fr {
     fn from(def: FrDef) -> Fr {
-        Fr::from(ark_ff::BigInt(def.limbs))
+        // the limbs are already in Montgomery form, see `get_limbs`
+        Fr::new_unchecked(ark_ff::BigInt(def.limbs))
     }
 }
 
@@ -209,6 +257,7 @@ impl Value {
         }
     }
 
+    #[allow(dead_code)]
     pub(crate) fn from_str(s: &str) -> Result<Value> {
         Ok(Value::BigInt(
             s.parse::<BigInt>()
@@ -232,6 +281,7 @@ impl Value {
         }
     }
 
+    #[allow(clippy::wrong_self_convention)]
     pub(crate) fn to_native(&mut self) {
         if let Value::BigInt(i) = self {
             clamp_bi(i);
@@ -259,6 +309,56 @@ impl Value {
         }
     }
 
+    /// The value as a signed integer, field elements in the upper half of the
+    /// field being the opposite of negative integers
+    pub(crate) fn to_signed_bi(&self) -> Result<BigInt> {
+        Ok(match self {
+            Value::BigInt(x) => x.clone(),
+            Value::Native(fr) => {
+                let x = self.to_bi();
+                if fr.into_bigint() > Fr::MODULUS_MINUS_ONE_DIV_TWO {
+                    x - BigInt::from_bytes_be(Sign::Plus, &Fr::MODULUS.to_bytes_be())
+                } else {
+                    x
+                }
+            }
+            Value::ExoNative(_) => bail!("{} is wider than a field element, and has no sign", self),
+        })
+    }
+
+    /// The signed integer `x`, in the same representation as `self`
+    pub(crate) fn signed_like(&self, x: BigInt) -> Value {
+        match self {
+            Value::BigInt(_) => Value::BigInt(x),
+            _ => {
+                let mut r = Value::BigInt(x.magnitude().clone().into()).into_native();
+                if x.sign() == Sign::Minus {
+                    r.negate();
+                }
+                r
+            }
+        }
+    }
+
+    /// The `k`-th bit of the value, in the same representation as `self`
+    pub(crate) fn bit(&self, k: u64) -> Value {
+        self.signed_like(if self.to_bi().bit(k) {
+            BigInt::one()
+        } else {
+            BigInt::zero()
+        })
+    }
+
+    /// -1, 0 or 1, depending on the sign of the value seen as a signed integer
+    pub(crate) fn signum(&self) -> Result<Value> {
+        Ok(self.signed_like(match self.to_signed_bi()?.sign() {
+            Sign::Minus => -BigInt::one(),
+            Sign::NoSign => BigInt::zero(),
+            Sign::Plus => BigInt::one(),
+        }))
+    }
+
+    #[allow(dead_code)]
     pub(crate) fn make_bi(&mut self) {
         match self {
             Value::BigInt(_) => {}
@@ -423,7 +523,11 @@ impl From<i32> for Value {
 impl From<&str> for Value {
     fn from(x: &str) -> Self {
         if *crate::IS_NATIVE.read().unwrap() {
-            Value::Native(Fr::from_str(x).unwrap())
+            // negative values are mapped to their field opposite
+            match x.strip_prefix('-') {
+                Some(x) => Value::Native(-Fr::from_str(x).unwrap()),
+                None => Value::Native(Fr::from_str(x).unwrap()),
+            }
         } else {
             Value::BigInt(BigInt::from_str(x).unwrap())
         }
@@ -451,7 +555,7 @@ impl Pretty for Value {
     fn pretty_with_base(&self, base: Base) -> String {
         match self {
             Value::BigInt(i) => match base {
-                Base::Dec => i.to_str_radix(10),
+                Base::Dec | Base::Signed => i.to_str_radix(10),
                 Base::Hex => format!("0x{}", i.to_str_radix(16)),
                 Base::Bin | Base::Bool | Base::Loob => i.to_str_radix(2),
                 Base::Bytes => i
@@ -470,6 +574,7 @@ impl Pretty for Value {
         }
     }
 }
+#[allow(clippy::non_canonical_partial_ord_impl)]
 impl std::cmp::PartialOrd for Value {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         match (self, other) {
@@ -520,6 +625,7 @@ impl std::fmt::Display for Value {
 pub struct FieldRegister {
     pub handle: Handle,
     #[serde(skip)]
+    #[allow(dead_code)]
     value: Option<Vec<Fr>>,
 }
 
@@ -537,6 +643,7 @@ pub enum ValueBacking {
     Function {
         /// if i >= 0, shall return the expected actual value; if i < 0, shall
         /// return the adequate padding value
+        #[allow(clippy::type_complexity)]
         f: Box<dyn Fn(isize, &ColumnSet) -> Option<Value> + Sync + Send>,
         len: usize,
         spilling: isize,
@@ -596,7 +703,25 @@ impl ValueBacking {
         }
     }
 
-    fn padded_len(&self) -> usize {
+    /// A coarse estimation of the memory used to store the values; lazy
+    /// backings are not taken into account, as they store no value.
+    pub fn memory_size(&self) -> usize {
+        match self {
+            ValueBacking::Vector { v, .. } => {
+                // Padding values are at the beginning and are typically
+                // smaller, the last value is more representative
+                let heap_size = match v.last() {
+                    Some(Value::BigInt(x)) => (x.bits() as usize).div_ceil(64) * 8,
+                    Some(Value::ExoNative(fs)) => fs.len() * std::mem::size_of::<Fr>(),
+                    _ => 0,
+                };
+                v.capacity() * (std::mem::size_of::<Value>() + heap_size)
+            }
+            ValueBacking::Expression { .. } | ValueBacking::Function { .. } => 0,
+        }
+    }
+
+    pub(crate) fn padded_len(&self) -> usize {
         match self {
             ValueBacking::Vector { v, .. } => v.len(),
             ValueBacking::Expression { len, spilling, .. }
@@ -604,7 +729,7 @@ impl ValueBacking {
         }
     }
 
-    fn spilling(&self) -> isize {
+    pub(crate) fn spilling(&self) -> isize {
         match self {
             ValueBacking::Vector { spilling, .. }
             | ValueBacking::Expression { spilling, .. }
@@ -652,7 +777,7 @@ impl ValueBacking {
                         }
                         v.get((v.len() as isize + i) as usize)
                     } else if i < -spilling {
-                        Some(v.get(0).unwrap())
+                        Some(v.first().unwrap())
                     } else {
                         v.get((i + spilling) as usize)
                     }
@@ -764,7 +889,7 @@ impl<'a> Iterator for ValueBackingIter<'a> {
                     None
                 } else {
                     self.i += 1;
-                    v.get(self.i as usize - 1 + self.spilling as usize).cloned()
+                    v.get((self.i - 1 + self.spilling) as usize).cloned()
                 }
             }
             ValueBacking::Expression { .. } => {
@@ -787,6 +912,19 @@ impl<'a> Iterator for ValueBackingIter<'a> {
     }
 }
 
+/// A register whose width exceeds that assumed by the constraints for some of
+/// the columns it stores
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WidenedRegister {
+    pub register: RegisterID,
+    /// the bit width of the register, i.e. of its widest column
+    pub bits: usize,
+    /// the narrowest width of the unproven columns it stores
+    pub assumed_bits: usize,
+    /// the column of width `assumed_bits`
+    pub assumed_by: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Register {
     pub handle: Option<Handle>,
@@ -794,6 +932,9 @@ pub struct Register {
     #[serde(skip_serializing, skip_deserializing, default)]
     value: Option<ValueBacking>,
     width: usize,
+    /// the number of rows provided by the trace, before any padding
+    #[serde(skip_serializing, skip_deserializing, default)]
+    trace_len: Option<usize>,
 }
 
 impl Register {
@@ -850,6 +991,10 @@ impl Register {
         self.value.as_ref().map(|v| v.padded_len())
     }
 
+    pub fn memory_size(&self) -> usize {
+        self.value.as_ref().map(|v| v.memory_size()).unwrap_or(0)
+    }
+
     pub fn len(&self) -> Option<usize> {
         self.value.as_ref().map(|v| v.len())
     }
@@ -883,11 +1028,29 @@ pub struct Column {
     pub intrinsic_size_factor: Option<usize>,
     pub base: Base,
     pub handle: Handle,
+    /// the group this column belongs to, if any; this is purely informative
+    #[serde(default)]
+    pub group: Option<String>,
+    /// the documentation of this column, if any
+    #[serde(default)]
+    pub doc: Option<String>,
+    /// where this column has been defined, if it stems from the sources
+    #[serde(default)]
+    pub span: Option<Span>,
+    /// if set, the expression of constants and of other columns padding
+    /// values setting `padding_value` before the trace is padded
+    #[serde(default)]
+    pub padding_expr: Option<Node>,
     computed: bool,
+    /// whether the computation filling this column has been skipped, as no
+    /// selected constraint depends on it
+    #[serde(default)]
+    skipped: bool,
 }
 #[buildstructor::buildstructor]
 impl Column {
     #[builder]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         register: Option<RegisterID>,
         shift: Option<i16>,
@@ -899,6 +1062,8 @@ impl Column {
         intrinsic_size_factor: Option<usize>,
         base: Option<Base>,
         handle: Handle,
+        group: Option<String>,
+        doc: Option<String>,
     ) -> Self {
         Column {
             register,
@@ -910,7 +1075,12 @@ impl Column {
             t: t.unwrap_or(Magma::native()),
             intrinsic_size_factor,
             base: base.unwrap_or(Base::Dec),
+            group,
+            doc,
+            span: None,
+            padding_expr: None,
             computed: false,
+            skipped: false,
             handle,
         }
     }
@@ -924,9 +1094,21 @@ pub struct ColumnSet {
     /// a module may have a lower bound on its columns length if it is involved
     /// in range proofs
     pub min_len: HashMap<String, usize>,
+    /// the modules that must always have exactly this many rows in traces
+    #[serde(default)]
+    pub fixed_len: HashMap<String, usize>,
     pub field_registers: Vec<FieldRegister>,
     pub registers: Vec<Register>,
     pub spilling: HashMap<String, isize>, // module -> (past-spilling, future-spilling)
+    /// if set, only the first rows of every module are imported from traces
+    #[serde(skip)]
+    pub row_limit: Option<usize>,
+    /// what to do with the values that traces provide for computed columns
+    #[serde(skip)]
+    pub computed_in_trace: crate::compute::ComputedInTrace,
+    /// if set, traces are expected to lack some columns
+    #[serde(skip)]
+    pub partial: bool,
 }
 
 impl ColumnSet {
@@ -996,21 +1178,61 @@ impl ColumnSet {
         }
     }
 
-    pub fn register(&self, h: &RegisterRef) -> Option<&Register> {
-        if h.is_id() {
-            self.registers.get(h.as_id())
-        } else if h.is_handle() {
-            self.registers.iter().find(|r| {
-                r.handle
-                    .as_ref()
-                    .map(|handle| handle == h.as_handle())
-                    .unwrap_or(false)
-            })
+    /// Resolve `r` into the ID of an existing register
+    pub fn register_id(&self, r: &RegisterRef) -> Option<RegisterID> {
+        match r {
+            RegisterRef::ID(id) => (*id < self.registers.len()).then_some(*id),
+            RegisterRef::Handle(handle) => self
+                .registers
+                .iter()
+                .position(|r| r.handle.as_ref() == Some(handle)),
+        }
+    }
+
+    /// The register backing the column `c`; this is the only way to go from
+    /// a column to a register
+    pub fn register_ref_of(&self, c: &ColumnRef) -> Result<RegisterRef> {
+        let column = self.column(c)?;
+        let id = column
+            .register
+            .ok_or_else(|| anyhow!("column {} has no backing register", column.handle.pretty()))?;
+        ensure!(
+            id < self.registers.len(),
+            "column {} is backed by the non-existing register #{}",
+            column.handle.pretty(),
+            id
+        );
+        Ok(RegisterRef::ID(id))
+    }
+
+    pub fn register(&self, r: &RegisterRef) -> Option<&Register> {
+        self.register_id(r).map(|id| &self.registers[id])
+    }
+
+    /// Mark `h` as filled, e.g. when its register has already been filled
+    /// through another column
+    pub(crate) fn mark_computed(&mut self, h: &ColumnRef) -> Result<()> {
+        if let Some(ref mut column) = self.get_col_mut(h) {
+            column.computed = true;
+            Ok(())
         } else {
-            unreachable!()
+            bail!("{} can not be found", h.pretty())
+        }
+    }
+
+    pub(crate) fn mark_skipped(&mut self, h: &ColumnRef) -> Result<()> {
+        if let Some(ref mut column) = self.get_col_mut(h) {
+            column.skipped = true;
+            Ok(())
+        } else {
+            bail!("{} can not be found", h.pretty())
         }
     }
 
+    pub fn is_skipped(&self, h: &ColumnRef) -> bool {
+        self.column(h).unwrap().skipped
+    }
+
     pub(crate) fn mark_used(&mut self, h: &ColumnRef) -> Result<()> {
         if let Some(ref mut column) = self.get_col_mut(h) {
             column.used = true;
@@ -1030,19 +1252,8 @@ impl ColumnSet {
         }
     }
 
-    pub fn get_register_mut(&mut self, h: &RegisterRef) -> Option<&mut Register> {
-        if h.is_id() {
-            self.registers.get_mut(h.as_id())
-        } else if h.is_handle() {
-            self.registers.iter_mut().find(|r| {
-                r.handle
-                    .as_ref()
-                    .map(|handle| handle == h.as_handle())
-                    .unwrap_or(false)
-            })
-        } else {
-            unreachable!()
-        }
+    pub fn get_register_mut(&mut self, r: &RegisterRef) -> Option<&mut Register> {
+        self.register_id(r).map(|id| &mut self.registers[id])
     }
 
     pub fn all(&self) -> Vec<ColumnRef> {
@@ -1103,7 +1314,7 @@ impl ColumnSet {
     pub fn iter_module<'a>(
         &'a self,
         module: &'a str,
-    ) -> impl Iterator<Item = (ColumnRef, &Column)> + 'a {
+    ) -> impl Iterator<Item = (ColumnRef, &'a Column)> + 'a {
         self.iter().filter(move |c| c.1.handle.module == module)
     }
 
@@ -1117,10 +1328,138 @@ impl ColumnSet {
             magma,
             value: None,
             width: crate::constants::col_count_magma(magma),
+            trace_len: None,
         });
         self.registers.len() - 1
     }
 
+    /// The name identifying a register across constraint sets
+    fn register_key(&self, r: RegisterID) -> String {
+        self.registers[r]
+            .handle
+            .as_ref()
+            .map(|h| h.to_string())
+            .unwrap_or_else(|| {
+                self._cols
+                    .iter()
+                    .filter(|c| c.register == Some(r))
+                    .map(|c| c.handle.to_string())
+                    .sorted()
+                    .join("/")
+            })
+    }
+
+    /// Set the padding values of the columns padded with an expression,
+    /// evaluating the expressions in the order of their dependencies. Fails if
+    /// paddings depend on each other circularly, or if a resulting value does
+    /// not fit its column.
+    pub fn resolve_paddings(&mut self) -> Result<()> {
+        fn visit(
+            columns: &ColumnSet,
+            i: ColumnID,
+            path: &mut Vec<ColumnID>,
+            order: &mut Vec<ColumnID>,
+        ) -> Result<()> {
+            if order.contains(&i) {
+                return Ok(());
+            }
+            if let Some(start) = path.iter().position(|j| *j == i) {
+                bail!(
+                    "circular padding definition: {}",
+                    path[start..]
+                        .iter()
+                        .chain(std::iter::once(&i))
+                        .map(|j| columns._cols[*j].handle.pretty())
+                        .join(" → ")
+                )
+            }
+            if let Some(expr) = columns._cols[i].padding_expr.as_ref() {
+                path.push(i);
+                for r in expr.dependencies() {
+                    visit(columns, columns.id_of(&r), path, order)?;
+                }
+                path.pop();
+                order.push(i);
+            }
+            Ok(())
+        }
+
+        let mut order = Vec::new();
+        for i in 0..self._cols.len() {
+            visit(self, i, &mut Vec::new(), &mut order)?;
+        }
+        for i in order {
+            let column = &self._cols[i];
+            let value = column
+                .padding_expr
+                .as_ref()
+                .unwrap()
+                .eval(
+                    0,
+                    |r, _, _| {
+                        Some(
+                            self.column(r)
+                                .ok()?
+                                .padding_value
+                                .clone()
+                                .unwrap_or_default(),
+                        )
+                    },
+                    &mut None,
+                    &EvalSettings::default(),
+                )
+                .ok_or_else(|| anyhow!("unable to evaluate the padding expression"))
+                .and_then(|v| column.t.rm().validate(v))
+                .with_context(|| anyhow!("invalid padding value for {}", column.handle.pretty()))?;
+            self._cols[i].padding_value = Some(value);
+        }
+        Ok(())
+    }
+
+    /// Order the registers as they are in `reference`, appending the ones it
+    /// does not contain at the end, and return how many were appended. Fails if
+    /// some registers of `reference` can not keep their index, e.g. because
+    /// they have been removed.
+    pub fn pin_register_order(&mut self, reference: &ColumnSet) -> Result<usize> {
+        let mut new_ids = (0..self.registers.len())
+            .map(|r| (self.register_key(r), r))
+            .collect::<HashMap<_, _>>();
+
+        let mut order = Vec::with_capacity(self.registers.len());
+        let mut broken = Vec::new();
+        for (i, key) in (0..reference.registers.len())
+            .map(|r| reference.register_key(r))
+            .enumerate()
+        {
+            match new_ids.remove(&key) {
+                Some(r) => order.push(r),
+                None => broken.push(format!("{} (r{}) has been removed", key.bold(), i)),
+            }
+        }
+        if !broken.is_empty() {
+            bail!(
+                "the registers order can not be preserved:\n{}",
+                broken.join("\n")
+            )
+        }
+        let appended = new_ids.len();
+        order.extend(new_ids.into_values().sorted());
+
+        let mut registers = std::mem::take(&mut self.registers)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        let mut translation = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            translation[old] = new;
+            self.registers.push(registers[old].take().unwrap());
+        }
+        for c in self._cols.iter_mut() {
+            c.register = c.register.map(|r| translation[r]);
+        }
+        Ok(appended)
+    }
+
     pub fn assign_register(&mut self, handle: &ColumnRef, reg: RegisterID) -> Result<()> {
         self.assign_register_for_id(self.id_of(handle), reg)
     }
@@ -1155,7 +1494,7 @@ impl ColumnSet {
     }
 
     pub fn maybe_insert_column(&mut self, column: Column) -> Option<ColumnRef> {
-        if let Some(id) = self.cols.get(&column.handle) {
+        if let Some(_id) = self.cols.get(&column.handle) {
             None
         } else {
             let id = self._cols.len();
@@ -1197,6 +1536,52 @@ impl ColumnSet {
         &self.registers[reg]
     }
 
+    /// The number of bits effectively required by every register, i.e. the
+    /// bit size of the widest column it stores
+    pub fn register_bit_widths(&self) -> Vec<usize> {
+        let mut r = self
+            .registers
+            .iter()
+            .map(|r| r.magma.bit_size())
+            .collect::<Vec<_>>();
+        for c in self._cols.iter() {
+            if let Some(reg) = c.register {
+                r[reg] = r[reg].max(c.t.bit_size());
+            }
+        }
+        r
+    }
+
+    /// The registers holding values wider than assumed by the constraints,
+    /// i.e. wider than some of the columns they store whose range is not
+    /// proven
+    pub fn widened_registers(&self) -> Vec<WidenedRegister> {
+        let widths = self.register_bit_widths();
+        // the narrowest width assumed for every register, and who assumed it;
+        // the range of a proven column is enforced, rather than assumed
+        let mut narrowest: Vec<Option<(usize, &Handle)>> = vec![None; self.registers.len()];
+        for c in self._cols.iter().filter(|c| !c.must_prove) {
+            if let Some(reg) = c.register {
+                let bits = c.t.bit_size();
+                if bits > 0 && narrowest[reg].map(|(n, _)| bits < n).unwrap_or(true) {
+                    narrowest[reg] = Some((bits, &c.handle));
+                }
+            }
+        }
+        narrowest
+            .into_iter()
+            .enumerate()
+            .filter_map(|(reg, n)| n.map(|(bits, handle)| (reg, bits, handle)))
+            .filter(|(reg, bits, _)| *bits < widths[*reg])
+            .map(|(reg, assumed_bits, assumed_by)| WidenedRegister {
+                register: reg,
+                bits: widths[reg],
+                assumed_bits,
+                assumed_by: assumed_by.to_string(),
+            })
+            .collect()
+    }
+
     pub fn get(&self, h: &ColumnRef, i: isize, wrap: bool) -> Option<Value> {
         self.register_of(h).get(i, wrap, self)
     }
@@ -1213,6 +1598,39 @@ impl ColumnSet {
         self.register_of(h).padded_len()
     }
 
+    /// The number of rows the trace provided for `h`, if it was imported
+    pub fn trace_len(&self, h: &ColumnRef) -> Option<usize> {
+        self.register_of(h).trace_len
+    }
+
+    /// Record that the trace provided `len` rows for the register `r`,
+    /// before it was padded
+    pub(crate) fn set_trace_len(&mut self, r: &RegisterRef, len: usize) -> Result<()> {
+        let reg_id = self
+            .register_id(r)
+            .ok_or_else(|| anyhow!("register {} can not be found", r.pretty()))?;
+        self.registers[reg_id].trace_len = Some(len);
+        Ok(())
+    }
+
+    /// The estimated memory used by the registers of each module, sorted by
+    /// decreasing size
+    pub fn memory_usage(&self) -> Vec<(String, usize)> {
+        let mut by_module = HashMap::<String, usize>::new();
+        for r in self.registers.iter() {
+            let module = r
+                .handle
+                .as_ref()
+                .map(|h| h.module.to_owned())
+                .unwrap_or_default();
+            *by_module.entry(module).or_default() += r.memory_size();
+        }
+        by_module
+            .into_iter()
+            .sorted_by_key(|(module, size)| (std::cmp::Reverse(*size), module.to_owned()))
+            .collect()
+    }
+
     pub fn backing(&self, h: &ColumnRef) -> Option<&ValueBacking> {
         self.register_of(h).value.as_ref()
     }
@@ -1235,37 +1653,20 @@ impl ColumnSet {
 
     pub fn set_register_value(
         &mut self,
-        h: &RegisterRef,
+        r: &RegisterRef,
         v: Vec<Value>,
         spilling: isize,
     ) -> Result<()> {
-        let reg_id = if h.is_id() {
-            h.as_id()
-        } else {
-            self.registers
-                .iter()
-                .enumerate()
-                .find(|(_, r)| {
-                    r.handle
-                        .as_ref()
-                        .map(|handle| handle == h.as_handle())
-                        .unwrap_or(false)
-                })
-                .map(|(id, _)| id)
-                .unwrap()
-        };
-        for column in self
-            ._cols
-            .iter_mut()
-            .filter(|c| c.register.map(|r| r == reg_id).unwrap_or(false))
-        {
+        let reg_id = self
+            .register_id(r)
+            .ok_or_else(|| anyhow!("register {} can not be found", r.pretty()))?;
+        for column in self._cols.iter_mut().filter(|c| c.register == Some(reg_id)) {
             column.computed = true;
         }
 
-        self.get_register_mut(h)
-            .unwrap()
+        self.registers[reg_id]
             .set_value(v, spilling)
-            .with_context(|| anyhow!("while filling {}", h.pretty()))
+            .with_context(|| anyhow!("while filling {}", r.pretty()))
     }
 
     pub fn set_raw_value(&mut self, h: &ColumnRef, v: Vec<Value>, spilling: isize) -> Result<()> {
@@ -1277,9 +1678,17 @@ impl ColumnSet {
         self.get_col_mut(h).unwrap().computed = true;
         self.register_of_mut(h).set_backing(v)
     }
-}
 
-type RegisterRef = ColumnRef;
+    /// Empty the register backing `h`, so that it may be filled anew, and
+    /// return its former value
+    pub(crate) fn take_value(&mut self, h: &ColumnRef) -> Option<ValueBacking> {
+        let reg_id = self.column(h).unwrap().register.unwrap();
+        for column in self._cols.iter_mut().filter(|c| c.register == Some(reg_id)) {
+            column.computed = false;
+        }
+        self.registers[reg_id].value.take()
+    }
+}
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum ExoOperation {
@@ -1331,6 +1740,13 @@ pub enum Computation {
         froms: Vec<ColumnRef>,
         tos: Vec<ColumnRef>,
         signs: Vec<bool>,
+        /// whether rows with equal sorting keys keep their original order
+        #[serde(default = "stable_by_default")]
+        stable: bool,
+        /// columns ordering, in ascending order, the rows with equal sorting
+        /// keys
+        #[serde(default)]
+        tiebreaks: Vec<ColumnRef>,
     },
     CyclicFrom {
         target: ColumnRef,
@@ -1347,6 +1763,10 @@ pub enum Computation {
         sorted: Vec<ColumnRef>,
     },
 }
+fn stable_by_default() -> bool {
+    true
+}
+
 impl std::fmt::Display for Computation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1371,15 +1791,30 @@ impl std::fmt::Display for Computation {
             Computation::ExoConstant { value, target } => {
                 write!(f, "{} := {}", target, value)
             }
-            Computation::Sorted { froms, tos, signs } => write!(
+            Computation::Sorted {
+                froms,
+                tos,
+                signs,
+                stable,
+                tiebreaks,
+            } => write!(
                 f,
-                "[{}] ⇳ [{}]",
+                "[{}] ⇳ [{}]{}{}",
                 tos.iter().map(|c| c.pretty()).join(" "),
                 froms
                     .iter()
-                    .zip(signs.iter())
-                    .map(|(c, s)| format!("{} {}", if *s { '↓' } else { '↑' }, c.pretty()))
+                    .enumerate()
+                    .map(|(i, c)| match signs.get(i) {
+                        Some(s) => format!("{} {}", if *s { '↓' } else { '↑' }, c.pretty()),
+                        None => c.pretty(),
+                    })
                     .join(" "),
+                if tiebreaks.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ≺ [{}]", tiebreaks.iter().map(|c| c.pretty()).join(" "))
+                },
+                if *stable { "" } else { " (unstable)" },
             ),
             Computation::CyclicFrom { target, froms, .. } => write!(
                 f,
@@ -1416,6 +1851,51 @@ impl Computation {
         }
     }
 
+    /// The columns filled by this computation
+    pub fn targets(&self) -> Vec<&ColumnRef> {
+        match self {
+            Computation::Composite { target, .. }
+            | Computation::Interleaved { target, .. }
+            | Computation::ExoOperation { target, .. }
+            | Computation::ExoConstant { target, .. }
+            | Computation::CyclicFrom { target, .. } => vec![target],
+            Computation::Sorted { tos, .. } => tos.iter().collect(),
+            Computation::SortingConstraints {
+                ats,
+                eq,
+                delta,
+                delta_bytes,
+                ..
+            } => ats
+                .iter()
+                .chain(std::iter::once(eq))
+                .chain(std::iter::once(delta))
+                .chain(delta_bytes.iter())
+                .collect(),
+        }
+    }
+
+    /// The columns this computation reads from
+    pub fn sources(&self) -> Vec<ColumnRef> {
+        match self {
+            Computation::Composite { exp, .. } => exp.dependencies().into_iter().collect(),
+            Computation::ExoOperation { sources, .. } => {
+                sources.iter().flat_map(|s| s.dependencies()).collect()
+            }
+            Computation::ExoConstant { .. } => vec![],
+            Computation::Interleaved { froms, .. } | Computation::CyclicFrom { froms, .. } => {
+                froms.clone()
+            }
+            Computation::Sorted {
+                froms, tiebreaks, ..
+            } => froms.iter().chain(tiebreaks.iter()).cloned().collect(),
+            Computation::SortingConstraints { froms, sorted, .. } => {
+                froms.iter().chain(sorted.iter()).cloned().collect()
+            }
+        }
+    }
+
+    #[allow(dead_code)]
     pub(crate) fn module(&self, cs: &ColumnSet) -> String {
         match self {
             Computation::Composite { target, .. } => cs.module_of(target),
@@ -0,0 +1,129 @@
+use num_bigint::BigInt;
+use num_traits::{One, ToPrimitive, Zero};
+
+use super::{Constraint, ConstraintSet, Expression, Intrinsic, Node, RawMagma};
+use crate::structs::Handle;
+
+/// An expression whose inferred bound comes within `margin` bits of the field
+/// modulus, and may thus wrap around when evaluated in the field.
+pub struct RiskyExpression {
+    /// The constraint containing the expression
+    pub handle: Handle,
+    /// The bound of the whole constraint expression
+    pub bound: BigInt,
+    /// The smallest sub-expressions crossing the threshold
+    pub culprits: Vec<(Node, BigInt)>,
+}
+
+/// Infer an upper bound on the absolute value that `n` may take, given the bit
+/// widths declared for the columns it refers to. `None` stands for an
+/// expression that may take any value in the field, e.g. because it involves a
+/// native column or an inverse.
+pub fn bound(n: &Node, cs: &ConstraintSet) -> Option<BigInt> {
+    fn bits_bound(bits: usize) -> BigInt {
+        (BigInt::one() << bits) - 1
+    }
+
+    match n.e() {
+        Expression::Const(x) => Some(x.to_bi().magnitude().to_owned().into()),
+        Expression::Column { handle, .. } => {
+            let m = cs
+                .columns
+                .column(handle)
+                .map(|c| c.t)
+                .unwrap_or_else(|_| n.t().m());
+            match m.rm() {
+                RawMagma::None => Some(BigInt::zero()),
+                RawMagma::Native | RawMagma::Any => None,
+                _ => Some(bits_bound(m.bit_size())),
+            }
+        }
+        Expression::ExoColumn { .. } | Expression::ArrayColumn { .. } => None,
+        Expression::Funcall { func, args } => match func {
+            Intrinsic::Add | Intrinsic::Sub | Intrinsic::VectorAdd | Intrinsic::VectorSub => args
+                .iter()
+                .map(|a| bound(a, cs))
+                .try_fold(BigInt::zero(), |ax, b| b.map(|b| ax + b)),
+            Intrinsic::Mul | Intrinsic::VectorMul => args
+                .iter()
+                .map(|a| bound(a, cs))
+                .try_fold(BigInt::one(), |ax, b| b.map(|b| ax * b)),
+            Intrinsic::Neg => bound(&args[0], cs),
+            Intrinsic::Exp => {
+                let base = bound(&args[0], cs)?;
+                let exp = args[1].pure_eval().ok()?.to_u32()?;
+                Some(base.pow(exp))
+            }
+            Intrinsic::Normalize | Intrinsic::Sgn => Some(BigInt::one()),
+            Intrinsic::Bits => match args.get(2) {
+                Some(_) => Some(BigInt::one()),
+                None => Some((BigInt::one() << args[1].pure_eval().ok()?.to_usize()?) - 1),
+            },
+            Intrinsic::Inv => None,
+            Intrinsic::Begin => max_bound(args.iter(), cs),
+            // The condition does not contribute to the value of the expression
+            Intrinsic::IfZero | Intrinsic::IfNotZero => max_bound(args.iter().skip(1), cs),
+        },
+        Expression::List(xs) => max_bound(xs.iter(), cs),
+        Expression::Void => Some(BigInt::zero()),
+    }
+}
+
+fn max_bound<'a>(xs: impl Iterator<Item = &'a Node>, cs: &ConstraintSet) -> Option<BigInt> {
+    xs.map(|x| bound(x, cs))
+        .try_fold(BigInt::zero(), |ax, b| b.map(|b| ax.max(b)))
+}
+
+/// Collect the smallest sub-expressions of `n` whose bound reaches
+/// `threshold`, i.e. the ones where the risk of overflow is introduced.
+fn culprits(n: &Node, threshold: &BigInt, cs: &ConstraintSet, ax: &mut Vec<(Node, BigInt)>) {
+    let Some(b) = bound(n, cs) else {
+        return;
+    };
+    if &b < threshold {
+        return;
+    }
+
+    let before = ax.len();
+    match n.e() {
+        Expression::Funcall { args, .. } => {
+            for a in args {
+                culprits(a, threshold, cs, ax);
+            }
+        }
+        Expression::List(xs) => {
+            for x in xs {
+                culprits(x, threshold, cs, ax);
+            }
+        }
+        _ => {}
+    }
+    if ax.len() == before {
+        ax.push((n.clone(), b));
+    }
+}
+
+/// List all the constraints featuring expressions whose bounds, although
+/// finite, come within `margin` bits of the field size.
+pub fn risky_expressions(cs: &ConstraintSet, margin: usize) -> Vec<RiskyExpression> {
+    let threshold = BigInt::one() << crate::constants::FIELD_BITSIZE.saturating_sub(margin);
+
+    cs.constraints
+        .iter()
+        .filter_map(|c| {
+            if let Constraint::Vanishes { handle, expr, .. } = c {
+                let bound = bound(expr, cs)?;
+                if bound >= threshold {
+                    let mut ax = Vec::new();
+                    culprits(expr, &threshold, cs, &mut ax);
+                    return Some(RiskyExpression {
+                        handle: handle.to_owned(),
+                        bound,
+                        culprits: ax,
+                    });
+                }
+            }
+            None
+        })
+        .collect()
+}
@@ -0,0 +1,361 @@
+use anyhow::*;
+use num_bigint::BigInt;
+use owo_colors::OwoColorize;
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    generator::PerspectiveTable, ColumnRef, ComputationTable, Constraint, ConstraintSet, Domain,
+    Kind, Magma, Node,
+};
+use crate::{
+    column::{Column, ColumnSet, Computation, RegisterID},
+    pretty::Pretty,
+    structs::Handle,
+};
+
+/// A [`ConstraintSetBuilder`] assembles a [`ConstraintSet`] programmatically,
+/// without going through the lisp front-end. All the cross-references between
+/// its components are checked when calling [`ConstraintSetBuilder::build`].
+///
+/// ```ignore
+/// let mut builder = ConstraintSetBuilder::new();
+/// let a = builder.commitment(Handle::new("m", "A"), Magma::byte())?;
+/// let b = builder.commitment(Handle::new("m", "B"), Magma::byte())?;
+/// builder.vanishes(
+///     Handle::new("m", "a-is-b"),
+///     None,
+///     Intrinsic::Sub.call(&[
+///         builder.node(&a, Kind::Commitment)?,
+///         builder.node(&b, Kind::Commitment)?,
+///     ])?,
+/// );
+/// let cs: ConstraintSet = builder.try_into()?;
+/// ```
+#[derive(Default)]
+pub struct ConstraintSetBuilder {
+    columns: ColumnSet,
+    constraints: Vec<Constraint>,
+    constants: HashMap<Handle, BigInt>,
+    computations: ComputationTable,
+    perspectives: PerspectiveTable,
+    shared_perspectives: HashSet<String>,
+}
+impl ConstraintSetBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Declare a new column
+    pub fn column(&mut self, column: Column) -> Result<ColumnRef> {
+        self.columns.insert_column(column)
+    }
+
+    /// Declare a new commitment column, i.e. filled from the trace
+    pub fn commitment(&mut self, handle: Handle, t: Magma) -> Result<ColumnRef> {
+        self.column(
+            Column::builder()
+                .handle(handle)
+                .kind(Kind::Commitment)
+                .t(t)
+                .build(),
+        )
+    }
+
+    /// Declare a new computed column; it must later on be filled by a
+    /// computation
+    pub fn computed(&mut self, handle: Handle, t: Magma) -> Result<ColumnRef> {
+        self.column(
+            Column::builder()
+                .handle(handle)
+                .kind(Kind::Computed)
+                .t(t)
+                .build(),
+        )
+    }
+
+    /// Create a typed expression node referring to `column`, whose kind must
+    /// be `kind`
+    pub fn node(&self, column: &ColumnRef, kind: Kind<Box<Node>>) -> Result<Node> {
+        let c = self.columns.column(column)?;
+        if kind.to_nil() != c.kind {
+            bail!(
+                "{} is a {:?} column, not a {:?} one",
+                c.handle.pretty(),
+                c.kind,
+                kind.to_nil()
+            )
+        }
+        Ok(Node::column()
+            .handle(column.clone())
+            .kind(kind)
+            .t(c.t)
+            .base(c.base)
+            .build())
+    }
+
+    /// Create a new register, that may then be shared by multiple columns of
+    /// the same module and type
+    pub fn register(&mut self, handle: Handle, magma: Magma) -> RegisterID {
+        self.columns.new_register(handle, magma)
+    }
+
+    /// Store `column` in the register `register`, instead of having it
+    /// automatically allocated
+    pub fn assign_register(&mut self, column: &ColumnRef, register: RegisterID) -> Result<()> {
+        if register >= self.columns.registers.len() {
+            bail!("unknown register r{}", register)
+        }
+        let col = self.columns.column(column)?;
+        if let Some(r) = col.register {
+            bail!(
+                "{} is already assigned to r{}",
+                col.handle.pretty(),
+                r.to_string().red()
+            )
+        }
+        self.columns.assign_register(column, register)
+    }
+
+    /// Declare a new constant
+    pub fn constant(&mut self, handle: Handle, value: BigInt) -> Result<()> {
+        if self.constants.contains_key(&handle) {
+            bail!("constant {} already exists", handle.pretty())
+        }
+        self.constants.insert(handle, value);
+        Ok(())
+    }
+
+    /// Declare a perspective of `module`, triggered by the expression `guard`
+    pub fn perspective(&mut self, module: &str, name: &str, guard: Node) -> Result<()> {
+        let perspectives = self.perspectives.entry(module.to_owned()).or_default();
+        if perspectives.contains_key(name) {
+            bail!("perspective {}/{} already exists", module, name)
+        }
+        perspectives.insert(name.to_owned(), guard);
+        Ok(())
+    }
+
+    /// Declare a perspective of `module` that is shared with the other
+    /// modules declaring a perspective named `name`, triggered in `module` by
+    /// the expression `guard`
+    pub fn shared_perspective(&mut self, module: &str, name: &str, guard: Node) -> Result<()> {
+        self.perspective(module, name, guard)?;
+        self.shared_perspectives.insert(name.to_owned());
+        Ok(())
+    }
+
+    /// Add an arbitrary constraint
+    pub fn constraint(&mut self, constraint: Constraint) -> &mut Self {
+        self.constraints.push(constraint);
+        self
+    }
+
+    /// Add a constraint stating that `expr` must vanish over `domain`, or
+    /// over all the rows if it is not set
+    pub fn vanishes(
+        &mut self,
+        handle: Handle,
+        domain: Option<Domain<isize>>,
+        expr: Node,
+    ) -> &mut Self {
+        self.constraint(Constraint::Vanishes {
+            handle,
+            domain,
+            expr: Box::new(expr),
+        })
+    }
+
+    /// Add a constraint stating that the tuples of `included` must be found
+    /// among those of `including`
+    pub fn lookup(
+        &mut self,
+        handle: Handle,
+        including: Vec<Node>,
+        included: Vec<Node>,
+    ) -> &mut Self {
+        self.constraint(Constraint::Lookup {
+            handle,
+            including,
+            included,
+        })
+    }
+
+    /// Add a computation filling one or more computed columns
+    pub fn computation(&mut self, computation: Computation) -> Result<()> {
+        let targets = computation.targets();
+        for target in targets.iter() {
+            if self.computations.computation_idx_for(target).is_some() {
+                bail!("{} is already computed", target.pretty())
+            }
+        }
+        let targets = targets.into_iter().cloned().collect::<Vec<_>>();
+        if let [target] = targets.as_slice() {
+            self.computations.insert(target, computation).map(|_| ())
+        } else {
+            self.computations.insert_many(&targets, computation)
+        }
+    }
+
+    fn ensure_exists(&self, r: &ColumnRef, user: &str) -> Result<()> {
+        let exists = if r.is_id() {
+            r.as_id() < self.columns._cols.len()
+        } else {
+            r.is_handle() && self.columns.cols.contains_key(r.as_handle())
+        };
+        if exists {
+            Ok(())
+        } else {
+            bail!(
+                "{} refers to the unknown column {}",
+                user.bold(),
+                r.to_string().red()
+            )
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        for c in self.constraints.iter() {
+            let name = c.name();
+            if !names.insert(name.clone()) {
+                bail!("constraint {} is defined twice", name.bold())
+            }
+
+            let dependencies = match c {
+                Constraint::Vanishes { expr, .. } => expr.dependencies().into_iter().collect(),
+                Constraint::Lookup {
+                    including,
+                    included,
+                    ..
+                } => {
+                    if including.is_empty() || including.len() != included.len() {
+                        bail!(
+                            "lookup {}: {} including vs. {} included expressions",
+                            name.bold(),
+                            including.len(),
+                            included.len()
+                        )
+                    }
+                    including
+                        .iter()
+                        .chain(included.iter())
+                        .flat_map(|n| n.dependencies())
+                        .collect()
+                }
+                Constraint::Permutation { from, to, .. } => {
+                    if from.len() != to.len() {
+                        bail!(
+                            "permutation {}: {} sources vs. {} targets",
+                            name.bold(),
+                            from.len(),
+                            to.len()
+                        )
+                    }
+                    from.iter().chain(to.iter()).cloned().collect()
+                }
+                Constraint::InRange { exp, .. } => exp.dependencies().into_iter().collect(),
+                Constraint::Normalization {
+                    reference,
+                    inverted,
+                    ..
+                } => reference
+                    .dependencies()
+                    .into_iter()
+                    .chain(std::iter::once(inverted.clone()))
+                    .collect::<Vec<_>>(),
+            };
+            for r in dependencies.iter() {
+                self.ensure_exists(r, &name)?;
+            }
+        }
+
+        for computation in self.computations.iter() {
+            let name = computation.pretty_target();
+            for r in computation
+                .targets()
+                .into_iter()
+                .cloned()
+                .chain(computation.sources())
+            {
+                self.ensure_exists(&r, &name)?;
+            }
+            for target in computation.targets() {
+                if self.columns.column(target)?.kind == Kind::Commitment {
+                    bail!(
+                        "{} is a commitment, and can not be computed",
+                        target.pretty()
+                    )
+                }
+            }
+        }
+
+        let mut register_owners = HashMap::<RegisterID, &Column>::new();
+        for c in self.columns.iter_cols() {
+            if let Some(perspective) = c.handle.perspective.as_ref() {
+                if !self
+                    .perspectives
+                    .get(&c.handle.module)
+                    .map(|ps| ps.contains_key(perspective))
+                    .unwrap_or(false)
+                {
+                    bail!(
+                        "{} belongs to the undeclared perspective {}",
+                        c.handle.pretty(),
+                        perspective.red()
+                    )
+                }
+            }
+
+            if let Some(r) = c.register {
+                let owner = register_owners.entry(r).or_insert(c);
+                if owner.handle.module != c.handle.module || owner.t != c.t {
+                    bail!(
+                        "{} and {} can not share register r{}",
+                        owner.handle.pretty(),
+                        c.handle.pretty(),
+                        r
+                    )
+                }
+            }
+        }
+
+        for c in self.columns.iter_cols() {
+            if c.kind != Kind::Commitment
+                && self
+                    .computations
+                    .computation_idx_for(&ColumnRef::from_handle(c.handle.clone()))
+                    .is_none()
+                && self
+                    .computations
+                    .computation_idx_for(&ColumnRef::from_id(self.columns.cols[&c.handle]))
+                    .is_none()
+            {
+                bail!("no computation fills {}", c.handle.pretty())
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check the coherency of the constraint system and finalize it
+    pub fn build(self) -> Result<ConstraintSet> {
+        self.validate()?;
+        ConstraintSet::new(
+            self.columns,
+            self.constraints,
+            Vec::new(),
+            self.constants,
+            self.computations,
+            self.perspectives,
+            self.shared_perspectives,
+        )
+    }
+}
+
+impl TryFrom<ConstraintSetBuilder> for ConstraintSet {
+    type Error = anyhow::Error;
+
+    fn try_from(builder: ConstraintSetBuilder) -> Result<Self> {
+        builder.build()
+    }
+}
@@ -126,6 +126,7 @@ pub enum Form {
     Debug,
     Todo,
     Reduce,
+    Cast,
 }
 
 /// A builtin is a regular applicable that acts on already reduced arguments
@@ -171,6 +172,11 @@ pub enum Intrinsic {
     Neg,
     Inv,
     Normalize,
+    /// -1, 0 or 1 according to the sign of its argument
+    Sgn,
+    /// `(bits-of x N)` is x, proven to fit in N bits by its decomposition;
+    /// `(bits-of x N k)` is the k-th bit of this decomposition
+    Bits,
 
     Begin,
 
@@ -194,6 +200,14 @@ impl Intrinsic {
         Ok(match self {
             Intrinsic::Inv => argtype[0],
             Intrinsic::Normalize => argtype[0].with_raw_magma(RawMagma::Binary),
+            Intrinsic::Sgn => argtype[0].with_raw_magma(RawMagma::Signed(2)),
+            Intrinsic::Bits => {
+                if argtype.len() > 2 {
+                    argtype[0].with_raw_magma(RawMagma::Binary)
+                } else {
+                    argtype[0]
+                }
+            }
             Intrinsic::Add | Intrinsic::Sub | Intrinsic::Neg => {
                 // Boolean is a corner case, as it is not stable under these operations
                 let max_t = max_type(argtype)?;
@@ -206,9 +220,9 @@ impl Intrinsic {
                 super::max_type(argtype.iter())?
             }
             Intrinsic::Exp => argtype[0],
-            Intrinsic::Mul => argtype.iter().max().cloned().unwrap_or(Type::INFIMUM),
+            Intrinsic::Mul => argtype.iter().fold(Type::INFIMUM, |ax, t| ax.joined(t)),
             Intrinsic::IfZero | Intrinsic::IfNotZero => {
-                argtype[1].max(argtype.get(2).cloned().unwrap_or(Type::INFIMUM))
+                argtype[1].joined(argtype.get(2).unwrap_or(&Type::INFIMUM))
             }
             Intrinsic::Begin => Type::List(max_type(argtype)?.m()),
         })
@@ -230,6 +244,8 @@ impl std::fmt::Display for Intrinsic {
                 Intrinsic::Neg => "-",
                 Intrinsic::Inv => "inv",
                 Intrinsic::Normalize => "~",
+                Intrinsic::Sgn => "sgn",
+                Intrinsic::Bits => "bits-of",
                 Intrinsic::Begin => "begin",
                 Intrinsic::IfZero => "if-zero",
                 Intrinsic::IfNotZero => "if-not-zero",
@@ -338,12 +354,15 @@ impl FuncVerifier<AstNode> for Form {
             Form::Todo => Arity::AtLeast(0),
             Form::Let => Arity::Dyadic,
             Form::Reduce => Arity::Dyadic,
+            Form::Cast => Arity::Dyadic,
         }
     }
     fn validate_types(&self, args: &[AstNode]) -> Result<()> {
         match self {
             Form::For => {
-                if matches!(args[0].class, Token::Symbol(_)) {
+                if matches!(args[0].class, Token::Symbol(_))
+                    && matches!(args[1].class, Token::Domain(_))
+                {
                     Ok(())
                 } else {
                     bail!(
@@ -377,6 +396,13 @@ impl FuncVerifier<AstNode> for Form {
                 }
                 Ok(())
             }
+            Form::Cast => {
+                if let Token::Keyword(ref kw) = args[1].class {
+                    Magma::try_from(kw.as_str()).map(|_| ())
+                } else {
+                    bail!("CAST expects a type, found `{:?}`", args[1])
+                }
+            }
         }
     }
 }
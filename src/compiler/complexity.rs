@@ -0,0 +1,156 @@
+use itertools::Itertools;
+use std::collections::HashMap;
+
+use super::{Constraint, ConstraintSet, Expression, Intrinsic, Node};
+use crate::structs::Handle;
+
+/// How many refactoring hints are given per offending constraint
+const HINTS: usize = 3;
+
+/// The maximal size of the expanded constraints, beyond which exporters and
+/// provers may break down
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ComplexityLimits {
+    /// the maximal number of nodes of a constraint expression
+    pub max_nodes: Option<usize>,
+    /// the maximal polynomial degree of a constraint expression
+    pub max_degree: Option<usize>,
+}
+impl ComplexityLimits {
+    pub fn is_set(&self) -> bool {
+        self.max_nodes.is_some() || self.max_degree.is_some()
+    }
+}
+
+/// A constraint expression exceeding the complexity limits
+pub struct ComplexExpression {
+    /// The constraint containing the expression
+    pub handle: Handle,
+    pub nodes: usize,
+    pub degree: usize,
+    /// Sub-expressions that could be committed to their own column to
+    /// simplify the constraint, with the number of nodes it would save
+    pub hints: Vec<(Node, usize)>,
+}
+
+/// The number of nodes of the AST rooted at `n`
+pub fn node_count(n: &Node) -> usize {
+    1 + match n.e() {
+        Expression::Funcall { args, .. } => args.iter().map(node_count).sum(),
+        Expression::List(xs) => xs.iter().map(node_count).sum(),
+        _ => 0,
+    }
+}
+
+/// The polynomial degree of `n` in the columns it reads. Non-polynomial
+/// operations, e.g. inverses, are replaced by columns when fully expanded,
+/// and thus account for a degree of one; a conditional weighs as its condition
+/// multiplied by its heaviest branch, as it ends up once expanded.
+pub fn degree(n: &Node) -> usize {
+    match n.e() {
+        Expression::Const(_) | Expression::Void => 0,
+        Expression::Column { .. }
+        | Expression::ExoColumn { .. }
+        | Expression::ArrayColumn { .. } => 1,
+        Expression::List(xs) => xs.iter().map(degree).max().unwrap_or(0),
+        Expression::Funcall { func, args } => match func {
+            Intrinsic::Add
+            | Intrinsic::Sub
+            | Intrinsic::VectorAdd
+            | Intrinsic::VectorSub
+            | Intrinsic::Begin => args.iter().map(degree).max().unwrap_or(0),
+            Intrinsic::Mul | Intrinsic::VectorMul => args.iter().map(degree).sum(),
+            Intrinsic::Neg => degree(&args[0]),
+            Intrinsic::Exp => args[1]
+                .pure_eval()
+                .ok()
+                .and_then(|e| usize::try_from(e).ok())
+                .map(|e| degree(&args[0]) * e)
+                .unwrap_or(1),
+            Intrinsic::IfZero | Intrinsic::IfNotZero => {
+                degree(&args[0]) + args.iter().skip(1).map(degree).max().unwrap_or(0)
+            }
+            Intrinsic::Inv | Intrinsic::Normalize | Intrinsic::Sgn | Intrinsic::Bits => 1,
+        },
+    }
+}
+
+/// The sub-expressions of `n` appearing several times in it, or, failing
+/// that, its largest direct sub-expressions, along with the number of nodes
+/// that committing each of them to a column would save.
+fn split_points(n: &Node) -> Vec<(Node, usize)> {
+    fn rec<'a>(n: &'a Node, ax: &mut HashMap<String, (&'a Node, usize)>) {
+        let args = match n.e() {
+            Expression::Funcall { args, .. } => args,
+            Expression::List(xs) => xs,
+            _ => return,
+        };
+        ax.entry(n.to_string()).or_insert((n, 0)).1 += 1;
+        for a in args {
+            rec(a, ax);
+        }
+    }
+
+    let mut subexprs = HashMap::new();
+    rec(n, &mut subexprs);
+    let shared = subexprs
+        .into_values()
+        .filter(|(_, count)| *count > 1)
+        .map(|(e, count)| (e.clone(), (count - 1) * node_count(e)))
+        .sorted_by_key(|(e, saved)| (std::cmp::Reverse(*saved), e.to_string()))
+        .take(HINTS)
+        .collect::<Vec<_>>();
+    if !shared.is_empty() {
+        return shared;
+    }
+
+    match n.e() {
+        Expression::Funcall { args, .. } => args
+            .iter()
+            .filter(|a| matches!(a.e(), Expression::Funcall { .. }))
+            .map(|a| (a.clone(), node_count(a) - 1))
+            .sorted_by_key(|(_, saved)| std::cmp::Reverse(*saved))
+            .take(HINTS)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// List the expressions of the constraints of `cs` exceeding `limits`
+pub fn complex_expressions(
+    cs: &ConstraintSet,
+    limits: &ComplexityLimits,
+) -> Vec<ComplexExpression> {
+    cs.constraints
+        .iter()
+        .flat_map(|c| {
+            let exprs: Vec<&Node> = match c {
+                Constraint::Vanishes { expr, .. } => vec![expr.as_ref()],
+                Constraint::Lookup {
+                    including,
+                    included,
+                    ..
+                } => including.iter().chain(included.iter()).collect(),
+                Constraint::InRange { exp, .. } => vec![exp],
+                Constraint::Permutation { .. } | Constraint::Normalization { .. } => vec![],
+            };
+            exprs.into_iter().map(move |e| (c.handle(), e))
+        })
+        .filter_map(|(handle, e)| {
+            let nodes = node_count(e);
+            let degree = degree(e);
+            if limits.max_nodes.is_some_and(|max| nodes > max)
+                || limits.max_degree.is_some_and(|max| degree > max)
+            {
+                Some(ComplexExpression {
+                    handle: handle.to_owned(),
+                    nodes,
+                    degree,
+                    hints: split_points(e),
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
@@ -12,7 +12,7 @@ use std::sync::OnceLock;
 
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Debug;
-use std::io::Write;
+use std::io::{Read, Write};
 
 use std::sync::atomic::AtomicUsize;
 
@@ -23,13 +23,45 @@ use super::{common::*, CompileSettings, Conditioning, Expression, Magma, Node, T
 use crate::column::{Column, ColumnSet, Computation, RegisterID, Value, ValueBacking};
 use crate::dag::ComputationDag;
 use crate::errors::{self, CompileError, RuntimeError};
+use crate::import::ValuesEncoding;
 use crate::pretty::Pretty;
-use crate::structs::Handle;
+use crate::structs::{Handle, Span};
 use crate::utils::hash_strings;
 
+/// The largest shifts with which the constraints read the columns of a
+/// module, that backends need to size its evaluation domain
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShiftWindow {
+    /// the largest backward shift, as a number of rows
+    pub backward: isize,
+    /// the largest forward shift, as a number of rows
+    pub forward: isize,
+}
+
+/// A column read by a constraint beyond the spilling of its module
+#[derive(Debug, Clone)]
+pub struct PaddingRead {
+    pub constraint: String,
+    pub column: Handle,
+    pub shift: isize,
+    pub spilling: isize,
+}
+impl std::fmt::Display for PaddingRead {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} reads {} at shift {}, beyond the spilling of {} rows of its module; it will read padding in the first rows",
+            self.constraint.bold(),
+            self.column.pretty(),
+            self.shift.to_string().yellow().bold(),
+            self.spilling
+        )
+    }
+}
+
 static COUNTER: OnceLock<AtomicUsize> = OnceLock::new();
 
-fn uniquify(n: String) -> String {
+pub(crate) fn uniquify(n: String) -> String {
     format!(
         "{}-{}",
         n,
@@ -79,6 +111,60 @@ impl Constraint {
         }
     }
 
+    pub fn handle(&self) -> &Handle {
+        match self {
+            Constraint::Vanishes { handle, .. }
+            | Constraint::Lookup { handle, .. }
+            | Constraint::Permutation { handle, .. }
+            | Constraint::InRange { handle, .. }
+            | Constraint::Normalization { handle, .. } => handle,
+        }
+    }
+
+    /// The columns this constraint reads from
+    pub fn dependencies(&self) -> HashSet<ColumnRef> {
+        match self {
+            Constraint::Vanishes { expr, .. } => expr.dependencies(),
+            Constraint::Lookup {
+                including,
+                included,
+                ..
+            } => including
+                .iter()
+                .chain(included.iter())
+                .flat_map(|e| e.dependencies())
+                .collect(),
+            Constraint::Permutation { from, to, .. } => {
+                from.iter().chain(to.iter()).cloned().collect()
+            }
+            Constraint::InRange { exp, .. } => exp.dependencies(),
+            Constraint::Normalization {
+                reference,
+                inverted,
+                ..
+            } => {
+                let mut r = reference.dependencies();
+                r.insert(inverted.clone());
+                r
+            }
+        }
+    }
+
+    /// The expressions this constraint is evaluated on
+    pub fn expressions(&self) -> Vec<&Node> {
+        match self {
+            Constraint::Vanishes { expr, .. } => vec![expr],
+            Constraint::Lookup {
+                including,
+                included,
+                ..
+            } => including.iter().chain(included.iter()).collect(),
+            Constraint::Permutation { .. } => vec![],
+            Constraint::InRange { exp, .. } => vec![exp],
+            Constraint::Normalization { reference, .. } => vec![reference],
+        }
+    }
+
     pub fn add_id_to_handles(&mut self, set_id: &dyn Fn(&mut ColumnRef)) {
         match self {
             Constraint::Vanishes { expr, .. } => expr.add_id_to_handles(set_id),
@@ -133,7 +219,7 @@ impl EvalSettings {
     }
 
     pub fn wrap(self, w: bool) -> Self {
-        Self { wrap: w, ..self }
+        Self { wrap: w }
     }
 }
 
@@ -251,6 +337,8 @@ impl FuncVerifier<Node> for Intrinsic {
             Intrinsic::Neg => Arity::Monadic,
             Intrinsic::Inv => Arity::Monadic,
             Intrinsic::Normalize => Arity::Monadic,
+            Intrinsic::Sgn => Arity::Monadic,
+            Intrinsic::Bits => Arity::Between(2, 3),
             Intrinsic::Begin => Arity::AtLeast(1),
             Intrinsic::IfZero | Intrinsic::IfNotZero => Arity::Between(2, 3),
         }
@@ -278,22 +366,39 @@ impl FuncVerifier<Node> for Intrinsic {
                 //     )
                 // }
             }
-            Intrinsic::IfZero => {
-                if !args_t[0].is_conditioned() {
-                    bail!(CompileError::ConditioningError(self.to_string(), args_t[0]))
+            Intrinsic::Bits => {
+                let width = args[1]
+                    .pure_eval()
+                    .ok()
+                    .and_then(|n| n.to_usize())
+                    .filter(|n| (1..crate::constants::FIELD_BITSIZE).contains(n))
+                    .with_context(|| {
+                        anyhow!(
+                            "the width of {} must be a constant in [1; {}[, found {}",
+                            self,
+                            crate::constants::FIELD_BITSIZE,
+                            args[1].pretty()
+                        )
+                    })?;
+                if let Some(k) = args.get(2) {
+                    if !k
+                        .pure_eval()
+                        .ok()
+                        .and_then(|k| k.to_usize())
+                        .map(|k| k < width)
+                        .unwrap_or(false)
+                    {
+                        bail!(
+                            "the bit selected by {} must be a constant in [0; {}[, found {}",
+                            self,
+                            width,
+                            k.pretty()
+                        )
+                    }
                 }
-
-                // TODO: maybe?
-                // if args_t
-                //     .iter()
-                //     .skip(1)
-                //     .any(|t| args_t.get(1).map(|tt| tt.c() != t.c()).unwrap_or(false))
-                // {
-                //     bail!(
-                //         "can not mix multiple conditionings: {}",
-                //         args_t.iter().map(|t| t.to_string()).join(" ")
-                //     )
-                // }
+            }
+            Intrinsic::IfZero if !args_t[0].is_conditioned() => {
+                bail!(CompileError::ConditioningError(self.to_string(), args_t[0]))
             }
             _ => {}
         }
@@ -319,7 +424,8 @@ impl FuncVerifier<Node> for Intrinsic {
             | Intrinsic::VectorMul => &[&[Type::Any(Magma::ANY)]],
             Intrinsic::Exp => &[&[Type::Any(Magma::ANY)], &[Type::Scalar(Magma::ANY)]],
             Intrinsic::Neg => &[&[Type::Scalar(Magma::ANY), Type::Column(Magma::ANY)]],
-            Intrinsic::Inv | Intrinsic::Normalize => &[&[Type::Any(Magma::ANY)]],
+            Intrinsic::Inv | Intrinsic::Normalize | Intrinsic::Sgn => &[&[Type::Any(Magma::ANY)]],
+            Intrinsic::Bits => &[&[Type::Any(Magma::ANY)], &[Type::Scalar(Magma::ANY)]],
             Intrinsic::IfZero | Intrinsic::IfNotZero => &[
                 // condition type
                 &[Type::Any(Magma::ANY)],
@@ -343,6 +449,14 @@ impl FuncVerifier<Node> for Intrinsic {
 
 pub type PerspectiveTable = HashMap<String, HashMap<String, Node>>;
 
+/// The header of constraint sets serialized in the binary format, followed
+/// by the version of their layout
+const BINARY_MAGIC: &[u8] = b"CORSET\x00";
+/// The version of the layout of the binary format; as bincode ignores
+/// `#[serde(default)]`, it must be bumped whenever a field serialized within a
+/// [`ConstraintSet`] is added, removed or moved.
+pub const BINARY_VERSION: u8 = 3;
+
 pub const ADDER_MODULE: &str = "#adder";
 pub const MULER_MODULE: &str = "#muler";
 
@@ -353,16 +467,88 @@ pub struct ConstraintSet {
     pub constants: HashMap<Handle, BigInt>,
     pub computations: ComputationTable,
     pub perspectives: PerspectiveTable,
+    /// the perspectives declared in several modules under the same name
+    #[serde(default)]
+    pub shared_perspectives: HashSet<String>,
     pub transformations: u32,
     pub auto_constraints: u32,
+    /// the documentation of the constraints, from the `;;` comments preceding
+    /// their definition
+    #[serde(default)]
+    pub docs: HashMap<Handle, String>,
+    /// the shift window of every module, as of the last expansion
+    #[serde(default)]
+    pub shift_windows: BTreeMap<String, ShiftWindow>,
+    /// the build of corset that compiled this constraint set, if it has been
+    /// exported
+    #[serde(default)]
+    pub build_info: Option<crate::version::BuildInfo>,
+    /// where the constraints have been defined in the sources
+    #[serde(default)]
+    pub spans: HashMap<Handle, Span>,
+    /// the constraints only evaluated when checking a trace, and never
+    /// exported to the prover
+    #[serde(default)]
+    pub assertions: Vec<Constraint>,
 }
 impl ConstraintSet {
+    /// Serialize this constraint set in the binary format, which is much
+    /// faster to load than RON
+    pub fn to_binary(&self) -> Result<Vec<u8>> {
+        let mut r = BINARY_MAGIC.to_vec();
+        r.push(BINARY_VERSION);
+        bincode::serialize_into(&mut r, self)?;
+        Ok(r)
+    }
+
+    /// Load a constraint set serialized either in the binary format or in RON
+    /// from `bytes`, originating from `artifact`.
+    pub fn from_bytes(bytes: &[u8], artifact: &str) -> Result<Self> {
+        Self::from_reader(bytes, artifact)
+    }
+
+    /// Load a constraint set serialized either in the binary format or in RON
+    /// from `r`, originating from `artifact`, without first reading it whole.
+    pub fn from_reader(mut r: impl Read, artifact: &str) -> Result<Self> {
+        let mut head = Vec::with_capacity(BINARY_MAGIC.len() + 1);
+        (&mut r)
+            .take(BINARY_MAGIC.len() as u64 + 1)
+            .read_to_end(&mut head)
+            .with_context(|| anyhow!("while reading `{}`", artifact))?;
+        let cs: ConstraintSet = if let Some(version) = head.strip_prefix(BINARY_MAGIC) {
+            match version.first() {
+                Some(&BINARY_VERSION) => bincode::deserialize_from(r)
+                    .with_context(|| anyhow!("while deserializing `{}`", artifact))?,
+                Some(version) => bail!(
+                    "`{}` uses version {} of the binary format, but this corset reads version {}; it must be rebuilt",
+                    artifact,
+                    version.to_string().red().bold(),
+                    BINARY_VERSION
+                ),
+                None => bail!("`{}` is truncated", artifact),
+            }
+        } else {
+            let mut text = String::new();
+            head.as_slice()
+                .chain(r)
+                .read_to_string(&mut text)
+                .with_context(|| anyhow!("`{}` is neither binary nor RON", artifact))?;
+            ron::from_str(&text).with_context(|| anyhow!("while parsing `{}`", artifact))?
+        };
+        if let Some(build_info) = cs.build_info.as_ref() {
+            build_info.check_compatibility(artifact);
+        }
+        Ok(cs)
+    }
+
     pub fn new(
         columns: ColumnSet,
         constraints: Vec<Constraint>,
+        assertions: Vec<Constraint>,
         constants: HashMap<Handle, BigInt>,
         computations: ComputationTable,
         perspectives: PerspectiveTable,
+        shared_perspectives: HashSet<String>,
     ) -> Result<Self> {
         let mut r = ConstraintSet {
             constraints,
@@ -370,17 +556,40 @@ impl ConstraintSet {
             constants,
             computations,
             perspectives,
+            shared_perspectives,
             transformations: 0,
             auto_constraints: 0,
+            docs: HashMap::new(),
+            shift_windows: BTreeMap::new(),
+            build_info: None,
+            spans: HashMap::new(),
+            assertions,
         };
         r.convert_refs_to_ids()?;
         r.allocate_registers();
+        r.warn_widened_registers();
         r.fill_perspectives()?;
         r.compute_spillings();
+        r.compute_shift_windows();
         r.validate()?;
         Ok(r)
     }
 
+    /// Warn about the registers wider than some of their unproven columns, as
+    /// the values they hold may then silently exceed the range assumed by the
+    /// constraints
+    fn warn_widened_registers(&self) {
+        for w in self.columns.widened_registers() {
+            super::warning(format!(
+                "register r{} is {} bits wide, but {} assumes {} bits",
+                w.register,
+                w.bits.to_string().red(),
+                w.assumed_by.bold(),
+                w.assumed_bits
+            ));
+        }
+    }
+
     fn allocate_registers(&mut self) {
         #[derive(Default, Debug)]
         struct ColumnPool {
@@ -389,6 +598,7 @@ impl ConstraintSet {
             // multiple compilations of the same constraint system result in the
             // same allocation; hence the use of BTreeMap instead of HashMap.
             // Module -> SizeFactor -> Type -> Perspective -> Columns names
+            #[allow(clippy::type_complexity)]
             perspectives: BTreeMap<
                 String,
                 BTreeMap<usize, BTreeMap<Magma, BTreeMap<String, Vec<ColumnRef>>>>,
@@ -401,7 +611,8 @@ impl ConstraintSet {
             .iter()
             .sorted_by(|a, b| a.1.handle.cmp(&b.1.handle))
         {
-            if col.kind == Kind::Commitment {
+            // columns may have been explicitly assigned to a register
+            if col.kind == Kind::Commitment && col.register.is_none() {
                 match &col.handle.perspective {
                     Some(name) => {
                         let module = col.handle.module.to_string();
@@ -584,6 +795,7 @@ impl ConstraintSet {
                         mut froms,
                         mut tos,
                         mut signs,
+                        ..
                     } => {
                         if let Some(perspective) = froms
                             .iter()
@@ -593,7 +805,7 @@ impl ConstraintSet {
                                 hash_strings(froms.iter().map(|f| f.as_handle().name.clone()));
                             let module = self.columns.column(&froms[0])?.handle.module.to_owned();
                             if let Expression::Column { handle, .. } =
-                                self.get_perspective(&module, &perspective)?.e().clone()
+                                self.get_perspective(&module, perspective)?.e().clone()
                             {
                                 let srt_guard_col_handle = Handle::new(
                                     &module,
@@ -645,13 +857,13 @@ impl ConstraintSet {
 
     // do not pollute compilation output of the binary
     #[allow(dead_code)]
-    pub fn from_ptr<'a>(ptr: *const ConstraintSet) -> &'a Self {
+    pub(crate) fn from_ptr<'a>(ptr: *const ConstraintSet) -> &'a Self {
         assert!(!ptr.is_null());
         unsafe { &*ptr }
     }
 
     #[allow(dead_code)]
-    pub fn mut_from_ptr<'a>(ptr: *mut ConstraintSet) -> &'a mut Self {
+    pub(crate) fn mut_from_ptr<'a>(ptr: *mut ConstraintSet) -> &'a mut Self {
         assert!(!ptr.is_null());
         unsafe { &mut *ptr }
     }
@@ -760,6 +972,21 @@ impl ConstraintSet {
         }
     }
 
+    /// The constraints and assertions selected by name, either explicitly by
+    /// `only` if it is set, or by not being excluded by `skip`
+    pub(crate) fn selected_constraints(
+        &self,
+        only: &Option<Vec<String>>,
+        skip: &[String],
+    ) -> Vec<&Constraint> {
+        self.constraints
+            .iter()
+            .chain(self.assertions.iter())
+            .filter(|c| only.as_ref().map(|o| o.contains(&c.name())).unwrap_or(true))
+            .filter(|c| !skip.contains(&c.name()))
+            .collect()
+    }
+
     pub(crate) fn get_perspective(&self, module: &str, name: &str) -> Result<&Node> {
         self.perspectives
             .get(module)
@@ -775,6 +1002,7 @@ impl ConstraintSet {
         };
         self.constraints
             .iter_mut()
+            .chain(self.assertions.iter_mut())
             .for_each(|x| x.add_id_to_handles(&convert_to_id));
 
         self.computations.dependencies = self
@@ -797,9 +1025,15 @@ impl ConstraintSet {
                 Computation::Interleaved { target, froms } => std::iter::once(target)
                     .chain(froms.iter_mut())
                     .for_each(convert_to_id),
-                Computation::Sorted { froms, tos, .. } => froms
+                Computation::Sorted {
+                    froms,
+                    tos,
+                    tiebreaks,
+                    ..
+                } => froms
                     .iter_mut()
                     .chain(tos.iter_mut())
+                    .chain(tiebreaks.iter_mut())
                     .for_each(convert_to_id),
                 Computation::CyclicFrom { target, froms, .. } => std::iter::once(target)
                     .chain(froms.iter_mut())
@@ -832,6 +1066,31 @@ impl ConstraintSet {
         self.columns.effective_len.get(m).copied()
     }
 
+    /// Only import the first `limit` rows of every module from traces. As
+    /// modules are truncated independently of each other, cross-module
+    /// lookups may not hold anymore.
+    pub fn set_row_limit(&mut self, limit: Option<usize>) {
+        if let Some(limit) = limit {
+            warn!(
+                "only the first {} rows of every module will be imported; cross-module lookups may fail",
+                limit.to_string().bold()
+            );
+        }
+        self.columns.row_limit = limit;
+    }
+
+    /// Set what to do with the values that traces provide for the columns
+    /// filled by computations
+    pub fn set_computed_in_trace(&mut self, policy: crate::compute::ComputedInTrace) {
+        self.columns.computed_in_trace = policy;
+    }
+
+    /// Expect traces to lack some columns, whose absence is then not an error,
+    /// the constraints depending on them being left unchecked
+    pub fn set_partial(&mut self, partial: bool) {
+        self.columns.partial = partial;
+    }
+
     pub fn effective_len_or_set(&mut self, m: &str, x: isize) -> isize {
         *self.columns.effective_len.entry(m.to_string()).or_insert(x)
     }
@@ -845,36 +1104,85 @@ impl ConstraintSet {
         self.spilling_of(module)
     }
 
-    pub(crate) fn compute_spilling(&mut self, m: &str) -> isize {
-        let spilling = self
-            .computations
+    /// The spilling required by every composite computation and vanishing
+    /// constraint, along with the module it applies to
+    fn spillings(&self) -> impl Iterator<Item = (String, isize)> + '_ {
+        let spilling = |exp: &Node| exp.past_spill().abs().max(exp.future_spill().abs());
+        self.computations
             .iter()
-            .filter_map(|c| match c {
-                Computation::Composite { target, exp } => {
-                    if target.as_handle().module == m {
-                        Some(exp.past_spill().abs().max(exp.future_spill().abs()))
+            .filter_map(move |c| match c {
+                Computation::Composite { target, exp } => Some((
+                    if target.is_handle() {
+                        target.as_handle().module.clone()
                     } else {
-                        None
-                    }
-                }
+                        self.columns.module_of(target)
+                    },
+                    spilling(exp),
+                )),
                 _ => None,
             })
-            .chain(self.constraints.iter().filter_map(|c| match c {
+            .chain(self.constraints.iter().filter_map(move |c| match c {
                 Constraint::Vanishes { handle, expr, .. } => {
-                    if handle.module == m {
-                        Some(expr.past_spill().abs().max(expr.future_spill().abs()))
-                    } else {
-                        None
-                    }
+                    Some((handle.module.clone(), spilling(expr)))
                 }
                 _ => None,
             }))
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn compute_spilling(&mut self, m: &str) -> isize {
+        let spilling = self
+            .spillings()
+            .filter_map(|(module, spilling)| (module == m).then_some(spilling))
             .max()
             .unwrap_or(0);
         self.columns.spilling.insert(m.to_owned(), spilling);
         spilling
     }
 
+    /// Find the columns read by constraints with a negative shift larger than
+    /// the spilling of their module; such reads silently fall back to the
+    /// padding value, and may mask errors in the first rows of a trace.
+    pub fn padding_reads(&self) -> Vec<PaddingRead> {
+        let mut r = Vec::new();
+        for c in self.constraints.iter() {
+            if matches!(
+                c,
+                Constraint::Vanishes {
+                    domain: Some(_),
+                    ..
+                }
+            ) {
+                // evaluation at fixed rows wraps around instead of spilling
+                continue;
+            }
+            for e in c.expressions() {
+                for leaf in e.leaves() {
+                    if let Expression::Column { handle, shift, .. }
+                    | Expression::ExoColumn { handle, shift, .. } = leaf.e()
+                    {
+                        let shift = *shift as isize;
+                        let Result::Ok(column) = self.columns.column(handle) else {
+                            continue;
+                        };
+                        let spilling = self.spilling_of(&column.handle.module).unwrap_or(0);
+                        if -shift > spilling {
+                            r.push(PaddingRead {
+                                constraint: c.name(),
+                                column: column.handle.clone(),
+                                shift,
+                                spilling,
+                            })
+                        }
+                    }
+                }
+            }
+        }
+        r.sort_by(|a, b| (&a.constraint, &a.column).cmp(&(&b.constraint, &b.column)));
+        r.dedup_by(|a, b| a.constraint == b.constraint && a.column == b.column);
+        r
+    }
+
     pub fn iter_len(&self, module: &str) -> usize {
         self.effective_len_for(module)
             // If the module is empty, use its spilling
@@ -886,12 +1194,56 @@ impl ConstraintSet {
         self.columns.spilling.get(m).cloned()
     }
 
-    fn compute_spillings(&mut self) {
-        let all_modules = self.columns.modules();
-        for m in all_modules {
-            let spilling = self.compute_spilling(&m);
-            self.columns.spilling.insert(m, spilling);
+    /// Compute the spilling of all the modules in a single traversal of the
+    /// constraint set, and cache it in the column set, where the import, the
+    /// computations and the checks read it from; it must be refreshed
+    /// whenever constraints or computations are added.
+    pub(crate) fn compute_spillings(&mut self) {
+        let mut spillings = self
+            .columns
+            .modules()
+            .into_iter()
+            .map(|m| (m, 0))
+            .collect::<HashMap<_, _>>();
+        for (module, spilling) in self.spillings() {
+            let max = spillings.entry(module).or_insert(0);
+            *max = (*max).max(spilling);
         }
+        self.columns.spilling = spillings;
+    }
+
+    pub fn shift_window_of(&self, m: &str) -> ShiftWindow {
+        self.shift_windows.get(m).cloned().unwrap_or_default()
+    }
+
+    /// Compute the shift window of all the modules, the shifts being
+    /// attributed to the module of the column they are applied to; like the
+    /// spillings, it must be refreshed whenever constraints are added.
+    pub(crate) fn compute_shift_windows(&mut self) {
+        let mut windows = self
+            .columns
+            .modules()
+            .into_iter()
+            .map(|m| (m, ShiftWindow::default()))
+            .collect::<BTreeMap<_, _>>();
+        for c in self.constraints.iter() {
+            for e in c.expressions() {
+                for leaf in e.leaves() {
+                    if let Expression::Column { handle, shift, .. }
+                    | Expression::ExoColumn { handle, shift, .. } = leaf.e()
+                    {
+                        let Result::Ok(column) = self.columns.column(handle) else {
+                            continue;
+                        };
+                        let window = windows.entry(column.handle.module.clone()).or_default();
+                        let shift = *shift as isize;
+                        window.backward = window.backward.max(-shift);
+                        window.forward = window.forward.max(shift);
+                    }
+                }
+            }
+        }
+        self.shift_windows = windows;
     }
 
     pub(crate) fn module_of_expr(&self, e: &Node) -> Option<String> {
@@ -936,9 +1288,20 @@ impl ConstraintSet {
                 .unwrap_or(1)
     }
 
+    /// Write the expanded trace to `out`, the values of the columns being
+    /// encoded as per `encoding`; if `checksums` is set, the checksum of each
+    /// column is appended to it.
     #[time("info", "Exporting expanded trace")]
-    pub fn write(&mut self, out: &mut impl Write) -> Result<()> {
+    pub fn write(
+        &mut self,
+        out: &mut impl Write,
+        checksums: bool,
+        encoding: ValuesEncoding,
+    ) -> Result<()> {
         let mut cache = cached::SizedCache::with_size(200000); // ~1.60MB cache
+        let mut column_checksums = Vec::new();
+        // module -> (padded length, spilling), as actually written
+        let mut module_lengths = Vec::new();
 
         out.write_all("{\"columns\":{\n".as_bytes())?;
 
@@ -956,10 +1319,17 @@ impl ConstraintSet {
                 .filter(|(_, c)| c.handle.module == module)
                 .peekable();
             let empty_backing: ValueBacking = ValueBacking::default();
+            let mut module_length = None;
             while let Some((r, column)) = current_col.next() {
                 let handle = &column.handle;
                 trace!("Writing {}", handle);
                 let backing = self.columns.backing(&r).unwrap_or(&empty_backing);
+                if module_length.is_none()
+                    && backing.padded_len() > 0
+                    && self.length_multiplier(&r) == 1
+                {
+                    module_length = Some((backing.padded_len(), backing.spilling()));
+                }
                 let padding: Value = if let Some(v) = column.padding_value.as_ref() {
                     v.clone()
                 } else {
@@ -987,22 +1357,58 @@ impl ConstraintSet {
                 };
 
                 out.write_all(format!("\"{}\":{{\n", handle).as_bytes())?;
-                out.write_all("\"values\":[".as_bytes())?;
-
-                let mut value = backing.iter(&self.columns).peekable();
-                while let Some(x) = value.next() {
-                    out.write_all(
-                        cache
-                            .cache_get_or_set_with(x.to_owned(), || {
-                                format!("\"0x0{}\"", x.to_string()[2..].trim_start_matches('0'))
-                            })
-                            .as_bytes(),
-                    )?;
-                    if value.peek().is_some() {
-                        out.write_all(b",")?;
+                out.write_all("\"values\":".as_bytes())?;
+
+                let mut hasher = checksums.then(crate::checksum::ColumnHasher::default);
+                match encoding {
+                    ValuesEncoding::Hex => {
+                        out.write_all(b"[")?;
+                        let mut value = backing.iter(&self.columns).peekable();
+                        while let Some(x) = value.next() {
+                            let s = cache.cache_get_or_set_with(x.to_owned(), || {
+                                let hex = match x {
+                                    Value::ExoNative(_) => x.pretty(),
+                                    _ => format!("{:x}", x.to_bi()),
+                                };
+                                format!("\"0x0{}\"", hex.trim_start_matches('0'))
+                            });
+                            out.write_all(s.as_bytes())?;
+                            if let Some(hasher) = hasher.as_mut() {
+                                hasher.update_value(&x)?;
+                            }
+                            if value.peek().is_some() {
+                                out.write_all(b",")?;
+                            }
+                        }
+                        out.write_all(b"]")?;
+                    }
+                    ValuesEncoding::Base64Fixed32 | ValuesEncoding::Compact => {
+                        const WIDTH: usize = ValuesEncoding::FIXED_WIDTH;
+                        let mut bytes = Vec::with_capacity(backing.padded_len() * WIDTH);
+                        for (i, x) in backing.iter(&self.columns).enumerate() {
+                            let (_, be) = BigInt::from(&x).to_bytes_be();
+                            if be.len() > WIDTH {
+                                bail!(
+                                    "{}th element of {} does not fit in {} bytes",
+                                    i,
+                                    handle.pretty(),
+                                    WIDTH
+                                );
+                            }
+                            let start = bytes.len();
+                            bytes.resize(start + WIDTH - be.len(), 0);
+                            bytes.extend_from_slice(&be);
+                            if let Some(hasher) = hasher.as_mut() {
+                                hasher.update_value(&x)?;
+                            }
+                        }
+                        out.write_all(encoding.encode(&bytes).as_bytes())?;
                     }
                 }
-                out.write_all(b"],\n")?;
+                out.write_all(b",\n")?;
+                if let Some(hasher) = hasher {
+                    column_checksums.push((handle.to_string(), hasher.finalize()));
+                }
                 out.write_all(
                     format!(
                         "\"padding_strategy\": {{\"action\": \"prepend\", \"value\": \"{}\"}}",
@@ -1010,13 +1416,60 @@ impl ConstraintSet {
                     )
                     .as_bytes(),
                 )?;
+                if self.columns.is_skipped(&r) {
+                    // skipped columns are empty, and must not be mistaken for
+                    // actual zeroes
+                    out.write_all(b",\n\"skipped\": true")?;
+                }
                 out.write_all(b"\n}\n")?;
                 if current_col.peek().is_some() {
                     out.write_all(b",")?;
                 }
             }
+            let (padded_len, spilling) =
+                module_length.unwrap_or((0, self.spilling_of(&module).unwrap_or(0)));
+            module_lengths.push((module, padded_len, spilling));
         }
-        out.write_all("}}".as_bytes())?;
+        out.write_all(b"}")?;
+
+        // the lengths of the modules, so that consumers of the trace do not
+        // have to guess the spilling applied by corset
+        out.write_all(b",\n\"modules\":{\n")?;
+        out.write_all(
+            module_lengths
+                .iter()
+                .map(|(module, padded_len, spilling)| {
+                    format!(
+                        "\"{}\":{{\"raw_length\":{},\"padded_length\":{},\"spilling\":{}}}",
+                        module,
+                        padded_len.saturating_sub(*spilling as usize),
+                        padded_len,
+                        spilling
+                    )
+                })
+                .join(",\n")
+                .as_bytes(),
+        )?;
+        out.write_all(b"}")?;
+
+        if checksums {
+            out.write_all(
+                format!(
+                    ",\n\"checksums\":{{\"algorithm\":\"{}\",\"columns\":{{\n",
+                    crate::checksum::ALGORITHM
+                )
+                .as_bytes(),
+            )?;
+            out.write_all(
+                column_checksums
+                    .iter()
+                    .map(|(column, checksum)| format!("\"{}\":\"{}\"", column, checksum))
+                    .join(",\n")
+                    .as_bytes(),
+            )?;
+            out.write_all(b"}}")?;
+        }
+        out.write_all(b"}")?;
 
         Ok(())
     }
@@ -1027,7 +1480,7 @@ impl ConstraintSet {
         //
 
         // Check the constraints
-        for c in self.constraints.iter() {
+        for c in self.constraints.iter().chain(self.assertions.iter()) {
             match c {
                 Constraint::Vanishes { handle, expr, .. } => {
                     if expr.dependencies().into_iter().any(|r| !r.is_id()) {
@@ -1107,8 +1560,18 @@ impl ConstraintSet {
                         ))
                     }
                 }
-                Computation::Sorted { froms, tos, .. } => {
-                    if tos.iter().any(|r| !r.is_id()) || froms.iter().any(|r| !r.is_id()) {
+                Computation::Sorted {
+                    froms,
+                    tos,
+                    tiebreaks,
+                    ..
+                } => {
+                    if tos
+                        .iter()
+                        .chain(froms.iter())
+                        .chain(tiebreaks.iter())
+                        .any(|r| !r.is_id())
+                    {
                         bail!(errors::compiler::Error::ComputationWithHandles(
                             c.to_string()
                         ))
@@ -1211,13 +1674,74 @@ impl ConstraintSet {
                         }
                     }
                 }
-                Computation::Sorted { froms, tos, signs } => {
+                Computation::Sorted {
+                    froms,
+                    tos,
+                    signs,
+                    tiebreaks,
+                    ..
+                } => {
                     assert!(froms.len() == tos.len());
-                    assert!(froms.len() == signs.len());
+                    assert!(froms.len() >= signs.len());
+                    for t in tiebreaks.iter() {
+                        if self.columns.module_of(t) != self.columns.module_of(&froms[0]) {
+                            bail!(
+                                "tie-breaking column {} is not in module {}",
+                                self.handle(t).pretty(),
+                                self.columns.module_of(&froms[0]).blue()
+                            )
+                        }
+                    }
                 }
                 _ => {}
             }
         }
+
+        //
+        // Check that shared perspectives hold the same columns in all modules,
+        // so that they are packed in the same registers layout
+        //
+        for perspective in self.shared_perspectives.iter().sorted() {
+            let mut layouts = BTreeMap::<&str, Vec<(&str, Magma)>>::new();
+            for module in self
+                .perspectives
+                .iter()
+                .filter(|(_, ps)| ps.contains_key(perspective))
+                .map(|(module, _)| module)
+            {
+                layouts.insert(module, Vec::new());
+            }
+            for c in self.columns.iter_cols().filter(|c| {
+                c.kind == Kind::Commitment && c.handle.perspective.as_ref() == Some(perspective)
+            }) {
+                layouts
+                    .entry(&c.handle.module)
+                    .or_default()
+                    .push((&c.handle.name, c.t));
+            }
+            for layout in layouts.values_mut() {
+                layout.sort();
+            }
+            if let Some(((m1, l1), (m2, l2))) = layouts
+                .iter()
+                .tuple_windows()
+                .find(|((_, l1), (_, l2))| l1 != l2)
+            {
+                let render = |l: &[(&str, Magma)]| {
+                    l.iter()
+                        .map(|(name, t)| format!("{}:{}", name, t))
+                        .join(" ")
+                };
+                bail!(
+                    "shared perspective {} differs between module {} ({}) and module {} ({})",
+                    perspective.red(),
+                    m1.blue(),
+                    render(l1),
+                    m2.blue(),
+                    render(l2)
+                )
+            }
+        }
         Ok(())
     }
 }
@@ -1239,8 +1763,7 @@ fn apply_form(
             {
                 let is = is.concretize(|n| {
                     crate::compiler::generator::reduce(n, &mut ctx.clone(), settings)
-                        .transpose()
-                        .unwrap()
+                        .and_then(|r| r.ok_or_else(|| anyhow!("empty domain bound {}", n.src)))
                         .and_then(|r| r.pure_eval())
                         .and_then(|bi| bi.to_isize().ok_or_else(|| anyhow!("{} is not an i64", bi)))
                 })?;
@@ -1252,10 +1775,10 @@ fn apply_form(
                     for_ctx.insert_symbol(i_name, Expression::Const(Value::from(i)).into())?;
 
                     if let Some(r) = reduce(&body.clone(), &mut for_ctx, settings)? {
-                        t = t.max(r.t());
+                        t = t.joined(&r.t());
                         l.push(r);
                     } else {
-                        warn!("empty for loop body: {}", body.src.white().bold())
+                        super::warning(format!("empty for loop body: {}", body.src.white().bold()))
                     };
                 }
 
@@ -1296,25 +1819,28 @@ fn apply_form(
             for pair in args[0].as_list().unwrap().iter() {
                 let pair = pair.as_list().unwrap();
                 let name = pair[0].as_symbol().unwrap();
-                let value = reduce(&pair[1], &mut sub_ctx, settings)?.unwrap();
+                let value = reduce(&pair[1], &mut sub_ctx, settings)?
+                    .ok_or_else(|| anyhow!("empty value bound to {}", name.red()))?;
                 sub_ctx.insert_symbol(name, value)?;
             }
-            let body = reduce(&args[1], &mut sub_ctx, settings)?.unwrap();
-
-            Ok(Some(body))
+            reduce(&args[1], &mut sub_ctx, settings)
         }
         Form::Reduce => {
             let f_name = args[0].as_symbol().unwrap();
             let f = ctx.resolve_function(f_name)?;
 
-            let mut body = reduce(&args[1], ctx, settings)?.unwrap();
+            let mut body = reduce(&args[1], ctx, settings)?
+                .ok_or_else(|| anyhow!("nothing to reduce in {}", args[1].src.red()))?;
 
-            return match body.e_mut() {
+            match body.e_mut() {
                 Expression::Column { .. }
                 | Expression::Void
                 | Expression::ArrayColumn { .. }
                 | Expression::Funcall { .. }
-                | Expression::Const(_) => panic!(),
+                | Expression::Const(_)
+                | Expression::ExoColumn { .. } => {
+                    bail!("expected a list to reduce, found {}", args[1].src.red())
+                }
                 Expression::List(xs) => {
                     if xs.len() < 2 {
                         Ok(Some(body))
@@ -1326,13 +1852,30 @@ fn apply_form(
                             settings,
                         );
                         while let Some(x) = xs.pop() {
-                            r = apply_function(&f, vec![x, r?.unwrap()], ctx, settings);
+                            let ax = r?.ok_or_else(|| anyhow!("{} returned nothing", f_name))?;
+                            r = apply_function(&f, vec![x, ax], ctx, settings);
                         }
                         r
                     }
                 }
-                Expression::ExoColumn { .. } => todo!(),
+            }
+        }
+        Form::Cast => {
+            let Token::Keyword(ref kw) = args[1].class else {
+                unreachable!()
             };
+            let to = Magma::try_from(kw.as_str())?;
+            let x = reduce(&args[0], ctx, settings)?
+                .filter(|x| x.t() != Type::Void)
+                .ok_or_else(|| anyhow!("nothing to cast in {}", args[0].src.red()))?;
+            // a constant can be checked right away against its new type
+            if let Result::Ok(value) = x.pure_eval() {
+                to.rm()
+                    .validate(Value::big_int(value))
+                    .with_context(|| anyhow!("casting {} to {}", args[0].src.red(), kw.blue()))?;
+            }
+            let t = x.t().with_magma(to);
+            Ok(Some(x.with_type(t)))
         }
     }
 }
@@ -1414,7 +1957,7 @@ fn apply_builtin(
         }
         Builtin::Shift => {
             let shift = traversed_args[1].pure_eval()?.to_i16().unwrap();
-            Ok(Some(traversed_args.get(0).unwrap().clone().shift(shift)))
+            Ok(Some(traversed_args.first().unwrap().clone().shift(shift)))
         }
         Builtin::NormFlat => {
             if traversed_args[0].is_exocolumn() {
@@ -1467,7 +2010,10 @@ fn apply_intrinsic(
                 } else {
                     traversed_args[0].to_string()
                 };
-                warn!("condition {} may overflow", pretty.bright_white().bold());
+                super::warning(format!(
+                    "condition {} may overflow",
+                    pretty.bright_white().bold()
+                ));
             }
             Ok(Some(r))
         }
@@ -1481,7 +2027,20 @@ fn apply_intrinsic(
         | Intrinsic::Exp
         | Intrinsic::Neg
         | Intrinsic::Inv
-        | Intrinsic::Normalize) => Ok(Some(b.call(&traversed_args)?)),
+        | Intrinsic::Normalize
+        | Intrinsic::Sgn) => Ok(Some(b.call(&traversed_args)?)),
+
+        Intrinsic::Bits => {
+            let r = Intrinsic::Bits.call(&traversed_args)?;
+            if traversed_args.len() == 2 {
+                // the width has been validated with the arguments
+                let width = traversed_args[1].pure_eval()?.to_usize().unwrap();
+                let t = r.t().with_raw_magma(super::RawMagma::Integer(width));
+                Ok(Some(r.with_type(t)))
+            } else {
+                Ok(Some(r))
+            }
+        }
     }
 }
 
@@ -1522,9 +2081,34 @@ fn apply(
     }
 }
 
+/// A padding expression may only combine constants and the padding values of
+/// other columns, so that it can be evaluated without any trace.
+fn validate_padding_expr(e: &Node) -> Result<()> {
+    match e.e() {
+        Expression::Const(..) => Ok(()),
+        Expression::Column { shift, .. } => {
+            if *shift != 0 {
+                bail!("padding expressions can not shift columns")
+            }
+            Ok(())
+        }
+        Expression::Funcall { func, args } => {
+            if !matches!(
+                func,
+                Intrinsic::Add | Intrinsic::Sub | Intrinsic::Mul | Intrinsic::Neg | Intrinsic::Exp
+            ) {
+                bail!("{} is not allowed in padding expressions", func.red())
+            }
+            args.iter().try_for_each(validate_padding_expr)
+        }
+        _ => bail!("{} is not allowed in padding expressions", e.red()),
+    }
+}
+
 pub fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<Option<Node>> {
     match &e.class {
         Token::Keyword(_) | Token::Domain(_) => Ok(None),
+        Token::String(_) => bail!("unexpected string {}", e.src.red()),
         Token::Value(x) => Ok(Some(
             // We want the value to specifically be a BigInt here, as we may
             // have negative ones, e.g. as shift arguments.
@@ -1595,19 +2179,25 @@ pub fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Resul
             name,
             t: _,
             kind: k,
+            padding_expr,
             ..
-        } => match k {
-            Kind::Expression(e) => {
+        } => {
+            if let Some(padding_expr) = padding_expr {
+                let n = reduce(padding_expr, ctx, settings)?.unwrap();
+                validate_padding_expr(&n).with_context(|| make_ast_error(padding_expr))?;
+                let handle = Handle::maybe_with_perspective(ctx.module(), name, ctx.perspective());
+                ctx.set_padding_expr(handle, n);
+            }
+            if let Kind::Expression(e) = k {
                 let n = reduce(e, ctx, settings)?.unwrap();
                 ctx.edit_symbol(name, &|x| {
                     if let Expression::Column { kind, .. } = x {
                         *kind = Kind::Expression(Box::new(n.clone()))
                     }
                 })?;
-                Ok(None)
             }
-            _ => Ok(None),
-        },
+            Ok(None)
+        }
         Token::DefInterleaving { target, froms } => {
             let target_handle =
                 if let Expression::Column { handle, .. } = ctx.resolve_symbol(&target.name)?.e() {
@@ -1662,7 +2252,7 @@ pub fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Resul
         | Token::DefPerspective { .. }
         | Token::DefConstraint { .. }
         | Token::DefArrayColumn { .. }
-        | Token::DefModule(_)
+        | Token::DefModule(..)
         | Token::DefAliases(_)
         | Token::DefAlias(..)
         | Token::DefunAlias(..)
@@ -1677,6 +2267,27 @@ pub fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Resul
     .with_context(|| make_ast_error(e))
 }
 
+/// Reduce an expression used in `context`, e.g. a lookup or a range
+/// constraint, where it must be non-empty and yield a single value per row.
+fn reduce_scalar(
+    e: &AstNode,
+    ctx: &mut Scope,
+    settings: &CompileSettings,
+    context: &str,
+) -> Result<Node> {
+    let n = reduce(e, ctx, settings)?
+        .ok_or_else(|| anyhow!("empty expression {} in {}", e.src.red(), context.blue()))?;
+    match n.t() {
+        Type::Void | Type::List(_) => bail!(
+            "in {}, expected a single value, found {} of type {}",
+            context.blue(),
+            e.src.red(),
+            n.t().red().bold()
+        ),
+        _ => Ok(n),
+    }
+}
+
 pub(crate) fn reduce_toplevel(
     e: &AstNode,
     ctx: &mut Scope,
@@ -1689,6 +2300,7 @@ pub(crate) fn reduce_toplevel(
             guard,
             perspective,
             body,
+            ..
         } => {
             let handle = Handle::new(ctx.module(), name);
             let module = ctx.module();
@@ -1727,12 +2339,12 @@ pub(crate) fn reduce_toplevel(
             };
 
             if body.t() == Type::Void {
-                warn!(
+                super::warning(format!(
                     "constraint {} should be of type {}, found {}",
                     handle.pretty(),
                     "Loobean".yellow().bold(),
                     body.t().red().bold()
-                );
+                ));
                 Ok(None)
             } else {
                 if !body.t().m().is_loobean() {
@@ -1773,11 +2385,11 @@ pub(crate) fn reduce_toplevel(
             let handle = Handle::new(ctx.module(), name);
             let parents = parent
                 .iter()
-                .map(|e| reduce(e, ctx, settings).map(Option::unwrap))
+                .map(|e| reduce_scalar(e, ctx, settings, &format!("lookup {}", name)))
                 .collect::<Result<Vec<_>>>()?;
             let children = child
                 .iter()
-                .map(|e| reduce(e, ctx, settings).map(Option::unwrap))
+                .map(|e| reduce_scalar(e, ctx, settings, &format!("lookup {}", name)))
                 .collect::<Result<Vec<_>>>()?;
             if parents.len() != children.len() {
                 bail!(
@@ -1798,7 +2410,7 @@ pub(crate) fn reduce_toplevel(
             let handle = Handle::new(ctx.module(), format!("{}_lt_{}", e, range));
             Ok(Some(Constraint::InRange {
                 handle,
-                exp: reduce(e, ctx, settings)?.unwrap(),
+                exp: reduce_scalar(e, ctx, settings, "range constraint")?,
                 max: Value::from(*range),
             }))
         }
@@ -1812,6 +2424,7 @@ pub(crate) fn reduce_toplevel(
             name,
             trigger,
             columns,
+            ..
         } => {
             // Create the new perspective in the current module and ensures it does not exist
             let module = ctx.module();
@@ -1833,7 +2446,7 @@ pub(crate) fn reduce_toplevel(
             }
             Ok(None)
         }
-        Token::DefModule(name) => {
+        Token::DefModule(name, _) => {
             *ctx = ctx.switch_to_module(name)?;
             Ok(None)
         }
@@ -1845,17 +2458,28 @@ pub(crate) fn reduce_toplevel(
         | Token::DefAliases(_)
         | Token::DefunAlias(..)
         | Token::DefConsts(..) => Ok(None),
-        Token::DefPermutation { from, to, signs } => {
+        Token::DefPermutation {
+            from,
+            to,
+            signs,
+            stable,
+            tiebreaks,
+        } => {
+            let mut as_column = |from: &AstNode| {
+                if let Some(n) = reduce(from, ctx, settings)? {
+                    if let Expression::Column { handle, .. } = n.e() {
+                        return Ok(handle.clone());
+                    }
+                }
+                bail!("`{}` is not a column", from.white().bold())
+            };
             let froms = from
                 .iter()
-                .map(|from| {
-                    if let Some(n) = reduce(from, ctx, settings)? {
-                        if let Expression::Column { handle, .. } = n.e() {
-                            return Ok(handle.clone());
-                        }
-                    }
-                    bail!("`{}` is not a column", from.white().bold())
-                })
+                .map(&mut as_column)
+                .collect::<Result<Vec<_>>>()?;
+            let tiebreaks = tiebreaks
+                .iter()
+                .map(&mut as_column)
                 .collect::<Result<Vec<_>>>()?;
 
             // TODO is this needed?
@@ -1887,6 +2511,8 @@ pub(crate) fn reduce_toplevel(
                     froms: froms.clone(),
                     tos: tos.clone(),
                     signs: signs.clone(),
+                    stable: *stable,
+                    tiebreaks,
                 },
             )?;
 
@@ -4,10 +4,10 @@ use crate::{
 };
 use anyhow::*;
 use log::*;
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap};
 
 pub use common::*;
-pub use generator::{Constraint, ConstraintSet, EvalSettings};
+pub use generator::{Constraint, ConstraintSet, EvalSettings, ShiftWindow};
 pub use node::{ColumnRef, Expression, Node};
 use num_bigint::BigInt;
 use owo_colors::OwoColorize;
@@ -17,25 +17,77 @@ pub use types::*;
 use self::parser::Ast;
 use crate::{column::Column, compiler::tables::Symbol, errors::CompileError};
 
+pub mod bounds;
+pub mod builder;
 pub mod codetyper;
 mod common;
+pub mod complexity;
 pub mod generator;
 mod node;
 pub mod parser;
+pub mod pil;
 pub mod tables;
 mod types;
 
 pub(crate) const MAIN_MODULE: &str = "<prelude>";
 
+#[derive(Default)]
 pub struct CompileSettings {
     pub debug: bool,
+    /// the values overriding those of constants, indexed by the name of the
+    /// constant, prefixed by its module unless it belongs to the root module
+    pub assumptions: HashMap<String, BigInt>,
+    /// if set, the width difference, in bits, beyond which mixing operands
+    /// of different magmas in an arithmetic operation must be made explicit
+    /// with a `cast`
+    pub promotion_gap: Option<usize>,
+}
+
+thread_local! {
+    /// the warnings emitted on this thread while they are being recorded, see
+    /// [`record_warnings`]
+    static WARNINGS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Emit the compilation warning `w`, recording it if required
+pub(crate) fn warning(w: String) {
+    WARNINGS.with(|ws| {
+        if let Some(ws) = ws.borrow_mut().as_mut() {
+            ws.push(w.clone());
+        }
+    });
+    warn!("{}", w);
+}
+
+/// Run `f`, and return the compilation warnings it emitted alongside its
+/// result, so that they can be replayed later, e.g. when the compilation is
+/// cached; the warnings are still emitted as usual.
+pub(crate) fn record_warnings<R>(f: impl FnOnce() -> R) -> (R, Vec<String>) {
+    let outer = WARNINGS.with(|ws| ws.replace(Some(Vec::new())));
+    let r = f();
+    let recorded = WARNINGS.with(|ws| ws.replace(outer)).unwrap_or_default();
+    // nested recordings are seen by the enclosing ones
+    WARNINGS.with(|ws| {
+        if let Some(ws) = ws.borrow_mut().as_mut() {
+            ws.extend(recorded.iter().cloned());
+        }
+    });
+    (r, recorded)
 }
 
 pub fn make<S1: AsRef<str>, S2: AsRef<str>>(
     sources: &[(S1, S2)],
     settings: &CompileSettings,
 ) -> Result<(Vec<Ast>, ConstraintSet)> {
-    let (mut ctx, asts) = parser::parse(sources, settings)?;
+    make_from_asts(parser::parse_ast(sources)?, settings)
+}
+
+/// Compile the already parsed `asts`
+pub fn make_from_asts(
+    asts: Vec<(String, Ast)>,
+    settings: &CompileSettings,
+) -> Result<(Vec<Ast>, ConstraintSet)> {
+    let (mut ctx, asts) = parser::analyze(asts, settings)?;
 
     //
     // Reduce the AST and create the constraints
@@ -50,6 +102,10 @@ pub fn make<S1: AsRef<str>, S2: AsRef<str>>(
     }
     // Sort by decreasing complexity for more efficient multi-threaded computation
     constraints.sort_by_cached_key(|x| -(x.size() as isize));
+    let assertion_handles = ctx.assertions();
+    let (assertions, mut constraints): (Vec<_>, Vec<_>) = constraints
+        .into_iter()
+        .partition(|c| assertion_handles.contains(c.handle()));
 
     let mut columns: ColumnSet = Default::default();
     let mut constants: HashMap<Handle, BigInt> = Default::default();
@@ -60,7 +116,7 @@ pub fn make<S1: AsRef<str>, S2: AsRef<str>>(
             Symbol::Alias(_) => {}
             Symbol::Final(symbol, used) => {
                 if !*used {
-                    warn!("{}", CompileError::NotUsed(handle.clone()));
+                    warning(CompileError::NotUsed(handle.clone()).to_string());
                 }
 
                 match symbol.e() {
@@ -154,7 +210,42 @@ pub fn make<S1: AsRef<str>, S2: AsRef<str>>(
         })
         .collect::<HashMap<_, _>>();
 
-    let mut cs = ConstraintSet::new(columns, constraints, constants, computations, perspectives)?;
+    let shared_perspectives = ctx.tree.borrow().metadata().shared_perspectives();
+
+    for (handle, group) in ctx.groups() {
+        if let Some(column) = columns.get_col_mut(&handle.into()) {
+            column.group = Some(group);
+        }
+    }
+    columns.fixed_len = ctx.module_lengths();
+    for (handle, doc) in ctx.column_docs() {
+        if let Some(column) = columns.get_col_mut(&handle.into()) {
+            column.doc = Some(doc);
+        }
+    }
+    for (handle, span) in ctx.column_spans() {
+        if let Some(column) = columns.get_col_mut(&handle.into()) {
+            column.span = Some(span);
+        }
+    }
+    for (handle, expr) in ctx.padding_exprs() {
+        if let Some(column) = columns.get_col_mut(&handle.into()) {
+            column.padding_expr = Some(expr);
+        }
+    }
+    columns.resolve_paddings()?;
+
+    let mut cs = ConstraintSet::new(
+        columns,
+        constraints,
+        assertions,
+        constants,
+        computations,
+        perspectives,
+        shared_perspectives,
+    )?;
+    cs.docs = ctx.constraint_docs();
+    cs.spans = ctx.constraint_spans();
     crate::transformer::precompute(&mut cs);
     Ok((asts.into_iter().map(|x| x.1).collect(), cs))
 }
@@ -375,7 +375,7 @@ impl Node {
     }
     pub fn perspective(&self) -> Option<String> {
         match self.e() {
-            Expression::Funcall { args, .. } | Expression::List(args) => todo!(),
+            Expression::Funcall { args: _, .. } | Expression::List(_) => todo!(),
             Expression::Const(_) => None,
             Expression::Column { handle, .. }
             | Expression::ExoColumn { handle, .. }
@@ -610,6 +610,18 @@ impl Node {
                     })
                 }
                 Intrinsic::Neg => Ok(-args[0].pure_eval()?),
+                Intrinsic::Sgn => Ok(num_traits::Signed::signum(&args[0].pure_eval()?)),
+                Intrinsic::Bits => {
+                    let x = args[0].pure_eval()?;
+                    match args.get(2) {
+                        Some(k) => Ok(if x.bit(k.pure_eval()?.to_u64().unwrap_or(0)) {
+                            BigInt::one()
+                        } else {
+                            BigInt::zero()
+                        }),
+                        None => Ok(x),
+                    }
+                }
                 Intrinsic::Exp => {
                     let args = args
                         .iter()
@@ -719,6 +731,16 @@ impl Node {
                 Intrinsic::Normalize => args[0]
                     .eval_fold(i, get, cache, settings, f)
                     .map(|x| x.normalize()),
+                Intrinsic::Sgn => args[0]
+                    .eval_fold(i, get, cache, settings, f)
+                    .and_then(|x| x.signum().ok()),
+                Intrinsic::Bits => {
+                    let x = args[0].eval_fold(i, get, cache, settings, f)?;
+                    match args.get(2) {
+                        Some(k) => Some(x.bit(k.pure_eval().ok()?.to_u64()?)),
+                        None => Some(x),
+                    }
+                }
                 Intrinsic::Begin => unreachable!(),
                 Intrinsic::IfZero => {
                     if args[0].eval_fold(i, get, cache, settings, f)?.is_zero() {
@@ -771,6 +793,7 @@ impl Node {
                 tty.write(" ");
             }
         }
+        #[allow(clippy::too_many_arguments)]
         fn _debug(
             n: &Node,
             tty: &mut Tty,
@@ -833,7 +856,7 @@ impl Node {
                             |s| s.color(c).to_string(),
                             Some(fname.len() + 2),
                             |tty| {
-                                if let Some(a) = args.get(0) {
+                                if let Some(a) = args.first() {
                                     _debug(
                                         a,
                                         tty,
@@ -870,7 +893,7 @@ impl Node {
                         );
                     } else {
                         tty.write(format!("({fname} ",).color(c).to_string());
-                        if let Some(a) = args.get(0) {
+                        if let Some(a) = args.first() {
                             _debug(
                                 a,
                                 tty,
@@ -1,32 +1,63 @@
 use anyhow::*;
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
 
-use crate::compiler::{generator::make_ast_error, tables::Scope, CompileSettings, Node};
+use crate::{
+    compiler::{generator::make_ast_error, tables::Scope, CompileSettings, Node, MAIN_MODULE},
+    errors,
+};
 
-use super::{Ast, AstNode, Token};
+use super::{stdlib::collect_symbols, Ast, AstNode, Token};
 
-fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()> {
+/// A constant waiting to be evaluated
+struct Definition<'a> {
+    /// the file where the constant is defined
+    file: &'a str,
+    /// the module where the constant is defined
+    ctx: Scope,
+    name: &'a str,
+    exp: &'a AstNode,
+}
+
+/// The full path of the constant `name` defined in `module`
+fn path(module: &str, name: &str) -> String {
+    if module == MAIN_MODULE {
+        name.to_owned()
+    } else {
+        format!("{}.{}", module, name)
+    }
+}
+
+/// Collect the constants defined in `e`, directly inserting those whose
+/// value is overridden by an assumption.
+fn collect<'a>(
+    file: &'a str,
+    e: &'a AstNode,
+    ctx: &mut Scope,
+    settings: &CompileSettings,
+    assumed: &mut HashSet<String>,
+    definitions: &mut Vec<Definition<'a>>,
+) -> Result<()> {
     match &e.class {
-        Token::DefModule(name) => {
+        Token::DefModule(name, _) => {
             *ctx = ctx.switch_to_module(name)?.public(true);
             Ok(())
         }
 
         Token::DefConsts(cs) => {
             for (name, exp) in cs.iter() {
-                let value = match &exp.class {
-                    // If the constant value is iota, assign it to a deterministic pseudo-random value
-                    Token::Symbol(x) if ["iota", "ι", "ɩ"].contains(&x.as_str()) => {
-                        let mut hasher = std::collections::hash_map::DefaultHasher::new();
-                        std::hash::Hash::hash(&name, &mut hasher);
-                        Node::from_isize((std::hash::Hasher::finish(&hasher) >> 1) as isize)
-                    }
-                    _ => crate::compiler::generator::reduce(exp, ctx, settings)?.unwrap(),
-                };
-                ctx.insert_constant(
+                let path = path(&ctx.module(), name);
+                if let Some(value) = settings.assumptions.get(&path) {
+                    ctx.insert_constant(name, value.clone(), true)?;
+                    assumed.insert(path);
+                    continue;
+                }
+                definitions.push(Definition {
+                    file,
+                    ctx: ctx.clone(),
                     name,
-                    value.pure_eval().with_context(|| make_ast_error(exp))?,
-                    true,
-                )?;
+                    exp,
+                });
             }
             Ok(())
         }
@@ -34,14 +65,144 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
     }
 }
 
-/// The `Definitions` pass skim through an [`Ast`] and fill the
-/// [`SymbolTableTree`] with all the required elements (columns, functions,
-/// perspectives, constraints, aliases, ...)
-pub fn pass(ast: &Ast, ctx: Scope, settings: &CompileSettings) -> Result<()> {
-    let mut module = ctx;
-    for e in ast.exprs.iter() {
-        reduce(e, &mut module, settings)?;
+/// The constants, among `pending`, referenced by the expression defining the
+/// constant `module.name`
+fn dependencies(
+    module: &str,
+    name: &str,
+    exp: &AstNode,
+    pending: &HashMap<String, usize>,
+) -> Vec<String> {
+    let mut symbols = HashSet::new();
+    collect_symbols(exp, &mut symbols);
+    symbols
+        .into_iter()
+        .sorted()
+        .filter_map(|s| {
+            if s.contains('.') {
+                Some(s)
+            } else {
+                // a constant of the module shadows those of the main module
+                [path(module, &s), s]
+                    .into_iter()
+                    .find(|p| pending.contains_key(p))
+            }
+        })
+        .filter(|p| pending.contains_key(p) && *p != path(module, name))
+        .collect()
+}
+
+/// Evaluate the `i`th definition, after those it depends on; `stack` holds
+/// the path of the constants being evaluated, to detect cycles.
+fn evaluate(
+    i: usize,
+    definitions: &[Definition],
+    pending: &HashMap<String, usize>,
+    done: &mut [bool],
+    stack: &mut Vec<String>,
+    settings: &CompileSettings,
+) -> Result<()> {
+    if done[i] {
+        return Ok(());
+    }
+    let Definition {
+        file,
+        ctx,
+        name,
+        exp,
+    } = &definitions[i];
+    let module = ctx.module();
+    let this = path(&module, name);
+    if let Some(start) = stack.iter().position(|p| *p == this) {
+        return Err(anyhow!(errors::symbols::Error::CircularDefinition(
+            stack[start..]
+                .iter()
+                .chain(std::iter::once(&this))
+                .join(" → ")
+        )))
+        .with_context(|| make_ast_error(exp))
+        .with_context(|| anyhow!("parsing definitions in `{}`", file));
     }
 
+    stack.push(this);
+    for dependency in dependencies(&module, name, exp, pending) {
+        evaluate(
+            pending[&dependency],
+            definitions,
+            pending,
+            done,
+            stack,
+            settings,
+        )?;
+    }
+    stack.pop();
+
+    let mut ctx = ctx.clone();
+    let value = match &exp.class {
+        // If the constant value is iota, assign it to a deterministic pseudo-random value
+        Token::Symbol(x) if ["iota", "ι", "ɩ"].contains(&x.as_str()) => {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            std::hash::Hash::hash(name, &mut hasher);
+            Node::from_isize((std::hash::Hasher::finish(&hasher) >> 1) as isize)
+        }
+        _ => crate::compiler::generator::reduce(exp, &mut ctx, settings)
+            .with_context(|| anyhow!("parsing definitions in `{}`", file))?
+            .unwrap(),
+    };
+    value
+        .pure_eval()
+        .and_then(|value| ctx.insert_constant(name, value, true))
+        .with_context(|| make_ast_error(exp))
+        .with_context(|| anyhow!("parsing definitions in `{}`", file))?;
+    done[i] = true;
     Ok(())
 }
+
+/// The `Constants` pass skim through the [`Ast`]s and fill the
+/// [`SymbolTableTree`] with all the constants they define, returning those
+/// whose value has been overridden by an assumption.
+///
+/// A constant may be defined by an expression referring to other constants,
+/// wherever they are defined; they are then evaluated first.
+pub fn pass(
+    asts: &[(String, Ast)],
+    ctx: Scope,
+    settings: &CompileSettings,
+) -> Result<HashSet<String>> {
+    let mut assumed = HashSet::new();
+    let mut definitions = Vec::new();
+    for (file, ast) in asts.iter() {
+        let mut module = ctx.clone();
+        for e in ast.exprs.iter() {
+            collect(
+                file,
+                e,
+                &mut module,
+                settings,
+                &mut assumed,
+                &mut definitions,
+            )
+            .with_context(|| anyhow!("parsing definitions in `{}`", file))?;
+        }
+    }
+
+    // should a constant be defined several times, the last definition wins
+    let pending = definitions
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (path(&d.ctx.module(), d.name), i))
+        .collect::<HashMap<_, _>>();
+    let mut done = vec![false; definitions.len()];
+    for i in 0..definitions.len() {
+        evaluate(
+            i,
+            &definitions,
+            &pending,
+            &mut done,
+            &mut Vec::new(),
+            settings,
+        )?;
+    }
+
+    Ok(assumed)
+}
@@ -1,6 +1,5 @@
 use anyhow::Context;
 use anyhow::*;
-use crossterm::style::Stylize;
 use num_traits::ToPrimitive;
 use owo_colors::OwoColorize;
 
@@ -17,27 +16,62 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
         Token::Value(_)
         | Token::Symbol(_)
         | Token::Keyword(_)
+        | Token::String(_)
         | Token::List(_)
         | Token::Domain(_)
-        | Token::DefLookup { .. }
         | Token::Defpurefun { .. }
         | Token::DefConsts { .. }
         | Token::DefInrange(..) => Ok(()),
 
         Token::IndexedSymbol { name: _, index } => reduce(index, ctx, settings),
-        Token::DefConstraint { name, .. } => ctx.insert_constraint(name),
-        Token::DefModule(name) => {
+        Token::DefLookup { name, .. } => {
+            ctx.set_constraint_span(Handle::new(ctx.module(), name), e.lc);
+            Ok(())
+        }
+        Token::DefConstraint {
+            name,
+            doc,
+            assertion,
+            ..
+        } => {
+            if let Some(doc) = doc {
+                ctx.set_constraint_doc(Handle::new(ctx.module(), name), doc);
+            }
+            ctx.set_constraint_span(Handle::new(ctx.module(), name), e.lc);
+            if *assertion {
+                ctx.set_assertion(Handle::new(ctx.module(), name));
+            }
+            ctx.insert_constraint(name)
+        }
+        Token::DefModule(name, length) => {
             *ctx = ctx.switch_to_module(name)?.public(true);
+            if let Some(length) = length {
+                let length =
+                    crate::compiler::generator::reduce(length, &mut ctx.clone(), settings)?
+                        .ok_or_else(|| anyhow!("empty length for module {}", name))?
+                        .pure_eval()?
+                        .to_usize()
+                        .filter(|l| *l > 0)
+                        .with_context(|| {
+                            anyhow!("invalid length {} for module {}", length.src, name)
+                        })?;
+                ctx.set_module_length(name, length)?;
+            }
             Ok(())
         }
         Token::DefColumns(columns) => columns
             .iter()
             .fold(Ok(()), |ax, col| ax.and(reduce(col, ctx, settings))),
-        Token::DefPerspective { name, columns, .. } => {
+        Token::DefPerspective {
+            name,
+            shared,
+            columns,
+            ..
+        } => {
             let mut new_ctx = ctx
                 .derive(&format!("in-{}", name))?
                 .public(true)
-                .with_perspective(name)?;
+                .with_perspective(name, *shared)?;
             columns.iter().fold(Ok(()), |ax, col| {
                 ax.and(reduce(col, &mut new_ctx, settings))
             })
@@ -49,14 +83,21 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
             padding_value,
             must_prove,
             base,
+            group,
+            doc,
+            ..
         } => {
             let module_name = ctx.module();
+            let handle = Handle::maybe_with_perspective(module_name, name, ctx.perspective());
+            if let Some(group) = group {
+                ctx.set_group(handle.clone(), group);
+            }
+            if let Some(doc) = doc {
+                ctx.set_column_doc(handle.clone(), doc);
+            }
+            ctx.set_column_span(handle.clone(), e.lc);
             let symbol = Node::column()
-                .handle(Handle::maybe_with_perspective(
-                    module_name,
-                    name,
-                    ctx.perspective(),
-                ))
+                .handle(handle)
                 .kind(match kind {
                     Kind::Commitment => Kind::Commitment,
                     Kind::Computed => Kind::Computed, // unreachable?
@@ -76,6 +117,8 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
             padding_value,
             must_prove,
             base,
+            group,
+            doc,
         } => {
             let handle = Handle::maybe_with_perspective(ctx.module(), name, ctx.perspective());
             // those are inserted for symbol lookups
@@ -97,6 +140,13 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
 
             for i in domain.iter() {
                 let ith_handle = handle.ith(i.try_into().unwrap());
+                if let Some(group) = group {
+                    ctx.set_group(ith_handle.clone(), group);
+                }
+                if let Some(doc) = doc {
+                    ctx.set_column_doc(ith_handle.clone(), doc);
+                }
+                ctx.set_column_span(ith_handle.clone(), e.lc);
                 ctx.insert_used_symbol(
                     &ith_handle.name,
                     Node::column()
@@ -123,20 +173,22 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
             Ok(())
         }
         Token::DefInterleaving { target, froms } => {
+            let handle = Handle::maybe_with_perspective(
+                // TODO unsure about this
+                ctx.module(),
+                target.name.clone(),
+                ctx.perspective(),
+            );
+            ctx.set_column_span(handle.clone(), e.lc);
             let node = Node::column()
-                .handle(Handle::maybe_with_perspective(
-                    // TODO unsure about this
-                    ctx.module(),
-                    target.name.clone(),
-                    ctx.perspective(),
-                ))
+                .handle(handle)
                 .kind(Kind::Computed)
                 .base(target.base)
                 .t(froms.iter().try_fold(Magma::BINARY, |ax, f| {
                     generator::reduce(f, ctx, settings)
                         .transpose()
                         .unwrap()
-                        .map(|s| s.t().m().max(ax))
+                        .map(|s| s.t().m().joined(&ax))
                 })?)
                 .build();
 
@@ -156,31 +208,41 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
             }
             let froms = froms
                 .iter()
-                .map(|f| generator::reduce(f, ctx, settings).transpose().unwrap())
+                .map(|f| {
+                    generator::reduce(f, ctx, settings)?
+                        .filter(|n| {
+                            matches!(
+                                n.e(),
+                                crate::compiler::Expression::Column { .. }
+                                    | crate::compiler::Expression::ArrayColumn { .. }
+                                    | crate::compiler::Expression::ExoColumn { .. }
+                            )
+                        })
+                        .ok_or_else(|| anyhow!("expected a column, found {}", f.src.as_str().red()))
+                })
                 .collect::<Result<Vec<_>>>()?;
             let suffix = hash_strings(froms.iter().map(|f| match f.e() {
-                crate::compiler::Expression::Const(_) => todo!(),
-                crate::compiler::Expression::Funcall { .. } => todo!(),
                 crate::compiler::Expression::Column { handle, .. }
                 | crate::compiler::Expression::ArrayColumn { handle, .. }
                 | crate::compiler::Expression::ExoColumn { handle, .. } => {
                     handle.as_handle().name.to_owned()
                 }
-                crate::compiler::Expression::List(_) => todo!(),
-                crate::compiler::Expression::Void => todo!(),
+                _ => unreachable!(),
             }));
             for pair in tos.iter().zip(froms.iter()) {
                 let to = pair.0;
                 let from = pair.1;
                 let from_m = from.t().m();
+                let handle = Handle::maybe_with_perspective(
+                    ctx.module(),
+                    to.name.clone(),
+                    from.perspective().map(|p| format!("{p}-srt-{suffix}")),
+                );
+                ctx.set_column_span(handle.clone(), e.lc);
                 ctx.insert_symbol(
                     &to.name,
                     Node::column()
-                        .handle(Handle::maybe_with_perspective(
-                            ctx.module(),
-                            to.name.clone(),
-                            from.perspective().map(|p| format!("{p}-srt-{suffix}")),
-                        ))
+                        .handle(handle)
                         .kind(Kind::Computed)
                         .t(from_m)
                         .base(to.base)
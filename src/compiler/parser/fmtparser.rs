@@ -131,10 +131,17 @@ fn rec_parse(source: &str, pair: Pair<Rule>) -> Result<AstNode> {
             })
         }
         Rule::symbol => Ok(AstNode {
+            // quoted symbols are kept as written, so that they are formatted
+            // back as such
             class: Token::Symbol(pair.as_str().to_owned()),
             lc,
             src,
         }),
+        Rule::string => Ok(AstNode {
+            class: Token::String(pair.as_str().trim_matches('"').to_owned()),
+            lc,
+            src,
+        }),
         Rule::integer => {
             let s = pair.as_str();
             let sign = if s.starts_with('-') {
@@ -223,6 +230,7 @@ fn rec_parse(source: &str, pair: Pair<Rule>) -> Result<AstNode> {
 
 pub fn parse(source: &str) -> Result<Ast> {
     let mut ast = Ast { exprs: vec![] };
+    let source: &str = &super::normalize_newlines(source);
 
     for pair in FmtParser::parse(Rule::corset, source)? {
         if pair.as_rule() == Rule::corset {
@@ -0,0 +1,137 @@
+use anyhow::*;
+use itertools::Itertools;
+use log::*;
+use owo_colors::OwoColorize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use super::{fmtparser, Token};
+
+/// Extract the paths listed in the `(include "...")` directives of `source`
+fn included_paths(source: &str) -> Result<Vec<String>> {
+    let ast = match fmtparser::parse(source) {
+        Result::Ok(ast) => ast,
+        // syntax errors are all reported at once by the actual parser
+        Err(_) if super::parser::parse(source).is_err() => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut r = Vec::new();
+    for n in ast.exprs.iter() {
+        let Token::List(xs) = &n.class else {
+            continue;
+        };
+        if xs.first().and_then(|x| x.as_symbol().ok()) != Some("include") {
+            continue;
+        }
+        for x in xs.iter().skip(1) {
+            match &x.class {
+                Token::String(path) => r.push(path.to_owned()),
+                _ if x.is_comment() => {}
+                _ => bail!("expected a path to include, found {}", x.src.red()),
+            }
+        }
+    }
+    Ok(r)
+}
+
+#[derive(Default)]
+struct Resolver {
+    /// the files already included, that must not be included twice
+    seen: HashSet<PathBuf>,
+    /// the chain of files currently being included, to detect cycles
+    stack: Vec<PathBuf>,
+    /// the resolved sources, dependencies first
+    sources: Vec<(String, String)>,
+}
+impl Resolver {
+    fn ensure_acyclic(&self, path: &Path) -> Result<()> {
+        if let Some(start) = self.stack.iter().position(|p| p == path) {
+            bail!(
+                "circular inclusion: {}",
+                self.stack[start..]
+                    .iter()
+                    .chain(std::iter::once(&path.to_path_buf()))
+                    .map(|p| p.display().to_string().yellow().bold().to_string())
+                    .join(" → ")
+            )
+        }
+        Ok(())
+    }
+
+    /// Insert the source `content` named `name` after all the files it
+    /// includes; `path` is its canonical location if it stems from a file.
+    fn visit(&mut self, name: &str, content: &str, path: Option<PathBuf>) -> Result<()> {
+        if let Some(path) = path.as_ref() {
+            self.ensure_acyclic(path)?;
+            if !self.seen.insert(path.to_owned()) {
+                debug!("{} already included", name);
+                return Ok(());
+            }
+            self.stack.push(path.to_owned());
+        }
+
+        let root = path
+            .as_ref()
+            .and_then(|p| p.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        for included in included_paths(content).with_context(|| anyhow!("parsing {}", name))? {
+            let included_name = root.join(crate::utils::portable_path(&included));
+            let included_path = included_name.canonicalize().with_context(|| {
+                anyhow!(
+                    "{} includes {}, which can not be found",
+                    name.bright_white().bold(),
+                    included_name.display().to_string().red().bold()
+                )
+            })?;
+            self.ensure_acyclic(&included_path)?;
+            if self.seen.contains(&included_path) {
+                continue;
+            }
+
+            let included_content = std::fs::read_to_string(&included_path).with_context(|| {
+                anyhow!(
+                    "reading {}",
+                    included_name.display().to_string().yellow().bold()
+                )
+            })?;
+            info!(
+                "{} includes {}",
+                name.bright_white().bold(),
+                included_name.display().to_string().bright_white().bold()
+            );
+            self.visit(
+                &included_name.display().to_string(),
+                &included_content,
+                Some(included_path),
+            )?;
+        }
+
+        if path.is_some() {
+            self.stack.pop();
+        }
+        self.sources.push((name.to_owned(), content.to_owned()));
+        Ok(())
+    }
+}
+
+/// Recursively expand the `(include "...")` directives found in `sources`.
+///
+/// Included paths are resolved relatively to the including file, or to the
+/// current directory for sources that do not stem from a file. Every included
+/// file is inserted before the first file including it, and a file
+/// included several times, either directly or transitively, is only kept
+/// once.
+pub(crate) fn resolve_includes(sources: &[(String, String)]) -> Result<Vec<(String, String)>> {
+    let mut resolver = Resolver::default();
+    for (name, content) in sources {
+        let path = Path::new(name);
+        let path = if path.is_file() {
+            Some(path.canonicalize()?)
+        } else {
+            None
+        };
+        resolver.visit(name, content, path)?;
+    }
+    Ok(resolver.sources)
+}
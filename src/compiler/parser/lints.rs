@@ -0,0 +1,342 @@
+use anyhow::*;
+use num_traits::Zero;
+use owo_colors::OwoColorize;
+use std::collections::HashSet;
+
+use crate::compiler::{
+    generator::{reduce, uniquify},
+    tables::Scope,
+    CompileSettings, Conditioning, Expression, Intrinsic, Magma, Node, RawMagma,
+};
+use crate::pretty::Pretty;
+
+use super::{Ast, AstNode, LinCol, Token};
+
+/// A suspicious, albeit valid, construct found in the body of a user-defined
+/// function or of a constraint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Lint {
+    /// `argument` is never referred to in the body of `function`
+    UnusedArgument {
+        function: String,
+        argument: String,
+        lc: LinCol,
+    },
+    /// the condition of the `conditional` form in `function` is statically
+    /// known, and its `branch` branch can never be taken
+    DeadBranch {
+        function: String,
+        conditional: String,
+        branch: &'static str,
+        lc: LinCol,
+    },
+    /// in `constraint`, an arithmetic operation implicitly promotes `narrow`
+    /// to the magma of `wide`, across more bits than the promotion gap
+    ImplicitPromotion {
+        constraint: String,
+        narrow: (String, Magma),
+        wide: (String, Magma),
+        lc: LinCol,
+    },
+}
+impl Lint {
+    pub(crate) fn lc(&self) -> LinCol {
+        match self {
+            Lint::UnusedArgument { lc, .. }
+            | Lint::DeadBranch { lc, .. }
+            | Lint::ImplicitPromotion { lc, .. } => *lc,
+        }
+    }
+}
+impl std::fmt::Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lc = self.lc();
+        write!(
+            f,
+            "{}:{}: ",
+            lc.0.to_string().blue(),
+            lc.1.to_string().blue()
+        )?;
+        match self {
+            Lint::UnusedArgument {
+                function, argument, ..
+            } => write!(
+                f,
+                "argument {} of {} is never used",
+                argument.yellow().bold(),
+                function.white().bold()
+            ),
+            Lint::DeadBranch {
+                function,
+                conditional,
+                branch,
+                ..
+            } => write!(
+                f,
+                "the condition of this {} in {} is constant; its {} branch is dead",
+                conditional.white().bold(),
+                function.white().bold(),
+                branch.yellow().bold()
+            ),
+            Lint::ImplicitPromotion {
+                constraint,
+                narrow,
+                wide,
+                ..
+            } => write!(
+                f,
+                "in {}, {} ({}) is implicitly promoted to {} by {} ({}); use {} if this is intended",
+                constraint.white().bold(),
+                narrow.0.yellow().bold(),
+                narrow.1,
+                wide.1,
+                wide.0.yellow().bold(),
+                wide.1,
+                "(cast ...)".bold()
+            ),
+        }
+    }
+}
+
+/// Collect all the symbols referred to in `e`
+fn referenced_symbols<'a>(e: &'a AstNode, ax: &mut HashSet<&'a str>) {
+    match &e.class {
+        Token::Symbol(name) => {
+            ax.insert(name);
+        }
+        Token::IndexedSymbol { name, index } => {
+            ax.insert(name);
+            referenced_symbols(index, ax);
+        }
+        Token::List(xs) => xs.iter().for_each(|x| referenced_symbols(x, ax)),
+        Token::Domain(d) => d.iter_nodes().for_each(|x| referenced_symbols(x, ax)),
+        _ => {}
+    }
+}
+
+/// If `e` does not depend on the function arguments `args`, try to evaluate
+/// it in a pure scope, so that no column can be involved
+fn static_value(
+    e: &AstNode,
+    args: &[String],
+    ctx: &mut Scope,
+    settings: &CompileSettings,
+) -> Option<(num_bigint::BigInt, Conditioning)> {
+    let mut symbols = HashSet::new();
+    referenced_symbols(e, &mut symbols);
+    if args.iter().any(|a| symbols.contains(a.as_str())) {
+        return None;
+    }
+
+    let mut pure_ctx = ctx.derive(&uniquify("lint".to_string())).ok()?.closed(true);
+    let node = reduce(e, &mut pure_ctx, settings).ok()??;
+    let value = node.pure_eval().ok()?;
+    Some((value, node.t().c()))
+}
+
+/// Check whether `e` is a conditional with a statically known condition, and
+/// if so, which of its branches will never be taken
+fn dead_branch(
+    e: &AstNode,
+    args: &[String],
+    ctx: &mut Scope,
+    settings: &CompileSettings,
+) -> Option<(String, &'static str)> {
+    let xs = e.as_list().ok()?;
+    let verb = xs.first()?.as_symbol().ok()?;
+    // The index of the then branch, and whether this branch is taken
+    let (then_idx, takes_then) = match verb {
+        "if" => {
+            let (value, conditioning) = static_value(xs.get(1)?, args, ctx, settings)?;
+            match conditioning {
+                Conditioning::None => return None,
+                Conditioning::Boolean => (2, !value.is_zero()),
+                Conditioning::Loobean => (2, value.is_zero()),
+            }
+        }
+        "if-zero" => (
+            2,
+            static_value(xs.get(1)?, args, ctx, settings)?.0.is_zero(),
+        ),
+        "if-not-zero" => (
+            2,
+            !static_value(xs.get(1)?, args, ctx, settings)?.0.is_zero(),
+        ),
+        "if-eq" | "if-eq-else" => {
+            let x = static_value(xs.get(1)?, args, ctx, settings)?.0;
+            let val = static_value(xs.get(2)?, args, ctx, settings)?.0;
+            (3, x == val)
+        }
+        _ => return None,
+    };
+
+    let (dead_idx, branch) = if takes_then {
+        (then_idx + 1, "else")
+    } else {
+        (then_idx, "then")
+    };
+    (dead_idx < xs.len()).then(|| (verb.to_owned(), branch))
+}
+
+fn lint_body(
+    function: &str,
+    e: &AstNode,
+    args: &[String],
+    ctx: &mut Scope,
+    settings: &CompileSettings,
+    lints: &mut Vec<Lint>,
+) {
+    if let Token::List(xs) = &e.class {
+        if let Some((conditional, branch)) = dead_branch(e, args, ctx, settings) {
+            lints.push(Lint::DeadBranch {
+                function: function.to_owned(),
+                conditional,
+                branch,
+                lc: e.lc,
+            });
+        }
+        for x in xs.iter() {
+            lint_body(function, x, args, ctx, settings, lints);
+        }
+    }
+}
+
+/// The first operand of an arithmetic operation in `n` that is promoted
+/// across more than `gap` bits, along with the operand promoting it.
+/// Constants are typed after their value and binary operands are typically
+/// flags, so neither of them is considered.
+fn find_promotion(n: &Node, gap: usize) -> Option<(Node, Node)> {
+    match n.e() {
+        Expression::Funcall { func, args } => {
+            if matches!(
+                func,
+                Intrinsic::Add
+                    | Intrinsic::Sub
+                    | Intrinsic::Mul
+                    | Intrinsic::VectorAdd
+                    | Intrinsic::VectorSub
+                    | Intrinsic::VectorMul
+            ) {
+                let operands = args
+                    .iter()
+                    .filter(|a| !matches!(a.e(), Expression::Const(_)))
+                    .filter(|a| !matches!(a.t().rm(), RawMagma::Binary))
+                    .collect::<Vec<_>>();
+                let narrow = operands.iter().min_by_key(|a| a.t().m().bit_size());
+                let wide = operands.iter().max_by_key(|a| a.t().m().bit_size());
+                if let (Some(narrow), Some(wide)) = (narrow, wide) {
+                    if wide.t().m().bit_size() - narrow.t().m().bit_size() > gap {
+                        return Some(((*narrow).clone(), (*wide).clone()));
+                    }
+                }
+            }
+            args.iter().find_map(|a| find_promotion(a, gap))
+        }
+        Expression::List(xs) => xs.iter().find_map(|x| find_promotion(x, gap)),
+        _ => None,
+    }
+}
+
+/// Report the implicit promotions in `e` at the innermost form they can be
+/// traced back to, and return whether any was found.
+fn lint_promotions(
+    constraint: &str,
+    e: &AstNode,
+    gap: usize,
+    ctx: &mut Scope,
+    settings: &CompileSettings,
+    lints: &mut Vec<Lint>,
+) -> bool {
+    let Token::List(xs) = &e.class else {
+        return false;
+    };
+    // forms depending on their context, e.g. on a loop variable, do not reduce
+    // in isolation and are accounted for by their parent
+    let Some((narrow, wide)) = reduce(e, ctx, settings)
+        .ok()
+        .flatten()
+        .and_then(|n| find_promotion(&n, gap))
+    else {
+        return false;
+    };
+
+    let mut in_children = false;
+    for x in xs.iter().skip(1) {
+        in_children |= lint_promotions(constraint, x, gap, ctx, settings, lints);
+    }
+    if !in_children {
+        lints.push(Lint::ImplicitPromotion {
+            constraint: constraint.to_owned(),
+            narrow: (narrow.pretty(), narrow.t().m()),
+            wide: (wide.pretty(), wide.t().m()),
+            lc: e.lc,
+        });
+    }
+    true
+}
+
+fn reduce_lints(
+    e: &AstNode,
+    ctx: &mut Scope,
+    settings: &CompileSettings,
+    lints: &mut Vec<Lint>,
+) -> Result<()> {
+    match &e.class {
+        Token::DefModule(name, _) => {
+            *ctx = ctx.switch_to_module(name)?.public(true);
+        }
+        Token::Defun {
+            name, args, body, ..
+        }
+        | Token::Defpurefun {
+            name, args, body, ..
+        } => {
+            let mut used = HashSet::new();
+            referenced_symbols(body, &mut used);
+            for arg in args.iter() {
+                // Arguments prefixed by an underscore are deliberately unused
+                if !arg.starts_with('_') && !used.contains(arg.as_str()) {
+                    lints.push(Lint::UnusedArgument {
+                        function: name.to_owned(),
+                        argument: arg.to_owned(),
+                        lc: e.lc,
+                    });
+                }
+            }
+            lint_body(name, body, args, ctx, settings, lints);
+        }
+        Token::DefConstraint {
+            name,
+            perspective,
+            body,
+            ..
+        } => {
+            if let Some(gap) = settings.promotion_gap {
+                let ctx = if let Some(perspective) = perspective {
+                    ctx.jump_in(&format!("in-{perspective}"))
+                } else {
+                    Ok(ctx.clone())
+                };
+                if let Result::Ok(mut ctx) = ctx {
+                    lint_promotions(name, body, gap, &mut ctx, settings, lints);
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// The `Lints` pass skim through the functions defined in an [`Ast`], and
+/// report their unused arguments and their dead branches; if a promotion gap
+/// is set, it also reports the implicit promotions in the constraints. It
+/// must run once the constants have been defined.
+pub(crate) fn pass(ast: &Ast, ctx: Scope, settings: &CompileSettings) -> Result<Vec<Lint>> {
+    let mut module = ctx;
+    let mut lints = Vec::new();
+    for e in ast.exprs.iter() {
+        reduce_lints(e, &mut module, settings, &mut lints)?;
+    }
+
+    Ok(lints)
+}
@@ -1,7 +1,9 @@
 use anyhow::*;
+use itertools::Itertools;
 use log::*;
 use num_bigint::BigInt;
 use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
 
 use self::parser::DisplayableColumn;
 
@@ -16,16 +18,20 @@ use super::{CompileSettings, Domain, Kind};
 mod constants;
 mod definitions;
 mod fmtparser;
+pub(crate) mod include;
+pub(crate) mod lints;
+#[allow(clippy::module_inception)]
 pub(crate) mod parser;
 mod purefuns;
+pub(crate) mod stdlib;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Ast {
     pub exprs: Vec<AstNode>,
 }
 
 pub type LinCol = (usize, usize);
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct AstNode {
     /// the token in which this node devolves
     pub class: Token,
@@ -125,7 +131,7 @@ impl std::fmt::Display for AstNode {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum Token {
     /// an immediate value; can be “arbitrarily” large
     Value(BigInt),
@@ -142,6 +148,8 @@ pub enum Token {
     },
     /// a keyword
     Keyword(String),
+    /// a string literal, without its quotes; only used by include directives
+    String(String),
     /// a list of nodes
     List(Vec<AstNode>),
     /// a range; typically used in discrete constraints declaration and loops
@@ -149,8 +157,9 @@ pub enum Token {
     /// from Domain<AstNode> to Domain<isize> after the parsing.
     Domain(Box<Domain<AstNode>>),
 
-    /// definition of a module; this will derive a symbol table
-    DefModule(String),
+    /// definition of a module; this will derive a symbol table. If set, the
+    /// expression is the number of rows the module must always have.
+    DefModule(String, Option<Box<AstNode>>),
     /// a list of constant definition: (name, value)
     DefConsts(Vec<(String, Box<AstNode>)>),
     /// a list of columns declaration, normally only DefColumn
@@ -159,6 +168,8 @@ pub enum Token {
     /// when the trigger is non-zero
     DefPerspective {
         name: String,
+        /// whether this perspective is shared with other modules
+        shared: bool,
         trigger: Box<AstNode>,
         columns: Vec<AstNode>,
     },
@@ -172,10 +183,17 @@ pub enum Token {
         kind: Kind<Box<AstNode>>,
         /// the value to pad the column with; defaults to 0 if None
         padding_value: Option<i64>,
+        /// an expression of constants and of other columns padding values,
+        /// computing the value to pad the column with
+        padding_expr: Option<Box<AstNode>>,
         /// if set, generate constraint to prove the column type
         must_prove: bool,
         /// which numeric base should be used to display column values; this is a purely aesthetic setting
         base: Base,
+        /// the group this column belongs to, e.g. to lay out the inspector
+        group: Option<String>,
+        /// the documentation of the column, from its preceding `;;` comments
+        doc: Option<String>,
     },
     /// defines an array
     DefArrayColumn {
@@ -191,6 +209,10 @@ pub enum Token {
         must_prove: bool,
         /// which numeric base should be used to display column values; this is a purely aesthetic setting
         base: Base,
+        /// the group this array columns belong to
+        group: Option<String>,
+        /// the documentation of the array, from its preceding `;;` comments
+        doc: Option<String>,
     },
     /// definition of a function
     Defun {
@@ -234,12 +256,23 @@ pub enum Token {
         perspective: Option<String>,
         /// this expression has to reduce to 0 for the constraint to be satisfied
         body: Box<AstNode>,
+        /// the documentation of the constraint, from its preceding `;;` comments
+        doc: Option<String>,
+        /// an assertion is only evaluated when checking a trace, and never
+        /// reaches the exporters
+        assertion: bool,
     },
     /// declaration of a permutation constraint between two sets of columns
     DefPermutation {
         from: Vec<AstNode>,
         to: Vec<DisplayableColumn>,
+        /// the ordering of the sorting columns, i.e. the `signs.len()` first
+        /// ones; if no ordering option is set, all columns are sorting columns
         signs: Vec<bool>,
+        /// whether rows with equal sorting keys keep their original order
+        stable: bool,
+        /// columns breaking the ties between rows with equal sorting keys
+        tiebreaks: Vec<AstNode>,
     },
     DefInterleaving {
         /// new column, which will be filled by the interleaving of the source columns
@@ -273,7 +306,7 @@ impl Token {
             Token::Symbol(ref name) => Some(name.to_string()),
             Token::Keyword(ref name) => Some(name.to_string()),
             Token::List(ref args) => {
-                if let Some(verb) = args.get(0) {
+                if let Some(verb) = args.first() {
                     if let Result::Ok(verb) = verb.as_symbol() {
                         match verb {
                             "if-zero" | "if-not-zero" => {
@@ -347,12 +380,13 @@ impl std::fmt::Debug for Token {
                 ref index,
             } => write!(f, "[{} {}]", name, index),
             Token::Keyword(ref name) => write!(f, "{}", name),
+            Token::String(ref s) => write!(f, "{:?}", s),
             Token::List(ref args) => {
                 write!(f, "({})", Token::format_list(args, LIST_DISPLAY_THRESHOLD))
             }
             Token::Domain(ref args) => write!(f, "{:?}", args),
 
-            Token::DefModule(name) => write!(f, "MODULE {}", name),
+            Token::DefModule(name, _) => write!(f, "MODULE {}", name),
             Token::DefConsts(v) => {
                 write!(
                     f,
@@ -379,7 +413,18 @@ impl std::fmt::Debug for Token {
             } => {
                 write!(f, "DECLARATION {}{:?}{{{:?}}}", name, range, t)
             }
-            Token::DefConstraint { name, .. } => write!(f, "{:?}:CONSTRAINT", name),
+            Token::DefConstraint {
+                name, assertion, ..
+            } => write!(
+                f,
+                "{:?}:{}",
+                name,
+                if *assertion {
+                    "ASSERTION"
+                } else {
+                    "CONSTRAINT"
+                }
+            ),
             Token::Defun {
                 name,
                 args,
@@ -405,9 +450,17 @@ impl std::fmt::Debug for Token {
             }
             Token::DefPerspective {
                 name,
+                shared,
                 trigger,
                 columns,
-            } => write!(f, "SET {}/{:?} {:?}", name, trigger, columns),
+            } => write!(
+                f,
+                "SET {}{}/{:?} {:?}",
+                name,
+                if *shared { " (shared)" } else { "" },
+                trigger,
+                columns
+            ),
             Token::DefInterleaving {
                 target,
                 froms: sources,
@@ -443,6 +496,22 @@ pub(crate) fn maybe_bail<R>(errs: Vec<Result<R>>) -> Result<Vec<R>> {
     }
 }
 
+/// Normalize the line endings of `source` to `\n`, so that sources written
+/// with CRLF or CR line endings are parsed, located and documented exactly
+/// like the others.
+pub(crate) fn normalize_newlines(source: &str) -> std::borrow::Cow<'_, str> {
+    if source.contains('\r') {
+        source.replace("\r\n", "\n").replace('\r', "\n").into()
+    } else {
+        source.into()
+    }
+}
+
+/// Parse the single source `content`
+pub(crate) fn parse_source(content: &str) -> Result<Ast> {
+    parser::parse(content)
+}
+
 /// Given a list of sources and their names, parse them and return a
 /// corresponding list of Ast
 pub(crate) fn parse_ast<S1: AsRef<str>, S2: AsRef<str>>(
@@ -486,46 +555,60 @@ pub fn parse<S1: AsRef<str>, S2: AsRef<str>>(
     sources: &[(S1, S2)],
     settings: &CompileSettings,
 ) -> Result<(Scope, Vec<(String, Ast)>)> {
-    let ctx = Scope::new();
+    analyze(parse_ast(sources)?, settings)
+}
 
-    //
-    // Parse the source into an AST
-    //
-    let asts = maybe_bail(
-        sources
-            .iter()
-            .map(|(name, content)| {
-                info!("Parsing {}", name.as_ref().bright_white().bold());
-                parser::parse(content.as_ref())
-                    .with_context(|| anyhow!("parsing `{}`", name.as_ref()))
-                    .map(|ast| (name.as_ref().to_string(), ast))
-            })
-            .collect::<Vec<_>>(),
-    )?;
+/// Fill a new symbol table with the definitions of the already parsed `asts`
+pub fn analyze(
+    asts: Vec<(String, Ast)>,
+    settings: &CompileSettings,
+) -> Result<(Scope, Vec<(String, Ast)>)> {
+    let ctx = Scope::new();
 
     // The parsing order is crucial to make const. expr. work. Therefore, it
     // must be:
     // 1 - pure functions, which are dependent on constants at run-time but
     //     self-standing at parse-time;
     // 2 - constants, that may be immediate or const. expr., but then pure
-    //     functions are already there;
+    //     functions are already there; as they may refer to each other
+    //     across files, they are collected from all of them first;
     // 3 - the remaining elements, which may be dependent on everything else.
 
     // 1. Pure functions
     for (name, ast) in asts.iter() {
-        purefuns::pass(&ast, ctx.clone())
+        purefuns::pass(ast, ctx.clone())
             .with_context(|| anyhow!("parsing definitions in `{}`", name))?;
     }
     // 2. Constants
-    for (name, ast) in asts.iter() {
-        constants::pass(&ast, ctx.clone(), settings)
-            .with_context(|| anyhow!("parsing definitions in `{}`", name))?;
-    }
+    let assumed = constants::pass(&asts, ctx.clone(), settings)?;
     // 3. The rest
     for (name, ast) in asts.iter() {
-        definitions::pass(&ast, ctx.clone(), settings)
+        ctx.set_source(name);
+        definitions::pass(ast, ctx.clone(), settings)
             .with_context(|| anyhow!("parsing definitions in `{}`", name))?;
     }
 
+    // 4. Lints, once everything they may refer to is defined
+    for (name, ast) in asts.iter().filter(|(name, _)| name != "stdlib") {
+        for lint in lints::pass(ast, ctx.clone(), settings)
+            .with_context(|| anyhow!("linting `{}`", name))?
+        {
+            super::warning(format!("{}:{}", name, lint));
+        }
+    }
+
+    for assumption in settings.assumptions.keys().sorted() {
+        if !assumed.contains(assumption) {
+            if ctx.defines(assumption) {
+                bail!("cannot assume {}: not a constant", assumption.red().bold())
+            } else {
+                bail!(
+                    "cannot assume {}: no such constant",
+                    assumption.red().bold()
+                )
+            }
+        }
+    }
+
     Ok((ctx, asts))
 }
@@ -35,7 +35,7 @@ impl std::fmt::Display for Symbol {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct DisplayableColumn {
     /// name of the column
     pub name: String,
@@ -43,10 +43,39 @@ pub struct DisplayableColumn {
     pub base: Base,
 }
 
+/// The documentation of the element starting at `span`, i.e. the `;;`
+/// comments on the lines directly above it, if it starts its own line.
+fn doc_comment(span: &pest::Span) -> Option<String> {
+    let (above, indent) = span.get_input()[..span.start()].rsplit_once('\n')?;
+    if !indent.trim().is_empty() {
+        return None;
+    }
+
+    let mut lines = above
+        .lines()
+        .rev()
+        .map(str::trim)
+        .take_while(|l| l.starts_with(";;"))
+        .map(|l| {
+            let l = l.trim_start_matches(';');
+            l.strip_prefix(' ').unwrap_or(l)
+        })
+        .collect::<Vec<_>>();
+    lines.reverse();
+    let doc = lines.join("\n").trim().to_owned();
+    if doc.is_empty() {
+        None
+    } else {
+        Some(doc)
+    }
+}
+
 fn parse_defconstraint<I: Iterator<Item = Result<AstNode>>>(
     mut tokens: I,
     lc: (usize, usize),
     src: String,
+    doc: Option<String>,
+    assertion: bool,
 ) -> Result<AstNode> {
     enum GuardParser {
         Begin,
@@ -82,24 +111,24 @@ fn parse_defconstraint<I: Iterator<Item = Result<AstNode>>>(
                     _ => bail!("expected :guard, :domain or :perspective, found `{:?}`", x),
                 },
                 GuardParser::Guard => {
-                    if guard.is_some() {
-                        bail!("guard already defined: `{:?}`", guard.unwrap())
+                    if let Some(guard) = guard {
+                        bail!("guard already defined: `{:?}`", guard)
                     } else {
                         guard = Some(Box::new(x.clone()));
                         status = GuardParser::Begin;
                     }
                 }
                 GuardParser::Perspective => {
-                    if guard.is_some() {
-                        bail!("perspective already defined: `{:?}`", guard.unwrap())
+                    if let Some(perspective) = perspective {
+                        bail!("perspective already defined: `{:?}`", perspective)
                     } else {
                         perspective = Some(x.as_symbol()?.to_owned());
                         status = GuardParser::Begin;
                     }
                 }
                 GuardParser::Domain => {
-                    if domain.is_some() {
-                        bail!("domain already defined: `{:?}`", domain.unwrap())
+                    if let Some(domain) = domain {
+                        bail!("domain already defined: `{:?}`", domain)
                     } else {
                         if let Token::Domain(range) = &x.class {
                             domain = Some(range.to_owned())
@@ -139,6 +168,8 @@ fn parse_defconstraint<I: Iterator<Item = Result<AstNode>>>(
             guard,
             perspective,
             body,
+            doc,
+            assertion,
         },
         src,
         lc,
@@ -153,6 +184,15 @@ fn parse_defperspective<I: Iterator<Item = Result<AstNode>>>(mut tokens: I) -> R
         .with_context(|| "expected perspective name")?
         .to_owned();
 
+    let mut tokens = tokens.peekable();
+    let shared = matches!(
+        tokens.peek(),
+        Some(Ok(AstNode { class: Token::Keyword(kw), .. })) if kw == ":shared"
+    );
+    if shared {
+        tokens.next();
+    }
+
     let trigger = Box::new(
         tokens
             .next()
@@ -165,11 +205,12 @@ fn parse_defperspective<I: Iterator<Item = Result<AstNode>>>(mut tokens: I) -> R
     let lc = columns_tokens.lc;
     let src = columns_tokens.src.to_owned();
     let columns = columns_tokens.as_list()?.iter().cloned().map(Ok);
-    let columns = parse_defcolumns(columns, lc, src.to_owned())?;
+    let columns = parse_defcolumns(columns, lc, src.to_owned(), Vec::new(), None)?;
     if let Token::DefColumns(columns) = columns.class {
         Ok(AstNode {
             class: Token::DefPerspective {
                 name,
+                shared,
                 trigger,
                 columns,
             },
@@ -188,7 +229,9 @@ struct ColumnAttributes {
     must_prove: bool,
     range: OnceCell<Box<Domain<AstNode>>>,
     padding_value: OnceCell<i64>,
+    padding_expr: OnceCell<AstNode>,
     base: OnceCell<Base>,
+    group: OnceCell<String>,
     computation: Option<AstNode>,
 }
 
@@ -200,6 +243,8 @@ impl std::convert::TryInto<DisplayableColumn> for ColumnAttributes {
             ("type", self.t.get().is_some()),
             ("range", self.range.get().is_some()),
             ("padding value", self.padding_value.get().is_some()),
+            ("padding expression", self.padding_expr.get().is_some()),
+            ("group", self.group.get().is_some()),
         ] {
             if exists {
                 bail!("cannot specify {} to {}", attribute, self.name)
@@ -221,9 +266,10 @@ fn parse_column_attributes(source: AstNode) -> Result<ColumnAttributes> {
         Computation,
         PaddingValue,
         Base,
+        Group,
     }
     let re_type = regex_lite::Regex::new(
-        r"^:(?<RawMagma>i(?<Integer>\d+)|[a-z]+)?(@(?<Conditioning>bool|loob))?(?<Proven>@prove)?$",
+        r"^:(?<RawMagma>i(?<Integer>\d+)|s(?<Signed>\d+)|[a-z]+)?(@(?<Conditioning>bool|loob))?(?<Proven>@prove)?$",
     )?;
     let mut attributes = ColumnAttributes::default();
     let mut state = ColumnParser::Begin;
@@ -234,7 +280,7 @@ fn parse_column_attributes(source: AstNode) -> Result<ColumnAttributes> {
     } else if let Token::List(l) = source.class {
         l.into_iter()
     } else {
-        unreachable!()
+        bail!("expected column definition, found {}", source.src.red())
     };
 
     let name_token = tokens
@@ -253,18 +299,45 @@ fn parse_column_attributes(source: AstNode) -> Result<ColumnAttributes> {
                         ":comp" => ColumnParser::Computation,
                         // e.g. (A :array {1 3 5}) or (A :array [5])
                         ":array" => ColumnParser::Array,
-                        // a specific padding value, e.g. (NOT :padding 255)
+                        // a specific padding value, e.g. (NOT :padding 255), or
+                        // one mirroring other columns, e.g. (B :padding (+ A 1))
                         ":padding" => ColumnParser::PaddingValue,
                         // how to display the column values in debug
                         ":display" => ColumnParser::Base,
+                        // a purely informative grouping, e.g. (SP :group "stack")
+                        ":group" => ColumnParser::Group,
                         _ => {
                             if let Some(caps) = re_type.captures(kw) {
                                 let raw_magma = if let Some(integer) = caps.name("Integer") {
-                                    let bit_size = integer.as_str().parse::<usize>().unwrap();
-                                    if bit_size > crate::constants::FIELD_BITSIZE {
-                                        panic!("Not yet :(");
-                                    }
+                                    let bit_size = integer
+                                        .as_str()
+                                        .parse::<usize>()
+                                        .ok()
+                                        .filter(|b| *b <= crate::constants::FIELD_BITSIZE)
+                                        .ok_or_else(|| {
+                                            anyhow!(
+                                                "{} exceeds the field size of {} bits",
+                                                kw.red(),
+                                                crate::constants::FIELD_BITSIZE
+                                            )
+                                        })?;
                                     RawMagma::Integer(bit_size)
+                                } else if let Some(signed) = caps.name("Signed") {
+                                    let bit_size = signed
+                                        .as_str()
+                                        .parse::<usize>()
+                                        .ok()
+                                        .filter(|b| {
+                                            (2..=crate::constants::FIELD_BITSIZE).contains(b)
+                                        })
+                                        .ok_or_else(|| {
+                                            anyhow!(
+                                                "{} must be between 2 and {} bits",
+                                                kw.red(),
+                                                crate::constants::FIELD_BITSIZE
+                                            )
+                                        })?;
+                                    RawMagma::Signed(bit_size)
                                 } else {
                                     caps.name("RawMagma")
                                         .map_or(Ok(RawMagma::Native), |s| s.as_str().try_into())?
@@ -329,14 +402,20 @@ fn parse_column_attributes(source: AstNode) -> Result<ColumnAttributes> {
                 ColumnParser::Begin
             }
             ColumnParser::PaddingValue => {
-                attributes.padding_value.set(x.as_i64()?).map_err(|_| {
-                    anyhow!(
-                        "trying to redefine column {} of type {} as {:?}",
+                if attributes.padding_value.get().is_some()
+                    || attributes.padding_expr.get().is_some()
+                {
+                    bail!(
+                        "trying to redefine the padding of column {} as {}",
                         attributes.name,
-                        attributes.padding_value.get().unwrap(),
-                        x.as_i64().unwrap()
+                        x.src.red()
                     )
-                })?;
+                }
+                if let Token::List(_) | Token::Symbol(_) = x.class {
+                    attributes.padding_expr.set(x).unwrap();
+                } else {
+                    attributes.padding_value.set(x.as_i64()?).unwrap();
+                }
                 ColumnParser::Begin
             }
             ColumnParser::Base => {
@@ -355,6 +434,21 @@ fn parse_column_attributes(source: AstNode) -> Result<ColumnAttributes> {
                 })?;
                 ColumnParser::Begin
             }
+            ColumnParser::Group => {
+                let group = if let Token::String(ref s) = x.class {
+                    s.to_owned()
+                } else {
+                    bail!(":group expects a string; found {}", x.src.red())
+                };
+                attributes.group.set(group).map_err(|_| {
+                    anyhow!(
+                        "trying to redefine the group of column {} as {}",
+                        attributes.name,
+                        x.src
+                    )
+                })?;
+                ColumnParser::Begin
+            }
         };
     }
     // Ensure that we are in a clean state
@@ -364,22 +458,45 @@ fn parse_column_attributes(source: AstNode) -> Result<ColumnAttributes> {
         ColumnParser::Computation => bail!("incomplate :comp definition"),
         ColumnParser::PaddingValue => bail!("incomplete :padding definition"),
         ColumnParser::Base => bail!("incomplete :display definition"),
+        ColumnParser::Group => bail!("incomplete :group definition"),
     }
     Ok(attributes)
 }
 
+/// Parse a columns definition, where `docs` are the documentations of each
+/// column, and `doc` the one of the whole definition, used for the columns
+/// lacking their own.
 fn parse_defcolumns<I: Iterator<Item = Result<AstNode>>>(
     tokens: I,
     lc: (usize, usize),
     src: String,
+    docs: Vec<Option<String>>,
+    doc: Option<String>,
 ) -> Result<AstNode> {
     // A columns definition is a list of column definition
     let columns = tokens
-        .map(|c| {
+        .zip(docs.into_iter().chain(std::iter::repeat(None)))
+        .map(|(c, column_doc)| {
+            let doc = column_doc.or_else(|| doc.clone());
             c.and_then(|c| {
                 let column_attributes = parse_column_attributes(c.clone())?;
 
-                let base = column_attributes.base.get().cloned().unwrap_or(Base::Hex);
+                // signed columns are displayed as such by default
+                let base =
+                    column_attributes.base.get().cloned().unwrap_or_else(
+                        || match column_attributes.t.get().map(|t| t.rm()) {
+                            Some(RawMagma::Signed(_)) => Base::Signed,
+                            _ => Base::Hex,
+                        },
+                    );
+                if column_attributes.range.get().is_some()
+                    && column_attributes.padding_expr.get().is_some()
+                {
+                    bail!(
+                        "array {} can not be padded with an expression",
+                        column_attributes.name.red()
+                    )
+                }
                 Ok(AstNode {
                     class: if let Some(range) = column_attributes.range.get() {
                         Token::DefArrayColumn {
@@ -395,6 +512,8 @@ fn parse_defcolumns<I: Iterator<Item = Result<AstNode>>>(
                             domain: range.clone(),
                             must_prove: column_attributes.must_prove,
                             base,
+                            group: column_attributes.group.get().cloned(),
+                            doc,
                         }
                     } else {
                         Token::DefColumn {
@@ -411,8 +530,15 @@ fn parse_defcolumns<I: Iterator<Item = Result<AstNode>>>(
                                 .map(|c| Kind::Expression(Box::new(c)))
                                 .unwrap_or(Kind::Commitment),
                             padding_value: column_attributes.padding_value.get().cloned(),
+                            padding_expr: column_attributes
+                                .padding_expr
+                                .get()
+                                .cloned()
+                                .map(Box::new),
                             must_prove: column_attributes.must_prove,
                             base,
+                            group: column_attributes.group.get().cloned(),
+                            doc,
                         }
                     },
                     lc: c.lc,
@@ -433,6 +559,14 @@ fn parse_defcolumns<I: Iterator<Item = Result<AstNode>>>(
 fn parse_definition(pair: Pair<Rule>) -> Result<AstNode> {
     let lc = pair.as_span().start_pos().line_col();
     let src = pair.as_str().to_owned();
+    let doc = doc_comment(&pair.as_span());
+    // the documentation of the arguments, after the definition keyword
+    let docs = pair
+        .clone()
+        .into_inner()
+        .skip(1)
+        .map(|p| doc_comment(&p.as_span()))
+        .collect::<Vec<_>>();
 
     let mut tokens = pair.into_inner().map(rec_parse);
 
@@ -443,13 +577,32 @@ fn parse_definition(pair: Pair<Rule>) -> Result<AstNode> {
                 .with_context(|| anyhow!("module name missing"))??
                 .as_symbol()?
                 .to_owned();
+            let length = match tokens.next().transpose()? {
+                Some(AstNode {
+                    class: Token::Keyword(kw),
+                    ..
+                }) if kw == ":length" => {
+                    Some(Box::new(tokens.next().with_context(|| {
+                        anyhow!("missing length for module {}", name)
+                    })??))
+                }
+                Some(x) => bail!("expected :length, found `{}`", x.src),
+                None => None,
+            };
+            if let Some(last) = tokens.next() {
+                bail!(
+                    "too many arguments found for module {}: {}",
+                    name,
+                    last?.src
+                )
+            }
             Ok(AstNode {
-                class: Token::DefModule(name),
+                class: Token::DefModule(name, length),
                 lc,
                 src,
             })
         }
-        "defcolumns" => parse_defcolumns(tokens, lc, src),
+        "defcolumns" => parse_defcolumns(tokens, lc, src, docs, doc),
         "defperspective" => parse_defperspective(tokens),
         "defconst" => Ok(AstNode {
             class: Token::DefConsts(
@@ -630,7 +783,8 @@ fn parse_definition(pair: Pair<Rule>) -> Result<AstNode> {
                 lc,
             })
         }
-        "defconstraint" => parse_defconstraint(tokens, lc, src),
+        "defconstraint" => parse_defconstraint(tokens, lc, src, doc, false),
+        "defassertion" => parse_defconstraint(tokens, lc, src, doc, true),
         "definrange" => {
             let exp = tokens
                 .next()
@@ -696,7 +850,7 @@ fn parse_definition(pair: Pair<Rule>) -> Result<AstNode> {
                 .to_vec();
             for from_w_sign in froms_with_sign {
                 if let Result::Ok(list) = from_w_sign.as_list() {
-                    if let Some(s) = list.get(0).and_then(|a| a.as_symbol().ok()) {
+                    if let Some(s) = list.first().and_then(|a| a.as_symbol().ok()) {
                         let sign = if s == "+" || s == "↓" {
                             Some(true)
                         } else if s == "-" || s == "↑" {
@@ -729,10 +883,40 @@ fn parse_definition(pair: Pair<Rule>) -> Result<AstNode> {
             if signs.is_empty() {
                 bail!("no sorting criterion found")
             }
-            signs.resize(from.len(), true); // ensure that signs & froms are the same size
+
+            let mut ordering = false;
+            let mut stable = true;
+            let mut tiebreaks = Vec::new();
+            while let Some(option) = tokens.next() {
+                let option = option?;
+                ordering = true;
+                match &option.class {
+                    Token::Keyword(kw) if kw == ":stable" => stable = true,
+                    Token::Keyword(kw) if kw == ":unstable" => stable = false,
+                    Token::Keyword(kw) if kw == ":tie-break" => {
+                        tiebreaks = tokens
+                            .next()
+                            .with_context(|| anyhow!("missing columns after :tie-break"))??
+                            .as_list()?
+                            .to_vec();
+                    }
+                    _ => bail!("unexpected permutation option: {}", option.src.red()),
+                }
+            }
+            if !ordering {
+                // without explicit ordering options, all the columns are
+                // sorting criteria, so that the sorted rows are fully ordered
+                signs.resize(from.len(), true);
+            }
 
             Ok(AstNode {
-                class: Token::DefPermutation { from, to, signs },
+                class: Token::DefPermutation {
+                    from,
+                    to,
+                    signs,
+                    stable,
+                    tiebreaks,
+                },
                 src,
                 lc,
             })
@@ -793,11 +977,36 @@ fn rec_parse(pair: Pair<Rule>) -> Result<AstNode> {
                 src,
             })
         }
-        Rule::symbol | Rule::definition_kw => Ok(AstNode {
-            class: Token::Symbol(pair.as_str().to_owned()),
-            lc,
-            src,
-        }),
+        Rule::symbol | Rule::definition_kw => {
+            // quoting bars are not part of the name, contrary to the
+            // deprecated ones found elsewhere in a symbol
+            let start = pair.as_span().start();
+            let mut name = String::new();
+            let mut bare = String::new();
+            let mut last = 0;
+            for quoted in pair.into_inner() {
+                let span = quoted.as_span();
+                bare.push_str(&src[last..span.start() - start]);
+                name.push_str(&src[last..span.start() - start]);
+                name.push_str(quoted.as_str().trim_matches('|'));
+                last = span.end() - start;
+            }
+            bare.push_str(&src[last..]);
+            name.push_str(&src[last..]);
+            if bare.contains('|') {
+                log::warn!(
+                    "{}:{}: `{}`: `|` is deprecated in symbols, where it will only quote names",
+                    lc.0,
+                    lc.1,
+                    src
+                );
+            }
+            Ok(AstNode {
+                class: Token::Symbol(name),
+                lc,
+                src,
+            })
+        }
         Rule::integer => {
             let s = pair.as_str();
             let sign = if s.starts_with('-') {
@@ -873,10 +1082,15 @@ fn rec_parse(pair: Pair<Rule>) -> Result<AstNode> {
                 src,
             })
         }
+        Rule::string => Ok(AstNode {
+            class: Token::String(pair.as_str().trim_matches('"').to_owned()),
+            src,
+            lc,
+        }),
         Rule::natural => Ok(AstNode {
             class: Token::Value(BigInt::from_str(pair.as_str()).unwrap()),
-            src: src,
-            lc: lc,
+            src,
+            lc,
         }),
         x => {
             unimplemented!("{:?}", x)
@@ -884,13 +1098,103 @@ fn rec_parse(pair: Pair<Rule>) -> Result<AstNode> {
     }
 }
 
+/// Split `source` into its top-level forms, each with the offset where it
+/// starts. A form ends on its closing parenthesis, or when a parenthesis
+/// opens at the start of a line, so that a missing closing parenthesis does
+/// not swallow the following definitions.
+fn toplevel_forms(source: &str) -> Vec<(usize, &str)> {
+    let mut forms = Vec::new();
+    let mut start: Option<usize> = None;
+    let (mut depth, mut column) = (0usize, 0);
+    let (mut in_string, mut in_comment) = (false, false);
+
+    for (i, c) in source.char_indices() {
+        if in_comment {
+            in_comment = c != '\n';
+        } else if in_string {
+            in_string = c != '"' && c != '\n';
+        } else {
+            match c {
+                ';' => in_comment = true,
+                '"' => in_string = true,
+                '(' if depth == 0 || column == 0 => {
+                    if let Some(start) = start.take() {
+                        forms.push((start, source[start..i].trim_end()));
+                    }
+                    start = Some(i);
+                    depth = 1;
+                }
+                '(' => depth += 1,
+                ')' if depth > 0 => {
+                    depth -= 1;
+                    if depth == 0 {
+                        if let Some(start) = start.take() {
+                            forms.push((start, &source[start..=i]));
+                        }
+                    }
+                }
+                // stray characters are a form of their own
+                c if depth == 0 && !c.is_whitespace() && start.is_none() => {
+                    start = Some(i);
+                }
+                _ => {}
+            }
+        }
+
+        if c == '\n' {
+            column = 0;
+        } else {
+            column += 1;
+        }
+    }
+    if let Some(start) = start {
+        forms.push((start, source[start..].trim_end()));
+    }
+    forms
+}
+
+/// Parse every top-level form of `source` on its own, to report all of its
+/// syntax errors at once rather than only the first one.
+fn syntax_errors(source: &str) -> Vec<pest::error::Error<Rule>> {
+    toplevel_forms(source)
+        .into_iter()
+        .filter_map(|(offset, form)| {
+            let err = CorsetParser::parse(Rule::corset, form).err()?;
+            // the error is moved back to its location in the whole source
+            let at = |i: usize| pest::Position::new(source, offset + i).unwrap();
+            Some(match err.location {
+                pest::error::InputLocation::Pos(i) => {
+                    pest::error::Error::new_from_pos(err.variant, at(i))
+                }
+                pest::error::InputLocation::Span((start, end)) => {
+                    pest::error::Error::new_from_span(err.variant, at(start).span(&at(end)))
+                }
+            })
+        })
+        .collect()
+}
+
 pub fn parse(source: &str) -> Result<Ast> {
     let mut ast = Ast { exprs: vec![] };
+    let source: &str = &super::normalize_newlines(source);
 
-    for pair in CorsetParser::parse(Rule::corset, source)? {
+    let pairs = CorsetParser::parse(Rule::corset, source).map_err(|err| {
+        let errs = syntax_errors(source);
+        if errs.len() > 1 {
+            anyhow!(
+                "{}\n{} syntax errors found",
+                errs.iter().join("\n"),
+                errs.len()
+            )
+        } else {
+            anyhow!(err)
+        }
+    })?;
+    for pair in pairs {
         if pair.as_rule() == Rule::corset {
             for constraint in pair.into_inner() {
-                if constraint.as_rule() != Rule::EOI {
+                // Inclusions are resolved when loading the source files
+                if !matches!(constraint.as_rule(), Rule::EOI | Rule::include) {
                     ast.exprs.push(rec_parse(constraint)?);
                 }
             }
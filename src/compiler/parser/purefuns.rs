@@ -8,7 +8,7 @@ use super::{Ast, AstNode, Token};
 
 fn reduce(e: &AstNode, ctx: &mut Scope) -> Result<()> {
     match &e.class {
-        Token::DefModule(name) => {
+        Token::DefModule(name, _) => {
             *ctx = ctx.switch_to_module(name)?.public(true);
             Ok(())
         }
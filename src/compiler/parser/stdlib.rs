@@ -0,0 +1,148 @@
+use anyhow::*;
+use itertools::Itertools;
+use log::*;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+use super::{fmtparser, AstNode, Token};
+
+/// The standard library embedded in this build of corset
+pub(crate) const STDLIB: &str = include_str!("../../stdlib.lisp");
+
+/// The SHA-256 of the embedded standard library, identifying it in the
+/// artifacts compiled by this build of corset
+pub(crate) fn stdlib_hash() -> String {
+    Sha256::digest(STDLIB.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// A top-level definition of the standard library
+struct StdlibItem<'a> {
+    /// the symbols introduced by this definition
+    defines: Vec<String>,
+    /// the symbols referenced by this definition
+    uses: HashSet<String>,
+    /// the source code of the definition
+    src: &'a str,
+}
+
+pub(super) fn collect_symbols(n: &AstNode, ax: &mut HashSet<String>) {
+    match &n.class {
+        Token::Symbol(name) => {
+            ax.insert(name.to_owned());
+        }
+        Token::IndexedSymbol { name, index } => {
+            ax.insert(name.to_owned());
+            collect_symbols(index, ax);
+        }
+        Token::List(xs) => {
+            for x in xs {
+                collect_symbols(x, ax);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Extract the name of a function from its signature, i.e. either `(name args...)`
+/// or `((name :attributes...) args...)`
+fn function_name(signature: &AstNode) -> Option<String> {
+    let head = signature.as_list().ok()?.first()?;
+    match &head.class {
+        Token::Symbol(name) => Some(name.to_owned()),
+        Token::List(xs) => xs.first()?.as_symbol().ok().map(|s| s.to_owned()),
+        _ => None,
+    }
+}
+
+fn stdlib_item(node: &AstNode) -> Option<StdlibItem<'_>> {
+    let xs = node.as_list().ok()?;
+    let kw = xs.first()?.as_symbol().ok()?;
+
+    let mut uses = HashSet::new();
+    let defines = match kw {
+        "defun" | "defpurefun" => {
+            let name = function_name(xs.get(1)?)?;
+            for x in xs.iter().skip(1) {
+                collect_symbols(x, &mut uses);
+            }
+            uses.remove(&name);
+            vec![name]
+        }
+        // (defalias new old new old ...)
+        "defalias" | "defunalias" | "defconst" => {
+            let mut defines = Vec::new();
+            for (new, old) in xs.iter().skip(1).tuples() {
+                defines.push(new.as_symbol().ok()?.to_owned());
+                collect_symbols(old, &mut uses);
+            }
+            defines
+        }
+        _ => return None,
+    };
+
+    Some(StdlibItem {
+        defines,
+        uses,
+        src: &node.src,
+    })
+}
+
+/// Only retain from the standard library `stdlib` the definitions that are
+/// transitively referenced from `sources`, so that unused definitions are
+/// neither elaborated nor cluttering the symbol tables.
+pub(crate) fn prune_stdlib<S1: AsRef<str>, S2: AsRef<str>>(
+    stdlib: &str,
+    sources: &[(S1, S2)],
+) -> Result<String> {
+    let stdlib_ast = fmtparser::parse(stdlib).context("parsing the standard library")?;
+    let items = stdlib_ast
+        .exprs
+        .iter()
+        .filter_map(stdlib_item)
+        .collect::<Vec<_>>();
+
+    let mut used = HashSet::new();
+    for (name, source) in sources {
+        let ast = fmtparser::parse(source.as_ref())
+            .with_context(|| anyhow!("parsing `{}`", name.as_ref()))?;
+        for n in ast.exprs.iter() {
+            collect_symbols(n, &mut used);
+        }
+    }
+
+    let mut kept = vec![false; items.len()];
+    loop {
+        let mut changed = false;
+        for (i, item) in items.iter().enumerate() {
+            if !kept[i] && item.defines.iter().any(|d| used.contains(d)) {
+                kept[i] = true;
+                changed = true;
+                used.extend(item.uses.iter().cloned());
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let kept_items = items
+        .iter()
+        .zip(kept.iter())
+        .filter_map(|(item, keep)| keep.then_some(item))
+        .collect::<Vec<_>>();
+    info!(
+        "using {}/{} stdlib definitions: {}",
+        kept_items.len(),
+        items.len(),
+        kept_items
+            .iter()
+            .flat_map(|i| i.defines.iter())
+            .unique()
+            .join(" ")
+    );
+
+    Ok(kept_items.iter().map(|i| i.src).join("\n"))
+}
@@ -0,0 +1,260 @@
+//! Import constraint systems described in PIL, so that they can be checked and
+//! inspected with corset.
+//!
+//! Only a subset of PIL is supported:
+//!   - committed and constant polynomials, the latter being expected to be
+//!     filled by the trace like any other column;
+//!   - intermediate polynomials, which are inlined where they are used;
+//!   - polynomial identities;
+//!   - plookups, their optional selectors multiplying the looked up
+//!     expressions.
+//!
+//! Namespaces become modules, and the namespaces sizes are ignored, as the
+//! modules lengths are set by the trace.
+use anyhow::*;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use owo_colors::OwoColorize;
+use pest::{
+    iterators::{Pair, Pairs},
+    pratt_parser::{Assoc, Op, PrattParser},
+    Parser,
+};
+use std::collections::HashMap;
+
+use super::{
+    builder::ConstraintSetBuilder, ColumnRef, ConstraintSet, Intrinsic, Kind, Magma, Node,
+};
+use crate::{pretty::Pretty, structs::Handle};
+
+#[derive(Parser)]
+#[grammar = "pil.pest"]
+struct PilParser;
+
+struct Importer {
+    builder: ConstraintSetBuilder,
+    pratt: PrattParser<Rule>,
+    /// the current namespace
+    namespace: String,
+    constants: HashMap<String, BigInt>,
+    columns: HashMap<Handle, ColumnRef>,
+    /// intermediate polynomials
+    aliases: HashMap<Handle, Node>,
+}
+impl Importer {
+    fn new() -> Self {
+        Importer {
+            builder: ConstraintSetBuilder::new(),
+            pratt: PrattParser::new()
+                .op(Op::infix(Rule::add, Assoc::Left) | Op::infix(Rule::sub, Assoc::Left))
+                .op(Op::infix(Rule::mul, Assoc::Left))
+                .op(Op::infix(Rule::pow, Assoc::Right))
+                .op(Op::prefix(Rule::neg))
+                .op(Op::postfix(Rule::next)),
+            namespace: String::new(),
+            constants: HashMap::new(),
+            columns: HashMap::new(),
+            aliases: HashMap::new(),
+        }
+    }
+
+    /// Resolve a possibly namespaced polynomial name
+    fn handle(&self, name: &str) -> Handle {
+        match name.split_once('.') {
+            Some((namespace, name)) => Handle::new(namespace, name),
+            None => Handle::new(&self.namespace, name),
+        }
+    }
+
+    fn constant(&self, name: &str) -> Result<BigInt> {
+        self.constants
+            .get(name)
+            .cloned()
+            .with_context(|| anyhow!("unknown constant {}", name.red()))
+    }
+
+    fn number(s: &str) -> Result<BigInt> {
+        match s.strip_prefix("0x") {
+            Some(hex) => BigInt::parse_bytes(hex.as_bytes(), 16),
+            None => BigInt::parse_bytes(s.as_bytes(), 10),
+        }
+        .with_context(|| anyhow!("invalid number {}", s.red()))
+    }
+
+    /// Evaluate an expression that may only contain numbers and constants
+    fn eval(&self, pairs: Pairs<Rule>) -> Result<BigInt> {
+        self.pratt
+            .map_primary(|p| match p.as_rule() {
+                Rule::number => Self::number(p.as_str()),
+                Rule::constant => self.constant(p.as_str()),
+                Rule::expr => self.eval(p.into_inner()),
+                _ => bail!("{} is not a constant expression", p.as_str().red()),
+            })
+            .map_prefix(|_, x| Ok(-x?))
+            .map_postfix(|_, p| bail!("{} can not be shifted", p.as_str().red()))
+            .map_infix(|x, op, y| {
+                let (x, y) = (x?, y?);
+                Ok(match op.as_rule() {
+                    Rule::add => x + y,
+                    Rule::sub => x - y,
+                    Rule::mul => x * y,
+                    Rule::pow => x.pow(
+                        y.to_u32()
+                            .with_context(|| anyhow!("invalid exponent {}", y.red()))?,
+                    ),
+                    _ => unreachable!(),
+                })
+            })
+            .parse(pairs)
+    }
+
+    fn expression(&self, pairs: Pairs<Rule>) -> Result<Node> {
+        self.pratt
+            .map_primary(|p| match p.as_rule() {
+                Rule::number => Ok(Node::from_bigint(Self::number(p.as_str())?)),
+                Rule::constant => Ok(Node::from_bigint(self.constant(p.as_str())?)),
+                Rule::reference => {
+                    let handle = self.handle(p.as_str());
+                    if let Some(alias) = self.aliases.get(&handle) {
+                        Ok(alias.clone())
+                    } else if let Some(column) = self.columns.get(&handle) {
+                        self.builder.node(column, Kind::Commitment)
+                    } else {
+                        bail!("unknown polynomial {}", handle.pretty())
+                    }
+                }
+                Rule::expr => self.expression(p.into_inner()),
+                _ => unreachable!(),
+            })
+            .map_prefix(|_, x| Intrinsic::Neg.call(&[x?]))
+            .map_postfix(|x, _| Ok(x?.shift(1)))
+            .map_infix(|x, op, y| {
+                let (x, y) = (x?, y?);
+                match op.as_rule() {
+                    Rule::add => Intrinsic::Add.call(&[x, y]),
+                    Rule::sub => Intrinsic::Sub.call(&[x, y]),
+                    Rule::mul => Intrinsic::Mul.call(&[x, y]),
+                    Rule::pow => {
+                        if !y.is_constant() {
+                            bail!("exponents must be constant")
+                        }
+                        Intrinsic::Exp.call(&[x, y])
+                    }
+                    _ => unreachable!(),
+                }
+            })
+            .parse(pairs)
+    }
+
+    /// The expressions of a lookup side, multiplied by its selector if any
+    fn selection(&self, pair: Pair<Rule>) -> Result<Vec<Node>> {
+        let mut selector = None;
+        let mut exprs = Vec::new();
+        for p in pair.into_inner() {
+            match p.as_rule() {
+                Rule::selector => {
+                    selector = Some(self.expression(p.into_inner().next().unwrap().into_inner())?)
+                }
+                Rule::expr => exprs.push(self.expression(p.into_inner())?),
+                _ => unreachable!(),
+            }
+        }
+        if let Some(selector) = selector {
+            exprs
+                .into_iter()
+                .map(|e| Intrinsic::Mul.call(&[selector.clone(), e]))
+                .collect()
+        } else {
+            Ok(exprs)
+        }
+    }
+
+    fn declare(&mut self, names: Pairs<Rule>) -> Result<()> {
+        for name in names.filter(|p| p.as_rule() == Rule::identifier) {
+            let handle = self.handle(name.as_str());
+            if self.columns.contains_key(&handle) || self.aliases.contains_key(&handle) {
+                bail!("{} is declared twice", handle.pretty())
+            }
+            let column = self.builder.commitment(handle.clone(), Magma::native())?;
+            self.columns.insert(handle, column);
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, pair: Pair<Rule>) -> Result<()> {
+        // several statements may share a line, so they are named after their
+        // position in the source
+        let (line, column) = pair.line_col();
+        let rule = pair.as_rule();
+        let mut inner = pair.into_inner();
+        match rule {
+            Rule::include => bail!("includes are not supported; merge the PIL files first"),
+            Rule::constant_def => {
+                let _ = inner.next(); // constant keyword
+                let name = inner.next().unwrap().as_str().to_owned();
+                let value = self.eval(inner.next().unwrap().into_inner())?;
+                self.constants.insert(name, value);
+            }
+            Rule::namespace => {
+                let _ = inner.next(); // namespace keyword
+                self.namespace = inner.next().unwrap().as_str().to_owned();
+            }
+            Rule::pol_commit | Rule::pol_constant => self.declare(inner)?,
+            Rule::pol_alias => {
+                let _ = inner.next(); // pol keyword
+                let handle = self.handle(inner.next().unwrap().as_str());
+                if self.columns.contains_key(&handle) || self.aliases.contains_key(&handle) {
+                    bail!("{} is declared twice", handle.pretty())
+                }
+                let expr = self.expression(inner.next().unwrap().into_inner())?;
+                self.aliases.insert(handle, expr);
+            }
+            Rule::identity => {
+                let lhs = self.expression(inner.next().unwrap().into_inner())?;
+                let rhs = self.expression(inner.next().unwrap().into_inner())?;
+                self.builder.vanishes(
+                    Handle::new(&self.namespace, format!("identity-{}-{}", line, column)),
+                    None,
+                    Intrinsic::Sub.call(&[lhs, rhs])?,
+                );
+            }
+            Rule::lookup => {
+                let included = self.selection(inner.next().unwrap())?;
+                let _ = inner.next(); // in keyword
+                let including = self.selection(inner.next().unwrap())?;
+                if included.len() != including.len() {
+                    bail!(
+                        "{} looked up expressions, but {} in the target",
+                        included.len(),
+                        including.len()
+                    )
+                }
+                self.builder.lookup(
+                    Handle::new(&self.namespace, format!("lookup-{}-{}", line, column)),
+                    including,
+                    included,
+                );
+            }
+            Rule::permutation => bail!("permutation arguments are not supported"),
+            Rule::EOI => {}
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+}
+
+/// Translate the PIL description `source` into a [`ConstraintSet`]
+pub fn from_pil(source: &str) -> Result<ConstraintSet> {
+    let mut importer = Importer::new();
+    let pil = PilParser::parse(Rule::pil, source)
+        .map_err(|e| anyhow!("{}", e))?
+        .next()
+        .unwrap();
+    for statement in pil.into_inner() {
+        let line = statement.line_col().0;
+        importer
+            .statement(statement)
+            .with_context(|| anyhow!("at line {}", line.to_string().bold()))?;
+    }
+    importer.builder.build()
+}
@@ -1,13 +1,12 @@
-use super::{generator::Function, ColumnRef, Expression, Magma, Node, Type};
+use super::{generator::Function, parser::LinCol, ColumnRef, Expression, Magma, Node, Type};
 use crate::{
     column::Computation,
     compiler::{generator::FunctionClass, Builtin, Form, Intrinsic},
     errors::symbols,
-    structs::{Handle, PERSPECTIVE_SEPARATOR},
+    structs::{Handle, Span, PERSPECTIVE_SEPARATOR},
 };
 use anyhow::*;
 use itertools::Itertools;
-use log::*;
 use num_bigint::BigInt;
 use num_traits::{One, Zero};
 use owo_colors::OwoColorize;
@@ -44,6 +43,10 @@ lazy_static::lazy_static! {
             handle: Handle::new(super::MAIN_MODULE, "reduce"),
             class: FunctionClass::Form(Form::Reduce)
         },
+        "cast" => Function {
+            handle: Handle::new(super::MAIN_MODULE, "cast"),
+            class: FunctionClass::Form(Form::Cast)
+        },
 
         // Builtin functions
         "len" => Function {
@@ -92,6 +95,14 @@ lazy_static::lazy_static! {
             handle: Handle::new(super::MAIN_MODULE, "~"),
             class: FunctionClass::Intrinsic(Intrinsic::Normalize)
         },
+        "sgn" => Function {
+            handle: Handle::new(super::MAIN_MODULE, "sgn"),
+            class: FunctionClass::Intrinsic(Intrinsic::Sgn)
+        },
+        "bits-of" => Function {
+            handle: Handle::new(super::MAIN_MODULE, "bits-of"),
+            class: FunctionClass::Intrinsic(Intrinsic::Bits)
+        },
         "neg" => Function {
             handle: Handle::new(super::MAIN_MODULE, "neg"),
             class: FunctionClass::Intrinsic(Intrinsic::Neg)
@@ -195,8 +206,61 @@ pub enum Symbol {
 pub struct GlobalData {
     computations: ComputationTable,
     pub perspectives: HashMap<String, HashMap<String, Option<Node>>>, // module -> {Perspectives}
+    /// for each perspective name, the first module declaring it and whether it
+    /// is shared across modules
+    pub perspectives_sharing: HashMap<String, (String, bool)>,
+    /// the group, if any, of every column
+    pub groups: HashMap<Handle, String>,
+    /// the documentation of the columns
+    pub column_docs: HashMap<Handle, String>,
+    /// the expressions computing the padding value of some columns
+    pub padding_exprs: HashMap<Handle, Node>,
+    /// the documentation of the constraints
+    pub constraint_docs: HashMap<Handle, String>,
+    /// the constraints declared as assertions
+    pub assertions: HashSet<Handle>,
+    /// the source file currently being processed
+    pub source: String,
+    /// where the columns have been defined
+    pub column_spans: HashMap<Handle, Span>,
+    /// where the constraints have been defined
+    pub constraint_spans: HashMap<Handle, Span>,
+    /// the modules that must always have a given number of rows
+    pub module_lengths: HashMap<String, usize>,
 }
 impl GlobalData {
+    /// Ensure that all the modules declaring a perspective agree on whether it
+    /// is shared.
+    fn declare_perspective(&mut self, module: &str, perspective: &str, shared: bool) -> Result<()> {
+        let (first_module, first_shared) = self
+            .perspectives_sharing
+            .entry(perspective.to_owned())
+            .or_insert_with(|| (module.to_owned(), shared));
+        if *first_shared != shared {
+            let (shared_in, private_in) = if shared {
+                (module, first_module.as_str())
+            } else {
+                (first_module.as_str(), module)
+            };
+            bail!(
+                "perspective {} is shared in module {}, but not in module {}",
+                perspective.red(),
+                shared_in.blue(),
+                private_in.blue()
+            )
+        }
+        Ok(())
+    }
+
+    /// The perspectives shared by several modules
+    pub fn shared_perspectives(&self) -> HashSet<String> {
+        self.perspectives_sharing
+            .iter()
+            .filter(|(_, (_, shared))| *shared)
+            .map(|(name, _)| name.to_owned())
+            .collect()
+    }
+
     pub fn set_perspective_trigger(
         &mut self,
         module: &str,
@@ -381,8 +445,12 @@ impl Scope {
         self
     }
 
-    pub fn with_perspective(self, perspective: &str) -> Result<Self> {
+    pub fn with_perspective(self, perspective: &str, shared: bool) -> Result<Self> {
         let module = self.module();
+        self.tree
+            .borrow_mut()
+            .metadata_mut()
+            .declare_perspective(&module, perspective, shared)?;
         let perspective_already_exists = self
             .tree
             .borrow_mut()
@@ -417,6 +485,125 @@ impl Scope {
         data!(self).perspective.clone()
     }
 
+    pub fn set_group(&self, column: Handle, group: &str) {
+        self.tree
+            .borrow_mut()
+            .metadata_mut()
+            .groups
+            .insert(column, group.to_owned());
+    }
+
+    pub fn groups(&self) -> HashMap<Handle, String> {
+        self.tree.borrow().metadata().groups.clone()
+    }
+
+    pub fn set_column_doc(&self, column: Handle, doc: &str) {
+        self.tree
+            .borrow_mut()
+            .metadata_mut()
+            .column_docs
+            .insert(column, doc.to_owned());
+    }
+
+    pub fn column_docs(&self) -> HashMap<Handle, String> {
+        self.tree.borrow().metadata().column_docs.clone()
+    }
+
+    pub fn set_padding_expr(&self, column: Handle, expr: Node) {
+        self.tree
+            .borrow_mut()
+            .metadata_mut()
+            .padding_exprs
+            .insert(column, expr);
+    }
+
+    pub fn padding_exprs(&self) -> HashMap<Handle, Node> {
+        self.tree.borrow().metadata().padding_exprs.clone()
+    }
+
+    pub fn set_constraint_doc(&self, constraint: Handle, doc: &str) {
+        self.tree
+            .borrow_mut()
+            .metadata_mut()
+            .constraint_docs
+            .insert(constraint, doc.to_owned());
+    }
+
+    pub fn constraint_docs(&self) -> HashMap<Handle, String> {
+        self.tree.borrow().metadata().constraint_docs.clone()
+    }
+
+    /// Set the name of the source file whose definitions are processed next
+    pub fn set_source(&self, name: &str) {
+        self.tree.borrow_mut().metadata_mut().source = name.to_owned();
+    }
+
+    fn span(&self, lc: LinCol) -> Span {
+        Span::new(&self.tree.borrow().metadata().source, lc)
+    }
+
+    pub fn set_column_span(&self, column: Handle, lc: LinCol) {
+        let span = self.span(lc);
+        self.tree
+            .borrow_mut()
+            .metadata_mut()
+            .column_spans
+            .insert(column, span);
+    }
+
+    pub fn column_spans(&self) -> HashMap<Handle, Span> {
+        self.tree.borrow().metadata().column_spans.clone()
+    }
+
+    pub fn set_constraint_span(&self, constraint: Handle, lc: LinCol) {
+        let span = self.span(lc);
+        self.tree
+            .borrow_mut()
+            .metadata_mut()
+            .constraint_spans
+            .insert(constraint, span);
+    }
+
+    pub fn constraint_spans(&self) -> HashMap<Handle, Span> {
+        self.tree.borrow().metadata().constraint_spans.clone()
+    }
+
+    pub fn set_assertion(&self, constraint: Handle) {
+        self.tree
+            .borrow_mut()
+            .metadata_mut()
+            .assertions
+            .insert(constraint);
+    }
+
+    pub fn assertions(&self) -> HashSet<Handle> {
+        self.tree.borrow().metadata().assertions.clone()
+    }
+
+    /// Fix the number of rows of `module`; it may be set several times, e.g.
+    /// if the module is spread over several files, as long as it does not
+    /// change.
+    pub fn set_module_length(&self, module: &str, length: usize) -> Result<()> {
+        let mut tree = self.tree.borrow_mut();
+        match tree
+            .metadata_mut()
+            .module_lengths
+            .insert(module.to_owned(), length)
+        {
+            Some(previous) if previous != length => bail!(
+                "module {} is declared with lengths {} and {}",
+                module.blue(),
+                previous.to_string().red(),
+                length.to_string().red()
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn module_lengths(&self) -> HashMap<String, usize> {
+        self.tree.borrow().metadata().module_lengths.clone()
+    }
+
     pub fn computations(&self) -> ComputationTable {
         self.tree.borrow().metadata().computations.clone()
     }
@@ -682,7 +869,7 @@ impl Scope {
 
     pub fn insert_constraint(&mut self, name: &str) -> Result<()> {
         if data!(self).constraints.contains(name) {
-            warn!("redefining constraint `{}`", name.yellow());
+            super::warning(format!("redefining constraint `{}`", name.yellow()));
         }
         if data_mut!(self).constraints.insert(name.to_owned()) {
             Ok(())
@@ -816,7 +1003,7 @@ impl Scope {
             data_mut!(self).symbols.insert(
                 name.to_owned(),
                 Symbol::Final(
-                    Node::from_expr(Expression::Const(value.try_into().unwrap())).with_type(t),
+                    Node::from_expr(Expression::Const(value.try_into()?)).with_type(t),
                     false,
                 ),
             );
@@ -828,6 +1015,25 @@ impl Scope {
         self.at(self.tree.borrow().root())
     }
 
+    /// Whether `path`, i.e. a name prefixed by its module unless it belongs
+    /// to the root module, is defined in a module of this tree
+    pub(crate) fn defines(&self, path: &str) -> bool {
+        let mut components = path.split('.').collect::<Vec<_>>();
+        let name = components.pop().unwrap();
+        let mut scope = self.root();
+        for module in components {
+            match scope
+                .children()
+                .into_iter()
+                .find(|c| data!(c).name == module)
+            {
+                Some(child) => scope = child,
+                None => return false,
+            }
+        }
+        data!(scope).symbols.contains_key(name)
+    }
+
     #[allow(dead_code)]
     pub fn print(&self) {
         self.tree.borrow().print(|s| {
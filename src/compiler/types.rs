@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 use anyhow::*;
+use num_bigint::BigInt;
+use num_traits::One;
 use owo_colors::OwoColorize;
 use serde::{Deserialize, Serialize};
 use std::{cmp::Ordering, sync::OnceLock};
@@ -179,6 +181,15 @@ impl Type {
     pub(crate) fn maxed(&self, other: &Type) -> Result<Type> {
         Ok(self.max(other).with_magma(self.m().maxed(&other.m())?))
     }
+
+    /// The type of an expression whose value may be either of `self` or
+    /// `other`, conditioned as the largest of the two
+    pub(crate) fn joined(&self, other: &Type) -> Type {
+        match (self, other) {
+            (Type::Void, t) | (t, Type::Void) => *t,
+            _ => self.max(other).with_magma(self.m().joined(&other.m())),
+        }
+    }
 }
 impl std::cmp::PartialOrd for Type {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
@@ -308,6 +319,9 @@ pub enum RawMagma {
     Native,
     /// an arbitrary long integer
     Integer(usize),
+    /// an arbitrary long signed integer, i.e. in [-2^(n-1), 2^(n-1)); negative
+    /// values are stored as their field opposite
+    Signed(usize),
     /// Anything
     Any,
 }
@@ -319,14 +333,37 @@ impl RawMagma {
             RawMagma::Nibble => 4,
             RawMagma::Byte => 8,
             RawMagma::Native => crate::constants::FIELD_BITSIZE,
-            RawMagma::Integer(x) => *x,
+            RawMagma::Integer(x) | RawMagma::Signed(x) => *x,
             RawMagma::Any => crate::constants::FIELD_BITSIZE,
         }
     }
 
     fn byte_size(&self) -> usize {
         let bit_size = self.bit_size();
-        (bit_size + 8 - 1) / 8
+        bit_size.div_ceil(8)
+    }
+
+    /// The smallest magma holding the values of both `self` and `other`; mixing
+    /// signed and unsigned magmas requires a sign bit on top of the unsigned
+    /// one, e.g. s8 ∨ i16 = s17.
+    pub fn join(self, other: RawMagma) -> RawMagma {
+        let is_unsigned = |m: RawMagma| {
+            matches!(
+                m,
+                RawMagma::Binary | RawMagma::Nibble | RawMagma::Byte | RawMagma::Integer(_)
+            )
+        };
+        match (self, other) {
+            (RawMagma::Signed(s), u) | (u, RawMagma::Signed(s)) if is_unsigned(u) => {
+                let bits = s.max(u.bit_size() + 1);
+                if bits > constants::FIELD_BITSIZE {
+                    RawMagma::Native
+                } else {
+                    RawMagma::Signed(bits)
+                }
+            }
+            _ => Magma::from(self).max(Magma::from(other)).m,
+        }
     }
 
     pub fn validate(&self, x: Value) -> Result<Value> {
@@ -369,6 +406,19 @@ impl RawMagma {
                     Ok(x)
                 }
             }
+            RawMagma::Signed(b) => {
+                // values may be given either signed, or in their b-bits
+                // two's-complement encoding
+                let half = BigInt::one() << (b - 1);
+                let v = x.to_signed_bi()?;
+                if v >= -half.clone() && v < half {
+                    Ok(x)
+                } else if v >= half && v < (half.clone() << 1) {
+                    Ok(x.signed_like(v - (half << 1)))
+                } else {
+                    bail!(RuntimeError::InvalidValue("signed integer", x))
+                }
+            }
             RawMagma::Any => unreachable!(),
         }
     }
@@ -453,7 +503,7 @@ impl Magma {
     }
 
     pub fn byte_size(&self) -> usize {
-        (self.m.bit_size() + 7) / 8
+        self.m.bit_size().div_ceil(8)
     }
 
     pub fn invert(&self) -> Magma {
@@ -494,6 +544,17 @@ impl Magma {
             (RawMagma::Native, RawMagma::Native) => true,
             (RawMagma::Native, RawMagma::Any) => true,
 
+            // unsigned magmas can not hold negative values
+            (RawMagma::Signed(_), RawMagma::None)
+            | (RawMagma::Signed(_), RawMagma::Binary)
+            | (RawMagma::Signed(_), RawMagma::Nibble)
+            | (RawMagma::Signed(_), RawMagma::Byte)
+            | (RawMagma::Signed(_), RawMagma::Integer(_)) => false,
+            (RawMagma::Signed(x), RawMagma::Signed(y)) => x <= y,
+            (RawMagma::Signed(_), RawMagma::Native) | (RawMagma::Signed(_), RawMagma::Any) => true,
+            (RawMagma::Native, RawMagma::Signed(_)) => false,
+            (_, RawMagma::Signed(y)) => self.m.bit_size() < y,
+
             (RawMagma::Integer(_), RawMagma::None) => false,
             (RawMagma::Integer(x), RawMagma::Binary) => x == 1,
             (RawMagma::Integer(_), RawMagma::Nibble)
@@ -547,22 +608,33 @@ impl Magma {
     pub fn integer(b: usize) -> Magma {
         RawMagma::Integer(b).into()
     }
+    pub fn signed(b: usize) -> Magma {
+        RawMagma::Signed(b).into()
+    }
     pub fn any() -> Magma {
         RawMagma::Any.into()
     }
     pub(crate) fn maxed(&self, other: &Magma) -> Result<Magma> {
         Ok(Magma {
-            m: self.m.max(other.m),
+            m: self.m.join(other.m),
             c: self.c.max(&other.c)?,
         })
     }
+    /// The smallest magma holding both `self` and `other`, conditioned as the
+    /// largest of the two
+    pub(crate) fn joined(&self, other: &Magma) -> Magma {
+        Magma {
+            m: self.m.join(other.m),
+            c: (*self).max(*other).c,
+        }
+    }
 }
 impl std::convert::TryFrom<&str> for Magma {
     type Error = anyhow::Error;
 
     fn try_from(s: &str) -> Result<Self, Self::Error> {
         let re_global = regex_lite::Regex::new(
-            r":(?<RawMagma>i(?<Integer>\d+)|[a-z]+)?(@(?<Conditioning>bool|loob))?",
+            r":(?<RawMagma>i(?<Integer>\d+)|s(?<Signed>\d+)|[a-z]+)?(@(?<Conditioning>bool|loob))?",
         )?;
 
         if let Some(caps) = re_global.captures(s) {
@@ -576,6 +648,12 @@ impl std::convert::TryFrom<&str> for Magma {
                     panic!("Not yet :(");
                 }
                 RawMagma::Integer(bit_size)
+            } else if let Some(signed) = caps.name("Signed") {
+                let bit_size = signed.as_str().parse::<usize>().unwrap();
+                if !(2..=constants::FIELD_BITSIZE).contains(&bit_size) {
+                    bail!("invalid signed integer size: {}", s.bold().red())
+                }
+                RawMagma::Signed(bit_size)
             } else {
                 caps.name("RawMagma")
                     .map_or(Ok(RawMagma::Native), |s| s.as_str().try_into())?
@@ -601,6 +679,7 @@ impl std::cmp::Ord for Magma {
 }
 /// The ordering relation defines the casting rules for inter-magmas operations
 /// e.g. boolean × boolean = boolean, but boolean × integer = Integer
+#[allow(clippy::non_canonical_partial_ord_impl)]
 impl std::cmp::PartialOrd for Magma {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match (self.m, other.m) {
@@ -608,6 +687,29 @@ impl std::cmp::PartialOrd for Magma {
             (RawMagma::None, _) => Some(Ordering::Less),
             (_, RawMagma::None) => Some(Ordering::Greater),
 
+            (RawMagma::Any, RawMagma::Any) => Some(Ordering::Equal),
+            (RawMagma::Any, _) => Some(Ordering::Greater),
+            (_, RawMagma::Any) => Some(Ordering::Less),
+
+            (RawMagma::Signed(_), RawMagma::Native) | (RawMagma::Native, RawMagma::Signed(_)) => {
+                Some(self.m.bit_size().cmp(&other.m.bit_size()))
+            }
+            (RawMagma::Signed(_), RawMagma::Signed(_)) => {
+                Some(self.m.bit_size().cmp(&other.m.bit_size()))
+            }
+            // a signed magma only holds the unsigned ones with less bits, e.g.
+            // i8 < s9, but s8 < i8; see [`RawMagma::join`] for their union
+            (RawMagma::Signed(x), _) => Some(if x > other.m.bit_size() {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            }),
+            (_, RawMagma::Signed(y)) => Some(if self.m.bit_size() < y {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }),
+
             (RawMagma::Binary, RawMagma::Binary) => Some(Ordering::Equal),
             (RawMagma::Binary, RawMagma::Nibble) => Some(Ordering::Less),
             (RawMagma::Binary, RawMagma::Byte) => Some(Ordering::Less),
@@ -634,10 +736,6 @@ impl std::cmp::PartialOrd for Magma {
                 Some(self.m.bit_size().cmp(&other.m.bit_size()))
             }
 
-            (RawMagma::Any, RawMagma::Any) => Some(Ordering::Equal),
-            (RawMagma::Any, _) => Some(Ordering::Greater),
-            (_, RawMagma::Any) => Some(Ordering::Less),
-
             // The following are assuming that field elements will always be larger than 8 bits
             (RawMagma::Integer(_), RawMagma::Binary) => Some(Ordering::Greater),
             (RawMagma::Integer(_), RawMagma::Nibble) => Some(Ordering::Greater),
@@ -660,6 +758,7 @@ impl std::fmt::Display for Magma {
             RawMagma::Byte => write!(f, "𝟠"),
             RawMagma::Native => write!(f, "𝔽"),
             RawMagma::Integer(x) => write!(f, "i{}", x),
+            RawMagma::Signed(x) => write!(f, "s{}", x),
             RawMagma::Any => write!(f, "∀"),
         }?;
 
@@ -2,18 +2,25 @@ use anyhow::{anyhow, bail, Context, Result};
 use itertools::Itertools;
 use log::*;
 use logging_timer::time;
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
 use owo_colors::OwoColorize;
-use rayon::prelude::*;
-use std::{cmp::Ordering, collections::HashSet};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    io::Write,
+};
 
 use crate::{
     column::{ColumnSet, Computation, ExoOperation, Value, ValueBacking},
-    compiler::{ColumnRef, ConstraintSet, EvalSettings, Kind, Node},
+    compiler::{ColumnRef, Constraint, ConstraintSet, EvalSettings, Kind, Node},
     dag::ComputationDag,
     errors::RuntimeError,
     import,
     pretty::Pretty,
+    provenance, run_stats,
     structs::Handle,
+    utils::human_size,
 };
 
 /// Given a set of operation and their arguments, generate the traces required
@@ -126,32 +133,93 @@ fn compute_ancillaries(
     Ok(())
 }
 
-#[time("info", "Computing expanded columns")]
-fn compute_all(cs: &mut ConstraintSet) -> Result<()> {
+/// The indices of the computations that must be run for `constraints` to be
+/// checked, i.e. those filling the columns they transitively depend on.
+fn required_computations<'a>(
+    cs: &ConstraintSet,
+    constraints: impl Iterator<Item = &'a Constraint>,
+) -> HashSet<usize> {
+    let mut todo = constraints
+        .flat_map(|c| c.dependencies())
+        .collect::<Vec<_>>();
+    let mut seen = HashSet::new();
+    let mut required = HashSet::new();
+    while let Some(h) = todo.pop() {
+        if !seen.insert(h.clone()) {
+            continue;
+        }
+        if let Some(i) = cs.computations.computation_idx_for(&h) {
+            if required.insert(i) {
+                todo.extend(cs.computations.get(i).unwrap().sources());
+            }
+        }
+    }
+    required
+}
+
+/// Run, slice after slice, the computations whose index is in `required`,
+/// or all of them if it is not set. If `skip_others` is set, the columns
+/// filled by the other ones are marked as skipped.
+fn run_computations(
+    cs: &mut ConstraintSet,
+    required: Option<&HashSet<usize>>,
+    skip_others: bool,
+    exo_operations: &mut HashSet<(ExoOperation, Value, Value)>,
+) -> Result<()> {
     // Computations are split in sequentially dependent sets, where each set as
     // to be completely computed before the next one is started, but all
     // computations within a set can be processed in parallel
-    let jobs = ComputationDag::from_computations(cs.computations.iter());
-
-    let mut exo_operations = HashSet::new();
+    let jobs = if skip_others {
+        ComputationDag::from_computations(cs.computations.iter())
+    } else {
+        ComputationDag::from_computations(
+            cs.computations
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| required.map(|r| r.contains(i)).unwrap_or(true))
+                .map(|(_, c)| c),
+        )
+    };
 
     for processing_slice in jobs.job_slices() {
+        crate::utils::ensure_deadline("computing columns")?;
+        crate::utils::ensure_not_cancelled("computing columns")?;
         trace!(
             "Processing computation slice {}",
             processing_slice.iter().join(" ")
         );
-        let comps = processing_slice
+        let (todo, skipped): (Vec<_>, Vec<_>) = processing_slice
             .iter()
             .filter_map(|h| cs.computations.computation_idx_for(h))
             .collect::<HashSet<_>>()
+            .into_iter()
+            .partition(|i| required.map(|r| r.contains(i)).unwrap_or(true));
+        let comps = todo
             .iter()
             .map(|i| cs.computations.get(*i).unwrap().to_owned())
             .collect::<Vec<_>>();
+        let mut estimated = HashMap::new();
+        for comp in comps.iter() {
+            for (module, size) in estimated_memory(cs, comp) {
+                *estimated.entry(module).or_default() += size;
+            }
+        }
+        ensure_memory_estimate(cs, estimated, "computing columns")?;
+        if skip_others {
+            for i in skipped {
+                for target in cs.computations.get(i).unwrap().targets() {
+                    if !cs.columns.is_computed(target) {
+                        trace!("Skipping {}", target.pretty());
+                        cs.columns.mark_skipped(target)?;
+                    }
+                }
+            }
+        }
 
         for r in comps
             .iter()
             // .into_par_iter() // TODO: is that a bottleneck?
-            .filter_map(|comp| apply_computation(cs, comp, &mut exo_operations))
+            .filter_map(|comp| apply_computation(cs, comp, exo_operations))
             .collect::<Vec<_>>()
             .into_iter()
         {
@@ -167,13 +235,99 @@ fn compute_all(cs: &mut ConstraintSet) -> Result<()> {
                 Err(e) => warn!("{}", e),
             }
         }
+        ensure_memory_budget(cs, "computing columns")?;
     }
 
+    Ok(())
+}
+
+/// Fill the computed columns; if `required` is set, only the computations it
+/// contains are run, and the columns filled by the other ones are marked as
+/// skipped.
+#[time("info", "Computing expanded columns")]
+fn compute_all(cs: &mut ConstraintSet, required: Option<&HashSet<usize>>) -> Result<()> {
+    let mut exo_operations = HashSet::new();
+    run_computations(cs, required, true, &mut exo_operations)?;
+
+    crate::utils::ensure_deadline("computing columns")?;
     compute_ancillaries(cs, exo_operations)?;
+    ensure_memory_budget(cs, "computing ancillary columns")?;
 
     Ok(())
 }
 
+/// Fills the computed columns of a [`ConstraintSet`] on demand, i.e. only
+/// once a constraint depending on them is about to be checked. The
+/// computations already run are remembered, so that they are never run twice.
+#[derive(Default)]
+pub struct LazyComputer {
+    /// the indices of the computations already run
+    done: HashSet<usize>,
+    /// the exogenous operations met so far, from which the ancillary columns
+    /// are derived
+    exo_operations: HashSet<(ExoOperation, Value, Value)>,
+}
+impl LazyComputer {
+    /// The computations still to be run before `c` can be checked; those
+    /// whose targets have already been filled, e.g. by a previous check, are
+    /// not run again.
+    pub fn pending_for(&self, cs: &ConstraintSet, c: &Constraint) -> HashSet<usize> {
+        required_computations(cs, std::iter::once(c))
+            .into_iter()
+            .filter(|i| !self.done.contains(i))
+            .filter(|i| {
+                !cs.computations
+                    .get(*i)
+                    .unwrap()
+                    .targets()
+                    .into_iter()
+                    .all(|t| cs.columns.is_computed(t))
+            })
+            .collect()
+    }
+
+    /// Whether all the computations in `computations` have already been run
+    pub fn is_done(&self, computations: &HashSet<usize>) -> bool {
+        computations.is_subset(&self.done)
+    }
+
+    /// Run the computations `c` depends on that have not been run yet
+    pub fn materialize(&mut self, cs: &mut ConstraintSet, c: &Constraint) -> Result<()> {
+        let todo = self.pending_for(cs, c);
+        if todo.is_empty() {
+            return Ok(());
+        }
+        debug!(
+            "computing {} column group(s) required by {}",
+            todo.len(),
+            c.name()
+        );
+        let _phase = run_stats::phase("compute");
+        let exo_count = self.exo_operations.len();
+        run_computations(cs, Some(&todo), false, &mut self.exo_operations)
+            .with_context(|| anyhow!("while computing the columns required by {}", c.name()))?;
+        self.done.extend(todo);
+        // The ancillary columns are rebuilt from all the operations met so far
+        if self.exo_operations.len() > exo_count {
+            compute_ancillaries(cs, self.exo_operations.clone())?;
+            ensure_memory_budget(cs, "computing ancillary columns")?;
+        }
+        Ok(())
+    }
+
+    /// The number of computations that have not been run, as no constraint
+    /// required them
+    pub fn skipped(&self, cs: &ConstraintSet) -> usize {
+        cs.computations
+            .iter()
+            .enumerate()
+            .filter(|(i, c)| {
+                !self.done.contains(i) && !c.targets().iter().all(|t| cs.columns.is_computed(t))
+            })
+            .count()
+    }
+}
+
 fn ensure_is_computed(h: &ColumnRef, cs: &ConstraintSet) -> Result<()> {
     if !cs.columns.is_computed(h) {
         bail!(err_missing_column(cs.columns.column(h).unwrap()))
@@ -221,30 +375,54 @@ fn compute_sorted(
     froms: &[ColumnRef],
     tos: &[ColumnRef],
     signs: &[bool],
+    stable: bool,
+    tiebreaks: &[ColumnRef],
+    comp: &Computation,
 ) -> Result<Vec<ComputedColumn>> {
     let spilling = cs.spilling_for_column(&froms[0]).unwrap();
-    for from in froms.iter() {
+    for from in froms.iter().chain(tiebreaks.iter()) {
         ensure_is_computed(from, cs)?;
     }
 
     if !froms
-        .windows(2)
-        .all(|w| cs.columns.padded_len(&w[0]) == cs.columns.padded_len(&w[1]))
+        .iter()
+        .chain(tiebreaks.iter())
+        .tuple_windows()
+        .all(|(a, b)| cs.columns.padded_len(a) == cs.columns.padded_len(b))
     {
         bail!("sorted columns are of incoherent lengths")
     }
     let len = cs.columns.len(&froms[0]).unwrap();
 
-    let mut sorted_is = (0..len).collect::<Vec<_>>();
-    sorted_is.sort_by(|i, j| {
-        for (sign, from) in signs.iter().zip(froms.iter()) {
+    // Rows are ordered by the sorting columns, i.e. the ones with a sign, then
+    // by the tie-breaking columns, then, if the sort is stable, by their
+    // original position
+    let keys = signs
+        .iter()
+        .zip(froms.iter())
+        .chain(tiebreaks.iter().map(|t| (&true, t)))
+        .collect::<Vec<_>>();
+    let cmp = |i: &usize, j: &usize| {
+        for (sign, from) in keys.iter() {
             let x_i = cs.columns.get(from, *i as isize, false).unwrap();
             let x_j = cs.columns.get(from, *j as isize, false).unwrap();
             if let x @ (Ordering::Greater | Ordering::Less) = x_i.cmp(&x_j) {
-                return if *sign { x } else { x.reverse() };
+                return if **sign { x } else { x.reverse() };
             }
         }
         Ordering::Equal
+    };
+    let mut sorted_is = (0..len).collect::<Vec<_>>();
+    if stable {
+        sorted_is.sort_unstable_by(|i, j| cmp(i, j).then(i.cmp(j)));
+    } else {
+        sorted_is.sort_unstable_by(cmp);
+    }
+    // the permutation is only known here, so the sorted columns record their
+    // provenance themselves
+    provenance::record(cs, comp, tos, len, |to, i| {
+        let k = tos.iter().position(|t| t == to).unwrap();
+        vec![(froms[k].clone(), sorted_is[i as usize] as isize)]
     });
 
     Ok(froms
@@ -543,12 +721,70 @@ fn compute_sorting_auxs(cs: &ConstraintSet, comp: &Computation) -> Result<Vec<Co
     }
 }
 
+/// Record, if required, the cells read to fill the columns `computed` by
+/// `computation`
+fn record_provenance(cs: &ConstraintSet, computation: &Computation, computed: &[ComputedColumn]) {
+    if !provenance::is_armed() {
+        return;
+    }
+    let Some(len) = computed.first().map(|(_, backing)| backing.len()) else {
+        return;
+    };
+    let targets = computed.iter().map(|(h, _)| h.clone()).collect::<Vec<_>>();
+    match computation {
+        Computation::Composite { exp, .. } => {
+            provenance::record(cs, computation, &targets, len, |_, i| {
+                provenance::expression_inputs(exp, i)
+            })
+        }
+        Computation::ExoOperation { sources, .. } => {
+            provenance::record(cs, computation, &targets, len, |_, i| {
+                sources
+                    .iter()
+                    .flat_map(|s| provenance::expression_inputs(s, i))
+                    .collect()
+            })
+        }
+        Computation::Interleaved { froms, .. } => {
+            provenance::record(cs, computation, &targets, len, |_, k| {
+                let count = froms.len() as isize;
+                vec![(froms[(k % count) as usize].clone(), k / count)]
+            })
+        }
+        // recorded by compute_sorted, the only one knowing the permutation
+        Computation::Sorted { .. } => {}
+        Computation::ExoConstant { .. } | Computation::CyclicFrom { .. } => {
+            provenance::record(cs, computation, &targets, len, |_, _| Vec::new())
+        }
+        Computation::SortingConstraints { sorted, .. } => {
+            provenance::record(cs, computation, &targets, len, |_, i| {
+                sorted
+                    .iter()
+                    .flat_map(|s| [(s.clone(), i - 1), (s.clone(), i)])
+                    .collect()
+            })
+        }
+    }
+}
+
 pub fn apply_computation(
     cs: &ConstraintSet,
     computation: &Computation,
     exo_operations: &mut HashSet<(ExoOperation, Value, Value)>,
 ) -> Option<Result<Vec<ComputedColumn>>> {
     trace!("Computing {}", computation.pretty_target());
+    let r = run_computation(cs, computation, exo_operations);
+    if let Some(Ok(computed)) = r.as_ref() {
+        record_provenance(cs, computation, computed);
+    }
+    r
+}
+
+fn run_computation(
+    cs: &ConstraintSet,
+    computation: &Computation,
+    exo_operations: &mut HashSet<(ExoOperation, Value, Value)>,
+) -> Option<Result<Vec<ComputedColumn>>> {
     match computation {
         Computation::Composite { target, exp } => {
             if !cs.columns.is_computed(target) {
@@ -564,9 +800,23 @@ pub fn apply_computation(
                 None
             }
         }
-        Computation::Sorted { froms, tos, signs } => {
+        Computation::Sorted {
+            froms,
+            tos,
+            signs,
+            stable,
+            tiebreaks,
+        } => {
             if !cs.columns.is_computed(&tos[0]) {
-                Some(compute_sorted(cs, froms, tos, signs))
+                Some(compute_sorted(
+                    cs,
+                    froms,
+                    tos,
+                    signs,
+                    *stable,
+                    tiebreaks,
+                    computation,
+                ))
             } else {
                 None
             }
@@ -613,6 +863,505 @@ pub fn apply_computation(
     }
 }
 
+/// Compare two values irrespective of their internal representation
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::ExoNative(_), _) | (_, Value::ExoNative(_)) => a.to_string() == b.to_string(),
+        _ => a.to_bi() == b.to_bi(),
+    }
+}
+
+/// The row of the trace the `i`th row of `c` comes from, i.e. `i` without the
+/// padding rows prepended to `c` on import; computed columns are aligned on
+/// the imported columns of their module.
+fn trace_row(cs: &ConstraintSet, c: &ColumnRef, i: isize) -> isize {
+    let len = cs.columns.len(c).unwrap_or(0);
+    let module = cs.columns.module_of(c);
+    cs.columns
+        .trace_len(c)
+        .or_else(|| {
+            cs.columns
+                .iter_module(&module)
+                .filter(|(h, _)| cs.columns.len(h) == Some(len))
+                .find_map(|(h, _)| cs.columns.trace_len(&h))
+        })
+        .map(|trace_len| i - len.saturating_sub(trace_len) as isize)
+        .unwrap_or(i)
+}
+
+fn ensure_same(
+    cs: &ConstraintSet,
+    target: &ColumnRef,
+    i: isize,
+    found: Option<Value>,
+    expected: &Value,
+) -> Result<()> {
+    match found {
+        Some(found) if same_value(&found, expected) => Ok(()),
+        found => bail!(
+            "{}[{}] = {}, expected {}",
+            target.pretty(),
+            trace_row(cs, target, i),
+            found
+                .map(|f| f.to_string())
+                .unwrap_or_else(|| "nil".into())
+                .red(),
+            expected.to_string().green()
+        ),
+    }
+}
+
+/// Ensure that the values filled by `computation` satisfy its defining
+/// property, and report the first row where they do not.
+fn verify_computation(cs: &ConstraintSet, computation: &Computation) -> Result<()> {
+    let getter = |handle: &ColumnRef, j, _| {
+        cs.columns.get(handle, j, false).or_else(|| {
+            cs.columns
+                .column(handle)
+                .unwrap()
+                .padding_value
+                .as_ref()
+                .cloned()
+        })
+    };
+
+    match computation {
+        Computation::Composite { target, exp } => {
+            let mut cache = Some(cached::SizedCache::with_size(200000));
+            for i in 0..cs.columns.len(target).unwrap_or(0) as isize {
+                if let Some(expected) =
+                    exp.eval(i, getter, &mut cache, &EvalSettings { wrap: false })
+                {
+                    ensure_same(cs, target, i, cs.columns.get(target, i, false), &expected)?;
+                }
+            }
+        }
+        Computation::ExoOperation {
+            op,
+            sources,
+            target,
+        } => {
+            let mut cache = Some(cached::SizedCache::with_size(200000));
+            for i in 0..cs.columns.len(target).unwrap_or(0) as isize {
+                let r1 = sources[0].eval(i, getter, &mut cache, &EvalSettings { wrap: false });
+                let r2 = sources[1].eval(i, getter, &mut cache, &EvalSettings { wrap: false });
+                if let Some((mut r1, r2)) = r1.zip(r2) {
+                    match op {
+                        ExoOperation::Add => r1.add_assign(&r2),
+                        ExoOperation::Sub => r1.sub_assign(&r2),
+                        ExoOperation::Mul => r1.mul_assign(&r2),
+                    }
+                    ensure_same(cs, target, i, cs.columns.get(target, i, false), &r1)?;
+                }
+            }
+        }
+        Computation::ExoConstant { value, target } => {
+            for i in 0..cs.columns.len(target).unwrap_or(0) as isize {
+                ensure_same(cs, target, i, cs.columns.get(target, i, false), value)?;
+            }
+        }
+        Computation::Interleaved { target, froms } => {
+            let count = froms.len();
+            let expected_len = froms
+                .iter()
+                .map(|f| cs.columns.len(f).unwrap_or(0))
+                .sum::<usize>();
+            let len = cs.columns.len(target).unwrap_or(0);
+            if len != expected_len {
+                bail!(
+                    "{} has length {}, expected {}",
+                    target.pretty(),
+                    len,
+                    expected_len
+                )
+            }
+            for k in 0..len {
+                let expected = cs
+                    .columns
+                    .get(&froms[k % count], (k / count) as isize, false)
+                    .unwrap_or_default();
+                ensure_same(
+                    cs,
+                    target,
+                    k as isize,
+                    cs.columns.get(target, k as isize, false),
+                    &expected,
+                )?;
+            }
+        }
+        Computation::Sorted {
+            froms, tos, signs, ..
+        } => {
+            let rows = |cols: &[ColumnRef]| -> Vec<Vec<_>> {
+                let len = cs.columns.len(&cols[0]).unwrap_or(0);
+                (0..len as isize)
+                    .map(|i| {
+                        cols.iter()
+                            .map(|c| cs.columns.get(c, i, false).unwrap_or_default().to_bi())
+                            .collect()
+                    })
+                    .collect()
+            };
+            let from_rows = rows(froms);
+            let to_rows = rows(tos);
+
+            // The sorted columns must be ordered according to the signs...
+            for (i, w) in to_rows.windows(2).enumerate() {
+                for (k, sign) in signs.iter().enumerate() {
+                    match w[0][k].cmp(&w[1][k]) {
+                        Ordering::Equal => continue,
+                        o => {
+                            if (o == Ordering::Less) != *sign {
+                                let i = trace_row(cs, &tos[k], i as isize);
+                                bail!(
+                                    "{} is not sorted between rows {} and {}",
+                                    tos[k].pretty(),
+                                    i,
+                                    i + 1
+                                )
+                            }
+                            break;
+                        }
+                    }
+                }
+            }
+
+            // ...and must be a permutation of their sources
+            if from_rows.into_iter().sorted().collect::<Vec<_>>()
+                != to_rows.into_iter().sorted().collect::<Vec<_>>()
+            {
+                bail!(
+                    "[{}] is not a permutation of [{}]",
+                    tos.iter().map(|c| c.pretty()).join(", "),
+                    froms.iter().map(|c| c.pretty()).join(", ")
+                )
+            }
+        }
+        Computation::CyclicFrom { target, modulo, .. } => {
+            for i in 0..cs.columns.len(target).unwrap_or(0) {
+                ensure_same(
+                    cs,
+                    target,
+                    i as isize,
+                    cs.columns.get(target, i as isize, false),
+                    &(i % modulo).into(),
+                )?;
+            }
+        }
+        Computation::SortingConstraints {
+            ats,
+            eq,
+            delta,
+            delta_bytes,
+            ..
+        } => {
+            for i in 0..cs.columns.len(eq).unwrap_or(0) as isize {
+                // At most one of the @ is set, and only if Eq is not
+                let flags = ats
+                    .iter()
+                    .chain(std::iter::once(eq))
+                    .map(|c| cs.columns.get(c, i, false).unwrap_or_default().to_bi())
+                    .fold(BigInt::zero(), |ax, x| ax + x);
+                if !flags.is_one() {
+                    bail!(
+                        "row {}: [{}] and {} are not mutually exclusive",
+                        trace_row(cs, eq, i),
+                        ats.iter().map(|c| c.pretty()).join(", "),
+                        eq.pretty()
+                    )
+                }
+
+                // Delta is the recomposition of its bytes
+                let recomposed = delta_bytes
+                    .iter()
+                    .rev()
+                    .map(|b| cs.columns.get(b, i, false).unwrap_or_default().to_bi())
+                    .fold(BigInt::zero(), |ax, b| (ax << 8) + b);
+                ensure_same(
+                    cs,
+                    delta,
+                    i,
+                    cs.columns.get(delta, i, false),
+                    &Value::BigInt(recomposed),
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that all the computed columns satisfy the defining property of the
+/// computation that filled them, bailing on the first one that does not.
+#[time("info", "Verifying computations")]
+pub fn verify_computations(cs: &ConstraintSet) -> Result<()> {
+    for computation in cs.computations.iter() {
+        let all_computed = match computation {
+            Computation::Sorted { tos, .. } => cs.columns.is_computed(&tos[0]),
+            Computation::SortingConstraints { eq, .. } => cs.columns.is_computed(eq),
+            Computation::Composite { target, .. }
+            | Computation::ExoOperation { target, .. }
+            | Computation::ExoConstant { target, .. }
+            | Computation::Interleaved { target, .. }
+            | Computation::CyclicFrom { target, .. } => cs.columns.is_computed(target),
+        };
+        if !all_computed {
+            continue;
+        }
+
+        verify_computation(cs, computation).with_context(|| {
+            anyhow!(
+                "computation {} does not hold",
+                computation.to_string().bold().white()
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+/// What to do with the values that a trace provides for columns that a
+/// computation fills
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ComputedInTrace {
+    /// keep the provided values, and only compute the missing columns
+    #[default]
+    Trust,
+    /// refuse the trace
+    Error,
+    /// discard the provided values, and compute the columns as usual
+    Ignore,
+    /// compute the columns, and ensure that they match the provided values
+    Verify,
+}
+
+/// How the modules of an imported trace are padded, on top of their spilling,
+/// e.g. to match the domain sizes expected by a prover
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PaddingStrategy {
+    /// only up to the minimal length of the module, if any
+    #[default]
+    None,
+    /// up to the next power of two
+    PowerOfTwo,
+    /// up to exactly this many rows
+    Length(usize),
+}
+impl PaddingStrategy {
+    /// The number of rows that a module spanning `len` rows, spilling
+    /// included, must be padded to
+    pub fn target(&self, len: usize) -> Result<usize> {
+        match self {
+            PaddingStrategy::None => Ok(len),
+            PaddingStrategy::PowerOfTwo => Ok(len.next_power_of_two()),
+            PaddingStrategy::Length(target) => {
+                if len > *target {
+                    bail!(
+                        "{} rows can not be padded to {}",
+                        len.to_string().red().bold(),
+                        target
+                    )
+                }
+                Ok(*target)
+            }
+        }
+    }
+}
+impl std::str::FromStr for PaddingStrategy {
+    type Err = anyhow::Error;
+
+    /// Parse `none`, `pow2`, a number of rows, or a power of two as `2^k`
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        match s {
+            "none" => Ok(PaddingStrategy::None),
+            "pow2" => Ok(PaddingStrategy::PowerOfTwo),
+            _ => {
+                let len = if let Some(k) = s.strip_prefix("2^") {
+                    k.parse::<u32>().ok().and_then(|k| 1usize.checked_shl(k))
+                } else {
+                    s.parse::<usize>().ok()
+                };
+                len.map(PaddingStrategy::Length).ok_or_else(|| {
+                    anyhow!(
+                        "invalid padding `{}`; expected none, pow2, N or 2^k",
+                        s.red()
+                    )
+                })
+            }
+        }
+    }
+}
+
+/// The padding strategy of every module
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Padding {
+    /// the strategy of the modules without their own
+    pub default: PaddingStrategy,
+    pub modules: BTreeMap<String, PaddingStrategy>,
+}
+impl Padding {
+    pub const fn new() -> Self {
+        Padding {
+            default: PaddingStrategy::None,
+            modules: BTreeMap::new(),
+        }
+    }
+
+    /// Parse a list of `[MODULE=]STRATEGY`, a strategy without module being
+    /// the default one
+    pub fn parse(specs: &[String]) -> Result<Padding> {
+        let mut r = Padding::new();
+        for spec in specs {
+            match spec.split_once('=') {
+                Some((module, strategy)) => {
+                    r.modules
+                        .insert(module.trim().to_owned(), strategy.parse()?);
+                }
+                None => r.default = spec.parse()?,
+            }
+        }
+        Ok(r)
+    }
+
+    pub fn of(&self, module: &str) -> PaddingStrategy {
+        self.modules.get(module).cloned().unwrap_or(self.default)
+    }
+}
+
+/// The number of rows, padding included but not spilling, that the imported
+/// columns of `module` spanning `len` rows must be extended to
+pub(crate) fn padded_len(cs: &ConstraintSet, module: &str, len: usize) -> Result<usize> {
+    let min_len = cs.columns.min_len.get(module).cloned().unwrap_or(0);
+    let spilling = cs.spilling_of(module).unwrap_or(0).max(0) as usize;
+    let target = crate::PADDING
+        .read()
+        .unwrap()
+        .of(module)
+        .target(len + spilling)
+        .with_context(|| anyhow!("while padding {}", module.blue()))?;
+    Ok(target.saturating_sub(spilling).max(min_len))
+}
+
+/// How many of the mismatching rows of a column are reported when verifying
+/// the computed columns provided by a trace
+const MISMATCHES_SHOWN: usize = 5;
+
+/// Remove from `cs` the values that the imported trace provides for columns
+/// that a computation fills, as per `policy`; return them if they are to be
+/// verified once the columns are computed.
+pub(crate) fn take_provided_computed(
+    cs: &mut ConstraintSet,
+    policy: ComputedInTrace,
+) -> Result<Vec<(ColumnRef, ValueBacking)>> {
+    let provided = cs
+        .columns
+        .all()
+        .into_iter()
+        .filter(|h| cs.columns.is_computed(h) && cs.computations.computation_idx_for(h).is_some())
+        .collect::<Vec<_>>();
+    if provided.is_empty() || policy == ComputedInTrace::Trust {
+        return Ok(Vec::new());
+    }
+
+    let names = provided.iter().map(|h| cs.handle(h).pretty()).join(", ");
+    match policy {
+        ComputedInTrace::Error => bail!(
+            "the trace provides {} computed column(s): {}",
+            provided.len(),
+            names
+        ),
+        ComputedInTrace::Ignore => warn!(
+            "discarding the values provided by the trace for {} computed column(s): {}",
+            provided.len(),
+            names
+        ),
+        ComputedInTrace::Verify => info!(
+            "verifying the values provided by the trace for {} computed column(s)",
+            provided.len()
+        ),
+        ComputedInTrace::Trust => unreachable!(),
+    }
+
+    let mut taken = Vec::new();
+    for h in provided {
+        // columns sharing a register are taken at once
+        if let Some(v) = cs.columns.take_value(&h) {
+            taken.push((h, v));
+        }
+    }
+    Ok(if policy == ComputedInTrace::Verify {
+        taken
+    } else {
+        Vec::new()
+    })
+}
+
+/// Ensure that the values `provided` by a trace for computed columns match
+/// the ones that have been computed, reporting the rows that differ
+pub(crate) fn verify_provided(
+    cs: &ConstraintSet,
+    provided: &[(ColumnRef, ValueBacking)],
+) -> Result<()> {
+    let mut mismatches = Vec::new();
+    for (h, expected) in provided.iter() {
+        if cs.columns.is_skipped(h) {
+            continue;
+        }
+        let handle = cs.handle(h).pretty();
+        let Some(len) = cs.columns.len(h) else {
+            mismatches.push(format!("{} has not been computed", handle));
+            continue;
+        };
+        if len != expected.len() {
+            mismatches.push(format!(
+                "{}: {} rows provided, {} computed",
+                handle,
+                expected.len(),
+                len
+            ));
+            continue;
+        }
+
+        let rows = (0..len as isize)
+            .filter_map(|i| {
+                let found = expected.get(i, false, &cs.columns);
+                let computed = cs.columns.get(h, i, false);
+                match (&found, &computed) {
+                    (Some(found), Some(computed)) if same_value(found, computed) => None,
+                    _ => Some((i, found, computed)),
+                }
+            })
+            .collect::<Vec<_>>();
+        if !rows.is_empty() {
+            mismatches.push(format!(
+                "{}: {} row(s) differ, e.g. {}",
+                handle,
+                rows.len(),
+                rows.iter()
+                    .take(MISMATCHES_SHOWN)
+                    .map(|(i, found, computed)| format!(
+                        "#{} (provided {}, computed {})",
+                        i,
+                        found.as_ref().map(|x| x.to_string()).unwrap_or_default(),
+                        computed.as_ref().map(|x| x.to_string()).unwrap_or_default(),
+                    ))
+                    .join(", ")
+            ));
+        }
+    }
+
+    if !mismatches.is_empty() {
+        bail!(
+            "{} computed column(s) differ from the trace:\n  - {}",
+            mismatches.len(),
+            mismatches.join("\n  - ")
+        )
+    }
+    Ok(())
+}
+
 fn err_missing_column(c: &crate::column::Column) -> RuntimeError {
     if matches!(c.kind, Kind::Commitment) {
         RuntimeError::EmptyColumn(c.handle.clone())
@@ -621,29 +1370,401 @@ fn err_missing_column(c: &crate::column::Column) -> RuntimeError {
     }
 }
 
-fn prepare(cs: &mut ConstraintSet, fail_on_missing: bool) -> Result<()> {
-    compute_all(cs).with_context(|| "while computing columns")?;
+/// Log the memory used by the trace columns, and ensure that it fits within
+/// [`crate::MEMORY_BUDGET`] if it is set.
+fn ensure_memory_budget(cs: &ConstraintSet, stage: &'static str) -> Result<()> {
+    let usage = cs.columns.memory_usage();
+    let used = usage.iter().map(|(_, size)| size).sum::<usize>();
+    debug!("trace columns use ~{} after {}", human_size(used), stage);
+
+    if let Some(budget) = *crate::MEMORY_BUDGET.read().unwrap() {
+        if used > budget {
+            bail!(RuntimeError::MemoryBudgetExceeded {
+                stage,
+                budget,
+                used,
+                estimated: false,
+                largest: usage.into_iter().take(5).collect(),
+            })
+        }
+    }
+    Ok(())
+}
+
+/// Ensure that the trace columns would still fit within
+/// [`crate::MEMORY_BUDGET`], if it is set, once the `estimated` bytes, by
+/// module, are allocated; so that `stage` fails before allocating them
+/// rather than after.
+pub(crate) fn ensure_memory_estimate(
+    cs: &ConstraintSet,
+    estimated: HashMap<String, usize>,
+    stage: &'static str,
+) -> Result<()> {
+    if let Some(budget) = *crate::MEMORY_BUDGET.read().unwrap() {
+        let mut usage = estimated;
+        for (module, size) in cs.columns.memory_usage() {
+            *usage.entry(module).or_default() += size;
+        }
+        let used = usage.values().sum::<usize>();
+        if used > budget {
+            bail!(RuntimeError::MemoryBudgetExceeded {
+                stage,
+                budget,
+                used,
+                estimated: true,
+                largest: usage
+                    .into_iter()
+                    .sorted_by_key(|(module, size)| (std::cmp::Reverse(*size), module.to_owned()))
+                    .take(5)
+                    .collect(),
+            })
+        }
+    }
+    Ok(())
+}
+
+/// A lower bound of the memory, by module, that the columns filled by
+/// `computation` will use: their rows times the size of a [`Value`]. The
+/// targets of a [`Computation::Composite`] are evaluated on the fly, and use
+/// none.
+fn estimated_memory(cs: &ConstraintSet, computation: &Computation) -> Vec<(String, usize)> {
+    let size = |c: &ColumnRef| {
+        let module = cs.columns.module_of(c);
+        cs.effective_len_for(&module)
+            .zip(cs.spilling_of(&module))
+            .map(|(len, spilling)| (len + spilling) as usize * std::mem::size_of::<Value>())
+            .unwrap_or_default()
+    };
+    match computation {
+        Computation::Composite { .. } => vec![],
+        // The module of an interleaved column may not have a length yet
+        Computation::Interleaved { target, froms } if !cs.columns.is_computed(target) => {
+            vec![(cs.columns.module_of(target), froms.iter().map(size).sum())]
+        }
+        _ => computation
+            .targets()
+            .into_iter()
+            .filter(|t| !cs.columns.is_computed(t))
+            .map(|t| (cs.columns.module_of(t), size(t)))
+            .collect(),
+    }
+}
+
+/// Ensure that the modules with a fixed length have exactly that many rows in
+/// the imported trace, filling them with their padding value if they are
+/// absent from it.
+fn enforce_fixed_lengths(cs: &mut ConstraintSet) -> Result<()> {
+    for (module, length) in cs.columns.fixed_len.clone().into_iter().sorted() {
+        // the imported columns are prefixed by a padding row
+        let expected = padded_len(cs, &module, length + 1)?;
+        match cs.effective_len_for(&module) {
+            Some(found) => {
+                if found != expected as isize && cs.columns.row_limit.is_none() {
+                    bail!(
+                        "module {} must have {} rows, found {}",
+                        module.blue(),
+                        length.to_string().bold(),
+                        (found - 1).to_string().red().bold()
+                    )
+                }
+            }
+            None => {
+                info!("padding {} to {} rows", module.blue(), length);
+                let spilling = cs.spilling_of(&module).unwrap_or(0);
+                for h in cs.columns.all() {
+                    let column = cs.columns.column(&h).unwrap();
+                    if column.handle.module == module && matches!(column.kind, Kind::Commitment) {
+                        let mut xs =
+                            vec![column.padding_value.clone().unwrap_or_default(); expected];
+                        xs[0] = Value::zero();
+                        cs.columns.set_column_value(&h, xs, spilling)?;
+                    }
+                }
+                cs.effective_len_or_set(&module, expected as isize);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Ensure that the imported columns have lengths coherent with the length
+/// multipliers of their module and with those of the columns they are linked
+/// to by an interleaving or a permutation, reporting all the violated
+/// relations at once rather than failing later in the computations. The
+/// lengths are the ones provided by the trace, before any padding.
+fn validate_length_relations(cs: &ConstraintSet) -> Result<()> {
+    // truncated columns are all cut to the same length, whatever their size
+    // factor
+    if cs.columns.row_limit.is_some() {
+        return Ok(());
+    }
+
+    let describe = |hs: &[ColumnRef]| {
+        hs.iter()
+            .map(|h| {
+                format!(
+                    "{} ({})",
+                    h.pretty(),
+                    cs.columns
+                        .trace_len(h)
+                        .map(|l| l.to_string())
+                        .unwrap_or("absent".into())
+                )
+            })
+            .join(", ")
+    };
+
+    let mut violations = Vec::new();
+
+    // Every imported column must be as long as its module, times its length
+    // multiplier; this also covers the columns whose sources are absent from
+    // the trace, and that can not be checked against them.
+    for module in cs.columns.modules().into_iter().sorted() {
+        let columns = cs
+            .columns
+            .iter_module(&module)
+            .filter_map(|(h, column)| {
+                cs.columns
+                    .trace_len(&h)
+                    .map(|len| (cs.length_multiplier(&h), len, &column.handle))
+            })
+            .sorted_by_key(|(_, _, handle)| &handle.name)
+            .collect::<Vec<_>>();
+        let Some(module_len) = columns
+            .iter()
+            .find(|(multiplier, _, _)| *multiplier == 1)
+            .or(columns.first())
+            .map(|(multiplier, len, _)| len / multiplier)
+        else {
+            continue;
+        };
+        for (multiplier, len, handle) in columns {
+            if len != module_len * multiplier {
+                violations.push(format!(
+                    "{} should be ×{} as long as its module {}, i.e. {} rows, found {}",
+                    handle.pretty(),
+                    multiplier,
+                    module.blue(),
+                    (module_len * multiplier).to_string().bold(),
+                    len.to_string().red().bold(),
+                ));
+            }
+        }
+    }
+
+    for c in cs.computations.iter() {
+        match c {
+            Computation::Interleaved { target, froms } => {
+                let lens = froms
+                    .iter()
+                    .filter_map(|f| cs.columns.trace_len(f))
+                    .collect::<Vec<_>>();
+                if !lens.iter().all_equal() {
+                    violations.push(format!(
+                        "the sources of {} have different lengths: {}",
+                        target.pretty(),
+                        describe(froms)
+                    ));
+                } else if let (Some(len), Some(from_len), true) = (
+                    cs.columns.trace_len(target),
+                    lens.first(),
+                    lens.len() == froms.len(),
+                ) {
+                    if len != from_len * froms.len() {
+                        violations.push(format!(
+                            "{} should be ×{} as long as its sources, i.e. {} rows, found {}; sources: {}",
+                            target.pretty(),
+                            froms.len(),
+                            (from_len * froms.len()).to_string().bold(),
+                            len.to_string().red().bold(),
+                            describe(froms)
+                        ));
+                    }
+                }
+            }
+            Computation::Sorted {
+                froms,
+                tos,
+                tiebreaks,
+                ..
+            } => {
+                let sources = froms
+                    .iter()
+                    .chain(tiebreaks.iter())
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if !sources
+                    .iter()
+                    .filter_map(|h| cs.columns.trace_len(h))
+                    .all_equal()
+                {
+                    violations.push(format!(
+                        "the sources of the permutation into {} have different lengths: {}",
+                        tos.iter().map(|h| h.pretty()).join(", "),
+                        describe(&sources)
+                    ));
+                } else {
+                    for (from, to) in froms.iter().zip(tos.iter()) {
+                        if let Some((from_len, to_len)) =
+                            cs.columns.trace_len(from).zip(cs.columns.trace_len(to))
+                        {
+                            if from_len != to_len {
+                                violations.push(format!(
+                                    "{} should be ×1 as long as its source {}, i.e. {} rows, found {}",
+                                    to.pretty(),
+                                    from.pretty(),
+                                    from_len.to_string().bold(),
+                                    to_len.to_string().red().bold(),
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !violations.is_empty() {
+        bail!(
+            "{} length relation(s) violated in the trace:\n  - {}",
+            violations.len(),
+            violations.join("\n  - ")
+        )
+    }
+    Ok(())
+}
+
+fn prepare(
+    cs: &mut ConstraintSet,
+    fail_on_missing: bool,
+    only: &Option<Vec<String>>,
+    skip: &[String],
+) -> Result<()> {
+    enforce_fixed_lengths(cs)?;
+    validate_length_relations(cs)?;
+    ensure_memory_budget(cs, "importing the trace")?;
+    let provided = take_provided_computed(cs, cs.columns.computed_in_trace)?;
+    let _phase = run_stats::phase("compute");
+    let required = (only.is_some() || !skip.is_empty())
+        .then(|| required_computations(cs, cs.selected_constraints(only, skip).into_iter()));
+    compute_all(cs, required.as_ref()).with_context(|| "while computing columns")?;
+    verify_provided(cs, &provided)?;
+    info!(
+        "trace columns use ~{}",
+        human_size(cs.columns.memory_usage().iter().map(|(_, s)| s).sum())
+    );
+    let mut skipped = 0;
     for h in cs.columns.all() {
-        if !cs.columns.is_computed(&h) {
+        if cs.columns.is_skipped(&h) {
+            skipped += 1;
+        } else if !cs.columns.is_computed(&h) {
             let err = err_missing_column(cs.columns.column(&h).unwrap());
             if fail_on_missing {
                 bail!(err)
+            } else if cs.columns.partial {
+                info!("{}", err);
             } else {
                 error!("{}", err);
             }
         }
     }
+    if skipped > 0 {
+        info!(
+            "{} columns not computed, as no selected constraint depends on them",
+            skipped
+        );
+    }
 
     Ok(())
 }
 
+/// Run all the computations over the trace previously imported in `cs`
+// This is only used by the library
+#[allow(dead_code)]
+pub fn compute(cs: &mut ConstraintSet, fail_on_missing: bool) -> Result<()> {
+    prepare(cs, fail_on_missing, &None, &[])
+}
+
 pub fn compute_trace(tracefile: &str, cs: &mut ConstraintSet, fail_on_missing: bool) -> Result<()> {
-    if tracefile.ends_with("lt") {
-        import::parse_binary_trace(tracefile, cs, false)?;
+    compute_trace_for(tracefile, cs, fail_on_missing, &None, &[])
+}
+
+/// Import `tracefile` and only run the computations required by the
+/// constraints selected by `only` and `skip`
+pub fn compute_trace_for(
+    tracefile: &str,
+    cs: &mut ConstraintSet,
+    fail_on_missing: bool,
+    only: &Option<Vec<String>>,
+    skip: &[String],
+) -> Result<()> {
+    import_trace(tracefile, cs)?;
+    prepare(cs, fail_on_missing, only, skip)
+}
+
+/// Write the trace computed in `cs` to `outfile`, as a binary LT trace if it
+/// ends with `.lt`, as JSON with its values encoded as per `encoding`
+/// otherwise
+pub fn write_trace(
+    cs: &mut ConstraintSet,
+    outfile: &str,
+    checksums: bool,
+    encoding: import::ValuesEncoding,
+) -> Result<()> {
+    let mut f =
+        std::fs::File::create(outfile).with_context(|| format!("while creating `{}`", outfile))?;
+    let mut out = std::io::BufWriter::with_capacity(10_000_000, &mut f);
+    if outfile.ends_with(".lt") {
+        if checksums {
+            warn!("checksums can not be embedded in LT traces");
+        }
+        import::write_binary_trace(cs, &[], true, &mut out)
     } else {
-        import::parse_json_trace(tracefile, cs, false)?;
+        cs.write(&mut out, checksums, encoding)
     }
-    prepare(cs, fail_on_missing)
+    .with_context(|| format!("while writing to `{}`", outfile))?;
+    Ok(out.flush()?)
+}
+
+/// Import `tracefile` into `cs`, whatever its format, without running any
+/// computation
+pub fn import_trace(tracefile: &str, cs: &mut ConstraintSet) -> Result<()> {
+    let _phase = run_stats::phase("import");
+    cs.columns.resolve_paddings()?;
+    import::parse_trace(tracefile, cs, false)?;
+    run_stats::record_rows(cs);
+    Ok(())
+}
+
+/// Import `tracefile` without running any computation, so that the computed
+/// columns can later be filled on demand by a [`LazyComputer`]
+pub fn import_trace_lazily(
+    tracefile: &str,
+    cs: &mut ConstraintSet,
+    fail_on_missing: bool,
+) -> Result<()> {
+    import_trace(tracefile, cs)?;
+    enforce_fixed_lengths(cs)?;
+    validate_length_relations(cs)?;
+    ensure_memory_budget(cs, "importing the trace")?;
+    if !take_provided_computed(cs, cs.columns.computed_in_trace)?.is_empty() {
+        warn!("the computed columns provided by the trace can not be verified when they are computed lazily");
+    }
+    // Only the columns that no computation will ever fill are missing
+    for h in cs.columns.all() {
+        if !cs.columns.is_computed(&h) && cs.computations.computation_idx_for(&h).is_none() {
+            let err = err_missing_column(cs.columns.column(&h).unwrap());
+            if fail_on_missing {
+                bail!(err)
+            } else if cs.columns.partial {
+                info!("{}", err);
+            } else {
+                error!("{}", err);
+            }
+        }
+    }
+    Ok(())
 }
 
 // This is only used by the lib
@@ -653,6 +1774,7 @@ pub fn compute_trace_str(
     cs: &mut ConstraintSet,
     fail_on_missing: bool,
 ) -> Result<()> {
+    cs.columns.resolve_paddings()?;
     import::read_trace_str(trace, cs, false)?;
-    prepare(cs, fail_on_missing)
+    prepare(cs, fail_on_missing, &None, &[])
 }
@@ -3,9 +3,10 @@ use crate::compiler::Magma;
 pub const FIELD_BITSIZE: usize = 254;
 
 pub fn col_count_bits(x: usize) -> usize {
-    (x + FIELD_BITSIZE - 1) / FIELD_BITSIZE
+    x.div_ceil(FIELD_BITSIZE)
 }
 
+#[allow(dead_code)]
 pub fn col_count_bytes(x: usize) -> usize {
     col_count_bits(x * 8)
 }
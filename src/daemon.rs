@@ -0,0 +1,296 @@
+//! A long-running process holding a compiled constraint set in memory, so
+//! that repeated checks and computations against the same constraint set do
+//! not pay for its loading every time.
+//!
+//! Clients connect to the daemon through a Unix socket and send it one
+//! [`Request`] per line, serialized in JSON; every request is answered by a
+//! single JSON-serialized [`Response`] line. Every connection is served in its
+//! own thread, and every request works on its own copy of the constraint set,
+//! decoded from an in-memory snapshot.
+use anyhow::*;
+use log::*;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{BufRead, BufReader, Write},
+    ops::Range,
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+    sync::Arc,
+    time::Instant,
+};
+
+use crate::{
+    check,
+    compiler::ConstraintSet,
+    compute, input,
+    transformer::{self, AutoConstraint, ExpansionLevel},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Request {
+    /// Check the constraints against `trace`
+    Check {
+        trace: String,
+        #[serde(default)]
+        only: Option<Vec<String>>,
+        #[serde(default)]
+        skip: Vec<String>,
+        #[serde(default)]
+        limit_rows: Option<usize>,
+        #[serde(default)]
+        continue_on_error: bool,
+        #[serde(default)]
+        strict_padding: Vec<String>,
+        #[serde(default)]
+        strict_magma: bool,
+        #[serde(default)]
+        rows: Option<Range<isize>>,
+        #[serde(default)]
+        partial: bool,
+    },
+    /// Expand `trace` with the computed columns, and write it to `out`
+    Compute {
+        trace: String,
+        out: String,
+        #[serde(default)]
+        fail_on_missing: bool,
+        #[serde(default)]
+        verify_computations: bool,
+        #[serde(default)]
+        checksums: bool,
+        #[serde(default)]
+        base64: bool,
+        #[serde(default)]
+        compact: bool,
+        #[serde(default)]
+        only: Option<Vec<String>>,
+        #[serde(default)]
+        skip: Vec<String>,
+        #[serde(default)]
+        limit_rows: Option<usize>,
+    },
+}
+impl Request {
+    /// The paths in a request are resolved by the daemon, whose working
+    /// directory may differ from the client's; so they are made absolute
+    /// before being sent. URLs are left as they are, and the daemon can not
+    /// read the standard input of its clients.
+    fn absolutize(mut self) -> Result<Self> {
+        let absolute = |p: &mut String| -> Result<()> {
+            if p == input::STDIN {
+                bail!("the daemon can not read traces from STDIN, but only from files or URLs")
+            }
+            let path = Path::new(p.as_str());
+            if path.is_relative() && !input::is_remote(p) {
+                *p = std::env::current_dir()?
+                    .join(path)
+                    .to_string_lossy()
+                    .into_owned();
+            }
+            Ok(())
+        };
+        match &mut self {
+            Request::Check { trace, .. } => absolute(trace)?,
+            Request::Compute { trace, out, .. } => {
+                absolute(trace)?;
+                absolute(out)?;
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Response {
+    /// if the request failed, the chain of its causes, outermost first
+    pub error: Option<Vec<String>>,
+}
+
+pub struct Daemon {
+    /// the constraint set, expanded as requested on the command line
+    check: Vec<u8>,
+    /// the constraint set, fully expanded as required to compute traces
+    compute: Vec<u8>,
+    /// what to do with the values that traces provide for computed columns
+    computed_in_trace: compute::ComputedInTrace,
+}
+impl Daemon {
+    pub fn new(cs: ConstraintSet) -> Result<Self> {
+        let check = cs.to_binary()?;
+        let mut cs = cs;
+        transformer::expand_to(&mut cs, ExpansionLevel::top(), AutoConstraint::all())?;
+        transformer::concretize(&mut cs);
+        Ok(Daemon {
+            check,
+            compute: cs.to_binary()?,
+            computed_in_trace: cs.columns.computed_in_trace,
+        })
+    }
+
+    /// Listen on `socket`, replacing a stale socket left by a previous daemon
+    pub fn bind(socket: &Path) -> Result<UnixListener> {
+        if let std::result::Result::Ok(metadata) = std::fs::symlink_metadata(socket) {
+            if !metadata.file_type().is_socket() {
+                bail!(
+                    "`{}` already exists and is not a socket",
+                    socket.display().to_string().red()
+                )
+            }
+            std::fs::remove_file(socket)
+                .with_context(|| anyhow!("while removing `{}`", socket.display()))?;
+        }
+        UnixListener::bind(socket).with_context(|| anyhow!("while binding `{}`", socket.display()))
+    }
+
+    /// Serve the clients connecting to `listener`, forever
+    pub fn serve(self, listener: UnixListener) -> Result<()> {
+        let daemon = Arc::new(self);
+        for stream in listener.incoming() {
+            match stream {
+                std::result::Result::Ok(stream) => {
+                    let daemon = daemon.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = daemon.serve_client(stream) {
+                            warn!("while serving client: {:?}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("failed to accept client: {}", e),
+            }
+        }
+        Ok(())
+    }
+
+    fn serve_client(&self, stream: UnixStream) -> Result<()> {
+        let mut out = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = Response {
+                error: serde_json::from_str::<Request>(&line)
+                    .context("invalid request")
+                    .and_then(|request| self.process(request))
+                    .err()
+                    .map(|e| e.chain().map(|c| c.to_string()).collect()),
+            };
+            serde_json::to_writer(&mut out, &response)?;
+            out.write_all(b"\n")?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn process(&self, request: Request) -> Result<()> {
+        let start = Instant::now();
+        info!("processing {:?}", request);
+        let r = match request {
+            Request::Check {
+                ref trace,
+                ref only,
+                ref skip,
+                limit_rows,
+                continue_on_error,
+                strict_padding,
+                strict_magma,
+                rows,
+                partial,
+            } => {
+                let mut cs = ConstraintSet::from_bytes(&self.check, "daemon")?;
+                cs.set_row_limit(limit_rows);
+                cs.set_computed_in_trace(self.computed_in_trace);
+                cs.set_partial(partial);
+                compute::compute_trace_for(trace, &mut cs, false, only, skip)
+                    .with_context(|| format!("while expanding `{}`", trace))?;
+                let only = if partial {
+                    check::partial_selection(&cs, only, skip)?
+                } else {
+                    only.clone()
+                };
+                check::check(
+                    &cs,
+                    &only,
+                    skip,
+                    check::CheckConfig::new()
+                        .report(check::ReportStyle::Silent)
+                        .continue_on_error(continue_on_error)
+                        .strict_padding(strict_padding)
+                        .strict_magma(strict_magma)
+                        .rows(rows),
+                )
+                .with_context(|| format!("while checking `{}`", trace))
+            }
+            Request::Compute {
+                ref trace,
+                ref out,
+                fail_on_missing,
+                verify_computations,
+                checksums,
+                base64,
+                compact,
+                ref only,
+                ref skip,
+                limit_rows,
+            } => {
+                let mut cs = ConstraintSet::from_bytes(&self.compute, "daemon")?;
+                cs.set_row_limit(limit_rows);
+                cs.set_computed_in_trace(self.computed_in_trace);
+                compute::compute_trace_for(trace, &mut cs, fail_on_missing, only, skip)
+                    .with_context(|| format!("while computing from `{}`", trace))?;
+                if verify_computations {
+                    compute::verify_computations(&cs)?;
+                }
+                compute::write_trace(
+                    &mut cs,
+                    out,
+                    checksums,
+                    crate::values_encoding(base64, compact),
+                )
+            }
+        };
+        info!(
+            "request {} in {:.2}s",
+            if r.is_ok() { "succeeded" } else { "failed" },
+            start.elapsed().as_secs_f32()
+        );
+        r
+    }
+}
+
+/// Send `request` to the daemon listening on `socket`, and wait for its
+/// completion.
+pub fn submit(socket: &Path, request: Request) -> Result<()> {
+    let request = request.absolutize()?;
+    let mut stream = UnixStream::connect(socket).with_context(|| {
+        anyhow!(
+            "unable to reach a daemon on `{}`",
+            socket.display().to_string().red()
+        )
+    })?;
+    serde_json::to_writer(&mut stream, &request)?;
+    stream.write_all(b"\n")?;
+    stream.flush()?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    if line.is_empty() {
+        bail!("the daemon closed the connection")
+    }
+    let response: Response = serde_json::from_str(&line)?;
+    match response.error {
+        None => Ok(()),
+        Some(mut chain) => {
+            let root = chain.pop().unwrap_or_default();
+            Err(chain
+                .into_iter()
+                .rev()
+                .fold(anyhow!(root), |err, context| err.context(context)))
+        }
+    }
+}
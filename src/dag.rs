@@ -63,8 +63,13 @@ impl ComputationDag {
                     self.depends(from, target);
                 }
             }
-            Computation::Sorted { froms, tos, .. } => {
-                for from in froms.iter() {
+            Computation::Sorted {
+                froms,
+                tos,
+                tiebreaks,
+                ..
+            } => {
+                for from in froms.iter().chain(tiebreaks.iter()) {
                     for to in tos.iter() {
                         self.depends(from, to);
                     }
@@ -0,0 +1,175 @@
+//! Compare two traces of the same constraint set column by column, e.g. a
+//! trace produced by a prover against a reference one.
+//!
+//! The rows are numbered as in the loaded columns, i.e. including their
+//! padding; traces should thus be compared in the same form, either both
+//! expanded, or both as given to `compute`.
+use anyhow::*;
+use itertools::Itertools;
+use owo_colors::OwoColorize;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{compiler::ConstraintSet, pretty::Pretty};
+
+/// How many differing cells are detailed per column
+const SAMPLES: usize = 3;
+
+/// A cell differing between the two traces
+#[derive(Debug, Clone, Serialize)]
+pub struct Cell {
+    pub row: usize,
+    /// the value of the cell in each trace, if it extends that far
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+/// The differences of a column filled in both traces
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnDiff {
+    pub column: String,
+    pub lengths: (usize, usize),
+    /// the first differing cells
+    pub samples: Vec<Cell>,
+    /// how many cells differ, the cells lacking from the shorter column
+    /// included
+    pub count: usize,
+}
+impl ColumnDiff {
+    pub fn first_row(&self) -> usize {
+        self.samples[0].row
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct TraceDiff {
+    /// the columns filled in the right trace only
+    pub missing_left: Vec<String>,
+    /// the columns filled in the left trace only
+    pub missing_right: Vec<String>,
+    pub differing: Vec<ColumnDiff>,
+    /// how many columns are identical in both traces
+    pub identical: usize,
+}
+impl TraceDiff {
+    pub fn is_empty(&self) -> bool {
+        self.missing_left.is_empty() && self.missing_right.is_empty() && self.differing.is_empty()
+    }
+
+    /// Print the differences, the traces being named `left` and `right`
+    pub fn print(&self, left: &str, right: &str) {
+        for column in self.missing_left.iter() {
+            println!("{}: missing from {}", column.bold(), left.bright_white());
+        }
+        for column in self.missing_right.iter() {
+            println!("{}: missing from {}", column.bold(), right.bright_white());
+        }
+        for d in self.differing.iter() {
+            println!(
+                "{}: {} cell(s) differ, first at row {}{}",
+                d.column.bold(),
+                d.count.to_string().red().bold(),
+                d.first_row().to_string().yellow(),
+                if d.lengths.0 != d.lengths.1 {
+                    format!("; {} vs. {} rows", d.lengths.0, d.lengths.1)
+                } else {
+                    String::new()
+                }
+            );
+            for cell in d.samples.iter() {
+                let show = |x: &Option<String>| x.clone().unwrap_or_else(|| "∅".to_owned());
+                println!(
+                    "  row {}: {} vs. {}",
+                    cell.row,
+                    show(&cell.left).red(),
+                    show(&cell.right).green()
+                );
+            }
+        }
+        println!(
+            "{} identical, {} differing, {} missing column(s)",
+            self.identical,
+            self.differing.len(),
+            self.missing_left.len() + self.missing_right.len()
+        );
+    }
+}
+
+/// Compare the columns of `left` and `right`, both filled from a copy of the
+/// same constraint set
+pub fn diff(left: &ConstraintSet, right: &ConstraintSet) -> TraceDiff {
+    let mut r = TraceDiff::default();
+    let columns = left
+        .columns
+        .iter()
+        .filter(|(_, c)| c.register.is_some())
+        .sorted_by_cached_key(|(_, c)| c.handle.to_string())
+        .collect::<Vec<_>>();
+
+    let mut compared = Vec::new();
+    for (h, c) in columns {
+        match (
+            left.columns.backing(&h).is_some(),
+            right.columns.backing(&h).is_some(),
+        ) {
+            (true, true) => compared.push((h, c)),
+            (true, false) => r.missing_right.push(c.handle.to_string()),
+            (false, true) => r.missing_left.push(c.handle.to_string()),
+            (false, false) => {}
+        }
+    }
+
+    let diffs = compared
+        .into_par_iter()
+        .map(|(h, c)| {
+            let lengths = (
+                left.columns.len(&h).unwrap_or(0),
+                right.columns.len(&h).unwrap_or(0),
+            );
+            let mut d = ColumnDiff {
+                column: c.handle.to_string(),
+                lengths,
+                samples: Vec::new(),
+                count: 0,
+            };
+            for i in 0..lengths.0.max(lengths.1) {
+                let x = left.columns.get(&h, i as isize, false);
+                let y = right.columns.get(&h, i as isize, false);
+                if x != y {
+                    if d.samples.len() < SAMPLES {
+                        d.samples.push(Cell {
+                            row: i,
+                            left: x.map(|x| x.pretty()),
+                            right: y.map(|y| y.pretty()),
+                        });
+                    }
+                    d.count += 1;
+                }
+            }
+            d
+        })
+        .collect::<Vec<_>>();
+    for d in diffs {
+        if d.count > 0 {
+            r.differing.push(d);
+        } else {
+            r.identical += 1;
+        }
+    }
+    r
+}
+
+/// Fail if `diff` is not empty
+pub fn conclude(diff: &TraceDiff) -> Result<()> {
+    if diff.is_empty() {
+        Ok(())
+    } else {
+        bail!(
+            "the traces differ in {} column(s)",
+            (diff.differing.len() + diff.missing_left.len() + diff.missing_right.len())
+                .to_string()
+                .red()
+                .bold()
+        )
+    }
+}
@@ -0,0 +1,364 @@
+use anyhow::*;
+use owo_colors::OwoColorize;
+use std::path::Path;
+
+use crate::{
+    compiler::ConstraintSet,
+    input::{self, Compression},
+    SourceLoader,
+};
+
+/// The minimal terminal size for the inspector to be usable
+const MIN_TERMINAL_SIZE: (u16, u16) = (80, 24);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Severity {
+    Ok,
+    Warning,
+    Error,
+}
+
+struct Finding {
+    severity: Severity,
+    topic: &'static str,
+    message: String,
+    /// what the user may do to fix the problem
+    hint: Option<String>,
+}
+
+#[derive(Default)]
+struct Report {
+    findings: Vec<Finding>,
+}
+impl Report {
+    fn push(
+        &mut self,
+        severity: Severity,
+        topic: &'static str,
+        message: String,
+        hint: Option<&str>,
+    ) {
+        self.findings.push(Finding {
+            severity,
+            topic,
+            message,
+            hint: hint.map(str::to_owned),
+        })
+    }
+
+    fn ok<S: Into<String>>(&mut self, topic: &'static str, message: S) {
+        self.push(Severity::Ok, topic, message.into(), None)
+    }
+
+    fn warn<S: Into<String>>(&mut self, topic: &'static str, message: S, hint: Option<&str>) {
+        self.push(Severity::Warning, topic, message.into(), hint)
+    }
+
+    fn error<S: Into<String>>(&mut self, topic: &'static str, message: S, hint: Option<&str>) {
+        self.push(Severity::Error, topic, message.into(), hint)
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| f.severity == severity)
+            .count()
+    }
+
+    fn print(&self) {
+        for f in self.findings.iter() {
+            let marker = match f.severity {
+                Severity::Ok => "✓".green().bold().to_string(),
+                Severity::Warning => "⚠".yellow().bold().to_string(),
+                Severity::Error => "✗".red().bold().to_string(),
+            };
+            println!("{} {}: {}", marker, f.topic.bold(), f.message);
+            if let Some(hint) = f.hint.as_ref() {
+                println!("  → {}", hint.italic());
+            }
+        }
+    }
+}
+
+fn check_locale(r: &mut Report) {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|v| std::env::var(v).ok())
+        .find(|l| !l.is_empty());
+    match locale {
+        Some(l) if l.to_lowercase().replace('-', "").contains("utf8") => {
+            r.ok("locale", format!("{} supports Unicode", l))
+        }
+        Some(l) => r.warn(
+            "locale",
+            format!(
+                "{} is not a UTF-8 locale; symbols in the output may be garbled",
+                l
+            ),
+            Some("export LANG=C.UTF-8"),
+        ),
+        None => r.warn(
+            "locale",
+            "no locale is set; symbols in the output may be garbled",
+            Some("export LANG=C.UTF-8"),
+        ),
+    }
+}
+
+#[cfg(feature = "inspector")]
+fn check_terminal(r: &mut Report) {
+    match crossterm::terminal::size() {
+        Result::Ok((columns, rows))
+            if columns < MIN_TERMINAL_SIZE.0 || rows < MIN_TERMINAL_SIZE.1 =>
+        {
+            r.warn(
+                "terminal",
+                format!(
+                    "the terminal is {}×{}, but the inspector needs at least {}×{}",
+                    columns, rows, MIN_TERMINAL_SIZE.0, MIN_TERMINAL_SIZE.1
+                ),
+                Some("enlarge the terminal window or reduce its font size"),
+            )
+        }
+        Result::Ok((columns, rows)) => r.ok("terminal", format!("{}×{}", columns, rows)),
+        Err(_) => r.warn(
+            "terminal",
+            "not running in a terminal; the inspector will not be usable",
+            None,
+        ),
+    }
+}
+
+#[cfg(feature = "exporters")]
+fn check_gofmt(r: &mut Report) {
+    match std::process::Command::new("gofmt")
+        .stdin(std::process::Stdio::null())
+        .output()
+    {
+        Result::Ok(_) => r.ok("gofmt", "found"),
+        Err(_) => r.warn(
+            "gofmt",
+            "gofmt can not be run; Go exports will fail",
+            Some("install the Go toolchain, and ensure that gofmt is in the PATH"),
+        ),
+    }
+}
+
+/// Ensure that `sources` are readable, and compile them together
+fn check_sources(r: &mut Report, sources: &[String], no_stdlib: bool) -> Option<ConstraintSet> {
+    if sources.is_empty() {
+        r.warn(
+            "constraints",
+            "no constraint system provided; traces can not be validated against it",
+            None,
+        );
+        return None;
+    }
+
+    let is_bin = |s: &str| {
+        Path::new(s)
+            .extension()
+            .map(|e| e == "bin")
+            .unwrap_or(false)
+    };
+    if sources.len() == 1 && is_bin(&sources[0]) {
+        let bin = &sources[0];
+        return match SourceLoader::from_bin(bin).and_then(|b| b.into_constraint_set()) {
+            Result::Ok(cs) => {
+                match cs.build_info.as_ref() {
+                    Some(info) if info != &crate::version::BuildInfo::current() => r.warn(
+                        "constraints",
+                        format!(
+                            "{} has been compiled by corset {} ({}) for version {} of the constraint language",
+                            bin, info.version, info.git_hash, info.language_version
+                        ),
+                        Some("recompile it from its sources with this corset"),
+                    ),
+                    Some(_) => r.ok("constraints", format!("{} is valid", bin)),
+                    None => r.warn(
+                        "constraints",
+                        format!("{} does not tell which corset compiled it", bin),
+                        Some("recompile it from its sources with this corset"),
+                    ),
+                }
+                Some(cs)
+            }
+            Err(e) => {
+                r.error(
+                    "constraints",
+                    format!("{} can not be loaded: {:#}", bin, e),
+                    Some("recompile it from its sources with this corset"),
+                );
+                None
+            }
+        };
+    }
+
+    let mut builder = SourceLoader::from_sources(no_stdlib, false);
+    for source in sources {
+        if is_bin(source) {
+            r.error(
+                "constraints",
+                format!(
+                    "{} is a compiled constraint system, and can not be mixed with sources",
+                    source
+                ),
+                None,
+            );
+            return None;
+        }
+        if let Err(e) = builder.add_source(source) {
+            r.error("constraints", format!("{:#}", e), None);
+            return None;
+        }
+    }
+    match builder.into_constraint_set() {
+        Result::Ok(cs) => {
+            r.ok(
+                "constraints",
+                format!("{} source(s) compiled successfully", sources.len()),
+            );
+            Some(cs)
+        }
+        Err(e) => {
+            r.error(
+                "constraints",
+                format!("compilation failed: {:#}", e),
+                Some("run `corset check` on the sources for a detailed report"),
+            );
+            None
+        }
+    }
+}
+
+/// Ensure that a compressed trace can be entirely decompressed, as truncated
+/// archives are the most common source of corruption
+fn check_archive(r: &mut Report, tracefile: &str) -> Result<bool> {
+    let compression = input::compression(tracefile)?;
+    if compression != Compression::None {
+        let decompressed = input::peek_reader(tracefile)
+            .and_then(|mut content| Ok(std::io::copy(&mut content, &mut std::io::sink())?));
+        if let Err(e) = decompressed {
+            r.error(
+                "trace",
+                format!(
+                    "{} is not a valid {} archive: {}",
+                    tracefile, compression, e
+                ),
+                Some("the file is likely truncated; download or generate it again"),
+            );
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Ensure that a JSON trace is complete and well-formed
+fn check_json_trace(r: &mut Report, tracefile: &str) -> Result<bool> {
+    match serde_json::from_reader::<_, serde::de::IgnoredAny>(input::reader(tracefile)?) {
+        Result::Ok(_) => {
+            r.ok("trace", format!("{} is well-formed JSON", tracefile));
+            Ok(true)
+        }
+        Err(e) => {
+            if e.is_eof() {
+                r.error(
+                    "trace",
+                    format!("{} ends prematurely: {}", tracefile, e),
+                    Some("the file is likely truncated; download or generate it again"),
+                )
+            } else {
+                r.error(
+                    "trace",
+                    format!("{} is not valid JSON: {}", tracefile, e),
+                    None,
+                )
+            }
+            Ok(false)
+        }
+    }
+}
+
+fn check_trace(r: &mut Report, tracefile: &str, cs: Option<ConstraintSet>) -> Result<()> {
+    match std::fs::metadata(tracefile) {
+        Err(e) => {
+            r.error(
+                "trace",
+                format!("{} can not be read: {}", tracefile, e),
+                None,
+            );
+            return Ok(());
+        }
+        Result::Ok(m) if m.len() == 0 => {
+            r.error("trace", format!("{} is empty", tracefile), None);
+            return Ok(());
+        }
+        Result::Ok(_) => {}
+    }
+
+    // the format is detected from the decompressed content
+    if !check_archive(r, tracefile)? {
+        return Ok(());
+    }
+    let is_binary =
+        crate::import::TraceFormat::detect(tracefile)? != crate::import::TraceFormat::Json;
+    if !is_binary && !check_json_trace(r, tracefile)? {
+        return Ok(());
+    }
+
+    if let Some(mut cs) = cs {
+        match crate::compute::compute_trace(tracefile, &mut cs, false) {
+            Result::Ok(_) => r.ok(
+                "trace",
+                format!("{} is compatible with the constraint system", tracefile),
+            ),
+            Err(e) => r.error(
+                "trace",
+                format!("{} can not be imported: {:#}", tracefile, e),
+                None,
+            ),
+        }
+    } else if is_binary {
+        r.warn(
+            "trace",
+            format!(
+                "{} can only be validated against a constraint system",
+                tracefile
+            ),
+            None,
+        )
+    }
+    Ok(())
+}
+
+/// Diagnose common environment problems, and ensure the integrity of the
+/// given constraint system and trace.
+pub fn doctor(sources: &[String], tracefile: Option<&str>, no_stdlib: bool) -> Result<()> {
+    let mut report = Report::default();
+
+    check_locale(&mut report);
+    #[cfg(feature = "inspector")]
+    check_terminal(&mut report);
+    #[cfg(feature = "exporters")]
+    check_gofmt(&mut report);
+
+    let cs = check_sources(&mut report, sources, no_stdlib);
+    if let Some(tracefile) = tracefile {
+        check_trace(&mut report, tracefile, cs)?;
+    }
+
+    report.print();
+    let (warnings, errors) = (
+        report.count(Severity::Warning),
+        report.count(Severity::Error),
+    );
+    if errors > 0 {
+        bail!("{} error(s) and {} warning(s) found", errors, warnings)
+    } else {
+        println!(
+            "\n{} warning(s) found",
+            warnings.to_string().yellow().bold()
+        );
+        Ok(())
+    }
+}
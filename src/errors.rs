@@ -1,5 +1,5 @@
 use super::pretty::Pretty;
-use crate::{column::Value, pretty::Base};
+use crate::{column::Value, pretty::Base, utils::human_size};
 use owo_colors::OwoColorize;
 use thiserror::Error;
 
@@ -36,6 +36,35 @@ pub enum RuntimeError {
 
     #[error("expected an array, found {:?}", .0)]
     NotAnArray(Expression),
+
+    #[error(
+        "memory budget of {} exceeded while {}: trace columns {} ~{}; largest modules: {}",
+        human_size(*.budget).bold(),
+        .stage,
+        if *.estimated { "would use" } else { "use" },
+        human_size(*.used).red().bold(),
+        .largest
+            .iter()
+            .map(|(module, size)| format!("{} ({})", module.blue(), human_size(*size)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )]
+    MemoryBudgetExceeded {
+        stage: &'static str,
+        budget: usize,
+        used: usize,
+        estimated: bool,
+        largest: Vec<(String, usize)>,
+    },
+
+    #[error("timed out after {} while {}", format!("{}s", .timeout.as_secs()).bold(), .stage)]
+    DeadlineExceeded {
+        stage: &'static str,
+        timeout: std::time::Duration,
+    },
+
+    #[error("interrupted while {}", .stage)]
+    Cancelled { stage: &'static str },
 }
 
 pub mod parser {
@@ -0,0 +1,144 @@
+//! Follow a single constraint through the compilation pipeline, showing how
+//! every transformation rewrites it.
+use anyhow::*;
+use itertools::Itertools;
+use owo_colors::OwoColorize;
+
+use crate::{
+    compiler::{
+        parser::{Ast, Token},
+        Constraint, ConstraintSet, MAIN_MODULE,
+    },
+    structs::Handle,
+    transformer::{self, AutoConstraint, ExpansionLevel},
+};
+
+/// The forms taken by the explained constraint after a step of the pipeline
+pub(crate) struct Stage {
+    pub label: &'static str,
+    pub forms: Vec<String>,
+}
+
+/// Whether `target` designates `handle`, either by its sole name or by its
+/// fully qualified one
+fn designates(target: &str, handle: &Handle) -> bool {
+    target == handle.name || target == handle.to_string()
+}
+
+fn render(c: &Constraint) -> String {
+    match c {
+        Constraint::Vanishes { domain, expr, .. } => match domain {
+            Some(domain) => format!("{} over {}", expr, domain),
+            None => expr.to_string(),
+        },
+        Constraint::Lookup {
+            including,
+            included,
+            ..
+        } => format!(
+            "{{{}}} ⊂ {{{}}}",
+            included.iter().join(" "),
+            including.iter().join(" ")
+        ),
+        Constraint::Permutation { from, to, .. } => {
+            format!("[{}] ⇔ [{}]", from.iter().join(" "), to.iter().join(" "))
+        }
+        Constraint::InRange { exp, max, .. } => format!("{} < {}", exp, max),
+        Constraint::Normalization {
+            reference,
+            inverted,
+            ..
+        } => format!("{} = {}⁻¹", inverted, reference),
+    }
+}
+
+fn forms(cs: &ConstraintSet, target: &str) -> Vec<String> {
+    cs.constraints
+        .iter()
+        .filter(|c| designates(target, c.handle()))
+        .map(render)
+        .collect()
+}
+
+/// The definitions of `target` as written in the sources
+fn sources(asts: &[Ast], target: &str) -> Vec<String> {
+    let mut r = Vec::new();
+    for ast in asts.iter() {
+        let mut module = MAIN_MODULE.to_owned();
+        for node in ast.exprs.iter() {
+            match &node.class {
+                Token::DefModule(name, _) => module = name.to_owned(),
+                Token::DefConstraint { name, .. }
+                    if designates(target, &Handle::new(&module, name)) =>
+                {
+                    r.push(node.src.clone())
+                }
+                _ => {}
+            }
+        }
+    }
+    r
+}
+
+/// Apply the whole pipeline to `cs`, recording the forms taken by the
+/// constraint `target` after every step; `asts` are the sources `cs` has been
+/// compiled from, if any.
+pub(crate) fn explain(
+    asts: &[Ast],
+    mut cs: ConstraintSet,
+    target: &str,
+    auto_constraints: &[AutoConstraint],
+) -> Result<Vec<Stage>> {
+    let mut stages = Vec::new();
+    if !asts.is_empty() {
+        stages.push(Stage {
+            label: "parsed",
+            forms: sources(asts, target),
+        });
+    }
+    stages.push(Stage {
+        label: "typed",
+        forms: forms(&cs, target),
+    });
+    if stages.iter().all(|s| s.forms.is_empty()) {
+        bail!("constraint {} not found", target.red().bold())
+    }
+
+    transformer::expand_to_with(
+        &mut cs,
+        ExpansionLevel::top(),
+        auto_constraints,
+        |label, cs| {
+            stages.push(Stage {
+                label,
+                forms: forms(cs, target),
+            })
+        },
+    )?;
+    transformer::concretize(&mut cs);
+    stages.push(Stage {
+        label: "exported",
+        forms: forms(&cs, target),
+    });
+
+    Ok(stages)
+}
+
+/// Print `stages`, eliding the ones that left the constraint untouched
+pub(crate) fn print(stages: &[Stage]) {
+    let mut previous: Option<&Vec<String>> = None;
+    for stage in stages.iter() {
+        println!("{}", format!("=== {} ===", stage.label).bold().yellow());
+        if previous == Some(&stage.forms) {
+            println!("{}", "unchanged".italic());
+        } else if stage.forms.is_empty() {
+            println!("{}", "absent".italic());
+        } else {
+            for form in stage.forms.iter() {
+                println!("{}", form);
+            }
+        }
+        println!();
+        previous = Some(&stage.forms);
+    }
+}
@@ -1,5 +1,3 @@
-use std::fs::File;
-use std::io::Write;
 use std::path::Path;
 use std::println;
 
@@ -61,7 +59,7 @@ fn magma_to_java_type(m: Magma) -> String {
         RawMagma::Nibble => "UnsignedByte",
         RawMagma::Byte => "UnsignedByte",
         RawMagma::Native => "Bytes",
-        RawMagma::Integer(w) => match w {
+        RawMagma::Integer(w) | RawMagma::Signed(w) => match w {
             1 => "boolean",
             2..=15 => "short",
             16..=31 => "int",
@@ -81,7 +79,7 @@ fn magma_to_java_zero(m: Magma) -> String {
         RawMagma::Nibble => "UnsignedByte.of(0)",
         RawMagma::Byte => "UnsignedByte.of(0)",
         RawMagma::Native => "Bytes.EMPTY",
-        RawMagma::Integer(w) => match w {
+        RawMagma::Integer(w) | RawMagma::Signed(w) => match w {
             1 => "false",
             2..=31 => "0",
             32..=63 => "0L",
@@ -97,7 +95,7 @@ fn magma_to_java_bytewidth(m: Magma) -> i16 {
     match m.rm() {
         RawMagma::Binary | RawMagma::Nibble | RawMagma::Byte => 1,
         RawMagma::Native => 32,
-        RawMagma::Integer(w) => match w {
+        RawMagma::Integer(w) | RawMagma::Signed(w) => match w {
             1 => 1,
             2..=15 => 2,
             16..=31 => 4,
@@ -115,7 +113,7 @@ fn magma_to_java_putter(m: Magma, register: &str) -> String {
         RawMagma::Nibble | RawMagma::Byte => {
             format!("{}.put(b.toByte());", &register)
         }
-        RawMagma::Integer(w) => match w {
+        RawMagma::Integer(w) | RawMagma::Signed(w) => match w {
             1 => format!("{}.put((byte) (b ? 1 : 0));", &register),
             2..=15 => format!("{}.putShort(b);", &register),
             16..=31 => format!("{}.putInt(b);", &register),
@@ -159,7 +157,12 @@ fn perspectivize_name(h: &Handle, p: &str) -> String {
     )
 }
 
-pub fn render(cs: &ConstraintSet, package: &str, output_path: Option<&String>) -> Result<()> {
+pub fn render(
+    cs: &ConstraintSet,
+    package: &str,
+    output_path: Option<&String>,
+) -> Result<Option<String>> {
+    let _phase = crate::run_stats::phase("export");
     let registers = cs
         .columns
         .registers
@@ -231,6 +234,19 @@ pub fn render(cs: &ConstraintSet, package: &str, output_path: Option<&String>) -
         .sorted_by_cached_key(|c| c.name.to_owned())
         .collect::<Vec<_>>();
 
+    super::ensure_unique(
+        "Java registers",
+        registers
+            .iter()
+            .map(|r| (r.java_name.clone(), r.corset_name.clone())),
+    )?;
+    super::ensure_unique(
+        "Java constants",
+        cs.constants
+            .keys()
+            .map(|h| (crate::utils::purify(&h.name), h.to_string())),
+    )?;
+
     let mut handlebars = Handlebars::new();
     handlebars.register_escape_fn(handlebars::no_escape);
 
@@ -254,13 +270,14 @@ pub fn render(cs: &ConstraintSet, package: &str, output_path: Option<&String>) -
 
             let trace_columns_java_filepath = Path::new(f).join("Trace.java");
 
-            File::create(&trace_columns_java_filepath)?
-                .write_all(trace_columns_render.as_bytes())
-                .with_context(|| anyhow!("writing to {:?}", trace_columns_java_filepath))?;
+            super::write_unit(
+                &trace_columns_java_filepath.display().to_string(),
+                &trace_columns_render,
+            )
         }
         None => {
             println!("{trace_columns_render}");
+            Ok(None)
         }
     }
-    Ok(())
 }
@@ -0,0 +1,179 @@
+pragma circom 2.1.0;
+
+// Constrain `in` to fit in `n` bits
+template Bits(n) {
+    signal input in;
+    signal bits[n];
+
+    var acc = 0;
+    var e = 1;
+    for (var i = 0; i < n; i++) {
+        bits[i] <-- (in >> i) & 1;
+        bits[i] * (bits[i] - 1) === 0;
+        acc += bits[i] * e;
+        e = e + e;
+    }
+    acc === in;
+}
+
+// Constrain `in` to be strictly smaller than `max`, with `max - 1` fitting
+// in `n` bits
+template InRange(n, max) {
+    signal input in;
+
+    component lo = Bits(n);
+    lo.in <== in;
+    component hi = Bits(n);
+    hi.in <== max - 1 - in;
+}
+
+// Fold the rows of `w` columns of length `n` into single values, as a random
+// linear combination of challenge `alpha`
+template Compress(w, n) {
+    signal input alpha;
+    signal input in[w][n];
+    signal output out[n];
+
+    signal powers[w];
+    powers[0] <== 1;
+    for (var c = 1; c < w; c++) {
+        powers[c] <== powers[c - 1] * alpha;
+    }
+
+    signal terms[w][n];
+    for (var j = 0; j < n; j++) {
+        var acc = 0;
+        for (var c = 0; c < w; c++) {
+            terms[c][j] <== powers[c] * in[c][j];
+            acc += terms[c][j];
+        }
+        out[j] <== acc;
+    }
+}
+
+// Constrain the rows of `to` to be a permutation of the rows of `from`, with
+// a grand product argument of challenges `alpha` and `gamma`
+template Permutation(w, n) {
+    signal input alpha;
+    signal input gamma;
+    signal input from[w][n];
+    signal input to[w][n];
+
+    component f = Compress(w, n);
+    component t = Compress(w, n);
+    f.alpha <== alpha;
+    t.alpha <== alpha;
+    for (var c = 0; c < w; c++) {
+        for (var j = 0; j < n; j++) {
+            f.in[c][j] <== from[c][j];
+            t.in[c][j] <== to[c][j];
+        }
+    }
+
+    signal fprod[n];
+    signal tprod[n];
+    fprod[0] <== f.out[0] + gamma;
+    tprod[0] <== t.out[0] + gamma;
+    for (var j = 1; j < n; j++) {
+        fprod[j] <== fprod[j - 1] * (f.out[j] + gamma);
+        tprod[j] <== tprod[j - 1] * (t.out[j] + gamma);
+    }
+    fprod[n - 1] === tprod[n - 1];
+}
+
+// Constrain the `m` rows of `included` to appear among the `n` rows of
+// `including`, with a logarithmic derivative argument of challenges `alpha`
+// and `gamma`
+template Inclusion(w, n, m) {
+    signal input alpha;
+    signal input gamma;
+    signal input including[w][n];
+    signal input included[w][m];
+
+    component t = Compress(w, n);
+    component a = Compress(w, m);
+    t.alpha <== alpha;
+    a.alpha <== alpha;
+    for (var c = 0; c < w; c++) {
+        for (var k = 0; k < n; k++) {
+            t.in[c][k] <== including[c][k];
+        }
+        for (var j = 0; j < m; j++) {
+            a.in[c][j] <== included[c][j];
+        }
+    }
+
+    // how many rows of `included` each row of `including` matches; duplicated
+    // rows of `including` are only counted on their first occurrence
+    signal count[n];
+    for (var k = 0; k < n; k++) {
+        var first = 1;
+        for (var l = 0; l < k; l++) {
+            if (t.out[l] == t.out[k]) {
+                first = 0;
+            }
+        }
+        var matches = 0;
+        if (first == 1) {
+            for (var j = 0; j < m; j++) {
+                if (a.out[j] == t.out[k]) {
+                    matches += 1;
+                }
+            }
+        }
+        count[k] <-- matches;
+    }
+
+    signal ainv[m];
+    var lhs = 0;
+    for (var j = 0; j < m; j++) {
+        ainv[j] <-- 1 / (gamma - a.out[j]);
+        ainv[j] * (gamma - a.out[j]) === 1;
+        lhs += ainv[j];
+    }
+    signal tinv[n];
+    signal terms[n];
+    var rhs = 0;
+    for (var k = 0; k < n; k++) {
+        tinv[k] <-- 1 / (gamma - t.out[k]);
+        tinv[k] * (gamma - t.out[k]) === 1;
+        terms[k] <== count[k] * tinv[k];
+        rhs += terms[k];
+    }
+    lhs === rhs;
+}
+
+// The constraint system exported by Corset, parameterized by the length of
+// the columns of each module. `alpha` and `gamma` are the challenges of the
+// lookup and permutation arguments, and must only be drawn once the columns
+// have been committed to.
+template Corset({{ lengths }}) {
+    {{ #if challenges }}
+    signal input alpha;
+    signal input gamma;
+    {{ /if }}
+
+    //
+    // Committed columns
+    //
+    {{ #each columns }}
+    // {{{ json_register }}}
+    signal input {{ circom_id }}[{{ size }}];
+    {{ /each }}
+
+
+    //
+    // Interleaved columns
+    //
+    {{ #each interleaved }}
+{{{ this }}}
+    {{ /each }}
+
+
+    //
+    // Constraints
+    //
+    {{ #each constraints }}
+{{{ this }}}
+    {{ /each }}
+}
@@ -0,0 +1,738 @@
+use anyhow::*;
+use handlebars::Handlebars;
+use itertools::Itertools;
+use log::*;
+use num_traits::ToPrimitive;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use super::{
+    wizardiop::{reg, reg_mangle, reg_mangle_ith, reg_splatter, render_sort_order},
+    Mangling, ModuleFilter,
+};
+use crate::{column::Computation, compiler::*, pretty::Pretty};
+
+const TEMPLATE: &str = include_str!("circom.circom");
+
+/// Turn a mangled symbol into a valid circom identifier
+fn circom_ident(s: String) -> String {
+    const KEYWORDS: &[&str] = &[
+        "alpha",
+        "assert",
+        "bus",
+        "component",
+        "custom",
+        "do",
+        "else",
+        "for",
+        "function",
+        "gamma",
+        "i",
+        "if",
+        "include",
+        "input",
+        "log",
+        "main",
+        "output",
+        "parallel",
+        "pragma",
+        "public",
+        "return",
+        "signal",
+        "template",
+        "var",
+        "while",
+    ];
+    let mut r = s
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '$' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if r.starts_with(|c: char| c.is_ascii_digit()) || KEYWORDS.contains(&r.as_str()) {
+        r.insert(0, '_');
+    }
+    r
+}
+
+fn column(cs: &ConstraintSet, m: &Mangling, c: &ColumnRef) -> Result<String> {
+    reg_mangle(cs, m, c).map(circom_ident)
+}
+
+fn column_ith(cs: &ConstraintSet, m: &Mangling, c: &ColumnRef, i: usize) -> Result<String> {
+    reg_mangle_ith(cs, m, c, i).map(circom_ident)
+}
+
+/// The template parameter holding the length of the columns of `module`
+fn length_param(module: &str) -> String {
+    circom_ident(format!("LEN_{}", module))
+}
+
+/// The length of the column `c`, as a circom expression
+fn size(cs: &ConstraintSet, m: &Mangling, c: &ColumnRef) -> Result<String> {
+    let module = length_param(&m.mangled_module(&cs.columns.column(c)?.handle));
+    Ok(match cs.length_multiplier(c) {
+        1 => module,
+        k => format!("{} * {}", k, module),
+    })
+}
+
+/// Indent all the lines of `s` by one level
+fn indent(s: &str) -> String {
+    s.lines().map(|l| format!("    {}", l)).join("\n")
+}
+
+/// Enclose `body` in a loop of `i` from `first` to `end`, excluded
+fn for_loop(first: &str, end: &str, body: &[String]) -> String {
+    format!(
+        "for (var i = {}; i < {}; i++) {{\n{}\n}}",
+        first,
+        end,
+        indent(&body.join("\n"))
+    )
+}
+
+/// Render the access to the cell of `c` at the current row shifted by `shift`
+fn render_cell(c: &str, shift: isize) -> String {
+    match shift {
+        0 => format!("{}[i]", c),
+        _ if shift < 0 => format!("{}[i - {}]", c, -shift),
+        _ => format!("{}[i + {}]", c, shift),
+    }
+}
+
+/// The rows a constraint is enforced on
+struct Rows {
+    /// the first row, as a circom expression
+    first: String,
+    /// the number of rows, as a circom expression
+    count: String,
+    /// the index of the current row among them
+    index: String,
+}
+impl Rows {
+    /// All the rows of columns of length `size` where `expr` does not read out
+    /// of its columns
+    fn everywhere(size: &str, expr: &Node) -> Self {
+        let past = -expr.past_spill();
+        let spill = past + expr.future_spill();
+        Rows {
+            first: past.to_string(),
+            count: if spill == 0 {
+                size.to_owned()
+            } else {
+                format!("{} - {}", size, spill)
+            },
+            index: if past == 0 {
+                "i".into()
+            } else {
+                format!("i - {}", past)
+            },
+        }
+    }
+
+    /// The single row `i` of columns of length `size`, negative rows being
+    /// counted from the end
+    fn at(size: &str, i: isize) -> Self {
+        Rows {
+            first: if i < 0 {
+                format!("{} - {}", size, -i)
+            } else {
+                i.to_string()
+            },
+            count: "1".into(),
+            index: "0".into(),
+        }
+    }
+
+    /// The rows, of columns of length `size`, on which `expr` is enforced
+    /// over `domain`
+    fn of(size: &str, domain: Option<&Domain<isize>>, expr: &Node) -> Vec<Self> {
+        match domain {
+            None => vec![Rows::everywhere(size, expr)],
+            Some(domain) => domain.iter().map(|i| Rows::at(size, i)).collect(),
+        }
+    }
+
+    /// Enclose `body` in a loop over these rows
+    fn render_loop(&self, body: &[String]) -> String {
+        let end = if self.first == "0" {
+            self.count.clone()
+        } else {
+            format!("{} + {}", self.first, self.count)
+        };
+        for_loop(&self.first, &end, body)
+    }
+}
+
+/// Lower the polynomial expressions of a constraint to quadratic circom
+/// constraints, introducing an intermediate signal for every product of two
+/// non-constant terms
+struct Lowering<'a> {
+    cs: &'a ConstraintSet,
+    m: &'a Mangling,
+    /// the prefix of the intermediate signals
+    prefix: String,
+    rows: &'a Rows,
+    /// the intermediate signals, along with the factors of their product
+    intermediates: Vec<(String, String, String)>,
+}
+impl<'a> Lowering<'a> {
+    fn new(cs: &'a ConstraintSet, m: &'a Mangling, prefix: String, rows: &'a Rows) -> Self {
+        Lowering {
+            cs,
+            m,
+            prefix,
+            rows,
+            intermediates: Vec::new(),
+        }
+    }
+
+    /// Render `e` as a linear circom expression, along with whether it is
+    /// constant
+    fn lower(&mut self, e: &Node) -> Result<(String, bool)> {
+        match e.e() {
+            // circuits are usually compiled over another field than the native
+            // one, so the constants are rendered as signed integers
+            Expression::Const(x) => {
+                let x = x.to_signed_bi()?;
+                Ok((
+                    if x.sign() == num_bigint::Sign::Minus {
+                        format!("({})", x)
+                    } else {
+                        x.to_string()
+                    },
+                    true,
+                ))
+            }
+            Expression::Column { handle, shift, .. } => Ok((
+                render_cell(&column(self.cs, self.m, handle)?, *shift as isize),
+                false,
+            )),
+            Expression::Funcall { func, args } => self.lower_funcall(func, args),
+            Expression::Void => {
+                warn!("Rendering VOID expression");
+                Ok(("0".into(), true))
+            }
+            // lists are split into as many constraints by render_constraint, and
+            // exo-columns can only appear at the top level of a constraint
+            Expression::List(_) | Expression::ExoColumn { .. } | Expression::ArrayColumn { .. } => {
+                bail!("unable to render {} within an expression", e.pretty())
+            }
+        }
+    }
+
+    fn lower_sum(&mut self, xs: &[Node], operator: &str) -> Result<(String, bool)> {
+        let xs = xs
+            .iter()
+            .map(|x| self.lower(x))
+            .collect::<Result<Vec<_>>>()?;
+        Ok((
+            format!(
+                "({})",
+                xs.iter().map(|x| &x.0).join(&format!(" {} ", operator))
+            ),
+            xs.iter().all(|x| x.1),
+        ))
+    }
+
+    fn lower_product(&mut self, xs: &[Node]) -> Result<(String, bool)> {
+        let mut r: Option<(String, bool)> = None;
+        for x in xs {
+            let x = self.lower(x)?;
+            r = Some(match r {
+                None => x,
+                Some(ax) if ax.1 || x.1 => (format!("({} * {})", ax.0, x.0), ax.1 && x.1),
+                Some(ax) => {
+                    let signal = format!(
+                        "{}_t{}[{}]",
+                        self.prefix,
+                        self.intermediates.len(),
+                        self.rows.index
+                    );
+                    self.intermediates.push((signal.clone(), ax.0, x.0));
+                    (signal, false)
+                }
+            });
+        }
+        r.ok_or_else(|| anyhow!("empty product"))
+    }
+
+    fn lower_funcall(&mut self, func: &Intrinsic, args: &[Node]) -> Result<(String, bool)> {
+        match func {
+            Intrinsic::Add => self.lower_sum(args, "+"),
+            Intrinsic::Sub | Intrinsic::VectorSub => self.lower_sum(args, "-"),
+            Intrinsic::Mul => self.lower_product(args),
+            Intrinsic::Exp => {
+                let exp = args[1]
+                    .pure_eval()
+                    .with_context(|| anyhow!("exponent {} is not evaluable", args[1].pretty()))?
+                    .to_usize()
+                    .ok_or_else(|| anyhow!("exponent {} is too large", args[1].pretty()))?;
+                match exp {
+                    0 => Ok(("1".into(), true)),
+                    _ => self.lower_product(
+                        &std::iter::repeat(args[0].clone())
+                            .take(exp)
+                            .collect::<Vec<_>>(),
+                    ),
+                }
+            }
+            Intrinsic::Neg => {
+                let (x, constant) = self.lower(&args[0])?;
+                Ok((format!("(-{})", x), constant))
+            }
+            x => bail!("unable to render {:?} in a circom expression", x),
+        }
+    }
+
+    /// Enforce `x`, as lowered by [`Lowering::lower`], to vanish over the rows
+    fn vanishes(mut self, x: String) -> String {
+        // a product may be constrained directly instead of going through an
+        // intermediate signal
+        let body = match self.intermediates.last() {
+            Some((signal, _, _)) if *signal == x => {
+                let (_, a, b) = self.intermediates.pop().unwrap();
+                format!("{} * {} === 0;", a, b)
+            }
+            _ => format!("{} === 0;", x),
+        };
+        self.render(vec![body])
+    }
+
+    /// The declarations of the intermediate signals, followed by the loop
+    /// assigning them and enforcing `body` over the rows
+    fn render(self, body: Vec<String>) -> String {
+        (0..self.intermediates.len())
+            .map(|k| format!("signal {}_t{}[{}];", self.prefix, k, self.rows.count))
+            .chain(std::iter::once(
+                self.rows.render_loop(
+                    &self
+                        .intermediates
+                        .iter()
+                        .map(|(signal, a, b)| format!("{} <== {} * {};", signal, a, b))
+                        .chain(body)
+                        .collect::<Vec<_>>(),
+                ),
+            ))
+            .join("\n")
+    }
+}
+
+/// The length of the columns a constraint applies to, as a circom expression
+fn constraint_size(cs: &ConstraintSet, m: &Mangling, name: &str, expr: &Node) -> Result<String> {
+    let dependency = expr
+        .dependencies()
+        .into_iter()
+        .map(|c| column(cs, m, &c).map(|name| (name, c)))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .min_by(|a, b| a.0.cmp(&b.0))
+        .ok_or_else(|| anyhow!("constraint {} does not read any column", name))?;
+    size(cs, m, &dependency.1)
+}
+
+/// Render the components of `xs`, splattering exo-columns in their registers,
+/// along with their length
+fn columns_of(cs: &ConstraintSet, m: &Mangling, xs: &[Node]) -> Result<(Vec<String>, String)> {
+    let mut r = Vec::new();
+    let mut len = None;
+    for x in xs {
+        match x.e() {
+            Expression::Column { handle, .. } => r.push(column(cs, m, handle)?),
+            Expression::ExoColumn { handle, .. } => {
+                for i in 0..cs.columns.register_of(handle).width() {
+                    r.push(column_ith(cs, m, handle, i)?);
+                }
+            }
+            _ => bail!("unable to render {} as a column", x.pretty()),
+        }
+        if len.is_none() {
+            len = Some(size(cs, m, &x.dependencies().into_iter().next().unwrap())?);
+        }
+    }
+    Ok((r, len.ok_or_else(|| anyhow!("no columns"))?))
+}
+
+/// Render the wiring of `columns`, of length `len`, into `input` of
+/// component `component`
+fn render_wiring(component: &str, input: &str, columns: &[String], len: &str) -> String {
+    for_loop(
+        "0",
+        len,
+        &columns
+            .iter()
+            .enumerate()
+            .map(|(k, c)| format!("{}.{}[{}][i] <== {}[i];", component, input, k, c))
+            .collect::<Vec<_>>(),
+    )
+}
+
+struct Renderer<'a> {
+    cs: &'a ConstraintSet,
+    m: &'a Mangling,
+    /// the number of constraints rendered so far, to name their intermediate
+    /// signals and components
+    count: usize,
+    /// whether the rendered constraints require the challenges
+    challenges: bool,
+}
+impl Renderer<'_> {
+    fn prefix(&mut self) -> String {
+        self.count += 1;
+        format!("c{}", self.count)
+    }
+
+    fn render_constraint(
+        &mut self,
+        name: &str,
+        domain: Option<&Domain<isize>>,
+        expr: &Node,
+    ) -> Result<Vec<String>> {
+        let (cs, m) = (self.cs, self.m);
+        match expr.e() {
+            Expression::List(xs) => Ok(xs
+                .iter()
+                .enumerate()
+                .map(|(i, x)| self.render_constraint(&format!("{}#{}", name, i), domain, x))
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .flatten()
+                .collect()),
+            Expression::ExoColumn { handle, shift, .. } => {
+                let size = size(cs, m, handle)?;
+                let mut r = Vec::new();
+                for i in 0..cs.columns.register_of(handle).width() {
+                    let column = column_ith(cs, m, handle, i)?;
+                    for rows in Rows::of(&size, domain, expr) {
+                        r.push(format!(
+                            "// {}/{}\n{}",
+                            name,
+                            i,
+                            rows.render_loop(&[format!(
+                                "{} === 0;",
+                                render_cell(&column, *shift as isize)
+                            )])
+                        ));
+                    }
+                }
+                Ok(r)
+            }
+            _ => {
+                let size = constraint_size(cs, m, name, expr)?;
+                let mut r = Vec::new();
+                for rows in Rows::of(&size, domain, expr) {
+                    let mut lowering = Lowering::new(cs, m, self.prefix(), &rows);
+                    let (x, _) = lowering.lower(expr)?;
+                    r.push(format!("// {}\n{}", name, lowering.vanishes(x)));
+                }
+                Ok(r)
+            }
+        }
+    }
+
+    fn render_constraints(&mut self, modules: &ModuleFilter) -> Result<Vec<String>> {
+        let (cs, m) = (self.cs, self.m);
+        let mut r = Vec::new();
+        for constraint in cs
+            .constraints
+            .iter()
+            .filter(|c| modules.exports(cs, c))
+            .sorted_by_key(|c| c.name())
+        {
+            r.extend(super::span_comment(cs, constraint.handle()));
+            match constraint {
+                Constraint::Vanishes {
+                    handle,
+                    domain,
+                    expr,
+                } => {
+                    r.extend(self.render_constraint(&handle.to_string(), domain.as_ref(), expr)?)
+                }
+                Constraint::Lookup {
+                    handle,
+                    including,
+                    included,
+                } => {
+                    self.challenges = true;
+                    let component = self.prefix();
+                    let (including, including_len) = columns_of(cs, m, including)?;
+                    let (included, included_len) = columns_of(cs, m, included)?;
+                    r.push(
+                        [
+                            format!("// {}", handle),
+                            format!(
+                                "component {} = Inclusion({}, {}, {});",
+                                component,
+                                including.len(),
+                                including_len,
+                                included_len
+                            ),
+                            format!("{}.alpha <== alpha;", component),
+                            format!("{}.gamma <== gamma;", component),
+                            render_wiring(&component, "including", &including, &including_len),
+                            render_wiring(&component, "included", &included, &included_len),
+                        ]
+                        .join("\n"),
+                    )
+                }
+                Constraint::Permutation {
+                    handle, from, to, ..
+                } => {
+                    self.challenges = true;
+                    let component = self.prefix();
+                    let len = size(cs, m, &from[0])?;
+                    let from = from
+                        .iter()
+                        .map(|c| column(cs, m, c))
+                        .collect::<Result<Vec<_>>>()?;
+                    let to_columns = to
+                        .iter()
+                        .map(|c| column(cs, m, c))
+                        .collect::<Result<Vec<_>>>()?;
+                    r.push(
+                        std::iter::once(format!("// {}", handle))
+                            .chain(render_sort_order(cs, m, to))
+                            .chain([
+                                format!(
+                                    "component {} = Permutation({}, {});",
+                                    component,
+                                    from.len(),
+                                    len
+                                ),
+                                format!("{}.alpha <== alpha;", component),
+                                format!("{}.gamma <== gamma;", component),
+                                render_wiring(&component, "from", &from, &len),
+                                render_wiring(&component, "to", &to_columns, &len),
+                            ])
+                            .join("\n"),
+                    )
+                }
+                Constraint::InRange { handle, exp, max } => {
+                    let size = constraint_size(cs, m, &handle.to_string(), exp)?;
+                    let rows = Rows::everywhere(&size, exp);
+                    let prefix = self.prefix();
+                    let mut lowering = Lowering::new(cs, m, prefix.clone(), &rows);
+                    let (x, _) = lowering.lower(exp)?;
+                    let max = max.to_bi();
+                    let bits = (max.clone() - 1u32).bits().max(1);
+                    r.push(format!(
+                        "// {}\ncomponent {}[{}];\n{}",
+                        handle,
+                        prefix,
+                        rows.count,
+                        lowering.render(vec![
+                            format!("{}[{}] = InRange({}, {});", prefix, rows.index, bits, max),
+                            format!("{}[{}].in <== {};", prefix, rows.index, x),
+                        ])
+                    ))
+                }
+                Constraint::Normalization {
+                    handle,
+                    reference,
+                    inverted,
+                } => {
+                    let x = reference.clone();
+                    let inv_x = Node::column().handle(inverted.clone()).build();
+                    let one_minus_x_inv_x = Intrinsic::Sub.call(&[
+                        Node::from_isize(1),
+                        Intrinsic::Mul.call(&[x.clone(), inv_x.clone()])?,
+                    ])?;
+
+                    // X × (1 - X × /X)
+                    r.extend(self.render_constraint(
+                        &format!("{}#1", handle),
+                        None,
+                        &Intrinsic::Mul.call(&[x, one_minus_x_inv_x.clone()])?,
+                    )?);
+                    // /X × (1 - X × /X)
+                    r.extend(self.render_constraint(
+                        &format!("{}#2", handle),
+                        None,
+                        &Intrinsic::Mul.call(&[inv_x, one_minus_x_inv_x])?,
+                    )?);
+                }
+            }
+        }
+        Ok(r)
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct CircomColumn {
+    circom_id: String,
+    json_register: String,
+    size: String,
+}
+
+fn render_columns(
+    cs: &ConstraintSet,
+    m: &Mangling,
+    modules: &ModuleFilter,
+) -> Result<Vec<CircomColumn>> {
+    let mut r = Vec::new();
+    for (reference, column) in cs
+        .columns
+        .iter()
+        .filter(|(r, _)| {
+            cs.computations
+                .computation_for(r)
+                .map(|c| c.is_interleaved())
+                != Some(true)
+        })
+        .sorted_by_cached_key(|(_, c)| c.handle.mangle())
+        .filter(|(_, c)| c.used && modules.contains(&c.handle.module))
+    {
+        let register = cs.columns.register_of(&reference);
+        let size = size(cs, m, &reference)?;
+        if register.width() > 1 {
+            for i in 0..register.width() {
+                r.push(CircomColumn {
+                    circom_id: column_ith(cs, m, &reference, i)?,
+                    json_register: reg_splatter(cs, &column.handle, i)?.to_string(),
+                    size: size.clone(),
+                });
+            }
+        } else {
+            r.push(CircomColumn {
+                circom_id: self::column(cs, m, &reference)?,
+                json_register: reg(cs, &column.handle)?.to_string(),
+                size,
+            });
+        }
+    }
+    Ok(r)
+}
+
+/// Render the interleaved columns, as signals constrained to the rows of the
+/// columns they interleave
+fn render_interleaved(
+    cs: &ConstraintSet,
+    m: &Mangling,
+    modules: &ModuleFilter,
+) -> Result<Vec<(String, String)>> {
+    let mut r = Vec::new();
+    for (h, _) in cs
+        .columns
+        .iter()
+        .sorted_by_cached_key(|(_, c)| c.handle.mangle())
+        .filter(|(_, c)| c.used && modules.contains(&c.handle.module))
+    {
+        if let Some(Computation::Interleaved { froms, .. }) = cs.computations.computation_for(&h) {
+            let id = column(cs, m, &h)?;
+            r.push((
+                id.clone(),
+                format!(
+                    "signal {}[{}];\n{}",
+                    id,
+                    size(cs, m, &h)?,
+                    for_loop(
+                        "0",
+                        &size(cs, m, &froms[0])?,
+                        &froms
+                            .iter()
+                            .enumerate()
+                            .map(|(k, from)| {
+                                Ok(format!(
+                                    "{}[{} * i + {}] <== {}[i];",
+                                    id,
+                                    froms.len(),
+                                    k,
+                                    column(cs, m, from)?
+                                ))
+                            })
+                            .collect::<Result<Vec<_>>>()?
+                    )
+                ),
+            ));
+        }
+    }
+    Ok(r)
+}
+
+#[cfg(test)]
+pub(crate) fn render_constraints(
+    cs: &ConstraintSet,
+    m: &Mangling,
+    modules: &ModuleFilter,
+) -> Result<Vec<String>> {
+    Renderer {
+        cs,
+        m,
+        count: 0,
+        challenges: false,
+    }
+    .render_constraints(modules)
+}
+
+/// Render `cs` as a circom template enforcing its constraints, parameterized
+/// by the length of the columns of each module; lookups and permutations are
+/// enforced by arguments relying on two challenges provided as inputs.
+pub fn render(
+    cs: &ConstraintSet,
+    out_filename: Option<&String>,
+    m: &Mangling,
+    modules: &ModuleFilter,
+) -> Result<Option<String>> {
+    let _phase = crate::run_stats::phase("export");
+    #[derive(Serialize)]
+    struct TemplateData {
+        lengths: String,
+        challenges: bool,
+        columns: Vec<CircomColumn>,
+        interleaved: Vec<String>,
+        constraints: Vec<String>,
+    }
+
+    let mut hb = Handlebars::new();
+    hb.set_dev_mode(true);
+    hb.set_strict_mode(true);
+
+    let columns = render_columns(cs, m, modules)?;
+    let interleaved = render_interleaved(cs, m, modules)?;
+    super::ensure_unique(
+        "circom identifiers",
+        columns
+            .iter()
+            .map(|c| (c.circom_id.clone(), c.json_register.clone()))
+            .chain(interleaved.iter().map(|(id, _)| (id.clone(), id.clone()))),
+    )?;
+    let lengths = cs
+        .columns
+        .iter_cols()
+        .filter(|c| c.used && modules.contains(&c.handle.module))
+        .map(|c| length_param(&m.mangled_module(&c.handle)))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .join(", ");
+
+    let mut renderer = Renderer {
+        cs,
+        m,
+        count: 0,
+        challenges: false,
+    };
+    let constraints = renderer.render_constraints(modules)?;
+
+    let r = hb.render_template(
+        TEMPLATE,
+        &TemplateData {
+            lengths,
+            challenges: renderer.challenges,
+            columns,
+            interleaved: interleaved.into_iter().map(|(_, i)| indent(&i)).collect(),
+            constraints: constraints.iter().map(|c| indent(c)).collect(),
+        },
+    )?;
+
+    if let Some(filename) = out_filename {
+        super::write_unit(filename, &r)
+    } else {
+        println!("{}", r);
+        Ok(None)
+    }
+}
@@ -1,4 +1,4 @@
-use std::{cmp::Ordering, collections::HashMap, io::Write};
+use std::{cmp::Ordering, collections::HashMap};
 
 use crate::compiler::{ConstraintSet, Kind};
 use anyhow::*;
@@ -26,7 +26,8 @@ struct TemplateData {
     modules: Vec<ConflaterModule>,
 }
 
-pub fn render(cs: &ConstraintSet, outfile: Option<&String>) -> Result<()> {
+pub fn render(cs: &ConstraintSet, outfile: Option<&String>) -> Result<Option<String>> {
+    let _phase = crate::run_stats::phase("export");
     const TEMPLATE: &str = include_str!("conflater.kt");
     let mut modules: HashMap<String, Vec<ConflaterColumn>> = Default::default();
     for c in cs.columns.iter_cols() {
@@ -67,12 +68,9 @@ pub fn render(cs: &ConstraintSet, outfile: Option<&String>) -> Result<()> {
 
     let r = Handlebars::new().render_template(TEMPLATE, &TemplateData { modules })?;
     if let Some(filename) = outfile.as_ref() {
-        std::fs::File::create(filename)
-            .with_context(|| format!("while creating `{}`", filename))?
-            .write_all(r.as_bytes())
-            .with_context(|| format!("while writing to `{}`", filename))
+        super::write_unit(filename, &r)
     } else {
         println!("{}", r);
-        Ok(())
+        Ok(None)
     }
 }
@@ -19,7 +19,7 @@ pub(crate) fn to_csv(cs: &ConstraintSet, exclude: &[String], filename: &str) ->
         .modules()
         .par_iter()
         .map(|module| {
-            if exclude.contains(&module) {
+            if exclude.contains(module) {
                 return Ok(());
             }
 
@@ -35,18 +35,18 @@ pub(crate) fn to_csv(cs: &ConstraintSet, exclude: &[String], filename: &str) ->
             info!("Exporting {}", module);
             let column_names = cs
                 .columns
-                .iter_module(&module)
+                .iter_module(module)
                 .map(|c| cs.handle(&c.0))
                 .sorted()
                 .collect::<Vec<_>>();
 
-            file.write(column_names.iter().map(|h| &h.name).join(",").as_bytes())?;
-            file.write(&[b'\n'])?;
-            let max_i = cs.iter_len(&module);
+            file.write_all(column_names.iter().map(|h| &h.name).join(",").as_bytes())?;
+            file.write_all(b"\n")?;
+            let max_i = cs.iter_len(module);
             for i in 0..max_i {
-                file.write(
+                file.write_all(
                     cs.columns
-                        .iter_module(&module)
+                        .iter_module(module)
                         .map(|col| {
                             cs.columns
                                 .get(&col.0, i.try_into().unwrap(), false)
@@ -56,7 +56,7 @@ pub(crate) fn to_csv(cs: &ConstraintSet, exclude: &[String], filename: &str) ->
                         .join(",")
                         .as_bytes(),
                 )?;
-                file.write(&[b'\n'])?;
+                file.write_all(b"\n")?;
             }
 
             Ok(file.flush()?)
@@ -64,6 +64,14 @@ pub(crate) fn to_csv(cs: &ConstraintSet, exclude: &[String], filename: &str) ->
         .collect::<Result<_>>()
 }
 
+pub(crate) fn to_lt(cs: &ConstraintSet, exclude: &[String], filename: &str) -> Result<()> {
+    let mut out = BufWriter::new(
+        File::create(filename).with_context(|| anyhow!("opening {}", filename.bold().yellow()))?,
+    );
+    crate::import::write_binary_trace(cs, exclude, false, &mut out)?;
+    Ok(out.flush()?)
+}
+
 pub(crate) fn to_json(cs: &ConstraintSet, exclude: &[String], filename: &str) -> Result<()> {
     let mut out = BufWriter::new(
         File::create(filename).with_context(|| anyhow!("opening {}", filename.bold().yellow()))?,
@@ -1,6 +1,6 @@
 use crate::column::Computation;
 use crate::compiler::codetyper::Tty;
-use crate::compiler::{Constraint, ConstraintSet, Expression, Intrinsic, Node};
+use crate::compiler::{bounds, Constraint, ConstraintSet, Expression, Intrinsic, Node};
 use crate::constants;
 use crate::pretty::Pretty;
 use crate::structs::Handle;
@@ -9,7 +9,10 @@ use ellipse::Ellipse;
 use itertools::Itertools;
 use owo_colors::XtermColors;
 use owo_colors::{colored::Color, OwoColorize};
+use regex_lite::Regex;
+use serde::Serialize;
 use std::cmp::Ordering;
+use std::io::Write;
 
 fn priority(a: Intrinsic, b: Intrinsic) -> Ordering {
     match (a, b) {
@@ -103,6 +106,20 @@ fn pretty_expr(n: &Node, prev: Option<Intrinsic>, tty: &mut Tty, show_types: boo
                 pretty_expr(&args[0], prev, tty, show_types);
                 tty.write(")");
             }
+            Intrinsic::Sgn => {
+                tty.write("SGN(");
+                pretty_expr(&args[0], prev, tty, show_types);
+                tty.write(")");
+            }
+            Intrinsic::Bits => {
+                tty.write("BITS(");
+                pretty_expr(&args[0], prev, tty, show_types);
+                for arg in args[1..].iter() {
+                    tty.write(", ");
+                    pretty_expr(arg, prev, tty, show_types);
+                }
+                tty.write(")");
+            }
             Intrinsic::Begin => todo!(),
             Intrinsic::IfZero => {
                 tty.write("if-zero ".color(c).bold().to_string());
@@ -185,12 +202,14 @@ fn pretty_expr(n: &Node, prev: Option<Intrinsic>, tty: &mut Tty, show_types: boo
 
 fn render_constraints(
     cs: &ConstraintSet,
+    title: &str,
+    constraints: &[Constraint],
     only: Option<&Vec<String>>,
     skip: &[String],
     show_types: bool,
 ) {
-    println!("\n{}", "=== Constraints ===".bold().yellow());
-    for c in cs.constraints.iter() {
+    println!("\n{}", format!("=== {} ===", title).bold().yellow());
+    for c in constraints.iter() {
         if !skip.contains(&c.name()) && only.map(|o| o.contains(&c.name())).unwrap_or(true) {
             match c {
                 Constraint::Vanishes {
@@ -208,6 +227,11 @@ fn render_constraints(
                             String::new()
                         }
                     );
+                    if let Some(doc) = cs.docs.get(handle) {
+                        for l in doc.lines() {
+                            println!("{}", format!(";; {}", l).italic());
+                        }
+                    }
                     pretty_expr(expr, None, &mut tty, show_types);
                     println!("{}", tty.page_feed());
                 }
@@ -285,7 +309,11 @@ fn render_constraints(
 fn render_modules(cs: &ConstraintSet) {
     println!("\n{}", "=== Modules ===".bold().yellow());
     for (module, spilling) in cs.columns.spilling.iter().sorted_by_key(|s| s.0) {
-        println!("{}: spilling {}", module, spilling);
+        if let Some(length) = cs.columns.fixed_len.get(module) {
+            println!("{}: spilling {}, length {}", module, spilling, length);
+        } else {
+            println!("{}: spilling {}", module, spilling);
+        }
     }
 }
 
@@ -300,38 +328,190 @@ fn render_constants(cs: &ConstraintSet) {
     }
 }
 
+/// What is known of a column, as listed by the debugger and by the `columns`
+/// command
+#[derive(Serialize)]
+pub(crate) struct ColumnInfo {
+    #[serde(skip)]
+    pub handle: Handle,
+    pub id: usize,
+    pub module: String,
+    pub perspective: Option<String>,
+    pub name: String,
+    pub group: Option<String>,
+    #[serde(rename = "type")]
+    pub t: String,
+    pub multiplier: usize,
+    pub register: Option<usize>,
+    pub register_name: Option<String>,
+    pub register_width: Option<usize>,
+    pub padding: Option<String>,
+    /// the first line of the documentation of the column, if any
+    pub doc: Option<String>,
+}
+impl ColumnInfo {
+    fn register_label(&self) -> Option<String> {
+        self.register.map(|r| {
+            format!(
+                "r{}/{}ι{}",
+                r,
+                self.register_name.as_deref().unwrap_or("?"),
+                self.register_width.unwrap_or_default()
+            )
+        })
+    }
+}
+
+/// Collect the columns of `cs`, ordered by register
+pub(crate) fn columns_info(cs: &ConstraintSet) -> Vec<ColumnInfo> {
+    cs.columns
+        .iter()
+        .sorted_by_key(|c| c.1.register)
+        .map(|(r, col)| ColumnInfo {
+            handle: col.handle.clone(),
+            id: r.as_id(),
+            module: col.handle.module.clone(),
+            perspective: col.handle.perspective.clone(),
+            name: col.handle.name.clone(),
+            group: col.group.clone(),
+            t: col.t.to_string(),
+            multiplier: cs.length_multiplier(&r),
+            register: col.register,
+            register_name: col
+                .register
+                .and_then(|r| cs.columns.registers[r].handle.as_ref())
+                .map(|h| h.to_string()),
+            register_width: col.register.map(|r| cs.columns.registers[r].width()),
+            padding: col.padding_value.as_ref().map(|v| v.to_string()),
+            doc: col
+                .doc
+                .as_deref()
+                .and_then(|d| d.lines().next())
+                .map(str::to_owned),
+        })
+        .collect()
+}
+
 fn render_columns(cs: &ConstraintSet) {
     println!("\n{}", "=== Columns ===".bold().yellow());
 
     println!(
-        "{:>4}{:>80}{:>6}{:>4}{:>50}",
-        "ID", "Name", "Type", "×", "Reg."
+        "{:>4}{:>80}{:>16}{:>6}{:>4}{:>50}  Doc.",
+        "ID", "Name", "Group", "Type", "×", "Reg."
     );
-    for (r, col) in cs.columns.iter().sorted_by_key(|c| c.1.register) {
+    for col in columns_info(cs) {
         println!(
-            "{:>4}{:>80}{:>6}{:>4}{:>50}",
-            r.as_id(),
+            "{:>4}{:>80}{:>16}{:>6}{:>4}{:>50}  {}",
+            col.id,
             col.handle.to_string().as_str().truncate_ellipse(75),
-            col.t.to_string(),
-            cs.length_multiplier(&r),
-            col.register
-                .map(|r| format!(
-                    "r{}/{}ι{}",
-                    r,
-                    cs.columns.registers[r]
-                        .handle
-                        .as_ref()
-                        .map(|h| h.to_string())
-                        .unwrap_or("?".into()),
-                    cs.columns.registers[r].width()
-                ))
+            col.group
+                .as_deref()
+                .unwrap_or_default()
+                .truncate_ellipse(14),
+            col.t,
+            col.multiplier,
+            col.register_label()
                 .unwrap_or_default()
                 .as_str()
-                .truncate_ellipse(45)
+                .truncate_ellipse(45),
+            col.doc.as_deref().unwrap_or_default().italic()
         );
     }
 }
 
+/// The formats the `columns` command can list columns in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ListingFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+/// List the columns of `cs` to `out`, restricted to those of `module` if
+/// any, and to those whose name matches `filter` if any
+pub(crate) fn list_columns(
+    cs: &ConstraintSet,
+    module: Option<&str>,
+    filter: Option<&Regex>,
+    format: ListingFormat,
+    out: &mut dyn Write,
+) -> Result<()> {
+    if let Some(module) = module {
+        if !cs.columns.modules().contains(module) {
+            bail!("unknown module {}", module.red().bold());
+        }
+    }
+    let columns = columns_info(cs)
+        .into_iter()
+        .filter(|c| module.map_or(true, |m| c.module == m))
+        .filter(|c| filter.map_or(true, |f| f.is_match(&c.name)))
+        .collect::<Vec<_>>();
+
+    match format {
+        ListingFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, &columns)?;
+            writeln!(out)?;
+        }
+        ListingFormat::Csv => {
+            let escape = |s: &str| {
+                if s.contains([',', '"', '\n']) {
+                    format!("\"{}\"", s.replace('"', "\"\""))
+                } else {
+                    s.to_owned()
+                }
+            };
+            writeln!(out, "id,module,name,type,multiplier,register,padding")?;
+            for c in columns.iter() {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{}",
+                    c.id,
+                    escape(&c.module),
+                    escape(&c.name),
+                    c.t,
+                    c.multiplier,
+                    escape(&c.register_label().unwrap_or_default()),
+                    escape(c.padding.as_deref().unwrap_or_default())
+                )?;
+            }
+        }
+        ListingFormat::Table => {
+            let rows = columns
+                .iter()
+                .map(|c| {
+                    [
+                        c.id.to_string(),
+                        c.handle.to_string(),
+                        c.t.clone(),
+                        c.multiplier.to_string(),
+                        c.register_label().unwrap_or_default(),
+                        c.padding.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            let header = ["ID", "Name", "Type", "×", "Reg.", "Padding"].map(String::from);
+            let widths = (0..header.len())
+                .map(|i| {
+                    std::iter::once(&header)
+                        .chain(rows.iter())
+                        .map(|r| r[i].chars().count())
+                        .max()
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<_>>();
+            for row in std::iter::once(&header).chain(rows.iter()) {
+                let line = row
+                    .iter()
+                    .zip(widths.iter())
+                    .map(|(x, w)| format!("{:<w$}", x, w = w))
+                    .join("  ");
+                writeln!(out, "{}", line.trim_end())?;
+            }
+        }
+    }
+    Ok(())
+}
+
 fn render_computations(cs: &ConstraintSet) {
     println!("\n{}", "=== Computations ===".bold().yellow());
     for comp in cs.computations.iter() {
@@ -346,18 +526,33 @@ fn render_computations(cs: &ConstraintSet) {
                     froms.iter().map(|c| cs.handle(c).pretty()).join(", ")
                 )
             }
-            Computation::Sorted { froms, tos, signs } => println!(
-                "[{}] ⇳ [{}]",
+            Computation::Sorted {
+                froms,
+                tos,
+                signs,
+                stable,
+                tiebreaks,
+            } => println!(
+                "[{}] ⇳ [{}]{}{}",
                 tos.iter().map(|c| cs.handle(c).pretty()).join(" "),
                 froms
                     .iter()
-                    .zip(signs.iter())
-                    .map(|(c, s)| format!(
-                        "{} {}",
-                        if *s { '↓' } else { '↑' },
-                        cs.handle(c).pretty()
-                    ))
+                    .enumerate()
+                    .map(|(i, c)| match signs.get(i) {
+                        Some(s) =>
+                            format!("{} {}", if *s { '↓' } else { '↑' }, cs.handle(c).pretty()),
+                        None => cs.handle(c).pretty(),
+                    })
                     .join(" "),
+                if tiebreaks.is_empty() {
+                    String::new()
+                } else {
+                    format!(
+                        " ≺ [{}]",
+                        tiebreaks.iter().map(|c| cs.handle(c).pretty()).join(" ")
+                    )
+                },
+                if *stable { "" } else { " (unstable)" },
             ),
             Computation::CyclicFrom { target, froms, .. } => println!(
                 "{} ≜ ↻ {}",
@@ -391,14 +586,40 @@ fn render_perspectives(cs: &ConstraintSet) {
     for (module, persps) in cs.perspectives.iter() {
         for (name, expr) in persps.iter() {
             println!(
-                "{}: {}",
+                "{}{}: {}",
                 Handle::new(module, name).pretty(),
+                if cs.shared_perspectives.contains(name) {
+                    " (shared)".italic().to_string()
+                } else {
+                    String::new()
+                },
                 expr.pretty_with_handle(cs)
             )
         }
     }
 }
 
+fn render_bounds(cs: &ConstraintSet, margin: usize) {
+    println!("\n{}", "=== Bounds ===".bold().yellow());
+    let risky = bounds::risky_expressions(cs, margin);
+    if risky.is_empty() {
+        println!(
+            "no expression within {} bits of the field size",
+            margin.to_string().bold()
+        );
+    }
+    for r in risky {
+        println!(
+            "\n{}: up to {} bits",
+            r.handle.pretty(),
+            r.bound.bits().to_string().red().bold()
+        );
+        for (culprit, bound) in r.culprits {
+            println!("  {:>4} bits ← {}", bound.bits(), culprit.pretty());
+        }
+    }
+}
+
 fn render_spilling(cs: &ConstraintSet) {
     println!("\n{}", "=== Spilling ===".bold().yellow());
     for (module, spilling) in cs.columns.spilling.iter() {
@@ -415,6 +636,8 @@ pub(crate) struct DebugSettings {
     pub perspectives: bool,
     pub types: bool,
     pub spilling: bool,
+    /// if set, report expressions within this many bits of the field size
+    pub bounds: Option<usize>,
 }
 
 pub(crate) fn debug(
@@ -430,7 +653,17 @@ pub(crate) fn debug(
         render_constants(cs);
     }
     if settings.constraints {
-        render_constraints(cs, only, skip, settings.types);
+        render_constraints(
+            cs,
+            "Constraints",
+            &cs.constraints,
+            only,
+            skip,
+            settings.types,
+        );
+        if !cs.assertions.is_empty() {
+            render_constraints(cs, "Assertions", &cs.assertions, only, skip, settings.types);
+        }
     }
     if settings.columns {
         render_columns(cs);
@@ -444,5 +677,8 @@ pub(crate) fn debug(
     if settings.spilling {
         render_spilling(cs);
     }
+    if let Some(margin) = settings.bounds {
+        render_bounds(cs, margin);
+    }
     Ok(())
 }
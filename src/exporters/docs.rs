@@ -0,0 +1,277 @@
+//! A human-readable reference of a constraint set, listing the columns and the
+//! constraints of every module, rendered either in Markdown or in HTML. The
+//! columns of a module are split in sections along their `:group`.
+use anyhow::*;
+use itertools::Itertools;
+use std::io::Write;
+
+use crate::compiler::{Constraint, ConstraintSet, Kind, Node};
+
+struct ColumnDoc {
+    name: String,
+    t: String,
+    kind: &'static str,
+    padding: String,
+    doc: String,
+    /// the group of the column, if it has been given one with `:group`
+    group: Option<String>,
+}
+
+struct ConstraintDoc {
+    name: String,
+    kind: &'static str,
+    /// what the constraint relates, e.g. the modules a lookup spans
+    details: String,
+}
+
+struct ModuleDoc {
+    name: String,
+    columns: Vec<ColumnDoc>,
+    constraints: Vec<ConstraintDoc>,
+}
+
+/// The modules a set of lookup expressions read from
+fn modules_of(cs: &ConstraintSet, exps: &[Node]) -> String {
+    exps.iter()
+        .flat_map(|e| e.dependencies())
+        .filter_map(|c| cs.columns.column(&c).ok())
+        .map(|c| c.handle.module.clone())
+        .sorted()
+        .dedup()
+        .join(", ")
+}
+
+fn collect(cs: &ConstraintSet) -> Vec<ModuleDoc> {
+    let columns = cs
+        .columns
+        .iter_cols()
+        .into_group_map_by(|c| c.handle.module.clone());
+    let constraints = cs
+        .constraints
+        .iter()
+        .into_group_map_by(|c| c.handle().module.clone());
+
+    columns
+        .keys()
+        .chain(constraints.keys())
+        .sorted()
+        .dedup()
+        .map(|module| ModuleDoc {
+            name: module.to_owned(),
+            columns: columns
+                .get(module)
+                .into_iter()
+                .flatten()
+                // the ungrouped columns come first
+                .sorted_by_cached_key(|c| (c.group.clone(), c.handle.to_string()))
+                .map(|c| ColumnDoc {
+                    name: match c.handle.perspective.as_ref() {
+                        Some(perspective) => format!("{}/{}", perspective, c.handle.name),
+                        None => c.handle.name.clone(),
+                    },
+                    t: super::json::magma(c.t),
+                    kind: match c.kind {
+                        Kind::Commitment => "committed",
+                        Kind::Computed | Kind::Expression(_) => "computed",
+                    },
+                    padding: c
+                        .padding_value
+                        .as_ref()
+                        .map(|v| v.to_string())
+                        .unwrap_or_default(),
+                    doc: c.doc.as_deref().unwrap_or_default().lines().join(" "),
+                    group: c.group.clone(),
+                })
+                .collect(),
+            constraints: constraints
+                .get(module)
+                .into_iter()
+                .flatten()
+                .sorted_by_cached_key(|c| c.handle().name.clone())
+                .map(|c| {
+                    let (kind, details) = match c {
+                        Constraint::Vanishes { domain, .. } => (
+                            "vanishes",
+                            domain
+                                .as_ref()
+                                .map(|d| format!("on rows {}", d))
+                                .unwrap_or_default(),
+                        ),
+                        Constraint::Lookup {
+                            including,
+                            included,
+                            ..
+                        } => (
+                            "lookup",
+                            format!(
+                                "{} into {}",
+                                modules_of(cs, included),
+                                modules_of(cs, including)
+                            ),
+                        ),
+                        Constraint::Permutation { from, .. } => {
+                            ("permutation", format!("of {} columns", from.len()))
+                        }
+                        Constraint::InRange { max, .. } => ("range", format!("below {}", max)),
+                        Constraint::Normalization { .. } => ("normalization", String::new()),
+                    };
+                    ConstraintDoc {
+                        name: c.handle().name.clone(),
+                        kind,
+                        details,
+                    }
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Escape `s` so that it does not break a Markdown table, nor is taken for
+/// an HTML tag, as e.g. the root module is
+fn md_escape(s: &str) -> String {
+    s.replace('|', "\\|").replace('<', "&lt;")
+}
+
+/// Render the reference of `cs` as Markdown
+pub fn render_markdown(cs: &ConstraintSet, out: &mut dyn Write) -> Result<()> {
+    let modules = collect(cs);
+    writeln!(out, "# Constraint system reference")?;
+    writeln!(out)?;
+    writeln!(out, "| Module | Columns | Constraints |")?;
+    writeln!(out, "|---|---:|---:|")?;
+    for m in modules.iter() {
+        writeln!(
+            out,
+            "| {} | {} | {} |",
+            md_escape(&m.name),
+            m.columns.len(),
+            m.constraints.len()
+        )?;
+    }
+
+    for m in modules.iter() {
+        writeln!(out)?;
+        writeln!(out, "## {}", md_escape(&m.name))?;
+        for (group, columns) in m.columns.iter().group_by(|c| &c.group).into_iter() {
+            writeln!(out)?;
+            if let Some(group) = group {
+                writeln!(out, "### {}", md_escape(group))?;
+                writeln!(out)?;
+            }
+            writeln!(out, "| Column | Type | Kind | Padding | Description |")?;
+            writeln!(out, "|---|---|---|---|---|")?;
+            for c in columns {
+                writeln!(
+                    out,
+                    "| `{}` | {} | {} | {} | {} |",
+                    c.name,
+                    c.t,
+                    c.kind,
+                    c.padding,
+                    md_escape(&c.doc)
+                )?;
+            }
+        }
+        if !m.constraints.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "| Constraint | Kind | Details |")?;
+            writeln!(out, "|---|---|---|")?;
+            for c in m.constraints.iter() {
+                writeln!(
+                    out,
+                    "| `{}` | {} | {} |",
+                    c.name,
+                    c.kind,
+                    md_escape(&c.details)
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render the reference of `cs` as a standalone HTML page
+pub fn render_html(cs: &ConstraintSet, out: &mut dyn Write) -> Result<()> {
+    let modules = collect(cs);
+    writeln!(
+        out,
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Constraint system reference</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; margin: 1em 0; }}
+th, td {{ border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }}
+code {{ font-family: monospace; }}
+</style>
+</head>
+<body>
+<h1>Constraint system reference</h1>
+<table>
+<tr><th>Module</th><th>Columns</th><th>Constraints</th></tr>"#
+    )?;
+    for m in modules.iter() {
+        writeln!(
+            out,
+            r##"<tr><td><a href="#{0}">{0}</a></td><td>{1}</td><td>{2}</td></tr>"##,
+            html_escape(&m.name),
+            m.columns.len(),
+            m.constraints.len()
+        )?;
+    }
+    writeln!(out, "</table>")?;
+
+    for m in modules.iter() {
+        writeln!(out, r#"<h2 id="{0}">{0}</h2>"#, html_escape(&m.name))?;
+        for (group, columns) in m.columns.iter().group_by(|c| &c.group).into_iter() {
+            if let Some(group) = group {
+                writeln!(out, "<h3>{}</h3>", html_escape(group))?;
+            }
+            writeln!(out, "<table>")?;
+            writeln!(
+                out,
+                "<tr><th>Column</th><th>Type</th><th>Kind</th><th>Padding</th><th>Description</th></tr>"
+            )?;
+            for c in columns {
+                writeln!(
+                    out,
+                    "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    html_escape(&c.name),
+                    c.t,
+                    c.kind,
+                    html_escape(&c.padding),
+                    html_escape(&c.doc)
+                )?;
+            }
+            writeln!(out, "</table>")?;
+        }
+        if !m.constraints.is_empty() {
+            writeln!(out, "<table>")?;
+            writeln!(
+                out,
+                "<tr><th>Constraint</th><th>Kind</th><th>Details</th></tr>"
+            )?;
+            for c in m.constraints.iter() {
+                writeln!(
+                    out,
+                    "<tr><td><code>{}</code></td><td>{}</td><td>{}</td></tr>",
+                    html_escape(&c.name),
+                    c.kind,
+                    html_escape(&c.details)
+                )?;
+            }
+            writeln!(out, "</table>")?;
+        }
+    }
+    writeln!(out, "</body>\n</html>")?;
+    Ok(())
+}
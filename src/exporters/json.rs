@@ -0,0 +1,484 @@
+//! A stable JSON rendition of a fully-expanded constraint set, for the
+//! tooling that can not read the binary format. Unlike the serialization of
+//! [`ConstraintSet`], that follows its internal structure, this format is
+//! versioned and described by the JSON Schema in `json.schema.json`.
+use anyhow::*;
+use itertools::Itertools;
+use num_bigint::BigInt;
+use serde::Serialize;
+use std::io::Write;
+
+use crate::{
+    column::{Computation, ExoOperation},
+    compiler::{
+        ColumnRef, Constraint, ConstraintSet, Domain, Expression, Intrinsic, Kind, Magma, Node,
+        RawMagma,
+    },
+};
+
+/// The version of the format, to be bumped on any breaking change
+const VERSION: usize = 2;
+
+/// The JSON Schema describing the format
+pub const SCHEMA: &str = include_str!("json.schema.json");
+
+#[derive(Serialize)]
+struct JsonConstraintSet {
+    format: &'static str,
+    version: usize,
+    corset: String,
+    modules: Vec<JsonModule>,
+    constants: Vec<JsonConstant>,
+    columns: Vec<JsonColumn>,
+    registers: Vec<JsonRegister>,
+    perspectives: Vec<JsonPerspective>,
+    constraints: Vec<JsonConstraint>,
+    computations: Vec<JsonComputation>,
+}
+
+#[derive(Serialize)]
+struct JsonModule {
+    name: String,
+    spilling: isize,
+    length: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct JsonConstant {
+    module: String,
+    name: String,
+    value: String,
+}
+
+#[derive(Serialize)]
+struct JsonColumn {
+    id: usize,
+    module: String,
+    perspective: Option<String>,
+    name: String,
+    #[serde(rename = "type")]
+    t: String,
+    kind: &'static str,
+    register: Option<usize>,
+    multiplier: usize,
+    padding: Option<String>,
+    must_prove: bool,
+}
+
+#[derive(Serialize)]
+struct JsonRegister {
+    id: usize,
+    name: Option<String>,
+    #[serde(rename = "type")]
+    t: String,
+    width: usize,
+    bits: usize,
+}
+
+#[derive(Serialize)]
+struct JsonPerspective {
+    module: String,
+    name: String,
+    shared: bool,
+    selector: JsonExpression,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum JsonExpression {
+    Const(String),
+    Column {
+        id: usize,
+        shift: i16,
+    },
+    ExoColumn {
+        id: usize,
+        shift: i16,
+    },
+    ArrayColumn {
+        id: usize,
+        domain: Vec<isize>,
+    },
+    Funcall {
+        op: &'static str,
+        args: Vec<JsonExpression>,
+    },
+    List(Vec<JsonExpression>),
+    Void,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum JsonConstraint {
+    Vanishes {
+        module: String,
+        name: String,
+        domain: Option<Vec<isize>>,
+        expr: JsonExpression,
+    },
+    Lookup {
+        module: String,
+        name: String,
+        including: Vec<JsonExpression>,
+        included: Vec<JsonExpression>,
+    },
+    Permutation {
+        module: String,
+        name: String,
+        from: Vec<usize>,
+        to: Vec<usize>,
+    },
+    Range {
+        module: String,
+        name: String,
+        expr: JsonExpression,
+        max: String,
+    },
+    Normalization {
+        module: String,
+        name: String,
+        reference: JsonExpression,
+        inverted: usize,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum JsonComputation {
+    Composite {
+        target: usize,
+        expr: JsonExpression,
+    },
+    Interleaved {
+        target: usize,
+        froms: Vec<usize>,
+    },
+    Sorted {
+        froms: Vec<usize>,
+        tos: Vec<usize>,
+        signs: Vec<bool>,
+        stable: bool,
+        tiebreaks: Vec<usize>,
+    },
+    CyclicFrom {
+        target: usize,
+        froms: Vec<usize>,
+        modulo: usize,
+    },
+    SortingConstraints {
+        ats: Vec<usize>,
+        eq: usize,
+        delta: usize,
+        delta_bytes: Vec<usize>,
+        signs: Vec<bool>,
+        froms: Vec<usize>,
+        sorted: Vec<usize>,
+    },
+    ExoOperation {
+        op: &'static str,
+        sources: Vec<JsonExpression>,
+        target: usize,
+    },
+    ExoConstant {
+        value: String,
+        target: usize,
+    },
+}
+
+pub(crate) fn magma(m: Magma) -> String {
+    match m.rm() {
+        RawMagma::None => "none".into(),
+        RawMagma::Binary => "binary".into(),
+        RawMagma::Nibble => "nibble".into(),
+        RawMagma::Byte => "byte".into(),
+        RawMagma::Native => "native".into(),
+        RawMagma::Integer(w) => format!("i{}", w),
+        RawMagma::Signed(w) => format!("s{}", w),
+        RawMagma::Any => "any".into(),
+    }
+}
+
+fn op(f: Intrinsic) -> &'static str {
+    match f {
+        Intrinsic::Add => "add",
+        Intrinsic::Sub => "sub",
+        Intrinsic::Mul => "mul",
+        Intrinsic::VectorAdd => "vector_add",
+        Intrinsic::VectorSub => "vector_sub",
+        Intrinsic::VectorMul => "vector_mul",
+        Intrinsic::Exp => "exp",
+        Intrinsic::Neg => "neg",
+        Intrinsic::Inv => "inv",
+        Intrinsic::Normalize => "normalize",
+        Intrinsic::Sgn => "sgn",
+        Intrinsic::Bits => "bits",
+        Intrinsic::Begin => "begin",
+        Intrinsic::IfZero => "if_zero",
+        Intrinsic::IfNotZero => "if_not_zero",
+    }
+}
+
+fn domain(d: &Domain<isize>) -> Vec<isize> {
+    d.iter().collect()
+}
+
+struct Renderer<'a> {
+    cs: &'a ConstraintSet,
+}
+impl Renderer<'_> {
+    fn id(&self, r: &ColumnRef) -> usize {
+        self.cs.columns.id_of(r)
+    }
+
+    fn ids(&self, rs: &[ColumnRef]) -> Vec<usize> {
+        rs.iter().map(|r| self.id(r)).collect()
+    }
+
+    fn expression(&self, n: &Node) -> JsonExpression {
+        match n.e() {
+            Expression::Funcall { func, args } => JsonExpression::Funcall {
+                op: op(*func),
+                args: self.expressions(args),
+            },
+            Expression::Const(x) => JsonExpression::Const(BigInt::from(x).to_string()),
+            Expression::Column { handle, shift, .. } => JsonExpression::Column {
+                id: self.id(handle),
+                shift: *shift,
+            },
+            Expression::ExoColumn { handle, shift, .. } => JsonExpression::ExoColumn {
+                id: self.id(handle),
+                shift: *shift,
+            },
+            Expression::ArrayColumn {
+                handle, domain: d, ..
+            } => JsonExpression::ArrayColumn {
+                id: self.id(handle),
+                domain: domain(d),
+            },
+            Expression::List(xs) => JsonExpression::List(self.expressions(xs)),
+            Expression::Void => JsonExpression::Void,
+        }
+    }
+
+    fn expressions(&self, ns: &[Node]) -> Vec<JsonExpression> {
+        ns.iter().map(|n| self.expression(n)).collect()
+    }
+
+    fn constraint(&self, c: &Constraint) -> JsonConstraint {
+        let module = c.handle().module.clone();
+        let name = c.handle().name.clone();
+        match c {
+            Constraint::Vanishes {
+                domain: d, expr, ..
+            } => JsonConstraint::Vanishes {
+                module,
+                name,
+                domain: d.as_ref().map(domain),
+                expr: self.expression(expr),
+            },
+            Constraint::Lookup {
+                including,
+                included,
+                ..
+            } => JsonConstraint::Lookup {
+                module,
+                name,
+                including: self.expressions(including),
+                included: self.expressions(included),
+            },
+            Constraint::Permutation { from, to, .. } => JsonConstraint::Permutation {
+                module,
+                name,
+                from: self.ids(from),
+                to: self.ids(to),
+            },
+            Constraint::InRange { exp, max, .. } => JsonConstraint::Range {
+                module,
+                name,
+                expr: self.expression(exp),
+                max: BigInt::from(max).to_string(),
+            },
+            Constraint::Normalization {
+                reference,
+                inverted,
+                ..
+            } => JsonConstraint::Normalization {
+                module,
+                name,
+                reference: self.expression(reference),
+                inverted: self.id(inverted),
+            },
+        }
+    }
+
+    fn computation(&self, c: &Computation) -> JsonComputation {
+        match c {
+            Computation::Composite { target, exp } => JsonComputation::Composite {
+                target: self.id(target),
+                expr: self.expression(exp),
+            },
+            Computation::Interleaved { target, froms } => JsonComputation::Interleaved {
+                target: self.id(target),
+                froms: self.ids(froms),
+            },
+            Computation::Sorted {
+                froms,
+                tos,
+                signs,
+                stable,
+                tiebreaks,
+            } => JsonComputation::Sorted {
+                froms: self.ids(froms),
+                tos: self.ids(tos),
+                signs: signs.clone(),
+                stable: *stable,
+                tiebreaks: self.ids(tiebreaks),
+            },
+            Computation::CyclicFrom {
+                target,
+                froms,
+                modulo,
+            } => JsonComputation::CyclicFrom {
+                target: self.id(target),
+                froms: self.ids(froms),
+                modulo: *modulo,
+            },
+            Computation::SortingConstraints {
+                ats,
+                eq,
+                delta,
+                delta_bytes,
+                signs,
+                froms,
+                sorted,
+            } => JsonComputation::SortingConstraints {
+                ats: self.ids(ats),
+                eq: self.id(eq),
+                delta: self.id(delta),
+                delta_bytes: self.ids(delta_bytes),
+                signs: signs.clone(),
+                froms: self.ids(froms),
+                sorted: self.ids(sorted),
+            },
+            Computation::ExoOperation {
+                op,
+                sources,
+                target,
+            } => JsonComputation::ExoOperation {
+                op: match op {
+                    ExoOperation::Add => "add",
+                    ExoOperation::Sub => "sub",
+                    ExoOperation::Mul => "mul",
+                },
+                sources: self.expressions(sources),
+                target: self.id(target),
+            },
+            Computation::ExoConstant { value, target } => JsonComputation::ExoConstant {
+                value: BigInt::from(value).to_string(),
+                target: self.id(target),
+            },
+        }
+    }
+
+    fn render(&self) -> JsonConstraintSet {
+        let cs = self.cs;
+        let bits = cs.columns.register_bit_widths();
+        JsonConstraintSet {
+            format: "corset-constraint-set",
+            version: VERSION,
+            corset: crate::version::BuildInfo::current().version,
+            modules: cs
+                .columns
+                .modules()
+                .into_iter()
+                .sorted()
+                .map(|m| JsonModule {
+                    spilling: cs.spilling_of(&m).unwrap_or(0),
+                    length: cs.columns.fixed_len.get(&m).cloned(),
+                    name: m,
+                })
+                .collect(),
+            constants: cs
+                .constants
+                .iter()
+                .sorted_by_key(|c| c.0)
+                .map(|(h, v)| JsonConstant {
+                    module: h.module.clone(),
+                    name: h.name.clone(),
+                    value: v.to_string(),
+                })
+                .collect(),
+            columns: cs
+                .columns
+                .iter()
+                .map(|(r, c)| JsonColumn {
+                    id: r.as_id(),
+                    module: c.handle.module.clone(),
+                    perspective: c.handle.perspective.clone(),
+                    name: c.handle.name.clone(),
+                    t: magma(c.t),
+                    kind: match c.kind {
+                        Kind::Commitment => "commitment",
+                        Kind::Computed => "computed",
+                        Kind::Expression(_) => "expression",
+                    },
+                    register: c.register,
+                    multiplier: cs.length_multiplier(&r),
+                    padding: c
+                        .padding_value
+                        .as_ref()
+                        .map(|v| BigInt::from(v).to_string()),
+                    must_prove: c.must_prove,
+                })
+                .collect(),
+            registers: cs
+                .columns
+                .registers
+                .iter()
+                .enumerate()
+                .map(|(i, r)| JsonRegister {
+                    id: i,
+                    name: r.handle.as_ref().map(|h| h.to_string()),
+                    t: magma(r.magma),
+                    width: r.width(),
+                    bits: bits[i],
+                })
+                .collect(),
+            perspectives: cs
+                .perspectives
+                .iter()
+                .flat_map(|(module, perspectives)| {
+                    perspectives
+                        .iter()
+                        .map(move |(name, selector)| (module, name, selector))
+                })
+                .sorted_by_key(|(module, name, _)| (*module, *name))
+                .map(|(module, name, selector)| JsonPerspective {
+                    module: module.clone(),
+                    name: name.clone(),
+                    shared: cs.shared_perspectives.contains(name),
+                    selector: self.expression(selector),
+                })
+                .collect(),
+            constraints: cs.constraints.iter().map(|c| self.constraint(c)).collect(),
+            computations: cs
+                .computations
+                .iter()
+                .map(|c| self.computation(c))
+                .collect(),
+        }
+    }
+}
+
+/// Write `cs` to `out` in the stable JSON format
+pub fn render(cs: &ConstraintSet, pretty: bool, out: &mut dyn Write) -> Result<()> {
+    let _phase = crate::run_stats::phase("export");
+    let json = Renderer { cs }.render();
+    if pretty {
+        serde_json::to_writer_pretty(&mut *out, &json)?;
+    } else {
+        serde_json::to_writer(&mut *out, &json)?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
@@ -262,6 +262,8 @@ fn render_node(n: &AstNode, state: State) -> Result<String> {
             guard: _,
             perspective: _,
             body,
+            doc: _,
+            assertion: false,
         } => Ok(format!(
             "\n\\begin{{constraint}}[{}{} {}]\n\\begin{{gather*}}\n{}\n\\end{{gather*}}\n\\end{{constraint}}\n",
             name.to_case(Case::Title),
@@ -294,6 +296,7 @@ const CONSTRAINT_TEMPLATE: &str = include_str!("constraint.tex");
 struct LatexTemplate {
     caption: String,
     content: String,
+    doc: Option<String>,
 }
 fn render_constraints(asts: &[Ast], columns: &[String]) -> Result<String> {
     let mut r = String::new();
@@ -309,6 +312,7 @@ fn render_constraints(asts: &[Ast], columns: &[String]) -> Result<String> {
             &LatexTemplate {
                 caption: constraint.h.name.to_owned(),
                 content: render_node(&constraint.e, state)?,
+                doc: constraint.doc.as_deref().map(sanitize),
             },
         )?;
         r += "\n";
@@ -320,6 +324,7 @@ type LatexConst = (String, AstNode);
 struct LatexConstraint {
     h: Handle,
     e: AstNode,
+    doc: Option<String>,
 }
 struct LatexColumn {
     name: String,
@@ -333,7 +338,7 @@ fn constraints(ast: &Ast) -> Vec<LatexConstraint> {
     ast.exprs
         .iter()
         .filter_map(|n| match &n.class {
-            Token::DefModule(m) => {
+            Token::DefModule(m, _) => {
                 module = m.to_owned();
                 None
             }
@@ -342,12 +347,15 @@ fn constraints(ast: &Ast) -> Vec<LatexConstraint> {
                 domain: _domain,
                 guard: _guard,
                 body,
+                doc,
+                assertion: false,
                 ..
             } => {
                 let h = Handle::new(&module, name);
                 Some(LatexConstraint {
                     h,
                     e: *body.to_owned(),
+                    doc: doc.clone(),
                 })
             }
             // Token::DefPermutation { from, to } => todo!(),
@@ -435,6 +443,7 @@ fn render_columns(asts: &[Ast]) -> Result<(String, Vec<String>)> {
 }
 
 pub fn render(asts: &[Ast], constraints_file: Option<String>) -> Result<()> {
+    let _phase = crate::run_stats::phase("export");
     if let Some(constraints_file) = constraints_file.as_ref() {
         let mut out = File::create(constraints_file)
             .with_context(|| anyhow!("while opening {}", constraints_file))?;
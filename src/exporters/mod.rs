@@ -1,19 +1,272 @@
+use anyhow::*;
+use convert_case::{Case, Casing};
+use itertools::Itertools;
 use log::*;
+use owo_colors::OwoColorize;
+use std::collections::{HashMap, HashSet};
 
 #[cfg(feature = "exporters")]
 pub mod besu;
+#[cfg(feature = "exporters")]
+pub mod circom;
 #[cfg(feature = "conflater")]
 pub mod conflater;
 pub mod convert;
 pub(crate) mod debugger;
 #[cfg(feature = "exporters")]
+pub mod docs;
+#[cfg(feature = "exporters")]
+pub mod json;
+#[cfg(feature = "exporters")]
 pub mod latex;
 #[cfg(feature = "exporters")]
+pub mod rust;
+#[cfg(feature = "exporters")]
+pub mod solidity;
+#[cfg(feature = "exporters")]
 pub mod wizardiop;
 #[cfg(feature = "exporters")]
 pub mod zkgeth;
 
-use crate::column::Register;
+use crate::{
+    column::Register,
+    compiler::{ColumnRef, Constraint, ConstraintSet, Node},
+    pretty::Pretty,
+    structs::{Handle, MangleScheme, MODULE_SEPARATOR},
+};
+
+/// How an exporter turns handles into identifiers of its target language
+#[derive(Clone, Debug)]
+pub struct Mangling {
+    /// what separates the module, or the perspective, of a symbol from its name
+    pub separator: String,
+    /// the case convention of the identifiers; if unset, the names are kept
+    /// as written in the constraints
+    pub case: Option<Case>,
+    /// how the symbols are purified into identifiers
+    pub scheme: MangleScheme,
+}
+impl Default for Mangling {
+    fn default() -> Self {
+        Mangling {
+            separator: MODULE_SEPARATOR.to_owned(),
+            case: None,
+            scheme: MangleScheme::default(),
+        }
+    }
+}
+impl Mangling {
+    fn convert(&self, s: String) -> String {
+        if let Some(case) = self.case {
+            s.to_case(case)
+        } else {
+            s
+        }
+    }
+
+    pub fn mangle(&self, h: &Handle) -> String {
+        self.convert(h.mangle_with(&self.separator, self.scheme))
+    }
+
+    pub fn mangle_ith(&self, h: &Handle, i: usize) -> String {
+        self.convert(h.mangle_ith_with(&self.separator, self.scheme, i))
+    }
+
+    pub fn mangled_name(&self, h: &Handle) -> String {
+        self.convert(h.mangled_name_with(&self.separator, self.scheme))
+    }
+
+    /// The mangled module of `h`, whose case is left as is
+    pub fn mangled_module(&self, h: &Handle) -> String {
+        h.mangled_module_with(self.scheme)
+    }
+}
+
+/// The modules rendered by an exporter: either all of them, or a selection
+/// completed with the modules its lookups depend on
+#[derive(Clone, Debug, Default)]
+pub struct ModuleFilter(Option<HashSet<String>>);
+impl ModuleFilter {
+    /// Select `modules`, along with the modules they transitively look up
+    /// into; all the modules are selected if `modules` is empty. As lookups
+    /// are the only dependencies that are followed, fail if any other
+    /// constraint or computation of a selected module would refer to a
+    /// module left out.
+    pub fn new(cs: &ConstraintSet, modules: &[String]) -> Result<Self> {
+        if modules.is_empty() {
+            return Ok(ModuleFilter(None));
+        }
+
+        let known = cs
+            .columns
+            .iter_cols()
+            .map(|c| c.handle.module.as_str())
+            .collect::<HashSet<_>>();
+        for module in modules {
+            if !known.contains(module.as_str()) {
+                bail!("unknown module {}", module.red().bold());
+            }
+        }
+
+        let modules_of = |nodes: &[Node]| {
+            nodes
+                .iter()
+                .flat_map(|n| n.dependencies())
+                .map(|c| cs.columns.module_of(&c))
+                .collect::<HashSet<_>>()
+        };
+        let mut selected = modules.iter().cloned().collect::<HashSet<_>>();
+        loop {
+            let mut grown = false;
+            for c in cs.constraints.iter() {
+                if let Constraint::Lookup {
+                    including,
+                    included,
+                    ..
+                } = c
+                {
+                    let included = modules_of(included);
+                    if !included.is_disjoint(&selected) {
+                        for module in included.into_iter().chain(modules_of(including)) {
+                            grown |= selected.insert(module);
+                        }
+                    }
+                }
+            }
+            if !grown {
+                break;
+            }
+        }
+
+        let dangling = |what: String, c: &ColumnRef| -> Result<()> {
+            let module = cs.columns.module_of(c);
+            if selected.contains(&module) {
+                Ok(())
+            } else {
+                bail!(
+                    "{} depends on {}, but module {} is not exported",
+                    what,
+                    cs.columns.column(c)?.handle.pretty(),
+                    module.red().bold()
+                )
+            }
+        };
+        for c in cs
+            .constraints
+            .iter()
+            .filter(|c| !matches!(c, Constraint::Lookup { .. }))
+            .filter(|c| selected.contains(&c.handle().module))
+        {
+            for d in c.dependencies() {
+                dangling(format!("constraint {}", c.handle().pretty()), &d)?;
+            }
+        }
+        for comp in cs.computations.iter() {
+            if let Some(target) = comp
+                .targets()
+                .into_iter()
+                .find(|t| selected.contains(&cs.columns.module_of(t)))
+            {
+                let target = cs.columns.column(target)?.handle.pretty();
+                for source in comp.sources() {
+                    dangling(format!("the computation of {}", target), &source)?;
+                }
+            }
+        }
+
+        Ok(ModuleFilter(Some(selected)))
+    }
+
+    /// Whether the symbols of `module` should be rendered
+    pub fn contains(&self, module: &str) -> bool {
+        self.0.as_ref().map_or(true, |s| s.contains(module))
+    }
+
+    /// Whether `c` should be rendered; lookups are attached to the modules
+    /// of their included columns
+    pub fn exports(&self, cs: &ConstraintSet, c: &Constraint) -> bool {
+        match c {
+            Constraint::Lookup { included, .. } => included
+                .iter()
+                .flat_map(|n| n.dependencies())
+                .any(|r| self.contains(&cs.columns.module_of(&r))),
+            _ => self.contains(&c.handle().module),
+        }
+    }
+}
+
+/// Ensure that no two different symbols have been mangled into the same
+/// identifier, which would otherwise only be caught when compiling the
+/// generated code.
+///
+/// * `what`  - the kind of the identifiers, for the error message
+/// * `names` - the identifiers and the symbols they have been generated from
+pub fn ensure_unique<I: IntoIterator<Item = (String, String)>>(what: &str, names: I) -> Result<()> {
+    let mut sources: HashMap<String, Vec<String>> = HashMap::new();
+    for (identifier, source) in names {
+        let sources = sources.entry(identifier).or_default();
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+    }
+    let collisions = sources
+        .into_iter()
+        .filter(|(_, sources)| sources.len() > 1)
+        .sorted()
+        .map(|(identifier, sources)| {
+            format!("{} ← {}", identifier.red().bold(), sources.join(", "))
+        })
+        .collect::<Vec<_>>();
+    if collisions.is_empty() {
+        Ok(())
+    } else {
+        bail!(
+            "{} {} mangled from different symbols:\n{}",
+            collisions.len(),
+            what,
+            collisions.join("\n")
+        )
+    }
+}
+
+/// Quote `s` as a Go string literal
+#[cfg(feature = "exporters")]
+fn go_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The shift window of a module, as rendered by the Go exporters
+#[cfg(feature = "exporters")]
+#[derive(serde::Serialize)]
+struct GoShiftWindow {
+    /// the module name, as a quoted Go string
+    module: String,
+    backward: isize,
+    forward: isize,
+}
+
+/// The shift windows of the modules selected by `modules`
+#[cfg(feature = "exporters")]
+fn go_shift_windows(cs: &ConstraintSet, modules: &ModuleFilter) -> Vec<GoShiftWindow> {
+    cs.shift_windows
+        .iter()
+        .filter(|(module, _)| modules.contains(module))
+        .map(|(module, w)| GoShiftWindow {
+            module: go_quote(module),
+            backward: w.backward,
+            forward: w.forward,
+        })
+        .collect()
+}
+
+/// A `//` comment pointing back to the definition of the constraint `handle`
+/// in the sources, if it stems from them
+#[cfg(feature = "exporters")]
+fn span_comment(cs: &ConstraintSet, handle: &Handle) -> Option<String> {
+    cs.spans
+        .get(handle)
+        .map(|span| format!("// defined at {}", span))
+}
 
 fn reg_to_string(r: &Register, i: usize) -> String {
     r.handle
@@ -22,18 +275,69 @@ fn reg_to_string(r: &Register, i: usize) -> String {
         .unwrap_or_else(|| format!("r{}", i))
 }
 
+/// Format `code` with `formatter`, reading it from its standard input; if
+/// the formatter fails, `code` is returned as is
 #[cfg(feature = "exporters")]
-fn gofmt(filename: &str) {
-    info!("Running gofmt on {}... ", filename);
-    let output = std::process::Command::new("gofmt")
-        .args(["-w", filename])
-        .output()
-        .expect("failed to execute gofmt");
-    if output.status.success() {
-        info!("done.");
-    } else {
+fn reformat(formatter: &str, args: &[&str], code: String) -> String {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    info!("Running {}... ", formatter);
+    let mut child = Command::new(formatter)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|_| panic!("failed to execute {}", formatter));
+    // the formatter output is only read once its input is closed, so the
+    // code is fed from another thread to avoid a deadlock on large files; the
+    // input is closed when this thread drops its end of the pipe
+    let mut stdin = child.stdin.take().unwrap();
+    let input = code.as_bytes();
+    let (written, output) = std::thread::scope(|s| {
+        let writer = s.spawn(move || stdin.write_all(input));
+        let output = child.wait_with_output();
+        (writer.join().unwrap(), output)
+    });
+    let output = output.unwrap_or_else(|_| panic!("failed to execute {}", formatter));
+    if !output.status.success() {
         error!("failed:");
-        eprintln!("{}", std::str::from_utf8(&output.stdout).unwrap());
         eprintln!("{}", std::str::from_utf8(&output.stderr).unwrap());
+        code
+    } else if let Err(e) = written {
+        error!("failed to write to {}: {}", formatter, e);
+        code
+    } else {
+        info!("done.");
+        String::from_utf8(output.stdout).unwrap()
     }
 }
+
+#[cfg(feature = "exporters")]
+fn gofmt(code: String) -> String {
+    reformat("gofmt", &[], code)
+}
+
+#[cfg(feature = "exporters")]
+fn rustfmt(code: String) -> String {
+    reformat("rustfmt", &["--edition", "2021"], code)
+}
+
+/// Write `content` to `filename`, unless the file already holds exactly this
+/// content, so that unchanged outputs keep their timestamp and do not trigger
+/// downstream rebuilds. Return `filename` if the file has been written.
+pub(crate) fn write_unit(filename: &str, content: &str) -> Result<Option<String>> {
+    use std::io::Write;
+
+    if std::fs::read(filename).is_ok_and(|old| old == content.as_bytes()) {
+        info!("{} is up to date", filename);
+        return Ok(None);
+    }
+
+    std::fs::File::create(filename)
+        .with_context(|| format!("while creating `{}`", filename))?
+        .write_all(content.as_bytes())
+        .with_context(|| format!("while writing to `{}`", filename))?;
+    Ok(Some(filename.to_owned()))
+}
@@ -0,0 +1,411 @@
+use anyhow::*;
+use handlebars::Handlebars;
+use itertools::Itertools;
+use log::*;
+use num_traits::ToPrimitive;
+use serde::Serialize;
+
+use super::{
+    wizardiop::{reg, reg_mangle, reg_mangle_ith, reg_splatter, render_sort_order},
+    Mangling, ModuleFilter,
+};
+use crate::{column::Computation, compiler::*, pretty::Pretty};
+
+const TEMPLATE: &str = include_str!("arkworks.rs.hbs");
+
+/// Turn a mangled symbol into a valid Rust identifier
+fn rust_ident(s: String) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "box", "break", "const", "continue", "do", "dyn", "else", "enum",
+        "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro",
+        "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+        "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+        "where", "while", "yield",
+    ];
+    match s.as_str() {
+        // these ones can not be raw identifiers
+        "self" | "Self" | "super" | "crate" | "_" => format!("{}_", s),
+        _ if KEYWORDS.contains(&s.as_str()) => format!("r#{}", s),
+        _ => s,
+    }
+}
+
+fn column(cs: &ConstraintSet, m: &Mangling, c: &ColumnRef) -> Result<String> {
+    reg_mangle(cs, m, c).map(rust_ident)
+}
+
+fn column_ith(cs: &ConstraintSet, m: &Mangling, c: &ColumnRef, i: usize) -> Result<String> {
+    reg_mangle_ith(cs, m, c, i).map(rust_ident)
+}
+
+/// Render the access to the cell of `c` at the current row shifted by `shift`
+fn render_cell(c: &str, shift: isize) -> String {
+    match shift {
+        0 => format!("{}.at(i)?", c),
+        _ if shift < 0 => format!("{}.at(i - {})?", c, -shift),
+        _ => format!("{}.at(i + {})?", c, shift),
+    }
+}
+
+/// Render references to the columns in `xs`, splattering exo-columns in
+/// their registers
+fn render_columns_of(cs: &ConstraintSet, m: &Mangling, xs: &[Node]) -> Result<String> {
+    let mut r = Vec::new();
+    for x in xs {
+        match x.e() {
+            Expression::Column { handle, .. } => r.push(column(cs, m, handle)?),
+            Expression::ExoColumn { handle, .. } => {
+                for i in 0..cs.columns.register_of(handle).width() {
+                    r.push(column_ith(cs, m, handle, i)?);
+                }
+            }
+            _ => bail!("unable to render {} as a column", x.pretty()),
+        }
+    }
+    Ok(r.into_iter().map(|c| format!("&{}", c)).join(", "))
+}
+
+fn render_chain(cs: &ConstraintSet, m: &Mangling, xs: &[Node], operator: &str) -> Result<String> {
+    if xs.len() == 1 {
+        render_expression(cs, m, &xs[0])
+    } else {
+        Ok(format!(
+            "({})",
+            xs.iter()
+                .map(|x| render_expression(cs, m, x))
+                .collect::<Result<Vec<_>>>()?
+                .join(&format!(" {} ", operator))
+        ))
+    }
+}
+
+fn render_expression(cs: &ConstraintSet, m: &Mangling, e: &Node) -> Result<String> {
+    match e.e() {
+        // the circuit is generic over the field, so the constants are
+        // rendered as signed integers rather than as elements of the native one
+        Expression::Const(x) => Ok(format!("constant(\"{}\")", x.to_signed_bi()?)),
+        Expression::Column { handle, shift, .. } => {
+            Ok(render_cell(&column(cs, m, handle)?, *shift as isize))
+        }
+        Expression::Funcall { func, args } => render_funcall(cs, m, func, args),
+        Expression::Void => {
+            warn!("Rendering VOID expression");
+            Ok("constant(\"0\")".into())
+        }
+        // lists are split into as many constraints by render_constraint, and
+        // exo-columns can only appear at the top level of a constraint
+        Expression::List(_) | Expression::ExoColumn { .. } | Expression::ArrayColumn { .. } => {
+            bail!("unable to render {} within an expression", e.pretty())
+        }
+    }
+}
+
+fn render_funcall(
+    cs: &ConstraintSet,
+    m: &Mangling,
+    func: &Intrinsic,
+    args: &[Node],
+) -> Result<String> {
+    match func {
+        Intrinsic::Add => render_chain(cs, m, args, "+"),
+        Intrinsic::Mul => render_chain(cs, m, args, "*"),
+        Intrinsic::Sub | Intrinsic::VectorSub => render_chain(cs, m, args, "-"),
+        Intrinsic::Exp => {
+            let exp = args[1]
+                .pure_eval()
+                .with_context(|| anyhow!("exponent {} is not evaluable", args[1].pretty()))?
+                .to_u64()
+                .ok_or_else(|| anyhow!("exponent {} is too large", args[1].pretty()))?;
+            Ok(format!(
+                "pow({}, {})",
+                render_expression(cs, m, &args[0])?,
+                exp
+            ))
+        }
+        Intrinsic::Neg => Ok(format!(
+            "(FpVar::zero() - {})",
+            render_expression(cs, m, &args[0])?
+        )),
+        x => bail!("unable to render {:?} in a Rust expression", x),
+    }
+}
+
+/// The length of the columns a constraint applies to, as a Rust expression
+fn render_len(cs: &ConstraintSet, m: &Mangling, name: &str, expr: &Node) -> Result<String> {
+    expr.dependencies()
+        .iter()
+        .map(|c| column(cs, m, c))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .min()
+        .map(|c| format!("{}.len()", c))
+        .ok_or_else(|| anyhow!("constraint {} does not read any column", name))
+}
+
+fn render_constraint(
+    cs: &ConstraintSet,
+    m: &Mangling,
+    name: &str,
+    domain: Option<&Domain<isize>>,
+    expr: &Node,
+) -> Result<Vec<String>> {
+    let vanishes = |len: String, body: String| -> String {
+        match domain {
+            None => format!(
+                "// {}\nvanishes_everywhere({}, |i| Some({}))?;",
+                name, len, body
+            ),
+            Some(domain) => domain
+                .iter()
+                .map(|x| {
+                    format!(
+                        "// {}\nvanishes_at({}, {}, |i| Some({}))?;",
+                        name, len, x, body
+                    )
+                })
+                .join("\n"),
+        }
+    };
+
+    match expr.e() {
+        Expression::List(xs) => Ok(xs
+            .iter()
+            .enumerate()
+            .map(|(i, x)| render_constraint(cs, m, &format!("{}#{}", name, i), domain, x))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect()),
+        Expression::ExoColumn { handle, shift, .. } => (0..cs.columns.register_of(handle).width())
+            .map(|i| {
+                let c = column_ith(cs, m, handle, i)?;
+                Ok(vanishes(
+                    format!("{}.len()", c),
+                    render_cell(&c, *shift as isize),
+                ))
+            })
+            .collect(),
+        _ => Ok(vec![vanishes(
+            render_len(cs, m, name, expr)?,
+            render_expression(cs, m, expr)?,
+        )]),
+    }
+}
+
+pub(crate) fn render_constraints(
+    cs: &ConstraintSet,
+    m: &Mangling,
+    modules: &ModuleFilter,
+) -> Result<Vec<String>> {
+    let mut r = Vec::new();
+    for constraint in cs
+        .constraints
+        .iter()
+        .filter(|c| modules.exports(cs, c))
+        .sorted_by_key(|c| c.name())
+    {
+        r.extend(super::span_comment(cs, constraint.handle()));
+        match constraint {
+            Constraint::Vanishes {
+                handle,
+                domain,
+                expr,
+            } => r.extend(render_constraint(
+                cs,
+                m,
+                &handle.to_string(),
+                domain.as_ref(),
+                expr,
+            )?),
+            Constraint::Lookup {
+                handle,
+                including,
+                included,
+            } => r.push(format!(
+                "self.arguments.inclusion(cs.clone(), \"{}\", &[{}], &[{}])?;",
+                handle,
+                render_columns_of(cs, m, including)?,
+                render_columns_of(cs, m, included)?,
+            )),
+            Constraint::Permutation {
+                handle, from, to, ..
+            } => {
+                r.extend(render_sort_order(cs, m, to));
+                r.push(format!(
+                    "self.arguments.permutation(cs.clone(), \"{}\", &[{}], &[{}])?;",
+                    handle,
+                    from.iter()
+                        .map(|c| column(cs, m, c).map(|c| format!("&{}", c)))
+                        .collect::<Result<Vec<_>>>()?
+                        .join(", "),
+                    to.iter()
+                        .map(|c| column(cs, m, c).map(|c| format!("&{}", c)))
+                        .collect::<Result<Vec<_>>>()?
+                        .join(", "),
+                ))
+            }
+            Constraint::InRange { handle, exp, max } => r.push(format!(
+                "// {}\nin_range({}, \"{}\", |i| Some({}))?;",
+                handle,
+                render_len(cs, m, &handle.to_string(), exp)?,
+                max.to_bi(),
+                render_expression(cs, m, exp)?
+            )),
+            Constraint::Normalization {
+                handle,
+                reference,
+                inverted,
+            } => {
+                let x = reference.clone();
+                let inv_x = Node::column().handle(inverted.clone()).build();
+                let one_minus_x_inv_x = Intrinsic::Sub.call(&[
+                    Node::from_isize(1),
+                    Intrinsic::Mul.call(&[x.clone(), inv_x.clone()])?,
+                ])?;
+
+                // X × (1 - X × /X)
+                r.extend(render_constraint(
+                    cs,
+                    m,
+                    &format!("{}#1", handle),
+                    None,
+                    &Intrinsic::Mul.call(&[x, one_minus_x_inv_x.clone()])?,
+                )?);
+                // /X × (1 - X × /X)
+                r.extend(render_constraint(
+                    cs,
+                    m,
+                    &format!("{}#2", handle),
+                    None,
+                    &Intrinsic::Mul.call(&[inv_x, one_minus_x_inv_x])?,
+                )?);
+            }
+        }
+    }
+    Ok(r)
+}
+
+#[derive(Serialize, Debug)]
+struct RustColumn {
+    rust_id: String,
+    json_register: String,
+}
+#[derive(Serialize)]
+struct RustInterleaved {
+    rust_id: String,
+    interleaving: String,
+}
+
+fn render_columns(
+    cs: &ConstraintSet,
+    m: &Mangling,
+    modules: &ModuleFilter,
+) -> Result<Vec<RustColumn>> {
+    let mut r = Vec::new();
+    for (reference, column) in cs
+        .columns
+        .iter()
+        .filter(|(r, _)| {
+            cs.computations
+                .computation_for(r)
+                .map(|c| c.is_interleaved())
+                != Some(true)
+        })
+        .sorted_by_cached_key(|(_, c)| c.handle.mangle())
+        .filter(|(_, c)| c.used && modules.contains(&c.handle.module))
+    {
+        let register = cs.columns.register_of(&reference);
+        if register.width() > 1 {
+            for i in 0..register.width() {
+                r.push(RustColumn {
+                    rust_id: column_ith(cs, m, &reference, i)?,
+                    json_register: reg_splatter(cs, &column.handle, i)?.to_string(),
+                });
+            }
+        } else {
+            r.push(RustColumn {
+                rust_id: self::column(cs, m, &reference)?,
+                json_register: reg(cs, &column.handle)?.to_string(),
+            });
+        }
+    }
+    Ok(r)
+}
+
+fn render_interleaved(
+    cs: &ConstraintSet,
+    m: &Mangling,
+    modules: &ModuleFilter,
+) -> Result<Vec<RustInterleaved>> {
+    let mut r = Vec::new();
+    for (h, _) in cs
+        .columns
+        .iter()
+        .sorted_by_cached_key(|(_, c)| c.handle.mangle())
+        .filter(|(_, c)| c.used && modules.contains(&c.handle.module))
+    {
+        if let Some(Computation::Interleaved { froms, .. }) = cs.computations.computation_for(&h) {
+            r.push(RustInterleaved {
+                rust_id: self::column(cs, m, &h)?,
+                interleaving: froms
+                    .iter()
+                    .map(|c| self::column(cs, m, c).map(|c| format!("&{}", c)))
+                    .collect::<Result<Vec<_>>>()?
+                    .join(", "),
+            });
+        }
+    }
+    Ok(r)
+}
+
+/// Render `cs` as an arkworks `ConstraintSynthesizer`, proving that a trace
+/// satisfies its constraints; the lookups and permutations are delegated to
+/// an `Arguments` implementation provided by the prover.
+pub fn render(
+    cs: &ConstraintSet,
+    out_filename: Option<&String>,
+    m: &Mangling,
+    modules: &ModuleFilter,
+) -> Result<Option<String>> {
+    let _phase = crate::run_stats::phase("export");
+    #[derive(Serialize)]
+    struct TemplateData {
+        columns: Vec<RustColumn>,
+        interleaved: Vec<RustInterleaved>,
+        constraints: Vec<String>,
+    }
+
+    let mut hb = Handlebars::new();
+    hb.set_dev_mode(true);
+    hb.set_strict_mode(true);
+
+    let columns = render_columns(cs, m, modules)?;
+    let interleaved = render_interleaved(cs, m, modules)?;
+    super::ensure_unique(
+        "Rust identifiers",
+        columns
+            .iter()
+            .map(|c| (c.rust_id.clone(), c.json_register.clone()))
+            .chain(
+                interleaved
+                    .iter()
+                    .map(|c| (c.rust_id.clone(), format!("⪡ {}", c.interleaving))),
+            ),
+    )?;
+
+    let r = hb.render_template(
+        TEMPLATE,
+        &TemplateData {
+            columns,
+            interleaved,
+            constraints: render_constraints(cs, m, modules)?,
+        },
+    )?;
+
+    if let Some(filename) = out_filename {
+        super::write_unit(filename, &super::rustfmt(r))
+    } else {
+        println!("{}", r);
+        Ok(None)
+    }
+}
@@ -0,0 +1,221 @@
+use anyhow::*;
+use convert_case::{Case, Casing};
+use handlebars::Handlebars;
+use itertools::Itertools;
+use num_bigint::BigInt;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+
+use super::Mangling;
+use crate::{
+    compiler::{ColumnRef, ConstraintSet, Kind},
+    pretty::Pretty,
+};
+
+/// How the values of the table columns are laid out in the generated library
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Packing {
+    /// each column is returned as a whole by a function building a
+    /// fixed-size array
+    Array,
+    /// each column is stored as a `bytes` constant, tightly packed on as
+    /// many bytes per element as its type requires, and read element-wise
+    Bytes,
+}
+
+#[derive(Serialize)]
+struct SolidityConstant {
+    name: String,
+    value: String,
+}
+#[derive(Serialize)]
+struct SolidityColumn {
+    name: String,
+    tupe: String,
+    /// the comma-separated elements of the array literal, for [`Packing::Array`]
+    values: String,
+    /// the hex-encoded packed elements, for [`Packing::Bytes`]
+    data: String,
+    data_name: String,
+    bytes: usize,
+    shift: usize,
+}
+#[derive(Serialize)]
+struct SolidityTable {
+    module: String,
+    rows: usize,
+    columns: Vec<SolidityColumn>,
+}
+#[derive(Serialize)]
+struct TemplateData {
+    library: String,
+    constants: Vec<SolidityConstant>,
+    tables: Vec<SolidityTable>,
+}
+
+/// Render the columns of the `tables` modules of `cs`, which must have been
+/// computed from a trace, as a Solidity library named `library`.
+///
+/// Only the trace columns and the columns defined by an expression are
+/// rendered; those created by the compiler for its own needs are left out.
+pub fn render(
+    cs: &ConstraintSet,
+    tables: &[String],
+    library: &str,
+    packing: Packing,
+    outfile: Option<&String>,
+    mangling: &Mangling,
+) -> Result<Option<String>> {
+    let _phase = crate::run_stats::phase("export");
+    const TEMPLATE: &str = include_str!("solidity.sol");
+
+    let modules = cs.columns.modules();
+    for table in tables {
+        if !modules.contains(table) {
+            bail!("unknown module {}", table.red().bold());
+        }
+    }
+
+    let constants = cs
+        .constants
+        .iter()
+        .filter(|c| tables.contains(&c.0.module))
+        .map(|c| SolidityConstant {
+            name: mangling.mangle(c.0).to_case(Case::ScreamingSnake),
+            value: c.1.to_string(),
+        })
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect::<Vec<_>>();
+
+    let mut tables_data = Vec::new();
+    for module in tables.iter().sorted().dedup() {
+        let len = cs.iter_len(module);
+        // the imported columns are prefixed by a padding row, or by as many
+        // as required to reach the module length for the fixed ones
+        let first_row = cs
+            .columns
+            .fixed_len
+            .get(module)
+            .map(|l| len.saturating_sub(*l))
+            .unwrap_or(1);
+        let rows = len.saturating_sub(first_row);
+        if rows == 0 {
+            bail!("table {} is empty", module.red().bold());
+        }
+
+        let columns = cs
+            .columns
+            .iter_module(module)
+            .filter(|(_, c)| matches!(c.kind, Kind::Commitment | Kind::Expression(_)))
+            .sorted_by(|a, b| a.1.handle.name.cmp(&b.1.handle.name))
+            .map(|(r, _)| render_column(cs, &r, first_row, rows, packing, mangling))
+            .collect::<Result<Vec<_>>>()?;
+        tables_data.push(SolidityTable {
+            module: module.to_owned(),
+            rows,
+            columns,
+        });
+    }
+
+    super::ensure_unique(
+        "Solidity identifiers",
+        tables
+            .iter()
+            .flat_map(|module| cs.columns.iter_module(module))
+            .filter(|(_, c)| matches!(c.kind, Kind::Commitment | Kind::Expression(_)))
+            .map(|(_, c)| (mangling.mangle(&c.handle), c.handle.to_string()))
+            .chain(
+                cs.constants
+                    .keys()
+                    .filter(|h| tables.contains(&h.module))
+                    .map(|h| {
+                        (
+                            mangling.mangle(h).to_case(Case::ScreamingSnake),
+                            h.to_string(),
+                        )
+                    }),
+            ),
+    )?;
+
+    let r = Handlebars::new().render_template(
+        TEMPLATE,
+        &TemplateData {
+            library: library.to_owned(),
+            constants,
+            tables: tables_data,
+        },
+    )?;
+
+    if let Some(filename) = outfile.as_ref() {
+        super::write_unit(filename, &r)
+    } else {
+        println!("{}", r);
+        Ok(None)
+    }
+}
+
+fn render_column(
+    cs: &ConstraintSet,
+    r: &ColumnRef,
+    first_row: usize,
+    rows: usize,
+    packing: Packing,
+    mangling: &Mangling,
+) -> Result<SolidityColumn> {
+    let column = cs.columns.column(r)?;
+    let bytes = column.t.byte_size().clamp(1, 32);
+    let tupe = format!("uint{}", 8 * bytes);
+    let values = (first_row..first_row + rows)
+        .map(|i| {
+            let x =
+                BigInt::from(cs.columns.get(r, i as isize, false).with_context(|| {
+                    anyhow!("{} has not been computed", column.handle.pretty())
+                })?);
+            if x.bits() > 8 * bytes as u64 {
+                bail!(
+                    "{} = {} at row {} does not fit in a {}",
+                    column.handle.pretty(),
+                    x,
+                    i - first_row,
+                    tupe
+                )
+            }
+            Ok(x)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let name = mangling.mangle(&column.handle);
+    let (values, data) = match packing {
+        Packing::Array => (
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, x)| {
+                    if i == 0 {
+                        // the first element sets the type of the literal
+                        format!("{}({})", tupe, x)
+                    } else {
+                        x.to_string()
+                    }
+                })
+                .join(", "),
+            String::new(),
+        ),
+        Packing::Bytes => (
+            String::new(),
+            values
+                .iter()
+                .map(|x| format!("{:0>width$}", x.to_str_radix(16), width = 2 * bytes))
+                .collect(),
+        ),
+    };
+    Ok(SolidityColumn {
+        data_name: format!("{}_DATA", name).to_case(Case::ScreamingSnake),
+        name,
+        tupe,
+        values,
+        data,
+        bytes,
+        shift: 8 * (32 - bytes),
+    })
+}
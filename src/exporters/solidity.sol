@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: Apache-2.0
+// Code generated by corset. DO NOT EDIT.
+pragma solidity ^0.8.0;
+
+library {{ library }} {
+{{#each constants}}
+    uint256 internal constant {{this.name}} = {{this.value}};
+{{/each}}
+{{#each tables}}
+
+    // {{this.module}}: {{this.rows}} rows
+{{#each this.columns}}
+{{#if this.data}}
+    bytes internal constant {{this.data_name}} = hex"{{this.data}}";
+    function {{this.name}}(uint256 i) internal pure returns ({{this.tupe}} r) {
+        require(i < {{../rows}}, "{{this.name}}: out of bounds");
+        bytes memory data = {{this.data_name}};
+        assembly {
+            r := shr({{this.shift}}, mload(add(add(data, 32), mul(i, {{this.bytes}}))))
+        }
+    }
+{{else}}
+    function {{this.name}}() internal pure returns ({{this.tupe}}[{{../rows}}] memory) {
+        return [{{this.values}}];
+    }
+{{/if}}
+{{#unless @last}}
+
+{{/unless}}
+{{/each}}
+{{/each}}
+}
@@ -30,3 +30,24 @@ func ZkEVMDefine(build *Builder) {
 	{{{ this }}}
 	{{ /each }}	
 }
+
+// ShiftWindow is made of the largest backward and forward shifts with which
+// the constraints read the columns of a module, to size its evaluation domain.
+type ShiftWindow struct {
+	Backward int
+	Forward  int
+}
+
+var ShiftWindows = map[string]ShiftWindow{
+	{{ #each shift_windows }}
+	{{{ this.module }}}: {Backward: {{ this.backward }}, Forward: {{ this.forward }}},
+	{{ /each }}
+}
+
+// RegisterBits is the number of bits required by the values of every
+// committed register, to size its commitment.
+var RegisterBits = map[string]int{
+	{{ #each register_bits }}
+	"{{ this.0 }}": {{ this.1 }},
+	{{ /each }}
+}
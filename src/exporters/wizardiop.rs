@@ -3,23 +3,31 @@ use itertools::Itertools;
 use log::*;
 use num_traits::ToPrimitive;
 use serde::Serialize;
-use std::{collections::HashSet, io::Write, unreachable};
+use std::{collections::HashSet, unreachable};
 
 use anyhow::*;
 use convert_case::{Case, Casing};
 
+use super::{Mangling, ModuleFilter};
 use crate::{column::Computation, compiler::*, pretty::Pretty, structs::Handle};
 
 const TEMPLATE: &str = include_str!("wizardiop.go");
+const TEST_TEMPLATE: &str = include_str!("wizardiop_test.go");
 
-fn make_chain(cs: &ConstraintSet, xs: &[Node], operand: &str, surround: bool) -> String {
-    let head = render_expression(cs, &xs[0]);
+fn make_chain(
+    cs: &ConstraintSet,
+    m: &Mangling,
+    xs: &[Node],
+    operand: &str,
+    surround: bool,
+) -> String {
+    let head = render_expression(cs, m, &xs[0]);
     if xs.len() > 1 {
         let tail = &xs[1..];
         if xs.len() > 2 {
             let tail = tail
                 .iter()
-                .map(|x| format!("{}({})", operand, render_expression(cs, x)))
+                .map(|x| format!("{}({})", operand, render_expression(cs, m, x)))
                 .collect::<Vec<_>>()
                 .join(".");
             let chain = format!("{}.{}", head, tail);
@@ -29,7 +37,7 @@ fn make_chain(cs: &ConstraintSet, xs: &[Node], operand: &str, surround: bool) ->
                 chain
             }
         } else {
-            format!("{}.{}({})", head, operand, render_expression(cs, &xs[1]))
+            format!("{}.{}({})", head, operand, render_expression(cs, m, &xs[1]))
         }
     } else {
         head
@@ -45,47 +53,47 @@ fn render_shift(shift: isize) -> String {
 }
 
 /// Render an expression, panicking if it is not a handle
-fn render_handle(cs: &ConstraintSet, e: &Node) -> String {
+fn render_handle(cs: &ConstraintSet, m: &Mangling, e: &Node) -> String {
     match e.e() {
         Expression::Column { handle, .. } => {
-            if cs.columns.register(handle).unwrap().width() > 1 {
+            if cs.columns.register_of(handle).width() > 1 {
                 panic!("unable to render exo-columns");
             }
-            reg_mangle(cs, handle).unwrap()
+            reg_mangle(cs, m, handle).unwrap()
         }
         _ => unreachable!("{:?}", e.e()),
     }
 }
 
-fn render_maybe_exo_handle(cs: &ConstraintSet, e: &Node) -> String {
+fn render_maybe_exo_handle(cs: &ConstraintSet, m: &Mangling, e: &Node) -> String {
     match e.e() {
-        Expression::Column { handle, .. } => reg_mangle(cs, handle).unwrap(),
+        Expression::Column { handle, .. } => reg_mangle(cs, m, handle).unwrap(),
         Expression::ExoColumn { handle, .. } => {
-            let register = cs.columns.register(handle).unwrap();
+            let register = cs.columns.register_of(handle);
             let width = register.width();
             (0..width)
-                .map(|i| reg_mangle_ith(cs, handle, i).unwrap())
+                .map(|i| reg_mangle_ith(cs, m, handle, i).unwrap())
                 .join(", ")
         }
         _ => unreachable!("{:?}", e.e()),
     }
 }
 
-fn render_expression(cs: &ConstraintSet, e: &Node) -> String {
+fn render_expression(cs: &ConstraintSet, m: &Mangling, e: &Node) -> String {
     match e.e() {
         Expression::ArrayColumn { .. } => unreachable!(),
         Expression::Const(x) => format!("symbolic.NewConstant(\"{}\")", x),
         Expression::Column { handle, shift, .. } => {
             format!(
                 "{}{}.AsVariable()",
-                reg_mangle(cs, handle).unwrap(),
+                reg_mangle(cs, m, handle).unwrap(),
                 render_shift(*shift as isize)
             )
         }
-        Expression::Funcall { func, args } => render_funcall(cs, func, args),
+        Expression::Funcall { func, args } => render_funcall(cs, m, func, args),
         Expression::List(constraints) => constraints
             .iter()
-            .map(|e| render_expression(cs, e))
+            .map(|e| render_expression(cs, m, e))
             .map(|mut x| {
                 if let Some(true) = x.chars().last().map(|c| c != ',') {
                     x.push(',');
@@ -104,11 +112,11 @@ fn render_expression(cs: &ConstraintSet, e: &Node) -> String {
     }
 }
 
-fn render_funcall(cs: &ConstraintSet, func: &Intrinsic, args: &[Node]) -> String {
+fn render_funcall(cs: &ConstraintSet, m: &Mangling, func: &Intrinsic, args: &[Node]) -> String {
     match func {
-        Intrinsic::Add => make_chain(cs, args, "Add", true),
-        Intrinsic::Mul => make_chain(cs, args, "Mul", false),
-        Intrinsic::Sub | Intrinsic::VectorSub => make_chain(cs, args, "Sub", true), // TODO: drop later
+        Intrinsic::Add => make_chain(cs, m, args, "Add", true),
+        Intrinsic::Mul => make_chain(cs, m, args, "Mul", false),
+        Intrinsic::Sub | Intrinsic::VectorSub => make_chain(cs, m, args, "Sub", true), // TODO: drop later
         Intrinsic::Exp => {
             let exp = args[1]
                 .pure_eval()
@@ -119,9 +127,10 @@ fn render_funcall(cs: &ConstraintSet, func: &Intrinsic, args: &[Node]) -> String
                 });
             match exp {
                 0 => "column.CONST_STRING(\"1\")".to_string(),
-                1 => render_expression(cs, &args[0]),
+                1 => render_expression(cs, m, &args[0]),
                 _ => make_chain(
                     cs,
+                    m,
                     &std::iter::repeat(args[0].clone())
                         .take(exp)
                         .collect::<Vec<_>>(),
@@ -130,103 +139,166 @@ fn render_funcall(cs: &ConstraintSet, func: &Intrinsic, args: &[Node]) -> String
                 ),
             }
         }
-        Intrinsic::Neg => format!("({}).Neg()", render_expression(cs, &args[0])),
+        Intrinsic::Neg => format!("({}).Neg()", render_expression(cs, m, &args[0])),
         x => {
             unimplemented!("{:?}/{:?}", x, args)
         }
     }
 }
 
-fn render_constraints(cs: &ConstraintSet) -> Vec<String> {
+/// Document how the witness generator must order the rows of the sorted
+/// columns `to`, so that it matches the trace computed by corset
+pub(super) fn render_sort_order(
+    cs: &ConstraintSet,
+    m: &Mangling,
+    to: &[ColumnRef],
+) -> Option<String> {
+    if let Some(Computation::Sorted {
+        froms,
+        signs,
+        stable,
+        tiebreaks,
+        ..
+    }) = cs.computations.computation_for(&to[0])
+    {
+        Some(format!(
+            "// sorted by {}{}; {}",
+            froms
+                .iter()
+                .zip(signs.iter())
+                .map(|(c, s)| format!(
+                    "{} {}",
+                    if *s { "ascending" } else { "descending" },
+                    reg_mangle(cs, m, c).unwrap()
+                ))
+                .join(", "),
+            if tiebreaks.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    ", ties broken by {}",
+                    tiebreaks
+                        .iter()
+                        .map(|c| reg_mangle(cs, m, c).unwrap())
+                        .join(", ")
+                )
+            },
+            if *stable {
+                "equal rows keep their original order"
+            } else {
+                "equal rows are in no particular order"
+            }
+        ))
+    } else {
+        None
+    }
+}
+
+pub(crate) fn render_constraints(
+    cs: &ConstraintSet,
+    m: &Mangling,
+    modules: &ModuleFilter,
+) -> Vec<String> {
     cs.constraints
         .iter()
+        .filter(|c| modules.exports(cs, c))
         .sorted_by_key(|c| c.name())
-        .flat_map(|constraint| match constraint {
-            Constraint::Vanishes {
-                handle,
-                domain,
-                expr,
-            } => render_constraint(cs, &handle.to_string(), domain.clone(), expr),
-            Constraint::Lookup {
-                handle,
-                including,
-                included,
-            } => vec![format!(
-                "build.Inclusion(\"{}\", []Handle{{{}}}, []Handle{{{}}})",
-                handle,
-                including
-                    .iter()
-                    .map(|h| render_maybe_exo_handle(cs, h))
-                    .collect::<Vec<_>>()
-                    .join(", "),
-                included
-                    .iter()
-                    .map(|h| render_maybe_exo_handle(cs, h))
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            )],
-            Constraint::Permutation {
-                handle, from, to, ..
-            } => vec![format!(
-                "build.Permutation(\"{}\", []Handle{{{}}}, []Handle{{{}}})",
-                handle.mangle().to_case(Case::Snake),
-                from.iter()
-                    .map(|c| reg_mangle(cs, c).unwrap())
-                    .collect::<Vec<_>>()
-                    .join(", "),
-                to.iter()
-                    .map(|h| reg_mangle(cs, h).unwrap())
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            )],
-            Constraint::InRange { handle, exp, max } => vec![format!(
-                "build.Range(\"{}\", {}, {})",
-                handle.mangle().to_case(Case::Snake),
-                render_handle(cs, exp),
-                max.pretty()
-            )],
-            Constraint::Normalization {
-                handle,
-                reference,
-                inverted,
-            } => {
-                let mut r = Vec::new();
-                let x = reference.clone();
-                let inv_x = Node::column().handle(inverted.clone()).build();
-                let x_times_inv_x = Intrinsic::Mul.call(&[x.clone(), inv_x.clone()]).unwrap();
-                let one = Node::from_isize(1);
-
-                // X × (1 - X × /X)
-                r.append(&mut render_constraint(
-                    cs,
-                    &format!("{}#1", handle),
-                    None,
-                    &Intrinsic::Mul
-                        .call(&[
-                            x.clone(),
-                            Intrinsic::Sub
-                                .call(&[one.clone(), x_times_inv_x.clone()])
+        .flat_map(|constraint| {
+            super::span_comment(cs, constraint.handle())
+                .into_iter()
+                .chain(match constraint {
+                    Constraint::Vanishes {
+                        handle,
+                        domain,
+                        expr,
+                    } => render_constraint(cs, m, &handle.to_string(), domain.clone(), expr),
+                    Constraint::Lookup {
+                        handle,
+                        including,
+                        included,
+                    } => vec![format!(
+                        "build.Inclusion(\"{}\", []Handle{{{}}}, []Handle{{{}}})",
+                        handle,
+                        including
+                            .iter()
+                            .map(|h| render_maybe_exo_handle(cs, m, h))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        included
+                            .iter()
+                            .map(|h| render_maybe_exo_handle(cs, m, h))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )],
+                    Constraint::Permutation {
+                        handle, from, to, ..
+                    } => render_sort_order(cs, m, to)
+                        .into_iter()
+                        .chain(std::iter::once(format!(
+                            "build.Permutation(\"{}\", []Handle{{{}}}, []Handle{{{}}})",
+                            handle.mangle().to_case(Case::Snake),
+                            from.iter()
+                                .map(|c| reg_mangle(cs, m, c).unwrap())
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                            to.iter()
+                                .map(|h| reg_mangle(cs, m, h).unwrap())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        )))
+                        .collect(),
+                    Constraint::InRange { handle, exp, max } => vec![format!(
+                        "build.Range(\"{}\", {}, {})",
+                        handle.mangle().to_case(Case::Snake),
+                        render_handle(cs, m, exp),
+                        max.pretty()
+                    )],
+                    Constraint::Normalization {
+                        handle,
+                        reference,
+                        inverted,
+                    } => {
+                        let mut r = Vec::new();
+                        let x = reference.clone();
+                        let inv_x = Node::column().handle(inverted.clone()).build();
+                        let x_times_inv_x =
+                            Intrinsic::Mul.call(&[x.clone(), inv_x.clone()]).unwrap();
+                        let one = Node::from_isize(1);
+
+                        // X × (1 - X × /X)
+                        r.append(&mut render_constraint(
+                            cs,
+                            m,
+                            &format!("{}#1", handle),
+                            None,
+                            &Intrinsic::Mul
+                                .call(&[
+                                    x.clone(),
+                                    Intrinsic::Sub
+                                        .call(&[one.clone(), x_times_inv_x.clone()])
+                                        .unwrap(),
+                                ])
                                 .unwrap(),
-                        ])
-                        .unwrap(),
-                ));
-                // /X × (1 - X × /X)
-                r.append(&mut render_constraint(
-                    cs,
-                    &format!("{}#2", handle),
-                    None,
-                    &Intrinsic::Mul
-                        .call(&[
-                            inv_x.clone(),
-                            Intrinsic::Sub
-                                .call(&[one.clone(), x_times_inv_x.clone()])
+                        ));
+                        // /X × (1 - X × /X)
+                        r.append(&mut render_constraint(
+                            cs,
+                            m,
+                            &format!("{}#2", handle),
+                            None,
+                            &Intrinsic::Mul
+                                .call(&[
+                                    inv_x.clone(),
+                                    Intrinsic::Sub
+                                        .call(&[one.clone(), x_times_inv_x.clone()])
+                                        .unwrap(),
+                                ])
                                 .unwrap(),
-                        ])
-                        .unwrap(),
-                ));
+                        ));
 
-                r
-            }
+                        r
+                    }
+                })
         })
         .collect()
 }
@@ -240,7 +312,7 @@ fn make_size(h: &Handle, sizes: &mut HashSet<String>) -> String {
     r
 }
 
-fn reg_mangle(cs: &ConstraintSet, c: &ColumnRef) -> Result<String> {
+pub(super) fn reg_mangle(cs: &ConstraintSet, m: &Mangling, c: &ColumnRef) -> Result<String> {
     let reg_id = cs
         .columns
         .column(c)?
@@ -254,11 +326,16 @@ fn reg_mangle(cs: &ConstraintSet, c: &ColumnRef) -> Result<String> {
     Ok(reg
         .handle
         .as_ref()
-        .map(|h| h.mangle())
-        .unwrap_or_else(|| Handle::new("", reg_id.to_string()).mangle()))
+        .map(|h| m.mangle(h))
+        .unwrap_or_else(|| m.mangle(&Handle::new("", reg_id.to_string()))))
 }
 
-fn reg_mangle_ith(cs: &ConstraintSet, c: &ColumnRef, i: usize) -> Result<String> {
+pub(super) fn reg_mangle_ith(
+    cs: &ConstraintSet,
+    m: &Mangling,
+    c: &ColumnRef,
+    i: usize,
+) -> Result<String> {
     let reg_id = cs
         .columns
         .column(c)?
@@ -272,11 +349,11 @@ fn reg_mangle_ith(cs: &ConstraintSet, c: &ColumnRef, i: usize) -> Result<String>
     Ok(reg
         .handle
         .as_ref()
-        .map(|h| h.mangle_ith(i))
-        .unwrap_or_else(|| Handle::new("", format!("{}_#{}", reg_id, i)).mangle()))
+        .map(|h| m.mangle_ith(h, i))
+        .unwrap_or_else(|| m.mangle(&Handle::new("", format!("{}_#{}", reg_id, i)))))
 }
 
-fn reg(cs: &ConstraintSet, c: &Handle) -> Result<Handle> {
+pub(super) fn reg(cs: &ConstraintSet, c: &Handle) -> Result<Handle> {
     let reg_id = cs
         .columns
         .by_handle(c)?
@@ -294,7 +371,7 @@ fn reg(cs: &ConstraintSet, c: &Handle) -> Result<Handle> {
         .unwrap_or_else(|| Handle::new(&c.module, reg_id.to_string())))
 }
 
-fn reg_splatter(cs: &ConstraintSet, c: &Handle, i: usize) -> Result<Handle> {
+pub(super) fn reg_splatter(cs: &ConstraintSet, c: &Handle, i: usize) -> Result<Handle> {
     let reg_id = cs
         .columns
         .by_handle(c)?
@@ -317,6 +394,8 @@ struct WiopColumn {
     go_id: String,
     json_register: String,
     size: String,
+    /// the bits required by the values of the register
+    bits: usize,
 }
 #[derive(Serialize)]
 struct WiopInterleaved {
@@ -324,7 +403,13 @@ struct WiopInterleaved {
     interleaving: String,
 }
 
-fn render_columns(cs: &ConstraintSet, sizes: &mut HashSet<String>) -> Vec<WiopColumn> {
+fn render_columns(
+    cs: &ConstraintSet,
+    m: &Mangling,
+    modules: &ModuleFilter,
+    sizes: &mut HashSet<String>,
+) -> Vec<WiopColumn> {
+    let bits = cs.columns.register_bit_widths();
     cs.columns
         .iter()
         .filter(|(r, _)| {
@@ -334,14 +419,18 @@ fn render_columns(cs: &ConstraintSet, sizes: &mut HashSet<String>) -> Vec<WiopCo
                 != Some(true)
         })
         .sorted_by_cached_key(|(_, c)| c.handle.mangle())
-        .filter(|(_, c)| c.used)
+        .filter(|(_, c)| c.used && modules.contains(&c.handle.module))
         .flat_map(|(reference, column)| {
             let size_multiplier = cs.length_multiplier(&reference);
             let register = cs.columns.register_of(&reference);
+            let register_bits = bits[column.register.unwrap()];
             if register.width() > 1 {
                 (0..register.width())
                     .map(|i| WiopColumn {
-                        go_id: reg_mangle_ith(cs, &reference, i).unwrap(),
+                        bits: register_bits
+                            .saturating_sub(i * crate::constants::FIELD_BITSIZE)
+                            .min(crate::constants::FIELD_BITSIZE),
+                        go_id: reg_mangle_ith(cs, m, &reference, i).unwrap(),
                         json_register: reg_splatter(cs, &column.handle, i).unwrap().to_string(),
                         size: if size_multiplier == 1 {
                             make_size(&column.handle, sizes)
@@ -352,7 +441,8 @@ fn render_columns(cs: &ConstraintSet, sizes: &mut HashSet<String>) -> Vec<WiopCo
                     .collect::<Vec<_>>()
             } else {
                 vec![WiopColumn {
-                    go_id: reg_mangle(cs, &reference).unwrap(),
+                    bits: register_bits,
+                    go_id: reg_mangle(cs, m, &reference).unwrap(),
                     json_register: reg(cs, &column.handle).unwrap().to_string(),
                     size: if size_multiplier == 1 {
                         make_size(&column.handle, sizes)
@@ -365,7 +455,12 @@ fn render_columns(cs: &ConstraintSet, sizes: &mut HashSet<String>) -> Vec<WiopCo
         .collect()
 }
 
-fn render_interleaved(cs: &ConstraintSet, _sizes: &mut HashSet<String>) -> Vec<WiopInterleaved> {
+fn render_interleaved(
+    cs: &ConstraintSet,
+    m: &Mangling,
+    modules: &ModuleFilter,
+    _sizes: &mut HashSet<String>,
+) -> Vec<WiopInterleaved> {
     cs.columns
         .iter()
         .filter(|col| {
@@ -376,15 +471,15 @@ fn render_interleaved(cs: &ConstraintSet, _sizes: &mut HashSet<String>) -> Vec<W
         })
         .sorted_by_cached_key(|col| col.1.handle.mangle())
         .filter_map(|(h, column)| {
-            if column.used {
+            if column.used && modules.contains(&column.handle.module) {
                 Some(WiopInterleaved {
-                    go_id: reg_mangle(cs, &h).unwrap(),
+                    go_id: reg_mangle(cs, m, &h).unwrap(),
                     interleaving: if let Some(Computation::Interleaved { froms, .. }) =
                         cs.computations.computation_for(&h)
                     {
                         froms
                             .iter()
-                            .map(|c| reg_mangle(cs, c).unwrap())
+                            .map(|c| reg_mangle(cs, m, c).unwrap())
                             .collect::<Vec<_>>()
                             .join(", ")
                     } else {
@@ -400,6 +495,7 @@ fn render_interleaved(cs: &ConstraintSet, _sizes: &mut HashSet<String>) -> Vec<W
 
 fn render_constraint(
     cs: &ConstraintSet,
+    m: &Mangling,
     name: &str,
     domain: Option<Domain<isize>>,
     expr: &Node,
@@ -408,14 +504,16 @@ fn render_constraint(
         Expression::List(xs) => xs
             .iter()
             .enumerate()
-            .flat_map(|(i, x)| render_constraint(cs, &format!("{}#{}", name, i), domain.clone(), x))
+            .flat_map(|(i, x)| {
+                render_constraint(cs, m, &format!("{}#{}", name, i), domain.clone(), x)
+            })
             .collect(),
         Expression::ExoColumn { handle, shift, .. } => {
             let register = cs.columns.register_of(handle);
 
             (0..register.width())
                 .map(|i| {
-                    let reg_name = reg_mangle_ith(cs, handle, i).unwrap();
+                    let reg_name = reg_mangle_ith(cs, m, handle, i).unwrap();
                     match &domain {
                         None => {
                             format!(
@@ -447,7 +545,7 @@ fn render_constraint(
             None => vec![format!(
                 "build.GlobalConstraint(\"{}\", {})",
                 name,
-                render_expression(cs, expr)
+                render_expression(cs, m, expr)
             )],
             Some(domain) => domain
                 .iter()
@@ -455,7 +553,7 @@ fn render_constraint(
                     format!(
                         "build.LocalConstraint(\"{}\", {})",
                         name,
-                        render_expression(cs, &expr.clone().shift(x.try_into().unwrap()))
+                        render_expression(cs, m, &expr.clone().shift(x.try_into().unwrap()))
                     )
                 })
                 .collect::<Vec<_>>(),
@@ -463,12 +561,21 @@ fn render_constraint(
     }
 }
 
-pub fn render(cs: &ConstraintSet, out_filename: &Option<String>) -> Result<()> {
+pub fn render(
+    cs: &ConstraintSet,
+    out_filename: &Option<String>,
+    m: &Mangling,
+    modules: &ModuleFilter,
+) -> Result<Option<String>> {
+    let _phase = crate::run_stats::phase("export");
     #[derive(Serialize)]
     struct TemplateData {
         columns: Vec<WiopColumn>,
         interleaved: Vec<WiopInterleaved>,
         constraints: Vec<String>,
+        shift_windows: Vec<super::GoShiftWindow>,
+        /// the bits required by every committed register
+        register_bits: Vec<(String, usize)>,
     }
     let mut sizes: HashSet<String> = HashSet::new();
 
@@ -476,23 +583,145 @@ pub fn render(cs: &ConstraintSet, out_filename: &Option<String>) -> Result<()> {
     hb.set_dev_mode(true);
     hb.set_strict_mode(true);
 
+    let columns = render_columns(cs, m, modules, &mut sizes);
+    let interleaved = render_interleaved(cs, m, modules, &mut sizes);
+    super::ensure_unique(
+        "Go identifiers",
+        columns
+            .iter()
+            .map(|c| (c.go_id.clone(), c.json_register.clone()))
+            .chain(
+                interleaved
+                    .iter()
+                    .map(|c| (c.go_id.clone(), format!("⪡ {}", c.interleaving))),
+            ),
+    )?;
+
+    // columns of different perspectives may share a register
+    let register_bits = columns
+        .iter()
+        .map(|c| (c.json_register.clone(), c.bits))
+        .unique_by(|(r, _)| r.clone())
+        .collect();
     let r = hb.render_template(
         TEMPLATE,
         &TemplateData {
-            columns: render_columns(cs, &mut sizes),
-            interleaved: render_interleaved(cs, &mut sizes),
-            constraints: render_constraints(cs),
+            register_bits,
+            columns,
+            interleaved,
+            constraints: render_constraints(cs, m, modules),
+            shift_windows: super::go_shift_windows(cs, modules),
         },
     )?;
 
     if let Some(filename) = out_filename.as_ref() {
-        std::fs::File::create(filename)
-            .with_context(|| format!("while creating `{}`", filename))?
-            .write_all(r.as_bytes())
-            .with_context(|| format!("while writing to `{}`", filename))?;
-        super::gofmt(filename);
+        super::write_unit(filename, &super::gofmt(r))
     } else {
         println!("{}", r);
+        Ok(None)
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct TestVector {
+    /// the constraint name, as a quoted Go string
+    pub(crate) constraint: String,
+    pub(crate) row: isize,
+    /// the value of the constraint at `row`, in decimal
+    pub(crate) expected: String,
+}
+
+/// Collect the global constraints as they are named by [`render_constraint`]
+fn global_constraints<'a>(name: String, expr: &'a Node, r: &mut Vec<(String, &'a Node)>) {
+    match expr.e() {
+        Expression::List(xs) => {
+            for (i, x) in xs.iter().enumerate() {
+                global_constraints(format!("{}#{}", name, i), x, r)
+            }
+        }
+        // exo-columns are split into as many constraints as registers and
+        // can not be evaluated as a whole
+        Expression::ExoColumn { .. } => {}
+        _ => r.push((name, expr)),
+    }
+}
+
+/// Evaluate the global constraints of `cs` exported by `modules` on the trace
+/// it has been filled with, at `samples` rows evenly spread over their span.
+pub(crate) fn test_vectors(
+    cs: &ConstraintSet,
+    samples: usize,
+    modules: &ModuleFilter,
+) -> Result<Vec<TestVector>> {
+    let mut constraints = Vec::new();
+    for c in cs
+        .constraints
+        .iter()
+        .filter(|c| modules.exports(cs, c))
+        .sorted_by_key(|c| c.name())
+    {
+        if let Constraint::Vanishes {
+            handle,
+            domain: None,
+            expr,
+        } = c
+        {
+            global_constraints(handle.to_string(), expr, &mut constraints);
+        }
+    }
+
+    let mut r = Vec::new();
+    for (name, expr) in constraints {
+        let Some(len) = cs.dependencies_len(expr, false)? else {
+            continue;
+        };
+        for row in (0..samples).map(|k| (k * len / samples) as isize).dedup() {
+            if let Some(x) = expr.eval(
+                row,
+                |handle, i, wrap| cs.columns.get_raw(handle, i, wrap),
+                &mut None,
+                &EvalSettings::new().wrap(false),
+            ) {
+                r.push(TestVector {
+                    constraint: super::go_quote(&name),
+                    row,
+                    expected: x.to_bi().to_string(),
+                });
+            }
+        }
+    }
+    Ok(r)
+}
+
+/// Render, as a Go test file, the values taken by the global constraints of
+/// `cs` at `samples` rows of `trace`, against which the Go evaluation of the
+/// constraints generated by [`render`] can be checked.
+pub fn render_test_vectors(
+    cs: &ConstraintSet,
+    trace: &str,
+    samples: usize,
+    out_filename: &str,
+    modules: &ModuleFilter,
+) -> Result<Option<String>> {
+    #[derive(Serialize)]
+    struct TemplateData {
+        trace: String,
+        vectors: Vec<TestVector>,
     }
-    Ok(())
+
+    let mut hb = Handlebars::new();
+    hb.set_dev_mode(true);
+    hb.set_strict_mode(true);
+
+    let vectors = test_vectors(cs, samples, modules)?;
+    info!("{} test vectors evaluated from {}", vectors.len(), trace);
+    let r = hb.render_template(
+        TEST_TEMPLATE,
+        &TemplateData {
+            trace: super::go_quote(trace),
+            vectors,
+        },
+    )?;
+
+    super::write_unit(out_filename, &super::gofmt(r))
 }
@@ -0,0 +1,49 @@
+package define
+
+import "testing"
+
+// The trace the test vectors below have been evaluated on
+const TestVectorsTrace = {{{ trace }}}
+
+// EvaluateConstraint shall evaluate the constraint named `name` at row `row` of
+// TestVectorsTrace, and return the result as a decimal string. It is expected
+// to be set by the test harness, e.g. in an init() function; the tests are
+// skipped otherwise.
+var EvaluateConstraint func(name string, row int) (string, error)
+
+var testVectors = []struct {
+	constraint string
+	row        int
+	expected   string
+}{
+	{{ #each vectors }}
+	{ {{{ constraint }}}, {{ row }}, "{{ expected }}" },
+	{{ /each }}
+}
+
+func TestConstraintVectors(t *testing.T) {
+	if EvaluateConstraint == nil {
+		t.Skip("EvaluateConstraint is not set")
+	}
+	for _, v := range testVectors {
+		found, err := EvaluateConstraint(v.constraint, v.row)
+		if err != nil {
+			t.Errorf("%s@%d: %v", v.constraint, v.row, err)
+		} else if found != v.expected {
+			t.Errorf("%s@%d: expected %s, found %s", v.constraint, v.row, v.expected, found)
+		}
+	}
+}
+
+func BenchmarkConstraintVectors(b *testing.B) {
+	if EvaluateConstraint == nil {
+		b.Skip("EvaluateConstraint is not set")
+	}
+	for i := 0; i < b.N; i++ {
+		for _, v := range testVectors {
+			if _, err := EvaluateConstraint(v.constraint, v.row); err != nil {
+				b.Fatalf("%s@%d: %v", v.constraint, v.row, err)
+			}
+		}
+	}
+}
@@ -1,6 +1,9 @@
 package {{ module }}
 
 import (
+	"encoding/json"
+	"io"
+
 	"github.com/ethereum/go-ethereum/zk-evm/zeroknowledge/witnessdata/column"
 )
 
@@ -22,3 +25,44 @@ var AllRegisters = column.ColumnList{
 	{{this.1}},
 	{{/each}}
 }
+
+// RegisterBits is the number of bits required by the values of every
+// register, i.e. that of its widest column, to size its commitment.
+var RegisterBits = map[string]int{
+	{{#each registers}}
+	"{{this.1}}": {{this.2}},
+	{{/each}}
+}
+
+// ShiftWindow is made of the largest backward and forward shifts with which
+// the constraints read the columns of a module.
+type ShiftWindow struct {
+	Backward int
+	Forward  int
+}
+
+var ShiftWindows = map[string]ShiftWindow{
+	{{#each shift_windows}}
+	{{{this.module}}}: {Backward: {{this.backward}}, Forward: {{this.forward}}},
+	{{/each}}
+}
+
+// ModuleLength is the length of a module in a trace expanded by corset; the
+// padded length is the raw length, increased by the spilling of the module.
+type ModuleLength struct {
+	RawLength    int `json:"raw_length"`
+	PaddedLength int `json:"padded_length"`
+	Spilling     int `json:"spilling"`
+}
+
+// ReadModuleLengths reads the lengths of the modules from a trace written by
+// `corset compute`, so that they do not have to be derived again.
+func ReadModuleLengths(r io.Reader) (map[string]ModuleLength, error) {
+	var trace struct {
+		Modules map[string]ModuleLength `json:"modules"`
+	}
+	if err := json.NewDecoder(r).Decode(&trace); err != nil {
+		return nil, err
+	}
+	return trace.Modules, nil
+}
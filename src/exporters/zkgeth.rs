@@ -3,8 +3,8 @@ use convert_case::{Case, Casing};
 use handlebars::Handlebars;
 use itertools::Itertools;
 use serde::Serialize;
-use std::io::Write;
 
+use super::{Mangling, ModuleFilter};
 use crate::compiler::*;
 
 #[derive(Serialize)]
@@ -23,22 +23,31 @@ struct TemplateData {
     module: String,
     columns: Vec<GoColumn>,
     constants: Vec<GoConstant>,
-    registers: Vec<(usize, String)>,
+    /// the ID, name and bit width of the registers
+    registers: Vec<(usize, String, usize)>,
+    shift_windows: Vec<super::GoShiftWindow>,
 }
 
-pub fn render(cs: &ConstraintSet, package: &str, outfile: Option<&String>) -> Result<()> {
+pub fn render(
+    cs: &ConstraintSet,
+    package: &str,
+    outfile: Option<&String>,
+    mangling: &Mangling,
+    modules: &ModuleFilter,
+) -> Result<Option<String>> {
+    let _phase = crate::run_stats::phase("export");
     const TEMPLATE: &str = include_str!("zkgeth.go");
     let columns = cs
         .columns
         .iter_cols()
         .filter_map(|c| {
-            if matches!(c.kind, Kind::Commitment) {
+            if matches!(c.kind, Kind::Commitment) && modules.contains(&c.handle.module) {
                 let r = c.register.unwrap();
                 let register = super::reg_to_string(&cs.columns.registers[r], r);
                 Some(GoColumn {
                     reg_name: register,
                     reg_id: r,
-                    go_name: c.handle.mangled_name(),
+                    go_name: mangling.mangled_name(&c.handle),
                 })
             } else {
                 None
@@ -47,24 +56,54 @@ pub fn render(cs: &ConstraintSet, package: &str, outfile: Option<&String>) -> Re
         .sorted_by(|a, b| a.reg_name.cmp(&b.reg_name))
         .collect::<Vec<_>>();
 
+    let bits = cs.columns.register_bit_widths();
     let registers = cs
         .columns
         .registers
         .iter()
         .enumerate()
-        .map(|(i, r)| (i, super::reg_to_string(r, i)))
+        .filter(|(_, r)| {
+            r.handle
+                .as_ref()
+                .map_or(true, |h| modules.contains(&h.module))
+        })
+        .map(|(i, r)| (i, super::reg_to_string(r, i), bits[i]))
         .collect::<Vec<_>>();
 
     let constants = cs
         .constants
         .iter()
+        .filter(|c| c.0.module == MAIN_MODULE || modules.contains(&c.0.module))
         .map(|c| GoConstant {
-            name: c.0.mangled_name().to_case(Case::ScreamingSnake),
+            name: c
+                .0
+                .mangled_name_with(&mangling.separator, mangling.scheme)
+                .to_case(Case::ScreamingSnake),
             value: c.1.to_string(),
         })
         .sorted_by(|a, b| a.name.cmp(&b.name))
         .collect::<Vec<_>>();
 
+    super::ensure_unique(
+        "Go identifiers",
+        cs.columns
+            .iter_cols()
+            .filter(|c| matches!(c.kind, Kind::Commitment) && modules.contains(&c.handle.module))
+            .map(|c| (mangling.mangled_name(&c.handle), c.handle.to_string()))
+            .chain(
+                cs.constants
+                    .keys()
+                    .filter(|h| h.module == MAIN_MODULE || modules.contains(&h.module))
+                    .map(|h| {
+                        (
+                            h.mangled_name_with(&mangling.separator, mangling.scheme)
+                                .to_case(Case::ScreamingSnake),
+                            h.to_string(),
+                        )
+                    }),
+            ),
+    )?;
+
     let r = Handlebars::new().render_template(
         TEMPLATE,
         &TemplateData {
@@ -72,18 +111,14 @@ pub fn render(cs: &ConstraintSet, package: &str, outfile: Option<&String>) -> Re
             columns,
             registers,
             constants,
+            shift_windows: super::go_shift_windows(cs, modules),
         },
     )?;
 
     if let Some(filename) = outfile.as_ref() {
-        std::fs::File::create(filename)
-            .with_context(|| format!("while creating `{}`", filename))?
-            .write_all(r.as_bytes())
-            .with_context(|| format!("while writing to `{}`", filename))?;
-        super::gofmt(filename);
-        Ok(())
+        super::write_unit(filename, &super::gofmt(r))
     } else {
         println!("{}", r);
-        Ok(())
+        Ok(None)
     }
 }
@@ -242,12 +242,19 @@ fn format_defperspective(xs: &[AstNode], tty: &mut Tty) {
                 Token::List(_) => {
                     format_headless_list(x, tty);
                 }
+                // keep the sharing marker on the same line as the name
+                Token::Keyword(kw) if kw == ":shared" => {
+                    tty.write(" ");
+                    x.format(tty);
+                }
                 _ => {
                     x.format(tty);
                 }
             },
             |x, tty| {
-                if !x.is_inline_comment() {
+                if !x.is_inline_comment()
+                    && !matches!(&x.class, Token::Keyword(kw) if kw == ":shared")
+                {
                     tty.cr()
                 }
             },
@@ -255,6 +262,22 @@ fn format_defperspective(xs: &[AstNode], tty: &mut Tty) {
     });
 }
 
+/// Format the trailing expressions of a definition, one per line
+fn format_body(body: &[AstNode], tty: &mut Tty) {
+    tty.each_but_last(
+        body.iter(),
+        |x, tty| x.format(tty),
+        |next, tty| {
+            if !next.is_inline_comment() {
+                tty.cr()
+            }
+        },
+    );
+    if body.last().map(|x| x.is_block_comment()).unwrap_or(false) {
+        tty.cr();
+    }
+}
+
 fn format_defun(xs: &[AstNode], tty: &mut Tty) {
     let fname = xs[0].as_symbol().unwrap();
     tty.within(fname, None, |tty| {
@@ -269,17 +292,12 @@ fn format_defun(xs: &[AstNode], tty: &mut Tty) {
             tty.cr();
         };
 
-        for rest in xs.iter().skip(2) {
-            rest.format(tty);
-            if matches!(rest.class, Token::BlockComment(_)) {
-                tty.cr();
-            }
-        }
+        format_body(xs.get(2..).unwrap_or_default(), tty);
     });
 }
 
-fn format_defconstraint(xs: &[AstNode], tty: &mut Tty) {
-    tty.within("defconstraint", None, |tty| {
+fn format_defconstraint(kw: &str, xs: &[AstNode], tty: &mut Tty) {
+    tty.within(kw, None, |tty| {
         if let Some(name) = xs.get(1) {
             name.format(tty);
         };
@@ -294,12 +312,7 @@ fn format_defconstraint(xs: &[AstNode], tty: &mut Tty) {
         }
 
         tty.cr();
-        for rest in xs.iter().skip(3) {
-            rest.format(tty);
-            if matches!(rest.class, Token::BlockComment(_)) {
-                tty.cr();
-            }
-        }
+        format_body(xs.get(3..).unwrap_or_default(), tty);
     });
 }
 
@@ -320,19 +333,9 @@ fn format_let(n: &[AstNode], tty: &mut Tty) {
                 )
             });
             tty.cr();
-            for n in n.iter().skip(2) {
-                n.format(tty);
-                if matches!(n.class, Token::BlockComment(_)) {
-                    tty.cr();
-                }
-            }
+            format_body(n.get(2..).unwrap_or_default(), tty);
         } else {
-            for n in n.iter().skip(1) {
-                n.format(tty);
-                if matches!(n.class, Token::BlockComment(_)) {
-                    tty.cr();
-                }
-            }
+            format_body(n.get(1..).unwrap_or_default(), tty);
         }
     });
 }
@@ -395,7 +398,7 @@ fn format_defpairs(xs: &[AstNode], tty: &mut Tty) {
             } else {
                 if i {
                     if let Ok(name) = n.as_symbol() {
-                        tty.write(&format!("{:1$}", name, max_length));
+                        tty.write(format!("{:1$}", name, max_length));
                     } else {
                         n.format(tty);
                     }
@@ -549,12 +552,34 @@ impl Ast {
         tty.page_feed()
     }
 }
+impl Ast {
+    /// The lines of the comments of the AST, in order of appearance and
+    /// stripped of their indentation
+    pub fn comments(&self) -> Vec<String> {
+        fn rec(n: &AstNode, ax: &mut Vec<String>) {
+            match &n.class {
+                Token::BlockComment(c) | Token::InlineComment(c) => ax.extend(
+                    c.lines()
+                        .map(|l| l.trim().to_owned())
+                        .filter(|l| !l.is_empty()),
+                ),
+                Token::List(ns) => ns.iter().for_each(|n| rec(n, ax)),
+                _ => {}
+            }
+        }
+
+        let mut r = Vec::new();
+        self.exprs.iter().for_each(|n| rec(n, &mut r));
+        r
+    }
+}
 impl AstNode {
     fn len(&self) -> usize {
         match &self.class {
             Token::BlockComment(_) => 0,
             Token::Value(x) => x.to_string().len(),
             Token::Symbol(s) | Token::Keyword(s) => s.len(),
+            Token::String(s) => s.len() + 2,
             Token::List(ns) => ns.iter().map(|n| n.len() + 1).sum::<usize>() + 2,
             Token::Domain(domain) => {
                 domain
@@ -563,7 +588,7 @@ impl AstNode {
                     .sum::<usize>()
                     + 1
             }
-            Token::DefModule(m) => 2 + "module".len() + 1 + m.len(),
+            Token::DefModule(m, _) => 2 + "module".len() + 1 + m.len(),
             _ => 0,
         }
     }
@@ -608,9 +633,11 @@ impl AstNode {
                         false
                     }
                     Token::List(ns) => {
-                        match ns.get(0).and_then(|x| x.as_symbol().ok()) {
+                        match ns.first().and_then(|x| x.as_symbol().ok()) {
                             Some("defun") | Some("defpurefun") => format_defun(ns, tty),
-                            Some("defconstraint") => format_defconstraint(ns, tty),
+                            Some(kw @ ("defconstraint" | "defassertion")) => {
+                                format_defconstraint(kw, ns, tty)
+                            }
                             Some("defcolumns") => format_defcolumns(ns, tty),
                             Some("defconst") | Some("defalias") | Some("defunalias") => {
                                 format_defpairs(ns, tty)
@@ -620,7 +647,7 @@ impl AstNode {
                             Some("defpermutation") => format_defpermutation(ns, tty),
                             Some("definterleaved") => format_definterleaved(ns, tty),
                             Some("let") => format_let(ns, tty),
-                            Some("module") | Some("definrange") => {
+                            Some("module") | Some("definrange") | Some("include") => {
                                 format_list(self, false, true, false, tty);
                             }
                             Some("if-eq") | Some("if-eq-else") => format_ifeq(self, tty),
@@ -628,7 +655,7 @@ impl AstNode {
                         };
                         false
                     }
-                    Token::Domain(_) => {
+                    Token::Domain(_) | Token::String(_) => {
                         tty.write(&self.src);
                         false
                     }
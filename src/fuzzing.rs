@@ -0,0 +1,33 @@
+//! Entry points exposing the untrusted-input boundaries of corset to fuzzers.
+//! They are not part of the public API, and may change without notice.
+use anyhow::*;
+
+use crate::compiler::{self, CompileSettings, ConstraintSet};
+
+/// Compile the Corset source `source` and fully expand the resulting
+/// constraint system
+pub fn compile(source: &str) -> Result<ConstraintSet> {
+    let (_, constraints) = compiler::make(
+        &[("fuzz", source)],
+        &CompileSettings {
+            debug: true,
+            ..Default::default()
+        },
+    )?;
+    crate::make_corset(constraints)
+}
+
+/// Load a constraint system serialized as by `corset compile`
+pub fn load_constraints(serialized: &str) -> Result<ConstraintSet> {
+    crate::_corset_from_str(serialized)
+}
+
+/// Import a binary trace into `cs`
+pub fn import_binary_trace(bytes: &[u8], cs: &mut ConstraintSet) -> Result<()> {
+    crate::import::read_binary_trace(bytes, cs, false)
+}
+
+/// Import a JSON, possibly gzipped, trace into `cs`
+pub fn import_json_trace(bytes: &[u8], cs: &mut ConstraintSet) -> Result<()> {
+    crate::import::read_trace_str(bytes, cs, false)
+}
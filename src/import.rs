@@ -1,43 +1,44 @@
 use super::compiler::{ColumnRef, Magma};
 use crate::column::Value as CValue;
 use anyhow::*;
+use base64::Engine;
 use cached::Cached;
-use flate2::bufread::GzDecoder;
 use itertools::Itertools;
 use log::*;
 use logging_timer::time;
 use num_bigint::{BigInt, Sign};
 use owo_colors::OwoColorize;
 use rayon::prelude::*;
-#[cfg(not(all(target_arch = "x86_64", target_feature = "avx")))]
-use serde_json::Value;
-#[cfg(all(target_arch = "x86_64", target_feature = "avx"))]
-use std::io::Read;
-#[cfg(all(target_arch = "x86_64", target_feature = "avx"))]
-use simd_json::BorrowedValue as Value;
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IgnoredAny, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
 use std::{
-    fs::File,
-    io::{BufReader, Seek},
+    cell::RefCell,
+    collections::HashMap,
+    io::{BufRead, Read, Write},
 };
 
 use crate::{
-    column::{Column, Register},
+    column::{Column, Register, RegisterRef},
     compiler::ConstraintSet,
+    compute, input,
     pretty::Pretty,
+    run_stats,
     structs::Handle,
 };
 
 #[derive(Debug)]
-struct RegisterHeader {
-    handle: Handle,
-    bytes_per_element: usize,
-    length: i32,
+pub(crate) struct RegisterHeader {
+    pub handle: Handle,
+    pub bytes_per_element: usize,
+    pub length: i32,
 }
 
-struct TraceMap {
-    headers: Vec<RegisterHeader>,
+pub(crate) struct TraceMap {
+    pub headers: Vec<RegisterHeader>,
 }
 impl TraceMap {
+    #[allow(dead_code)]
     fn size(&self) -> usize {
         4 + self
             .headers
@@ -72,60 +73,43 @@ impl std::fmt::Debug for TraceMap {
     }
 }
 
-struct TraceReader<Data: AsRef<[u8]>> {
-    bytes: Data,
-    cursor: usize,
+/// Read a binary trace sequentially, so that only one register at a time has
+/// to be held in memory
+pub(crate) struct TraceReader<R: Read> {
+    r: R,
 }
-impl<Data: AsRef<[u8]>> TraceReader<Data> {
-    fn from(bytes: Data) -> Self {
-        TraceReader { bytes, cursor: 0 }
+impl<R: Read> TraceReader<R> {
+    pub(crate) fn from(r: R) -> Self {
+        TraceReader { r }
     }
 
     fn i8(&mut self) -> Result<i8> {
-        self.cursor += 1;
-        self.bytes
-            .as_ref()
-            .get(self.cursor - 1)
-            .map(|x| *x as i8)
-            .with_context(|| anyhow!("not enough bytes"))
+        self.slice(1).map(|bs| bs[0] as i8)
     }
 
     fn i16(&mut self) -> Result<i16> {
-        self.cursor += 2;
-        self.bytes
-            .as_ref()
-            .get(self.cursor - 2..self.cursor)
+        self.slice(2)
             .map(|bs| i16::from_be_bytes(bs.try_into().unwrap()))
-            .with_context(|| anyhow!("not enough bytes"))
     }
 
     fn i32(&mut self) -> Result<i32> {
-        self.cursor += 4;
-        self.bytes
-            .as_ref()
-            .get(self.cursor - 4..self.cursor)
+        self.slice(4)
             .map(|bs| i32::from_be_bytes(bs.try_into().unwrap()))
-            .with_context(|| anyhow!("not enough bytes"))
     }
 
     fn string(&mut self, len: usize) -> Result<String> {
-        self.cursor += len;
-        String::from_utf8(
-            self.bytes
-                .as_ref()
-                .get(self.cursor - len..self.cursor)
-                .with_context(|| anyhow!("not enough bytes"))?
-                .to_vec(),
-        )
-        .with_context(|| anyhow!("invalid UTF8"))
+        String::from_utf8(self.slice(len)?).with_context(|| anyhow!("invalid UTF8"))
     }
 
-    fn slice(&mut self, len: usize) -> Result<&[u8]> {
-        self.cursor += len;
-        self.bytes
-            .as_ref()
-            .get(self.cursor - len..self.cursor)
-            .with_context(|| anyhow!("not enough bytes"))
+    pub(crate) fn slice(&mut self, len: usize) -> Result<Vec<u8>> {
+        // the length is not trusted to pre-allocate the buffer, as it comes
+        // from the trace itself
+        let mut r = Vec::new();
+        (&mut self.r).take(len as u64).read_to_end(&mut r)?;
+        if r.len() != len {
+            bail!("not enough bytes")
+        }
+        Ok(r)
     }
 
     fn header(&mut self) -> Result<RegisterHeader> {
@@ -133,24 +117,36 @@ impl<Data: AsRef<[u8]>> TraceReader<Data> {
             .i16()
             .with_context(|| anyhow!("parsing a register name length"))?;
         let handle_str = self
-            .string(handle_length as usize)
+            .string(
+                handle_length
+                    .try_into()
+                    .with_context(|| anyhow!("invalid register name length {}", handle_length))?,
+            )
             .with_context(|| anyhow!("parsing a register name"))?;
-        let mut splitted = handle_str.splitn(2, '.');
-        let bytes_per_element =
-            self.i8()
-                .with_context(|| anyhow!("parsing BPE for {}", handle_str))? as usize;
+        let (module, name) = handle_str
+            .split_once('.')
+            .with_context(|| anyhow!("invalid register name {}", handle_str))?;
+        let bytes_per_element = self
+            .i8()
+            .with_context(|| anyhow!("parsing BPE for {}", handle_str))?;
+        if bytes_per_element <= 0 {
+            bail!("invalid BPE {} for {}", bytes_per_element, handle_str)
+        }
         let length = self
             .i32()
             .with_context(|| anyhow!("parsing length of {}", handle_str))?;
+        if length < 0 {
+            bail!("invalid length {} for {}", length, handle_str)
+        }
 
         Ok(RegisterHeader {
-            handle: Handle::new(splitted.next().unwrap(), splitted.next().unwrap()),
-            bytes_per_element,
+            handle: Handle::new(module, name),
+            bytes_per_element: bytes_per_element as usize,
             length,
         })
     }
 
-    fn map(&mut self) -> Result<TraceMap> {
+    pub(crate) fn map(&mut self) -> Result<TraceMap> {
         let register_count = self.i32().with_context(|| "parsing register count")?;
         Ok(TraceMap {
             headers: (0..register_count)
@@ -160,23 +156,297 @@ impl<Data: AsRef<[u8]>> TraceReader<Data> {
     }
 }
 
+/// The number of rows to import from a trace column of length `len`, w.r.t.
+/// the row limit that may have been set on `cs`
+fn limit_rows(cs: &ConstraintSet, len: usize) -> usize {
+    cs.columns.row_limit.map(|l| l.min(len)).unwrap_or(len)
+}
+
+/// The formats a trace may come in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// JSON, possibly gzipped
+    Json,
+    /// the binary LT format, as read by [`read_binary_trace`]
+    Lt,
+    /// CBOR, laid out like JSON traces
+    Cbor,
+    /// MessagePack, laid out like JSON traces
+    MessagePack,
+}
+impl TraceFormat {
+    /// Detect the format of `tracefile` from its first bytes, once
+    /// decompressed. LT traces start with their big-endian register count,
+    /// that could only look like a JSON opening brace for hundreds of
+    /// millions of registers; and as it is positive, it can not look like
+    /// the header of a CBOR or MessagePack map either.
+    pub fn detect(tracefile: &str) -> Result<TraceFormat> {
+        Ok(Self::detect_bytes(&input::head(tracefile, 64)?))
+    }
+
+    fn detect_bytes(head: &[u8]) -> TraceFormat {
+        match head.first() {
+            // a map, or the self-describing tag
+            Some(0xa0..=0xbb | 0xbf | 0xd9) => return TraceFormat::Cbor,
+            Some(0x80..=0x8f | 0xde | 0xdf) => return TraceFormat::MessagePack,
+            _ => {}
+        }
+        match head.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'{') | None => TraceFormat::Json,
+            Some(_) => TraceFormat::Lt,
+        }
+    }
+}
+
+/// How the values of the columns are written in JSON traces
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValuesEncoding {
+    /// an array of hexadecimal strings
+    #[default]
+    Hex,
+    /// `{"encoding": "base64-fixed32", "data": ...}`, where `data` is the
+    /// base64 encoding of the values, each of them as 32 big-endian bytes
+    Base64Fixed32,
+    /// `{"encoding": "varint-dict", "data": ...}`, where `data` is the base64
+    /// encoding of a dictionary of the large values, then of the values as
+    /// varints; see [`ValuesEncoding::compact`]
+    Compact,
+}
+impl ValuesEncoding {
+    const BASE64_FIXED32: &'static str = "base64-fixed32";
+    const VARINT_DICT: &'static str = "varint-dict";
+    /// The width, in bytes, of a value encoded as [`ValuesEncoding::Base64Fixed32`]
+    pub(crate) const FIXED_WIDTH: usize = 32;
+
+    /// Decode the `data` of a column given as `{"encoding": ..., "data": ...}`
+    /// into the concatenation of its fixed-width values
+    pub(crate) fn decode(encoding: &str, data: &str) -> Result<Vec<u8>> {
+        if encoding != Self::BASE64_FIXED32 && encoding != Self::VARINT_DICT {
+            bail!(
+                "unknown encoding `{}`; expected `{}` or `{}`",
+                encoding.red(),
+                Self::BASE64_FIXED32,
+                Self::VARINT_DICT
+            )
+        }
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .with_context(|| anyhow!("invalid {} data", encoding))?;
+        if encoding == Self::VARINT_DICT {
+            return Self::expand(&bytes).with_context(|| anyhow!("invalid {} data", encoding));
+        }
+        if bytes.len() % Self::FIXED_WIDTH != 0 {
+            bail!(
+                "{} bytes can not be split in values of {} bytes",
+                bytes.len().to_string().red(),
+                Self::FIXED_WIDTH
+            )
+        }
+        Ok(bytes)
+    }
+
+    /// Encode `bytes`, the concatenation of fixed-width values, as the JSON
+    /// object standing for a column
+    pub(crate) fn encode(&self, bytes: &[u8]) -> String {
+        let (name, data) = match self {
+            ValuesEncoding::Hex => unreachable!("hexadecimal values are written as an array"),
+            ValuesEncoding::Base64Fixed32 => (Self::BASE64_FIXED32, bytes.to_vec()),
+            ValuesEncoding::Compact => (Self::VARINT_DICT, Self::compact(bytes)),
+        };
+        format!(
+            "{{\"encoding\":\"{}\",\"data\":\"{}\"}}",
+            name,
+            base64::engine::general_purpose::STANDARD.encode(data)
+        )
+    }
+
+    /// Compact `bytes`, the concatenation of fixed-width values, as:
+    ///   - the number of entries of the dictionary, then each of them as its
+    ///     length followed by its big-endian bytes;
+    ///   - then a varint per value: `2n` stands for the value `n`, and `2n + 1`
+    ///     for the `n`th entry of the dictionary.
+    ///
+    /// The values too large to be written as varints are stored in the
+    /// dictionary, once however often they are repeated.
+    fn compact(bytes: &[u8]) -> Vec<u8> {
+        let mut dictionary = Vec::new();
+        let mut indices = HashMap::<&[u8], u64>::new();
+        let mut values = Vec::with_capacity(bytes.len() / Self::FIXED_WIDTH);
+        for x in bytes.chunks(Self::FIXED_WIDTH) {
+            let start = x.iter().position(|b| *b != 0).unwrap_or(x.len());
+            let x = &x[start..];
+            if x.len() <= 8 {
+                let n = x.iter().fold(0u64, |ax, b| (ax << 8) | *b as u64);
+                if n < 1 << 63 {
+                    write_varint(&mut values, n << 1);
+                    continue;
+                }
+            }
+            let i = *indices.entry(x).or_insert_with(|| {
+                dictionary.push(x);
+                dictionary.len() as u64 - 1
+            });
+            write_varint(&mut values, (i << 1) | 1);
+        }
+
+        let mut r = Vec::with_capacity(values.len());
+        write_varint(&mut r, dictionary.len() as u64);
+        for x in dictionary {
+            write_varint(&mut r, x.len() as u64);
+            r.extend_from_slice(x);
+        }
+        r.extend(values);
+        r
+    }
+
+    /// Expand the output of [`ValuesEncoding::compact`] back into
+    /// fixed-width values
+    fn expand(bytes: &[u8]) -> Result<Vec<u8>> {
+        let mut i = 0;
+        let mut dictionary = Vec::new();
+        for _ in 0..read_varint(bytes, &mut i)? {
+            let len = read_varint(bytes, &mut i)? as usize;
+            if len > Self::FIXED_WIDTH {
+                bail!(
+                    "dictionary entry of {} bytes exceeds {} bytes",
+                    len.to_string().red(),
+                    Self::FIXED_WIDTH
+                )
+            }
+            let x = bytes
+                .get(i..i + len)
+                .ok_or_else(|| anyhow!("truncated dictionary"))?;
+            dictionary.push(x);
+            i += len;
+        }
+
+        let mut r = Vec::new();
+        while i < bytes.len() {
+            let n = read_varint(bytes, &mut i)?;
+            let start = r.len();
+            r.resize(start + Self::FIXED_WIDTH, 0);
+            if n & 1 == 0 {
+                r[start + Self::FIXED_WIDTH - 8..].copy_from_slice(&(n >> 1).to_be_bytes());
+            } else {
+                let x = dictionary.get((n >> 1) as usize).ok_or_else(|| {
+                    anyhow!(
+                        "entry {} not found in a dictionary of {}",
+                        (n >> 1).to_string().red(),
+                        dictionary.len()
+                    )
+                })?;
+                r[start + Self::FIXED_WIDTH - x.len()..].copy_from_slice(x);
+            }
+        }
+        Ok(r)
+    }
+}
+
+/// Write `x` to `out` as an LEB128 varint
+fn write_varint(out: &mut Vec<u8>, mut x: u64) {
+    while x >= 0x80 {
+        out.push((x as u8) | 0x80);
+        x >>= 7;
+    }
+    out.push(x as u8);
+}
+
+/// Read the LEB128 varint starting at `bytes[*i]`, moving `i` past it
+fn read_varint(bytes: &[u8], i: &mut usize) -> Result<u64> {
+    let mut r = 0u64;
+    for shift in (0..64).step_by(7) {
+        let b = *bytes.get(*i).ok_or_else(|| anyhow!("truncated varint"))?;
+        *i += 1;
+        r |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Ok(r);
+        }
+    }
+    bail!("varint overflowing 64 bits")
+}
+
+/// The concatenated fixed-width values of a column written in an expanded
+/// trace as `{"encoding": ..., "data": ...}`
+pub(crate) fn encoded_values(values: &serde_json::Value) -> Result<Vec<u8>> {
+    match (
+        values.get("encoding").and_then(serde_json::Value::as_str),
+        values.get("data").and_then(serde_json::Value::as_str),
+    ) {
+        (Some(encoding), Some(data)) => ValuesEncoding::decode(encoding, data),
+        _ => bail!("expected an array of values or an encoded column"),
+    }
+}
+
+/// Import `tracefile` into `cs`, whatever its format
+/// A reader over the content of `tracefile`, hashed as it is read if the
+/// invocation statistics are collected
+fn trace_reader(tracefile: &str) -> Result<Box<dyn BufRead>> {
+    Ok(run_stats::record_input_reader(
+        tracefile,
+        input::reader(tracefile)?,
+    ))
+}
+
+pub fn parse_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
+    match TraceFormat::detect(tracefile)? {
+        TraceFormat::Json => parse_json_trace(tracefile, cs, keep_raw),
+        TraceFormat::Lt => parse_binary_trace(tracefile, cs, keep_raw),
+        format @ (TraceFormat::Cbor | TraceFormat::MessagePack) => {
+            parse_packed_trace(tracefile, format, cs, keep_raw)
+        }
+    }
+}
+
+#[time("info", "Parsing packed trace")]
+pub fn parse_packed_trace(
+    tracefile: &str,
+    format: TraceFormat,
+    cs: &mut ConstraintSet,
+    keep_raw: bool,
+) -> Result<()> {
+    let reader = trace_reader(tracefile)?;
+    match format {
+        TraceFormat::Cbor => stream_cbor_trace(reader, cs, keep_raw),
+        TraceFormat::MessagePack => stream_msgpack_trace(reader, cs, keep_raw),
+        _ => unreachable!(),
+    }
+    .with_context(|| format!("while reading `{}`", tracefile))
+}
+
 #[time("info", "Parsing binary traces")]
 pub fn parse_binary_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
-    let file = File::open(tracefile)
-        .with_context(|| anyhow!("opening {}", tracefile.bright_white().bold()))?;
-    let mut trace_reader = TraceReader::from(unsafe {
-        memmap2::MmapOptions::new()
-            .map(&file)
-            .with_context(|| anyhow!("memory mapping {}", tracefile.bright_white().bold()))?
-    });
+    read_binary_trace(trace_reader(tracefile)?, cs, keep_raw)
+}
+
+/// Import into `cs` the registers contained in the binary trace read from
+/// `r`
+pub fn read_binary_trace<R: Read>(r: R, cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
+    let mut trace_reader = TraceReader::from(r);
     let trace_map = trace_reader.map()?;
+    // The header gives the length of every register, so that the memory
+    // they will use can be checked before any of them is read
+    let mut estimated = HashMap::new();
+    for h in trace_map.headers.iter() {
+        let register_ref = RegisterRef::from(h.handle.clone());
+        if cs.columns.register(&register_ref).is_some() {
+            *estimated.entry(h.handle.module.clone()).or_default() +=
+                (limit_rows(cs, h.length as usize) + 1) * std::mem::size_of::<CValue>();
+        }
+    }
+    compute::ensure_memory_estimate(cs, estimated, "importing the trace")?;
     for trace_register in trace_map.headers.into_iter() {
-        let column_ref: ColumnRef = trace_register.handle.clone().into();
-        let register_bytes = trace_reader
-            .slice(trace_register.length as usize * trace_register.bytes_per_element)?;
+        // binary traces contain registers, that are not necessarily named
+        // after any of the columns they back
+        let register_ref = RegisterRef::from(trace_register.handle.clone());
+        let register_bytes = trace_reader.slice(
+            (trace_register.length as usize)
+                .checked_mul(trace_register.bytes_per_element)
+                .with_context(|| anyhow!("{} is too large", trace_register.handle.pretty()))?,
+        )?;
 
-        if let Some(Register { magma, .. }) = cs.columns.register(&column_ref) {
-            let mut xs = (if keep_raw { 0 } else { -1 }..trace_register.length)
+        if let Some(Register { magma, .. }) = cs.columns.register(&register_ref) {
+            let length = limit_rows(cs, trace_register.length as usize) as i32;
+            let mut xs = (if keep_raw { 0 } else { -1 }..length)
                 .into_par_iter()
                 .map(|i| {
                     if i == -1 {
@@ -206,27 +476,26 @@ pub fn parse_binary_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: boo
                     )
                 })?;
 
-            let module_min_len = cs
-                .columns
-                .min_len
-                .get(&trace_register.handle.module)
-                .cloned()
-                .unwrap_or(0);
-
             debug!("Importing {}", trace_register.handle.pretty());
-            let module_spilling = cs.spilling_for_column(&column_ref).ok_or_else(|| {
-                anyhow!("no spilling found for {}", trace_register.handle.pretty())
-            })?;
+            let module_spilling =
+                cs.spilling_of(&trace_register.handle.module)
+                    .ok_or_else(|| {
+                        anyhow!("no spilling found for {}", trace_register.handle.pretty())
+                    })?;
 
             // If the parsed column is not long enought w.r.t. the
-            // minimal module length, prepend it with as many zeroes as
-            // required.
+            // minimal module length or its padding strategy, prepend it with
+            // as many zeroes as required.
             // Atomic columns are always padded with zeroes, so there is
             // no need to trigger a more complex padding system.
-            if !keep_raw && xs.len() < module_min_len {
-                xs.reverse();
-                xs.resize(module_min_len, CValue::zero()); // TODO: register padding values
-                xs.reverse();
+            if !keep_raw {
+                let module_min_len =
+                    compute::padded_len(cs, &trace_register.handle.module, xs.len())?;
+                if xs.len() < module_min_len {
+                    xs.reverse();
+                    xs.resize(module_min_len, CValue::zero()); // TODO: register padding values
+                    xs.reverse();
+                }
             }
 
             let module_raw_size =
@@ -241,7 +510,8 @@ pub fn parse_binary_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: boo
             }
 
             cs.columns
-                .set_register_value(&trace_register.handle.into(), xs, module_spilling)?
+                .set_register_value(&register_ref, xs, module_spilling)?;
+            cs.columns.set_trace_len(&register_ref, length as usize)?;
         } else {
             info!("unknown column {}", trace_register.handle.pretty());
         }
@@ -250,134 +520,225 @@ pub fn parse_binary_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: boo
     Ok(())
 }
 
-#[time("info", "Parsing trace from JSON file with SIMD")]
-pub fn parse_json_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
-    let mut f = File::open(tracefile).with_context(|| format!("while opening `{}`", tracefile))?;
+/// Write the filled registers of `cs`, save those of the `exclude`d modules,
+/// as a binary trace readable by [`read_binary_trace`]. If `padded`, the
+/// registers are written along with their spilling, as they would be
+/// handed to a prover; otherwise, only their rows proper are written.
+pub fn write_binary_trace(
+    cs: &ConstraintSet,
+    exclude: &[String],
+    padded: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let registers = cs
+        .columns
+        .registers
+        .iter()
+        .filter(|r| !r.is_empty())
+        .filter_map(|r| r.handle.as_ref().map(|h| (h, r)))
+        .filter(|(h, _)| !exclude.contains(&h.module))
+        .collect::<Vec<_>>();
 
-    #[cfg(all(target_arch = "x86_64", target_feature = "avx"))]
-    {
-        let mut content = Vec::new();
-        let mut gz = GzDecoder::new(BufReader::new(&f));
-        match gz.header() {
-            Some(_) => gz.read_to_end(&mut content),
-            None => {
-                f.rewind()?;
-                BufReader::new(&f).read_to_end(&mut content)
-            }
-        }
-        .with_context(|| format!("while reading `{}`", tracefile))?;
-        let v = simd_json::to_borrowed_value(&mut content)
-            .map_err(|e| anyhow!("while parsing json: {}", e))?;
-        fill_traces_from_json(&v, vec![], cs, &mut None, keep_raw).with_context(|| "while reading columns")
+    let count = i32::try_from(registers.len()).with_context(|| anyhow!("too many registers"))?;
+    out.write_all(&count.to_be_bytes())?;
+    let mut rows = Vec::with_capacity(registers.len());
+    for (handle, register) in registers.iter() {
+        let name = format!("{}.{}", handle.module, handle.name);
+        let bytes_per_element = register.magma.byte_size().max(1);
+        let spilling = if padded {
+            cs.spilling_of(&handle.module).unwrap_or(0)
+        } else {
+            0
+        };
+        let len = register.len().unwrap_or(0) as isize;
+        out.write_all(
+            &i16::try_from(name.len())
+                .with_context(|| anyhow!("register name {} is too long", name))?
+                .to_be_bytes(),
+        )?;
+        out.write_all(name.as_bytes())?;
+        out.write_all(
+            &i8::try_from(bytes_per_element)
+                .with_context(|| anyhow!("{} is too wide", handle.pretty()))?
+                .to_be_bytes(),
+        )?;
+        out.write_all(
+            &i32::try_from(len + spilling)
+                .with_context(|| anyhow!("{} is too long", handle.pretty()))?
+                .to_be_bytes(),
+        )?;
+        rows.push((bytes_per_element, -spilling..len));
     }
-    #[cfg(not(all(target_arch = "x86_64", target_feature = "avx")))]
-    {
-        let gz = GzDecoder::new(BufReader::new(&f));
-        let v: Value = match gz.header() {
-            Some(_) => serde_json::from_reader(gz),
-            None => {
-                f.rewind()?;
-                serde_json::from_reader(BufReader::new(&f))
+
+    for ((handle, register), (bytes_per_element, rows)) in registers.iter().zip(rows) {
+        debug!("Exporting {}", handle.pretty());
+        let mut buffer = vec![0u8; bytes_per_element];
+        for i in rows {
+            let x = register.get(i, false, &cs.columns).unwrap_or_default();
+            let (_, bytes) = BigInt::from(x).to_bytes_be();
+            if bytes.len() > bytes_per_element {
+                bail!(
+                    "{}th element of {} does not fit in {} bytes",
+                    i,
+                    handle.pretty(),
+                    bytes_per_element
+                );
             }
+            buffer.fill(0);
+            buffer[bytes_per_element - bytes.len()..].copy_from_slice(&bytes);
+            out.write_all(&buffer)?;
         }
-        .with_context(|| format!("while reading `{}`", tracefile))?;
-        fill_traces_from_json(&v, vec![], cs, &mut None, keep_raw)
-            .with_context(|| "while reading columns")
     }
+
+    Ok(())
 }
 
-#[time("info", "Parsing trace from JSON with SIMD")]
-pub fn read_trace_str(tracestr: &[u8], cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
-    #[cfg(all(target_arch = "x86_64", target_feature = "avx"))]
+#[time("info", "Parsing trace from JSON file")]
+pub fn parse_json_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
+    stream_json_trace(trace_reader(tracefile)?, cs, keep_raw)
+        .with_context(|| format!("while reading `{}`", tracefile))
+}
+
+/// Whether `tracefile` has been expanded by `compute`, i.e. whether all its
+/// columns, including the computed ones, are already filled
+pub fn is_expanded_trace(tracefile: &str) -> Result<bool> {
+    const EXPANDED_HEADER: &[u8] = b"{\"columns\":{";
+
+    Ok(input::head(tracefile, EXPANDED_HEADER.len())? == EXPANDED_HEADER)
+}
+
+/// Fill `cs` with the columns of a trace expanded by `compute`; as they are
+/// all already filled, no computation is run. `cs` must have been expanded
+/// like the constraint set that produced the trace.
+#[time("info", "Loading expanded trace")]
+pub fn parse_expanded_trace(tracefile: &str, cs: &mut ConstraintSet) -> Result<()> {
+    let trace = crate::checksum::read_json(tracefile)?;
+
+    // the columns are written under the display form of their handle
+    let handles = cs
+        .columns
+        .all()
+        .into_iter()
+        .map(|h| (cs.columns.column(&h).unwrap().handle.to_string(), h))
+        .collect::<HashMap<_, _>>();
+    let mut columns = Vec::new();
+    for (name, column) in trace
+        .get("columns")
+        .and_then(|c| c.as_object())
+        .with_context(|| anyhow!("`{}` does not contain any column", tracefile))?
+        .iter()
     {
-        let mut content = Vec::new();
-        let mut gz = GzDecoder::new(BufReader::new(tracestr));
-        match gz.header() {
-            Some(_) => {
-                gz.read_to_end(&mut content)?;
-            }
-            None => {
-                content = tracestr.to_vec();
-            }
+        let Some(h) = handles.get(name) else {
+            debug!("ignoring unknown column {}", name);
+            continue;
+        };
+        if column
+            .get("skipped")
+            .and_then(|s| s.as_bool())
+            .unwrap_or(false)
+        {
+            cs.columns.mark_skipped(h)?;
+            continue;
+        }
+        let values = column
+            .get("values")
+            .with_context(|| anyhow!("no values found for {}", name.blue()))?;
+        let xs = if let Some(xs) = values.as_array() {
+            xs.iter()
+                .map(|x| {
+                    x.as_str()
+                        .and_then(|x| x.strip_prefix("0x"))
+                        .and_then(|hex| BigInt::parse_bytes(hex.as_bytes(), 16))
+                        .with_context(|| anyhow!("invalid value in {}: `{}`", name.blue(), x))
+                        .and_then(|x| CValue::try_from(x).map_err(Error::msg))
+                })
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            encoded_values(values)
+                .with_context(|| anyhow!("reading {}", name.blue()))?
+                .chunks(ValuesEncoding::FIXED_WIDTH)
+                .map(|bs| {
+                    CValue::try_from(BigInt::from_bytes_be(Sign::Plus, bs)).map_err(Error::msg)
+                })
+                .collect::<Result<Vec<_>>>()?
         };
-        let v = simd_json::to_borrowed_value(&mut content)
-            .map_err(|e| anyhow!("while parsing json: {}", e))?;
-        fill_traces_from_json(&v, vec![], cs, &mut None, keep_raw).with_context(|| "while reading columns")
+        if !xs.is_empty() {
+            columns.push((h.clone(), cs.length_multiplier(h), xs));
+        }
     }
-    #[cfg(not(all(target_arch = "x86_64", target_feature = "avx")))]
-    {
-        let gz = GzDecoder::new(BufReader::new(tracestr));
-        let v: Value = match gz.header() {
-            Some(_) => serde_json::from_reader(gz),
-            None => serde_json::from_reader(BufReader::new(tracestr)),
-        }?;
-        fill_traces_from_json(&v, vec![], cs, &mut None, keep_raw)
-            .with_context(|| "while reading columns")
+
+    // the columns spanning a single row per row of their module set its
+    // length, from which the spilling of the other ones is derived
+    columns.sort_by_key(|(_, size_factor, _)| *size_factor);
+    for (h, size_factor, xs) in columns {
+        let module = cs.columns.column(&h).unwrap().handle.module.clone();
+        let module_spilling = cs
+            .spilling_of(&module)
+            .ok_or_else(|| anyhow!("no spilling found for {}", h.pretty()))?;
+        let raw_length = cs.effective_len_or_set(
+            &module,
+            xs.len() as isize / size_factor as isize - module_spilling,
+        );
+        let spilling = xs.len() as isize - raw_length * size_factor as isize;
+        if spilling < 0 {
+            bail!(
+                "{} is shorter than its module {}",
+                h.pretty(),
+                module.bold()
+            )
+        }
+
+        if cs.columns.backing(&h).is_some() {
+            // the register has already been filled through another column
+            cs.columns.mark_computed(&h)?;
+        } else {
+            let trace_len = xs.len() - spilling as usize;
+            cs.columns.set_raw_value(&h, xs, spilling)?;
+            cs.columns
+                .set_trace_len(&cs.columns.register_ref_of(&h)?, trace_len)?;
+        }
     }
+
+    Ok(())
 }
 
-#[cfg(not(all(target_arch = "x86_64", target_feature = "avx")))]
-fn parse_column(xs: &[Value], h: &Handle, t: Magma, keep_raw: bool) -> Result<Vec<CValue>> {
-    let mut cache_num = cached::SizedCache::with_size(200000); // ~1.60MB cache
-    let mut cache_str = cached::SizedCache::with_size(200000); // ~1.60MB cache
-    let mut r = if keep_raw {
-        Vec::new()
-    } else {
-        vec![CValue::zero()]
-    };
-    let xs = xs
-        .iter()
-        .map(|x| match x {
-            Value::Number(n) => t.rm().validate(
-                cache_num
-                    .cache_get_or_set_with(n, || CValue::from(n.as_str()))
-                    .to_owned(),
-            ),
-            Value::String(s) => t.rm().validate(
-                cache_str
-                    .cache_get_or_set_with(s.clone(), || CValue::from(s.as_str()))
-                    .to_owned(),
-            ),
-            _ => bail!("expected numeric value, found `{}`", x),
+#[time("info", "Parsing trace from JSON")]
+pub fn read_trace_str(tracestr: &[u8], cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
+    stream_json_trace(
+        std::io::BufReader::new(input::decompress(tracestr)?),
+        cs,
+        keep_raw,
+    )
+}
+
+/// Parse the values of a column given as `{"encoding": ..., "data": ...}`
+fn decode_column(
+    encoding: &str,
+    data: &str,
+    cs: &ConstraintSet,
+    h: &Handle,
+    t: Magma,
+    keep_raw: bool,
+) -> Result<Vec<CValue>> {
+    let bytes = ValuesEncoding::decode(encoding, data)?;
+    let len = limit_rows(cs, bytes.len() / ValuesEncoding::FIXED_WIDTH);
+    let xs = bytes
+        .par_chunks(ValuesEncoding::FIXED_WIDTH)
+        .take(len)
+        .enumerate()
+        .map(|(i, bs)| {
+            CValue::try_from(BigInt::from_bytes_be(Sign::Plus, bs))
+                .map_err(Error::msg)
+                .and_then(|x| t.rm().validate(x))
+                .with_context(|| anyhow!("reading {}th element", i))
         })
         .collect::<Result<Vec<_>>>()?;
 
-    if let Err(msg) = crate::utils::maybe_warn(t, &r, h) {
-        error!("{}", msg);
-    };
-    r.extend(xs);
-    Ok(r)
-}
-
-#[cfg(all(target_arch = "x86_64", target_feature = "avx"))]
-fn parse_column(xs: &[Value], h: &Handle, t: Magma, keep_raw: bool) -> Result<Vec<CValue>> {
-    let mut cache = cached::SizedCache::with_size(200000); // ~1.60MB cache
     let mut r = if keep_raw {
         Vec::new()
     } else {
         vec![CValue::zero()]
     };
-    let xs = xs
-        .iter()
-        .map(|x| {
-            let s = match x {
-                Value::Static(n) => match n {
-                    simd_json::StaticNode::I64(i) => i.to_string(),
-                    simd_json::StaticNode::U64(i) => i.to_string(),
-                    _ => {
-                        unreachable!()
-                    }
-                },
-                Value::String(s) => s.to_string(),
-                _ => bail!("expected numeric value, found `{}`", x),
-            };
-            t.rm().validate(
-                cache
-                    .cache_get_or_set_with(s.clone(), || CValue::from(s.as_str()))
-                    .to_owned(),
-            )
-        })
-        .collect::<Result<Vec<_>>>()?;
     r.extend(xs);
     if let Err(msg) = crate::utils::maybe_warn(t, &r, h) {
         error!("{}", msg);
@@ -385,119 +746,612 @@ fn parse_column(xs: &[Value], h: &Handle, t: Magma, keep_raw: bool) -> Result<Ve
     Ok(r)
 }
 
-pub fn fill_traces_from_json(
-    v: &Value,
+/// Import the JSON trace read from `reader` without materializing it: its
+/// columns are parsed as they are read, so that the memory required is the
+/// one of the imported columns.
+pub(crate) fn stream_json_trace<R: Read>(
+    reader: R,
+    cs: &mut ConstraintSet,
+    keep_raw: bool,
+) -> Result<()> {
+    let mut stream = TraceStream {
+        cs,
+        keep_raw,
+        error: None,
+    };
+    let mut de = serde_json::Deserializer::from_reader(reader);
+    let r = stream.read(&mut de).and_then(|_| de.end());
+    conclude(r, stream.error, "json")
+}
+
+/// Import the MessagePack trace read from `reader`, streamed like the JSON
+/// ones
+fn stream_msgpack_trace<R: Read>(
+    mut reader: R,
+    cs: &mut ConstraintSet,
+    keep_raw: bool,
+) -> Result<()> {
+    let mut stream = TraceStream {
+        cs,
+        keep_raw,
+        error: None,
+    };
+    let r = stream.read(&mut rmp_serde::Deserializer::new(&mut reader));
+    conclude(r, stream.error, "MessagePack")?;
+    crate::packed::ensure_exhausted(reader)
+}
+
+/// Import the CBOR trace read from `reader`, streamed like the JSON ones
+fn stream_cbor_trace<R: Read>(mut reader: R, cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
+    // ciborium only deserializes owned values, so the constraint set is lent
+    // to the root of the trace for the duration of the import
+    LENT.with(|lent| {
+        lent.replace(Some(LentStream {
+            cs: std::mem::take(cs),
+            keep_raw,
+            error: None,
+        }))
+    });
+    let r = ciborium::de::from_reader::<LentRoot, _>(&mut reader).map(|_| ());
+    let lent = LENT
+        .with(|lent| lent.take())
+        .expect("the lent constraint set has not been returned");
+    *cs = lent.cs;
+    conclude(r, lent.error, "CBOR")?;
+    crate::packed::ensure_exhausted(reader)
+}
+
+/// The outcome of the import of a trace in `format`; if it has been
+/// interrupted by a column, the deserializer error only carries its message.
+fn conclude<E: std::error::Error + Send + Sync + 'static>(
+    r: Result<(), E>,
+    error: Option<Error>,
+    format: &str,
+) -> Result<()> {
+    match (r, error) {
+        (Result::Ok(_), _) => Ok(()),
+        (Err(_), Some(err)) => Err(err).context("while reading columns"),
+        (Err(err), None) => Err(err).with_context(|| format!("while parsing {}", format)),
+    }
+}
+
+/// A constraint set lent to [`LentRoot`]
+struct LentStream {
+    cs: ConstraintSet,
+    keep_raw: bool,
+    error: Option<Error>,
+}
+
+thread_local! {
+    static LENT: RefCell<Option<LentStream>> = const { RefCell::new(None) };
+}
+
+/// The root of a trace, imported into the constraint set lent in [`LENT`]
+struct LentRoot;
+impl<'de> serde::Deserialize<'de> for LentRoot {
+    fn deserialize<D: de::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        // the stream is taken out of the slot, so that an import running on
+        // the same thread while this one waits for rayon may lend its own
+        let mut lent = LENT
+            .with(|lent| lent.take())
+            .ok_or_else(|| de::Error::custom("no constraint set to fill"))?;
+        let mut stream = TraceStream {
+            cs: &mut lent.cs,
+            keep_raw: lent.keep_raw,
+            error: None,
+        };
+        let r = stream.read(d);
+        lent.error = stream.error;
+        LENT.with(|slot| slot.replace(Some(lent)));
+        r.map(|_| LentRoot)
+    }
+}
+
+/// Abort the deserialization because of `err`, that is kept in `slot`, as
+/// serde errors can only carry a message
+fn abort<E: de::Error>(slot: &mut Option<Error>, err: Error) -> E {
+    let r = E::custom(format!("{:#}", err));
+    *slot = Some(err);
+    r
+}
+
+/// The state shared by the visitors streaming a trace
+struct TraceStream<'c> {
+    cs: &'c mut ConstraintSet,
+    keep_raw: bool,
+    /// the error that interrupted the import, if any
+    error: Option<Error>,
+}
+impl TraceStream<'_> {
+    fn read<'de, D: de::Deserializer<'de>>(&mut self, d: D) -> Result<(), D::Error> {
+        NodeSeed {
+            stream: self,
+            path: Vec::new(),
+            initiator: None,
+        }
+        .deserialize(d)
+        .map(|_| ())
+    }
+}
+
+/// The node of a trace found at `path`, be it a module, a column, or
+/// anything else to skip; strings are returned, as they may be the fields of
+/// an encoded column.
+struct NodeSeed<'s, 'c> {
+    stream: &'s mut TraceStream<'c>,
     path: Vec<String>,
+    initiator: Option<&'s mut String>,
+}
+impl<'de> DeserializeSeed<'de> for NodeSeed<'_, '_> {
+    type Value = Option<String>;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+        d.deserialize_any(self)
+    }
+}
+impl<'de> Visitor<'de> for NodeSeed<'_, '_> {
+    type Value = Option<String>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a trace")
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<Self::Value, E> {
+        Result::Ok(None)
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> Result<Self::Value, E> {
+        Result::Ok(None)
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> Result<Self::Value, E> {
+        Result::Ok(None)
+    }
+
+    fn visit_i128<E: de::Error>(self, _: i128) -> Result<Self::Value, E> {
+        Result::Ok(None)
+    }
+
+    fn visit_u128<E: de::Error>(self, _: u128) -> Result<Self::Value, E> {
+        Result::Ok(None)
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<Self::Value, E> {
+        Result::Ok(None)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Result::Ok(None)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Result::Ok(None)
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        Result::Ok(Some(s.to_owned()))
+    }
+
+    fn visit_bytes<E: de::Error>(self, _: &[u8]) -> Result<Self::Value, E> {
+        Result::Ok(None)
+    }
+
+    // MessagePack extensions
+    fn visit_newtype_struct<D: de::Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+        d.deserialize_ignored_any(IgnoredAny)?;
+        Result::Ok(None)
+    }
+
+    // CBOR tags, e.g. the self-describing one, are transparent
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let (IgnoredAny, node) = data.variant::<IgnoredAny>()?;
+        node.newtype_variant_seed(self)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<Self::Value, A::Error> {
+        let (mut encoding, mut data) = (None, None);
+        while let Some(k) = map.next_key::<String>()? {
+            if k == "Trace" {
+                if let Some(module) = self.path.last() {
+                    debug!("Importing {}", module);
+                }
+                let mut first_column = String::new();
+                map.next_value_seed(NodeSeed {
+                    stream: &mut *self.stream,
+                    path: self.path.clone(),
+                    initiator: Some(&mut first_column),
+                })?;
+            } else {
+                let mut path = self.path.clone();
+                path.push(k.clone());
+                let value = map.next_value_seed(NodeSeed {
+                    stream: &mut *self.stream,
+                    path,
+                    initiator: self.initiator.as_deref_mut(),
+                })?;
+                match k.as_str() {
+                    "encoding" => encoding = value,
+                    "data" => data = value,
+                    _ => {}
+                }
+            }
+        }
+
+        if let (false, Some(encoding), Some(data)) = (self.path.is_empty(), encoding, data) {
+            let keep_raw = self.stream.keep_raw;
+            let parse = |cs: &ConstraintSet, h: &ColumnRef, t: Magma| {
+                decode_column(&encoding, &data, cs, h.as_handle(), t, keep_raw)
+            };
+            if let Err(err) = fill_column(
+                &parse,
+                &self.path,
+                self.stream.cs,
+                &mut self.initiator,
+                keep_raw,
+            ) {
+                return Err(abort(&mut self.stream.error, err));
+            }
+        }
+        Result::Ok(None)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let column = (!self.path.is_empty())
+            .then(|| column_at(&self.path))
+            .and_then(|handle| {
+                let magma = column_magma(self.stream.cs, &handle);
+                if magma.is_none() {
+                    debug!("ignoring unknown column {}", handle.pretty());
+                }
+                Some(handle).zip(magma)
+            });
+        let Some((handle, t)) = column else {
+            while seq.next_element::<IgnoredAny>()?.is_some() {}
+            return Result::Ok(None);
+        };
+
+        let mut column = ColumnStream {
+            t,
+            limit: limit_rows(self.stream.cs, usize::MAX),
+            xs: Vec::new(),
+            read: 0,
+            segmented: None,
+            cache: cached::SizedCache::with_size(200000), // ~1.60MB cache
+            error: None,
+        };
+        let mut read = || -> Result<(), A::Error> {
+            while seq
+                .next_element_seed(ValueSeed {
+                    column: &mut column,
+                    in_segment: false,
+                })?
+                .is_some()
+            {}
+            Result::Ok(())
+        };
+        if let Err(err) = read() {
+            let err = column
+                .error
+                .take()
+                .unwrap_or_else(|| anyhow!("{}", err))
+                .context(anyhow!("importing {}", handle.pretty()));
+            return Err(abort(&mut self.stream.error, err));
+        }
+
+        let keep_raw = self.stream.keep_raw;
+        let xs = std::cell::Cell::new(column.xs);
+        let parse = |_: &ConstraintSet, h: &ColumnRef, t: Magma| {
+            let mut r = if keep_raw {
+                Vec::new()
+            } else {
+                vec![CValue::zero()]
+            };
+            r.extend(xs.take());
+            if let Err(msg) = crate::utils::maybe_warn(t, &r, h.as_handle()) {
+                error!("{}", msg);
+            };
+            Result::Ok(r)
+        };
+        if let Err(err) = fill_column(
+            &parse,
+            &self.path,
+            self.stream.cs,
+            &mut self.initiator,
+            keep_raw,
+        ) {
+            return Err(abort(&mut self.stream.error, err));
+        }
+        Result::Ok(None)
+    }
+}
+
+/// The values of a column being streamed
+struct ColumnStream {
+    t: Magma,
+    /// how many values to keep, w.r.t. the row limit
+    limit: usize,
+    xs: Vec<CValue>,
+    /// how many values have been read, including the ones beyond the limit
+    read: usize,
+    /// whether the column is split in segments, once known
+    segmented: Option<bool>,
+    cache: cached::SizedCache<String, CValue>,
+    error: Option<Error>,
+}
+impl ColumnStream {
+    fn push<E: de::Error>(&mut self, s: &str) -> Result<(), E> {
+        let i = self.read;
+        self.read += 1;
+        if self.xs.len() >= self.limit {
+            return Result::Ok(());
+        }
+        let x = self
+            .cache
+            .cache_get_or_set_with(s.to_owned(), || CValue::from(s))
+            .to_owned();
+        match self.t.rm().validate(x) {
+            Result::Ok(x) => {
+                self.xs.push(x);
+                Result::Ok(())
+            }
+            Err(err) => Err(abort(
+                &mut self.error,
+                err.context(anyhow!("reading {}th element", i)),
+            )),
+        }
+    }
+
+    /// Ensure that the column does not mix segments and values
+    fn shape<E: de::Error>(&mut self, segment: bool) -> Result<(), E> {
+        match self.segmented.replace(segment) {
+            Some(segmented) if segmented != segment => Err(abort(
+                &mut self.error,
+                anyhow!("mixes segments and values at element {}", self.read),
+            )),
+            _ => Result::Ok(()),
+        }
+    }
+}
+
+/// An element of a column being streamed: a value, or a segment of values, as
+/// producers streaming a column may split it in several segments, that are
+/// then concatenated in order
+struct ValueSeed<'s> {
+    column: &'s mut ColumnStream,
+    in_segment: bool,
+}
+impl ValueSeed<'_> {
+    fn value<E: de::Error>(self, s: &str) -> Result<(), E> {
+        if !self.in_segment {
+            self.column.shape(false)?;
+        }
+        self.column.push(s)
+    }
+}
+impl<'de> DeserializeSeed<'de> for ValueSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+        d.deserialize_any(self)
+    }
+}
+impl<'de> Visitor<'de> for ValueSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a numeric value")
+    }
+
+    fn visit_i64<E: de::Error>(self, x: i64) -> Result<Self::Value, E> {
+        self.value(&x.to_string())
+    }
+
+    fn visit_u64<E: de::Error>(self, x: u64) -> Result<Self::Value, E> {
+        self.value(&x.to_string())
+    }
+
+    fn visit_i128<E: de::Error>(self, x: i128) -> Result<Self::Value, E> {
+        self.value(&x.to_string())
+    }
+
+    fn visit_u128<E: de::Error>(self, x: u128) -> Result<Self::Value, E> {
+        self.value(&x.to_string())
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        self.value(s)
+    }
+
+    // big-endian unsigned integers
+    fn visit_bytes<E: de::Error>(self, bs: &[u8]) -> Result<Self::Value, E> {
+        self.value(&BigInt::from_bytes_be(Sign::Plus, bs).to_string())
+    }
+
+    // CBOR tags, bignums included, wrap the value they qualify
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let (tag, value) = data.variant::<u64>()?;
+        if tag == crate::packed::CBOR_NEGATIVE_BIGNUM {
+            return Err(abort(
+                &mut self.column.error,
+                anyhow!("negative bignum at element {}", self.column.read),
+            ));
+        }
+        value.newtype_variant_seed(self)
+    }
+
+    // numbers are given as maps, so that they keep their arbitrary precision
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        let n: serde_json::Number =
+            serde::Deserialize::deserialize(de::value::MapAccessDeserializer::new(map))?;
+        self.value(n.as_str())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        if self.in_segment {
+            return Err(abort(
+                &mut self.column.error,
+                anyhow!("segments can not be nested"),
+            ));
+        }
+        self.column.shape(true)?;
+        while seq
+            .next_element_seed(ValueSeed {
+                column: &mut *self.column,
+                in_segment: true,
+            })?
+            .is_some()
+        {}
+        Result::Ok(())
+    }
+}
+
+/// The column found at `path` in a trace
+fn column_at(path: &[String]) -> ColumnRef {
+    // columns found at the top level belong to the root module
+    let module = if path.len() >= 2 {
+        path[path.len() - 2].as_str()
+    } else {
+        ""
+    };
+    Handle::new(module, &path[path.len() - 1]).into()
+}
+
+/// The magma of the column or register `handle`, if it exists in `cs`
+fn column_magma(cs: &ConstraintSet, handle: &ColumnRef) -> Option<Magma> {
+    cs.columns.column(handle).map(|c| c.t).ok().or_else(|| {
+        cs.columns
+            .register(&RegisterRef::from(handle.as_handle().clone()))
+            .map(|r| r.magma)
+    })
+}
+
+/// Parsing a column prefixes it with a single padding row; but a row of its
+/// module spans `size_factor` rows of a column longer than it, e.g. an
+/// interleaving, whose padding row is completed accordingly. Otherwise, a
+/// provided interleaving would be one row short, and shifted with respect to
+/// both its sources and the same interleaving once computed.
+fn complete_padding_row(xs: &mut Vec<CValue>, size_factor: usize) {
+    if size_factor > 1 {
+        xs.splice(
+            0..0,
+            std::iter::repeat(CValue::zero()).take(size_factor - 1),
+        );
+    }
+}
+
+/// Fill the column at `path` with its values, as parsed by `parse` from the
+/// trace
+#[allow(clippy::type_complexity)]
+fn fill_column(
+    parse: &dyn Fn(&ConstraintSet, &ColumnRef, Magma) -> Result<Vec<CValue>>,
+    path: &[String],
     cs: &mut ConstraintSet,
     initiator: &mut Option<&mut String>,
     keep_raw: bool,
 ) -> Result<()> {
-    match v {
-        Value::Object(map) => {
-            for (k, v) in map.iter() {
-                if k == "Trace" {
-                    debug!("Importing {}", path[path.len() - 1]);
-                    let mut first_column = String::new();
-                    let mut initiator = Some(&mut first_column);
-                    fill_traces_from_json(v, path.clone(), cs, &mut initiator, keep_raw)?;
-                } else {
-                    let mut path = path.clone();
-                    path.push(k.to_string());
-                    fill_traces_from_json(v, path, cs, initiator, keep_raw)?;
-                }
+    let handle = column_at(path);
+    let module = handle.as_handle().module.to_owned();
+
+    let module_spilling = cs.spilling_for_column(&handle);
+
+    if let Result::Ok(Column {
+        t, padding_value, ..
+    }) = cs.columns.column(&handle)
+    {
+        trace!("inserting {}", handle);
+        if let Some(first_column) = initiator.as_mut() {
+            if first_column.is_empty() {
+                first_column.push_str(&handle.pretty());
             }
-            Ok(())
-        }
-        Value::Array(xs) => {
-            if path.len() >= 2 {
-                let module = path[path.len() - 2].to_string();
-                let handle: ColumnRef = Handle::new(&module, &path[path.len() - 1]).into();
-
-                // The min length can be set if the module contains range
-                // proofs, that require a minimal length of a certain power of 2
-                let module_min_len = cs.columns.min_len.get(&module).cloned().unwrap_or(0);
-                let module_spilling = cs.spilling_for_column(&handle);
-
-                if let Result::Ok(Column {
-                    t, padding_value, ..
-                }) = cs.columns.column(&handle)
-                {
-                    trace!("inserting {} ({})", handle, xs.len());
-                    if let Some(first_column) = initiator.as_mut() {
-                        if first_column.is_empty() {
-                            first_column.push_str(&handle.pretty());
-                        }
-                    }
+        }
 
-                    let module_spilling = module_spilling
-                        .ok_or_else(|| anyhow!("no spilling found for {}", handle.pretty()))?;
-
-                    let mut xs = parse_column(xs, handle.as_handle(), *t, keep_raw)
-                        .with_context(|| anyhow!("importing {}", handle.pretty()))?;
-
-                    // If the parsed column is not long enought w.r.t. the
-                    // minimal module length, prepend it with as many zeroes as
-                    // required.
-                    // Atomic columns are always padded with zeroes, so there is
-                    // no need to trigger a more complex padding system.
-                    if !keep_raw && xs.len() < module_min_len {
-                        xs.reverse();
-                        xs.resize_with(module_min_len, || {
-                            padding_value.clone().unwrap_or_default()
-                        });
-                        xs.reverse();
-                    }
+        let module_spilling =
+            module_spilling.ok_or_else(|| anyhow!("no spilling found for {}", handle.pretty()))?;
 
-                    // The first column sets the size of its module
-                    let module_raw_size = cs.effective_len_or_set(&module, xs.len() as isize);
-                    if xs.len() as isize != module_raw_size {
-                        bail!(
-                            "{} has an incorrect length: expected {} (from {}), found {}",
-                            handle.to_string().blue(),
-                            module_raw_size.to_string().red().bold(),
-                            initiator.as_ref().unwrap(),
-                            xs.len().to_string().yellow().bold(),
-                        );
-                    }
+        let mut xs =
+            parse(cs, &handle, *t).with_context(|| anyhow!("importing {}", handle.pretty()))?;
+        // the parsed column is prefixed by a padding row
+        let trace_len = if keep_raw { xs.len() } else { xs.len() - 1 };
 
-                    cs.columns.set_column_value(&handle, xs, module_spilling)?
-                } else if let Some(Register { magma, .. }) = cs.columns.register(&handle) {
-                    let module_spilling = module_spilling
-                        .ok_or_else(|| anyhow!("no spilling found for {}", handle.pretty()))?;
-
-                    let mut xs = parse_column(xs, handle.as_handle(), *magma, keep_raw)
-                        .with_context(|| anyhow!("importing {}", handle.pretty()))?;
-
-                    // If the parsed column is not long enought w.r.t. the
-                    // minimal module length, prepend it with as many zeroes as
-                    // required.
-                    // Atomic columns are always padded with zeroes, so there is
-                    // no need to trigger a more complex padding system.
-                    if xs.len() < module_min_len {
-                        xs.reverse();
-                        xs.resize(module_min_len, CValue::zero()); // TODO: register padding values
-                        xs.reverse();
-                    }
+        let size_factor = cs.length_multiplier(&handle);
+        if !keep_raw {
+            complete_padding_row(&mut xs, size_factor);
+        }
 
-                    let module_raw_size = cs.effective_len_or_set(&module, xs.len() as isize);
-                    if xs.len() as isize != module_raw_size {
-                        bail!(
-                            "{} has an incorrect length: expected {} (from {}), found {}",
-                            handle.to_string().blue(),
-                            module_raw_size.to_string().red().bold(),
-                            initiator.as_ref().unwrap(),
-                            xs.len().to_string().yellow().bold(),
-                        );
-                    }
+        // If the parsed column is not long enought w.r.t. the
+        // minimal module length, which can be set if the module contains
+        // range proofs, or its padding strategy, prepend it with as many
+        // padding values as required.
+        if !keep_raw {
+            let module_min_len = compute::padded_len(cs, &module, xs.len() / size_factor)?;
+            if xs.len() < module_min_len * size_factor {
+                xs.reverse();
+                xs.resize_with(module_min_len * size_factor, || {
+                    padding_value.clone().unwrap_or_default()
+                });
+                xs.reverse();
+            }
+        }
 
-                    cs.columns
-                        .set_register_value(&handle, xs, module_spilling)?
-                } else {
-                    debug!("ignoring unknown column {}", handle.pretty());
-                }
+        // The first column sets the size of its module; the
+        // lengths of the other ones are validated against their
+        // sources once the whole trace is imported.
+        if size_factor == 1 {
+            let module_raw_size = cs.effective_len_or_set(&module, xs.len() as isize);
+            if xs.len() as isize != module_raw_size {
+                bail!(
+                    "{} has an incorrect length: expected {} (from {}), found {}",
+                    handle.to_string().blue(),
+                    module_raw_size.to_string().red().bold(),
+                    initiator.as_deref().map_or("its module", |s| s.as_str()),
+                    xs.len().to_string().yellow().bold(),
+                );
             }
-            Ok(())
         }
-        _ => Ok(()),
+
+        cs.columns.set_column_value(&handle, xs, module_spilling)?;
+        cs.columns
+            .set_trace_len(&cs.columns.register_ref_of(&handle)?, trace_len)?;
+    } else if let Some(Register { magma, .. }) = cs
+        .columns
+        .register(&RegisterRef::from(handle.as_handle().clone()))
+    {
+        let module_spilling =
+            module_spilling.ok_or_else(|| anyhow!("no spilling found for {}", handle.pretty()))?;
+
+        let mut xs =
+            parse(cs, &handle, *magma).with_context(|| anyhow!("importing {}", handle.pretty()))?;
+        let trace_len = if keep_raw { xs.len() } else { xs.len() - 1 };
+
+        // If the parsed column is not long enought w.r.t. the
+        // minimal module length or its padding strategy, prepend it with as
+        // many zeroes as required.
+        // Atomic columns are always padded with zeroes, so there is
+        // no need to trigger a more complex padding system.
+        let module_min_len = compute::padded_len(cs, &module, xs.len())?;
+        if xs.len() < module_min_len {
+            xs.reverse();
+            xs.resize(module_min_len, CValue::zero()); // TODO: register padding values
+            xs.reverse();
+        }
+
+        let module_raw_size = cs.effective_len_or_set(&module, xs.len() as isize);
+        if xs.len() as isize != module_raw_size {
+            bail!(
+                "{} has an incorrect length: expected {} (from {}), found {}",
+                handle.to_string().blue(),
+                module_raw_size.to_string().red().bold(),
+                initiator.as_deref().map_or("its module", |s| s.as_str()),
+                xs.len().to_string().yellow().bold(),
+            );
+        }
+
+        let register = RegisterRef::from(handle.as_handle().clone());
+        cs.columns
+            .set_register_value(&register, xs, module_spilling)?;
+        cs.columns.set_trace_len(&register, trace_len)?;
+    } else {
+        debug!("ignoring unknown column {}", handle.pretty());
     }
+    Ok(())
 }
@@ -0,0 +1,214 @@
+//! Open the files given to corset, e.g. traces or compiled constraint sets,
+//! whether they are local paths, `-` for STDIN, or HTTP(S) URLs, and
+//! transparently decompress them if they are gzipped or zstd-compressed.
+//!
+//! STDIN and remote inputs can only be read once; they are thus buffered the
+//! first time they are opened, so that their format can be sniffed before
+//! they are parsed, and dropped as soon as a parser has consumed them.
+use anyhow::*;
+use flate2::read::GzDecoder;
+use owo_colors::OwoColorize;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Cursor, Read},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// The name standing for STDIN
+pub const STDIN: &str = "-";
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+lazy_static::lazy_static! {
+    /// The raw content of the inputs that can not be read twice, until they
+    /// are consumed
+    static ref BUFFERED: Mutex<HashMap<String, Shared>> = Default::default();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+impl Compression {
+    /// Detect the compression of a content from its first bytes
+    pub fn detect(head: &[u8]) -> Compression {
+        if head.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if head.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Wrap `r` in the decoder of this compression
+    fn decoder<'a, R: Read + 'a>(&self, r: R) -> Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Compression::None => Box::new(r),
+            Compression::Gzip => Box::new(GzDecoder::new(r)),
+            Compression::Zstd => Box::new(
+                ruzstd::StreamingDecoder::new(r)
+                    .map_err(|e| anyhow!("invalid zstd stream: {}", e))?,
+            ),
+        })
+    }
+}
+impl std::fmt::Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Compression::None => write!(f, "uncompressed"),
+            Compression::Gzip => write!(f, "gzip"),
+            Compression::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+/// Whether `input` is an HTTP(S) URL
+pub fn is_remote(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// The extension of `input`, ignoring the one of its compression, e.g. `bin`
+/// for `constraints.bin.zst`
+pub fn extension(input: &str) -> Option<&str> {
+    let path = Path::new(input);
+    match path.extension()?.to_str()? {
+        "gz" | "zst" => Path::new(path.file_stem()?).extension()?.to_str(),
+        ext => Some(ext),
+    }
+}
+
+/// A buffered input, shared by all the places opening it
+#[derive(Clone)]
+pub struct Shared(Arc<Vec<u8>>);
+impl AsRef<[u8]> for Shared {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(feature = "http")]
+fn fetch(url: &str) -> Result<Vec<u8>> {
+    let response = ureq::get(url)
+        .call()
+        .with_context(|| anyhow!("while fetching {}", url.bright_white().bold()))?;
+    let mut r = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut r)
+        .with_context(|| anyhow!("while downloading {}", url.bright_white().bold()))?;
+    Ok(r)
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch(url: &str) -> Result<Vec<u8>> {
+    bail!(
+        "unable to fetch {}: this build of corset does not support HTTP inputs",
+        url.bright_white().bold()
+    )
+}
+
+/// The raw content of `input`, that must be STDIN or a remote input; it is
+/// kept to be opened again, unless it is `consume`d
+fn buffered(input: &str, consume: bool) -> Result<Shared> {
+    let mut buffered = BUFFERED.lock().unwrap();
+    let content = if let Some(content) = buffered.remove(input) {
+        content
+    } else if input == STDIN {
+        let mut r = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut r)
+            .context("while reading STDIN")?;
+        Shared(Arc::new(r))
+    } else {
+        Shared(Arc::new(fetch(input)?))
+    };
+    if !consume {
+        buffered.insert(input.to_owned(), content.clone());
+    }
+    Ok(content)
+}
+
+/// A reader over `bytes`, decompressing them on the fly if they are
+/// compressed
+pub fn decompress(bytes: &[u8]) -> Result<Box<dyn Read + '_>> {
+    Compression::detect(bytes).decoder(bytes)
+}
+
+fn open(input: &str, consume: bool) -> Result<Box<dyn BufRead>> {
+    let raw: Box<dyn Read> = if input == STDIN || is_remote(input) {
+        Box::new(Cursor::new(buffered(input, consume)?))
+    } else {
+        Box::new(
+            File::open(input)
+                .with_context(|| anyhow!("opening {}", input.bright_white().bold()))?,
+        )
+    };
+    let mut raw = BufReader::new(raw);
+    let compression = Compression::detect(
+        raw.fill_buf()
+            .with_context(|| anyhow!("reading {}", input.bright_white().bold()))?,
+    );
+    Ok(match compression {
+        Compression::None => Box::new(raw),
+        _ => Box::new(BufReader::new(compression.decoder(raw)?)),
+    })
+}
+
+/// A reader over the decompressed content of `input`; STDIN and remote
+/// inputs can not be opened anymore afterwards
+pub fn reader(input: &str) -> Result<Box<dyn BufRead>> {
+    open(input, true)
+}
+
+/// A reader over the decompressed content of `input`, that can still be
+/// opened afterwards, e.g. to check it before parsing it
+pub fn peek_reader(input: &str) -> Result<Box<dyn BufRead>> {
+    open(input, false)
+}
+
+/// The compression of `input`
+pub fn compression(input: &str) -> Result<Compression> {
+    let mut head = Vec::with_capacity(ZSTD_MAGIC.len());
+    if input == STDIN || is_remote(input) {
+        head.extend(
+            buffered(input, false)?
+                .as_ref()
+                .iter()
+                .take(ZSTD_MAGIC.len()),
+        );
+    } else {
+        File::open(input)
+            .with_context(|| anyhow!("opening {}", input.bright_white().bold()))?
+            .take(ZSTD_MAGIC.len() as u64)
+            .read_to_end(&mut head)
+            .with_context(|| anyhow!("reading {}", input.bright_white().bold()))?;
+    }
+    Ok(Compression::detect(&head))
+}
+
+/// The first, at most `len`, decompressed bytes of `input`
+pub fn head(input: &str, len: usize) -> Result<Vec<u8>> {
+    let mut r = Vec::with_capacity(len);
+    peek_reader(input)?
+        .take(len as u64)
+        .read_to_end(&mut r)
+        .with_context(|| anyhow!("reading {}", input.bright_white().bold()))?;
+    Ok(r)
+}
+
+/// Whether `input` is empty
+pub fn is_empty(input: &str) -> Result<bool> {
+    if input == STDIN || is_remote(input) {
+        Ok(buffered(input, false)?.as_ref().is_empty())
+    } else {
+        std::fs::metadata(input)
+            .with_context(|| anyhow!("unable to read metadata of `{}`", input))
+            .map(|f| f.len() == 0)
+    }
+}
@@ -1,6 +1,8 @@
 use crate::{
-    compiler::{ColumnRef, ConstraintSet},
+    check::FailureReport,
+    compiler::{self, ColumnRef, ConstraintSet},
     pretty::Pretty,
+    provenance::{ProvenanceReport, Sample},
     structs::Handle,
 };
 use anyhow::{bail, Context, Result};
@@ -12,22 +14,38 @@ use crossterm::{
 use itertools::Itertools;
 use ratatui::{prelude::*, widgets::*};
 use regex_lite::Regex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use theme::Theme;
 
 type Backend = CrosstermBackend<std::io::Stdout>;
 type Frame<'a> = ratatui::Frame<'a>;
 type StdTerminal = Terminal<Backend>;
 
 const CONTEXT: isize = 50;
+/// How many of the most frequent values a histogram shows
+const HISTOGRAM_SIZE: usize = 10;
 
 mod forth;
-mod widgets;
+pub(crate) mod theme;
+pub(crate) mod widgets;
+
+/// A line of the table displaying a module
+enum ModuleLine {
+    /// the header of a column group, with its number of shown columns
+    Group(String, usize),
+    /// the index of a column in [`ModuleView::columns`]
+    Column(usize),
+}
 
 struct ModuleView {
     /// The name of the associated module
     name: String,
-    /// A cache of this module columns
+    /// A cache of this module columns, sorted by group
     columns: Vec<(ColumnRef, Handle)>,
+    /// The group of each of the `columns`, if any
+    groups: Vec<Option<String>>,
+    /// The groups whose columns are hidden
+    collapsed: HashSet<String>,
     /// Current horizontal offset in the table view
     h_shift: isize,
     /// Current vertical offset in the table view
@@ -43,25 +61,56 @@ struct ModuleView {
     /// If any, the latest used Forth/scan expression
     last_scan: String,
 
-    /// If set, avoid low-constrast colors
-    high_contrast: bool,
+    /// The indices in [`ConstraintSet::constraints`] of this module constraints
+    constraints: Vec<usize>,
+    /// The position in `constraints` of the constraint selected in the constraints pane
+    selected_constraint: Option<usize>,
+    /// The indices in `columns` of the columns read by the selected constraint
+    referenced: HashSet<usize>,
+
+    /// The cells, as indices in `columns` and rows, involved in a failure
+    /// recorded by the checker
+    failing_cells: HashSet<(usize, isize)>,
+    /// The rows on which the checker recorded failures, and the failing
+    /// constraints
+    violations: BTreeMap<isize, BTreeSet<String>>,
+
+    /// The provenance sampled by `compute`, per index in `columns` and row
+    provenance: HashMap<usize, BTreeMap<isize, Sample>>,
+    /// The cells, as indices in `columns` and rows, read to compute the cell
+    /// whose provenance is shown
+    provenance_cells: HashSet<(usize, isize)>,
+
+    /// How to draw the module
+    theme: Theme,
 }
 impl ModuleView {
-    fn from_cs(cs: &ConstraintSet, name: &str, high_contrast: bool) -> ModuleView {
+    fn from_cs(cs: &ConstraintSet, name: &str, theme: Theme) -> ModuleView {
         let mut max_size = 0;
-        let columns: Vec<(ColumnRef, Handle)> = cs
+        // ungrouped columns come first, then the groups in alphabetical order
+        let (columns, groups): (Vec<(ColumnRef, Handle)>, Vec<Option<String>>) = cs
             .columns
             .iter_module(name)
-            .sorted_by(|c, d| c.1.handle.cmp(&d.1.handle))
+            .sorted_by(|c, d| (&c.1.group, &c.1.handle).cmp(&(&d.1.group, &d.1.handle)))
             .map(|c| {
                 max_size = max_size.max(cs.columns.len(&c.0).unwrap_or_default());
-                (c.0, c.1.handle.clone())
+                ((c.0, c.1.handle.clone()), c.1.group.clone())
             })
-            .collect();
+            .unzip();
         let currently_shown = (0..columns.len()).collect();
+        let constraints = cs
+            .constraints
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.handle().module == name)
+            .sorted_by_cached_key(|(_, c)| c.handle().name.clone())
+            .map(|(i, _)| i)
+            .collect();
         ModuleView {
             name: name.to_owned(),
             columns,
+            groups,
+            collapsed: HashSet::new(),
             h_shift: 0,
             v_shift: 0,
             size: max_size as isize - 1,
@@ -70,7 +119,15 @@ impl ModuleView {
             to_show: currently_shown,
 
             last_scan: String::new(),
-            high_contrast,
+
+            constraints,
+            selected_constraint: None,
+            referenced: HashSet::new(),
+            failing_cells: HashSet::new(),
+            violations: BTreeMap::new(),
+            provenance: HashMap::new(),
+            provenance_cells: HashSet::new(),
+            theme,
         }
     }
 
@@ -91,7 +148,7 @@ impl ModuleView {
     }
 
     fn down(&mut self, x: i16) {
-        self.v_shift = (self.v_shift + x).min(self.to_show.len() as i16 - 1);
+        self.v_shift = (self.v_shift + x).min(self.lines().len() as i16 - 1);
     }
 
     fn home(&mut self) {
@@ -106,8 +163,63 @@ impl ModuleView {
         self.to_show.iter().map(|i| &self.columns[*i])
     }
 
+    /// The lines to display, i.e. the shown columns interspersed with the
+    /// headers of their groups, minus the columns of the collapsed groups
+    fn lines(&self) -> Vec<ModuleLine> {
+        let mut r = Vec::new();
+        for (group, is) in &self.to_show.iter().group_by(|i| &self.groups[**i]) {
+            let is = is.collect::<Vec<_>>();
+            if let Some(group) = group {
+                r.push(ModuleLine::Group(group.to_owned(), is.len()));
+                if self.collapsed.contains(group) {
+                    continue;
+                }
+            }
+            r.extend(is.into_iter().map(|i| ModuleLine::Column(*i)));
+        }
+        r
+    }
+
+    /// The column on the topmost displayed line, or the first one below it
+    fn selected_column(&self) -> Option<&(ColumnRef, Handle)> {
+        self.lines()
+            .into_iter()
+            .skip(self.v_shift as usize)
+            .find_map(|l| match l {
+                ModuleLine::Column(i) => Some(&self.columns[i]),
+                ModuleLine::Group(..) => None,
+            })
+    }
+
+    /// The rows currently displayed
+    fn window(&self) -> std::ops::Range<isize> {
+        0.max(self.h_shift)..(self.h_shift + CONTEXT).min(self.size) + 1
+    }
+
+    /// Collapse or expand the group of the topmost displayed line
+    fn toggle_group(&mut self) {
+        let group = match self.lines().get(self.v_shift as usize) {
+            Some(ModuleLine::Group(group, _)) => group.to_owned(),
+            Some(ModuleLine::Column(i)) => match self.groups[*i].as_ref() {
+                Some(group) => group.to_owned(),
+                None => return,
+            },
+            None => return,
+        };
+        if !self.collapsed.remove(&group) {
+            self.collapsed.insert(group.clone());
+        }
+        // keep the toggled group in view
+        self.v_shift = self
+            .lines()
+            .iter()
+            .position(|l| matches!(l, ModuleLine::Group(g, _) if *g == group))
+            .unwrap_or_default() as i16;
+    }
+
     fn filter(&mut self, regexps: Vec<Regex>) {
         self.regexps = regexps;
+        self.v_shift = 0;
         self.to_show = self
             .columns
             .iter()
@@ -131,8 +243,106 @@ impl ModuleView {
         self.filter(Vec::new());
     }
 
+    /// Select the `k`th constraint of the module, and mark the columns it reads
+    /// from; return the handles of those of them living in other modules
+    fn select_constraint(&mut self, cs: &ConstraintSet, k: usize) -> Vec<Handle> {
+        self.selected_constraint = Some(k);
+        let mut foreign = Vec::new();
+        self.referenced.clear();
+        for handle in cs.constraints[self.constraints[k]]
+            .dependencies()
+            .iter()
+            .map(|r| cs.handle(r))
+            .sorted()
+        {
+            match self.columns.iter().position(|(_, h)| h == handle) {
+                Some(i) => {
+                    self.referenced.insert(i);
+                }
+                None => foreign.push(handle.clone()),
+            }
+        }
+        foreign
+    }
+
+    fn unselect_constraint(&mut self) {
+        self.selected_constraint = None;
+        self.referenced.clear();
+    }
+
+    /// Bring the next column read by the selected constraint on the topmost
+    /// displayed line, showing it first if it is filtered out or collapsed
+    fn jump_to_reference(&mut self) -> bool {
+        if self.referenced.iter().any(|i| !self.to_show.contains(i)) {
+            self.clear_filter();
+        }
+        for i in self.referenced.iter() {
+            if let Some(group) = self.groups[*i].as_ref() {
+                self.collapsed.remove(group);
+            }
+        }
+        let lines = self.lines();
+        let is_referenced =
+            |l: &ModuleLine| matches!(l, ModuleLine::Column(i) if self.referenced.contains(i));
+        let target = lines
+            .iter()
+            .skip(self.v_shift as usize + 1)
+            .position(is_referenced)
+            .map(|p| p + self.v_shift as usize + 1)
+            .or_else(|| lines.iter().position(is_referenced));
+        if let Some(target) = target {
+            self.v_shift = target as i16;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Bring the next row on which a failure was recorded on the leftmost
+    /// displayed column, and return it along with the constraints failing there
+    fn next_violation(&mut self) -> Option<(isize, &BTreeSet<String>)> {
+        let (row, constraints) = self
+            .violations
+            .range(self.h_shift + 1..)
+            .next()
+            .or_else(|| self.violations.iter().next())?;
+        self.h_shift = (*row).clamp(0, self.size);
+        Some((*row, constraints))
+    }
+
+    /// Bring the next row of the selected column whose provenance has been
+    /// sampled on the leftmost displayed column, mark the cells it has been
+    /// computed from, and return its provenance
+    fn next_provenance(&mut self) -> Option<&Sample> {
+        let i_col = self
+            .lines()
+            .into_iter()
+            .skip(self.v_shift as usize)
+            .find_map(|l| match l {
+                ModuleLine::Column(i) => Some(i),
+                ModuleLine::Group(..) => None,
+            })?;
+        let samples = self.provenance.get(&i_col)?;
+        let (row, sample) = samples
+            .range(self.h_shift + 1..)
+            .next()
+            .or_else(|| samples.iter().next())?;
+        self.h_shift = (*row).clamp(0, self.size);
+        self.provenance_cells = sample
+            .inputs
+            .iter()
+            .filter_map(|input| {
+                self.columns
+                    .iter()
+                    .position(|(_, h)| h.to_string() == input.column)
+                    .map(|c| (c, input.row))
+            })
+            .collect();
+        Some(sample)
+    }
+
     fn render(&self, cs: &ConstraintSet, f: &mut Frame, target: Rect) {
-        let span = 0.max(self.h_shift)..(self.h_shift + CONTEXT).min(self.size) + 1;
+        let span = self.window();
         // max width for each column; defaults to 3
         let max_perspective_len = self
             .current_columns()
@@ -141,95 +351,91 @@ impl ModuleView {
             .unwrap_or_default();
         let mut maxes = vec![3; span.len() + 1];
 
-        let active_white_value = if self.high_contrast {
-            Color::White
-        } else {
-            Color::Gray
-        };
-        let dimmed_value = if self.high_contrast {
-            Color::Gray
-        } else {
-            Color::DarkGray
-        };
-
         let block = Block::new().borders(Borders::NONE);
         let rows = self
-            .current_columns()
+            .lines()
+            .into_iter()
             .skip(self.v_shift as usize)
-            .map(|(column_ref, h)| {
-                maxes[0] = maxes[0].max(h.name.len() + max_perspective_len);
-                Row::new(
-                    std::iter::once(
-                        Cell::from(format!(
-                            "{:width$} {}",
-                            if let Some(p) = h.perspective.as_ref() {
-                                p
-                            } else {
-                                ""
-                            },
-                            h.name.to_owned(),
-                            width = max_perspective_len,
-                        ))
-                        .style(Style::default().blue().bold()),
-                    )
-                    .chain(span.clone().enumerate().map(|(k, i)| {
-                        cs.columns
-                            .get(column_ref, i, false)
-                            .map(|x| {
-                                let base = cs.columns.column(column_ref).unwrap().base;
-                                let x_str = x.pretty_with_base(base);
-                                maxes[k + 1] = maxes[k + 1].max(x_str.len());
-                                // map color to the 231-17 range of readable color
-                                // https://i.stack.imgur.com/KTSQa.png
-                                let hash =
-                                    x.to_bytes().iter().fold(0u8, |ax, bx| ax.wrapping_add(*bx));
-                                let bg_color = (hash % (231 - 16)) + 0;
-                                // ensure that we write white on dark colors and white on dark ones
-                                let corrected_fg_color = if bg_color % 36 > 18 {
-                                    Color::Black
-                                } else if bg_color == 0 {
-                                    active_white_value
+            .map(|line| match line {
+                ModuleLine::Group(group, count) => Row::new([Cell::from(format!(
+                    "{} {} ({})",
+                    self.theme.group_marker(self.collapsed.contains(&group)),
+                    group,
+                    count
+                ))])
+                .style(Style::default().yellow().bold()),
+                ModuleLine::Column(i_col) => {
+                    let (column_ref, h) = &self.columns[i_col];
+                    maxes[0] = maxes[0].max(h.name.len() + max_perspective_len);
+                    Row::new(
+                        std::iter::once(
+                            Cell::from(format!(
+                                "{:width$} {}",
+                                if let Some(p) = h.perspective.as_ref() {
+                                    p
                                 } else {
-                                    Color::White
-                                };
-
-                                // dim the column if its perspective is inactive
-                                let dim = if let Some(perspective) =
-                                    cs.columns.perspective(column_ref).unwrap()
-                                {
-                                    cs.get_perspective(&h.module, perspective)
-                                        .unwrap()
-                                        .eval(
-                                            i,
-                                            |handle, i, wrap| cs.columns.get_raw(handle, i, wrap),
-                                            &mut None,
-                                            &Default::default(),
-                                        )
-                                        .map(|x| x.is_zero())
-                                        .unwrap_or(false)
+                                    ""
+                                },
+                                h.name.to_owned(),
+                                width = max_perspective_len,
+                            ))
+                            .style(
+                                if self.referenced.contains(&i_col) {
+                                    Style::default().black().on_yellow().bold()
                                 } else {
-                                    false
-                                };
+                                    Style::default().blue().bold()
+                                },
+                            ),
+                        )
+                        .chain(span.clone().enumerate().map(|(k, i)| {
+                            cs.columns
+                                .get(column_ref, i, false)
+                                .map(|x| {
+                                    let base = cs.columns.column(column_ref).unwrap().base;
+                                    let x_str = x.pretty_with_base(base);
+                                    maxes[k + 1] = maxes[k + 1].max(x_str.len());
+                                    let hash = x
+                                        .to_bytes()
+                                        .iter()
+                                        .fold(0u8, |ax, bx| ax.wrapping_add(*bx));
+                                    let (fg_color, bg_color) = self.theme.cell_colors(hash);
+
+                                    // dim the column if its perspective is inactive
+                                    let dim = if let Some(perspective) =
+                                        cs.columns.perspective(column_ref).unwrap()
+                                    {
+                                        cs.get_perspective(&h.module, perspective)
+                                            .unwrap()
+                                            .eval(
+                                                i,
+                                                |handle, i, wrap| {
+                                                    cs.columns.get_raw(handle, i, wrap)
+                                                },
+                                                &mut None,
+                                                &Default::default(),
+                                            )
+                                            .map(|x| x.is_zero())
+                                            .unwrap_or(false)
+                                    } else {
+                                        false
+                                    };
 
-                                // render the cell
-                                Cell::from(x_str)
-                                    .fg(if dim {
-                                        dimmed_value
+                                    // render the cell, failures first
+                                    if self.failing_cells.contains(&(i_col, i)) {
+                                        Cell::from(x_str).white().on_red().bold()
+                                    } else if self.provenance_cells.contains(&(i_col, i)) {
+                                        Cell::from(x_str).black().on_cyan().bold()
                                     } else {
-                                        corrected_fg_color
-                                    })
-                                    .bg({
-                                        if bg_color > 0 && !dim {
-                                            Color::Indexed(bg_color.wrapping_add(16) % 251)
-                                        } else {
-                                            Color::Reset
-                                        }
-                                    })
-                            })
-                            .unwrap_or(Cell::from("."))
-                    })),
-                )
-                .style(Style::default().white())
+                                        Cell::from(x_str)
+                                            .fg(if dim { self.theme.dimmed() } else { fg_color })
+                                            .bg(if dim { Color::Reset } else { bg_color })
+                                    }
+                                })
+                                .unwrap_or(Cell::from("."))
+                        })),
+                    )
+                    .style(Style::default().white())
+                }
             })
             .collect::<Vec<_>>();
         let widths = maxes
@@ -248,27 +454,32 @@ impl ModuleView {
     }
 }
 
-struct Inspector<'a> {
+pub(crate) struct Inspector<'a> {
     cs: &'a ConstraintSet,
     modules: Vec<ModuleView>,
     current_module: usize,
+    /// Whether the constraints of the current module are listed alongside its columns
+    constraints_pane: bool,
     minibuffer: Rect,
     message: Span<'a>,
+    theme: Theme,
 }
 impl<'a> Inspector<'a> {
-    fn from_cs(cs: &'a ConstraintSet, high_contrast: bool) -> Result<Self> {
+    pub(crate) fn from_cs(cs: &'a ConstraintSet, theme: Theme) -> Result<Self> {
         let r = Inspector {
             cs,
             modules: cs
                 .columns
                 .modules()
                 .iter()
-                .map(|n| ModuleView::from_cs(cs, n, high_contrast))
+                .map(|n| ModuleView::from_cs(cs, n, theme))
                 .sorted_by(|m1, m2| m1.name.cmp(&m2.name))
                 .collect(),
             current_module: 0,
+            constraints_pane: false,
             minibuffer: Default::default(),
             message: Span::from(""),
+            theme,
         };
         if r.modules.is_empty() {
             bail!("no modules found in provided constraint system");
@@ -277,6 +488,95 @@ impl<'a> Inspector<'a> {
         }
     }
 
+    /// Mark the cells involved in the failures of `report` in their modules
+    fn load_failures(&mut self, report: &FailureReport) {
+        let columns = self
+            .modules
+            .iter()
+            .enumerate()
+            .flat_map(|(m, module)| {
+                module
+                    .columns
+                    .iter()
+                    .enumerate()
+                    .map(move |(c, (_, h))| (h.to_string(), (m, c)))
+            })
+            .collect::<HashMap<_, _>>();
+        for violation in report.violations.iter() {
+            for column in violation.columns.iter() {
+                if let Some((m, c)) = columns.get(column) {
+                    let module = &mut self.modules[*m];
+                    module.failing_cells.insert((*c, violation.row));
+                    module
+                        .violations
+                        .entry(violation.row)
+                        .or_default()
+                        .insert(violation.constraint.clone());
+                }
+            }
+        }
+    }
+
+    /// Index the samples of `report` by module, column and row
+    fn load_provenance(&mut self, report: &ProvenanceReport) {
+        let columns = self
+            .modules
+            .iter()
+            .enumerate()
+            .flat_map(|(m, module)| {
+                module
+                    .columns
+                    .iter()
+                    .enumerate()
+                    .map(move |(c, (_, h))| (h.to_string(), (m, c)))
+            })
+            .collect::<HashMap<_, _>>();
+        for sample in report.samples.iter() {
+            if let Some((m, c)) = columns.get(&sample.column) {
+                self.modules[*m]
+                    .provenance
+                    .entry(*c)
+                    .or_default()
+                    .insert(sample.row, sample.clone());
+            }
+        }
+    }
+
+    fn next_provenance(&mut self) {
+        self.message = match self.current_module_mut().next_provenance() {
+            Some(sample) if sample.inputs.is_empty() => Span::from(format!(
+                "{}[{}] reads no cell: {}",
+                sample.column, sample.row, sample.computation
+            ))
+            .cyan(),
+            Some(sample) => Span::from(format!(
+                "{}[{}] ← {} :: {}",
+                sample.column,
+                sample.row,
+                sample
+                    .inputs
+                    .iter()
+                    .map(|input| format!("{}[{}]", input.column, input.row))
+                    .join(" "),
+                sample.computation
+            ))
+            .cyan(),
+            None => "No provenance sampled for this column".red(),
+        };
+    }
+
+    fn next_violation(&mut self) {
+        self.message = match self.current_module_mut().next_violation() {
+            Some((row, constraints)) => Span::from(format!(
+                "row {}: {} failed",
+                row,
+                constraints.iter().join(", ")
+            ))
+            .red(),
+            None => "No failures recorded in this module".red(),
+        };
+    }
+
     fn open_module(&mut self, module: &str) {
         self.current_module = self
             .modules
@@ -301,18 +601,27 @@ impl<'a> Inspector<'a> {
             .iter()
             .map(|t| t.name.clone())
             .collect::<Vec<_>>();
+        let (left, right) = self.theme.arrows();
+        // Shift-Tab is not reported by all the terminals
+        let (prev_key, next_key) = if self.theme.compat {
+            ("[[]", "[]]")
+        } else {
+            ("[S-TAB]", "[TAB]")
+        };
         let tabs = Tabs::new(titles)
             .block(
-                Block::default()
+                self.theme
+                    .block()
                     .borders(Borders::BOTTOM)
                     .title(Line::from(vec![
-                        "← ".into(),
-                        "[S-TAB]".yellow().bold(),
+                        format!("{} ", left).into(),
+                        prev_key.yellow().bold(),
                         " Modules ".into(),
-                        "[TAB]".yellow().bold(),
-                        " →".into(),
+                        next_key.yellow().bold(),
+                        format!(" {}", right).into(),
                     ])),
             )
+            .divider(self.theme.divider())
             .select(self.current_module)
             // .style(Style::default().dark_gray())
             .highlight_style(
@@ -328,6 +637,90 @@ impl<'a> Inspector<'a> {
         self.current_module().render(self.cs, f, target);
     }
 
+    fn render_constraints(&self, f: &mut Frame, target: Rect) {
+        let module = self.current_module();
+        let enter_key = if self.theme.compat { "[RET]" } else { "[⏎]" };
+        let items = module
+            .constraints
+            .iter()
+            .map(|i| {
+                let c = &self.cs.constraints[*i];
+                ListItem::new(Line::from(vec![
+                    format!(" {:4} ", constraint_kind(c)).dark_gray(),
+                    c.handle().name.clone().into(),
+                ]))
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(
+                self.theme
+                    .block()
+                    .borders(Borders::LEFT)
+                    .title(Line::from(vec![
+                        " Constraints ".into(),
+                        enter_key.yellow().bold(),
+                        " jump ".into(),
+                        "[ESC]".yellow().bold(),
+                        " close".into(),
+                    ])),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol(self.theme.arrows().1);
+        let mut state = ListState::default().with_selected(module.selected_constraint);
+        f.render_stateful_widget(list, target, &mut state);
+    }
+
+    /// Select the `k`th constraint of the current module and describe the
+    /// columns it reads from in the message line
+    fn select_constraint(&mut self, k: usize) {
+        let cs = self.cs;
+        let module = self.current_module_mut();
+        let foreign = module.select_constraint(cs, k);
+        let local = module.referenced.len();
+        let name = cs.constraints[module.constraints[k]].handle().name.clone();
+        self.message = if foreign.is_empty() {
+            Span::from(format!("{}: {} column(s) referenced", name, local))
+        } else {
+            Span::from(format!(
+                "{}: {} column(s) referenced; from other modules: {}",
+                name,
+                local,
+                foreign.iter().join(" ")
+            ))
+        };
+    }
+
+    /// Open the constraints pane, or close it if already opened
+    pub(crate) fn toggle_constraints(&mut self) {
+        if self.constraints_pane {
+            self.constraints_pane = false;
+            self.current_module_mut().unselect_constraint();
+        } else if self.current_module().constraints.is_empty() {
+            self.message = "No constraints in this module".red();
+        } else {
+            self.constraints_pane = true;
+            let k = self.current_module().selected_constraint.unwrap_or(0);
+            self.select_constraint(k);
+        }
+    }
+
+    /// Move the selection in the constraints pane by `delta`, wrapping around
+    pub(crate) fn move_constraint(&mut self, delta: isize) {
+        let module = self.current_module();
+        let count = module.constraints.len() as isize;
+        if count == 0 {
+            return;
+        }
+        let k = module.selected_constraint.unwrap_or(0) as isize;
+        self.select_constraint((k + delta).rem_euclid(count) as usize);
+    }
+
+    pub(crate) fn jump_to_reference(&mut self) {
+        if !self.current_module_mut().jump_to_reference() {
+            self.message = "No column of this module is referenced".red();
+        }
+    }
+
     fn render_help(&self, f: &mut Frame) {
         let titles = vec![
             "[g]".yellow().bold(),
@@ -343,6 +736,28 @@ impl<'a> Inspector<'a> {
             "[s]".yellow().bold(),
             "can".into(),
             " :: ".dark_gray(),
+            "[c]".yellow().bold(),
+            "ollapse group".into(),
+            " :: ".dark_gray(),
+            "[C]".yellow().bold(),
+            "onstraints".into(),
+            " :: ".dark_gray(),
+            "[v]".yellow().bold(),
+            "iolation".into(),
+            " :: ".dark_gray(),
+            "[p]".yellow().bold(),
+            "rovenance".into(),
+            " :: ".dark_gray(),
+            "[h]".yellow().bold(),
+            "istogram/".into(),
+            "[H]".yellow().bold(),
+            " in view".into(),
+            " :: ".dark_gray(),
+            "[<]".yellow().bold(),
+            "/".into(),
+            "[>]".yellow().bold(),
+            " jump".into(),
+            " :: ".dark_gray(),
             // "[p]".yellow().bold(),
             // "lookup".into(),
             // " :: ".into(),
@@ -351,12 +766,12 @@ impl<'a> Inspector<'a> {
         ];
         f.render_widget(
             Paragraph::new(vec![Line::from(titles), Line::from(self.message.clone())])
-                .block(Block::default().title("Commands").borders(Borders::TOP)),
+                .block(self.theme.block().title("Commands").borders(Borders::TOP)),
             self.minibuffer,
         );
     }
 
-    fn render(&mut self, f: &mut Frame) {
+    pub(crate) fn render(&mut self, f: &mut Frame) {
         let size = f.size();
         let chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -374,7 +789,16 @@ impl<'a> Inspector<'a> {
         let block = Block::default();
         f.render_widget(block, size);
         self.render_tabs(f, chunks[0]);
-        self.render_columns(f, chunks[1]);
+        if self.constraints_pane {
+            let panes = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(20), Constraint::Percentage(30)].as_ref())
+                .split(chunks[1]);
+            self.render_columns(f, panes[0]);
+            self.render_constraints(f, panes[1]);
+        } else {
+            self.render_columns(f, chunks[1]);
+        }
         self.render_help(f);
     }
 
@@ -383,11 +807,20 @@ impl<'a> Inspector<'a> {
             self.modules.len() - 1
         } else {
             self.current_module - 1
-        }
+        };
+        self.close_constraints();
     }
 
     fn next(&mut self) {
         self.current_module = (self.current_module + 1) % self.modules.len();
+        self.close_constraints();
+    }
+
+    fn close_constraints(&mut self) {
+        self.constraints_pane = false;
+        for module in self.modules.iter_mut() {
+            module.unselect_constraint();
+        }
     }
 
     fn run(&mut self, terminal: &mut StdTerminal, _settings: InspectorSettings) -> Result<()> {
@@ -415,6 +848,7 @@ impl<'a> Inspector<'a> {
                                 &self.current_module().name,
                                 &self.current_module().last_scan,
                                 &column_cache,
+                                self.theme,
                             )
                             .run(
                                 &mut t,
@@ -446,8 +880,9 @@ impl<'a> Inspector<'a> {
                                 },
                             )
                             .unwrap();
-                            let i = widgets::number::NumberInput::new("Go to column...")
-                                .run(&mut t, self.minibuffer);
+                            let i =
+                                widgets::number::NumberInput::new("Go to column...", self.theme)
+                                    .run(&mut t, self.minibuffer);
                             if let Some(i) = i {
                                 self.current_module_mut().goto(i);
                             }
@@ -468,6 +903,7 @@ impl<'a> Inspector<'a> {
                                     .iter()
                                     .map(|regexp| regexp.to_string())
                                     .join(" "),
+                                self.theme,
                             )
                             .run(&mut t, self.minibuffer);
                             if let Some(regexs) = regexs {
@@ -475,10 +911,57 @@ impl<'a> Inspector<'a> {
                             }
                             let _ = terminal.clear();
                         }
+                        KeyCode::Char('h') | KeyCode::Char('H') => {
+                            let module = self.current_module();
+                            let Some((column_ref, handle)) = module.selected_column() else {
+                                self.message = "No column to count".red();
+                                continue;
+                            };
+                            let rows = if key.code == KeyCode::Char('h') {
+                                0..self.cs.columns.len(column_ref).unwrap_or_default() as isize
+                            } else {
+                                module.window()
+                            };
+                            let histogram = widgets::histogram::Histogram::new(
+                                &handle.name,
+                                |i| self.cs.columns.get(column_ref, i, false),
+                                rows,
+                                self.cs.columns.column(column_ref).unwrap().base,
+                                HISTOGRAM_SIZE,
+                                self.theme,
+                            );
+                            // the histogram grows upward from the minibuffer
+                            let height = (HISTOGRAM_SIZE as u16 + 2).min(self.minibuffer.bottom());
+                            let area = Rect {
+                                y: self.minibuffer.bottom() - height,
+                                height,
+                                ..self.minibuffer
+                            };
+                            let mut t = Terminal::with_options(
+                                CrosstermBackend::new(std::io::stdout()),
+                                TerminalOptions {
+                                    viewport: Viewport::Fixed(area),
+                                },
+                            )
+                            .unwrap();
+                            histogram.run(&mut t, area);
+                            let _ = terminal.clear();
+                        }
                         KeyCode::Char('F') => self.current_module_mut().clear_filter(),
-                        KeyCode::BackTab => {
+                        KeyCode::Char('c') => self.current_module_mut().toggle_group(),
+                        KeyCode::Char('C') => self.toggle_constraints(),
+                        KeyCode::Char('v') => self.next_violation(),
+                        KeyCode::Char('p') => self.next_provenance(),
+                        KeyCode::Esc if self.constraints_pane => self.close_constraints(),
+                        KeyCode::Enter if self.constraints_pane => self.jump_to_reference(),
+                        KeyCode::Up if self.constraints_pane => self.move_constraint(-1),
+                        KeyCode::Down if self.constraints_pane => self.move_constraint(1),
+                        KeyCode::BackTab | KeyCode::Char('[') => {
                             self.prev();
                         }
+                        KeyCode::Char(']') => {
+                            self.next();
+                        }
                         KeyCode::Tab => {
                             if key.modifiers == KeyModifiers::SHIFT {
                                 self.prev();
@@ -498,6 +981,13 @@ impl<'a> Inspector<'a> {
                         KeyCode::Down => {
                             self.current_module_mut().down(1);
                         }
+                        // Shift-PageUp/PageDown are not reported by all the terminals
+                        KeyCode::Char('<') => {
+                            self.current_module_mut().left(1000);
+                        }
+                        KeyCode::Char('>') => {
+                            self.current_module_mut().right(1000);
+                        }
                         KeyCode::PageUp => {
                             if key.modifiers.contains(KeyModifiers::SHIFT) {
                                 self.current_module_mut().left(1000);
@@ -526,13 +1016,37 @@ impl<'a> Inspector<'a> {
     }
 }
 
+/// A short label for the kind of `c`, as shown in the constraints pane
+fn constraint_kind(c: &compiler::Constraint) -> &'static str {
+    match c {
+        compiler::Constraint::Vanishes { .. } => "vnsh",
+        compiler::Constraint::Lookup { .. } => "lkup",
+        compiler::Constraint::Permutation { .. } => "perm",
+        compiler::Constraint::InRange { .. } => "rnge",
+        compiler::Constraint::Normalization { .. } => "norm",
+    }
+}
+
 pub(crate) struct InspectorSettings {
     pub open_module: Option<String>,
     pub high_contrast: bool,
+    /// force the compatibility mode, otherwise enabled for the terminals
+    /// that seem to require it
+    pub compat: bool,
+    /// the failures recorded by a previous check, to be highlighted
+    pub failures: FailureReport,
+    /// the provenance sampled by a previous computation, to be displayed
+    pub provenance: ProvenanceReport,
 }
 
 pub(crate) fn inspect(cs: &ConstraintSet, settings: InspectorSettings) -> Result<()> {
-    let mut inspector = Inspector::from_cs(cs, settings.high_contrast)?;
+    let theme = Theme {
+        compat: settings.compat || theme::compat_needed(),
+        high_contrast: settings.high_contrast,
+    };
+    let mut inspector = Inspector::from_cs(cs, theme)?;
+    inspector.load_failures(&settings.failures);
+    inspector.load_provenance(&settings.provenance);
     if let Some(module) = settings.open_module.as_ref() {
         inspector.open_module(module);
     }
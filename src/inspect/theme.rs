@@ -0,0 +1,166 @@
+//! How the inspector draws itself, depending on what the terminal can render.
+use ratatui::{
+    style::Color,
+    symbols::{bar, border, line},
+    widgets::Block,
+};
+
+/// The borders drawn in compatibility mode
+const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+/// The bars drawn in compatibility mode
+const ASCII_BAR: bar::Set = bar::Set {
+    full: "#",
+    seven_eighths: "#",
+    three_quarters: "#",
+    five_eighths: "#",
+    half: "#",
+    three_eighths: "#",
+    one_quarter: "#",
+    one_eighth: "#",
+    empty: " ",
+};
+
+/// The background colors of the cells in compatibility mode, and whether they
+/// require a dark foreground
+const BASIC_COLORS: [(Color, bool); 6] = [
+    (Color::Red, false),
+    (Color::Green, true),
+    (Color::Yellow, true),
+    (Color::Blue, false),
+    (Color::Magenta, false),
+    (Color::Cyan, true),
+];
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Theme {
+    /// If set, only use ASCII glyphs and the 16 basic colors
+    pub compat: bool,
+    /// If set, avoid low-contrast colors
+    pub high_contrast: bool,
+}
+impl Theme {
+    pub fn group_marker(&self, collapsed: bool) -> &'static str {
+        match (self.compat, collapsed) {
+            (false, true) => "▸",
+            (false, false) => "▾",
+            (true, true) => "+",
+            (true, false) => "-",
+        }
+    }
+
+    pub fn arrows(&self) -> (&'static str, &'static str) {
+        if self.compat {
+            ("<", ">")
+        } else {
+            ("←", "→")
+        }
+    }
+
+    pub fn divider(&self) -> &'static str {
+        if self.compat {
+            "|"
+        } else {
+            line::VERTICAL
+        }
+    }
+
+    /// A block, bordered with ASCII glyphs in compatibility mode
+    pub fn block(&self) -> Block<'static> {
+        if self.compat {
+            Block::default().border_set(ASCII_BORDER)
+        } else {
+            Block::default()
+        }
+    }
+
+    pub fn bar_set(&self) -> bar::Set {
+        if self.compat {
+            ASCII_BAR
+        } else {
+            bar::NINE_LEVELS
+        }
+    }
+
+    /// The white of the values on a neutral background
+    pub fn active_white(&self) -> Color {
+        if self.high_contrast {
+            Color::White
+        } else {
+            Color::Gray
+        }
+    }
+
+    /// The color of the values in an inactive perspective
+    pub fn dimmed(&self) -> Color {
+        if self.high_contrast {
+            Color::Gray
+        } else {
+            Color::DarkGray
+        }
+    }
+
+    /// The foreground and background colors of a cell whose value hashes to
+    /// `hash`; a null color index yields a neutral background.
+    pub fn cell_colors(&self, hash: u8) -> (Color, Color) {
+        if self.compat {
+            match (hash as usize) % (BASIC_COLORS.len() + 1) {
+                0 => (self.active_white(), Color::Reset),
+                i => {
+                    let (bg, dark_fg) = BASIC_COLORS[i - 1];
+                    (if dark_fg { Color::Black } else { Color::White }, bg)
+                }
+            }
+        } else {
+            // map color to the 231-17 range of readable color
+            // https://i.stack.imgur.com/KTSQa.png
+            let bg_color = hash % (231 - 16);
+            // ensure that we write white on dark colors and white on dark ones
+            if bg_color == 0 {
+                (self.active_white(), Color::Reset)
+            } else {
+                (
+                    if bg_color % 36 > 18 {
+                        Color::Black
+                    } else {
+                        Color::White
+                    },
+                    Color::Indexed(bg_color.wrapping_add(16) % 251),
+                )
+            }
+        }
+    }
+}
+
+/// Whether the terminal is unlikely to render the Unicode glyphs, the 256
+/// colors, or the key combinations used by the inspector
+pub(crate) fn compat_needed() -> bool {
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+
+    // the legacy Windows console, unlike Windows Terminal, lacks most glyphs
+    if cfg!(windows) && std::env::var_os("WT_SESSION").is_none() {
+        return true;
+    }
+    if matches!(var("TERM").as_str(), "dumb" | "linux" | "vt100" | "vt220") {
+        return true;
+    }
+    // the first of these variables to be set defines the character encoding
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .map(var)
+        .find(|v| !v.is_empty())
+        .map(|locale| {
+            let locale = locale.to_lowercase();
+            !(locale.contains("utf-8") || locale.contains("utf8"))
+        })
+        .unwrap_or(false)
+}
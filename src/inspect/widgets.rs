@@ -1,3 +1,20 @@
+pub mod histogram;
 pub mod number;
 pub mod regexp;
 pub mod scan;
+
+use crossterm::event::{Event, KeyEvent, KeyEventKind};
+
+/// Wait for the next user input; the key releases, reported by some terminals
+/// such as the Windows console, are skipped so that keys are not typed twice.
+fn read_input() -> tui_textarea::Input {
+    loop {
+        match crossterm::event::read().unwrap() {
+            Event::Key(KeyEvent {
+                kind: KeyEventKind::Release,
+                ..
+            }) => continue,
+            event => return event.into(),
+        }
+    }
+}
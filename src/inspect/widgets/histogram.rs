@@ -0,0 +1,95 @@
+use crate::{
+    column::Value,
+    inspect::{theme::Theme, StdTerminal},
+    pretty::{Base, Pretty},
+};
+use ratatui::{
+    prelude::{Direction, Line, Rect},
+    style::{Color, Style},
+    widgets::{Bar, BarChart, BarGroup, Borders},
+};
+use std::{collections::HashMap, ops::Range};
+
+/// The distribution of the values of a column over a range of rows
+pub struct Histogram {
+    title: String,
+    /// the most frequent values and their number of occurrences, most
+    /// frequent first
+    top: Vec<(String, u64)>,
+    theme: Theme,
+}
+impl Histogram {
+    /// Count the values returned by `get` over `rows`, and keep the `k` most
+    /// frequent ones
+    pub fn new<F: Fn(isize) -> Option<Value>>(
+        column: &str,
+        get: F,
+        rows: Range<isize>,
+        base: Base,
+        k: usize,
+        theme: Theme,
+    ) -> Self {
+        let mut counts = HashMap::<Value, u64>::new();
+        for i in rows.clone() {
+            if let Some(x) = get(i) {
+                *counts.entry(x).or_default() += 1;
+            }
+        }
+        let total = counts.values().sum::<u64>();
+        let distinct = counts.len();
+        let mut counts = counts.into_iter().collect::<Vec<_>>();
+        counts.sort_by(|(x, n), (y, m)| m.cmp(n).then_with(|| x.cmp(y)));
+
+        Histogram {
+            title: format!(
+                "{} over [{}; {}] -- {} distinct values among {}",
+                column,
+                rows.start,
+                rows.end - 1,
+                distinct,
+                total
+            ),
+            top: counts
+                .into_iter()
+                .take(k)
+                .map(|(x, n)| (x.pretty_with_base(base), n))
+                .collect(),
+            theme,
+        }
+    }
+
+    /// The bar chart of the most frequent values
+    pub fn chart(&self) -> BarChart<'_> {
+        let bars = self
+            .top
+            .iter()
+            .map(|(x, n)| {
+                Bar::default()
+                    .value(*n)
+                    .text_value(n.to_string())
+                    .label(Line::from(x.as_str()))
+            })
+            .collect::<Vec<_>>();
+        BarChart::default()
+            .block(
+                self.theme
+                    .block()
+                    .borders(Borders::ALL)
+                    .title(format!("{} -- press any key", self.title)),
+            )
+            .direction(Direction::Horizontal)
+            .bar_set(self.theme.bar_set())
+            .bar_width(1)
+            .bar_gap(0)
+            .bar_style(Style::default().fg(Color::Yellow))
+            .value_style(Style::default().fg(Color::Black).bg(Color::Yellow))
+            .data(BarGroup::default().bars(&bars))
+    }
+
+    /// Display the histogram until a key is pressed
+    pub fn run(self, term: &mut StdTerminal, target: Rect) {
+        let _ = term.draw(|f| f.render_widget(self.chart(), target));
+        let _ = super::read_input();
+        let _ = term.clear();
+    }
+}
@@ -1,21 +1,23 @@
 use ratatui::{
     prelude::Rect,
     style::{Color, Style},
-    widgets::{Block, Borders},
+    widgets::Borders,
 };
 use tui_textarea::{Input, Key, TextArea};
 
-use crate::inspect::StdTerminal;
+use crate::inspect::{theme::Theme, StdTerminal};
 
 pub struct NumberInput<'a> {
     title: String,
     input: TextArea<'a>,
+    theme: Theme,
 }
 impl NumberInput<'_> {
-    pub fn new(title: &str) -> Self {
+    pub fn new(title: &str, theme: Theme) -> Self {
         NumberInput {
             title: title.to_owned(),
             input: TextArea::default(),
+            theme,
         }
     }
     fn validate(&mut self) -> Result<isize, std::num::ParseIntError> {
@@ -23,14 +25,16 @@ impl NumberInput<'_> {
         if let Err(ref err) = r {
             self.input.set_style(Style::default().fg(Color::LightRed));
             self.input.set_block(
-                Block::default()
+                self.theme
+                    .block()
                     .borders(Borders::ALL)
                     .title(format!("{} ERROR: {}", &self.title, err)),
             );
         } else {
             self.input.set_style(Style::default().fg(Color::LightGreen));
             self.input.set_block(
-                Block::default()
+                self.theme
+                    .block()
                     .borders(Borders::ALL)
                     .title(self.title.clone()),
             );
@@ -46,7 +50,7 @@ impl NumberInput<'_> {
                 f.render_widget(self.input.widget(), target);
             });
 
-            match crossterm::event::read().unwrap().into() {
+            match super::read_input() {
                 Input {
                     key: Key::Enter, ..
                 } => {
@@ -1,22 +1,24 @@
 use ratatui::{
     prelude::Rect,
     style::{Color, Style},
-    widgets::{Block, Borders},
+    widgets::Borders,
 };
 use regex_lite::Regex;
 use tui_textarea::{CursorMove, Input, Key, TextArea};
 
-use crate::inspect::StdTerminal;
+use crate::inspect::{theme::Theme, StdTerminal};
 
 pub struct RegexpInput<'a> {
     title: String,
     input: TextArea<'a>,
+    theme: Theme,
 }
 impl RegexpInput<'_> {
-    pub fn new(title: &str, content: String) -> Self {
+    pub fn new(title: &str, content: String, theme: Theme) -> Self {
         let mut r = RegexpInput {
             title: title.to_owned(),
             input: TextArea::from([content]),
+            theme,
         };
         r.input.move_cursor(CursorMove::End);
         r
@@ -31,14 +33,16 @@ impl RegexpInput<'_> {
         if let Err(ref err) = r {
             self.input.set_style(Style::default().fg(Color::LightRed));
             self.input.set_block(
-                Block::default()
+                self.theme
+                    .block()
                     .borders(Borders::ALL)
                     .title(format!("{} ERROR: {}", &self.title, err)),
             );
         } else {
             self.input.set_style(Style::default().fg(Color::LightGreen));
             self.input.set_block(
-                Block::default()
+                self.theme
+                    .block()
                     .borders(Borders::ALL)
                     .title(self.title.clone()),
             );
@@ -54,7 +58,7 @@ impl RegexpInput<'_> {
                 f.render_widget(self.input.widget(), target);
             });
 
-            match crossterm::event::read().unwrap().into() {
+            match super::read_input() {
                 Input {
                     key: Key::Enter, ..
                 } => {
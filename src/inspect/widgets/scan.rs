@@ -3,13 +3,14 @@ use crate::{
     compiler::ColumnRef,
     inspect::{
         forth::{self, Node},
+        theme::Theme,
         StdTerminal,
     },
 };
 use ratatui::{
     prelude::Rect,
     style::{Color, Style},
-    widgets::{Block, Borders},
+    widgets::Borders,
 };
 use std::collections::HashMap;
 use tui_textarea::{CursorMove, Input, Key, TextArea};
@@ -18,13 +19,20 @@ pub struct ScanInput<'a> {
     module: String,
     columns: &'a HashMap<String, ColumnRef>,
     input: TextArea<'a>,
+    theme: Theme,
 }
 impl<'a> ScanInput<'a> {
-    pub fn new(module: &str, content: &str, columns: &'a HashMap<String, ColumnRef>) -> Self {
+    pub fn new(
+        module: &str,
+        content: &str,
+        columns: &'a HashMap<String, ColumnRef>,
+        theme: Theme,
+    ) -> Self {
         let mut r = ScanInput {
             module: module.to_owned(),
             columns,
             input: TextArea::from([content]),
+            theme,
         };
         r.input.move_cursor(CursorMove::End);
         r
@@ -37,7 +45,8 @@ impl<'a> ScanInput<'a> {
             Err(err) => {
                 self.input.set_style(Style::default().fg(Color::LightRed));
                 self.input.set_block(
-                    Block::default()
+                    self.theme
+                        .block()
                         .borders(Borders::ALL)
                         .title(format!("ERROR: {}", err)),
                 );
@@ -45,7 +54,8 @@ impl<'a> ScanInput<'a> {
             Ok(node) => {
                 self.input.set_style(Style::default().fg(Color::LightGreen));
                 self.input.set_block(
-                    Block::default()
+                    self.theme
+                        .block()
                         .borders(Borders::ALL)
                         .title(node.to_string()),
                 );
@@ -68,7 +78,7 @@ impl<'a> ScanInput<'a> {
                 f.render_widget(self.input.widget(), target);
             });
 
-            match crossterm::event::read().unwrap().into() {
+            match super::read_input() {
                 Input {
                     key: Key::Enter, ..
                 } => {
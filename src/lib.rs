@@ -1,8 +1,22 @@
+//! Corset compiles constraint systems, expands traces against them and
+//! checks the result.
+//!
+//! The stable entry points of the library are:
+//!  - [`compile`] and [`load`], building a [`ConstraintSet`] respectively from
+//!    Corset sources and from the output of `corset compile`;
+//!  - [`import_trace`], filling a constraint set with a JSON or LT trace;
+//!  - [`compute`], running the computations over the imported trace;
+//!  - [`check`], checking the resulting trace against the constraints;
+//!  - [`write_trace`], writing the expanded trace back, its values encoded as
+//!    per [`ValuesEncoding`].
+//!
+//! A constraint set holds a single trace: to process several of them, keep
+//! its [`ConstraintSet::to_binary`] serialization and restore a fresh copy
+//! with [`ConstraintSet::from_bytes`] for each.
 #![allow(dead_code)]
 #[macro_use]
 extern crate pest_derive;
 use anyhow::*;
-use compiler::ConstraintSet;
 use errno::{set_errno, Errno};
 use libc::c_char;
 use log::*;
@@ -13,25 +27,58 @@ use std::{
 };
 use transformer::{AutoConstraint, ExpansionLevel};
 
-use crate::{
-    column::{Computation, Value, ValueBacking},
-    compiler::EvalSettings,
-};
+use crate::{column::ValueBacking, compiler::EvalSettings};
 
 mod check;
+mod checksum;
 mod column;
 mod compiler;
 mod compute;
 mod constants;
 mod dag;
 mod errors;
+#[cfg(feature = "exporters")]
+pub mod exporters;
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
 mod import;
+mod input;
+mod packed;
+pub mod plugin;
 mod pretty;
+mod provenance;
+#[cfg(feature = "python")]
+mod python;
+mod run_stats;
 mod structs;
 mod transformer;
 mod utils;
+pub mod version;
+
+pub use check::{CheckConfig, ReportStyle};
+pub use column::{Column, Computation, RegisterRef, Value};
+pub use compiler::{
+    builder::ConstraintSetBuilder, pil::from_pil, ColumnRef, CompileSettings, Constraint,
+    ConstraintSet, Domain, Intrinsic, Kind, Magma, Node, ShiftWindow,
+};
+pub use compute::{import_trace, write_trace};
+pub use import::ValuesEncoding;
+pub use structs::Handle;
 
 pub(crate) static IS_NATIVE: RwLock<bool> = RwLock::new(true);
+/// If set, the maximal amount of memory, in bytes, that trace columns may use
+pub(crate) static MEMORY_BUDGET: RwLock<Option<usize>> = RwLock::new(None);
+/// How the modules of the imported traces are padded
+pub(crate) static PADDING: RwLock<compute::Padding> = RwLock::new(compute::Padding::new());
+/// If set, the time allotted to the computation and checking of a trace, and
+/// the instant when it started
+pub(crate) static DEADLINE: RwLock<Option<(std::time::Duration, std::time::Instant)>> =
+    RwLock::new(None);
+/// Set when the user asked, e.g. with Ctrl-C, for the current operation to
+/// stop at the next cancellation point
+pub(crate) static CANCELLED: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
 
 type Corset = ConstraintSet;
 
@@ -209,20 +256,47 @@ fn make_corset(mut constraints: ConstraintSet) -> Result<Corset> {
     Ok(constraints)
 }
 
-fn _corset_from_file(zkevmfile: &str) -> Result<Corset> {
-    info!("Loading `{}`", &zkevmfile);
-    let constraints = ron::from_str(
-        &std::fs::read_to_string(zkevmfile)
-            .with_context(|| anyhow!("while reading `{}`", zkevmfile))?,
-    )
-    .with_context(|| anyhow!("while parsing `{}`", zkevmfile))?;
+/// Compile `sources`, pairs of a name and of some Corset code, into a
+/// constraint set ready to be filled with a trace; the parts of the standard
+/// library used by the sources are included unless `no_stdlib` is set.
+pub fn compile(
+    sources: &[(String, String)],
+    settings: &CompileSettings,
+    no_stdlib: bool,
+) -> Result<ConstraintSet> {
+    use compiler::parser::stdlib::{prune_stdlib, STDLIB};
+
+    let mut sources = compiler::parser::include::resolve_includes(sources)?;
+    if !no_stdlib {
+        let stdlib = prune_stdlib(STDLIB, &sources).unwrap_or_else(|_| STDLIB.to_owned());
+        sources.insert(0, ("stdlib".to_string(), stdlib));
+    }
+    make_corset(compiler::make(&sources, settings)?.1)
+}
+
+/// Load the constraint set compiled by `corset compile` in `path`, ready to
+/// be filled with a trace
+pub fn load(path: &str) -> Result<ConstraintSet> {
+    info!("Loading `{}`", &path);
+    let constraints = ConstraintSet::from_reader(input::reader(path)?, path)?;
     make_corset(constraints)
 }
 
-fn _corset_from_str(zkevmstr: &str) -> Result<Corset> {
-    let constraints =
-        ron::from_str(zkevmstr).with_context(|| anyhow!("while parsing the provided zkEVM"))?;
+/// Run all the computations over the trace imported in `cs` by
+/// [`import_trace`]; a column that is neither imported nor computed is an
+/// error if `fail_on_missing` is set, and is only logged otherwise.
+pub fn compute(cs: &mut ConstraintSet, fail_on_missing: bool) -> Result<()> {
+    compute::compute(cs, fail_on_missing)
+}
 
+/// Check all the constraints of `cs` against its computed trace, reporting
+/// the failures as set in `config`
+pub fn check(cs: &ConstraintSet, config: CheckConfig) -> Result<()> {
+    check::check(cs, &None, &[], config)
+}
+
+fn _corset_from_str(zkevmstr: &str) -> Result<Corset> {
+    let constraints = ConstraintSet::from_bytes(zkevmstr.as_bytes(), "the provided zkEVM")?;
     make_corset(constraints)
 }
 
@@ -249,7 +323,7 @@ fn _compute_trace_from_str(
 #[no_mangle]
 pub extern "C" fn corset_from_file(zkevmfile: *const c_char) -> *mut Corset {
     let zkevmfile = cstr_to_string(zkevmfile);
-    match _corset_from_file(zkevmfile) {
+    match load(zkevmfile) {
         Result::Ok(constraints) => {
             set_errno(Errno(0));
             Box::into_raw(Box::new(constraints))
@@ -278,25 +352,35 @@ pub extern "C" fn corset_from_string(zkevmstr: *const c_char) -> *mut Corset {
     }
 }
 
-fn _trace_check(corset: &mut ConstraintSet, tracefile: &str, fail_on_missing: bool) -> Result<()> {
+/// Expand `tracefile` and check it against `corset`, reporting the failures as
+/// set in `config`.
+pub fn check_trace(
+    corset: &mut ConstraintSet,
+    tracefile: &str,
+    fail_on_missing: bool,
+    config: CheckConfig,
+) -> Result<()> {
     compute::compute_trace(tracefile, corset, fail_on_missing)
         .with_context(|| format!("while expanding `{}`", tracefile))?;
 
-    check::check(
+    check(corset, config).with_context(|| format!("while checking `{}`", tracefile))?;
+    info!("{}: SUCCESS", tracefile);
+
+    Ok(())
+}
+
+fn _trace_check(corset: &mut ConstraintSet, tracefile: &str, fail_on_missing: bool) -> Result<()> {
+    check_trace(
         corset,
-        &None,
-        &[],
-        check::DebugSettings::new()
+        tracefile,
+        fail_on_missing,
+        CheckConfig::new()
             .unclutter(false)
             .dim(true)
             .continue_on_error(false)
-            .report(false)
+            .report(ReportStyle::Silent)
             .full_trace(false),
     )
-    .with_context(|| format!("while checking `{}`", tracefile))?;
-    info!("{}: SUCCESS", tracefile);
-
-    Ok(())
 }
 
 #[no_mangle]
@@ -416,6 +500,9 @@ pub extern "C" fn trace_compute_from_string(
     }
 }
 
+/// # Safety
+/// `trace` must either be null or have been obtained from one of the
+/// `trace_compute_*` functions, and must not be used after this call.
 #[no_mangle]
 pub unsafe extern "C" fn trace_free(trace: *mut Trace) {
     if !trace.is_null() {
@@ -0,0 +1,733 @@
+//! A Language Server Protocol server, talking JSON-RPC over stdio, so that
+//! editors may report the errors and lints of the constraint set being edited,
+//! jump to the definition of the symbols it uses, and show their type when
+//! hovered.
+//!
+//! The sources given on the command line are recompiled, along with the open
+//! documents shadowing their on-disk version, every time a document is opened
+//! or modified; definitions and hovers are then resolved against the symbol
+//! tables of the last successful compilation.
+use anyhow::*;
+use either::Either;
+use log::*;
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    check::strip_colors,
+    compiler::{
+        self,
+        parser::{Ast, AstNode, LinCol, Token},
+        tables::Scope,
+        CompileSettings, Expression, MAIN_MODULE,
+    },
+    structs::Handle,
+    SourceLoader,
+};
+
+/// The name given by the compiler to the embedded standard library
+const STDLIB: &str = "stdlib";
+
+/// The JSON-RPC error code of an unknown method
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Read a message from `r`, or `None` once the client closed it
+pub(crate) fn read_message(r: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if r.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = Some(
+                    value
+                        .trim()
+                        .parse::<usize>()
+                        .with_context(|| anyhow!("invalid header `{}`", header))?,
+                );
+            }
+        }
+    }
+    let length = length.ok_or_else(|| anyhow!("message without Content-Length"))?;
+    let mut content = vec![0; length];
+    r.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .context("while decoding a message")
+}
+
+/// Write `message` to `w`, framed as expected by LSP clients
+pub(crate) fn write_message(w: &mut impl Write, message: &Value) -> Result<()> {
+    let content = serde_json::to_string(message)?;
+    write!(w, "Content-Length: {}\r\n\r\n{}", content.len(), content)?;
+    w.flush()?;
+    Ok(())
+}
+
+/// The path of a `file://` URI
+pub(crate) fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut path = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        if encoded[i] == b'%' && i + 2 < encoded.len() {
+            let hex = std::str::from_utf8(&encoded[i + 1..i + 3]).ok()?;
+            if let Result::Ok(c) = u8::from_str_radix(hex, 16) {
+                path.push(c);
+                i += 3;
+                continue;
+            }
+        }
+        path.push(encoded[i]);
+        i += 1;
+    }
+    String::from_utf8(path).ok().map(PathBuf::from)
+}
+
+/// The `file://` URI of `path`
+pub(crate) fn path_to_uri(path: &Path) -> String {
+    let mut r = String::from("file://");
+    for b in path.to_string_lossy().bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+            r.push(b as char);
+        } else {
+            r.push_str(&format!("%{:02X}", b));
+        }
+    }
+    r
+}
+
+/// The name under which a source is compiled, i.e. its canonical path if it
+/// exists
+fn source_name(path: &Path) -> String {
+    std::fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_owned())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Whether `c` may be part of a symbol
+fn is_symbol_char(c: char) -> bool {
+    !c.is_whitespace() && !"()[]{};:\"".contains(c)
+}
+
+/// The symbol spanning the 0-based `character` of the 0-based `line` of
+/// `text`, if any
+pub(crate) fn symbol_at(text: &str, line: usize, character: usize) -> Option<String> {
+    let line = text.lines().nth(line)?.chars().collect::<Vec<_>>();
+    let at = if line.get(character).copied().map(is_symbol_char) == Some(true) {
+        character
+    } else if character > 0 && line.get(character - 1).copied().map(is_symbol_char) == Some(true) {
+        // the cursor may stand right after the symbol
+        character - 1
+    } else {
+        return None;
+    };
+    let start = (0..=at)
+        .rev()
+        .take_while(|&i| is_symbol_char(line[i]))
+        .last()?;
+    let end = (at..line.len())
+        .take_while(|&i| is_symbol_char(line[i]))
+        .last()?;
+    Some(line[start..=end].iter().collect())
+}
+
+/// The 0-based position of an error, extracted from its message
+pub(crate) fn locate(message: &str) -> Option<(usize, Option<usize>)> {
+    lazy_static::lazy_static! {
+        static ref PEST: regex_lite::Regex = regex_lite::Regex::new(r"--> (\d+):(\d+)").unwrap();
+        static ref AT_LINE: regex_lite::Regex = regex_lite::Regex::new(r"at line (\d+)").unwrap();
+    }
+    if let Some(c) = PEST.captures(message) {
+        let line = c[1].parse::<usize>().ok()?;
+        let column = c[2].parse::<usize>().ok()?;
+        Some((line.saturating_sub(1), Some(column.saturating_sub(1))))
+    } else {
+        let c = AT_LINE.captures(message)?;
+        Some((c[1].parse::<usize>().ok()?.saturating_sub(1), None))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Severity {
+    Error = 1,
+    Warning = 2,
+}
+
+/// A problem reported in one of the sources
+#[derive(Debug, Clone)]
+pub(crate) struct Diagnostic {
+    pub file: String,
+    /// the 0-based line of the problem
+    pub line: usize,
+    /// the 0-based column of the problem, if known; the whole line is
+    /// reported otherwise
+    pub character: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+impl Diagnostic {
+    fn to_json(&self, text: Option<&str>) -> Value {
+        let line_length = text
+            .and_then(|t| t.lines().nth(self.line))
+            .map(|l| l.chars().count())
+            .unwrap_or(0);
+        let start = self.character.unwrap_or(0);
+        json!({
+            "range": {
+                "start": { "line": self.line, "character": start },
+                "end": { "line": self.line, "character": line_length.max(start + 1) },
+            },
+            "severity": self.severity as u8,
+            "source": "corset",
+            "message": self.message,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DefinitionKind {
+    Column,
+    Function,
+    Alias,
+    Constant,
+    Perspective,
+}
+
+/// Where a symbol is defined in the sources
+#[derive(Debug, Clone)]
+pub(crate) struct Definition {
+    pub kind: DefinitionKind,
+    pub module: String,
+    pub perspective: Option<String>,
+    pub name: String,
+    pub file: String,
+    /// the 1-based position of the definition, as given by the parser
+    pub lc: LinCol,
+    /// the code of the definition
+    pub src: String,
+}
+
+/// List the symbols defined at the top level of `ast`, parsed from `file`
+pub(crate) fn definitions(file: &str, ast: &Ast) -> Vec<Definition> {
+    let mut r = Vec::new();
+    let mut module = MAIN_MODULE.to_owned();
+    let push = |r: &mut Vec<Definition>,
+                kind,
+                module: &str,
+                perspective: Option<&String>,
+                name: &str,
+                node: &AstNode| {
+        r.push(Definition {
+            kind,
+            module: module.to_owned(),
+            perspective: perspective.cloned(),
+            name: name.to_owned(),
+            file: file.to_owned(),
+            lc: node.lc,
+            src: node.src.clone(),
+        })
+    };
+    let columns = |r: &mut Vec<Definition>,
+                   module: &str,
+                   perspective: Option<&String>,
+                   columns: &[AstNode]| {
+        for c in columns {
+            if let Token::DefColumn { name, .. } | Token::DefArrayColumn { name, .. } = &c.class {
+                push(r, DefinitionKind::Column, module, perspective, name, c);
+            }
+        }
+    };
+
+    for e in ast.exprs.iter() {
+        match &e.class {
+            Token::DefModule(name, _) => module = name.to_owned(),
+            Token::DefColumns(cs) => columns(&mut r, &module, None, cs),
+            Token::DefPerspective {
+                name, columns: cs, ..
+            } => {
+                push(&mut r, DefinitionKind::Perspective, &module, None, name, e);
+                columns(&mut r, &module, Some(name), cs);
+            }
+            Token::Defun { name, .. } | Token::Defpurefun { name, .. } => {
+                push(&mut r, DefinitionKind::Function, &module, None, name, e)
+            }
+            Token::DefAliases(aliases) => {
+                for a in aliases {
+                    if let Token::DefAlias(from, _) = &a.class {
+                        push(&mut r, DefinitionKind::Alias, &module, None, from, a);
+                    }
+                }
+            }
+            Token::DefunAlias(from, _) => {
+                push(&mut r, DefinitionKind::Alias, &module, None, from, e)
+            }
+            Token::DefConsts(consts) => {
+                for (name, value) in consts {
+                    push(&mut r, DefinitionKind::Constant, &module, None, name, value);
+                }
+            }
+            Token::DefInterleaving { target, .. } => push(
+                &mut r,
+                DefinitionKind::Column,
+                &module,
+                None,
+                &target.name,
+                e,
+            ),
+            Token::DefPermutation { to, .. } => {
+                for target in to {
+                    push(
+                        &mut r,
+                        DefinitionKind::Column,
+                        &module,
+                        None,
+                        &target.name,
+                        e,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    r
+}
+
+/// The module in which the 0-based `line` of `ast` stands
+pub(crate) fn module_at(ast: &Ast, line: usize) -> String {
+    ast.exprs
+        .iter()
+        .take_while(|e| e.lc.0 <= line + 1)
+        .filter_map(|e| match &e.class {
+            Token::DefModule(name, _) => Some(name.to_owned()),
+            _ => None,
+        })
+        .last()
+        .unwrap_or_else(|| MAIN_MODULE.to_owned())
+}
+
+/// What a symbol resolves to in the symbol tables
+enum Resolved {
+    Column { handle: Handle, t: compiler::Type },
+    Function(Handle),
+    Constant(String),
+}
+
+/// The outcome of the last successful compilation
+pub(crate) struct Analysis {
+    scope: Scope,
+    asts: Vec<(String, Ast)>,
+    definitions: Vec<Definition>,
+}
+impl Analysis {
+    fn resolve(&self, module: &str, symbol: &str) -> Option<Resolved> {
+        let mut scope = self.scope.clone();
+        if module != MAIN_MODULE {
+            scope = scope.switch_to_module(module).ok()?;
+        }
+        match scope.resolve_symbol(symbol).map(|n| (n.e().clone(), n.t())) {
+            Result::Ok((
+                Expression::Column { handle, .. }
+                | Expression::ArrayColumn { handle, .. }
+                | Expression::ExoColumn { handle, .. },
+                t,
+            )) => Some(Resolved::Column {
+                handle: handle.as_handle().clone(),
+                t,
+            }),
+            Result::Ok((Expression::Const(value), _)) => {
+                Some(Resolved::Constant(value.to_string()))
+            }
+            _ => scope
+                .resolve_function(symbol)
+                .ok()
+                .map(|f| Resolved::Function(f.handle)),
+        }
+    }
+
+    /// The definition of `symbol`, once resolved in `module`
+    fn definition(&self, module: &str, symbol: &str) -> Option<(&Definition, Resolved)> {
+        let resolved = self.resolve(module, symbol)?;
+        let find = |kind: DefinitionKind, module: &str, name: &str| {
+            self.definitions
+                .iter()
+                .filter(|d| d.kind == kind && d.module == module && d.name == name)
+                .collect::<Vec<_>>()
+        };
+        let definition = match &resolved {
+            Resolved::Column { handle, .. } => {
+                let candidates = find(DefinitionKind::Column, &handle.module, &handle.name);
+                candidates
+                    .iter()
+                    .find(|d| d.perspective == handle.perspective)
+                    .or(candidates.first())
+                    .copied()
+            }
+            Resolved::Function(handle) => {
+                find(DefinitionKind::Function, &handle.module, &handle.name)
+                    .first()
+                    .copied()
+            }
+            Resolved::Constant(_) => find(DefinitionKind::Constant, module, symbol)
+                .first()
+                .or(find(DefinitionKind::Constant, MAIN_MODULE, symbol).first())
+                .copied(),
+        }?;
+        Some((definition, resolved))
+    }
+}
+
+/// The state of the language server
+pub(crate) struct Server {
+    /// the sources making up the constraint set, as given on the command line
+    sources: Vec<String>,
+    no_stdlib: bool,
+    /// the content of the documents open in the editor, by source name
+    documents: BTreeMap<String, String>,
+    /// the files currently holding diagnostics in the editor
+    published: BTreeSet<String>,
+    analysis: Option<Analysis>,
+    shutdown: bool,
+}
+impl Server {
+    pub(crate) fn new(sources: &[String], no_stdlib: bool) -> Server {
+        Server {
+            sources: sources.to_vec(),
+            no_stdlib,
+            documents: Default::default(),
+            published: Default::default(),
+            analysis: None,
+            shutdown: false,
+        }
+    }
+
+    /// The sources to compile, the open documents shadowing their on-disk
+    /// version
+    fn workspace(&self) -> Result<Vec<(String, String)>> {
+        let mut builder = SourceLoader::from_sources(self.no_stdlib, false);
+        for source in self.sources.iter() {
+            builder.add_source(source)?;
+        }
+        let Either::Left(sources) = &builder.source else {
+            unreachable!()
+        };
+        let mut sources = sources
+            .iter()
+            .map(|(name, content)| (source_name(Path::new(name)), content.to_owned()))
+            .collect::<Vec<_>>();
+        for (name, text) in self.documents.iter() {
+            match sources.iter_mut().find(|s| &s.0 == name) {
+                Some(source) => source.1 = text.to_owned(),
+                None => sources.push((name.to_owned(), text.to_owned())),
+            }
+        }
+        builder.prepare_sources(&sources)
+    }
+
+    /// Compile the workspace, keeping its symbol tables if it succeeds
+    pub(crate) fn analyze(&mut self) -> Vec<Diagnostic> {
+        let settings = CompileSettings::default();
+        let sources = match self.workspace() {
+            Result::Ok(sources) => sources,
+            Err(e) => return vec![self.blame(&[], &e)],
+        };
+        let names = sources.iter().map(|s| s.0.as_str()).collect::<Vec<_>>();
+        let (scope, asts) = match compiler::parser::parse(&sources, &settings) {
+            Result::Ok(r) => r,
+            Err(e) => return vec![self.blame(&names, &e)],
+        };
+
+        let mut r = Vec::new();
+        for (name, ast) in asts.iter() {
+            for e in compiler::generator::pass(ast, scope.clone(), &settings)
+                .into_iter()
+                .filter_map(Result::err)
+            {
+                r.push(self.blame(&[name.as_str()], &e));
+            }
+            if name == STDLIB {
+                continue;
+            }
+            match compiler::parser::lints::pass(ast, scope.clone(), &settings) {
+                Result::Ok(lints) => r.extend(lints.into_iter().map(|lint| {
+                    let lc = lint.lc();
+                    let message = strip_colors(&lint.to_string());
+                    Diagnostic {
+                        file: name.to_owned(),
+                        line: lc.0.saturating_sub(1),
+                        character: Some(lc.1.saturating_sub(1)),
+                        severity: Severity::Warning,
+                        // the position is already conveyed by the diagnostic
+                        message: message
+                            .split_once(": ")
+                            .map(|(_, m)| m.to_owned())
+                            .unwrap_or(message),
+                    }
+                })),
+                Err(e) => r.push(self.blame(&[name.as_str()], &e)),
+            }
+        }
+
+        let definitions = asts
+            .iter()
+            .flat_map(|(name, ast)| definitions(name, ast))
+            .collect();
+        self.analysis = Some(Analysis {
+            scope,
+            asts,
+            definitions,
+        });
+        r
+    }
+
+    /// Attribute `e` to the source among `names` it mentions, defaulting to
+    /// the first open document
+    fn blame(&self, names: &[&str], e: &Error) -> Diagnostic {
+        let message = strip_colors(&format!("{:#}", e));
+        let file = names
+            .iter()
+            .filter(|n| **n != STDLIB)
+            .find(|n| {
+                names.len() == 1
+                    || message.contains(&format!("`{}`", n))
+                    || message.contains(&format!("compiling {}", n))
+            })
+            .map(|n| n.to_string())
+            .or_else(|| self.documents.keys().next().cloned())
+            .unwrap_or_default();
+        let (line, character) = locate(&message).unwrap_or((0, None));
+        Diagnostic {
+            file,
+            line,
+            character,
+            severity: Severity::Error,
+            message,
+        }
+    }
+
+    /// The notifications publishing `diagnostics`, and clearing those
+    /// previously published for now-clean files
+    fn publish(&mut self, diagnostics: Vec<Diagnostic>) -> Vec<Value> {
+        let mut by_file = BTreeMap::<String, Vec<Value>>::new();
+        for file in self.published.iter() {
+            by_file.insert(file.to_owned(), Vec::new());
+        }
+        for d in diagnostics
+            .iter()
+            .filter(|d| Path::new(&d.file).is_absolute())
+        {
+            let text = self
+                .documents
+                .get(&d.file)
+                .cloned()
+                .or_else(|| std::fs::read_to_string(&d.file).ok());
+            by_file
+                .entry(d.file.to_owned())
+                .or_default()
+                .push(d.to_json(text.as_deref()));
+        }
+        self.published = by_file
+            .iter()
+            .filter(|(_, ds)| !ds.is_empty())
+            .map(|(file, _)| file.to_owned())
+            .collect();
+        by_file
+            .into_iter()
+            .map(|(file, ds)| {
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": { "uri": path_to_uri(Path::new(&file)), "diagnostics": ds },
+                })
+            })
+            .collect()
+    }
+
+    /// The document, module and symbol pointed to by the parameters of a
+    /// positional request
+    fn pointed(&self, params: &Value) -> Option<(&Analysis, String, String)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let name = source_name(&uri_to_path(uri)?);
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+        let text = self
+            .documents
+            .get(&name)
+            .cloned()
+            .or_else(|| std::fs::read_to_string(&name).ok())?;
+        let symbol = symbol_at(&text, line, character)?;
+        let analysis = self.analysis.as_ref()?;
+        let module = analysis
+            .asts
+            .iter()
+            .find(|(n, _)| n == &name)
+            .map(|(_, ast)| module_at(ast, line))
+            .unwrap_or_else(|| MAIN_MODULE.to_owned());
+        Some((analysis, module, symbol))
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let Some((analysis, module, symbol)) = self.pointed(params) else {
+            return Value::Null;
+        };
+        match analysis.definition(&module, &symbol) {
+            Some((d, _)) if d.file != STDLIB => {
+                let position = json!({ "line": d.lc.0.saturating_sub(1), "character": d.lc.1.saturating_sub(1) });
+                json!({
+                    "uri": path_to_uri(Path::new(&d.file)),
+                    "range": { "start": position, "end": position },
+                })
+            }
+            _ => Value::Null,
+        }
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let Some((analysis, module, symbol)) = self.pointed(params) else {
+            return Value::Null;
+        };
+        let definition = analysis.definition(&module, &symbol);
+        let (definition, resolved) = match definition {
+            Some((d, r)) => (Some(d), r),
+            None => match analysis.resolve(&module, &symbol) {
+                Some(r) => (None, r),
+                None => return Value::Null,
+            },
+        };
+        let module_of = |m: &str| {
+            if m == MAIN_MODULE {
+                "the root module".to_owned()
+            } else {
+                format!("module `{}`", m)
+            }
+        };
+        let value = match resolved {
+            Resolved::Column { handle, t } => format!(
+                "`{}` : `{}`\n\ncolumn of {}{}",
+                handle.name,
+                t,
+                module_of(&handle.module),
+                handle
+                    .perspective
+                    .as_ref()
+                    .map(|p| format!(", perspective `{}`", p))
+                    .unwrap_or_default()
+            ),
+            Resolved::Function(handle) => format!(
+                "{}function of {}",
+                definition
+                    .and_then(|d| d.src.lines().next())
+                    .map(|l| format!("```lisp\n{}\n```\n\n", l))
+                    .unwrap_or_default(),
+                module_of(&handle.module)
+            ),
+            Resolved::Constant(value) => format!(
+                "`{}` = `{}`\n\nconstant of {}",
+                symbol,
+                value,
+                module_of(definition.map(|d| d.module.as_str()).unwrap_or(&module))
+            ),
+        };
+        json!({ "contents": { "kind": "markdown", "value": value } })
+    }
+
+    /// Process a message from the client, returning the messages to send
+    /// back
+    pub(crate) fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let reply = |result: Value| {
+            vec![json!({ "jsonrpc": "2.0", "id": message["id"], "result": result })]
+        };
+        let document = || {
+            params["textDocument"]["uri"]
+                .as_str()
+                .and_then(uri_to_path)
+                .map(|p| source_name(&p))
+        };
+
+        match method {
+            "initialize" => reply(json!({
+                "capabilities": {
+                    // the whole document is sent on every change
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                },
+                "serverInfo": { "name": "corset", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                reply(Value::Null)
+            }
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                let text = if method == "textDocument/didOpen" {
+                    params["textDocument"]["text"].as_str()
+                } else {
+                    params["contentChanges"]
+                        .as_array()
+                        .and_then(|changes| changes.last())
+                        .and_then(|change| change["text"].as_str())
+                };
+                if let (Some(name), Some(text)) = (document(), text) {
+                    self.documents.insert(name, text.to_owned());
+                    let diagnostics = self.analyze();
+                    self.publish(diagnostics)
+                } else {
+                    Vec::new()
+                }
+            }
+            "textDocument/didClose" => {
+                if let Some(name) = document() {
+                    self.documents.remove(&name);
+                }
+                let diagnostics = self.analyze();
+                self.publish(diagnostics)
+            }
+            "textDocument/definition" => reply(self.definition(params)),
+            "textDocument/hover" => reply(self.hover(params)),
+            _ if message.get("id").is_some() => vec![json!({
+                "jsonrpc": "2.0",
+                "id": message["id"],
+                "error": { "code": METHOD_NOT_FOUND, "message": format!("unsupported method `{}`", method) },
+            })],
+            // other notifications are of no interest
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Serve the LSP requests sent on STDIN until the client exits
+pub fn serve(sources: &[String], no_stdlib: bool) -> Result<()> {
+    if sources.iter().any(|s| s == crate::input::STDIN) {
+        bail!("the language server talks over STDIN, which can not be a source")
+    }
+    let mut server = Server::new(sources, no_stdlib);
+    let mut stdin = std::io::stdin().lock();
+    let mut stdout = std::io::stdout().lock();
+    info!("Language server listening on STDIN");
+    while let Some(message) = read_message(&mut stdin)? {
+        if message["method"] == "exit" {
+            return if server.shutdown {
+                Ok(())
+            } else {
+                bail!("the client exited without shutting the server down")
+            };
+        }
+        for answer in server.handle(&message) {
+            write_message(&mut stdout, &answer)?;
+        }
+    }
+    Ok(())
+}
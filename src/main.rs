@@ -2,51 +2,84 @@
 #[macro_use]
 extern crate pest_derive;
 use anyhow::*;
+use compiler::complexity::{self, ComplexityLimits};
 use compiler::parser::Ast;
 use compiler::ConstraintSet;
 use either::Either;
 use log::*;
+use num_bigint::BigInt;
 use owo_colors::OwoColorize;
 use std::sync::RwLock;
 use std::{
-    io::{Read, Write},
-    path::Path,
+    collections::HashMap,
+    io::{IsTerminal, Read, Write},
+    path::{Path, PathBuf},
 };
-use serde::{Serialize};
-use serde_json::{Value};
 use transformer::{AutoConstraint, ExpansionLevel};
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 
+#[cfg(feature = "exporters")]
+mod bundle;
+mod cache;
 mod check;
+mod checksum;
 mod column;
 mod compiler;
 mod compute;
 mod constants;
+#[cfg(unix)]
+mod daemon;
 mod dag;
+mod diff;
+mod doctor;
 mod errors;
+mod explain;
 mod exporters;
 mod formatter;
 mod import;
+mod input;
 #[cfg(feature = "inspector")]
 mod inspect;
+mod lsp;
+mod manifest;
+mod packed;
+mod plugin;
 mod pretty;
+mod provenance;
+mod run_stats;
+mod scaffold;
+mod stats;
 mod structs;
 #[cfg(test)]
 mod tests;
 mod transformer;
 mod utils;
+mod version;
+mod watch;
 
 pub(crate) static IS_NATIVE: RwLock<bool> = RwLock::new(false);
+/// If set, the maximal amount of memory, in bytes, that trace columns may use
+pub(crate) static MEMORY_BUDGET: RwLock<Option<usize>> = RwLock::new(None);
+/// How the modules of the imported traces are padded
+pub(crate) static PADDING: RwLock<compute::Padding> = RwLock::new(compute::Padding::new());
+/// If set, the time allotted to the computation and checking of a trace, and
+/// the instant when it started
+pub(crate) static DEADLINE: RwLock<Option<(std::time::Duration, std::time::Instant)>> =
+    RwLock::new(None);
+/// Set when the user asked, e.g. with Ctrl-C, for the current operation to
+/// stop at the next cancellation point
+pub(crate) static CANCELLED: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
 
 #[derive(Parser)]
-#[command(author, version = concat!(clap::crate_version!(), " ", std::env!("GIT_HASH"), " ", std::env!("SIMD_ENABLED")), propagate_version = true)]
+#[command(author, version = concat!(clap::crate_version!(), " ", std::env!("GIT_HASH")), disable_version_flag = true)]
 pub struct Args {
     #[clap(flatten)]
     verbose: clap_verbosity_flag::Verbosity,
 
     #[arg(
-        help = "Either a file or a string containing the Corset code to process",
+        help = "Either a file or a string containing the Corset code to process, a compiled constraint set (.bin, possibly compressed or given as an HTTP(S) URL), or a PIL description (.pil)",
         global = true
     )]
     source: Vec<String>,
@@ -57,9 +90,22 @@ pub struct Args {
     #[arg(long="auto-constraints", value_parser=["sorts", "nhood"], value_delimiter=',', global=true)]
     auto_constraints: Vec<String>,
 
-    #[arg(long = "debug", help = "Compile code in debug mode", global = true)]
+    #[arg(
+        long = "debug",
+        help = "Compile code in debug mode",
+        overrides_with = "no_debug",
+        global = true
+    )]
     debug: bool,
 
+    #[arg(
+        long = "no-debug",
+        help = "do not compile code in debug mode, even if the manifest asks for it",
+        overrides_with = "debug",
+        global = true
+    )]
+    no_debug: bool,
+
     #[arg(
         short = 't',
         long = "threads",
@@ -73,15 +119,244 @@ pub struct Args {
         long = "native",
         short = 'N',
         help = "execute computations in target Galois field",
+        overrides_with = "no_native",
         global = true
     )]
     native_arithmetic: bool,
 
-    #[arg(long = "no-stdlib")]
+    #[arg(
+        long = "no-native",
+        help = "do not execute computations in target Galois field, even if the manifest asks for it",
+        overrides_with = "native_arithmetic",
+        global = true
+    )]
+    no_native: bool,
+
+    #[arg(
+        long = "memory-budget",
+        help = "fail early if the trace columns use more memory than this, in MB or with a K/M/G suffix; binary traces and computed columns are checked before being allocated, other traces once imported",
+        value_parser = utils::parse_size,
+        global = true
+    )]
+    memory_budget: Option<usize>,
+
+    #[arg(
+        long = "max-nodes",
+        help = "fail if an expanded constraint expression has more nodes than this",
+        global = true
+    )]
+    max_nodes: Option<usize>,
+
+    #[arg(
+        long = "max-degree",
+        help = "fail if an expanded constraint expression has a higher degree than this",
+        global = true
+    )]
+    max_degree: Option<usize>,
+
+    #[arg(
+        long = "promotion-gap",
+        help = "warn when an arithmetic operation mixes operands whose widths differ by more than this many bits, unless they are explicitly `cast`",
+        value_name = "BITS",
+        global = true
+    )]
+    promotion_gap: Option<usize>,
+
+    #[arg(
+        long = "padding",
+        help = "pad the modules of the imported traces, spilling included, to the next power of two (pow2), to N or 2^k rows, or not (none); as STRATEGY for all modules, or MODULE=STRATEGY for a single one",
+        value_delimiter = ',',
+        value_name = "[MODULE=]STRATEGY",
+        global = true
+    )]
+    padding: Vec<String>,
+
+    #[arg(
+        long = "timeout",
+        help = "abort the computation and checking of a trace after this long, in seconds or with a s/m/h suffix; in check-loop, every block gets this long",
+        value_parser = utils::parse_duration,
+        global = true
+    )]
+    timeout: Option<std::time::Duration>,
+
+    #[arg(
+        long = "computed-in-trace",
+        help = "what to do with the values that a trace provides for computed columns [default: trust]",
+        value_enum,
+        global = true
+    )]
+    computed_in_trace: Option<compute::ComputedInTrace>,
+
+    #[arg(
+        long = "order-from",
+        help = "keep the registers in the order they have in this compiled constraint set, new registers coming last",
+        global = true
+    )]
+    order_from: Option<String>,
+
+    #[arg(
+        long = "assume",
+        help = "override the value of a constant, e.g. `--assume FORK=3` or `--assume hub.SIZE=0x100`",
+        value_parser = utils::parse_assumption,
+        value_name = "NAME=VALUE",
+        global = true
+    )]
+    assume: Vec<(String, BigInt)>,
+
+    #[arg(
+        long = "no-stdlib",
+        help = "do not include the standard library",
+        overrides_with = "stdlib"
+    )]
     no_stdlib: bool,
 
+    #[arg(
+        long = "stdlib",
+        help = "include the standard library, even if the manifest excludes it",
+        overrides_with = "no_stdlib"
+    )]
+    stdlib: bool,
+
+    #[arg(
+        long = "no-cache",
+        help = "always compile the sources, instead of reusing a previous compilation of the same sources",
+        global = true
+    )]
+    no_cache: bool,
+
+    #[arg(
+        long = "cache-dir",
+        help = "where to cache the compiled constraint sets; defaults to `corset` in the user cache directory",
+        global = true
+    )]
+    cache_dir: Option<String>,
+
+    #[arg(
+        long = "manifest",
+        help = "the manifest to read the sources and settings from; defaults to `corset.toml` in the current directory, if any",
+        global = true
+    )]
+    manifest: Option<String>,
+
+    #[arg(
+        long = "stats-json",
+        help = "write a JSON summary of the invocation to this file: hashes of the inputs, rows per module, time per phase and result",
+        value_name = "FILE",
+        global = true
+    )]
+    stats_json: Option<String>,
+
+    #[cfg(feature = "exporters")]
+    #[arg(
+        long = "changed-only",
+        help = "list on stdout the files actually regenerated by an exporter; the files whose content did not change are never rewritten",
+        global = true
+    )]
+    changed_only: bool,
+
+    #[arg(
+        long = "print-stdlib",
+        help = "print the embedded standard library, and its SHA-256 on stderr",
+        exclusive = true
+    )]
+    print_stdlib: bool,
+
+    #[arg(short = 'V', long = "version", help = "Print version")]
+    version: bool,
+
+    #[arg(
+        long = "json",
+        requires = "version",
+        help = "print the version, along with the build information, as JSON"
+    )]
+    version_json: bool,
+
+    /// always set, but for `--print-stdlib` and `--version`
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
+}
+
+/// The case conventions of the identifiers generated by the exporters
+#[cfg(feature = "exporters")]
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum MangleCase {
+    Snake,
+    ScreamingSnake,
+    Camel,
+    Pascal,
+}
+
+#[cfg(feature = "exporters")]
+#[derive(clap::Args)]
+struct ManglingArgs {
+    #[arg(
+        long = "mangle-separator",
+        help = "what separates the module of a symbol from its name in the generated identifiers"
+    )]
+    separator: Option<String>,
+
+    #[arg(
+        long = "mangle-case",
+        value_enum,
+        help = "the case convention of the generated identifiers"
+    )]
+    case: Option<MangleCase>,
+
+    #[arg(
+        long = "mangle-escaped",
+        help = "do not prefix the symbols of the root module, and escape the punctuation of the symbols by its code point in the generated identifiers"
+    )]
+    escaped: bool,
+}
+#[cfg(feature = "exporters")]
+impl ManglingArgs {
+    fn mangling(&self) -> exporters::Mangling {
+        use convert_case::Case;
+
+        let default = exporters::Mangling::default();
+        exporters::Mangling {
+            separator: self.separator.clone().unwrap_or(default.separator),
+            case: self.case.map(|c| match c {
+                MangleCase::Snake => Case::Snake,
+                MangleCase::ScreamingSnake => Case::ScreamingSnake,
+                MangleCase::Camel => Case::Camel,
+                MangleCase::Pascal => Case::Pascal,
+            }),
+            scheme: if self.escaped {
+                structs::MangleScheme::Escaped
+            } else {
+                structs::MangleScheme::Legacy
+            },
+        }
+    }
+}
+
+/// The formats the columns may be listed in
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ColumnsFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+/// How the Solidity exporter lays out the table columns
+#[cfg(feature = "exporters")]
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum SolidityPacking {
+    /// a function returning each column as a fixed-size array
+    Array,
+    /// each column tightly packed in a `bytes` constant, read element-wise
+    Bytes,
+}
+
+/// In which order the check loop picks the blocks to process
+#[cfg(feature = "postgres")]
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum BlockOrder {
+    /// the smallest payloads first, to process as many blocks as possible
+    Size,
+    /// by increasing id, i.e. by height for blocks identified by their number
+    Id,
 }
 
 #[derive(Subcommand)]
@@ -103,12 +378,78 @@ enum Commands {
             help = "where to render the columns"
         )]
         filename: Option<String>,
+
+        #[arg(
+            long = "modules",
+            value_delimiter = ',',
+            help = "only export these modules, along with the modules they look up into"
+        )]
+        modules: Vec<String>,
+
+        #[command(flatten)]
+        mangling: ManglingArgs,
     },
     #[cfg(feature = "exporters")]
     /// Produce a WizardIOP constraint system
     WizardIOP {
         #[arg(short = 'o', long = "out", help = "where to render the constraints")]
         out_filename: Option<String>,
+
+        #[arg(
+            long = "test-vectors",
+            requires = "out_filename",
+            help = "generate, alongside the constraints, a Go test file checking their evaluation on this trace"
+        )]
+        test_vectors: Option<String>,
+
+        #[arg(
+            long = "samples",
+            default_value_t = 8,
+            help = "how many rows of the trace to sample per constraint for the test vectors"
+        )]
+        samples: usize,
+
+        #[arg(
+            long = "modules",
+            value_delimiter = ',',
+            help = "only export these modules, along with the modules they look up into"
+        )]
+        modules: Vec<String>,
+
+        #[command(flatten)]
+        mangling: ManglingArgs,
+    },
+    #[cfg(feature = "exporters")]
+    /// Produce an arkworks circuit enforcing the constraints
+    Rust {
+        #[arg(short = 'o', long = "out", help = "where to render the circuit")]
+        out_filename: Option<String>,
+
+        #[arg(
+            long = "modules",
+            value_delimiter = ',',
+            help = "only export these modules, along with the modules they look up into"
+        )]
+        modules: Vec<String>,
+
+        #[command(flatten)]
+        mangling: ManglingArgs,
+    },
+    #[cfg(feature = "exporters")]
+    /// Produce a circom template enforcing the constraints
+    Circom {
+        #[arg(short = 'o', long = "out", help = "where to render the template")]
+        out_filename: Option<String>,
+
+        #[arg(
+            long = "modules",
+            value_delimiter = ',',
+            help = "only export these modules, along with the modules they look up into"
+        )]
+        modules: Vec<String>,
+
+        #[command(flatten)]
+        mangling: ManglingArgs,
     },
     #[cfg(feature = "exporters")]
     /// Export columns in a format usable by zkBesu
@@ -124,6 +465,59 @@ enum Commands {
         #[arg(short = 'o', long = "out", help = "where to render the columns")]
         output_file_path: Option<String>,
     },
+    #[cfg(feature = "exporters")]
+    /// Export the fully-expanded constraint set in a stable JSON format
+    Json {
+        #[arg(short = 'o', long = "out", help = "where to write the constraint set")]
+        out_filename: Option<String>,
+
+        #[arg(long = "pretty", help = "indent the generated JSON")]
+        pretty: bool,
+
+        #[arg(long = "schema", help = "print the JSON Schema of the format and exit")]
+        schema: bool,
+    },
+    #[cfg(feature = "exporters")]
+    /// Export the content of fixed or computed tables as a Solidity library
+    Solidity {
+        #[arg(
+            short = 'T',
+            long = "trace",
+            required = true,
+            help = "the trace to compute the tables from"
+        )]
+        tracefile: String,
+
+        #[arg(
+            long = "tables",
+            required = true,
+            value_delimiter = ',',
+            help = "the modules to export as tables"
+        )]
+        tables: Vec<String>,
+
+        #[arg(
+            short = 'L',
+            long = "library",
+            default_value = "CorsetTables",
+            help = "the name of the generated library"
+        )]
+        library: String,
+
+        #[arg(
+            long = "packing",
+            value_enum,
+            default_value = "array",
+            help = "how to lay out the table columns"
+        )]
+        packing: SolidityPacking,
+
+        #[arg(short = 'o', long = "out", help = "where to render the library")]
+        out_filename: Option<String>,
+
+        #[command(flatten)]
+        mangling: ManglingArgs,
+    },
     #[cfg(feature = "conflater")]
     /// Export columns in a format usable by the trace conflater
     Conflater {
@@ -140,6 +534,37 @@ enum Commands {
         )]
         constraints_filename: Option<String>,
     },
+    /// Export the constraint system with a custom exporter
+    Export {
+        #[cfg(feature = "plugins")]
+        #[arg(
+            short = 'p',
+            long = "plugin",
+            help = "load the exporters provided by this plugin executable"
+        )]
+        plugins: Vec<String>,
+
+        #[arg(long = "list", help = "list the available exporters")]
+        list: bool,
+
+        #[arg(
+            short = 'x',
+            long = "exporter",
+            required_unless_present = "list",
+            help = "the exporter to use"
+        )]
+        exporter: Option<String>,
+
+        #[arg(short = 'o', long = "out", help = "where to write the export")]
+        out_filename: Option<String>,
+
+        #[arg(
+            long = "arg",
+            allow_hyphen_values = true,
+            help = "an argument forwarded to the exporter; may be repeated"
+        )]
+        args: Vec<String>,
+    },
     /// Given a set of constraints and a trace file, fill the computed columns
     Convert {
         #[arg(
@@ -176,13 +601,140 @@ enum Commands {
         #[arg(
             short = 'o',
             long = "out",
-            help = "where to write the computed trace",
+            help = "where to write the computed trace; as a binary LT trace if it ends with `.lt`, as JSON otherwise",
             required = true
         )]
         outfile: Option<String>,
 
         #[arg(long, help = "exit on failing columns")]
         fail_on_missing: bool,
+
+        #[arg(
+            long = "verify-computations",
+            help = "ensure that the computed columns satisfy their defining properties"
+        )]
+        verify_computations: bool,
+
+        #[arg(
+            long = "checksums",
+            help = "append the checksum of every column to the computed trace"
+        )]
+        checksums: bool,
+
+        #[arg(
+            long = "base64",
+            help = "write the values of the columns as base64-encoded 32-bytes big-endian integers"
+        )]
+        base64: bool,
+
+        #[arg(
+            long = "compact",
+            conflicts_with = "base64",
+            help = "write the values of the columns as base64-encoded varints, the large ones being stored once in a per-column dictionary"
+        )]
+        compact: bool,
+
+        #[arg(
+            long = "only",
+            help = "only run the computations required by these constraints",
+            value_delimiter = ','
+        )]
+        only: Option<Vec<String>>,
+
+        #[arg(
+            long = "skip",
+            help = "skip the computations only required by these constraints",
+            value_delimiter = ','
+        )]
+        skip: Vec<String>,
+
+        #[arg(
+            long = "limit-rows",
+            help = "only import the first N rows of every module, e.g. for quick smoke tests",
+            value_name = "N"
+        )]
+        limit_rows: Option<usize>,
+
+        #[arg(
+            long = "provenance",
+            value_name = "FILE",
+            help = "write to FILE, for sampled rows of the computed columns, the input cells they have been computed from, to be displayed by `inspect --provenance`"
+        )]
+        provenance: Option<String>,
+
+        #[arg(
+            long = "provenance-every",
+            value_name = "N",
+            help = "sample one row out of N for --provenance",
+            default_value_t = 1000,
+            requires = "provenance"
+        )]
+        provenance_every: usize,
+
+        #[cfg(unix)]
+        #[arg(
+            long = "daemon",
+            value_name = "SOCKET",
+            help = "delegate to the daemon listening on SOCKET instead of loading the constraint set",
+            conflicts_with = "provenance"
+        )]
+        daemon: Option<String>,
+    },
+    /// Compare two traces column by column, reporting the columns filled in
+    /// only one of them, and the cells differing in the others
+    DiffTrace {
+        #[arg(
+            help = "the reference trace, possibly compressed; either a file, `-` for STDIN, or an HTTP(S) URL"
+        )]
+        left: String,
+
+        #[arg(help = "the trace to compare to the reference one, in the same forms")]
+        right: String,
+
+        #[arg(
+            long = "compute",
+            help = "compute the columns of the traces that have not been expanded before comparing them"
+        )]
+        compute: bool,
+
+        #[arg(long = "json", help = "print the differences as JSON")]
+        json: bool,
+    },
+    /// Ensure that the columns of a computed trace match their checksums
+    VerifyTrace {
+        #[arg(
+            short = 'T',
+            long = "trace",
+            required = true,
+            help = "the computed trace to verify"
+        )]
+        tracefile: String,
+    },
+    /// Diagnose problems in the environment and in the provided constraint
+    /// system and trace
+    Doctor {
+        #[arg(short = 'T', long = "trace", help = "a trace to validate")]
+        tracefile: Option<String>,
+    },
+    /// Serve the Language Server Protocol over stdio, reporting the errors
+    /// of the sources being edited, their definitions and their types
+    Lsp,
+    /// Draft the definition of a module from the columns it features in a
+    /// trace
+    Scaffold {
+        #[arg(
+            short = 'T',
+            long = "trace",
+            required = true,
+            help = "the trace to infer the module from"
+        )]
+        tracefile: String,
+
+        #[arg(long = "module", required = true, help = "the module to scaffold")]
+        module: String,
+
+        #[arg(short = 'o', long = "out", help = "where to write the skeleton")]
+        out: Option<String>,
     },
     /// Given a set of constraints and a filled trace, check the validity of the constraints
     Check {
@@ -211,6 +763,35 @@ enum Commands {
         #[arg(long = "skip", help = "skip these constraints", value_delimiter = ',')]
         skip: Vec<String>,
 
+        #[arg(
+            long = "limit-rows",
+            help = "only import the first N rows of every module, e.g. for quick smoke tests",
+            value_name = "N"
+        )]
+        limit_rows: Option<usize>,
+
+        #[arg(
+            long = "strict-padding",
+            help = "in these modules, fail on constraints and lookups reading beyond the spilling, into the padding",
+            value_delimiter = ',',
+            value_name = "MODULES"
+        )]
+        strict_padding: Vec<String>,
+
+        #[arg(
+            long = "strict-magma",
+            help = "fail, even if the constraints hold, on columns holding values outside of their type, listing the offending rows"
+        )]
+        strict_magma: bool,
+
+        #[arg(
+            long = "rows",
+            help = "only evaluate the constraints on these rows, e.g. 1000..2000; the result is then partial",
+            value_parser = utils::parse_row_range,
+            value_name = "A..B"
+        )]
+        rows: Option<std::ops::Range<isize>>,
+
         #[arg(
             long = "no-abort",
             help = "continue checking a constraint after it met an error"
@@ -252,6 +833,99 @@ enum Commands {
 
         #[arg(short = 'A', long = "trace-span-after", help = "")]
         trace_span_after: Option<isize>,
+
+        #[arg(
+            long = "eager",
+            help = "fill all the computed columns before checking, instead of on demand the first time a constraint needs them"
+        )]
+        eager: bool,
+
+        #[arg(
+            long = "differential",
+            help = "rather than checking the trace, report the rows where a constraint and its expanded form, as exported, disagree on whether it holds"
+        )]
+        differential: bool,
+
+        #[arg(
+            long = "partial",
+            help = "only check the constraints whose columns are all present in the trace or computable from it, listing those that could and could not be checked"
+        )]
+        partial: bool,
+
+        #[arg(
+            long = "failures",
+            value_name = "FILE",
+            help = "write the rows where the constraints fail to FILE, to be highlighted by `inspect --failures`; with --no-abort, all the failing rows are recorded"
+        )]
+        failures: Option<String>,
+
+        #[arg(
+            long = "report-json",
+            value_name = "FILE",
+            help = "write to FILE a JSON report of the violated constraints, with their failing rows, the columns they read and the values of their sub-expressions; with --no-abort, all the failing rows are reported",
+            conflicts_with = "differential"
+        )]
+        report_json: Option<String>,
+
+        #[cfg(unix)]
+        #[arg(
+            long = "daemon",
+            value_name = "SOCKET",
+            help = "delegate to the daemon listening on SOCKET instead of loading the constraint set",
+            conflicts_with_all = ["report", "full_trace", "differential", "failures", "report_json"]
+        )]
+        daemon: Option<String>,
+    },
+    /// Compile the constraints, compute and check a trace, then write the
+    /// outputs required by the prover, all in a single process
+    Pipeline {
+        #[arg(
+            short = 'T',
+            long = "trace",
+            required = true,
+            help = "the trace to compute & verify"
+        )]
+        tracefile: String,
+
+        #[arg(
+            short = 'o',
+            long = "out",
+            required = true,
+            help = "the directory where to write the compiled `constraints.bin`, the computed `trace.lt` and the WizardIOP `define.go`"
+        )]
+        out_dir: String,
+
+        #[arg(long = "skip-bin", help = "do not write the compiled constraint set")]
+        skip_bin: bool,
+
+        #[arg(long = "skip-check", help = "do not check the computed trace")]
+        skip_check: bool,
+
+        #[arg(long = "skip-trace", help = "do not write the computed trace")]
+        skip_trace: bool,
+
+        #[cfg(feature = "exporters")]
+        #[arg(
+            long = "skip-export",
+            help = "do not render the WizardIOP constraint system"
+        )]
+        skip_export: bool,
+
+        #[cfg(feature = "exporters")]
+        #[command(flatten)]
+        mangling: ManglingArgs,
+    },
+    /// Gather the compiled constraint set, its JSON rendition and schema, its
+    /// documentation, statistics and version metadata in a release directory
+    #[cfg(feature = "exporters")]
+    Bundle {
+        #[arg(
+            short = 'o',
+            long = "out",
+            required = true,
+            help = "the directory where to write the bundle and its `manifest.json`"
+        )]
+        out_dir: String,
     },
     /// Inspect a trace file
     #[cfg(feature = "inspector")]
@@ -260,7 +934,7 @@ enum Commands {
             short = 'T',
             long = "trace",
             required = true,
-            help = "the trace to inspect"
+            help = "the trace to inspect, possibly compressed; either a file, `-` for STDIN, or an HTTP(S) URL"
         )]
         tracefile: String,
 
@@ -273,6 +947,26 @@ enum Commands {
 
         #[arg(long = "high-contrast", help = "avoid low-contrast colors")]
         high_contrast: bool,
+
+        #[arg(
+            long = "tui-compat",
+            help = "only use ASCII glyphs, basic colors and simple key bindings; automatically enabled for terminals that seem to require it"
+        )]
+        tui_compat: bool,
+
+        #[arg(
+            long = "failures",
+            value_name = "FILE",
+            help = "highlight the cells involved in the failures recorded by `check --failures FILE`"
+        )]
+        failures: Option<String>,
+
+        #[arg(
+            long = "provenance",
+            value_name = "FILE",
+            help = "show the input cells of the computed cells sampled by `compute --provenance FILE`"
+        )]
+        provenance: Option<String>,
     },
     /// Display the compiled the constraint system
     Debug {
@@ -316,6 +1010,19 @@ enum Commands {
         show_spilling: bool,
         #[arg(short = 'T', long = "types", help = "display types information")]
         show_types: bool,
+        #[arg(
+            short = 'b',
+            long = "bounds",
+            help = "list the constraints whose inferred bounds approach the field size"
+        )]
+        show_bounds: bool,
+        #[arg(
+            long = "bounds-margin",
+            help = "how many bits away from the field size a bound is considered risky",
+            default_value_t = 8,
+            requires = "show_bounds"
+        )]
+        bounds_margin: usize,
         #[arg(
             long = "only",
             help = "only show these constraints",
@@ -331,16 +1038,64 @@ enum Commands {
         )]
         skip: Vec<String>,
     },
-    /// Format the given source in an idiomatic way
+    /// Display the size of every module and, given a trace, how it occupies
+    /// the tables of the lookups
+    Stats {
+        #[arg(
+            short = 'T',
+            long = "trace",
+            help = "the trace whose lookups occupancy to analyze"
+        )]
+        tracefile: Option<String>,
+    },
+    /// List the columns of a module, along with their types, registers and
+    /// padding values
+    Columns {
+        #[arg(long = "module", help = "only list the columns of this module")]
+        module: Option<String>,
+
+        #[arg(
+            long = "filter",
+            help = "only list the columns whose name matches this regular expression"
+        )]
+        filter: Option<String>,
+
+        #[arg(
+            long = "format",
+            value_enum,
+            default_value = "table",
+            help = "how to list the columns"
+        )]
+        format: ColumnsFormat,
+    },
+    /// Show a constraint as parsed, then as rewritten by every expansion pass
+    Explain {
+        #[arg(
+            long = "constraint",
+            required = true,
+            help = "the constraint to explain, either as NAME or as MODULE.NAME"
+        )]
+        constraint: String,
+    },
+    /// Format the given sources in an idiomatic way, preserving their comments
+    #[command(alias = "fmt")]
     Format {
         #[arg(
             short = 'i',
             long = "in-place",
-            help = "format the given file in-place"
+            help = "format the given files in-place"
         )]
         inplace: bool,
+
+        #[arg(
+            long,
+            conflicts_with = "inplace",
+            help = "do not write anything, but fail if any of the given files is not formatted"
+        )]
+        check: bool,
     },
-    /// Given a set of constraints, indefinitely check the traces from an SQL table
+    /// Given a set of constraints, indefinitely check the traces from an SQL table,
+    /// counting how often each constraint fails in the `constraint_stats` table
     #[cfg(feature = "postgres")]
     CheckLoop {
         #[arg(long, default_value = "localhost")]
@@ -367,6 +1122,76 @@ enum Commands {
 
         #[arg(long = "skip", help = "skip these constraints", value_delimiter = ',')]
         skip: Vec<String>,
+
+        #[arg(
+            long = "checkpoint-every",
+            default_value_t = 60,
+            help = "how often, in seconds, to save the progress of a check so that it can be resumed after a restart"
+        )]
+        checkpoint_every: u64,
+
+        #[arg(
+            long = "order",
+            value_enum,
+            default_value = "size",
+            help = "in which order to process the blocks"
+        )]
+        order: BlockOrder,
+
+        #[arg(
+            long = "progress-every",
+            default_value_t = 60,
+            help = "how often, in seconds, to log the number of blocks left, the processing rate and the ETA"
+        )]
+        progress_every: u64,
+
+        #[arg(
+            long = "json-events",
+            help = "print on stdout a JSON line per processed block, with its ID, result, duration, failed constraints count and payload size"
+        )]
+        json_events: bool,
+    },
+    /// Load the constraint set once, and serve the check and compute requests
+    /// sent to SOCKET by `check --daemon` and `compute --daemon`
+    #[cfg(unix)]
+    Daemon {
+        #[arg(
+            long = "socket",
+            required = true,
+            help = "the Unix socket to listen on"
+        )]
+        socket: String,
+    },
+    /// Check a trace, then recompile the sources and check it again every
+    /// time they or the trace are modified
+    Watch {
+        #[arg(
+            short = 'T',
+            long = "trace",
+            required = true,
+            help = "the trace to compute & verify"
+        )]
+        tracefile: String,
+
+        #[arg(
+            long = "only",
+            help = "only check these constraints",
+            value_delimiter = ','
+        )]
+        only: Option<Vec<String>>,
+
+        #[arg(long = "skip", help = "skip these constraints", value_delimiter = ',')]
+        skip: Vec<String>,
+
+        #[arg(
+            long = "limit-rows",
+            help = "only import the first N rows of every module, e.g. for quick smoke tests",
+            value_name = "N"
+        )]
+        limit_rows: Option<usize>,
+
+        #[arg(short = 'r', long = "report", help = "detail the failing constraint")]
+        report: bool,
     },
     /// Given a set of Corset files, compile them into a single file for faster later use
     Compile {
@@ -380,44 +1205,100 @@ enum Commands {
 
         #[arg(long, help = "human-readably serialize the constraint system")]
         pretty: bool,
-        
+
+        #[arg(
+            long,
+            help = "generate output in the Rusty Object Notation (RON) instead of the faster-to-load binary format"
+        )]
+        ron: bool,
+
         #[arg(long, help = "generate output as JSON instead of in the Rusty Object Notation (RON)")]
         json: bool
     },
 }
+impl Commands {
+    /// Whether this subcommand compiles its sources into a constraint set,
+    /// rather than processing the source files themselves
+    fn compiles_sources(&self) -> bool {
+        !matches!(self, Commands::Format { .. } | Commands::Lsp)
+    }
+}
 
 type SourceMapping = Vec<(String, String)>;
-struct ConstraintSetBuilder {
+struct SourceLoader {
     debug: bool,
     no_stdlib: bool,
     source: Either<SourceMapping, ConstraintSet>,
     expand_to: ExpansionLevel,
     auto_constraints: Vec<AutoConstraint>,
+    /// a constraint set whose registers order must be preserved
+    order_from: Option<ConstraintSet>,
+    /// the values overriding those of some constants
+    assumptions: HashMap<String, BigInt>,
+    /// where to look for, and store, the compilations of the sources
+    cache: Option<cache::Cache>,
+    /// the size beyond which the expanded constraints are rejected
+    complexity_limits: ComplexityLimits,
+    /// the width gap beyond which implicit promotions are reported
+    promotion_gap: Option<usize>,
+    /// what to do with the values that traces provide for computed columns
+    computed_in_trace: compute::ComputedInTrace,
 }
-impl ConstraintSetBuilder {
-    fn from_sources(no_stdlib: bool, debug: bool) -> ConstraintSetBuilder {
-        ConstraintSetBuilder {
+impl SourceLoader {
+    fn from_sources(no_stdlib: bool, debug: bool) -> SourceLoader {
+        SourceLoader {
             debug,
             no_stdlib,
             source: Either::Left(Vec::new()),
             expand_to: Default::default(),
             auto_constraints: Default::default(),
+            order_from: None,
+            assumptions: Default::default(),
+            cache: None,
+            complexity_limits: Default::default(),
+            promotion_gap: None,
+            computed_in_trace: Default::default(),
         }
     }
 
-    fn from_bin(filename: &str) -> Result<ConstraintSetBuilder> {
-        Ok(ConstraintSetBuilder {
+    fn from_bin(filename: &str) -> Result<SourceLoader> {
+        let cs = ConstraintSet::from_reader(
+            run_stats::record_input_reader(filename, input::reader(filename)?),
+            filename,
+        )?;
+        Ok(SourceLoader {
             debug: false,
             no_stdlib: false,
-            source: Either::Right(
-                ron::from_str(
-                    &std::fs::read_to_string(filename)
-                        .with_context(|| anyhow!("while reading `{}`", filename))?,
-                )
-                .with_context(|| anyhow!("while parsing `{}`", filename))?,
-            ),
+            source: Either::Right(cs),
+            expand_to: Default::default(),
+            auto_constraints: Default::default(),
+            order_from: None,
+            assumptions: Default::default(),
+            cache: None,
+            complexity_limits: Default::default(),
+            promotion_gap: None,
+            computed_in_trace: Default::default(),
+        })
+    }
+
+    fn from_pil(filename: &str) -> Result<SourceLoader> {
+        let content = std::fs::read_to_string(filename)
+            .with_context(|| anyhow!("while reading `{}`", filename))?;
+        run_stats::record_input(filename, content.as_bytes());
+        let cs = compiler::pil::from_pil(&content)
+            .with_context(|| anyhow!("while importing `{}`", filename))?;
+        Ok(SourceLoader {
+            debug: false,
+            no_stdlib: false,
+            source: Either::Right(cs),
             expand_to: Default::default(),
             auto_constraints: Default::default(),
+            order_from: None,
+            assumptions: Default::default(),
+            cache: None,
+            complexity_limits: Default::default(),
+            promotion_gap: None,
+            computed_in_trace: Default::default(),
         })
     }
 
@@ -425,27 +1306,73 @@ impl ConstraintSetBuilder {
         self.expand_to = to;
     }
 
+    fn complexity_limits(&mut self, limits: ComplexityLimits) {
+        self.complexity_limits = limits;
+    }
+
+    fn promotion_gap(&mut self, gap: Option<usize>) {
+        self.promotion_gap = gap;
+    }
+
+    fn computed_in_trace(&mut self, policy: compute::ComputedInTrace) {
+        self.computed_in_trace = policy;
+    }
+
+    /// The build information to record in the compiled constraint set; it
+    /// only carries the hash of the stdlib if the stdlib has been linked
+    fn build_info(&self) -> version::BuildInfo {
+        version::BuildInfo {
+            stdlib_hash: match &self.source {
+                Either::Left(_) => (!self.no_stdlib).then(compiler::parser::stdlib::stdlib_hash),
+                Either::Right(cs) => cs.build_info.as_ref().and_then(|i| i.stdlib_hash.clone()),
+            },
+            ..version::BuildInfo::current()
+        }
+    }
+
     fn auto_constraints(&mut self, auto: &[AutoConstraint]) {
         self.auto_constraints = auto.to_vec();
     }
 
+    fn assume(&mut self, assumptions: &[(String, BigInt)]) {
+        self.assumptions = assumptions.iter().cloned().collect();
+    }
+
+    fn cache(&mut self, cache: Option<cache::Cache>) {
+        self.cache = cache;
+    }
+
+    fn compile_settings(&self) -> compiler::CompileSettings {
+        compiler::CompileSettings {
+            debug: self.debug,
+            assumptions: self.assumptions.clone(),
+            promotion_gap: self.promotion_gap,
+        }
+    }
+
+    fn order_from(&mut self, filename: &str) -> Result<()> {
+        self.order_from = Some(ConstraintSet::from_reader(
+            input::reader(filename)?,
+            filename,
+        )?);
+        Ok(())
+    }
+
     fn find_section(root: &Path, section: &str) -> Result<Option<SourceMapping>> {
         let section_file = root.join(format!("{}.lisp", section));
-        let section_str = section_file.to_str().unwrap();
+        let section_str = section_file.to_string_lossy().into_owned();
         // 1. Find a matching file
         if section_file.is_file() {
             let content = std::fs::read_to_string(&section_file)
                 .with_context(|| anyhow!("reading {}", section_str.yellow().bold()))?;
             info!("adding {}", section_str.bright_white().bold());
-            Ok(Some(vec![(
-                section_file.to_str().unwrap().to_owned(),
-                content,
-            )]))
+            run_stats::record_input(&section_str, content.as_bytes());
+            Ok(Some(vec![(section_str, content)]))
             // 2. Fail is the file is actually a directory
         } else if section_file.is_dir() {
             bail!(
                 "expected {} to be a file, not a directory",
-                section_file.to_str().unwrap().yellow().bold()
+                section_str.yellow().bold()
             )
         } else {
             // 3. Otherwise, repeat the process with a folder
@@ -453,26 +1380,36 @@ impl ConstraintSetBuilder {
             if section_dir.is_file() {
                 bail!(
                     "expected {} to be a directory, not a file",
-                    section_file.to_str().unwrap().yellow().bold()
+                    section_str.yellow().bold()
                 )
             } else if section_dir.is_dir() {
                 let mut r = Vec::new();
-                for entry in section_dir.read_dir().with_context(|| {
-                    anyhow!(
-                        "while reading {}",
-                        section_dir.to_str().unwrap().yellow().bold()
-                    )
-                })? {
-                    let p = entry?.path();
+                // directory listings are ordered differently on every
+                // platform, so they are sorted to keep the builds identical
+                let mut entries = section_dir
+                    .read_dir()
+                    .and_then(|entries| {
+                        entries
+                            .map(|entry| entry.map(|e| e.path()))
+                            .collect::<std::io::Result<Vec<_>>>()
+                    })
+                    .with_context(|| {
+                        anyhow!(
+                            "while reading {}",
+                            section_dir.display().to_string().yellow().bold()
+                        )
+                    })?;
+                entries.sort();
+                for p in entries {
                     if p.extension()
-                        .map(|ext| ["lisp", "corset"].contains(&ext.to_str().unwrap()))
-                        .unwrap_or(false)
+                        .is_some_and(|ext| ext == "lisp" || ext == "corset")
                     {
-                        info!("adding {}", p.to_str().unwrap().bright_white().bold());
-                        let content = std::fs::read_to_string(&p).with_context(|| {
-                            anyhow!("reading {}", section_file.to_str().unwrap().yellow().bold())
-                        })?;
-                        r.push((p.file_name().unwrap().to_str().unwrap().to_owned(), content))
+                        let p_str = p.to_string_lossy().into_owned();
+                        info!("adding {}", p_str.bright_white().bold());
+                        let content = std::fs::read_to_string(&p)
+                            .with_context(|| anyhow!("reading {}", p_str.yellow().bold()))?;
+                        run_stats::record_input(&p_str, content.as_bytes());
+                        r.push((p_str, content))
                     }
                 }
                 Ok(Some(r))
@@ -487,7 +1424,7 @@ impl ConstraintSetBuilder {
         let mut columns = Self::find_section(dir, "columns")?.ok_or_else(|| {
             anyhow!(
                 "no columns found in {}",
-                dir.to_str().unwrap().yellow().bold()
+                dir.display().to_string().yellow().bold()
             )
         })?;
         sources.append(&mut columns);
@@ -495,7 +1432,7 @@ impl ConstraintSetBuilder {
         let mut constraints = Self::find_section(dir, "constraints")?.ok_or_else(|| {
             anyhow!(
                 "no constraints found in {}",
-                dir.to_str().unwrap().yellow().bold()
+                dir.display().to_string().yellow().bold()
             )
         })?;
         sources.append(&mut constraints);
@@ -523,68 +1460,331 @@ impl ConstraintSetBuilder {
             if as_path.is_dir() {
                 sources.append(&mut Self::parse_dir(as_path)?);
             } else if as_path.is_file() {
-                sources.push((
-                    src.to_string(),
-                    std::fs::read_to_string(src)
-                        .with_context(|| anyhow!("reading {}", src.yellow().bold()))?,
-                ));
+                let content = std::fs::read_to_string(src)
+                    .with_context(|| anyhow!("reading {}", src.yellow().bold()))?;
+                run_stats::record_input(src, content.as_bytes());
+                sources.push((src.to_string(), content));
             } else if src == "-" {
                 let mut buffer = String::new();
                 std::io::stdin().read_to_string(&mut buffer)?;
+                run_stats::record_input("STDIN", buffer.as_bytes());
                 sources.push(("STDIN".to_string(), buffer));
             } else {
+                run_stats::record_input("Immediate expression", src.as_bytes());
                 sources.push(("Immediate expression".to_string(), src.into()));
             }
             Ok(())
         } else {
-            bail!("unable to push source to ConstraintSetBuilder built from compiled ConstraintSet")
+            bail!("unable to push source to SourceLoader built from compiled ConstraintSet")
         }
     }
 
     /// Pre-process the sources before compilation:
-    ///   - insert the stdlib if it is enabled
-    fn prepare_sources(&self, sources: &[(String, String)]) -> Vec<(String, String)> {
-        let mut sources = sources.to_vec();
+    ///   - expand the `include` directives they contain;
+    ///   - insert the parts of the stdlib used by the sources if it is enabled
+    fn prepare_sources(&self, sources: &[(String, String)]) -> Result<Vec<(String, String)>> {
+        use compiler::parser::stdlib::STDLIB;
+
+        let mut sources = compiler::parser::include::resolve_includes(sources)?;
         if !self.no_stdlib {
-            sources.insert(
-                0,
-                ("stdlib".to_string(), include_str!("stdlib.lisp").to_owned()),
-            );
+            // If the sources can not be parsed, the whole stdlib is included and
+            // the error will be reported by the actual parser.
+            let stdlib = compiler::parser::stdlib::prune_stdlib(STDLIB, &sources)
+                .unwrap_or_else(|_| STDLIB.to_owned());
+            sources.insert(0, ("stdlib".to_string(), stdlib));
+        }
+        Ok(sources)
+    }
+
+    /// Builds a simple AST that will be used by the formatter; as the files
+    /// are formatted as written, their inclusions are not expanded.
+    fn to_simple_ast(&self) -> Result<Vec<(String, Ast)>> {
+        match self.source.as_ref() {
+            Either::Left(sources) => compiler::parser::parse_simple_ast(sources),
+            Either::Right(_) => bail!("unable to retrieve AST from compiled CponstraintSet"),
+        }
+    }
+
+    /// Builds a standard AST that will be used for compilation
+    fn to_ast(&self) -> Result<Vec<(String, Ast)>> {
+        match self.source.as_ref() {
+            Either::Left(sources) => compiler::parser::parse_ast(&self.prepare_sources(sources)?),
+            Either::Right(_) => bail!("unable to retrieve AST from compiled CponstraintSet"),
+        }
+    }
+
+    /// Compiles the sources without any expansion, keeping their ASTs
+    /// alongside the constraint set; a compiled constraint set has no AST.
+    fn into_asts_and_constraint_set(self) -> Result<(Vec<Ast>, ConstraintSet)> {
+        let _phase = run_stats::phase("compile");
+        match self.source {
+            Either::Left(ref sources) => {
+                compiler::make(&self.prepare_sources(sources)?, &self.compile_settings())
+            }
+            Either::Right(cs) => Ok((Vec::new(), cs)),
+        }
+    }
+
+    /// Compile `sources`, reusing a previous compilation of the same sources
+    /// if the cache is enabled
+    fn compile(&self, sources: &[(String, String)]) -> Result<ConstraintSet> {
+        let sources = self.prepare_sources(sources)?;
+        let settings = self.compile_settings();
+        let Some(cache) = self.cache.as_ref() else {
+            return compiler::make(&sources, &settings).map(|r| r.1);
+        };
+
+        let key = cache::Cache::key(&sources, &settings);
+        if let Some((cs, warnings)) = cache.get(&key) {
+            info!("Reusing the cached compilation of the sources");
+            for w in warnings {
+                compiler::warning(w);
+            }
+            return Ok(cs);
+        }
+        // the unchanged modules do not have to be parsed again
+        let asts = compiler::parser::maybe_bail(
+            sources
+                .iter()
+                .map(|(name, content)| cache.parse(name, content).map(|ast| (name.clone(), ast)))
+                .collect(),
+        )?;
+        let (cs, warnings) =
+            compiler::record_warnings(|| compiler::make_from_asts(asts, &settings));
+        let cs = cs?.1;
+        if let Err(e) = cache.put(&key, &cs, &warnings) {
+            debug!("failed to cache the constraint set: {:?}", e);
+        }
+        Ok(cs)
+    }
+
+    fn into_constraint_set(self) -> Result<ConstraintSet> {
+        let _phase = run_stats::phase("compile");
+        let mut cs = match self.source {
+            Either::Left(ref sources) => self.compile(sources),
+            Either::Right(_) if !self.assumptions.is_empty() => {
+                bail!("constants can not be assumed in a compiled constraint set")
+            }
+            Either::Right(cs) => Ok(cs),
+        }?;
+
+        transformer::expand_to(&mut cs, self.expand_to, &self.auto_constraints)?;
+        if self.complexity_limits.is_set() {
+            ensure_complexity(&cs, &self.complexity_limits)?;
+        }
+        transformer::concretize(&mut cs);
+        if let Some(reference) = self.order_from.as_ref() {
+            let appended = cs.columns.pin_register_order(&reference.columns)?;
+            if appended > 0 {
+                info!("{} new registers appended", appended);
+            }
+        }
+        cs.set_computed_in_trace(self.computed_in_trace);
+        Ok(cs)
+    }
+}
+
+/// Compile the constraints checked by `check-loop`, fully expanded and with
+/// all the automatic constraints; they are serialized to be reloaded for each
+/// block, as computing a trace fills them
+#[cfg(feature = "postgres")]
+fn check_loop_constraints(mut builder: SourceLoader) -> Result<Vec<u8>> {
+    builder.expand_to(ExpansionLevel::top());
+    builder.auto_constraints(AutoConstraint::all());
+    builder.into_constraint_set()?.to_binary()
+}
+
+/// Prepare the compilation of the constraint set specified by `args`
+fn source_loader(args: &Args, command: &Commands) -> Result<SourceLoader> {
+    let mut builder = if matches!(command, Commands::Format { .. }) {
+        if let Some(bin) = args
+            .source
+            .iter()
+            .find(|s| input::extension(s) == Some("bin"))
+        {
+            bail!(
+                "expected Corset source files, found compiled constraint set `{}`",
+                bin
+            )
+        } else {
+            let mut r = SourceLoader::from_sources(args.no_stdlib, args.debug);
+            for f in args.source.iter() {
+                r.add_source(f)?;
+            }
+            r
+        }
+    } else if args.source.len() == 1 && input::extension(&args.source[0]) == Some("bin") {
+        info!("Loading `{}`", &args.source[0]);
+        SourceLoader::from_bin(&args.source[0])?
+    } else if args.source.len() == 1
+        && Path::new(&args.source[0])
+            .extension()
+            .map(|e| e == "pil")
+            .unwrap_or(false)
+    {
+        info!("Importing PIL from `{}`", &args.source[0]);
+        SourceLoader::from_pil(&args.source[0])?
+    } else {
+        info!("Parsing Corset source files...");
+        let mut r = SourceLoader::from_sources(args.no_stdlib, args.debug);
+        for f in args.source.iter() {
+            r.add_source(f)?;
+        }
+        r
+    };
+
+    builder.expand_to(args.expand.into());
+    builder.auto_constraints(&AutoConstraint::parse(&args.auto_constraints));
+    builder.complexity_limits(ComplexityLimits {
+        max_nodes: args.max_nodes,
+        max_degree: args.max_degree,
+    });
+    builder.promotion_gap(args.promotion_gap);
+    builder.computed_in_trace(args.computed_in_trace.unwrap_or_default());
+    builder.assume(&args.assume);
+    if !args.no_cache {
+        builder.cache(cache::Cache::new(args.cache_dir.as_deref()));
+    }
+    if let Some(reference) = args.order_from.as_ref() {
+        builder
+            .order_from(reference)
+            .with_context(|| anyhow!("while loading the registers order"))?;
+    }
+    Ok(builder)
+}
+
+/// Fail, suggesting where to split them, if some expressions of the
+/// constraints of `cs` exceed `limits`
+fn ensure_complexity(cs: &ConstraintSet, limits: &ComplexityLimits) -> Result<()> {
+    let offenders = complexity::complex_expressions(cs, limits);
+    if offenders.is_empty() {
+        return Ok(());
+    }
+
+    let mut msg = format!(
+        "{} constraint expressions exceed the complexity limits",
+        offenders.len()
+    );
+    for o in offenders.iter() {
+        msg.push_str(&format!(
+            "\n{}: {} nodes, degree {}",
+            o.handle.to_string().bold(),
+            o.nodes.to_string().red().bold(),
+            o.degree.to_string().red().bold()
+        ));
+        for (hint, saved) in o.hints.iter() {
+            msg.push_str(&format!(
+                "\n  consider committing {} to a column (-{} nodes)",
+                hint.pretty_with_handle(cs).bold(),
+                saved
+            ));
+        }
+    }
+    bail!(msg)
+}
+
+/// How the values of the columns of computed traces are written
+fn values_encoding(base64: bool, compact: bool) -> import::ValuesEncoding {
+    if compact {
+        import::ValuesEncoding::Compact
+    } else if base64 {
+        import::ValuesEncoding::Base64Fixed32
+    } else {
+        import::ValuesEncoding::Hex
+    }
+}
+
+/// The stages of `corset pipeline` to run on a compiled constraint set, and
+/// where to write their outputs
+struct Pipeline {
+    out_dir: PathBuf,
+    /// write the compiled constraint set
+    bin: bool,
+    /// check the computed trace
+    check: bool,
+    /// write the computed trace
+    trace: bool,
+    /// render the WizardIOP constraint system with this mangling
+    #[cfg(feature = "exporters")]
+    export: Option<exporters::Mangling>,
+}
+impl Pipeline {
+    const BIN: &'static str = "constraints.bin";
+    const TRACE: &'static str = "trace.lt";
+    const WIZARDIOP: &'static str = "define.go";
+
+    fn out(&self, name: &str) -> String {
+        self.out_dir.join(name).to_string_lossy().into_owned()
+    }
+
+    /// Run the stages on `cs`, fully expanded, and `tracefile`; a failing
+    /// check prevents the computed trace and the exports from being written.
+    fn run(&self, cs: &mut ConstraintSet, tracefile: &str) -> Result<()> {
+        std::fs::create_dir_all(&self.out_dir)
+            .with_context(|| anyhow!("while creating `{}`", self.out_dir.display()))?;
+
+        if self.bin {
+            let out = self.out(Self::BIN);
+            std::fs::write(&out, cs.to_binary()?)
+                .with_context(|| anyhow!("while writing to `{}`", out))?;
+        }
+
+        compute::compute_trace(tracefile, cs, false)
+            .with_context(|| format!("while computing from `{}`", tracefile))?;
+
+        if self.check {
+            check::check(cs, &None, &[], check::CheckConfig::new())
+                .with_context(|| format!("while checking {}", tracefile.bright_white().bold()))?;
+            info!("{}: SUCCESS", tracefile);
         }
-        sources
-    }
 
-    /// Builds a simple AST that will be used by the formatter
-    fn to_simple_ast(&self) -> Result<Vec<(String, Ast)>> {
-        match self.source.as_ref() {
-            Either::Left(sources) => {
-                compiler::parser::parse_simple_ast(&self.prepare_sources(sources))
-            }
-            Either::Right(_) => bail!("unable to retrieve AST from compiled CponstraintSet"),
+        if self.trace {
+            compute::write_trace(cs, &self.out(Self::TRACE), false, Default::default())?;
         }
-    }
 
-    /// Builds a standard AST that will be used for compilation
-    fn to_ast(&self) -> Result<Vec<(String, Ast)>> {
-        match self.source.as_ref() {
-            Either::Left(sources) => compiler::parser::parse_ast(&self.prepare_sources(sources)),
-            Either::Right(_) => bail!("unable to retrieve AST from compiled CponstraintSet"),
+        #[cfg(feature = "exporters")]
+        if let Some(mangling) = self.export.as_ref() {
+            *crate::IS_NATIVE.write().unwrap() = true;
+            transformer::concretize(cs);
+            exporters::wizardiop::render(
+                cs,
+                &Some(self.out(Self::WIZARDIOP)),
+                mangling,
+                &exporters::ModuleFilter::new(cs, &[])?,
+            )?;
         }
+        Ok(())
     }
+}
 
-    fn into_constraint_set(self) -> Result<ConstraintSet> {
-        let mut cs = match self.source {
-            Either::Left(ref sources) => compiler::make(
-                &self.prepare_sources(sources),
-                &compiler::CompileSettings { debug: self.debug },
-            )
-            .map(|r| r.1),
-            Either::Right(cs) => Ok(cs),
-        }?;
+/// The exit code of a command interrupted by Ctrl-C, as customary for SIGINT
+const EXIT_INTERRUPTED: i32 = 130;
 
-        transformer::expand_to(&mut cs, self.expand_to, &self.auto_constraints)?;
-        transformer::concretize(&mut cs);
-        Ok(cs)
+/// On a first Ctrl-C, ask the running operation to stop at its next
+/// cancellation point, so that it can report its partial results; on a second
+/// one, exit immediately.
+fn install_interrupt_handler() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if CANCELLED.swap(true, std::sync::atomic::Ordering::Relaxed) {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+        eprintln!("\ninterrupted; finishing the constraints being checked, Ctrl-C again to abort");
+    })
+    .with_context(|| "while installing the Ctrl-C handler")
+}
+
+/// Exit with [`EXIT_INTERRUPTED`] if `result` stems from a cancellation,
+/// after having written the invocation statistics
+fn exit_if_cancelled<T>(result: Result<T>, stats: &mut Option<run_stats::Concluder>) -> Result<T> {
+    match result {
+        Err(err) if utils::is_cancelled(&err) => {
+            eprintln!("Error: {:?}", err);
+            run_stats::record_result::<()>(&Err(err));
+            // exiting skips the destructors, that would write the statistics
+            stats.take();
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+        result => result,
     }
 }
 
@@ -592,92 +1792,369 @@ impl ConstraintSetBuilder {
 fn main() -> Result<()> {
     use crate::{inspect::InspectorSettings, transformer::concretize};
 
-    let args = Args::parse();
-    *crate::IS_NATIVE.write().unwrap() = args.native_arithmetic;
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches)?;
+    if args.version {
+        if args.version_json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&version::BuildInfo::current())?
+            );
+        } else {
+            print!("{}", Args::command().render_version());
+        }
+        return Ok(());
+    }
+    // `--print-stdlib` does not require any source nor subcommand
+    if args.print_stdlib {
+        eprintln!(
+            "sha256: {}",
+            compiler::parser::stdlib::stdlib_hash()
+                .bright_white()
+                .bold()
+        );
+        print!("{}", compiler::parser::stdlib::STDLIB);
+        return Ok(());
+    }
     buche::new()
         .verbosity(args.verbose.log_level_filter())
         .quiet(args.verbose.is_silent())
         .init()
         .unwrap();
+    // the statistics are written when leaving, whatever the outcome
+    let mut stats = args
+        .stats_json
+        .as_ref()
+        .zip(matches.subcommand_name())
+        .map(|(path, command)| run_stats::arm(path, command));
+    if let Some(manifest) = manifest::find(args.manifest.as_deref())? {
+        manifest.apply(&mut args, &matches)?;
+    }
+    let Some(command) = args.command.take() else {
+        Args::command()
+            .error(
+                clap::error::ErrorKind::MissingSubcommand,
+                "a subcommand is required",
+            )
+            .exit();
+    };
+    *crate::IS_NATIVE.write().unwrap() = args.native_arithmetic;
+    *crate::MEMORY_BUDGET.write().unwrap() = args.memory_budget;
+    *crate::PADDING.write().unwrap() = compute::Padding::parse(&args.padding)?;
+    utils::arm_deadline(args.timeout);
+    #[cfg(feature = "exporters")]
+    let changed_only = args.changed_only;
 
     rayon::ThreadPoolBuilder::new()
         .num_threads(args.threads)
         .build_global()
         .unwrap();
 
-    let mut builder = if matches!(args.command, Commands::Format { .. }) {
-        if args.source.len() != 1 {
-            bail!(
-                "can only format one file at a time; found {}",
-                args.source.len()
-            )
-        } else if args.source.len() == 1
-            && Path::new(&args.source[0])
-                .extension()
-                .map(|e| e == "bin")
-                .unwrap_or(false)
-        {
-            bail!("expected Corset source file, found compiled constraint set")
-        } else {
-            let mut r = ConstraintSetBuilder::from_sources(args.no_stdlib, args.debug);
-            for f in args.source.iter() {
-                r.add_source(f)?;
+    // the doctor must be able to diagnose inputs that can not be loaded
+    if let Commands::Doctor { tracefile } = &command {
+        return run_stats::recorded(doctor::doctor(
+            &args.source,
+            tracefile.as_deref(),
+            args.no_stdlib,
+        ));
+    }
+
+    // the language server compiles the edited sources itself
+    if let Commands::Lsp = &command {
+        return run_stats::recorded(lsp::serve(&args.source, args.no_stdlib));
+    }
+
+    // a scaffolded module has no constraint system yet
+    if let Commands::Scaffold {
+        tracefile,
+        module,
+        out,
+    } = &command
+    {
+        let skeleton = scaffold::scaffold_trace(tracefile, module)?;
+        return run_stats::recorded(match out {
+            Some(out) => {
+                std::fs::write(out, skeleton).with_context(|| anyhow!("while writing `{}`", out))
             }
-            r
-        }
-    } else if args.source.len() == 1
-        && Path::new(&args.source[0])
-            .extension()
-            .map(|e| e == "bin")
-            .unwrap_or(false)
+            None => {
+                print!("{}", skeleton);
+                Ok(())
+            }
+        });
+    }
+
+    #[cfg(feature = "exporters")]
+    if let Commands::Json { schema: true, .. } = &command {
+        print!("{}", exporters::json::SCHEMA);
+        run_stats::succeed();
+        return Ok(());
+    }
+
+    // the constraint set is recompiled on every modification of the sources
+    if let Commands::Watch {
+        tracefile,
+        only,
+        skip,
+        limit_rows,
+        report,
+    } = &command
     {
-        info!("Loading `{}`", &args.source[0]);
-        ConstraintSetBuilder::from_bin(&args.source[0])?
-    } else {
-        info!("Parsing Corset source files...");
-        let mut r = ConstraintSetBuilder::from_sources(args.no_stdlib, args.debug);
-        for f in args.source.iter() {
-            r.add_source(f)?;
+        return watch::watch(
+            &args.source,
+            tracefile,
+            watch::WatchSettings {
+                only: only.clone(),
+                skip: skip.clone(),
+                limit_rows: *limit_rows,
+                report: match (report, std::io::stdout().is_terminal()) {
+                    (false, _) => check::ReportStyle::Silent,
+                    (true, false) => check::ReportStyle::Plain,
+                    (true, true) => check::ReportStyle::Colored,
+                },
+                timeout: args.timeout,
+            },
+            || source_loader(&args, &command)?.into_constraint_set(),
+        );
+    }
+
+    // the daemon already holds the constraint set
+    #[cfg(unix)]
+    match &command {
+        Commands::Check {
+            daemon: Some(socket),
+            tracefile,
+            only,
+            skip,
+            limit_rows,
+            continue_on_error,
+            strict_padding,
+            strict_magma,
+            rows,
+            partial,
+            ..
+        } => {
+            run_stats::recorded(daemon::submit(
+                Path::new(socket),
+                daemon::Request::Check {
+                    trace: tracefile.clone(),
+                    only: only.clone(),
+                    skip: skip.clone(),
+                    limit_rows: *limit_rows,
+                    continue_on_error: *continue_on_error,
+                    strict_padding: strict_padding.clone(),
+                    strict_magma: *strict_magma,
+                    rows: rows.clone(),
+                    partial: *partial,
+                },
+            ))?;
+            info!("{}: SUCCESS", tracefile);
+            return Ok(());
         }
-        r
-    };
+        Commands::Compute {
+            daemon: Some(socket),
+            tracefile,
+            outfile,
+            fail_on_missing,
+            verify_computations,
+            checksums,
+            base64,
+            compact,
+            only,
+            skip,
+            limit_rows,
+            provenance,
+            ..
+        } => {
+            if provenance.is_some() {
+                bail!("--provenance can not be used with --daemon")
+            }
+            return run_stats::recorded(daemon::submit(
+                Path::new(socket),
+                daemon::Request::Compute {
+                    trace: tracefile.clone(),
+                    out: outfile.clone().unwrap(),
+                    fail_on_missing: *fail_on_missing,
+                    verify_computations: *verify_computations,
+                    checksums: *checksums,
+                    base64: *base64,
+                    compact: *compact,
+                    only: only.clone(),
+                    skip: skip.clone(),
+                    limit_rows: *limit_rows,
+                },
+            ));
+        }
+        _ => {}
+    }
 
-    builder.expand_to(args.expand.into());
-    builder.auto_constraints(&AutoConstraint::parse(&args.auto_constraints));
+    let mut builder = source_loader(&args, &command)?;
+    // the files actually written by the exporters, in order
+    #[cfg(feature = "exporters")]
+    let mut regenerated: Vec<String> = Vec::new();
 
-    match args.command {
+    match command {
         #[cfg(feature = "exporters")]
-        Commands::Go { package, filename } => {
-            exporters::zkgeth::render(
-                &builder.into_constraint_set()?,
+        Commands::Go {
+            package,
+            filename,
+            modules,
+            mangling,
+        } => {
+            let cs = builder.into_constraint_set()?;
+            let modules = exporters::ModuleFilter::new(&cs, &modules)?;
+            regenerated.extend(exporters::zkgeth::render(
+                &cs,
                 &package,
                 filename.as_ref(),
-            )?;
+                &mangling.mangling(),
+                &modules,
+            )?);
         }
         #[cfg(feature = "exporters")]
         Commands::Besu {
             package,
             output_file_path: output_path,
         } => {
-            exporters::besu::render(
+            regenerated.extend(exporters::besu::render(
                 &builder.into_constraint_set()?,
                 &package,
                 output_path.as_ref(),
-            )?;
+            )?);
+        }
+        #[cfg(feature = "exporters")]
+        Commands::Json {
+            out_filename,
+            pretty,
+            ..
+        } => {
+            builder.expand_to(ExpansionLevel::top());
+            builder.auto_constraints(AutoConstraint::all());
+            let cs = builder.into_constraint_set()?;
+            let mut out: Box<dyn Write> = if let Some(out_filename) = out_filename.as_ref() {
+                Box::new(std::io::BufWriter::new(
+                    std::fs::File::create(out_filename)
+                        .with_context(|| format!("while creating `{}`", out_filename))?,
+                ))
+            } else {
+                Box::new(std::io::stdout())
+            };
+            exporters::json::render(&cs, pretty, &mut out)?;
+            out.flush()?;
+        }
+        #[cfg(feature = "exporters")]
+        Commands::Solidity {
+            tracefile,
+            tables,
+            library,
+            packing,
+            out_filename,
+            mangling,
+        } => {
+            builder.expand_to(ExpansionLevel::top());
+            builder.auto_constraints(AutoConstraint::all());
+            let mut cs = builder.into_constraint_set()?;
+            compute::compute_trace(&tracefile, &mut cs, false)
+                .with_context(|| format!("while computing from `{}`", tracefile))?;
+            regenerated.extend(exporters::solidity::render(
+                &cs,
+                &tables,
+                &library,
+                match packing {
+                    SolidityPacking::Array => exporters::solidity::Packing::Array,
+                    SolidityPacking::Bytes => exporters::solidity::Packing::Bytes,
+                },
+                out_filename.as_ref(),
+                &mangling.mangling(),
+            )?);
         }
         #[cfg(feature = "conflater")]
         Commands::Conflater { filename } => {
-            exporters::conflater::render(&builder.to_constraint_set(), filename.as_ref())?;
+            regenerated.extend(exporters::conflater::render(
+                &builder.to_constraint_set(),
+                filename.as_ref(),
+            )?);
+        }
+        #[cfg(feature = "exporters")]
+        Commands::WizardIOP {
+            out_filename,
+            test_vectors,
+            samples,
+            modules,
+            mangling,
+        } => {
+            *crate::IS_NATIVE.write().unwrap() = true;
+            builder.expand_to(ExpansionLevel::top());
+            builder.auto_constraints(AutoConstraint::all());
+            let mut cs = builder.into_constraint_set()?;
+            concretize(&mut cs);
+            let modules = exporters::ModuleFilter::new(&cs, &modules)?;
+
+            regenerated.extend(exporters::wizardiop::render(
+                &cs,
+                &out_filename,
+                &mangling.mangling(),
+                &modules,
+            )?);
+
+            if let (Some(tracefile), Some(out_filename)) = (test_vectors, out_filename) {
+                compute::compute_trace(&tracefile, &mut cs, false)
+                    .with_context(|| format!("while expanding `{}`", tracefile))?;
+                let out = std::path::Path::new(&out_filename);
+                let test_filename = out
+                    .with_file_name(format!(
+                        "{}_test.go",
+                        out.file_stem().unwrap_or_default().to_string_lossy()
+                    ))
+                    .display()
+                    .to_string();
+                regenerated.extend(exporters::wizardiop::render_test_vectors(
+                    &cs,
+                    &tracefile,
+                    samples,
+                    &test_filename,
+                    &modules,
+                )?);
+            }
+        }
+        #[cfg(feature = "exporters")]
+        Commands::Rust {
+            out_filename,
+            modules,
+            mangling,
+        } => {
+            *crate::IS_NATIVE.write().unwrap() = true;
+            builder.expand_to(ExpansionLevel::top());
+            builder.auto_constraints(AutoConstraint::all());
+            let mut cs = builder.into_constraint_set()?;
+            concretize(&mut cs);
+            let modules = exporters::ModuleFilter::new(&cs, &modules)?;
+
+            regenerated.extend(exporters::rust::render(
+                &cs,
+                out_filename.as_ref(),
+                &mangling.mangling(),
+                &modules,
+            )?);
         }
         #[cfg(feature = "exporters")]
-        Commands::WizardIOP { out_filename } => {
+        Commands::Circom {
+            out_filename,
+            modules,
+            mangling,
+        } => {
             *crate::IS_NATIVE.write().unwrap() = true;
             builder.expand_to(ExpansionLevel::top());
             builder.auto_constraints(AutoConstraint::all());
             let mut cs = builder.into_constraint_set()?;
             concretize(&mut cs);
+            let modules = exporters::ModuleFilter::new(&cs, &modules)?;
 
-            exporters::wizardiop::render(&cs, &out_filename)?;
+            regenerated.extend(exporters::circom::render(
+                &cs,
+                out_filename.as_ref(),
+                &mangling.mangling(),
+                &modules,
+            )?);
         }
         #[cfg(feature = "exporters")]
         Commands::Latex {
@@ -693,6 +2170,38 @@ fn main() -> Result<()> {
                 constraints_filename,
             )?;
         }
+        Commands::Export {
+            #[cfg(feature = "plugins")]
+            plugins,
+            list,
+            exporter,
+            out_filename,
+            args,
+        } => {
+            #[cfg(feature = "plugins")]
+            for plugin in plugins.iter() {
+                plugin::load_plugin(plugin)?;
+            }
+            if list {
+                for (name, description) in plugin::exporters() {
+                    println!("{}\t{}", name.bold(), description);
+                }
+                run_stats::succeed();
+                return Ok(());
+            }
+
+            let cs = builder.into_constraint_set()?;
+            let mut out: Box<dyn Write> = if let Some(out_filename) = out_filename.as_ref() {
+                Box::new(std::io::BufWriter::new(
+                    std::fs::File::create(out_filename)
+                        .with_context(|| format!("while creating `{}`", out_filename))?,
+                ))
+            } else {
+                Box::new(std::io::stdout())
+            };
+            plugin::export(exporter.as_ref().unwrap(), &cs, &args, &mut out)?;
+            out.flush()?;
+        }
         Commands::Convert {
             tracefile,
             outfile,
@@ -700,29 +2209,25 @@ fn main() -> Result<()> {
             exclude,
         } => {
             let mut cs = builder.into_constraint_set()?;
-            if tracefile.ends_with("lt") {
-                import::parse_binary_trace(&tracefile, &mut cs, true)
-            } else {
-                import::parse_json_trace(&tracefile, &mut cs, true)
-            }
-            .with_context(|| format!("while computing from `{}`", tracefile))?;
+            import::parse_trace(&tracefile, &mut cs, true)
+                .with_context(|| format!("while computing from `{}`", tracefile))?;
 
             match format.as_str() {
                 "csv" => exporters::convert::to_csv(
                     &cs,
                     &exclude.unwrap_or_default(),
-                    outfile.as_ref().map(String::as_str).unwrap_or("trace.csv"),
+                    outfile.as_deref().unwrap_or("trace.csv"),
                 ),
                 "json" => exporters::convert::to_json(
                     &cs,
                     &exclude.unwrap_or_default(),
-                    outfile.as_ref().map(String::as_str).unwrap_or("trace.json"),
+                    outfile.as_deref().unwrap_or("trace.json"),
+                ),
+                "lt" => exporters::convert::to_lt(
+                    &cs,
+                    &exclude.unwrap_or_default(),
+                    outfile.as_deref().unwrap_or("trace.lt"),
                 ),
-                // "lt" => exporters::convert::to_lt(
-                //     &cs,
-                //     &exclude.unwrap_or_default(),
-                //     outfile.as_ref().map(String::as_str).unwrap_or("trace.csv"),
-                // ),
                 _ => unreachable!(),
             }?;
         }
@@ -730,22 +2235,75 @@ fn main() -> Result<()> {
             tracefile,
             outfile,
             fail_on_missing,
+            verify_computations,
+            checksums,
+            base64,
+            compact,
+            only,
+            skip,
+            limit_rows,
+            provenance,
+            provenance_every,
+            ..
         } => {
             builder.expand_to(ExpansionLevel::top());
             builder.auto_constraints(AutoConstraint::all());
             let mut cs = builder.into_constraint_set()?;
+            cs.set_row_limit(limit_rows);
 
-            compute::compute_trace(&tracefile, &mut cs, fail_on_missing)
+            if provenance.is_some() {
+                provenance::arm(provenance_every);
+            }
+            compute::compute_trace_for(&tracefile, &mut cs, fail_on_missing, &only, &skip)
                 .with_context(|| format!("while computing from `{}`", tracefile))?;
+            if let Some(provenance) = provenance.as_ref() {
+                provenance::take().save(provenance)?;
+            }
+            if verify_computations {
+                compute::verify_computations(&cs)?;
+            }
 
-            let outfile = outfile.as_ref().unwrap();
-            let mut f = std::fs::File::create(outfile)
-                .with_context(|| format!("while creating `{}`", &outfile))?;
-
-            let mut out = std::io::BufWriter::with_capacity(10_000_000, &mut f);
-            cs.write(&mut out)
-                .with_context(|| format!("while writing to `{}`", &outfile))?;
-            out.flush()?;
+            compute::write_trace(
+                &mut cs,
+                outfile.as_ref().unwrap(),
+                checksums,
+                values_encoding(base64, compact),
+            )?;
+        }
+        Commands::VerifyTrace { tracefile } => {
+            checksum::verify_trace(&tracefile)?;
+        }
+        Commands::DiffTrace {
+            left,
+            right,
+            compute,
+            json,
+        } => {
+            builder.expand_to(ExpansionLevel::top());
+            builder.auto_constraints(AutoConstraint::all());
+            // each trace is loaded in its own copy of the constraint set
+            let constraints = builder.into_constraint_set()?.to_binary()?;
+            let load = |tracefile: &str| -> Result<ConstraintSet> {
+                let mut cs = ConstraintSet::from_bytes(&constraints, tracefile)?;
+                cs.set_computed_in_trace(args.computed_in_trace.unwrap_or_default());
+                if import::is_expanded_trace(tracefile)? {
+                    cs.columns.resolve_paddings()?;
+                    import::parse_expanded_trace(tracefile, &mut cs)
+                } else if compute {
+                    compute::compute_trace(tracefile, &mut cs, false)
+                } else {
+                    compute::import_trace(tracefile, &mut cs)
+                }
+                .with_context(|| format!("while loading `{}`", tracefile))?;
+                Ok(cs)
+            };
+            let diff = diff::diff(&load(&left)?, &load(&right)?);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&diff)?);
+            } else {
+                diff.print(&left, &right);
+            }
+            diff::conclude(&diff)?;
         }
         #[cfg(feature = "postgres")]
         Commands::CheckLoop {
@@ -757,54 +2315,137 @@ fn main() -> Result<()> {
             rerun,
             only,
             skip,
+            checkpoint_every,
+            order,
+            progress_every,
+            json_events,
         } => {
-            let mut constraints = builder.to_constraint_set()?;
-            transformer::validate_nhood(&mut constraints)
-                .with_context(|| anyhow!("while creating nhood constraints"))?;
-            transformer::lower_shifts(&mut constraints);
-            transformer::expand_ifs(&mut constraints);
-            transformer::expand_constraints(&mut constraints)
-                .with_context(|| anyhow!("while expanding constraints"))?;
-            transformer::sorts(&mut constraints)
-                .with_context(|| anyhow!("while creating sorting constraints"))?;
-            transformer::expand_invs(&mut constraints)
-                .with_context(|| anyhow!("while expanding inverses"))?;
+            let constraints = check_loop_constraints(builder)?;
 
             let mut db = utils::connect_to_db(&user, &password, &host, &database)?;
+            db.batch_execute(
+                "CREATE TABLE IF NOT EXISTS checkpoints (id TEXT PRIMARY KEY, progress TEXT NOT NULL)",
+            )
+            .with_context(|| "while creating the checkpoints table")?;
+            utils::create_constraint_stats(&mut db)?;
+            // checkpoints are saved outside of the transaction locking the
+            // block, so that they survive its rollback if the worker dies
+            let checkpoints = std::sync::Arc::new(std::sync::Mutex::new(utils::connect_to_db(
+                &user, &password, &host, &database,
+            )?));
+
+            let todo = if rerun { "failed" } else { "to_corset" };
+            let ordering = match order {
+                BlockOrder::Size => "length(payload)",
+                BlockOrder::Id => "id",
+            };
+            let mut backlog =
+                utils::BacklogReporter::new(std::time::Duration::from_secs(progress_every));
 
             info!("Initiating waiting loop");
             loop {
-                let mut local_constraints = constraints.clone();
+                let mut local_constraints = ConstraintSet::from_bytes(&constraints, "check-loop")?;
+                local_constraints.set_computed_in_trace(args.computed_in_trace.unwrap_or_default());
+                if let Err(e) = backlog.maybe_report(&mut db, todo) {
+                    warn!("{:?}", e);
+                }
 
                 let mut tx = db.transaction()?;
-                let todo = if rerun { "failed" } else { "to_corset" };
                 for row in tx.query(
-                    &format!("SELECT id, status, payload FROM blocks WHERE STATUS='{}' ORDER BY length(payload) ASC LIMIT 1 FOR UPDATE SKIP LOCKED", todo),
+                    &format!("SELECT id, status, payload FROM blocks WHERE STATUS='{}' ORDER BY {} ASC LIMIT 1 FOR UPDATE SKIP LOCKED", todo, ordering),
                     &[],
                 )? {
+                    backlog.processed();
                     let id: &str = row.get(0);
                     let payload: &[u8] = row.get(2);
                     info!("Processing {}", id);
+                    let start = std::time::Instant::now();
+                    let emit = |result, failures| {
+                        if json_events {
+                            utils::BlockEvent {
+                                block: id,
+                                result,
+                                duration_ms: start.elapsed().as_millis(),
+                                failures,
+                                payload_size: payload.len(),
+                            }
+                            .emit()
+                        } else {
+                            Ok(())
+                        }
+                    };
 
-                    compute::compute_trace_str(
+                    utils::arm_deadline(args.timeout);
+                    if let Err(e) = compute::compute_trace_str(
                         payload,
                         &mut local_constraints,
                         false,
-                    )
-                        .with_context(|| format!("while expanding from {}", id))?;
+                    ) {
+                        if utils::is_deadline_exceeded(&e) {
+                            warn!("{} timed out", id);
+                            tx.execute("UPDATE blocks SET status='failed(timeout)' WHERE id=$1", &[&id])
+                                .with_context(|| "while inserting timed out back row")?;
+                            emit("timeout", 0)?;
+                            continue;
+                        }
+                        return Err(e.context(format!("while expanding from {}", id)));
+                    }
 
-                    match check::check(
+                    let progress = tx
+                        .query_opt("SELECT progress FROM checkpoints WHERE id=$1", &[&id])?
+                        .map(|row| serde_json::from_str::<check::Checkpoint>(row.get(0)))
+                        .transpose()
+                        .with_context(|| format!("while reading the checkpoint of {}", id))?
+                        .unwrap_or_default();
+                    if progress != Default::default() {
+                        info!(
+                            "Resuming {} after {} checked constraints",
+                            id,
+                            progress.done.len() + progress.failed.len()
+                        );
+                    }
+                    let checkpointer = {
+                        let checkpoints = checkpoints.clone();
+                        let id = id.to_owned();
+                        std::sync::Arc::new(check::Checkpointer::new(
+                            progress,
+                            std::time::Duration::from_secs(checkpoint_every),
+                            move |progress| {
+                                checkpoints.lock().unwrap().execute(
+                                    "INSERT INTO checkpoints (id, progress) VALUES ($1, $2) ON CONFLICT (id) DO UPDATE SET progress=EXCLUDED.progress",
+                                    &[&id, &serde_json::to_string(progress)?],
+                                )?;
+                                Ok(())
+                            },
+                        ))
+                    };
+
+                    let result = check::check(
                         &local_constraints,
                         &only,
                         &skip,
-                        args.verbose.log_level_filter() >= log::Level::Warn
-                            && std::io::stdout().is_terminal(),
-                        false,
-                        check::DebugSettings::new()
+                        check::CheckConfig::new()
                             .unclutter(true)
-                            .report(args.verbose.log_level_filter() >= log::Level::Warn)
-                    ) {
-                        Ok(_) => {
+                            // keep stdout parseable when emitting JSON events
+                            .report(if !json_events && args.verbose.log_level_filter() >= log::Level::Warn {
+                                check::ReportStyle::Plain
+                            } else {
+                                check::ReportStyle::Silent
+                            })
+                            .checkpoint(checkpointer.clone())
+                    );
+                    // a check interrupted by the deadline is not representative
+                    if !matches!(&result, Err(e) if utils::is_deadline_exceeded(e)) {
+                        utils::record_constraint_stats(
+                            &mut tx,
+                            &local_constraints,
+                            &checkpointer.progress(),
+                        )?;
+                    }
+                    let failures = checkpointer.progress().failed.len();
+                    match result {
+                        std::result::Result::Ok(_) => {
+                            emit("done", failures)?;
                             if remove {
                                 tx.execute("DELETE FROM blocks WHERE id=$1", &[&id])
                                     .with_context(|| "while inserting successful back row")?;
@@ -813,11 +2454,20 @@ fn main() -> Result<()> {
                                     .with_context(|| "while inserting failed back row")?;
                             }
                         },
+                        Err(e) if utils::is_deadline_exceeded(&e) => {
+                            warn!("{} timed out", id);
+                            emit("timeout", failures)?;
+                            tx.execute("UPDATE blocks SET status='failed(timeout)' WHERE id=$1", &[&id])
+                                .with_context(|| "while inserting timed out back row")?;
+                        },
                         Err(_) => {
+                            emit("failed", failures)?;
                             tx.execute("UPDATE blocks SET status='failed' WHERE id=$1", &[&id])
                                 .with_context(|| "while inserting failed back row")?;
                         },
                     }
+                    tx.execute("DELETE FROM checkpoints WHERE id=$1", &[&id])
+                        .with_context(|| "while removing checkpoint")?;
 
                 }
                 if let Err(e) = tx.commit() {
@@ -827,12 +2477,20 @@ fn main() -> Result<()> {
                 std::thread::sleep(std::time::Duration::from_secs(1));
             }
         }
+        Commands::Doctor { .. } => unreachable!("the doctor does not load the constraint system"),
+        Commands::Lsp => unreachable!("the language server compiles its own constraint system"),
+        Commands::Scaffold { .. } => unreachable!("scaffolding does not load a constraint system"),
+        Commands::Watch { .. } => unreachable!("the watcher compiles its own constraint system"),
         Commands::Check {
             tracefile,
             full_trace,
             report,
             only,
             skip,
+            limit_rows,
+            strict_padding,
+            strict_magma,
+            rows,
             continue_on_error,
             unclutter,
             dim,
@@ -840,54 +2498,214 @@ fn main() -> Result<()> {
             trace_span,
             trace_span_before,
             trace_span_after,
+            eager,
+            differential,
+            failures,
+            partial,
+            report_json,
+            ..
         } => {
-            if utils::is_file_empty(&tracefile)? {
+            if input::is_empty(&tracefile)? {
                 warn!("`{}` is empty, exiting", tracefile);
+                run_stats::succeed();
+                return Ok(());
+            }
+
+            if differential {
+                builder.expand_to(ExpansionLevel::None);
+                let mut reference = builder.into_constraint_set()?;
+                let mut lowered =
+                    ConstraintSet::from_bytes(&reference.to_binary()?, "the constraint set")?;
+                transformer::expand_to(&mut lowered, ExpansionLevel::top(), &[])?;
+                transformer::concretize(&mut lowered);
+                lowered.set_computed_in_trace(reference.columns.computed_in_trace);
+                install_interrupt_handler()?;
+                for cs in [&mut reference, &mut lowered] {
+                    cs.set_row_limit(limit_rows);
+                    exit_if_cancelled(
+                        compute::compute_trace(&tracefile, cs, false)
+                            .with_context(|| format!("while expanding `{}`", tracefile)),
+                        &mut stats,
+                    )?;
+                }
+                exit_if_cancelled(
+                    check::differential(&reference, &lowered, &only, &skip).with_context(|| {
+                        format!("while comparing on {}", tracefile.bright_white().bold())
+                    }),
+                    &mut stats,
+                )?;
+                run_stats::succeed();
                 return Ok(());
             }
 
             let mut cs = builder.into_constraint_set()?;
+            cs.set_row_limit(limit_rows);
+            cs.set_partial(partial);
 
-            compute::compute_trace(&tracefile, &mut cs, false)
-                .with_context(|| format!("while expanding `{}`", tracefile))?;
-            check::check(
-                &cs,
-                &only,
-                &skip,
-                check::DebugSettings::new()
-                    .unclutter(unclutter)
-                    .dim(dim)
-                    .src(with_src)
-                    .continue_on_error(continue_on_error)
-                    .report(report)
-                    .full_trace(full_trace)
-                    .context_span(trace_span)
-                    .and_context_span_before(trace_span_before)
-                    .and_context_span_after(trace_span_after),
-            )
-            .with_context(|| format!("while checking {}", tracefile.bright_white().bold()))?;
+            let settings = check::CheckConfig::new()
+                .progress(
+                    args.verbose.log_level_filter() >= log::Level::Warn
+                        && std::io::stderr().is_terminal(),
+                )
+                .unclutter(unclutter)
+                .dim(dim)
+                .src(with_src)
+                .continue_on_error(continue_on_error)
+                .report(match (report, std::io::stdout().is_terminal()) {
+                    (false, _) => check::ReportStyle::Silent,
+                    (true, false) => check::ReportStyle::Plain,
+                    (true, true) => check::ReportStyle::Colored,
+                })
+                .full_trace(full_trace)
+                .strict_padding(strict_padding)
+                .strict_magma(strict_magma)
+                .rows(rows)
+                .context_span(trace_span)
+                .and_context_span_before(trace_span_before)
+                .and_context_span_after(trace_span_after);
+            let report =
+                std::sync::Arc::new(std::sync::Mutex::new(check::FailureReport::default()));
+            let settings = if failures.is_some() || report_json.is_some() {
+                settings
+                    .record_failures(report.clone())
+                    .record_values(report_json.is_some())
+            } else {
+                settings
+            };
+            install_interrupt_handler()?;
+            // the provided computed columns can only be verified once computed
+            let result =
+                if eager || cs.columns.computed_in_trace == compute::ComputedInTrace::Verify {
+                    compute::compute_trace_for(&tracefile, &mut cs, false, &only, &skip)
+                        .with_context(|| format!("while expanding `{}`", tracefile))
+                        .and_then(|_| {
+                            let only = if partial {
+                                check::partial_selection(&cs, &only, &skip)?
+                            } else {
+                                only
+                            };
+                            check::check(&cs, &only, &skip, settings)
+                        })
+                } else {
+                    compute::import_trace_lazily(&tracefile, &mut cs, false)
+                        .with_context(|| format!("while importing `{}`", tracefile))
+                        .and_then(|_| {
+                            let only = if partial {
+                                check::partial_selection(&cs, &only, &skip)?
+                            } else {
+                                only
+                            };
+                            check::check_lazily(&mut cs, &only, &skip, settings)
+                        })
+                };
+            // the failures are saved even, and foremost, if the check failed
+            if let Some(failures) = failures.as_ref() {
+                let mut report = report.lock().unwrap();
+                // the constraints are checked in parallel
+                report.violations.sort();
+                report.save(failures)?;
+            }
+            if let Some(report_json) = report_json.as_ref() {
+                let json = report.lock().unwrap().json_report(&result);
+                std::fs::write(report_json, serde_json::to_string_pretty(&json)?)
+                    .with_context(|| anyhow!("while writing `{}`", report_json))?;
+            }
+            exit_if_cancelled(
+                result
+                    .with_context(|| format!("while checking {}", tracefile.bright_white().bold())),
+                &mut stats,
+            )?;
             info!("{}: SUCCESS", tracefile)
         }
+        Commands::Pipeline {
+            tracefile,
+            out_dir,
+            skip_bin,
+            skip_check,
+            skip_trace,
+            #[cfg(feature = "exporters")]
+            skip_export,
+            #[cfg(feature = "exporters")]
+            mangling,
+        } => {
+            builder.expand_to(ExpansionLevel::top());
+            builder.auto_constraints(AutoConstraint::all());
+            let build_info = builder.build_info();
+            let mut cs = builder.into_constraint_set()?;
+            cs.build_info = Some(build_info);
+            install_interrupt_handler()?;
+            let result = Pipeline {
+                out_dir: out_dir.into(),
+                bin: !skip_bin,
+                check: !skip_check,
+                trace: !skip_trace,
+                #[cfg(feature = "exporters")]
+                export: (!skip_export).then(|| mangling.mangling()),
+            }
+            .run(&mut cs, &tracefile);
+            exit_if_cancelled(result, &mut stats)?;
+        }
+        #[cfg(feature = "exporters")]
+        Commands::Bundle { out_dir } => {
+            builder.expand_to(ExpansionLevel::top());
+            builder.auto_constraints(AutoConstraint::all());
+            let build_info = builder.build_info();
+            let mut cs = builder.into_constraint_set()?;
+            cs.build_info = Some(build_info);
+            let manifest = bundle::bundle(&mut cs, Path::new(&out_dir))?;
+            info!(
+                "bundled {} files in `{}`",
+                manifest.files.len(),
+                out_dir.bold()
+            );
+        }
         #[cfg(feature = "inspector")]
         Commands::Inspect {
             tracefile,
             open_module,
             high_contrast,
+            tui_compat,
+            failures,
+            provenance,
         } => {
-            if utils::is_file_empty(&tracefile)? {
+            if input::is_empty(&tracefile)? {
                 warn!("`{}` is empty, exiting", tracefile);
+                run_stats::succeed();
                 return Ok(());
             }
-            let mut cs = builder.into_constraint_set()?;
-
-            compute::compute_trace(&tracefile, &mut cs, false)
-                .with_context(|| format!("while expanding `{}`", tracefile))?;
+            // traces expanded by `compute` already contain all the computed
+            // columns, and are loaded as is
+            let cs = if import::is_expanded_trace(&tracefile)? {
+                builder.expand_to(ExpansionLevel::top());
+                builder.auto_constraints(AutoConstraint::all());
+                let mut cs = builder.into_constraint_set()?;
+                cs.columns.resolve_paddings()?;
+                import::parse_expanded_trace(&tracefile, &mut cs)
+                    .with_context(|| format!("while loading `{}`", tracefile))?;
+                cs
+            } else {
+                let mut cs = builder.into_constraint_set()?;
+                compute::compute_trace(&tracefile, &mut cs, false)
+                    .with_context(|| format!("while expanding `{}`", tracefile))?;
+                cs
+            };
 
             inspect::inspect(
                 &cs,
                 InspectorSettings {
                     open_module,
                     high_contrast,
+                    compat: tui_compat,
+                    failures: failures
+                        .as_deref()
+                        .map(check::FailureReport::load)
+                        .transpose()?
+                        .unwrap_or_default(),
+                    provenance: provenance
+                        .as_deref()
+                        .map(provenance::ProvenanceReport::load)
+                        .transpose()?
+                        .unwrap_or_default(),
                 },
             )
             .with_context(|| format!("while checking {}", tracefile.bright_white().bold()))?;
@@ -902,6 +2720,8 @@ fn main() -> Result<()> {
             show_perspectives,
             show_types,
             show_spilling,
+            show_bounds,
+            bounds_margin,
             only,
             skip,
         } => {
@@ -918,46 +2738,147 @@ fn main() -> Result<()> {
                     perspectives: show_perspectives,
                     computations: show_computations,
                     spilling: show_spilling,
+                    bounds: show_bounds.then_some(bounds_margin),
                 },
                 only.as_ref(),
                 &skip,
             )?;
         }
-        Commands::Format { inplace } => {
+        Commands::Stats { tracefile } => {
+            let mut cs = builder.into_constraint_set()?;
+            let lookups = if let Some(tracefile) = tracefile {
+                compute::compute_trace(&tracefile, &mut cs, false)
+                    .with_context(|| format!("while expanding `{}`", tracefile))?;
+                Some(stats::lookup_stats(&cs)?)
+            } else {
+                None
+            };
+            stats::print(&stats::module_stats(&cs), lookups.as_deref());
+        }
+        Commands::Columns {
+            module,
+            filter,
+            format,
+        } => {
+            let cs = builder.into_constraint_set()?;
+            let filter = filter
+                .as_deref()
+                .map(regex_lite::Regex::new)
+                .transpose()
+                .with_context(|| anyhow!("invalid filter"))?;
+            exporters::debugger::list_columns(
+                &cs,
+                module.as_deref(),
+                filter.as_ref(),
+                match format {
+                    ColumnsFormat::Table => exporters::debugger::ListingFormat::Table,
+                    ColumnsFormat::Csv => exporters::debugger::ListingFormat::Csv,
+                    ColumnsFormat::Json => exporters::debugger::ListingFormat::Json,
+                },
+                &mut std::io::stdout(),
+            )?;
+        }
+        Commands::Explain { constraint } => {
+            // the normalizations are only expanded for native exporters
+            *crate::IS_NATIVE.write().unwrap() = true;
+            let auto_constraints = builder.auto_constraints.clone();
+            let (asts, cs) = builder.into_asts_and_constraint_set()?;
+            let stages = explain::explain(&asts, cs, &constraint, &auto_constraints)?;
+            explain::print(&stages);
+        }
+        Commands::Format { inplace, check } => {
             builder.no_stdlib = true;
             let asts = builder.to_simple_ast()?;
-            for (filename, ast) in asts.iter() {
+            let sources = builder.source.as_ref().left().cloned().unwrap_or_default();
+            let mut unformatted = 0;
+            for ((filename, ast), (_, original)) in asts.iter().zip(sources.iter()) {
                 let formatted = ast.format();
-                if inplace {
-                    std::fs::File::create(filename)?.write_all(formatted.as_bytes())?;
+                // never write out sources that would have lost some comments
+                let reformatted = compiler::parser::parse_simple_ast(&[(filename, &formatted)])
+                    .with_context(|| anyhow!("while re-parsing formatted `{}`", filename))?;
+                if reformatted[0].1.comments() != ast.comments() {
+                    bail!(
+                        "formatting {} would alter its comments",
+                        filename.bright_white().bold()
+                    )
+                }
+
+                if check {
+                    if formatted != *original {
+                        warn!("{} is not formatted", filename.bright_white().bold());
+                        unformatted += 1;
+                    }
+                } else if inplace {
+                    if !Path::new(filename).is_file() {
+                        bail!(
+                            "unable to format {} in place",
+                            filename.bright_white().bold()
+                        )
+                    }
+                    if formatted != *original {
+                        std::fs::File::create(filename)?.write_all(formatted.as_bytes())?;
+                        info!("formatted {}", filename.bright_white().bold());
+                    }
                 } else {
                     println!("{}", formatted);
                 }
             }
+            if unformatted > 0 {
+                bail!("{} file(s) are not formatted", unformatted)
+            }
+        }
+        #[cfg(unix)]
+        Commands::Daemon { socket } => {
+            // the deadline is shared by all the threads, and would expire for
+            // every request at once
+            if args.timeout.is_some() {
+                warn!("--timeout is ignored by the daemon");
+                utils::arm_deadline(None);
+            }
+            let daemon = daemon::Daemon::new(builder.into_constraint_set()?)?;
+            let listener = daemon::Daemon::bind(Path::new(&socket))?;
+            info!("listening on {}", socket.bold());
+            daemon.serve(listener)?;
         }
-        Commands::Compile { outfile, pretty, json } => {
-            let constraints = builder.into_constraint_set()?;
+        Commands::Compile {
+            outfile,
+            pretty,
+            ron,
+            json,
+        } => {
+            let build_info = builder.build_info();
+            let mut constraints = builder.into_constraint_set()?;
+            constraints.build_info = Some(build_info);
             std::fs::File::create(&outfile)
                 .with_context(|| format!("while creating `{}`", &outfile))?
-                .write_all(
+                .write_all(&
                     if json && cfg!(feature="json-bin") {
                         if pretty {
-                            serde_json::to_string_pretty(&constraints)?                            
+                            serde_json::to_string_pretty(&constraints)?
                         } else {
                             serde_json::to_string(&constraints)?
-                        }
+                        }.into_bytes()
                     } else if json {
                         panic!("Exporting as JSON requires the `json-bin` feature.");
                     } else if pretty {
-                        ron::ser::to_string_pretty(&constraints, ron::ser::PrettyConfig::default())?
+                        ron::ser::to_string_pretty(&constraints, ron::ser::PrettyConfig::default())?.into_bytes()
+                    } else if ron {
+                        ron::ser::to_string(&constraints)?.into_bytes()
                     } else {
-                        ron::ser::to_string(&constraints)?
-                    }
-                    .as_bytes(),
+                        constraints.to_binary()?
+                    },
                 )
                 .with_context(|| format!("while writing to `{}`", &outfile))?;
         }
     }
 
+    #[cfg(feature = "exporters")]
+    if changed_only {
+        for filename in regenerated {
+            println!("{}", filename);
+        }
+    }
+
+    run_stats::succeed();
     Ok(())
 }
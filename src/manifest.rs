@@ -0,0 +1,285 @@
+//! A `corset.toml` manifest gathers the sources and the settings of a
+//! constraint system, so that they do not have to be repeated on every
+//! invocation, e.g.:
+//!
+//! ```toml
+//! sources = ["constants.lisp", "alu/add.lisp", "alu/mul.lisp"]
+//! stdlib = true
+//! auto-constraints = ["sorts"]
+//! memory-budget = "16G"
+//! max-degree = 8
+//! padding = ["pow2", "rom=2^20"]
+//!
+//! [defines]
+//! FORK = 3
+//!
+//! [exporters]
+//! mangle-case = "snake"
+//! ```
+//!
+//! Relative paths are resolved from the directory of the manifest, and any
+//! setting given on the command line overrides the manifest; the boolean
+//! settings can be turned off with `--stdlib`, `--no-debug` and
+//! `--no-native`. The defines are only added to the sources of the
+//! subcommands compiling them, and must be numbers.
+use anyhow::*;
+use clap::{parser::ValueSource, ArgMatches};
+use log::*;
+use owo_colors::OwoColorize;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{utils, Args};
+
+/// The manifest looked for in the current directory
+pub(crate) const MANIFEST_NAME: &str = "corset.toml";
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct Manifest {
+    /// the Corset sources, as accepted on the command line
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// constants defined in the root module, e.g. to select features
+    #[serde(default)]
+    pub defines: BTreeMap<String, toml::Value>,
+    pub stdlib: Option<bool>,
+    pub debug: Option<bool>,
+    pub native: Option<bool>,
+    pub threads: Option<usize>,
+    pub expand: Option<u8>,
+    pub auto_constraints: Option<Vec<String>>,
+    pub memory_budget: Option<String>,
+    pub max_nodes: Option<usize>,
+    pub max_degree: Option<usize>,
+    pub promotion_gap: Option<usize>,
+    pub padding: Option<Vec<String>>,
+    pub timeout: Option<String>,
+    pub computed_in_trace: Option<String>,
+    #[serde(default)]
+    pub exporters: ExporterSettings,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct ExporterSettings {
+    pub mangle_separator: Option<String>,
+    pub mangle_case: Option<String>,
+}
+
+impl Manifest {
+    /// Load the manifest at `path`, resolving its sources from its directory
+    pub(crate) fn load(path: &Path) -> Result<Manifest> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| anyhow!("while reading `{}`", path.display()))?;
+        let mut manifest: Manifest = toml::from_str(&content)
+            .with_context(|| anyhow!("while parsing `{}`", path.display()))?;
+
+        let root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        for source in manifest.sources.iter_mut() {
+            let resolved: PathBuf = root.join(utils::portable_path(source));
+            if !resolved.exists() {
+                bail!(
+                    "source {} declared in `{}` not found",
+                    source.red().bold(),
+                    path.display()
+                )
+            }
+            *source = resolved.to_string_lossy().into_owned();
+        }
+        for auto in manifest.auto_constraints.iter().flatten() {
+            if !matches!(auto.as_str(), "sorts" | "nhood") {
+                bail!("unknown auto-constraint {}", auto.red().bold())
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// The source defining the constants of the manifest, if any
+    fn defines_source(&self) -> Result<Option<String>> {
+        if self.defines.is_empty() {
+            return Ok(None);
+        }
+        let defines = self
+            .defines
+            .iter()
+            .map(|(name, value)| {
+                if !is_identifier(name) {
+                    bail!("invalid define name {}", name.red().bold())
+                }
+                // the values are spliced in the source, and must thus be
+                // plain numbers
+                let value = match value {
+                    toml::Value::Integer(x) => x.to_string(),
+                    toml::Value::Boolean(b) => (*b as u8).to_string(),
+                    toml::Value::String(s) => utils::parse_assumption(&format!("{}={}", name, s))
+                        .with_context(|| anyhow!("define {} must be a number", name.red().bold()))?
+                        .1
+                        .to_string(),
+                    _ => bail!("define {} must be a number", name.red().bold()),
+                };
+                Ok(format!("{} {}", name, value))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Some(format!("(defconst {})", defines.join(" "))))
+    }
+
+    /// Fill in `args` with the settings of the manifest that have not been
+    /// set on the command line, as reported by `matches`.
+    pub(crate) fn apply(&self, args: &mut Args, matches: &ArgMatches) -> Result<()> {
+        let from_cli = |id: &str| {
+            !matches!(
+                matches.value_source(id),
+                None | Some(ValueSource::DefaultValue)
+            )
+        };
+
+        if args.source.is_empty() {
+            args.source = self.sources.clone();
+        }
+        // constants can not be added to compiled constraint sets, and are
+        // meaningless to the subcommands processing the source files
+        // themselves
+        let compiled = args.source.len() == 1
+            && Path::new(&args.source[0])
+                .extension()
+                .map(|e| e == "bin" || e == "pil")
+                .unwrap_or(false);
+        if args
+            .command
+            .as_ref()
+            .is_some_and(crate::Commands::compiles_sources)
+            && !compiled
+        {
+            if let Some(defines) = self.defines_source()? {
+                args.source.insert(0, defines);
+            }
+        }
+
+        // an explicit flag wins over the manifest, whichever way it goes
+        let switch = |on: &str, off: &str, manifest: Option<bool>| {
+            if from_cli(on) {
+                true
+            } else if from_cli(off) {
+                false
+            } else {
+                manifest.unwrap_or(false)
+            }
+        };
+        args.no_stdlib = switch("no_stdlib", "stdlib", self.stdlib.map(|s| !s));
+        args.debug = switch("debug", "no_debug", self.debug);
+        args.native_arithmetic = switch("native_arithmetic", "no_native", self.native);
+        if !from_cli("threads") {
+            if let Some(threads) = self.threads {
+                args.threads = threads;
+            }
+        }
+        if !from_cli("expand") {
+            if let Some(expand) = self.expand {
+                args.expand = expand;
+            }
+        }
+        if args.auto_constraints.is_empty() {
+            args.auto_constraints = self.auto_constraints.clone().unwrap_or_default();
+        }
+        if args.memory_budget.is_none() {
+            args.memory_budget = self
+                .memory_budget
+                .as_deref()
+                .map(utils::parse_size)
+                .transpose()
+                .context("invalid memory-budget")?;
+        }
+        if args.padding.is_empty() {
+            args.padding = self.padding.clone().unwrap_or_default();
+        }
+        if args.max_nodes.is_none() {
+            args.max_nodes = self.max_nodes;
+        }
+        if args.max_degree.is_none() {
+            args.max_degree = self.max_degree;
+        }
+        if args.promotion_gap.is_none() {
+            args.promotion_gap = self.promotion_gap;
+        }
+        if args.timeout.is_none() {
+            args.timeout = self
+                .timeout
+                .as_deref()
+                .map(utils::parse_duration)
+                .transpose()
+                .context("invalid timeout")?;
+        }
+        if args.computed_in_trace.is_none() {
+            use clap::ValueEnum;
+
+            args.computed_in_trace = self
+                .computed_in_trace
+                .as_deref()
+                .map(|p| {
+                    crate::compute::ComputedInTrace::from_str(p, true)
+                        .map_err(|_| anyhow!("unknown computed-in-trace {}", p.red().bold()))
+                })
+                .transpose()?;
+        }
+
+        #[cfg(feature = "exporters")]
+        if let Some(
+            crate::Commands::Go { mangling, .. }
+            | crate::Commands::WizardIOP { mangling, .. }
+            | crate::Commands::Rust { mangling, .. }
+            | crate::Commands::Circom { mangling, .. }
+            | crate::Commands::Pipeline { mangling, .. },
+        ) = &mut args.command
+        {
+            use clap::ValueEnum;
+
+            if mangling.separator.is_none() {
+                mangling.separator = self.exporters.mangle_separator.clone();
+            }
+            if mangling.case.is_none() {
+                mangling.case = self
+                    .exporters
+                    .mangle_case
+                    .as_deref()
+                    .map(|c| {
+                        crate::MangleCase::from_str(c, true)
+                            .map_err(|_| anyhow!("unknown mangle-case {}", c.red().bold()))
+                    })
+                    .transpose()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `name` may be spliced as is in a source as the name of a constant
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map(|c| c.is_ascii_alphabetic() || c == '_')
+        .unwrap_or(false)
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+}
+
+/// Find the manifest to use: the one given on the command line if any, or
+/// the one in the current directory
+pub(crate) fn find(explicit: Option<&str>) -> Result<Option<Manifest>> {
+    match explicit {
+        Some(path) => Manifest::load(Path::new(path)).map(Some),
+        None => {
+            let path = Path::new(MANIFEST_NAME);
+            if path.is_file() {
+                info!("using {}", MANIFEST_NAME.bright_white().bold());
+                Manifest::load(path).map(Some)
+            } else {
+                Ok(None)
+            }
+        }
+    }
+}
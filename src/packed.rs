@@ -0,0 +1,153 @@
+//! Decoding of the traces encoded in CBOR or in MessagePack, that follow the
+//! same layout as the JSON ones, but where values may be stored as binary
+//! integers rather than as strings to be parsed.
+//!
+//! Traces are imported by streaming them through the same visitors as the
+//! JSON ones; an [`Item`] tree is only built when there is no constraint set
+//! to fill, e.g. when scaffolding a module from a trace. Byte strings stand
+//! for big-endian unsigned integers, as do CBOR bignums.
+use anyhow::*;
+use num_bigint::{BigInt, Sign};
+use serde::de::{self, Deserialize, EnumAccess, IgnoredAny, MapAccess, SeqAccess, VariantAccess};
+use std::io::Read;
+
+/// The CBOR tag of negative bignums
+pub(crate) const CBOR_NEGATIVE_BIGNUM: u64 = 3;
+
+/// A decoded item
+#[derive(Debug, PartialEq)]
+pub(crate) enum Item {
+    Map(Vec<(String, Item)>),
+    Array(Vec<Item>),
+    Int(BigInt),
+    Text(String),
+    /// null, booleans, floats, extensions, ...
+    Other,
+}
+impl Item {
+    /// The JSON value equivalent to this item, integers that do not fit in 64
+    /// bits being rendered as decimal strings
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        use serde_json::Value;
+
+        match self {
+            Item::Map(kvs) => Value::Object(
+                kvs.iter()
+                    .map(|(k, v)| (k.to_owned(), v.to_json()))
+                    .collect(),
+            ),
+            Item::Array(xs) => Value::Array(xs.iter().map(Item::to_json).collect()),
+            Item::Int(i) => i64::try_from(i)
+                .map(Value::from)
+                .or_else(|_| u64::try_from(i).map(Value::from))
+                .unwrap_or_else(|_| Value::String(i.to_string())),
+            Item::Text(s) => Value::String(s.to_owned()),
+            Item::Other => Value::Null,
+        }
+    }
+}
+impl<'de> Deserialize<'de> for Item {
+    fn deserialize<D: de::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        d.deserialize_any(ItemVisitor)
+    }
+}
+
+struct ItemVisitor;
+impl<'de> de::Visitor<'de> for ItemVisitor {
+    type Value = Item;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a trace item")
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<Item, E> {
+        Result::Ok(Item::Other)
+    }
+
+    fn visit_i64<E: de::Error>(self, x: i64) -> Result<Item, E> {
+        Result::Ok(Item::Int(x.into()))
+    }
+
+    fn visit_u64<E: de::Error>(self, x: u64) -> Result<Item, E> {
+        Result::Ok(Item::Int(x.into()))
+    }
+
+    fn visit_i128<E: de::Error>(self, x: i128) -> Result<Item, E> {
+        Result::Ok(Item::Int(x.into()))
+    }
+
+    fn visit_u128<E: de::Error>(self, x: u128) -> Result<Item, E> {
+        Result::Ok(Item::Int(x.into()))
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<Item, E> {
+        Result::Ok(Item::Other)
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Item, E> {
+        Result::Ok(Item::Text(s.to_owned()))
+    }
+
+    fn visit_bytes<E: de::Error>(self, bs: &[u8]) -> Result<Item, E> {
+        Result::Ok(Item::Int(BigInt::from_bytes_be(Sign::Plus, bs)))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Item, E> {
+        Result::Ok(Item::Other)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Item, E> {
+        Result::Ok(Item::Other)
+    }
+
+    // MessagePack extensions
+    fn visit_newtype_struct<D: de::Deserializer<'de>>(self, d: D) -> Result<Item, D::Error> {
+        d.deserialize_ignored_any(IgnoredAny)?;
+        Result::Ok(Item::Other)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Item, A::Error> {
+        let mut xs = Vec::new();
+        while let Some(x) = seq.next_element()? {
+            xs.push(x);
+        }
+        Result::Ok(Item::Array(xs))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Item, A::Error> {
+        let mut kvs = Vec::new();
+        while let Some(kv) = map.next_entry()? {
+            kvs.push(kv);
+        }
+        Result::Ok(Item::Map(kvs))
+    }
+
+    // CBOR tags, e.g. the self-describing one or bignums
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Item, A::Error> {
+        let (tag, item) = data.variant::<u64>()?;
+        match (tag, item.newtype_variant()?) {
+            (CBOR_NEGATIVE_BIGNUM, Item::Int(x)) => Result::Ok(Item::Int(-1 - x)),
+            (_, item) => Result::Ok(item),
+        }
+    }
+}
+
+/// Ensure that nothing follows the decoded item in `r`
+pub(crate) fn ensure_exhausted<R: Read>(mut r: R) -> Result<()> {
+    if r.read(&mut [0])? > 0 {
+        bail!("trailing data after the trace")
+    }
+    Ok(())
+}
+
+pub(crate) fn decode_cbor<R: Read>(mut r: R) -> Result<Item> {
+    let item = ciborium::de::from_reader(&mut r).map_err(|e| anyhow!("invalid CBOR: {}", e))?;
+    ensure_exhausted(r)?;
+    Ok(item)
+}
+
+pub(crate) fn decode_msgpack<R: Read>(mut r: R) -> Result<Item> {
+    let item = rmp_serde::from_read(&mut r).context("invalid MessagePack")?;
+    ensure_exhausted(r)?;
+    Ok(item)
+}
@@ -0,0 +1,202 @@
+//! Extension point for third-party exporters.
+//!
+//! An exporter is any type implementing [`Exporter`]. Exporters can either be
+//! [registered](register) by programs embedding corset as a library, or be
+//! shipped as a standalone executable loaded at runtime by [`load_plugin`]
+//! when corset is built with the `plugins` feature. In the latter case, the
+//! plugin communicates with corset through its command line and standard
+//! streams only, so that it does not depend on the Rust ABI nor on the
+//! version of corset it has been built against.
+use anyhow::*;
+use itertools::Itertools;
+use owo_colors::OwoColorize;
+use std::{
+    io::Write,
+    sync::{Arc, RwLock},
+};
+
+use crate::compiler::ConstraintSet;
+
+/// The version of the plugin interface; it must be bumped whenever the way
+/// plugins are described or invoked changes.
+pub const PLUGIN_API_VERSION: u32 = 2;
+
+/// A custom output format for a constraint set
+pub trait Exporter: Send + Sync {
+    /// The name under which this exporter is invoked
+    fn name(&self) -> &str;
+
+    /// A one-line description of what this exporter produces
+    fn description(&self) -> &str {
+        ""
+    }
+
+    /// Export `cs` into `out`, using the exporter-specific arguments `args`
+    fn export(&self, cs: &ConstraintSet, args: &[String], out: &mut dyn Write) -> Result<()>;
+}
+
+static EXPORTERS: RwLock<Vec<Arc<dyn Exporter>>> = RwLock::new(Vec::new());
+
+/// Make `exporter` available under its name; an already registered exporter
+/// with the same name is replaced.
+pub fn register(exporter: Arc<dyn Exporter>) {
+    let mut exporters = EXPORTERS.write().unwrap();
+    exporters.retain(|e| e.name() != exporter.name());
+    exporters.push(exporter);
+}
+
+/// The names and descriptions of all the registered exporters
+pub fn exporters() -> Vec<(String, String)> {
+    EXPORTERS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|e| (e.name().to_owned(), e.description().to_owned()))
+        .sorted()
+        .collect()
+}
+
+/// Export `cs` into `out` with the registered exporter `name`
+pub fn export(name: &str, cs: &ConstraintSet, args: &[String], out: &mut dyn Write) -> Result<()> {
+    let _phase = crate::run_stats::phase("export");
+    let exporter = EXPORTERS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|e| e.name() == name)
+        .cloned()
+        .with_context(|| {
+            anyhow!(
+                "unknown exporter {}; available exporters: {}",
+                name.red().bold(),
+                exporters().into_iter().map(|e| e.0).join(", ")
+            )
+        })?;
+    exporter
+        .export(cs, args, out)
+        .with_context(|| anyhow!("while exporting with {}", name.bold()))
+}
+
+/// What a plugin reports when invoked with [`DESCRIBE_FLAG`]
+#[cfg(feature = "plugins")]
+#[derive(serde::Deserialize)]
+struct PluginDescription {
+    api_version: u32,
+    exporters: Vec<PluginExporter>,
+}
+
+#[cfg(feature = "plugins")]
+#[derive(serde::Deserialize)]
+struct PluginExporter {
+    name: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// The flag with which plugins are asked to describe the exporters they
+/// provide
+pub const DESCRIBE_FLAG: &str = "--corset-plugin";
+
+/// An exporter provided by an external plugin executable, invoked as
+/// `path name args...` with the JSON export of the constraint set on its
+/// standard input, and its standard output becoming the export.
+#[cfg(feature = "plugins")]
+struct External {
+    path: std::path::PathBuf,
+    name: String,
+    description: String,
+}
+#[cfg(feature = "plugins")]
+impl Exporter for External {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn export(&self, cs: &ConstraintSet, args: &[String], out: &mut dyn Write) -> Result<()> {
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new(&self.path)
+            .arg(&self.name)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| anyhow!("while running `{}`", self.path.display()))?;
+        // the constraint set is fed from another thread, so that the plugin
+        // never blocks on a full output pipe while it is being written
+        let mut stdin = child.stdin.take().unwrap();
+        let mut json = Vec::new();
+        crate::exporters::json::render(cs, false, &mut json)?;
+        let feeder = std::thread::spawn(move || stdin.write_all(&json));
+        std::io::copy(child.stdout.as_mut().unwrap(), out)?;
+        let output = child.wait_with_output()?;
+        // a plugin may legitimately stop reading its input early
+        let _ = feeder.join();
+        if !output.status.success() {
+            bail!(
+                "`{}` failed ({}): {}",
+                self.path.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        }
+        Ok(())
+    }
+}
+
+/// Register the exporters provided by the plugin executable `path`.
+///
+/// Plugins run as separate processes, so that they can be written in any
+/// language and built independently from corset. Invoked with
+/// [`DESCRIBE_FLAG`], a plugin must print a JSON object such as
+/// `{"api_version": 2, "exporters": [{"name": "x", "description": "..."}]}`.
+/// Its exporters are then invoked as `path name args...`, and read the
+/// constraint set, in the format of the JSON exporter, on their standard
+/// input.
+#[cfg(feature = "plugins")]
+pub fn load_plugin(path: &str) -> Result<()> {
+    let path = std::path::Path::new(path);
+    let output = std::process::Command::new(path)
+        .arg(DESCRIBE_FLAG)
+        .output()
+        .with_context(|| anyhow!("while loading plugin `{}`", path.display()))?;
+    if !output.status.success() {
+        bail!(
+            "`{}` is not a corset plugin ({}): {}",
+            path.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+    let description: PluginDescription = serde_json::from_slice(&output.stdout)
+        .with_context(|| anyhow!("`{}` is not a corset plugin", path.display()))?;
+    if description.api_version != PLUGIN_API_VERSION {
+        bail!(
+            "`{}` implements version {} of the plugin API, but this corset expects version {}",
+            path.display(),
+            description.api_version.to_string().red().bold(),
+            PLUGIN_API_VERSION
+        )
+    }
+    if description.exporters.is_empty() {
+        bail!("`{}` does not declare any exporter", path.display())
+    }
+    for exporter in description.exporters {
+        log::info!(
+            "loading exporter {} from {}",
+            exporter.name.bold(),
+            path.display()
+        );
+        register(Arc::new(External {
+            path: path.to_owned(),
+            name: exporter.name,
+            description: exporter.description,
+        }));
+    }
+    Ok(())
+}
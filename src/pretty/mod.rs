@@ -6,7 +6,7 @@ use owo_colors::{colored::Color, OwoColorize};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    compiler::{ColumnRef, Conditioning, Expression, Magma, Node},
+    compiler::{ColumnRef, Conditioning, Expression, Magma, Node, RawMagma},
     structs::Handle,
 };
 
@@ -31,6 +31,8 @@ pub enum Base {
     Loob,
     Bytes,
     OpCode,
+    /// field elements in the upper half of the field are shown as negative
+    Signed,
 }
 
 impl std::convert::TryFrom<&str> for Base {
@@ -43,9 +45,10 @@ impl std::convert::TryFrom<&str> for Base {
             ":hex" => Ok(Base::Hex),
             ":bytes" => Ok(Base::Bytes),
             ":opcode" => Ok(Base::OpCode),
+            ":signed" => Ok(Base::Signed),
             ":truthiness" => Ok(Base::Bool),
             _ => anyhow::bail!(
-                ":display expects one of :hex, :dec, :bin, :bytes, :opcode, :truthiness; found {}",
+                ":display expects one of :hex, :dec, :bin, :bytes, :opcode, :truthiness, :signed; found {}",
                 value
             ),
         }
@@ -57,6 +60,7 @@ impl std::convert::From<Magma> for Base {
         match m.c() {
             Conditioning::Boolean => Base::Bool,
             Conditioning::Loobean => Base::Loob,
+            _ if matches!(m.rm(), RawMagma::Signed(_)) => Base::Signed,
             _ => Base::Hex,
         }
     }
@@ -128,6 +132,13 @@ impl Pretty for Fr {
                 Base::OpCode => to_byte(self)
                     .map(opcodes::to_str)
                     .unwrap_or_else(|| self.pretty()),
+                Base::Signed => {
+                    if self.into_bigint() > Fr::MODULUS_MINUS_ONE_DIV_TWO {
+                        format!("-{}", -*self)
+                    } else {
+                        self.pretty()
+                    }
+                }
             }
         }
     }
@@ -178,7 +189,7 @@ impl Pretty for Node {
 
 impl Pretty for Handle {
     fn pretty(&self) -> String {
-        if self.module != crate::compiler::MAIN_MODULE {
+        if !self.is_root() {
             format!(
                 "{}.{}{}",
                 self.module.blue(),
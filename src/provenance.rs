@@ -0,0 +1,137 @@
+//! Provenance sampling, to debug the computed columns: for some sampled rows
+//! of every computed column, the input cells that the computation filling it
+//! read are recorded, to be saved by `compute --provenance` and displayed by
+//! the inspector.
+//!
+//! Nothing is recorded unless [`arm`] has been called, so that computing a
+//! trace pays nothing for it.
+use anyhow::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::{
+    column::Computation,
+    compiler::{ColumnRef, ConstraintSet, Expression, Node},
+};
+
+static PROVENANCE: Mutex<Option<Recorder>> = Mutex::new(None);
+
+struct Recorder {
+    /// one row out of `every` is sampled
+    every: usize,
+    samples: Vec<Sample>,
+}
+
+/// A cell read by a computation
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct InputCell {
+    pub column: String,
+    pub row: isize,
+}
+
+/// The cells from which the value of a computed column on a sampled row has
+/// been computed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    pub column: String,
+    pub row: isize,
+    /// the computation filling the column
+    pub computation: String,
+    /// empty for the computations not reading any column, e.g. the constant
+    /// ones
+    pub inputs: Vec<InputCell>,
+}
+
+/// The provenance recorded while computing a trace
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct ProvenanceReport {
+    pub samples: Vec<Sample>,
+}
+impl ProvenanceReport {
+    pub fn load(path: &str) -> Result<Self> {
+        let content =
+            std::fs::read_to_string(path).with_context(|| anyhow!("while reading `{}`", path))?;
+        serde_json::from_str(&content).with_context(|| anyhow!("while parsing `{}`", path))
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| anyhow!("while writing `{}`", path))
+    }
+}
+
+/// Start recording the provenance of one row out of `every` of the computed
+/// columns
+pub(crate) fn arm(every: usize) {
+    *PROVENANCE.lock().unwrap() = Some(Recorder {
+        every: every.max(1),
+        samples: Vec::new(),
+    });
+}
+
+pub(crate) fn is_armed() -> bool {
+    PROVENANCE.lock().unwrap().is_some()
+}
+
+/// Stop recording, and return what has been recorded so far
+pub(crate) fn take() -> ProvenanceReport {
+    ProvenanceReport {
+        samples: PROVENANCE
+            .lock()
+            .unwrap()
+            .take()
+            .map(|r| r.samples)
+            .unwrap_or_default(),
+    }
+}
+
+/// The cells read by `exp` when evaluated on row `i`
+pub(crate) fn expression_inputs(exp: &Node, i: isize) -> Vec<(ColumnRef, isize)> {
+    exp.leaves()
+        .iter()
+        .filter_map(|l| match l.e() {
+            Expression::Column { handle, shift, .. }
+            | Expression::ExoColumn { handle, shift, .. } => {
+                Some((handle.clone(), i + *shift as isize))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Record, for the sampled rows among the `len` first ones of the `targets`
+/// of `computation`, the cells given by `inputs` for the target and the row
+pub(crate) fn record<F>(
+    cs: &ConstraintSet,
+    computation: &Computation,
+    targets: &[ColumnRef],
+    len: usize,
+    inputs: F,
+) where
+    F: Fn(&ColumnRef, isize) -> Vec<(ColumnRef, isize)>,
+{
+    let mut provenance = PROVENANCE.lock().unwrap();
+    let Some(recorder) = provenance.as_mut() else {
+        return;
+    };
+    let description = crate::check::strip_colors(&computation.to_string());
+    for target in targets {
+        for i in (0..len).step_by(recorder.every) {
+            let mut inputs = inputs(target, i as isize)
+                .into_iter()
+                .map(|(h, row)| InputCell {
+                    column: cs.handle(&h).to_string(),
+                    row,
+                })
+                .collect::<Vec<_>>();
+            inputs.sort();
+            inputs.dedup();
+            recorder.samples.push(Sample {
+                column: cs.handle(target).to_string(),
+                row: i as isize,
+                computation: description.clone(),
+                inputs,
+            });
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! The `corset_py` Python module, exposing the compiler, the trace expansion
+//! and the constraint checker to Python research tooling.
+//!
+//! Field elements do not fit in any numpy type; columns are thus returned
+//! either as `uint64` arrays, if all their values fit, or as `(rows, 32)`
+//! arrays of big-endian `uint8`.
+use anyhow::{anyhow, bail, Context, Result};
+use numpy::{PyArray1, PyArrayMethods};
+use pyo3::{exceptions::PyOverflowError, prelude::*};
+use std::sync::Arc;
+
+use crate::{
+    check::{CheckConfig, Checkpoint, Checkpointer},
+    column::Value,
+    compiler::{ColumnRef, CompileSettings, ConstraintSet},
+    compute::{self, LazyComputer},
+};
+
+/// The name of the column used to evaluate expressions over a trace
+const EVAL_COLUMN: &str = "__corset_py_eval__";
+
+/// The values of `column`, spilling included
+fn column_values(cs: &ConstraintSet, column: &ColumnRef) -> Vec<Value> {
+    cs.columns
+        .backing(column)
+        .map(|backing| backing.iter(&cs.columns).collect())
+        .unwrap_or_default()
+}
+
+/// Convert `values` to a numpy array; see the module documentation
+fn to_numpy<'py>(py: Python<'py>, values: &[Value], raw: bool) -> PyResult<Bound<'py, PyAny>> {
+    if raw {
+        let bytes = values
+            .iter()
+            .flat_map(|v| {
+                let bytes = v.to_bytes();
+                let mut padded = vec![0u8; 32usize.saturating_sub(bytes.len())];
+                padded.extend(bytes);
+                padded
+            })
+            .collect::<Vec<_>>();
+        Ok(PyArray1::from_vec(py, bytes)
+            .reshape([values.len(), 32])?
+            .into_any())
+    } else {
+        let ints = values
+            .iter()
+            .map(|v| u64::try_from(v.to_bi()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| {
+                PyOverflowError::new_err("some values do not fit in 64 bits; use raw=True")
+            })?;
+        Ok(PyArray1::from_vec(py, ints).into_any())
+    }
+}
+
+/// A constraint system, either compiled from sources or loaded from a
+/// compiled file
+#[pyclass(name = "ConstraintSystem", module = "corset_py", unsendable)]
+struct PyConstraintSystem {
+    /// the serialized constraint set, reloaded for every trace, as a
+    /// [`ConstraintSet`] can only be filled once
+    compiled: Vec<u8>,
+    /// the sources, if any, used to compile expressions to evaluate
+    sources: Option<(Vec<(String, String)>, bool)>,
+    debug: bool,
+}
+impl PyConstraintSystem {
+    fn fresh(&self) -> Result<ConstraintSet> {
+        ConstraintSet::from_bytes(&self.compiled, "the constraint system")
+    }
+
+    /// A fresh constraint set, filled with the trace `tracefile`
+    fn filled(&self, tracefile: &str) -> Result<ConstraintSet> {
+        let mut cs = self.fresh()?;
+        compute::compute_trace(tracefile, &mut cs, false)
+            .with_context(|| anyhow!("while expanding `{}`", tracefile))?;
+        Ok(cs)
+    }
+}
+
+#[pymethods]
+impl PyConstraintSystem {
+    /// The names of the constraints
+    fn constraints(&self) -> PyResult<Vec<String>> {
+        Ok(self.fresh()?.constraints.iter().map(|c| c.name()).collect())
+    }
+
+    /// The names of the columns, including the computed ones
+    fn columns(&self) -> PyResult<Vec<String>> {
+        Ok(self
+            .fresh()?
+            .columns
+            .iter_cols()
+            .map(|c| c.handle.to_string())
+            .collect())
+    }
+
+    /// Import `trace` and fill all the computed columns
+    fn compute(&self, trace: &str) -> PyResult<PyTrace> {
+        Ok(PyTrace {
+            cs: self.filled(trace)?,
+        })
+    }
+
+    /// Check `trace`, and return the names of the failing constraints
+    #[pyo3(signature = (trace, only=None, skip=Vec::new()))]
+    fn check(
+        &self,
+        trace: &str,
+        only: Option<Vec<String>>,
+        skip: Vec<String>,
+    ) -> PyResult<Vec<String>> {
+        let mut cs = self.fresh()?;
+        compute::import_trace_lazily(trace, &mut cs, false)
+            .with_context(|| anyhow!("while importing `{}`", trace))?;
+        let checkpointer = Arc::new(Checkpointer::new(
+            Checkpoint::default(),
+            std::time::Duration::MAX,
+            |_| Ok(()),
+        ));
+        let failed = match crate::check::check_lazily(
+            &mut cs,
+            &only,
+            &skip,
+            CheckConfig::new().checkpoint(checkpointer.clone()),
+        ) {
+            Ok(_) => Vec::new(),
+            // the failing constraints are read back from the checkpoint
+            Err(err) => {
+                let failed = checkpointer.progress().failed;
+                if failed.is_empty() {
+                    return Err(err.into());
+                }
+                failed.into_iter().collect()
+            }
+        };
+        Ok(failed)
+    }
+
+    /// Evaluate the Corset expression `expr`, in the scope of `module`, over
+    /// every row of `trace`; this requires the sources of the constraint
+    /// system.
+    #[pyo3(signature = (expr, trace, module=None, raw=false))]
+    fn evaluate<'py>(
+        &self,
+        py: Python<'py>,
+        expr: &str,
+        trace: &str,
+        module: Option<&str>,
+        raw: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let values = || -> Result<Vec<Value>> {
+            let Some((sources, no_stdlib)) = self.sources.as_ref() else {
+                bail!(
+                    "expressions can only be evaluated in constraint systems compiled from sources"
+                )
+            };
+            let mut sources = sources.clone();
+            sources.push((
+                "evaluated expression".to_string(),
+                format!(
+                    "{} (defcolumns ({} :comp {}))",
+                    module
+                        .map(|m| format!("(module {})", m))
+                        .unwrap_or_default(),
+                    EVAL_COLUMN,
+                    expr
+                ),
+            ));
+            let settings = CompileSettings {
+                debug: self.debug,
+                ..Default::default()
+            };
+            let mut cs = crate::compile(&sources, &settings, *no_stdlib)?;
+            compute::import_trace_lazily(trace, &mut cs, false)
+                .with_context(|| anyhow!("while importing `{}`", trace))?;
+
+            // only the computations the expression depends on are run
+            let prove = cs
+                .constraints
+                .iter()
+                .find(|c| c.handle().name == format!("prove-{}", EVAL_COLUMN))
+                .cloned()
+                .ok_or_else(|| anyhow!("{} is not a valid expression", expr))?;
+            LazyComputer::default().materialize(&mut cs, &prove)?;
+            let column = cs
+                .columns
+                .all()
+                .into_iter()
+                .find(|c| cs.handle(c).name == EVAL_COLUMN)
+                .unwrap();
+            Ok(column_values(&cs, &column))
+        }()?;
+        to_numpy(py, &values, raw)
+    }
+}
+
+/// A trace, whose computed columns have been filled
+#[pyclass(name = "Trace", module = "corset_py", unsendable)]
+struct PyTrace {
+    cs: ConstraintSet,
+}
+#[pymethods]
+impl PyTrace {
+    /// The names of the columns in the trace
+    fn columns(&self) -> Vec<String> {
+        self.cs
+            .columns
+            .iter_cols()
+            .map(|c| c.handle.to_string())
+            .collect()
+    }
+
+    /// The values of the column `name`, spilling included
+    #[pyo3(signature = (name, raw=false))]
+    fn column<'py>(&self, py: Python<'py>, name: &str, raw: bool) -> PyResult<Bound<'py, PyAny>> {
+        let column = self
+            .cs
+            .columns
+            .all()
+            .into_iter()
+            .find(|c| self.cs.handle(c).to_string() == name)
+            .ok_or_else(|| anyhow!("column `{}` not found", name))?;
+        to_numpy(py, &column_values(&self.cs, &column), raw)
+    }
+}
+
+/// Compile `sources`, each of them either a file or some Corset code
+#[pyfunction]
+#[pyo3(signature = (sources, debug=false, no_stdlib=false))]
+fn compile(sources: Vec<String>, debug: bool, no_stdlib: bool) -> PyResult<PyConstraintSystem> {
+    let sources = sources
+        .into_iter()
+        .map(|s| {
+            if std::path::Path::new(&s).is_file() {
+                let content = std::fs::read_to_string(&s)
+                    .with_context(|| anyhow!("while reading `{}`", s))?;
+                Ok((s, content))
+            } else {
+                Ok(("Immediate expression".to_string(), s))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let settings = CompileSettings {
+        debug,
+        ..Default::default()
+    };
+    let cs = crate::compile(&sources, &settings, no_stdlib)?;
+    Ok(PyConstraintSystem {
+        compiled: cs.to_binary()?,
+        sources: Some((sources, no_stdlib)),
+        debug,
+    })
+}
+
+/// Load a constraint system compiled by `corset compile`
+#[pyfunction]
+fn load(path: &str) -> PyResult<PyConstraintSystem> {
+    let cs = crate::load(path)?;
+    Ok(PyConstraintSystem {
+        compiled: cs.to_binary()?,
+        sources: None,
+        debug: false,
+    })
+}
+
+#[pymodule]
+fn corset_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(compile, m)?)?;
+    m.add_function(wrap_pyfunction!(load, m)?)?;
+    m.add_class::<PyConstraintSystem>()?;
+    m.add_class::<PyTrace>()?;
+    Ok(())
+}
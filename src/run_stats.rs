@@ -0,0 +1,226 @@
+//! A record of what an invocation did, written as JSON by `--stats-json` for
+//! the observability of the pipelines running Corset.
+//!
+//! Nothing is collected unless [`arm`] has been called, so that the library
+//! pays nothing for it.
+use anyhow::*;
+use log::*;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Read},
+    sync::Mutex,
+    time::Instant,
+};
+
+use crate::compiler::ConstraintSet;
+
+static STATS: Mutex<Option<RunStats>> = Mutex::new(None);
+
+#[derive(Serialize)]
+struct Input {
+    name: String,
+    bytes: usize,
+    sha256: String,
+}
+
+#[derive(Serialize)]
+struct RunStats {
+    /// where to write these statistics
+    #[serde(skip)]
+    path: String,
+    command: String,
+    corset: String,
+    inputs: Vec<Input>,
+    /// the number of rows of every module of the imported trace, if any
+    rows: BTreeMap<String, usize>,
+    /// the cumulated time spent in every phase, in seconds
+    phases: BTreeMap<&'static str, f64>,
+    /// `failure` until a result is recorded
+    status: &'static str,
+    error: Option<String>,
+}
+
+fn with_stats<F: FnOnce(&mut RunStats)>(f: F) {
+    if let Some(stats) = STATS.lock().unwrap().as_mut() {
+        f(stats)
+    }
+}
+
+fn is_armed() -> bool {
+    STATS.lock().unwrap().is_some()
+}
+
+/// Start collecting statistics on the `command` subcommand, to be written to
+/// `path` once the returned guard is dropped
+#[must_use]
+pub(crate) fn arm(path: &str, command: &str) -> Concluder {
+    *STATS.lock().unwrap() = Some(RunStats {
+        path: path.to_owned(),
+        command: command.to_owned(),
+        corset: crate::version::BuildInfo::current().version,
+        inputs: Vec::new(),
+        rows: BTreeMap::new(),
+        phases: BTreeMap::new(),
+        status: "failure",
+        error: None,
+    });
+    Concluder
+}
+
+fn push_input(name: &str, bytes: usize, hasher: Sha256) {
+    let sha256 = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    with_stats(|stats| {
+        stats.inputs.push(Input {
+            name: name.to_owned(),
+            bytes,
+            sha256,
+        })
+    });
+}
+
+/// Record the input `name`, of content `bytes`
+pub(crate) fn record_input(name: &str, bytes: &[u8]) {
+    if is_armed() {
+        push_input(name, bytes.len(), Sha256::new_with_prefix(bytes));
+    }
+}
+
+/// Record the input `name` as it is read from `r`, so that it does not have
+/// to be read once more to be hashed
+pub(crate) fn record_input_reader(name: &str, r: Box<dyn BufRead>) -> Box<dyn BufRead> {
+    if is_armed() {
+        Box::new(HashingReader {
+            name: name.to_owned(),
+            inner: r,
+            bytes: 0,
+            hasher: Sha256::new(),
+        })
+    } else {
+        r
+    }
+}
+
+/// Hashes the bytes read through it, and records them as an input once
+/// dropped
+struct HashingReader {
+    name: String,
+    inner: Box<dyn BufRead>,
+    bytes: usize,
+    hasher: Sha256,
+}
+impl Read for HashingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes += n;
+        self.hasher.update(&buf[..n]);
+        std::io::Result::Ok(n)
+    }
+}
+impl BufRead for HashingReader {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // the buffer has already been filled, and is thus returned as is
+        if let std::io::Result::Ok(buf) = self.inner.fill_buf() {
+            let amt = amt.min(buf.len());
+            self.bytes += amt;
+            self.hasher.update(&buf[..amt]);
+        }
+        self.inner.consume(amt)
+    }
+}
+impl Drop for HashingReader {
+    fn drop(&mut self) {
+        push_input(&self.name, self.bytes, std::mem::take(&mut self.hasher));
+    }
+}
+
+/// Record the number of rows of every module of the trace imported in `cs`
+pub(crate) fn record_rows(cs: &ConstraintSet) {
+    with_stats(|stats| {
+        for module in cs.columns.modules() {
+            if let Some(len) = cs.effective_len_for(&module) {
+                stats.rows.insert(module, len.max(0) as usize);
+            }
+        }
+    });
+}
+
+/// Measures the time spent in a phase until it is dropped
+pub(crate) struct PhaseTimer {
+    name: &'static str,
+    start: Instant,
+}
+impl Drop for PhaseTimer {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        with_stats(|stats| *stats.phases.entry(self.name).or_default() += elapsed);
+    }
+}
+
+/// Time the phase `name` until the returned guard is dropped; the times of
+/// the phases entered several times add up.
+pub(crate) fn phase(name: &'static str) -> PhaseTimer {
+    PhaseTimer {
+        name,
+        start: Instant::now(),
+    }
+}
+
+/// Record `result` as the final one of the invocation, and return it
+pub(crate) fn recorded<T>(result: Result<T>) -> Result<T> {
+    record_result(&result);
+    result
+}
+
+/// Record `result` as the final one of the invocation
+pub(crate) fn record_result<T>(result: &Result<T>) {
+    with_stats(|stats| match result {
+        Result::Ok(_) => {
+            stats.status = "success";
+            stats.error = None;
+        }
+        Err(err) => {
+            stats.status = if crate::utils::is_cancelled(err) {
+                "interrupted"
+            } else if crate::utils::is_deadline_exceeded(err) {
+                "timeout"
+            } else {
+                "failure"
+            };
+            stats.error = Some(crate::check::strip_colors(&format!("{:#}", err)));
+        }
+    });
+}
+
+/// Record that the invocation succeeded
+pub(crate) fn succeed() {
+    record_result(&Ok(()))
+}
+
+/// Writes the statistics once dropped, see [`arm`]
+pub(crate) struct Concluder;
+impl Drop for Concluder {
+    fn drop(&mut self) {
+        let Some(stats) = STATS.lock().unwrap().take() else {
+            return;
+        };
+        if let Err(err) = serde_json::to_string_pretty(&stats)
+            .map_err(Error::from)
+            .and_then(|json| {
+                std::fs::write(&stats.path, json)
+                    .with_context(|| anyhow!("while writing `{}`", stats.path))
+            })
+        {
+            warn!("{:?}", err);
+        }
+    }
+}
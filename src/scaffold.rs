@@ -0,0 +1,179 @@
+//! Draft the definition of a new module from a trace featuring it, typically
+//! produced by the Go generator before any constraint has been written.
+use anyhow::*;
+use itertools::Itertools;
+use num_bigint::{BigInt, Sign};
+use num_traits::{One, Signed, Zero};
+use owo_colors::OwoColorize;
+use std::str::FromStr;
+
+use crate::{
+    compiler::MAIN_MODULE,
+    import::{TraceFormat, TraceReader},
+    input,
+    structs::Handle,
+};
+
+/// Read all the columns of `tracefile`, without any constraint system to
+/// validate them against
+fn read_columns(tracefile: &str) -> Result<Vec<(Handle, Vec<BigInt>)>> {
+    let format = TraceFormat::detect(tracefile)?;
+    if let TraceFormat::Cbor | TraceFormat::MessagePack = format {
+        let reader = input::reader(tracefile)?;
+        let item = if format == TraceFormat::Cbor {
+            crate::packed::decode_cbor(reader)
+        } else {
+            crate::packed::decode_msgpack(reader)
+        }
+        .with_context(|| anyhow!("while parsing `{}`", tracefile))?;
+        let mut r = Vec::new();
+        json_columns(&item.to_json(), &mut Vec::new(), &mut r)?;
+        Ok(r)
+    } else if format == TraceFormat::Lt {
+        let mut reader = TraceReader::from(input::reader(tracefile)?);
+        let map = reader.map()?;
+        map.headers
+            .into_iter()
+            .map(|h| {
+                let bytes = reader.slice(h.length as usize * h.bytes_per_element)?;
+                Ok((
+                    h.handle,
+                    bytes
+                        .chunks(h.bytes_per_element)
+                        .map(|bs| BigInt::from_bytes_be(Sign::Plus, bs))
+                        .collect(),
+                ))
+            })
+            .collect()
+    } else {
+        let v: serde_json::Value = serde_json::from_reader(input::reader(tracefile)?)
+            .with_context(|| anyhow!("while parsing `{}`", tracefile))?;
+        let mut r = Vec::new();
+        json_columns(&v, &mut Vec::new(), &mut r)?;
+        Ok(r)
+    }
+}
+
+fn json_columns(
+    v: &serde_json::Value,
+    path: &mut Vec<String>,
+    r: &mut Vec<(Handle, Vec<BigInt>)>,
+) -> Result<()> {
+    use serde_json::Value;
+
+    match v {
+        Value::Object(map) => {
+            for (k, v) in map.iter() {
+                if k == "Trace" {
+                    json_columns(v, path, r)?;
+                } else {
+                    path.push(k.to_owned());
+                    json_columns(v, path, r)?;
+                    path.pop();
+                }
+            }
+        }
+        Value::Array(xs) if !path.is_empty() => {
+            // columns found at the top level belong to the root module
+            let module = if path.len() >= 2 {
+                path[path.len() - 2].as_str()
+            } else {
+                ""
+            };
+            let handle = Handle::new(module, &path[path.len() - 1]);
+            // segmented columns are concatenated
+            let values = xs
+                .iter()
+                .flat_map(|x| match x {
+                    Value::Array(segment) => segment.iter().collect::<Vec<_>>(),
+                    x => vec![x],
+                })
+                .map(|x| match x {
+                    Value::Number(n) => BigInt::from_str(&n.to_string()).ok(),
+                    Value::String(s) => BigInt::from_str(s).ok(),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()
+                .with_context(|| anyhow!("{} holds non-numeric values", handle))?;
+            r.push((handle, values));
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// The most specific type accepting all the values `xs`, if any
+fn infer_type(xs: &[BigInt]) -> Option<&'static str> {
+    if xs.is_empty() || xs.iter().any(|x| x.is_negative()) {
+        None
+    } else if xs.iter().all(|x| x.is_zero() || x.is_one()) {
+        Some(":binary")
+    } else if xs.iter().all(|x| *x <= BigInt::from(255)) {
+        Some(":byte")
+    } else {
+        None
+    }
+}
+
+/// Generate the skeleton of `module`, from its columns found in `columns`
+fn scaffold(all: &[(Handle, Vec<BigInt>)], module: &str, origin: &str) -> Result<String> {
+    let module = Handle::new(module, "").module;
+    let columns = all
+        .iter()
+        .filter(|(h, _)| h.module == module)
+        .map(|(h, xs)| (h.name.as_str(), infer_type(xs)))
+        .collect::<Vec<_>>();
+    if columns.is_empty() {
+        bail!(
+            "no columns found for module {} in {}; available modules: {}",
+            module.red(),
+            origin,
+            all.iter()
+                .map(|(h, _)| h.module.as_str())
+                .unique()
+                .sorted()
+                .join(", ")
+        )
+    }
+
+    let mut r = format!(
+        ";; Scaffolded from {}; the column types are inferred from the values\n\
+         ;; found there, and must be reviewed.\n",
+        origin
+    );
+    if module != MAIN_MODULE {
+        r.push_str(&format!("(module {})\n", module));
+    }
+
+    r.push_str("\n(defcolumns");
+    for (name, t) in columns.iter() {
+        match t {
+            Some(t) => r.push_str(&format!("\n  ({} {})", name, t)),
+            None => r.push_str(&format!("\n  {}", name)),
+        }
+    }
+    r.push_str(")\n");
+
+    let prefix = if module == MAIN_MODULE {
+        String::new()
+    } else {
+        format!("{}-", module)
+    };
+    r.push_str(&format!(
+        "\n;; TODO: the constraints of the module\n(defconstraint {}todo ()\n  (vanishes! 0))\n",
+        prefix
+    ));
+    for (name, _) in columns.iter().filter(|c| c.1 == Some(":binary")) {
+        r.push_str(&format!(
+            "\n;; TODO: {} is binary, and may select some rows\n\
+             (defconstraint {}{}-todo ()\n  (if-not-zero {}\n    (vanishes! 0)))\n",
+            name, prefix, name, name
+        ));
+    }
+    Ok(r)
+}
+
+/// Generate the skeleton of `module`, from its columns found in `tracefile`
+pub(crate) fn scaffold_trace(tracefile: &str, module: &str) -> Result<String> {
+    scaffold(&read_columns(tracefile)?, module, tracefile)
+}
@@ -0,0 +1,204 @@
+//! Statistics on a constraint system and, if a trace is supplied, on how the
+//! trace exercises it.
+use anyhow::{anyhow, Result};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{
+    column::Value,
+    compiler::{Constraint, ConstraintSet, EvalSettings, Kind, Node, ShiftWindow},
+    pretty::Pretty,
+    structs::Handle,
+};
+
+/// The size of every module of a constraint system
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub(crate) struct ModuleStats {
+    pub columns: usize,
+    pub computed: usize,
+    pub constraints: usize,
+    pub lookups: usize,
+    pub shift_window: ShiftWindow,
+}
+
+/// How a trace occupies the table of a lookup, i.e. its including side
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct LookupStats {
+    pub handle: Handle,
+    /// the number of rows of the looking-up (included) side
+    pub source_rows: usize,
+    /// the number of distinct tuples in the looking-up side
+    pub distinct_sources: usize,
+    /// the number of rows of the table
+    pub table_rows: usize,
+    /// the number of distinct tuples in the table
+    pub distinct_entries: usize,
+    /// the number of source rows hitting the most looked-up table entry
+    pub max_multiplicity: usize,
+    /// the number of table rows whose entry is never looked up
+    pub unused_rows: usize,
+    /// the number of source rows whose tuple is absent from the table
+    pub missing: usize,
+}
+impl LookupStats {
+    /// The share of the table rows that are looked up at least once
+    pub fn occupancy(&self) -> f64 {
+        if self.table_rows == 0 {
+            0.
+        } else {
+            (self.table_rows - self.unused_rows) as f64 / self.table_rows as f64
+        }
+    }
+}
+
+pub(crate) fn module_stats(cs: &ConstraintSet) -> BTreeMap<String, ModuleStats> {
+    let mut r = BTreeMap::<String, ModuleStats>::new();
+    for c in cs.columns.iter_cols() {
+        let stats = r.entry(c.handle.module.clone()).or_default();
+        stats.columns += 1;
+        if !matches!(c.kind, Kind::Commitment) {
+            stats.computed += 1;
+        }
+    }
+    for c in cs.constraints.iter() {
+        let stats = r.entry(c.handle().module.clone()).or_default();
+        stats.constraints += 1;
+        if matches!(c, Constraint::Lookup { .. }) {
+            stats.lookups += 1;
+        }
+    }
+    for (module, stats) in r.iter_mut() {
+        stats.shift_window = cs.shift_window_of(module);
+    }
+    r
+}
+
+/// The tuple formed by the values of `exps` at row `i`
+fn tuple_at(cs: &ConstraintSet, exps: &[Node], i: isize) -> Result<Vec<Value>> {
+    exps.iter()
+        .map(|e| {
+            e.eval(
+                i,
+                |handle, j, _| {
+                    cs.columns
+                        .get(handle, j, false)
+                        .or_else(|| cs.columns.column(handle).unwrap().padding_value.clone())
+                },
+                &mut None,
+                &EvalSettings::default(),
+            )
+            .ok_or_else(|| {
+                anyhow!(
+                    "unable to evaluate {} at row {}",
+                    e.pretty_with_handle(cs),
+                    i
+                )
+            })
+        })
+        .collect()
+}
+
+/// Compute the occupancy statistics of every lookup of `cs`, whose trace must
+/// have been computed
+pub(crate) fn lookup_stats(cs: &ConstraintSet) -> Result<Vec<LookupStats>> {
+    cs.constraints
+        .iter()
+        .filter_map(|c| match c {
+            Constraint::Lookup {
+                handle,
+                including,
+                included,
+            } => Some((handle, including, included)),
+            _ => None,
+        })
+        .map(|(handle, including, included)| {
+            let len_of = |exps: &[Node]| {
+                cs.module_of_exprs(exps)
+                    .map(|m| cs.iter_len(&m))
+                    .unwrap_or_default()
+            };
+            let (table_rows, source_rows) = (len_of(including), len_of(included));
+
+            let table = (0..table_rows as isize)
+                .map(|i| tuple_at(cs, including, i))
+                .collect::<Result<Vec<_>>>()?;
+            let entries = table.iter().collect::<HashSet<_>>();
+            let mut sources = HashMap::<Vec<Value>, usize>::new();
+            for i in 0..source_rows as isize {
+                *sources.entry(tuple_at(cs, included, i)?).or_default() += 1;
+            }
+
+            Ok(LookupStats {
+                handle: handle.to_owned(),
+                source_rows,
+                distinct_sources: sources.len(),
+                table_rows,
+                distinct_entries: entries.len(),
+                max_multiplicity: sources
+                    .iter()
+                    .filter(|(t, _)| entries.contains(t))
+                    .map(|(_, count)| *count)
+                    .max()
+                    .unwrap_or(0),
+                unused_rows: table.iter().filter(|t| !sources.contains_key(*t)).count(),
+                missing: sources
+                    .iter()
+                    .filter(|(t, _)| !entries.contains(t))
+                    .map(|(_, count)| count)
+                    .sum(),
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn print(modules: &BTreeMap<String, ModuleStats>, lookups: Option<&[LookupStats]>) {
+    println!(
+        "{:<24} {:>8} {:>8} {:>12} {:>8} {:>8}",
+        "module".bold(),
+        "columns".bold(),
+        "computed".bold(),
+        "constraints".bold(),
+        "lookups".bold(),
+        "shifts".bold()
+    );
+    for (module, stats) in modules.iter() {
+        println!(
+            "{:<24} {:>8} {:>8} {:>12} {:>8} {:>8}",
+            module.blue(),
+            stats.columns,
+            stats.computed,
+            stats.constraints,
+            stats.lookups,
+            format!(
+                "-{}/+{}",
+                stats.shift_window.backward, stats.shift_window.forward
+            )
+        );
+    }
+
+    if let Some(lookups) = lookups {
+        println!();
+        for l in lookups.iter() {
+            println!("{}", l.handle.pretty().bold());
+            println!(
+                "  sources: {} rows, {} distinct tuples, {} not in the table",
+                l.source_rows,
+                l.distinct_sources,
+                if l.missing > 0 {
+                    l.missing.red().bold().to_string()
+                } else {
+                    l.missing.to_string()
+                }
+            );
+            println!(
+                "  table:   {} rows, {} distinct entries, {} unused rows ({:.1}% occupancy)",
+                l.table_rows,
+                l.distinct_entries,
+                l.unused_rows,
+                100. * l.occupancy()
+            );
+            println!("  max. multiplicity: {}", l.max_multiplicity);
+        }
+    }
+}
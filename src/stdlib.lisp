@@ -106,9 +106,15 @@
 
 ;; Helpers
 (defpurefun ((vanishes! :@loob :force) e0) e0)
+(defpurefun (abs x) (* (sgn x) x))
 (defpurefun (if-eq x val then) (if (eq! x val) then))
 (defpurefun (if-eq-else x val then else) (if (eq! x val) then else))
 
+;; Constants
+;; the modulus of the field, i.e. one above the largest native value
+(defconst FIELD_MODULUS 8444461749428370424248824938781546531375899335154063827935233455917409239041)
+(defpurefun (pow2 k) (^ 2 k))
+
 ;; counter constancy constraint
 (defpurefun ((counter-constancy :@loob) ct X)
   (if-not-zero ct
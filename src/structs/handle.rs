@@ -1,9 +1,33 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{compiler::MAIN_MODULE, utils::purify};
+use crate::{
+    compiler::MAIN_MODULE,
+    utils::{purify, purify_escaped},
+};
 
 use super::{ARRAY_SEPARATOR, MODULE_SEPARATOR};
 
+/// How symbols are turned into identifiers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MangleScheme {
+    /// every symbol is prefixed with its module, and the punctuation without
+    /// a conventional replacement is replaced by `_`
+    #[default]
+    Legacy,
+    /// the symbols of the root module are not prefixed, and the punctuation
+    /// without a conventional replacement is escaped by its code point, so
+    /// that distinct symbols never share an identifier
+    Escaped,
+}
+impl MangleScheme {
+    fn purify(self, s: &str) -> String {
+        match self {
+            MangleScheme::Legacy => purify(s),
+            MangleScheme::Escaped => purify_escaped(s),
+        }
+    }
+}
+
 /// A handle uniquely and absolutely defines a symbol
 #[derive(Clone, Deserialize)]
 pub struct Handle {
@@ -46,10 +70,20 @@ impl std::hash::Hash for Handle {
         self.name.hash(state);
     }
 }
+/// Symbols defined outside of any module, or in an unnamed one, belong to the
+/// root module
+fn normalize_module(module: &str) -> String {
+    if module.is_empty() {
+        MAIN_MODULE.to_owned()
+    } else {
+        module.to_owned()
+    }
+}
+
 impl Handle {
     pub fn new<S1: AsRef<str>, S2: AsRef<str>>(module: S1, name: S2) -> Self {
         Handle {
-            module: module.as_ref().to_owned(),
+            module: normalize_module(module.as_ref()),
             name: name.as_ref().to_owned(),
             perspective: None,
         }
@@ -61,12 +95,17 @@ impl Handle {
         perspective: Option<String>,
     ) -> Handle {
         Handle {
-            module: module.as_ref().to_owned(),
+            module: normalize_module(module.as_ref()),
             name: name.as_ref().to_owned(),
             perspective,
         }
     }
 
+    /// Whether this symbol belongs to the root module
+    pub fn is_root(&self) -> bool {
+        self.module == MAIN_MODULE
+    }
+
     pub fn and_with_perspective(mut self, perspective: Option<String>) -> Self {
         self.perspective = perspective;
         self
@@ -90,43 +129,56 @@ impl Handle {
         }
     }
 
+    /// The prefix of the mangled symbols of the module of this handle
+    fn mangle_prefix(&self, separator: &str, scheme: MangleScheme) -> String {
+        if self.is_root() && scheme == MangleScheme::Escaped {
+            String::new()
+        } else {
+            format!("{}{}", scheme.purify(&self.module), separator)
+        }
+    }
+
     /// Uniquely mangle a symbol into something usable in Go
     pub fn mangle(&self) -> String {
-        let r = format!(
-            "{}{}{}",
-            purify(&self.module),
-            if self.module.is_empty() {
-                ""
-            } else {
-                MODULE_SEPARATOR
-            },
-            purify(&self.name)
-        );
-        r
+        self.mangle_with(MODULE_SEPARATOR, MangleScheme::Legacy)
+    }
+
+    /// Mangle a symbol with `scheme`, separating its module from its name with
+    /// `separator`
+    pub fn mangle_with(&self, separator: &str, scheme: MangleScheme) -> String {
+        format!(
+            "{}{}",
+            self.mangle_prefix(separator, scheme),
+            scheme.purify(&self.name)
+        )
     }
 
     pub fn mangle_ith(&self, i: usize) -> String {
-        let r = format!(
-            "{}{}{}ɩ{}",
-            purify(&self.module),
-            if self.module.is_empty() {
-                ""
-            } else {
-                MODULE_SEPARATOR
-            },
-            purify(&self.name),
-            i,
-        );
-        r
+        self.mangle_ith_with(MODULE_SEPARATOR, MangleScheme::Legacy, i)
+    }
+
+    pub fn mangle_ith_with(&self, separator: &str, scheme: MangleScheme, i: usize) -> String {
+        format!(
+            "{}{}ɩ{}",
+            self.mangle_prefix(separator, scheme),
+            scheme.purify(&self.name),
+            i
+        )
     }
 
     /// Uniquely mangle the name of a symbol into something usable in Go
     pub fn mangled_name(&self) -> String {
-        purify(&format!(
+        self.mangled_name_with(MODULE_SEPARATOR, MangleScheme::Legacy)
+    }
+
+    /// Mangle the name of a symbol with `scheme`, separating its perspective
+    /// from its name with `separator`
+    pub fn mangled_name_with(&self, separator: &str, scheme: MangleScheme) -> String {
+        scheme.purify(&format!(
             "{}{}",
             self.perspective
                 .clone()
-                .map(|s| format!("{s}__"))
+                .map(|s| format!("{s}{separator}"))
                 .unwrap_or_default(),
             &self.name
         ))
@@ -134,7 +186,16 @@ impl Handle {
 
     /// Uniquely mangle the module of a symbol into something usable in Go
     pub fn mangled_module(&self) -> String {
-        purify(&self.module)
+        self.mangled_module_with(MangleScheme::Legacy)
+    }
+
+    /// Mangle the module of a symbol with `scheme`
+    pub fn mangled_module_with(&self, scheme: MangleScheme) -> String {
+        if self.is_root() && scheme == MangleScheme::Escaped {
+            scheme.purify(MAIN_MODULE.trim_start_matches('<').trim_end_matches('>'))
+        } else {
+            scheme.purify(&self.module)
+        }
     }
 }
 impl std::fmt::Debug for Handle {
@@ -144,7 +205,7 @@ impl std::fmt::Debug for Handle {
 }
 impl std::fmt::Display for Handle {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if self.module == MAIN_MODULE {
+        if self.is_root() {
             write!(
                 f,
                 "{}{}",
@@ -1,7 +1,11 @@
 mod handle;
+mod span;
 
 pub use handle::Handle;
+#[cfg(feature = "exporters")]
+pub use handle::MangleScheme;
+pub use span::Span;
 
 const ARRAY_SEPARATOR: &str = "_";
 pub const PERSPECTIVE_SEPARATOR: char = '/';
-const MODULE_SEPARATOR: &str = "__";
+pub const MODULE_SEPARATOR: &str = "__";
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// The position in the sources of the definition of a symbol
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    /// the name of the source file, as given to the compiler
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+impl Span {
+    pub fn new<S: AsRef<str>>(file: S, lc: (usize, usize)) -> Self {
+        Span {
+            file: file.as_ref().to_owned(),
+            line: lc.0,
+            column: lc.1,
+        }
+    }
+}
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
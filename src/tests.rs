@@ -1,8 +1,8 @@
-use crate::{transformer::ExpansionLevel, ConstraintSetBuilder};
+use crate::{transformer::ExpansionLevel, SourceLoader};
 use anyhow::*;
 
-fn make(name: &str, source: &str) -> Result<()> {
-    let mut r = ConstraintSetBuilder::from_sources(false, false);
+fn make(_name: &str, source: &str) -> Result<()> {
+    let mut r = SourceLoader::from_sources(false, false);
     r.add_source(source)?;
     r.expand_to(ExpansionLevel::top());
 
@@ -22,6 +22,15 @@ fn must_fail(name: &str, source: &str) {
     assert!(r.is_err());
 }
 
+/// A fresh directory for the files written by a test, removed once dropped, so
+/// that tests running in parallel never clobber each other's files
+fn temp_dir() -> tempfile::TempDir {
+    tempfile::Builder::new()
+        .prefix("corset-")
+        .tempdir()
+        .expect("unable to create a temporary directory")
+}
+
 #[test]
 fn types_declaration() -> Result<()> {
     make("type", include_str!("../tests/types.lisp"))
@@ -246,3 +255,4818 @@ fn complex_for() {
 //     //     "(module foobar) (defcolumns A B (C :bool) (D :i32)) (defconstraint pipo () (if (eq! A D) C D))",
 //     // );
 // }
+
+#[test]
+fn bounds_inference() -> Result<()> {
+    let mut r = SourceLoader::from_sources(false, false);
+    r.add_source(
+        "(defcolumns (A :i128) (B :i128) (C :byte) X)
+         (defconstraint overflows () (vanishes! (- (* A B) C)))
+         (defconstraint fits () (vanishes! (- A (* 256 C))))
+         (defconstraint native () (vanishes! (* A X)))",
+    )?;
+    r.expand_to(ExpansionLevel::top());
+    let cs = r.into_constraint_set()?;
+
+    let risky = crate::compiler::bounds::risky_expressions(&cs, 8);
+    assert_eq!(risky.len(), 1);
+    assert_eq!(risky[0].handle.name, "overflows");
+    assert_eq!(risky[0].bound.bits(), 256);
+    Ok(())
+}
+
+#[test]
+fn verified_computations() -> Result<()> {
+    let tmp = temp_dir();
+    let verify = |trace: &str| -> Result<()> {
+        let path = tmp.path().join("verified-computations.json");
+        std::fs::write(&path, trace)?;
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source("(defcolumns A (B :comp (* A 2))) (defpermutation (S) ((+ A)))")?;
+        builder.expand_to(ExpansionLevel::top());
+        let mut cs = builder.into_constraint_set()?;
+        crate::compute::compute_trace(path.to_str().unwrap(), &mut cs, true)?;
+        crate::compute::verify_computations(&cs)
+    };
+    let error =
+        |trace: &str| crate::check::strip_colors(&format!("{:#}", verify(trace).unwrap_err()));
+
+    // the columns filled by corset hold
+    verify(r#"{"A": [3, 1, 2]}"#)?;
+    verify(r#"{"A": [3, 1, 2], "B": [6, 2, 4], "S": [1, 2, 3]}"#)?;
+
+    // the ones provided by the trace are reported at their first wrong row
+    let err = error(r#"{"A": [3, 1, 2], "B": [6, 2, 5]}"#);
+    assert!(err.contains("does not hold"), "{}", err);
+    assert!(err.contains("B[2] = 5, expected 4"), "{}", err);
+    let err = error(r#"{"A": [3, 1, 2], "S": [3, 1, 2]}"#);
+    assert!(
+        err.contains("is not sorted between rows 0 and 1"),
+        "{}",
+        err
+    );
+    let err = error(r#"{"A": [3, 1, 2], "S": [1, 2, 4]}"#);
+    assert!(err.contains("is not a permutation of"), "{}", err);
+    Ok(())
+}
+
+#[test]
+fn stdlib_transitive_use() {
+    must_run(
+        "stdlib dependencies are pulled in",
+        "(defcolumns A (B :binary) CT) (defconstraint c () (begin (will-inc! A 1) (plateau-constraint CT B A) (= A CT)))",
+    );
+}
+
+#[test]
+fn programmatic_constraint_set() -> Result<()> {
+    use crate::column::Computation;
+    use crate::compiler::{builder::ConstraintSetBuilder, Expression, Intrinsic, Kind, Magma};
+    use crate::structs::Handle;
+
+    let mut builder = ConstraintSetBuilder::new();
+    let a = builder.commitment(Handle::new("m", "A"), Magma::byte())?;
+    let b = builder.commitment(Handle::new("m", "B"), Magma::byte())?;
+    let c = builder.computed(Handle::new("m", "C"), Magma::byte())?;
+    builder.computation(Computation::Sorted {
+        froms: vec![a.clone()],
+        tos: vec![c.clone()],
+        signs: vec![true],
+        stable: true,
+        tiebreaks: vec![],
+    })?;
+    // nodes must agree with the kind of their column
+    assert!(builder.node(&c, Kind::Commitment).is_err());
+    let (a, b, c) = (
+        builder.node(&a, Kind::Commitment)?,
+        builder.node(&b, Kind::Commitment)?,
+        builder.node(&c, Kind::Computed)?,
+    );
+    assert!(matches!(
+        c.e(),
+        Expression::Column {
+            kind: Kind::Computed,
+            ..
+        }
+    ));
+    builder
+        .vanishes(
+            Handle::new("m", "a-is-b"),
+            None,
+            Intrinsic::Sub.call(&[a, b.clone()])?,
+        )
+        .lookup(Handle::new("m", "c-in-b"), vec![b], vec![c]);
+    let cs = builder.build()?;
+    assert_eq!(cs.constraints.len(), 2);
+    assert_eq!(cs.columns.registers.len(), 3);
+
+    // A computed column must be filled
+    let mut builder = ConstraintSetBuilder::new();
+    builder.computed(Handle::new("m", "C"), Magma::byte())?;
+    assert!(builder.build().is_err());
+
+    // Lookups must be balanced
+    let mut builder = ConstraintSetBuilder::new();
+    let a = builder.commitment(Handle::new("m", "A"), Magma::byte())?;
+    let a = builder.node(&a, Kind::Commitment)?;
+    builder.lookup(Handle::new("m", "l"), vec![a], vec![]);
+    assert!(builder.build().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn includes() {
+    must_run("include", "tests/include/main.lisp");
+    must_fail("include-cycle", "tests/include/cycle-a.lisp");
+    must_fail(
+        "include-missing",
+        "(include \"tests/include/missing.lisp\")",
+    );
+    must_run(
+        "include-backslashes",
+        "(include \"tests\\include\\lib\\a.lisp\")",
+    );
+}
+
+#[test]
+fn memory_sizes() -> Result<()> {
+    use crate::utils::{human_size, parse_size};
+
+    assert_eq!(parse_size("512")?, 512 << 20);
+    assert_eq!(parse_size("4G")?, 4 << 30);
+    assert_eq!(parse_size("256kb")?, 256 << 10);
+    assert!(parse_size("lots").is_err());
+    assert_eq!(human_size(1536 << 20), "1.5GB");
+    assert_eq!(human_size(12), "12.0B");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "exporters")]
+fn wizardiop_test_vectors() -> Result<()> {
+    let tmp = temp_dir();
+    let trace = tmp.path().join("test-vectors.json");
+    std::fs::write(
+        &trace,
+        r#"{"<prelude>": {"A": [1, 2, 3, 4], "B": [2, 4, 6, 9]}}"#,
+    )?;
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(defcolumns A B) (defconstraint c () (begin (vanishes! (- B (* 2 A))) (vanishes! A)))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, false)?;
+
+    let vectors = crate::exporters::wizardiop::test_vectors(&cs, 3, &Default::default())?
+        .into_iter()
+        .map(|v| (v.constraint, v.row, v.expected))
+        .collect::<Vec<_>>();
+    // the trace is prefixed by a padding row
+    let expected = [("c#0", 0, "0"), ("c#0", 1, "0"), ("c#0", 3, "0")]
+        .into_iter()
+        .chain([("c#1", 0, "0"), ("c#1", 1, "1"), ("c#1", 3, "3")])
+        .map(|(c, i, x)| (format!("\"{}\"", c), i, x.to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(vectors, expected);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "exporters")]
+fn rust_constraints() -> Result<()> {
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(defcolumns A B)
+         (defconstraint c () (begin (vanishes! (- B (* 2 A))) (vanishes! (shift A -1))))
+         (defconstraint first (:domain {0 -1}) (vanishes! (^ A 2)))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let cs = builder.into_constraint_set()?;
+    let escaped = crate::exporters::Mangling {
+        scheme: crate::structs::MangleScheme::Escaped,
+        ..Default::default()
+    };
+
+    let constraints =
+        crate::exporters::rust::render_constraints(&cs, &escaped, &Default::default())?;
+    assert_eq!(
+        constraints,
+        [
+            "// defined at Immediate expression:2:10",
+            "// c#0\nvanishes_everywhere(A.len(), |i| Some((B.at(i)? - (constant(\"2\") * A.at(i)?))))?;",
+            "// c#1\nvanishes_everywhere(A.len(), |i| Some(A.at(i - 1)?))?;",
+            "// defined at Immediate expression:3:10",
+            "// first\nvanishes_at(A.len(), 0, |i| Some(pow(A.at(i)?, 2)))?;\n// first\nvanishes_at(A.len(), -1, |i| Some(pow(A.at(i)?, 2)))?;",
+        ]
+    );
+    Ok(())
+}
+
+/// The arkworks circuit rendered from [`ARKWORKS_SOURCE`], compiled along with
+/// the tests to ensure that the Rust exporter produces valid code
+#[cfg(feature = "exporters")]
+#[path = "../tests/arkworks/circuit.rs"]
+mod arkworks_circuit;
+
+#[cfg(feature = "exporters")]
+const ARKWORKS_SOURCE: &str = "(defcolumns A B)
+(definterleaved I (A B))
+(defconstraint double () (vanishes! (- B (* 2 A))))
+(defconstraint cube (:domain {0}) (vanishes! (^ A 3)))
+(defconstraint interleaved (:domain {3}) (vanishes! (- I 2)))";
+
+#[test]
+#[cfg(feature = "exporters")]
+fn arkworks_circuit() -> Result<()> {
+    use ark_bls12_377::Fr;
+    use ark_relations::r1cs::{
+        ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError,
+    };
+    use arkworks_circuit::{Arguments, Circuit, Column, Trace};
+
+    let tmp = temp_dir();
+
+    // the compiled circuit must be the one currently rendered
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(ARKWORKS_SOURCE)?;
+    builder.expand_to(ExpansionLevel::top());
+    let cs = builder.into_constraint_set()?;
+    let out = tmp
+        .path()
+        .join("arkworks-circuit.rs")
+        .to_string_lossy()
+        .to_string();
+    crate::exporters::rust::render(&cs, Some(&out), &Default::default(), &Default::default())?;
+    assert_eq!(
+        std::fs::read_to_string(&out)?,
+        include_str!("../tests/arkworks/circuit.rs"),
+        "tests/arkworks/circuit.rs must be regenerated"
+    );
+
+    // and it enforces the constraints
+    struct NoArguments;
+    impl Arguments<Fr> for NoArguments {
+        fn inclusion(
+            &self,
+            _: ConstraintSystemRef<Fr>,
+            _: &str,
+            _: &[&Column<Fr>],
+            _: &[&Column<Fr>],
+        ) -> Result<(), SynthesisError> {
+            std::result::Result::Ok(())
+        }
+
+        fn permutation(
+            &self,
+            _: ConstraintSystemRef<Fr>,
+            _: &str,
+            _: &[&Column<Fr>],
+            _: &[&Column<Fr>],
+        ) -> Result<(), SynthesisError> {
+            std::result::Result::Ok(())
+        }
+    }
+    let satisfied = |a: [u64; 3], b: [u64; 3]| -> Result<bool> {
+        let r1cs = ConstraintSystem::<Fr>::new_ref();
+        Circuit {
+            trace: Trace {
+                _prelude___A: a.map(Fr::from).to_vec(),
+                _prelude___B: b.map(Fr::from).to_vec(),
+            },
+            arguments: NoArguments,
+        }
+        .generate_constraints(r1cs.clone())?;
+        Ok(r1cs.is_satisfied()?)
+    };
+    assert!(satisfied([0, 1, 2], [0, 2, 4])?);
+    assert!(!satisfied([0, 1, 2], [0, 2, 5])?);
+    assert!(!satisfied([2, 1, 2], [4, 2, 4])?);
+    assert!(!satisfied([0, 3, 2], [0, 6, 4])?);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "exporters")]
+fn unchanged_exports() -> Result<()> {
+    use itertools::Itertools;
+
+    let tmp = temp_dir();
+
+    // enough constraints for the rendered code to overflow the formatter pipe
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        &(0..500)
+            .map(|i| {
+                format!(
+                    "(defcolumns A{0}) (defconstraint c{0} () (vanishes! A{0}))",
+                    i
+                )
+            })
+            .join("\n"),
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let cs = builder.into_constraint_set()?;
+    let out = tmp
+        .path()
+        .join("unchanged-exports.rs")
+        .display()
+        .to_string();
+    let render = || {
+        crate::exporters::rust::render(&cs, Some(&out), &Default::default(), &Default::default())
+    };
+
+    assert_eq!(render()?, Some(out.clone()));
+    let written = std::fs::read_to_string(&out)?;
+    assert!(written.contains("A499"));
+    // an identical output is left untouched
+    assert_eq!(render()?, None);
+    std::fs::write(&out, "// stale")?;
+    assert_eq!(render()?, Some(out.clone()));
+    assert_eq!(std::fs::read_to_string(&out)?, written);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "exporters")]
+fn circom_constraints() -> Result<()> {
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(defcolumns A B)
+         (defconstraint c () (vanishes! (- B (* A (shift A 1) 3))))
+         (defconstraint last (:domain {-1}) (vanishes! (^ A 2)))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let cs = builder.into_constraint_set()?;
+    let escaped = crate::exporters::Mangling {
+        scheme: crate::structs::MangleScheme::Escaped,
+        ..Default::default()
+    };
+
+    let constraints =
+        crate::exporters::circom::render_constraints(&cs, &escaped, &Default::default())?;
+    assert_eq!(
+        constraints,
+        [
+            "// defined at Immediate expression:2:10",
+            "// c\nsignal c1_t0[LEN_prelude - 1];\nfor (var i = 0; i < LEN_prelude - 1; i++) {\n    c1_t0[i] <== A[i] * (A[i + 1] * 3);\n    (B[i] - c1_t0[i]) === 0;\n}",
+            "// defined at Immediate expression:3:10",
+            "// last\nfor (var i = LEN_prelude - 1; i < LEN_prelude - 1 + 1; i++) {\n    A[i] * A[i] === 0;\n}",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "exporters")]
+fn exported_modules() -> Result<()> {
+    use crate::exporters::ModuleFilter;
+    use itertools::Itertools;
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module a) (defcolumns X) (defconstraint ca () (vanishes! X))
+         (module b) (defcolumns Y) (defconstraint cb () (vanishes! Y))
+         (module c) (defcolumns Z)
+         (module d) (defcolumns W)
+         (deflookup ab (b.Y) (a.X))
+         (deflookup cb (b.Y) (c.Z))
+         (defconstraint cross () (vanishes! (- d.W c.Z)))",
+    )?;
+    let cs = builder.into_constraint_set()?;
+
+    let selected = |modules: &[&str]| {
+        let filter = ModuleFilter::new(&cs, &modules.iter().map(|m| m.to_string()).collect_vec())?;
+        Ok(["a", "b", "c", "d"]
+            .into_iter()
+            .filter(|m| filter.contains(m))
+            .collect_vec())
+    };
+    assert_eq!(selected(&[])?, ["a", "b", "c", "d"]);
+    // a depends on b, but b does not depend on a
+    assert_eq!(selected(&["a"])?, ["a", "b"]);
+    assert_eq!(selected(&["b"])?, ["b"]);
+    assert_eq!(selected(&["c"])?, ["b", "c"]);
+    // `cross` is not a lookup, and would dangle without c
+    assert!(selected(&["d"]).is_err());
+    assert!(selected(&["nope"]).is_err());
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "exporters")]
+fn configurable_mangling() {
+    use crate::{exporters::Mangling, structs::Handle};
+    use convert_case::Case;
+
+    let h = Handle::new("hub", "stamp-x");
+    assert_eq!(Mangling::default().mangle(&h), "hub__stamp_x");
+    let m = Mangling {
+        separator: "_".into(),
+        case: Some(Case::Pascal),
+        ..Default::default()
+    };
+    assert_eq!(m.mangle(&h), "HubStampX");
+    assert_eq!(m.mangle_ith(&h, 2), "HubStampXɩ2");
+
+    // hub.a-b and hub.a_b are both mangled into hub__a_b
+    let colliding = [Handle::new("hub", "a-b"), Handle::new("hub", "a_b")];
+    let names = || {
+        colliding
+            .iter()
+            .map(|h| (Mangling::default().mangle(h), h.to_string()))
+    };
+    assert!(crate::exporters::ensure_unique("identifiers", names().take(1)).is_ok());
+    assert!(crate::exporters::ensure_unique("identifiers", names()).is_err());
+}
+
+#[test]
+#[cfg(unix)]
+fn daemon() -> Result<()> {
+    use crate::daemon::{submit, Daemon, Request};
+
+    let tmp = temp_dir();
+
+    let dir = tmp.path();
+    let socket = dir.join("test-daemon.sock");
+    let good = dir.join("daemon-good.json");
+    let bad = dir.join("daemon-bad.json");
+    std::fs::write(&good, r#"{"A": [1, 2, 3], "B": [2, 4, 6]}"#)?;
+    std::fs::write(&bad, r#"{"A": [1, 2, 3], "B": [2, 4, 7]}"#)?;
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(defcolumns A B) (defconstraint double () (vanishes! (- B (* 2 A))))")?;
+    let daemon = Daemon::new(builder.into_constraint_set()?)?;
+    let listener = Daemon::bind(&socket)?;
+    std::thread::spawn(move || daemon.serve(listener));
+
+    let check = |trace: &std::path::Path| Request::Check {
+        trace: trace.to_str().unwrap().to_owned(),
+        only: None,
+        skip: Vec::new(),
+        limit_rows: None,
+        continue_on_error: false,
+        strict_padding: Vec::new(),
+        strict_magma: false,
+        rows: None,
+        partial: false,
+    };
+    // every request is processed on a fresh copy of the constraint set
+    for _ in 0..2 {
+        submit(&socket, check(&good))?;
+        assert!(submit(&socket, check(&bad)).is_err());
+    }
+    // URLs are fetched as they are by the daemon, which can not read STDIN
+    let url = "http://127.0.0.1:1/trace.json";
+    let err = format!("{:#}", submit(&socket, check(url.as_ref())).unwrap_err());
+    assert!(err.contains(&format!("`{}`", url)), "{}", err);
+    let err = submit(&socket, check(crate::input::STDIN.as_ref())).unwrap_err();
+    assert!(err.to_string().contains("STDIN"));
+
+    let out = dir.join("daemon-computed.json");
+    submit(
+        &socket,
+        Request::Compute {
+            trace: good.to_str().unwrap().to_owned(),
+            out: out.to_str().unwrap().to_owned(),
+            fail_on_missing: false,
+            verify_computations: false,
+            checksums: false,
+            base64: false,
+            compact: false,
+            only: None,
+            skip: Vec::new(),
+            limit_rows: None,
+        },
+    )?;
+    assert!(std::fs::read_to_string(&out)?.contains("\"columns\""));
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn daemon_matches_cli() -> Result<()> {
+    use crate::check::CheckConfig;
+    use crate::daemon::{submit, Daemon, Request};
+    use crate::transformer::AutoConstraint;
+    use clap::Parser;
+
+    let tmp = temp_dir();
+
+    const SOURCE: &str = "(defcolumns A (B :byte :comp (* A 200)) (C :comp (* A 2)))
+         (defconstraint c-is-2a () (vanishes! (- C (* A 2))))
+         (module m) (defcolumns X Y) (deflookup l (X) ((shift Y -2)))";
+    let builder = || -> Result<SourceLoader> {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(SOURCE)?;
+        Ok(builder)
+    };
+
+    let dir = tmp.path();
+    let socket = dir.join("test-daemon-cli.sock");
+    // B overflows its type, and the provided C is wrong on the last row
+    let trace = dir.join("daemon-cli.json");
+    std::fs::write(
+        &trace,
+        r#"{"A": [0, 1, 2, 0, 3], "C": [0, 2, 4, 0, 0], "m": {"X": [0, 0], "Y": [0, 0]}}"#,
+    )?;
+    let trace = trace.to_str().unwrap().to_owned();
+    let out = dir.join("daemon-cli-computed.json");
+    let out = out.to_str().unwrap().to_owned();
+
+    let daemon = Daemon::new(builder()?.into_constraint_set()?)?;
+    let listener = Daemon::bind(&socket)?;
+    std::thread::spawn(move || daemon.serve(listener));
+
+    // the same steps as the CLI check command
+    let cli_check = |config: CheckConfig| -> Result<()> {
+        let mut cs = builder()?.into_constraint_set()?;
+        crate::compute::compute_trace_for(&trace, &mut cs, false, &None, &[])?;
+        crate::check::check(
+            &cs,
+            &None,
+            &[],
+            config.report(crate::check::ReportStyle::Silent),
+        )
+    };
+    let check = |strict_padding: Vec<String>, strict_magma, rows| Request::Check {
+        trace: trace.clone(),
+        only: None,
+        skip: Vec::new(),
+        limit_rows: None,
+        continue_on_error: false,
+        strict_padding,
+        strict_magma,
+        rows,
+        partial: false,
+    };
+    for (request, config, holds) in [
+        (
+            check(vec![], false, Some(0..4)),
+            CheckConfig::new().rows(Some(0..4)),
+            true,
+        ),
+        (check(vec![], false, None), CheckConfig::new(), false),
+        (
+            check(vec![], true, Some(0..4)),
+            CheckConfig::new().strict_magma(true).rows(Some(0..4)),
+            false,
+        ),
+        (
+            check(vec!["m".into()], false, Some(0..4)),
+            CheckConfig::new()
+                .strict_padding(vec!["m".into()])
+                .rows(Some(0..4)),
+            false,
+        ),
+    ] {
+        assert_eq!(
+            submit(&socket, request.clone()).is_ok(),
+            holds,
+            "{:?}",
+            request
+        );
+        assert_eq!(cli_check(config).is_ok(), holds, "{:?}", request);
+    }
+
+    // the same steps as the CLI compute command
+    let cli_compute = |verify_computations: bool| -> Result<()> {
+        let mut builder = builder()?;
+        builder.expand_to(ExpansionLevel::top());
+        builder.auto_constraints(AutoConstraint::all());
+        let mut cs = builder.into_constraint_set()?;
+        crate::compute::compute_trace_for(&trace, &mut cs, false, &None, &[])?;
+        if verify_computations {
+            crate::compute::verify_computations(&cs)?;
+        }
+        Ok(())
+    };
+    for verify_computations in [false, true] {
+        let request = Request::Compute {
+            trace: trace.clone(),
+            out: out.clone(),
+            fail_on_missing: false,
+            verify_computations,
+            checksums: false,
+            base64: false,
+            compact: false,
+            only: None,
+            skip: Vec::new(),
+            limit_rows: None,
+        };
+        assert_eq!(submit(&socket, request).is_ok(), !verify_computations);
+        assert_eq!(
+            cli_compute(verify_computations).is_ok(),
+            !verify_computations
+        );
+    }
+
+    // the CLI accepts every option it forwards to the daemon...
+    let args = crate::Args::try_parse_from([
+        "corset",
+        "check",
+        "-T",
+        "t.json",
+        "--daemon",
+        "s",
+        "--rows",
+        "0..4",
+        "--partial",
+    ])?;
+    assert!(matches!(
+        args.command,
+        Some(crate::Commands::Check {
+            rows: Some(_),
+            partial: true,
+            daemon: Some(_),
+            ..
+        })
+    ));
+    // ...and rejects the others
+    for option in ["--differential", "--report"] {
+        assert!(crate::Args::try_parse_from([
+            "corset", "check", "-T", "t.json", "--daemon", "s", option,
+        ])
+        .is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn shared_perspectives() {
+    let systx = |module: &str, columns: &str, shared: &str| {
+        format!(
+            "(module {module}) (defcolumns (S :binary)) (defperspective systx {shared} S ({columns}))
+             (defconstraint c (:perspective systx) (vanishes! (- FROM TO)))"
+        )
+    };
+    must_run(
+        "shared",
+        &(systx("hub", "(FROM :i64) (TO :i64)", ":shared")
+            + &systx("rom", "(TO :i64) (FROM :i64)", ":shared")),
+    );
+    // same-named private perspectives are independent
+    must_run(
+        "private",
+        &(systx("hub", "FROM TO", "") + &systx("rom", "FROM TO (X :byte)", "")),
+    );
+    // shared perspectives must hold the same columns
+    must_fail(
+        "different-types",
+        &(systx("hub", "(FROM :i64) (TO :i64)", ":shared")
+            + &systx("rom", "(FROM :i64) (TO :i32)", ":shared")),
+    );
+    must_fail(
+        "different-columns",
+        &(systx("hub", "FROM TO", ":shared") + &systx("rom", "FROM TO X", ":shared")),
+    );
+    // all modules must agree on the sharing
+    must_fail(
+        "not-shared",
+        &(systx("hub", "FROM TO", ":shared") + &systx("rom", "FROM TO", "")),
+    );
+}
+
+#[test]
+fn build_info_roundtrip() -> Result<()> {
+    use crate::{compiler::ConstraintSet, version::BuildInfo};
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(defcolumns A) (defconstraint c () A)")?;
+    let mut cs = builder.into_constraint_set()?;
+
+    // artifacts predating the build information can still be loaded
+    let old: ConstraintSet =
+        ron::from_str(&ron::ser::to_string(&cs)?.replace(",build_info:None", ""))?;
+    assert!(old.build_info.is_none());
+
+    cs.build_info = Some(BuildInfo::current());
+    let loaded: ConstraintSet = ron::from_str(&ron::ser::to_string(&cs)?)?;
+    assert_eq!(loaded.build_info, Some(BuildInfo::current()));
+    assert_eq!(
+        loaded.build_info.unwrap().stdlib_hash,
+        Some(crate::compiler::parser::stdlib::stdlib_hash())
+    );
+
+    // nor the stdlib hash
+    let old: ConstraintSet = ron::from_str(&ron::ser::to_string(&cs)?.replace(
+        &format!(
+            ",stdlib_hash:Some(\"{}\")",
+            crate::compiler::parser::stdlib::stdlib_hash()
+        ),
+        "",
+    ))?;
+    assert!(old.build_info.unwrap().stdlib_hash.is_none());
+    Ok(())
+}
+
+#[test]
+fn stdlib_hash() -> Result<()> {
+    use crate::compiler::parser::stdlib::stdlib_hash;
+    use clap::{CommandFactory, Parser};
+
+    let tmp = temp_dir();
+
+    // the hash is only recorded if the stdlib is linked...
+    let builder = |no_stdlib| {
+        let mut builder = SourceLoader::from_sources(no_stdlib, false);
+        builder.add_source("(defcolumns A)")?;
+        Ok(builder)
+    };
+    assert_eq!(
+        builder(false)?.build_info().stdlib_hash,
+        Some(stdlib_hash())
+    );
+    let unlinked = builder(true)?;
+    let build_info = unlinked.build_info();
+    assert!(build_info.stdlib_hash.is_none());
+
+    // ...including by the compiled constraint sets
+    let mut cs = unlinked.into_constraint_set()?;
+    cs.build_info = Some(build_info);
+    let bin = tmp.path().join("stdlib-hash.bin");
+    std::fs::write(&bin, cs.to_binary()?)?;
+    let reloaded = SourceLoader::from_bin(bin.to_str().unwrap())?;
+    assert!(reloaded.build_info().stdlib_hash.is_none());
+
+    // the stdlib may be printed without any source nor subcommand
+    let args = crate::Args::try_parse_from(["corset", "--print-stdlib"])?;
+    assert!(args.print_stdlib && args.command.is_none());
+    assert!(crate::Args::try_parse_from(["corset", "--print-stdlib", "--no-stdlib"]).is_err());
+    assert!(crate::Args::command()
+        .render_help()
+        .to_string()
+        .contains("--print-stdlib"));
+    Ok(())
+}
+
+#[test]
+fn version_flags() -> Result<()> {
+    use crate::version::BuildInfo;
+    use clap::{CommandFactory, Parser};
+
+    let args = crate::Args::try_parse_from(["corset", "--version", "--json"])?;
+    assert!(args.version && args.version_json && args.command.is_none());
+    assert!(crate::Args::try_parse_from(["corset", "-V"]).is_ok_and(|a| !a.version_json));
+    // `--json` only refines `--version`
+    assert!(crate::Args::try_parse_from(["corset", "--json"]).is_err());
+    assert!(crate::Args::command()
+        .render_version()
+        .starts_with(&format!("corset {}", env!("CARGO_PKG_VERSION"))));
+
+    // the build date is set whatever the platform
+    let date = BuildInfo::current().build_date;
+    assert!(
+        date.len() == 20 && date.as_bytes()[10] == b'T' && date.ends_with('Z'),
+        "{}",
+        date
+    );
+    Ok(())
+}
+
+#[test]
+fn trace_checksums() -> Result<()> {
+    let tmp = temp_dir();
+    let dir = tmp.path();
+    let trace = dir.join("checksums-trace.json");
+    std::fs::write(
+        &trace,
+        r#"{"<prelude>": {"A": [1, 2, 3], "B": [0, 255, 65536]}}"#,
+    )?;
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(defcolumns A B)")?;
+    let mut cs = builder.into_constraint_set()?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, false)?;
+    let mut out = Vec::new();
+    cs.write(&mut out, true, crate::import::ValuesEncoding::Hex)?;
+    let computed = String::from_utf8(out)?;
+
+    // the checksum of a column is the BLAKE3 of its 32-bytes values
+    let json = serde_json::from_str::<serde_json::Value>(&computed)?;
+    assert_eq!(json["checksums"]["algorithm"], "blake3");
+    let mut hasher = blake3::Hasher::new();
+    for x in [0u8, 1, 2, 3] {
+        let mut value = [0; 32];
+        value[31] = x;
+        hasher.update(&value);
+    }
+    assert_eq!(
+        json["checksums"]["columns"]["A"],
+        hasher.finalize().to_hex().as_str()
+    );
+
+    let expanded = dir.join("checksums-expanded.json");
+    std::fs::write(&expanded, &computed)?;
+    crate::checksum::verify_trace(expanded.to_str().unwrap())?;
+
+    std::fs::write(&expanded, computed.replace("\"0x0ff\"", "\"0x0fe\""))?;
+    assert!(crate::checksum::verify_trace(expanded.to_str().unwrap()).is_err());
+    Ok(())
+}
+
+#[test]
+fn base64_columns() -> Result<()> {
+    use crate::{check::CheckConfig, import::ValuesEncoding};
+    use base64::Engine;
+
+    let tmp = temp_dir();
+
+    let encode = |xs: &[u8]| {
+        let mut bytes = Vec::new();
+        for x in xs {
+            bytes.extend([0; 31]);
+            bytes.push(*x);
+        }
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    };
+    let dir = tmp.path();
+    let trace = dir.join("base64-trace.json");
+    std::fs::write(
+        &trace,
+        format!(
+            r#"{{"A": {{"encoding": "base64-fixed32", "data": "{}"}}, "B": [2, 4, 255]}}"#,
+            encode(&[1, 2, 3])
+        ),
+    )?;
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder
+        .add_source("(defcolumns A (B :byte)) (defconstraint c () (vanishes! (- B (* 2 A))))")?;
+    let mut cs = builder.into_constraint_set()?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, false)?;
+    assert!(crate::check::check(&cs, &None, &[], CheckConfig::new()).is_err());
+
+    // encoded columns are checksummed like the hexadecimal ones
+    let mut written = Vec::new();
+    for encoding in [ValuesEncoding::Hex, ValuesEncoding::Base64Fixed32] {
+        let mut out = Vec::new();
+        cs.write(&mut out, true, encoding)?;
+        let expanded = dir.join("base64-expanded.json");
+        std::fs::write(&expanded, &out)?;
+        crate::checksum::verify_trace(expanded.to_str().unwrap())?;
+        written.push(serde_json::from_slice::<serde_json::Value>(&out)?);
+    }
+    assert_eq!(written[0]["checksums"], written[1]["checksums"]);
+    assert_eq!(
+        written[1]["columns"]["A"]["values"]["data"],
+        // the first row is the padding one
+        encode(&[0, 1, 2, 3]).as_str()
+    );
+
+    std::fs::write(
+        &trace,
+        r#"{"A": {"encoding": "base64-fixed32", "data": "AAAA"}, "B": [2]}"#,
+    )?;
+    assert!(crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, false).is_err());
+    Ok(())
+}
+
+#[test]
+fn compact_columns() -> Result<()> {
+    use crate::{compiler::ConstraintSet, import::ValuesEncoding};
+    use itertools::Itertools;
+
+    let tmp = temp_dir();
+
+    let dir = tmp.path();
+    let trace = dir.join("compact-trace.json");
+    let xs = (0..1000).map(|i| (i % 3).to_string()).join(",");
+    std::fs::write(&trace, format!(r#"{{"A": [{}]}}"#, xs))?;
+    let build = || -> Result<ConstraintSet> {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source("(defcolumns A (B :comp (* A 0x1000000000000000000000000000000)))")?;
+        builder.expand_to(ExpansionLevel::top());
+        builder.into_constraint_set()
+    };
+    let mut cs = build()?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, false)?;
+
+    let mut written = Vec::new();
+    for encoding in [
+        ValuesEncoding::Hex,
+        ValuesEncoding::Base64Fixed32,
+        ValuesEncoding::Compact,
+    ] {
+        let mut out = Vec::new();
+        cs.write(&mut out, true, encoding)?;
+        written.push(out);
+    }
+    // the large values of B are only written once
+    assert!(written[2].len() * 10 < written[0].len());
+    let compact = serde_json::from_slice::<serde_json::Value>(&written[2])?;
+    assert_eq!(compact["columns"]["B"]["values"]["encoding"], "varint-dict");
+    let parsed = written
+        .iter()
+        .map(|w| serde_json::from_slice::<serde_json::Value>(w))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    // the checksums only depend on the values
+    assert_eq!(parsed[0]["checksums"], parsed[1]["checksums"]);
+    assert_eq!(parsed[0]["checksums"], parsed[2]["checksums"]);
+
+    // the compact output is read back like the other ones
+    let expanded = dir.join("compact-expanded.json");
+    std::fs::write(&expanded, &written[2])?;
+    crate::checksum::verify_trace(expanded.to_str().unwrap())?;
+    let mut reread = build()?;
+    reread.columns.resolve_paddings()?;
+    crate::import::parse_expanded_trace(expanded.to_str().unwrap(), &mut reread)?;
+    let mut out = Vec::new();
+    reread.write(&mut out, true, ValuesEncoding::Hex)?;
+    // the IDs, hence the order, of the columns may differ from one
+    // compilation to the other
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&out)?,
+        parsed[0]
+    );
+
+    std::fs::write(
+        &trace,
+        r#"{"A": {"encoding": "varint-dict", "data": "AQIBAgM="}}"#,
+    )?;
+    assert!(crate::compute::compute_trace(trace.to_str().unwrap(), &mut build()?, false).is_err());
+    Ok(())
+}
+
+#[test]
+fn register_refs() -> Result<()> {
+    use crate::{column::RegisterRef, structs::Handle};
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(defcolumns A B) (definterleaved C (A B))")?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+
+    for (c, column) in cs.columns.iter() {
+        let r = cs.columns.register_ref_of(&c)?;
+        assert_eq!(r, RegisterRef::ID(column.register.unwrap()));
+        assert!(std::ptr::eq(
+            cs.columns.register(&r).unwrap(),
+            cs.columns.register_of(&c)
+        ));
+    }
+    let a = RegisterRef::from(Handle::new("<prelude>", "A"));
+    assert_eq!(
+        cs.columns.register_id(&a),
+        cs.columns
+            .by_handle(&Handle::new("<prelude>", "A"))?
+            .register
+    );
+
+    let len = cs.columns.registers.len();
+    for missing in [
+        RegisterRef::from(len),
+        RegisterRef::from(Handle::new("<prelude>", "D")),
+    ] {
+        assert!(cs.columns.register(&missing).is_none());
+        assert!(cs.columns.set_register_value(&missing, vec![], 0).is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn expanded_trace_loading() -> Result<()> {
+    use crate::{
+        compiler::{ColumnRef, ConstraintSet},
+        transformer::AutoConstraint,
+    };
+
+    let tmp = temp_dir();
+
+    let src =
+        "(defcolumns A B) (definterleaved C (A B)) (defconstraint c () (if-zero A (vanishes! B)))";
+    let build = || -> Result<ConstraintSet> {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(src)?;
+        builder.expand_to(ExpansionLevel::top());
+        builder.auto_constraints(AutoConstraint::all());
+        builder.into_constraint_set()
+    };
+
+    let dir = tmp.path();
+    let trace = dir.join("expanded-loading.json");
+    std::fs::write(&trace, r#"{"A": [0, 2, 3], "B": [0, 5, 7]}"#)?;
+    assert!(!crate::import::is_expanded_trace(trace.to_str().unwrap())?);
+    let mut computed = build()?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut computed, true)?;
+    let expanded = dir.join("expanded-loading-out.json");
+    computed.write(
+        &mut std::fs::File::create(&expanded)?,
+        false,
+        crate::import::ValuesEncoding::Hex,
+    )?;
+    assert!(crate::import::is_expanded_trace(
+        expanded.to_str().unwrap()
+    )?);
+
+    let mut loaded = build()?;
+    loaded.columns.resolve_paddings()?;
+    crate::import::parse_expanded_trace(expanded.to_str().unwrap(), &mut loaded)?;
+    for h in computed.columns.all() {
+        // the IDs of the columns may differ from one compilation to the other
+        let handle: ColumnRef = computed.columns.column(&h)?.handle.clone().into();
+        assert!(loaded.columns.is_computed(&handle));
+        let (expected, found) = (
+            computed.columns.backing(&h).unwrap(),
+            loaded.columns.backing(&handle).unwrap(),
+        );
+        assert_eq!(expected.spilling(), found.spilling());
+        assert_eq!(
+            expected.iter(&computed.columns).collect::<Vec<_>>(),
+            found.iter(&loaded.columns).collect::<Vec<_>>()
+        );
+    }
+    crate::check::check(&loaded, &None, &[], Default::default())?;
+    Ok(())
+}
+
+#[test]
+fn syntax_errors_recovery() {
+    use crate::compiler::parser::parser::parse;
+
+    let src = "(defcolumns A B)
+(defconstraint foo () (vanishes! A)
+(defconstraint bar () (vanishes! [B))
+; (unbalanced comment
+(defconstraint baz () (vanishes! B))
+)";
+    let err = format!("{:?}", parse(src).unwrap_err());
+    assert!(err.contains("3 syntax errors found"));
+    for location in ["--> 2:36", "--> 3:36", "--> 6:1"] {
+        assert!(err.contains(location), "{} not in {}", location, err);
+    }
+
+    // a single error is reported as is
+    let err = format!("{:?}", parse("(defcolumns A))").unwrap_err());
+    assert!(err.contains("--> 1:15") && !err.contains("syntax errors found"));
+
+    // errors are located in large sources too
+    let src = "(defconstraint c () (vanishes! [A))\n".repeat(500);
+    let err = format!("{:?}", parse(&src).unwrap_err());
+    assert!(err.contains("500 syntax errors found"));
+    assert!(err.contains("--> 500:34"));
+}
+
+#[test]
+fn quoted_identifiers() -> Result<()> {
+    use crate::{compiler::parser::parse_simple_ast, exporters::Mangling, structs::Handle};
+
+    let src = "(module |my mod|)
+(defcolumns |a col| (SEL :binary) |1st|)
+(defperspective |p q| SEL ((|x!y| :binary)))
+(defconstraint |c 1| () (vanishes! (* |a col| |p q|/|x!y| |1st|)))
+(module other)
+(defcolumns A)
+(deflookup l (|my mod|.|a col|) (A))";
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(src)?;
+    let cs = builder.into_constraint_set()?;
+    for (module, name) in [("my mod", "a col"), ("my mod", "1st"), ("other", "A")] {
+        cs.columns.by_handle(&Handle::new(module, name))?;
+    }
+    assert!(cs.constraints.iter().any(|c| c.name() == "my mod.c 1"));
+
+    // the exporters get valid and distinct identifiers
+    let identifier = regex_lite::Regex::new("^[A-Za-z_][A-Za-z0-9_ɩ]*$").unwrap();
+    let mangling = Mangling {
+        scheme: crate::structs::MangleScheme::Escaped,
+        ..Default::default()
+    };
+    let mangled = cs
+        .columns
+        .iter_cols()
+        .map(|c| (mangling.mangle(&c.handle), c.handle.to_string()))
+        .collect::<Vec<_>>();
+    for (m, _) in mangled.iter() {
+        assert!(identifier.is_match(m), "{} is not a valid identifier", m);
+    }
+    crate::exporters::ensure_unique("columns", mangled)?;
+
+    // quoted symbols are formatted back as written
+    let formatted = parse_simple_ast(&[("quoted", src)])?[0].1.format();
+    assert!(formatted.contains("|p q|/|x!y|"));
+
+    // bars elsewhere than at the start of a name are deprecated, but still
+    // part of the symbols, as they used to be
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(defcolumns a|b c|d| |e f|g)")?;
+    let cs = builder.into_constraint_set()?;
+    for name in ["a|b", "c|d|", "e fg"] {
+        cs.columns.by_handle(&Handle::new("", name))?;
+    }
+    Ok(())
+}
+
+#[test]
+fn assumed_constants() -> Result<()> {
+    use crate::structs::Handle;
+    use num_bigint::BigInt;
+
+    let src = "(defconst SIZE 4) (defcolumns A) (module m) (defconst W 2 V (* W 3))";
+    let compile = |assumptions: &[&str]| {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(src)?;
+        builder.assume(
+            &assumptions
+                .iter()
+                .map(|a| crate::utils::parse_assumption(a))
+                .collect::<Result<Vec<_>>>()?,
+        );
+        builder.into_constraint_set()
+    };
+
+    let cs = compile(&["SIZE=7", "m.W=0x10"])?;
+    assert_eq!(
+        cs.constants[&Handle::new("<prelude>", "SIZE")],
+        BigInt::from(7)
+    );
+    // constants defined from assumed ones follow them
+    assert_eq!(cs.constants[&Handle::new("m", "V")], BigInt::from(48));
+
+    for wrong in ["A=1", "W=1", "m.NOPE=1"] {
+        assert!(compile(&[wrong]).is_err(), "{} should fail", wrong);
+    }
+    assert!(crate::utils::parse_assumption("SIZE").is_err());
+    Ok(())
+}
+
+#[test]
+fn constant_expressions() -> Result<()> {
+    use crate::structs::Handle;
+    use ark_ff::{BigInteger, PrimeField};
+    use num_bigint::{BigInt, Sign};
+
+    let compile = |src: &str| {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(src)?;
+        builder.into_constraint_set()
+    };
+
+    // constants may refer to constants defined later on
+    let cs = compile(
+        "(defconst A (+ B 1) B (pow2 4)) (module m) (defconst C (- D A) D (- FIELD_MODULUS 1))",
+    )?;
+    assert_eq!(
+        cs.constants[&Handle::new("<prelude>", "A")],
+        BigInt::from(17)
+    );
+    let modulus = BigInt::from_bytes_le(Sign::Plus, &ark_bls12_377::Fr::MODULUS.to_bytes_le());
+    assert_eq!(cs.constants[&Handle::new("m", "C")], modulus - 18);
+
+    let err = compile("(defconst A (+ B 1) B (* C 2) C A)").unwrap_err();
+    assert!(crate::check::strip_colors(&format!("{:?}", err)).contains("A → B → C → A"));
+    assert!(compile("(defconst A (+ A 1))").is_err());
+    Ok(())
+}
+
+#[test]
+fn function_lints() -> Result<()> {
+    use crate::compiler::parser::{lints::Lint, stdlib::STDLIB};
+
+    let src = "(defcolumns A B) (defconst FLAG 0)
+(defun (f x y _z) (if-zero FLAG (+ x A) (* x 2)))
+(defun (g x) (if-eq-else FLAG 1 x B))
+(defun (h x) (if-not-zero x A (if-zero [B x] 1)))
+(defpurefun (p x) (if-zero 3 x))";
+    let sources = [("stdlib", STDLIB), ("user", src)];
+    let settings = crate::compiler::CompileSettings::default();
+    let (ctx, asts) = crate::compiler::parser::parse(&sources, &settings)?;
+    let lints = crate::compiler::parser::lints::pass(&asts[1].1, ctx, &settings)?;
+
+    let dead = |function: &str, conditional: &str, branch, lc| Lint::DeadBranch {
+        function: function.into(),
+        conditional: conditional.into(),
+        branch,
+        lc,
+    };
+    assert_eq!(
+        lints,
+        vec![
+            Lint::UnusedArgument {
+                function: "f".into(),
+                argument: "y".into(),
+                lc: (2, 1)
+            },
+            dead("f", "if-zero", "else", (2, 19)),
+            dead("g", "if-eq-else", "then", (3, 14)),
+            dead("p", "if-zero", "then", (5, 19)),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn implicit_promotions() -> Result<()> {
+    use crate::compiler::{
+        parser::{lints::Lint, stdlib::STDLIB},
+        Magma,
+    };
+
+    let src = "(defcolumns (B :byte) (W :i32) (F :binary))
+(defconstraint mixed () (vanishes! (+ B (* F W))))
+(defconstraint cast () (vanishes! (+ (cast B :i32) W)))
+(defconstraint close () (vanishes! (- B (cast W :i12))))
+(defconstraint eq () (eq! B W))";
+    let sources = [("stdlib", STDLIB), ("user", src)];
+    let lints = |promotion_gap| -> Result<Vec<Lint>> {
+        let settings = crate::compiler::CompileSettings {
+            promotion_gap,
+            ..Default::default()
+        };
+        let (ctx, asts) = crate::compiler::parser::parse(&sources, &settings)?;
+        crate::compiler::parser::lints::pass(&asts[1].1, ctx, &settings)
+    };
+
+    // the operands are pretty-printed, so only their magmas are compared
+    let promotions = |promotion_gap| -> Result<Vec<_>> {
+        Ok(lints(promotion_gap)?
+            .into_iter()
+            .map(|l| match l {
+                Lint::ImplicitPromotion {
+                    constraint,
+                    narrow,
+                    wide,
+                    lc,
+                } => (constraint, narrow.1, wide.1, lc),
+                _ => unreachable!(),
+            })
+            .collect())
+    };
+    assert!(lints(None)?.is_empty());
+    assert_eq!(
+        promotions(Some(8))?,
+        vec![
+            ("mixed".into(), Magma::byte(), Magma::integer(32), (2, 36)),
+            ("eq".into(), Magma::byte(), Magma::integer(32), (5, 22)),
+        ]
+    );
+    assert!(lints(Some(24))?.is_empty());
+
+    // casting a constant checks that it fits its new type
+    must_fail(
+        "cast",
+        "(defcolumns A) (defconstraint c () (vanishes! (+ A (cast 256 :byte))))",
+    );
+    make(
+        "cast",
+        "(defcolumns A) (defconstraint c () (vanishes! (+ A (cast 255 :byte))))",
+    )
+}
+
+#[test]
+fn malformed_definitions() {
+    // Inputs that used to make the compiler panic rather than fail
+    for src in [
+        "(defcolumns 1)",
+        "(defcolumns (A :i99999999999999999999999))",
+        "(defcolumns (A :i300))",
+        "(defcolumns A) (deflookup l (A) ((-)))",
+        "(defcolumns A) (deflookup l (()) (()))",
+        "(defcolumns A) (definrange () 300)",
+        "(defcolumns A) (defconstraint c () (for i A A))",
+        "(defcolumns A) (defconstraint c () (reduce + A))",
+        "(defcolumns A) (defpermutation (P) ((- (~ 0))))",
+        "(defcolumns A) (defconstraint c (:guard (if-zero A 1)) A)",
+    ] {
+        must_fail("malformed", src);
+    }
+}
+
+mod properties {
+    use super::make;
+    use crate::compiler::parser::parse_simple_ast;
+    use proptest::prelude::*;
+
+    /// Random s-expressions built from tokens the parser is likely to
+    /// interpret, so that most of them go past the syntactic stage
+    fn sexpr() -> impl Strategy<Value = String> {
+        const LEAVES: &str = "A B C X i module begin if + - * ^ ~ shift nth for let reduce len \
+            eq! if-zero if-not-zero vanishes! inv debug :guard :domain :padding :i8 :byte \
+            :binary :display :hex -1 0 1 2 300 [1:3] ()";
+        let leaf = prop::sample::select(
+            LEAVES
+                .split_whitespace()
+                .chain(["{1 2}", "[C 1]", "[C 7]"])
+                .collect::<Vec<_>>(),
+        )
+        .prop_map(str::to_owned);
+        leaf.prop_recursive(4, 32, 5, |inner| {
+            prop::collection::vec(inner, 0..5).prop_map(|xs| format!("({})", xs.join(" ")))
+        })
+    }
+
+    /// Random top-level forms wrapping random s-expressions
+    fn form() -> impl Strategy<Value = String> {
+        (0usize..7, prop::collection::vec(sexpr(), 2..4)).prop_map(|(k, es)| match k {
+            0 => format!("(defconstraint c () {})", es.join(" ")),
+            1 => format!("(defconstraint c (:guard {}) {})", es[0], es[1]),
+            2 => format!("(deflookup l ({}) ({}))", es[0], es[1]),
+            3 => format!("(definrange {} {})", es[0], es[1]),
+            4 => format!("(defcolumns {})", es.join(" ")),
+            5 => format!("(defpermutation (P Q) ({} {}))", es[0], es[1]),
+            _ => format!(
+                "(defperspective p {} ((Z :byte))) (defconstraint d (:perspective p) {})",
+                es[0], es[1]
+            ),
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn compiling_never_panics(forms in prop::collection::vec(form(), 1..3)) {
+            let src = format!("(defcolumns A B (C :array[3]) (X :byte)) {}", forms.join(" "));
+            let _ = make("property", &src);
+        }
+
+        #[test]
+        fn formatting_is_stable(forms in prop::collection::vec(form(), 1..4)) {
+            let src = forms.join("\n");
+            let format = |src: &str| parse_simple_ast(&[("property", src)]).unwrap()[0].1.format();
+            let formatted = format(&src);
+            let reformatted = format(&formatted);
+            prop_assert_eq!(formatted, reformatted);
+        }
+    }
+}
+
+#[test]
+fn selected_computations() -> Result<()> {
+    let tmp = temp_dir();
+    let trace = tmp.path().join("selected-computations.json");
+    std::fs::write(
+        &trace,
+        r#"{"<prelude>": {"A": [1, 2], "B": [3, 4], "C": [5, 6], "D": [7, 8]}}"#,
+    )?;
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(defcolumns A B C D) (definterleaved X (A B)) (definterleaved Y (C D))
+         (defconstraint cx () (vanishes! (- X X))) (defconstraint cy () (vanishes! (- Y Y)))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    let only = Some(vec!["cx".to_string()]);
+    crate::compute::compute_trace_for(trace.to_str().unwrap(), &mut cs, true, &only, &[])?;
+    crate::check::check(&cs, &only, &[], crate::check::CheckConfig::new())?;
+
+    let skipped = cs
+        .columns
+        .all()
+        .into_iter()
+        .filter(|h| cs.columns.is_skipped(h))
+        .map(|h| cs.columns.column(&h).unwrap().handle.name.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(skipped, ["Y"]);
+
+    let mut out = Vec::new();
+    cs.write(&mut out, false, crate::import::ValuesEncoding::Hex)?;
+    assert_eq!(
+        String::from_utf8(out)?.matches("\"skipped\": true").count(),
+        1
+    );
+    Ok(())
+}
+
+#[test]
+fn custom_exporter() -> Result<()> {
+    use crate::plugin::{self, Exporter};
+    use itertools::Itertools;
+
+    struct ColumnNames;
+    impl Exporter for ColumnNames {
+        fn name(&self) -> &str {
+            "column-names"
+        }
+
+        fn export(
+            &self,
+            cs: &crate::compiler::ConstraintSet,
+            args: &[String],
+            out: &mut dyn std::io::Write,
+        ) -> Result<()> {
+            for name in cs.columns.iter_cols().map(|c| &c.handle.name).sorted() {
+                writeln!(out, "{}{}", args.join(""), name)?;
+            }
+            Ok(())
+        }
+    }
+    plugin::register(std::sync::Arc::new(ColumnNames));
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(defcolumns A B)")?;
+    let cs = builder.into_constraint_set()?;
+    let mut out = Vec::new();
+    plugin::export("column-names", &cs, &["> ".to_string()], &mut out)?;
+    assert_eq!(String::from_utf8(out)?, "> A\n> B\n");
+    assert!(plugin::export("unknown", &cs, &[], &mut Vec::new()).is_err());
+    Ok(())
+}
+
+#[test]
+fn cli_layout() -> Result<()> {
+    use clap::{CommandFactory, Parser};
+
+    // clap only validates the subcommands it builds, which are all of them
+    // as soon as it suggests a correction for a usage error
+    crate::Args::command().debug_assert();
+    assert!(crate::Args::try_parse_from(["corset", "check", "--bogus"]).is_err());
+
+    let parsed = crate::Args::try_parse_from([
+        "corset",
+        "export",
+        "-x",
+        "go",
+        "--arg",
+        "--package",
+        "--arg",
+        "m",
+        "m.lisp",
+    ])?;
+    assert_eq!(parsed.source, ["m.lisp"]);
+    let Some(crate::Commands::Export { exporter, args, .. }) = parsed.command else {
+        bail!("expected the export subcommand")
+    };
+    assert_eq!(exporter.as_deref(), Some("go"));
+    assert_eq!(args, ["--package", "m"]);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn plugin_exporter() -> Result<()> {
+    use crate::plugin;
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = temp_dir();
+
+    let dir = tmp.path().join("plugin");
+    std::fs::create_dir_all(&dir)?;
+    let write_plugin = |name: &str, api_version: u32| -> Result<String> {
+        let path = dir.join(name);
+        std::fs::write(
+            &path,
+            format!(
+                r#"#!/bin/sh
+if [ "$1" = "--corset-plugin" ]; then
+    echo '{{"api_version": {api_version}, "exporters": [{{"name": "{name}", "description": "the first byte of the input"}}]}}'
+    exit 0
+fi
+shift
+printf '%s' "$*"
+head -c 1
+"#
+            ),
+        )?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+        Ok(path.to_str().unwrap().to_owned())
+    };
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(defcolumns A B) (defconstraint a-is-b () (vanishes! (- A B)))")?;
+    let cs = builder.into_constraint_set()?;
+
+    plugin::load_plugin(&write_plugin("first-byte", plugin::PLUGIN_API_VERSION)?)?;
+    assert!(plugin::exporters()
+        .iter()
+        .any(|(name, description)| name == "first-byte"
+            && description == "the first byte of the input"));
+    let mut out = Vec::new();
+    plugin::export("first-byte", &cs, &["> ".to_string()], &mut out)?;
+    // the plugin reads the JSON export of the constraint set
+    assert_eq!(String::from_utf8(out)?, "> {");
+
+    assert!(plugin::load_plugin(&write_plugin("outdated", 1)?).is_err());
+    assert!(plugin::load_plugin(dir.join("missing").to_str().unwrap()).is_err());
+    Ok(())
+}
+
+#[test]
+fn sort_tiebreaks() -> Result<()> {
+    let tmp = temp_dir();
+    let trace = tmp.path().join("sort-tiebreaks.json");
+    std::fs::write(
+        &trace,
+        r#"{"<prelude>": {"K": [1, 0, 1, 0], "V": [30, 40, 10, 20], "T": [0, 1, 2, 0]}}"#,
+    )?;
+    let sorted = |options: &str| -> Result<Vec<String>> {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(&format!(
+            "(defcolumns K V T) (defpermutation (SK SV) ((↓ K) V) {options})"
+        ))?;
+        let mut cs = builder.into_constraint_set()?;
+        crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+        let sv = cs
+            .columns
+            .all()
+            .into_iter()
+            .find(|h| cs.columns.column(h).unwrap().handle.name == "SV")
+            .unwrap();
+        Ok((0..cs.columns.len(&sv).unwrap() as isize)
+            .map(|i| cs.columns.get(&sv, i, false).unwrap().to_string())
+            .collect())
+    };
+
+    // the trace is prefixed by a padding row; without ordering options, all
+    // the columns are sorted
+    assert_eq!(sorted("")?, ["0", "20", "40", "10", "30"]);
+    assert_eq!(sorted(":stable")?, ["0", "40", "20", "30", "10"]);
+    assert_eq!(sorted(":tie-break (T)")?, ["0", "20", "40", "30", "10"]);
+    assert_eq!(
+        sorted(":unstable :tie-break (T V)")?,
+        ["0", "20", "40", "30", "10"]
+    );
+    must_fail(
+        "unknown-option",
+        "(defcolumns K V) (defpermutation (SK SV) ((↓ K) V) :sideways)",
+    );
+    must_fail(
+        "missing-tiebreaks",
+        "(defcolumns K V) (defpermutation (SK SV) ((↓ K) V) :tie-break)",
+    );
+    Ok(())
+}
+
+#[test]
+fn check_config() -> Result<()> {
+    use crate::check::{CheckConfig, ReportStyle};
+    use owo_colors::OwoColorize;
+
+    let tmp = temp_dir();
+
+    assert_eq!(
+        crate::check::strip_colors(&format!("{} = {}", "A".red().bold(), "0".dimmed())),
+        "A = 0"
+    );
+
+    let trace = tmp.path().join("check-config.json");
+    std::fs::write(&trace, r#"{"<prelude>": {"A": [1, 0, 1]}}"#)?;
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(defcolumns A) (defconstraint a-is-zero () (vanishes! A))")?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+    for report in [
+        ReportStyle::Silent,
+        ReportStyle::Plain,
+        ReportStyle::Colored,
+    ] {
+        let config = CheckConfig::new().report(report).progress(true);
+        assert!(crate::check::check(&cs, &None, &[], config).is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn root_module() -> Result<()> {
+    use crate::{compiler::MAIN_MODULE, structs::Handle};
+
+    let root = Handle::new("", "A");
+    assert_eq!(root, Handle::new(MAIN_MODULE, "A"));
+    assert!(root.is_root());
+    assert_eq!(root.to_string(), "A");
+    assert_eq!(Handle::new("hub", "A").mangle(), "hub__A");
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "exporters")]
+fn mangling_schemes() {
+    use crate::{
+        exporters::Mangling,
+        structs::{Handle, MangleScheme},
+        utils::purify,
+    };
+
+    // the historical mangling is kept by default
+    let root = Handle::new("", "A");
+    assert_eq!(root.mangle(), "_prelude___A");
+    assert_eq!(root.mangle_ith(2), "_prelude___Aɩ2");
+    assert_eq!(root.mangled_module(), "_prelude_");
+    assert_eq!(Mangling::default().mangle(&root), "_prelude___A");
+    let h = Handle::new("hub", "x!y.z[1]");
+    assert_eq!(h.mangle(), "hub__x!y_z_1_");
+    assert_eq!(purify("α+β~"), "alphaaddbetanorm");
+    assert_eq!(purify("1st"), "1st");
+
+    // the escaping mangling is opt-in
+    let escaped = Mangling {
+        scheme: MangleScheme::Escaped,
+        ..Default::default()
+    };
+    assert_eq!(escaped.mangle(&root), "A");
+    assert_eq!(escaped.mangle_ith(&root, 2), "Aɩ2");
+    assert_eq!(root.mangled_module_with(MangleScheme::Escaped), "prelude");
+    assert_eq!(escaped.mangle(&h), "hub__x_21_y_z_1_");
+    assert_eq!(escaped.mangle(&Handle::new("hub", "1st")), "hub___1st");
+}
+
+#[test]
+fn root_module_traces() -> Result<()> {
+    use crate::compiler::MAIN_MODULE;
+
+    let tmp = temp_dir();
+
+    // columns at the top level of a trace belong to the root module
+    let trace = tmp.path().join("root-module.json");
+    std::fs::write(&trace, r#"{"A": [1, 2], "m": {"B": [3]}}"#)?;
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(defcolumns A) (module m) (defcolumns B)")?;
+    let mut cs = builder.into_constraint_set()?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+    // including the padding row
+    assert_eq!(cs.effective_len_for(MAIN_MODULE), Some(3));
+    Ok(())
+}
+
+#[test]
+fn binary_constraint_set() -> Result<()> {
+    use crate::compiler::ConstraintSet;
+    use itertools::Itertools;
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(defcolumns A (B :i16) (C :binary)) (definterleaved I (A B))
+         (defpermutation (SA SB) ((↓ A) B)) (defconstraint c () (if-zero C (vanishes! (- A B))))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let cs = builder.into_constraint_set()?;
+
+    // hash maps are not serialized in a stable order
+    let summary = |cs: &ConstraintSet| -> Result<String> {
+        Ok(format!(
+            "{} {} {} {}",
+            ron::ser::to_string(&cs.constraints)?,
+            ron::ser::to_string(&cs.columns.registers)?,
+            cs.computations.iter().map(|c| c.to_string()).join(";"),
+            cs.columns
+                .iter_cols()
+                .map(|c| c.handle.to_string())
+                .sorted()
+                .join(";")
+        ))
+    };
+    let binary = ConstraintSet::from_bytes(&cs.to_binary()?, "binary")?;
+    assert_eq!(summary(&binary)?, summary(&cs)?);
+    // RON constraint sets can still be loaded
+    let ron = ConstraintSet::from_bytes(ron::ser::to_string(&cs)?.as_bytes(), "ron")?;
+    assert_eq!(summary(&ron)?, summary(&cs)?);
+    // constraint sets written with a previous layout must be rebuilt, rather
+    // than being misread
+    let mut previous = b"CORSET\x00\x01".to_vec();
+    previous.extend_from_slice(&cs.to_binary()?[8..]);
+    let err = ConstraintSet::from_bytes(&previous, "previous").unwrap_err();
+    assert!(
+        crate::check::strip_colors(&err.to_string()).contains(&format!(
+        "uses version 1 of the binary format, but this corset reads version {}; it must be rebuilt",
+        crate::compiler::generator::BINARY_VERSION
+    ))
+    );
+    Ok(())
+}
+
+#[test]
+fn limit_rows() -> Result<()> {
+    use crate::compiler::{ConstraintSet, MAIN_MODULE};
+
+    let tmp = temp_dir();
+
+    let trace = tmp.path().join("limit-rows.json");
+    std::fs::write(&trace, r#"{"A": [0, 0, 7, 7], "m": {"B": [1, 2, 3]}}"#)?;
+    let constraints = || -> Result<ConstraintSet> {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(
+            "(defcolumns A) (defconstraint a-is-zero () (vanishes! A)) (module m) (defcolumns B)",
+        )?;
+        builder.expand_to(ExpansionLevel::top());
+        builder.into_constraint_set()
+    };
+
+    let mut full = constraints()?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut full, true)?;
+    assert!(crate::check::check(&full, &None, &[], Default::default()).is_err());
+
+    let mut limited = constraints()?;
+    limited.set_row_limit(Some(2));
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut limited, true)?;
+    // including the padding row
+    assert_eq!(limited.effective_len_for(MAIN_MODULE), Some(3));
+    assert_eq!(limited.effective_len_for("m"), Some(3));
+    crate::check::check(&limited, &None, &[], Default::default())
+}
+
+#[test]
+fn padding_reads() -> Result<()> {
+    use crate::check::CheckConfig;
+
+    let tmp = temp_dir();
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(defcolumns A B) (deflookup l (A) ((shift B -2))) (defconstraint c () (vanishes! (- A (prev A))))",
+    )?;
+    builder.add_source(
+        "(module m) (defcolumns X) (defconstraint inc () (vanishes! (- X (prev X) 1)))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    // c is covered by the spilling of the module, but not the lookup
+    let reads = cs.padding_reads();
+    assert_eq!(reads.len(), 1);
+    assert_eq!(reads[0].constraint, "l");
+    assert_eq!(reads[0].shift, -2);
+
+    let trace = tmp.path().join("padding-reads.json");
+    std::fs::write(
+        &trace,
+        r#"{"A": [0, 0, 0], "B": [0, 0, 0], "m": {"X": [1, 2, 3]}}"#,
+    )?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+    crate::check::check(&cs, &None, &[], CheckConfig::new())?;
+    // the lookup reads B two rows back, i.e. beyond the spilling on row 0
+    let strict = CheckConfig::new().strict_padding(vec![crate::compiler::MAIN_MODULE.to_owned()]);
+    let failures = crate::check::check(&cs, &None, &[], strict).unwrap_err();
+    let failures = crate::check::strip_colors(&failures.to_string());
+    assert!(failures.ends_with(": l"), "{}", failures);
+    // inc shifts backward, but only ever reads the spilling
+    crate::check::check(
+        &cs,
+        &None,
+        &[],
+        CheckConfig::new().strict_padding(vec!["m".into()]),
+    )?;
+    Ok(())
+}
+
+#[test]
+fn check_row_window() -> Result<()> {
+    use crate::check::CheckConfig;
+
+    let tmp = temp_dir();
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(defcolumns A) (defconstraint inc () (vanishes! (- A (prev A) 1)))")?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+
+    // after the padding row, the jump to 7 lands on row 4
+    let trace = tmp.path().join("row-window.json");
+    std::fs::write(&trace, r#"{"A": [1, 2, 3, 7, 8]}"#)?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+    assert!(crate::check::check(&cs, &None, &[], CheckConfig::new()).is_err());
+    crate::check::check(&cs, &None, &[], CheckConfig::new().rows(Some(0..4)))?;
+    crate::check::check(&cs, &None, &[], CheckConfig::new().rows(Some(5..100)))?;
+    // row 4 still reads row 3 through the shift
+    assert!(crate::check::check(&cs, &None, &[], CheckConfig::new().rows(Some(4..5))).is_err());
+
+    assert_eq!(crate::utils::parse_row_range("1_000..2_000")?, 1000..2000);
+    assert!(crate::utils::parse_row_range("10..5").is_err());
+    assert!(crate::utils::parse_row_range("10").is_err());
+    Ok(())
+}
+
+#[test]
+fn pil_import() -> Result<()> {
+    let tmp = temp_dir();
+    let mut cs = crate::compiler::pil::from_pil(include_str!("../tests/fibonacci.pil"))?;
+    assert_eq!(cs.constraints.len(), 5);
+    assert_eq!(cs.columns.iter_cols().count(), 4);
+
+    let trace = tmp.path().join("pil-import.json");
+    std::fs::write(
+        &trace,
+        r#"{"Fibonacci": {"ISFIRST": [1, 0, 0, 0], "a": [1, 1, 2, 3], "b": [1, 2, 3, 5]},
+            "Table": {"x": [0, 1, 2, 3, 5, 8]}}"#,
+    )?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+    crate::check::check(&cs, &None, &[], Default::default())?;
+
+    // 4 is not a Fibonacci term, nor in the table
+    let mut cs = crate::compiler::pil::from_pil(include_str!("../tests/fibonacci.pil"))?;
+    std::fs::write(
+        &trace,
+        r#"{"Fibonacci": {"ISFIRST": [1, 0, 0, 0], "a": [1, 1, 2, 3], "b": [1, 2, 3, 4]},
+            "Table": {"x": [0, 1, 2, 3, 5, 8]}}"#,
+    )?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+    assert!(crate::check::check(&cs, &None, &[], Default::default()).is_err());
+
+    // statements sharing a line are still told apart
+    let cs = crate::compiler::pil::from_pil(
+        "namespace M(4); pol commit a, b; a = b; b = a; {a} in {b}; {b} in {a};",
+    )?;
+    assert_eq!(
+        cs.constraints
+            .iter()
+            .map(|c| c.name())
+            .collect::<std::collections::HashSet<_>>()
+            .len(),
+        4
+    );
+
+    assert!(crate::compiler::pil::from_pil("namespace M(4); pol commit a; a = b;").is_err());
+    assert!(crate::compiler::pil::from_pil("namespace M(4); pol commit a; {a} is {a};").is_err());
+    Ok(())
+}
+
+#[test]
+fn column_groups() -> Result<()> {
+    use crate::structs::Handle;
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        r#"(module hub)
+           (defcolumns (SP :group "stack") (MEM :array [2] :group "memory") (STAMP :binary))
+           (defperspective ctx STAMP ((CALLER :group "context")))"#,
+    )?;
+    let cs = builder.into_constraint_set()?;
+    let group = |h: Handle| cs.columns.column(&h.into()).unwrap().group.clone();
+    assert_eq!(group(Handle::new("hub", "SP")), Some("stack".to_owned()));
+    assert_eq!(
+        group(Handle::new("hub", "MEM_2")),
+        Some("memory".to_owned())
+    );
+    assert_eq!(group(Handle::new("hub", "STAMP")), None);
+    assert_eq!(
+        group(Handle::maybe_with_perspective(
+            "hub",
+            "CALLER",
+            Some("ctx".to_owned())
+        )),
+        Some("context".to_owned())
+    );
+
+    must_fail("group-not-a-string", "(defcolumns (A :group stack))");
+    must_fail("group-twice", r#"(defcolumns (A :group "a" :group "b"))"#);
+    // strings are only attribute values
+    must_fail("string-column", r#"(defcolumns A "B")"#);
+    must_fail(
+        "string-expression",
+        r#"(defcolumns A) (defconstraint c () (vanishes! "A"))"#,
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "exporters")]
+fn grouped_docs() -> Result<()> {
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        r#"(module hub)
+           (defcolumns (SP :group "stack") (MEM :array [2] :group "memory") (STAMP :binary))
+           (defperspective ctx STAMP ((CALLER :group "context")))"#,
+    )?;
+    let cs = builder.into_constraint_set()?;
+
+    // the ungrouped columns come first, then every group in its own section
+    let mut markdown = Vec::new();
+    crate::exporters::docs::render_markdown(&cs, &mut markdown)?;
+    let markdown = String::from_utf8(markdown)?;
+    let sections = [
+        "## hub",
+        "`STAMP`",
+        "### context",
+        "`ctx/CALLER`",
+        "### memory",
+        "### stack",
+    ]
+    .map(|s| {
+        markdown
+            .find(s)
+            .unwrap_or_else(|| panic!("{} not found", s))
+    });
+    assert!(sections.windows(2).all(|w| w[0] < w[1]), "{}", markdown);
+    let mut html = Vec::new();
+    crate::exporters::docs::render_html(&cs, &mut html)?;
+    assert!(String::from_utf8(html)?.contains("<h3>stack</h3>"));
+    Ok(())
+}
+
+#[test]
+fn segmented_columns() -> Result<()> {
+    let tmp = temp_dir();
+    let values = |trace: &str| -> Result<Vec<String>> {
+        let path = tmp.path().join("segmented-columns.json");
+        std::fs::write(&path, trace)?;
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source("(defcolumns A)")?;
+        let mut cs = builder.into_constraint_set()?;
+        crate::compute::compute_trace(path.to_str().unwrap(), &mut cs, true)?;
+        let a = cs.columns.all().into_iter().next().unwrap();
+        Ok((0..cs.columns.len(&a).unwrap() as isize)
+            .map(|i| cs.columns.get(&a, i, false).unwrap().to_string())
+            .collect())
+    };
+
+    let expected = values(r#"{"A": [1, 2, 3, 4]}"#)?;
+    assert_eq!(values(r#"{"A": [[1, 2], [], [3], [4]]}"#)?, expected);
+    assert!(values(r#"{"A": [[1, 2], 3, [4]]}"#).is_err());
+    Ok(())
+}
+
+#[test]
+fn streamed_json_traces() -> Result<()> {
+    let tmp = temp_dir();
+    let import = |trace: &str| -> Result<Vec<String>> {
+        let path = tmp.path().join("streamed-json-traces.json");
+        std::fs::write(&path, trace)?;
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source("(module m) (defcolumns (A :byte) B)")?;
+        let mut cs = builder.into_constraint_set()?;
+        crate::import::parse_trace(path.to_str().unwrap(), &mut cs, true)?;
+        let b = crate::compiler::ColumnRef::from_handle(crate::structs::Handle::new("m", "B"));
+        Ok((0..cs.columns.len(&b).unwrap() as isize)
+            .map(|i| cs.columns.get(&b, i, false).unwrap().to_string())
+            .collect())
+    };
+
+    // values wider than 64 bits, strings, segments, and unknown or
+    // unrelated entries of any shape
+    assert_eq!(
+        import(
+            r#"{"version": 3.5, "m": {"Trace": {
+                 "A": [[1], [2, 3]],
+                 "B": [18446744073709551616, "16", 7],
+                 "C": [{"x": [null]}, true]}}}"#
+        )?,
+        ["18446744073709551616", "16", "7"]
+    );
+
+    let error = |trace: &str| format!("{:#}", import(trace).unwrap_err());
+    let e = error(r#"{"m": {"A": [1, 2, 256], "B": [0, 0, 0]}}"#);
+    assert!(
+        e.contains("importing") && e.contains("reading 2th element"),
+        "{}",
+        e
+    );
+    let e = error(r#"{"m": {"B": [[1, 2], 3]}}"#);
+    assert!(
+        e.contains("importing") && e.contains("mixes segments and values"),
+        "{}",
+        e
+    );
+    let e = error(r#"{"m": {"B": [1, 2, 3]}} {}"#);
+    assert!(e.contains("trailing characters"), "{}", e);
+
+    // the trace is parsed as it is read: the invalid value is reported before
+    // reading the endless values that follow it
+    struct Endless {
+        head: &'static [u8],
+        tail: usize,
+    }
+    impl std::io::Read for Endless {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.head.read(buf)?;
+            if n > 0 {
+                return std::io::Result::Ok(n);
+            }
+            if self.tail > 1 << 20 {
+                return Err(std::io::Error::other("read more than 1MiB of values"));
+            }
+            for b in buf.iter_mut() {
+                *b = b", 1"[self.tail % 3];
+                self.tail += 1;
+            }
+            std::io::Result::Ok(buf.len())
+        }
+    }
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(module m) (defcolumns (A :byte))")?;
+    let mut cs = builder.into_constraint_set()?;
+    let mut trace = Endless {
+        head: br#"{"m": {"A": [1, 256"#,
+        tail: 0,
+    };
+    let e = crate::import::stream_json_trace(&mut trace, &mut cs, true).unwrap_err();
+    assert!(
+        format!("{:#}", e).contains("reading 1th element"),
+        "{:#}",
+        e
+    );
+    assert!(trace.tail < 1 << 10, "{}", trace.tail);
+    Ok(())
+}
+
+#[test]
+fn computed_in_trace() -> Result<()> {
+    use crate::compute::{self, ComputedInTrace};
+
+    let tmp = temp_dir();
+
+    let trace = tmp.path().join("computed-in-trace.json");
+    std::fs::write(
+        &trace,
+        r#"{"m": {"A": [1, 2], "B": [3, 4], "C": [1, 3, 2, 5]}}"#,
+    )?;
+    let import_with = |policy| -> Result<crate::compiler::ConstraintSet> {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source("(module m) (defcolumns A B) (definterleaved C (A B))")?;
+        builder.computed_in_trace(policy);
+        let mut cs = builder.into_constraint_set()?;
+        compute::import_trace(trace.to_str().unwrap(), &mut cs)?;
+        Ok(cs)
+    };
+    let import = || import_with(ComputedInTrace::default());
+    let c = crate::compiler::ColumnRef::from_handle(crate::structs::Handle::new("m", "C"));
+
+    // by default, the provided values are kept
+    let mut cs = import()?;
+    assert!(compute::take_provided_computed(&mut cs, ComputedInTrace::Trust)?.is_empty());
+    let provided = cs.columns.get(&c, 5, false).unwrap().to_string();
+    compute::compute(&mut cs, true)?;
+    assert_eq!(cs.columns.get(&c, 5, false).unwrap().to_string(), provided);
+    assert_ne!(provided, "4");
+
+    // the policy of the constraint set applies when computing
+    assert!(compute::compute(&mut import_with(ComputedInTrace::Error)?, true).is_err());
+
+    let e = compute::take_provided_computed(&mut import()?, ComputedInTrace::Error)
+        .unwrap_err()
+        .to_string();
+    assert!(e.contains("1 computed column(s)"), "{}", e);
+
+    // the provided values are replaced by the computed ones
+    let mut cs = import()?;
+    assert!(compute::take_provided_computed(&mut cs, ComputedInTrace::Ignore)?.is_empty());
+    compute::compute(&mut cs, true)?;
+    assert_eq!(cs.columns.get(&c, 5, false).unwrap().to_string(), "4");
+
+    let mut cs = import()?;
+    let provided = compute::take_provided_computed(&mut cs, ComputedInTrace::Verify)?;
+    assert_eq!(provided.len(), 1);
+    compute::compute(&mut cs, true)?;
+    let e = compute::verify_provided(&cs, &provided)
+        .unwrap_err()
+        .to_string();
+    assert!(e.contains("1 row(s) differ") && e.contains("#5"), "{}", e);
+    Ok(())
+}
+
+#[test]
+fn checkpoints() -> Result<()> {
+    use crate::check::{CheckConfig, Checkpoint, Checkpointer};
+    use std::sync::{Arc, Mutex};
+
+    let tmp = temp_dir();
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(defcolumns A B) (defconstraint a-is-zero () (vanishes! A)) (defconstraint b-is-zero () (vanishes! B))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    let trace = tmp.path().join("checkpoints.json");
+    std::fs::write(&trace, r#"{"A": [0, 0, 0], "B": [0, 1, 0]}"#)?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+
+    let saved = Arc::new(Mutex::new(None));
+    let checkpointer = |progress: Checkpoint| {
+        let saved = saved.clone();
+        Arc::new(Checkpointer::new(
+            progress,
+            std::time::Duration::ZERO,
+            move |progress| {
+                *saved.lock().unwrap() = Some(progress.clone());
+                Ok(())
+            },
+        ))
+    };
+
+    let config = CheckConfig::new().checkpoint(checkpointer(Default::default()));
+    assert!(crate::check::check(&cs, &None, &[], config).is_err());
+    let progress = saved.lock().unwrap().take().unwrap();
+    assert_eq!(progress.done.len(), 1);
+    assert_eq!(progress.failed.len(), 1);
+    assert!(progress.rows.is_empty());
+
+    // failures are remembered when resuming...
+    let config = CheckConfig::new().checkpoint(checkpointer(progress.clone()));
+    assert!(crate::check::check(&cs, &None, &[], config).is_err());
+
+    // ...and the constraints already checked are skipped
+    let mut resumed = progress;
+    let failed = std::mem::take(&mut resumed.failed);
+    resumed.done.extend(failed);
+    let config = CheckConfig::new().checkpoint(checkpointer(resumed.clone()));
+    crate::check::check(&cs, &None, &[], config)?;
+    assert_eq!(saved.lock().unwrap().take(), Some(resumed));
+    Ok(())
+}
+
+#[test]
+fn failure_report() -> Result<()> {
+    use crate::check::{CheckConfig, FailureReport, Violation};
+    use std::sync::{Arc, Mutex};
+
+    let tmp = temp_dir();
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module m) (defcolumns A B) (defconstraint eq () (vanishes! (- A B))) (deflookup lk (A) (B))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    let trace = tmp.path().join("failure-report.json");
+    std::fs::write(&trace, r#"{"m": {"A": [1, 2, 3], "B": [1, 5, 4]}}"#)?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+
+    let report = Arc::new(Mutex::new(FailureReport::default()));
+    let config = CheckConfig::new()
+        .continue_on_error(true)
+        .record_failures(report.clone());
+    assert!(crate::check::check(&cs, &None, &[], config).is_err());
+    let mut violations = report.lock().unwrap().violations.clone();
+    violations.sort();
+    let violation = |constraint: &str, row, columns: &[&str]| Violation {
+        constraint: constraint.to_owned(),
+        row,
+        columns: columns.iter().map(|c| c.to_string()).collect(),
+        values: Vec::new(),
+    };
+    assert_eq!(
+        violations,
+        [
+            violation("m.eq", 2, &["m.A", "m.B"]),
+            violation("m.eq", 3, &["m.A", "m.B"]),
+            // only the first mismatch of a lookup is reported
+            violation("m.lk", 2, &["m.B"]),
+        ]
+    );
+
+    let path = tmp.path().join("failure-report.out.json");
+    let saved = report.lock().unwrap().clone();
+    saved.save(path.to_str().unwrap())?;
+    assert_eq!(FailureReport::load(path.to_str().unwrap())?, saved);
+    Ok(())
+}
+
+#[test]
+fn json_report() -> Result<()> {
+    use crate::check::{CheckConfig, FailureReport};
+    use std::sync::{Arc, Mutex};
+
+    let tmp = temp_dir();
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(module m) (defcolumns A B) (defconstraint eq () (vanishes! (- A B)))")?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    let trace = tmp.path().join("json-report.json");
+    std::fs::write(&trace, r#"{"m": {"A": [1, 2, 3], "B": [1, 5, 4]}}"#)?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+
+    let report = Arc::new(Mutex::new(FailureReport::default()));
+    let config = CheckConfig::new()
+        .continue_on_error(true)
+        .record_failures(report.clone())
+        .record_values(true);
+    let result = crate::check::check(&cs, &None, &[], config);
+    let json = serde_json::to_value(report.lock().unwrap().json_report(&result))?;
+
+    assert_eq!(json["success"], false);
+    let violated = json["violated"].as_array().unwrap();
+    assert_eq!(violated.len(), 1);
+    assert_eq!(violated[0]["constraint"], "m.eq");
+    assert_eq!(violated[0]["columns"], serde_json::json!(["m.A", "m.B"]));
+    let rows = violated[0]["rows"].as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["row"], 2);
+    let value = |expr: &str| {
+        rows[0]["values"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|v| v["expr"] == expr)
+            .map(|v| v["value"].clone())
+    };
+    assert_eq!(value("A"), Some("2".into()));
+    assert_eq!(value("B"), Some("5".into()));
+    Ok(())
+}
+
+#[test]
+fn provenance_sampling() -> Result<()> {
+    use crate::provenance::InputCell;
+
+    let tmp = temp_dir();
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module prov) (defcolumns A B) (definterleaved I (A B)) (defpermutation (S) ((+ A)))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    let trace = tmp.path().join("provenance.json");
+    std::fs::write(&trace, r#"{"prov": {"A": [3, 1, 2], "B": [1, 2, 0]}}"#)?;
+
+    // other tests may compute traces meanwhile, so only this module is looked at
+    crate::provenance::arm(1);
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+    let report = crate::provenance::take();
+    let inputs = |column: &str, row: isize| {
+        report
+            .samples
+            .iter()
+            .find(|s| s.column == column && s.row == row)
+            .map(|s| s.inputs.clone())
+    };
+    let cell = |column: &str, row| InputCell {
+        column: column.to_owned(),
+        row,
+    };
+
+    assert_eq!(inputs("prov.I", 2), Some(vec![cell("prov.A", 1)]));
+    assert_eq!(inputs("prov.I", 3), Some(vec![cell("prov.B", 1)]));
+    // row 0 is the padding, and the smallest value of A is on its second row
+    assert_eq!(inputs("prov.S", 1), Some(vec![cell("prov.A", 2)]));
+    assert_eq!(inputs("prov.S", 3), Some(vec![cell("prov.A", 1)]));
+    Ok(())
+}
+
+#[test]
+fn partial_check() -> Result<()> {
+    let tmp = temp_dir();
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module a) (defcolumns X Y) (definterleaved Z (X Y)) (defconstraint a-eq () (vanishes! (- X Y))) (defconstraint a-z () (vanishes! Z))
+         (module b) (defcolumns U) (definterleaved W (U U)) (defconstraint b-w () (vanishes! W))
+         (deflookup b-in-a (a.X) (b.U))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    cs.set_partial(true);
+    let trace = tmp.path().join("partial-check.json");
+    std::fs::write(&trace, r#"{"a": {"X": [0, 0], "Y": [0, 0]}}"#)?;
+    crate::compute::import_trace_lazily(trace.to_str().unwrap(), &mut cs, false)?;
+
+    // the interleaving of imported columns can be computed, the one of
+    // absent columns can not
+    let only = crate::check::partial_selection(&cs, &None, &[])?;
+    assert_eq!(only, Some(vec!["a.a-eq".to_string(), "a.a-z".to_string()]));
+    crate::check::check_lazily(&mut cs, &only, &[], Default::default())?;
+
+    let only = Some(vec!["b.b-w".to_string()]);
+    assert!(crate::check::partial_selection(&cs, &only, &[]).is_err());
+    Ok(())
+}
+
+#[test]
+fn doc_comments() -> Result<()> {
+    use crate::{compiler::MAIN_MODULE, structs::Handle};
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        ";; the columns
+;; of the prelude
+(defcolumns
+  ;; always zero
+  A
+  B)
+
+; not documentation
+(defconstraint plain () (vanishes! B))
+
+;; A vanishes
+(defconstraint a-vanishes () (vanishes! A))",
+    )?;
+    let cs = builder.into_constraint_set()?;
+
+    let doc = |name: &str| {
+        cs.columns
+            .iter()
+            .find(|c| c.1.handle.name == name)
+            .unwrap()
+            .1
+            .doc
+            .clone()
+    };
+    assert_eq!(doc("A").as_deref(), Some("always zero"));
+    assert_eq!(doc("B").as_deref(), Some("the columns\nof the prelude"));
+
+    let constraint_doc = |name: &str| cs.docs.get(&Handle::new(MAIN_MODULE, name)).cloned();
+    assert_eq!(constraint_doc("plain"), None);
+    assert_eq!(constraint_doc("a-vanishes").as_deref(), Some("A vanishes"));
+    Ok(())
+}
+
+#[test]
+fn crlf_sources() -> Result<()> {
+    use crate::{compiler::MAIN_MODULE, structs::Handle};
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        ";; first\r\n;; second\r\n(defcolumns A\r\n  B)\r\n\r\n;; A is B\r(defconstraint a-is-b () (eq! A B))\r\n",
+    )?;
+    let cs = builder.into_constraint_set()?;
+
+    let doc = cs
+        .columns
+        .iter()
+        .find(|c| c.1.handle.name == "A")
+        .unwrap()
+        .1
+        .doc
+        .clone();
+    assert_eq!(doc.as_deref(), Some("first\nsecond"));
+    assert_eq!(
+        cs.docs
+            .get(&Handle::new(MAIN_MODULE, "a-is-b"))
+            .map(String::as_str),
+        Some("A is B")
+    );
+    Ok(())
+}
+
+#[test]
+fn fixed_module_length() -> Result<()> {
+    let tmp = temp_dir();
+    let compute = |trace: &str| -> Result<crate::compiler::ConstraintSet> {
+        let path = tmp.path().join("fixed-module-length.json");
+        std::fs::write(&path, trace)?;
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source("(defcolumns A) (module t :length 3) (defcolumns (B :padding 7))")?;
+        let mut cs = builder.into_constraint_set()?;
+        crate::compute::compute_trace(path.to_str().unwrap(), &mut cs, true)?;
+        Ok(cs)
+    };
+
+    assert!(compute(r#"{"A": [1], "t": {"B": [1, 2, 3]}}"#).is_ok());
+    assert!(compute(r#"{"A": [1], "t": {"B": [1, 2]}}"#).is_err());
+
+    // absent fixed modules are filled with their padding
+    let cs = compute(r#"{"A": [1]}"#)?;
+    let b = cs
+        .columns
+        .all()
+        .into_iter()
+        .find(|h| cs.columns.column(h).unwrap().handle.name == "B")
+        .unwrap();
+    assert_eq!(cs.effective_len_for("t"), Some(4));
+    assert_eq!(cs.columns.get(&b, 3, false).unwrap().to_string(), "7");
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(module t :length 3) (module t :length 4)")?;
+    assert!(builder.into_constraint_set().is_err());
+    Ok(())
+}
+
+#[test]
+fn signed_columns() -> Result<()> {
+    use crate::{
+        check::CheckConfig,
+        column::Value,
+        pretty::{Base, Pretty},
+        transformer::AutoConstraint,
+    };
+    use ark_bls12_377::Fr;
+
+    let tmp = temp_dir();
+
+    let check = |trace: &str| -> Result<()> {
+        let path = tmp.path().join("signed-columns.json");
+        std::fs::write(&path, trace)?;
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(
+            "(defcolumns (A :s8@prove) B) (defconstraint c () (vanishes! (- B (abs A))))",
+        )?;
+        builder.expand_to(ExpansionLevel::top());
+        builder.auto_constraints(AutoConstraint::all());
+        let mut cs = builder.into_constraint_set()?;
+        crate::compute::compute_trace(path.to_str().unwrap(), &mut cs, true)?;
+        crate::check::check(&cs, &None, &[], CheckConfig::new())
+    };
+
+    // 255 is the two's complement of -1
+    check(r#"{"A": ["-3", 5, 255, "-128"], "B": [3, 5, 1, 128]}"#)?;
+    assert!(check(r#"{"A": ["-3", 5], "B": [-3, 5]}"#).is_err());
+    assert!(check(r#"{"A": [300], "B": [44]}"#).is_err());
+    assert!(check(r#"{"A": ["-129"], "B": [129]}"#).is_err());
+
+    assert_eq!(
+        Value::Native(-Fr::from(3)).pretty_with_base(Base::Signed),
+        "-3"
+    );
+    assert_eq!(
+        Value::Native(Fr::from(3)).pretty_with_base(Base::Signed),
+        "3"
+    );
+    assert!(Value::ExoNative(vec![Fr::from(1), Fr::from(0)])
+        .to_signed_bi()
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn signed_magmas_ordering() -> Result<()> {
+    use crate::compiler::{max_type, Conditioning, Magma, RawMagma, Type};
+
+    // the sign is kept, and the unsigned values still fit
+    assert!(Magma::signed(8) < Magma::integer(8));
+    assert!(Magma::signed(9) > Magma::integer(8));
+    assert_eq!(
+        RawMagma::Signed(8).join(RawMagma::Integer(16)),
+        RawMagma::Signed(17)
+    );
+    assert_eq!(
+        RawMagma::Byte.join(RawMagma::Signed(16)),
+        RawMagma::Signed(16)
+    );
+    assert_eq!(
+        RawMagma::Signed(4).join(RawMagma::Signed(8)),
+        RawMagma::Signed(8)
+    );
+    assert_eq!(
+        RawMagma::Signed(8).join(RawMagma::Integer(crate::constants::FIELD_BITSIZE)),
+        RawMagma::Native
+    );
+
+    let s8 = Type::Column(Magma::signed(8));
+    let i16 = Type::Column(Magma::integer(16));
+    assert_eq!(max_type([&s8, &i16])?, Type::Column(Magma::signed(17)));
+    assert_eq!(i16.joined(&s8), Type::Column(Magma::signed(17)));
+    assert_eq!(
+        Type::Column(Magma::binary().with_conditioning(Conditioning::Boolean)).joined(&s8),
+        Type::Column(Magma::signed(8))
+    );
+    Ok(())
+}
+
+#[test]
+fn module_lengths_metadata() -> Result<()> {
+    let tmp = temp_dir();
+    let trace = tmp.path().join("module-lengths.json");
+    std::fs::write(&trace, r#"{"A": [1, 2, 3], "m": {"B": [0, 0]}}"#)?;
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(defcolumns A) (module m) (defcolumns B) (defconstraint c () (vanishes! (- B (prev B))))",
+    )?;
+    let mut cs = builder.into_constraint_set()?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+    let mut out = Vec::new();
+    cs.write(&mut out, false, crate::import::ValuesEncoding::Hex)?;
+
+    let written: serde_json::Value = serde_json::from_slice(&out)?;
+    let m = &written["modules"]["m"];
+    assert_eq!(m["spilling"], 1);
+    assert_eq!(m["raw_length"], 3);
+    assert_eq!(m["padded_length"], 4);
+    assert_eq!(
+        written["columns"]["m.B"]["values"]
+            .as_array()
+            .unwrap()
+            .len(),
+        4
+    );
+    Ok(())
+}
+
+#[test]
+fn timeouts() {
+    use crate::{errors::RuntimeError, utils};
+    use std::time::Duration;
+
+    assert_eq!(
+        utils::parse_duration("90").unwrap(),
+        Duration::from_secs(90)
+    );
+    assert_eq!(
+        utils::parse_duration("2m").unwrap(),
+        Duration::from_secs(120)
+    );
+    assert_eq!(
+        utils::parse_duration("1h").unwrap(),
+        Duration::from_secs(3600)
+    );
+    assert!(utils::parse_duration("1d").is_err());
+
+    let timed_out = anyhow!(RuntimeError::DeadlineExceeded {
+        stage: "checking the constraints",
+        timeout: Duration::from_secs(1),
+    })
+    .context("while checking");
+    assert!(utils::is_deadline_exceeded(&timed_out));
+    assert!(!utils::is_deadline_exceeded(&anyhow!("constraints failed")));
+}
+
+#[test]
+fn cancellations() {
+    use crate::{errors::RuntimeError, utils};
+
+    // the flag is global, and must not be raised while other tests run
+    assert!(utils::ensure_not_cancelled("checking the constraints").is_ok());
+    let cancelled = anyhow!(RuntimeError::Cancelled {
+        stage: "checking the constraints"
+    })
+    .context("while checking");
+    assert!(utils::is_cancelled(&cancelled));
+    assert!(!utils::is_deadline_exceeded(&cancelled));
+    assert!(!utils::is_cancelled(&anyhow!("constraints failed")));
+}
+
+#[test]
+fn pinned_register_order() -> Result<()> {
+    let compile = |source: &str| {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(source)?;
+        builder.into_constraint_set()
+    };
+    let registers = |cs: &crate::compiler::ConstraintSet| {
+        cs.columns
+            .registers
+            .iter()
+            .map(|r| r.handle.as_ref().unwrap().name.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let reference = compile("(defcolumns A B C)")?;
+    let mut cs = compile("(defcolumns D C B A)")?;
+    assert_eq!(cs.columns.pin_register_order(&reference.columns)?, 1);
+    assert_eq!(registers(&cs), ["A", "B", "C", "D"]);
+    for c in cs.columns.iter_cols() {
+        assert_eq!(
+            cs.columns.registers[c.register.unwrap()].handle.as_ref(),
+            Some(&c.handle)
+        );
+    }
+
+    let mut cs = compile("(defcolumns C A)")?;
+    assert!(cs.columns.pin_register_order(&reference.columns).is_err());
+    Ok(())
+}
+
+#[test]
+fn guard_and_perspective() -> Result<()> {
+    use crate::compiler::parser::parser::parse;
+
+    let parse = |attributes: &str| {
+        parse(&format!(
+            "(defcolumns (S :binary) G) (defperspective p S ((Z :byte)))
+             (defconstraint c ({}) (vanishes! Z))",
+            attributes
+        ))
+    };
+
+    // a perspective may follow a guard, but neither may be repeated
+    assert!(parse(":guard G :perspective p").is_ok());
+    assert!(parse(":perspective p :guard G").is_ok());
+    for (attributes, error) in [
+        (":guard G :guard G", "guard already defined"),
+        (
+            ":perspective p :perspective p",
+            "perspective already defined",
+        ),
+    ] {
+        let err = parse(attributes).unwrap_err();
+        assert!(format!("{:#}", err).contains(error), "{:#}", err);
+    }
+    Ok(())
+}
+
+#[test]
+fn explain_constraint() -> Result<()> {
+    use crate::transformer::AutoConstraint;
+
+    // (+ B 0) is only simplified by the last step of the pipeline
+    const SOURCE: &str =
+        "(module m) (defcolumns A B) (defconstraint foo () (if-zero A (vanishes! (+ B 0))))";
+    let explain = |target: &str| {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(SOURCE)?;
+        let (asts, cs) = builder.into_asts_and_constraint_set()?;
+        crate::explain::explain(&asts, cs, target, AutoConstraint::all())
+    };
+
+    let stages = explain("m.foo")?;
+    assert_eq!(
+        stages.iter().map(|s| s.label).collect::<Vec<_>>(),
+        [
+            "parsed",
+            "typed",
+            "if-expansion",
+            "splatting",
+            "columnization",
+            "normalization expansion",
+            "simplification",
+            "exported"
+        ]
+    );
+    assert!(stages[0].forms[0].starts_with("(defconstraint foo"));
+    assert_ne!(stages[1].forms, stages[2].forms);
+    assert_eq!(stages.last().unwrap().forms.len(), 1);
+
+    // the last stage is what gets exported
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(SOURCE)?;
+    builder.expand_to(ExpansionLevel::top());
+    builder.auto_constraints(AutoConstraint::all());
+    let cs = builder.into_constraint_set()?;
+    let exported = cs
+        .constraints
+        .iter()
+        .filter(|c| c.name() == "m.foo")
+        .map(|c| match c {
+            crate::compiler::Constraint::Vanishes { expr, .. } => expr.to_string(),
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(stages.last().unwrap().forms, exported);
+    assert_ne!(stages[5].forms, stages[6].forms);
+
+    assert!(explain("foo").is_ok());
+    assert!(explain("bar").is_err());
+    Ok(())
+}
+
+#[test]
+fn padding_expressions() -> Result<()> {
+    let tmp = temp_dir();
+    let compile = |source: &str| {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(source)?;
+        builder.into_constraint_set()
+    };
+    let padding = |cs: &crate::compiler::ConstraintSet, name: &str| {
+        cs.columns
+            .iter_cols()
+            .find(|c| c.handle.name == name)
+            .and_then(|c| c.padding_value.as_ref())
+            .map(|v| v.to_string())
+    };
+
+    let cs = compile(
+        "(defconst K 2)
+         (defcolumns (B :padding (* K (+ A 1))) (A :padding 3) (C :byte :padding (- B 1)))",
+    )?;
+    assert_eq!(padding(&cs, "B").as_deref(), Some("8"));
+    assert_eq!(padding(&cs, "C").as_deref(), Some("7"));
+
+    // the padding of a fixed-length module follows the expressions
+    let path = tmp.path().join("padding-expressions.json");
+    std::fs::write(&path, r#"{"A": [1]}"#)?;
+    let mut cs = compile("(defcolumns A) (module t :length 2) (defcolumns (B :padding (+ 1 2)))")?;
+    crate::compute::compute_trace(path.to_str().unwrap(), &mut cs, true)?;
+    let b = cs
+        .columns
+        .all()
+        .into_iter()
+        .find(|h| cs.columns.column(h).unwrap().handle.name == "B")
+        .unwrap();
+    assert_eq!(cs.columns.get(&b, 2, false).unwrap().to_string(), "3");
+
+    assert!(compile("(defcolumns (A :padding (+ B 1)) (B :padding (- A 1)))").is_err());
+    assert!(compile("(defcolumns A (B :padding (shift A 1)))").is_err());
+    assert!(compile("(defcolumns (A :padding 300) (B :byte :padding A))").is_err());
+    assert!(compile("(defcolumns (A :padding 1 :padding (+ 1 1)))").is_err());
+    assert!(compile("(defcolumns (A :array [2] :padding (+ 1 1)))").is_err());
+    Ok(())
+}
+
+#[test]
+fn scaffold_from_trace() -> Result<()> {
+    let tmp = temp_dir();
+    let trace = tmp.path().join("scaffold.json");
+    std::fs::write(
+        &trace,
+        r#"{"A": [1], "m": {"Trace": {"F": [0, 1, 1], "B": [3, "200", 7], "N": [[1, 2], [300]]}}}"#,
+    )?;
+    let skeleton = crate::scaffold::scaffold_trace(trace.to_str().unwrap(), "m")?;
+    assert!(skeleton.contains("(module m)"));
+    assert!(skeleton.contains("(F :binary)"));
+    assert!(skeleton.contains("(B :byte)"));
+    assert!(skeleton.contains("\n  N"));
+    assert!(!skeleton.contains(" A"));
+
+    // the skeleton must be a valid module
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(&skeleton)?;
+    let cs = builder.into_constraint_set()?;
+    assert_eq!(cs.columns.iter_cols().count(), 3);
+
+    assert!(crate::scaffold::scaffold_trace(trace.to_str().unwrap(), "nope").is_err());
+    Ok(())
+}
+
+#[test]
+fn interleaving_length_relations() -> Result<()> {
+    let tmp = temp_dir();
+    let compute = |trace: &str| -> Result<()> {
+        let path = tmp.path().join("interleaving-lengths.json");
+        std::fs::write(&path, trace)?;
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source("(module m) (defcolumns X Y) (definterleaved I (X Y))")?;
+        builder.expand_to(ExpansionLevel::top());
+        let mut cs = builder.into_constraint_set()?;
+        crate::compute::compute_trace(path.to_str().unwrap(), &mut cs, true)
+    };
+
+    // an interleaving present in the trace is checked against its sources
+    compute(r#"{"m": {"Trace": {"I": [1, 3, 2, 4], "X": [1, 2], "Y": [3, 4]}}}"#)?;
+    let err = crate::check::strip_colors(
+        &compute(r#"{"m": {"Trace": {"X": [1, 2], "Y": [3, 4], "I": [1, 3, 2]}}}"#)
+            .unwrap_err()
+            .to_string(),
+    );
+    assert!(err.contains("length relation"));
+    assert!(err.contains("i.e. 4 rows, found 3; sources: m.X (2), m.Y (2)"));
+    assert!(compute(r#"{"m": {"X": [1, 2], "Y": [3]}}"#).is_err());
+    Ok(())
+}
+
+#[test]
+fn provided_interleavings() -> Result<()> {
+    let tmp = temp_dir();
+    let interleaving = |trace: &str| -> Result<Vec<String>> {
+        let path = tmp.path().join("provided-interleavings.json");
+        std::fs::write(&path, trace)?;
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source("(module m) (defcolumns X Y) (definterleaved I (X Y))")?;
+        builder.expand_to(ExpansionLevel::top());
+        let mut cs = builder.into_constraint_set()?;
+        crate::compute::compute_trace(path.to_str().unwrap(), &mut cs, true)?;
+        let i = crate::structs::Handle::new("m", "I").into();
+        Ok((0..cs.columns.len(&i).unwrap() as isize)
+            .map(|k| cs.columns.get(&i, k, false).unwrap().to_string())
+            .collect())
+    };
+
+    // the padding row of the module spans two rows of the interleaving
+    let computed = interleaving(r#"{"m": {"X": [1, 2], "Y": [3, 4]}}"#)?;
+    assert_eq!(computed, ["0", "0", "1", "3", "2", "4"]);
+    // a provided interleaving is aligned as if it were computed
+    assert_eq!(
+        interleaving(r#"{"m": {"I": [1, 3, 2, 4], "X": [1, 2], "Y": [3, 4]}}"#)?,
+        computed
+    );
+    Ok(())
+}
+
+#[test]
+fn module_length_multipliers() -> Result<()> {
+    let tmp = temp_dir();
+    let path = tmp.path().join("module-length-multipliers.json");
+    std::fs::write(
+        &path,
+        r#"{"m": {"Trace": {"Z": [1, 2], "I": [1, 3, 2], "J": [1, 3, 2, 4, 5]}}}"#,
+    )?;
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module m) (defcolumns X Y Z) (definterleaved I (X Y)) (definterleaved J (X Y))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+
+    // the sources of the interleavings are absent, yet both are reported
+    // against the length of their module
+    let err = crate::check::strip_colors(
+        &crate::compute::compute_trace(path.to_str().unwrap(), &mut cs, false)
+            .unwrap_err()
+            .to_string(),
+    );
+    // and the lengths are the ones of the trace, not the padded ones
+    assert!(err.starts_with("2 length relation(s) violated"));
+    assert!(err.contains("m.I should be ×2 as long as its module m, i.e. 4 rows, found 3"));
+    assert!(err.contains("m.J should be ×2 as long as its module m, i.e. 4 rows, found 5"));
+    Ok(())
+}
+
+#[test]
+fn manifest_settings() -> Result<()> {
+    use crate::{manifest::Manifest, Args};
+    use clap::{CommandFactory, FromArgMatches};
+
+    let tmp = temp_dir();
+
+    let dir = tmp.path().join("manifest");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(
+        dir.join("a.lisp"),
+        "(defcolumns A) (defconstraint c () (vanishes! (- A K)))",
+    )?;
+    let path = dir.join(crate::manifest::MANIFEST_NAME);
+    std::fs::write(
+        &path,
+        "sources = [\"a.lisp\"]\nthreads = 3\nmemory-budget = \"2K\"\n[defines]\nK = 2\n",
+    )?;
+    let parse_with = |path: &std::path::Path, cli: &[&str]| -> Result<Args> {
+        let manifest = Manifest::load(path)?;
+        let matches = Args::command().try_get_matches_from(cli)?;
+        let mut args = Args::from_arg_matches(&matches)?;
+        manifest.apply(&mut args, &matches)?;
+        Ok(args)
+    };
+    let parse = |cli: &[&str]| parse_with(&path, cli);
+    let args = parse(&["corset", "debug"])?;
+    assert_eq!(args.threads, 3);
+    assert_eq!(args.memory_budget, Some(2048));
+    assert_eq!(args.source.len(), 2);
+    let mut builder = SourceLoader::from_sources(false, false);
+    for source in args.source.iter() {
+        builder.add_source(source)?;
+    }
+    builder.into_constraint_set()?;
+
+    // the command line overrides the manifest
+    let args = parse(&["corset", "debug", "-t", "1", "--memory-budget", "1K"])?;
+    assert_eq!(args.threads, 1);
+    assert_eq!(args.memory_budget, Some(1024));
+
+    // the defines are only added to compiled sources
+    let args = parse(&["corset", "fmt", "--check"])?;
+    assert_eq!(args.source, [dir.join("a.lisp").to_string_lossy()]);
+
+    // explicit flags win over the manifest, whichever way they go
+    std::fs::write(
+        &path,
+        "sources = [\"a.lisp\"]\nstdlib = false\ndebug = true\n",
+    )?;
+    let args = parse(&["corset", "debug"])?;
+    assert!(args.no_stdlib && args.debug);
+    let args = parse(&["corset", "--stdlib", "debug", "--no-debug"])?;
+    assert!(!args.no_stdlib && !args.debug);
+    let args = parse(&["corset", "--stdlib", "--no-stdlib", "debug"])?;
+    assert!(args.no_stdlib);
+
+    // the defines are spliced in the sources, and must thus be numbers
+    for defines in ["K = \"2) (defconst J 3\"", "\"K J\" = 2", "K = 2.5"] {
+        std::fs::write(
+            &path,
+            format!("sources = [\"a.lisp\"]\n[defines]\n{defines}\n"),
+        )?;
+        assert!(parse(&["corset", "debug"]).is_err());
+    }
+    std::fs::write(&path, "sources = [\"a.lisp\"]\n[defines]\nK = \"0x10\"\n")?;
+    assert_eq!(parse(&["corset", "debug"])?.source[0], "(defconst K 16)");
+
+    std::fs::write(&path, "sources = [\"missing.lisp\"]")?;
+    assert!(Manifest::load(&path).is_err());
+    std::fs::write(&path, "unknown = 1")?;
+    assert!(Manifest::load(&path).is_err());
+    Ok(())
+}
+
+#[test]
+fn bit_decompositions() -> Result<()> {
+    use crate::check::CheckConfig;
+
+    let tmp = temp_dir();
+
+    let check = |trace: &str| -> Result<()> {
+        let path = tmp.path().join("bit-decompositions.json");
+        std::fs::write(&path, trace)?;
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(
+            "(defcolumns X B)
+             (defconstraint low-bit () (vanishes! (- B (bits-of X 4 0))))
+             (defconstraint range () (vanishes! (- (bits-of X 4) X)))
+             (defconstraint high-bit () (if-not-zero (bits-of (* 2 X) 5 4) (vanishes! (- X 8))))",
+        )?;
+        builder.expand_to(ExpansionLevel::top());
+        let mut cs = builder.into_constraint_set()?;
+        assert_eq!(
+            cs.columns
+                .iter_cols()
+                .filter(|c| c.handle.name.contains("BITS"))
+                .count(),
+            9
+        );
+        crate::compute::compute_trace(path.to_str().unwrap(), &mut cs, true)?;
+        crate::check::check(&cs, &None, &[], CheckConfig::new())
+    };
+
+    check(r#"{"X": [2, 5, 7, 8], "B": [0, 1, 1, 0]}"#)?;
+    assert!(check(r#"{"X": [9], "B": [1]}"#).is_err());
+    assert!(check(r#"{"X": [2, 5], "B": [1, 1]}"#).is_err());
+    // 17 does not fit in 4 bits
+    assert!(check(r#"{"X": [17], "B": [1]}"#).is_err());
+    // the width and the selected bit must be constants, the latter below the
+    // former
+    must_fail(
+        "",
+        "(defcolumns X) (defconstraint c () (vanishes! (bits-of X X)))",
+    );
+    must_fail(
+        "",
+        "(defcolumns X) (defconstraint c () (vanishes! (bits-of X 3 3)))",
+    );
+    Ok(())
+}
+
+#[test]
+fn lazy_computations() -> Result<()> {
+    use crate::{check::CheckConfig, compiler::ConstraintSet};
+
+    let tmp = temp_dir();
+
+    let load = |trace: &str| -> Result<ConstraintSet> {
+        let path = tmp.path().join("lazy-computations.json");
+        std::fs::write(&path, trace)?;
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(
+            "(defcolumns A B)
+             (definterleaved I (A B))
+             (defconstraint plain () (vanishes! (- A B)))
+             (defconstraint binary () (vanishes! (* I (- I 1))))",
+        )?;
+        builder.expand_to(ExpansionLevel::top());
+        let mut cs = builder.into_constraint_set()?;
+        crate::compute::import_trace_lazily(path.to_str().unwrap(), &mut cs, true)?;
+        Ok(cs)
+    };
+    let computed = |cs: &ConstraintSet| {
+        cs.columns
+            .all()
+            .into_iter()
+            .filter(|h| cs.computations.computation_idx_for(h).is_some())
+            .filter(|h| cs.columns.is_computed(h))
+            .count()
+    };
+
+    // only the constraint not needing any computed column is checked
+    let mut cs = load(r#"{"A": [1, 1, 0], "B": [1, 1, 0]}"#)?;
+    let only = Some(vec!["plain".to_string()]);
+    crate::check::check_lazily(&mut cs, &only, &[], CheckConfig::new())?;
+    assert_eq!(computed(&cs), 0);
+    crate::check::check_lazily(&mut cs, &None, &[], CheckConfig::new())?;
+    assert!(computed(&cs) > 0);
+
+    // failures are found just like when computing everything beforehand
+    let mut cs = load(r#"{"A": [2, 0], "B": [2, 0]}"#)?;
+    assert!(crate::check::check_lazily(&mut cs, &None, &[], CheckConfig::new()).is_err());
+    let only = Some(vec!["binary".to_string()]);
+    assert!(crate::check::check_lazily(&mut cs, &only, &[], CheckConfig::new()).is_err());
+    Ok(())
+}
+
+#[test]
+fn lookup_occupancy() -> Result<()> {
+    let tmp = temp_dir();
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module tbl) (defcolumns X)
+         (module src) (defcolumns A)
+         (deflookup lk (tbl.X) (src.A))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    let trace = tmp.path().join("lookup-occupancy.json");
+    std::fs::write(
+        &trace,
+        r#"{"tbl": {"X": [1, 2, 3, 4]}, "src": {"A": [1, 1, 1, 3, 5]}}"#,
+    )?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+
+    let modules = crate::stats::module_stats(&cs);
+    assert_eq!(modules["src"].lookups, 1);
+    assert_eq!(modules["tbl"].columns, 1);
+
+    // both sides start with a padding row of zeroes
+    let stats = crate::stats::lookup_stats(&cs)?;
+    assert_eq!(stats.len(), 1);
+    let lk = &stats[0];
+    assert_eq!((lk.source_rows, lk.distinct_sources), (6, 4));
+    assert_eq!((lk.table_rows, lk.distinct_entries), (5, 5));
+    assert_eq!(lk.max_multiplicity, 3);
+    assert_eq!(lk.unused_rows, 2);
+    assert_eq!(lk.missing, 1);
+    Ok(())
+}
+
+#[test]
+fn assertions() -> Result<()> {
+    use crate::{check::CheckConfig, compiler::ConstraintSet};
+
+    let tmp = temp_dir();
+
+    let load = |trace: &str| -> Result<ConstraintSet> {
+        let path = tmp.path().join("assertions.json");
+        std::fs::write(&path, trace)?;
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(
+            "(defcolumns A B)
+             (defconstraint same () (vanishes! (- A B)))
+             (defassertion increasing () (vanishes! (* (- A (prev A)) (- A (prev A) 1))))",
+        )?;
+        builder.expand_to(ExpansionLevel::top());
+        let mut cs = builder.into_constraint_set()?;
+        crate::compute::compute_trace(path.to_str().unwrap(), &mut cs, true)?;
+        Ok(cs)
+    };
+
+    // assertions are kept apart from the constraints sent to the exporters
+    let cs = load(r#"{"A": [1, 2], "B": [1, 2]}"#)?;
+    assert!(cs.constraints.iter().all(|c| c.name() != "increasing"));
+    assert_eq!(
+        cs.assertions.iter().map(|c| c.name()).collect::<Vec<_>>(),
+        ["increasing"]
+    );
+    crate::check::check(&cs, &None, &[], CheckConfig::new())?;
+
+    // but they are checked all the same
+    let cs = load(r#"{"A": [2, 1], "B": [2, 1]}"#)?;
+    assert!(crate::check::check(&cs, &None, &[], CheckConfig::new()).is_err());
+    let skip = ["increasing".to_string()];
+    crate::check::check(&cs, &None, &skip, CheckConfig::new())?;
+    Ok(())
+}
+
+#[test]
+fn lowered_assertions() -> Result<()> {
+    use crate::{check::CheckConfig, compiler::ConstraintSet};
+
+    let tmp = temp_dir();
+
+    let load = |trace: &str| -> Result<ConstraintSet> {
+        let path = tmp.path().join("lowered-assertions.json");
+        std::fs::write(&path, trace)?;
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(
+            "(defcolumns X B)
+             (defassertion low-bit () (if-not-zero X (vanishes! (- B (bits-of X 4 0)))))",
+        )?;
+        builder.expand_to(ExpansionLevel::top());
+        let mut cs = builder.into_constraint_set()?;
+        crate::compute::compute_trace(path.to_str().unwrap(), &mut cs, true)?;
+        Ok(cs)
+    };
+
+    // assertions are expanded like the constraints, and so are the
+    // constraints introduced by their expansion
+    let cs = load(r#"{"X": [2, 5], "B": [0, 1]}"#)?;
+    assert_eq!(
+        cs.columns
+            .iter_cols()
+            .filter(|c| c.handle.name.contains("BITS"))
+            .count(),
+        4
+    );
+    assert!(cs.constraints.is_empty());
+    assert!(cs.assertions.len() > 1);
+    crate::check::check(&cs, &None, &[], CheckConfig::new())?;
+
+    let cs = load(r#"{"X": [2, 5], "B": [1, 1]}"#)?;
+    assert!(crate::check::check(&cs, &None, &[], CheckConfig::new()).is_err());
+    Ok(())
+}
+
+#[test]
+fn factored_ifs() -> Result<()> {
+    use crate::compiler::ConstraintSet;
+
+    let tmp = temp_dir();
+
+    // a sum of 16 conditionals would be raised into 2^16 branches
+    let n = 16;
+    let load = |trace: &str| -> Result<ConstraintSet> {
+        let path = tmp.path().join("factored-ifs.json");
+        std::fs::write(&path, trace)?;
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(&format!(
+            "(defcolumns A S (C :binary))
+             (defconstraint sum () (eq! S (+ {})))",
+            (0..n)
+                .map(|i| format!("(if-zero (- A {i}) {i} (if-not-zero C 1 0))"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ))?;
+        builder.expand_to(ExpansionLevel::top());
+        let mut cs = builder.into_constraint_set()?;
+        crate::compute::compute_trace(path.to_str().unwrap(), &mut cs, true)?;
+        Ok(cs)
+    };
+
+    let cs = load(r#"{"A": [3, 3], "S": [18, 3], "C": [1, 0]}"#)?;
+    assert!(cs.constraints.iter().map(|c| c.size()).sum::<usize>() < 50 * n);
+    crate::check::check(&cs, &None, &[], crate::check::CheckConfig::new())?;
+
+    let cs = load(r#"{"A": [3, 3], "S": [3, 3], "C": [1, 0]}"#)?;
+    assert!(crate::check::check(&cs, &None, &[], crate::check::CheckConfig::new()).is_err());
+    Ok(())
+}
+
+#[test]
+fn run_stats() -> Result<()> {
+    use crate::run_stats;
+    use sha2::{Digest, Sha256};
+
+    let tmp = temp_dir();
+
+    let path = tmp.path().join("run-stats.json");
+    let read = || -> Result<serde_json::Value> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(&path)?)?)
+    };
+
+    let stats = run_stats::arm(path.to_str().unwrap(), "check");
+    run_stats::record_input("constraints.lisp", b"(defcolumns A)");
+    {
+        let _phase = run_stats::phase("check");
+    }
+    {
+        let _phase = run_stats::phase("check");
+    }
+    assert!(run_stats::recorded(Err::<(), _>(anyhow!("constraints failed: foo"))).is_err());
+    drop(stats);
+
+    let stats = read()?;
+    assert_eq!(stats["command"], "check");
+    assert_eq!(stats["status"], "failure");
+    assert_eq!(stats["error"], "constraints failed: foo");
+    assert_eq!(stats["inputs"][0]["bytes"], 14);
+    assert_eq!(stats["inputs"][0]["sha256"].as_str().unwrap().len(), 64);
+    assert!(stats["phases"]["check"].is_number());
+
+    // once concluded, nothing more is collected nor written
+    std::fs::remove_file(&path)?;
+    run_stats::succeed();
+    assert!(!path.exists());
+
+    // an invocation that does not reach its end is a failure
+    drop(run_stats::arm(path.to_str().unwrap(), "check"));
+    assert_eq!(read()?["status"], "failure");
+
+    // traces are hashed as they are imported
+    let trace = tmp.path().join("run-stats-trace.json");
+    let content = r#"{"m": {"A": [1, 2, 3]}}"#;
+    std::fs::write(&trace, content)?;
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(module m) (defcolumns A)")?;
+    let mut cs = builder.into_constraint_set()?;
+    let stats = run_stats::arm(path.to_str().unwrap(), "check");
+    crate::compute::import_trace(trace.to_str().unwrap(), &mut cs)?;
+    run_stats::succeed();
+    drop(stats);
+    let stats = read()?;
+    assert_eq!(stats["status"], "success");
+    // other tests may import traces concurrently
+    let input = stats["inputs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|i| i["name"] == trace.to_str().unwrap())
+        .unwrap();
+    assert_eq!(input["bytes"], content.len());
+    assert_eq!(
+        input["sha256"],
+        format!("{:x}", Sha256::digest(content.as_bytes()))
+    );
+    assert_eq!(stats["rows"]["m"], 4);
+    Ok(())
+}
+
+#[test]
+fn lt_traces() -> Result<()> {
+    use crate::compiler::ColumnRef;
+    use crate::import::{self, TraceFormat};
+    use crate::structs::Handle;
+
+    let tmp = temp_dir();
+
+    let load = || -> Result<crate::compiler::ConstraintSet> {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(
+            "(defcolumns (A :byte) B) (defconstraint double () (vanishes! (- B (* 2 A))))",
+        )?;
+        builder.expand_to(ExpansionLevel::top());
+        builder.into_constraint_set()
+    };
+    let json = tmp.path().join("lt-traces.json");
+    let lt = tmp.path().join("lt-traces.bin");
+    std::fs::write(
+        &json,
+        r#"  {"<prelude>": {"A": [1, 2, 255], "B": [2, 4, 510]}}"#,
+    )?;
+
+    // JSON -> LT, as done by `convert`
+    let mut cs = load()?;
+    import::parse_trace(json.to_str().unwrap(), &mut cs, true)?;
+    let mut out = std::fs::File::create(&lt)?;
+    import::write_binary_trace(&cs, &[], false, &mut out)?;
+    assert_eq!(
+        TraceFormat::detect(json.to_str().unwrap())?,
+        TraceFormat::Json
+    );
+    assert_eq!(TraceFormat::detect(lt.to_str().unwrap())?, TraceFormat::Lt);
+
+    // the LT trace is detected as such, whatever its extension
+    let mut cs = load()?;
+    crate::compute::compute_trace(lt.to_str().unwrap(), &mut cs, true)?;
+    crate::check::check(&cs, &None, &[], crate::check::CheckConfig::new())?;
+    let b = ColumnRef::from_handle(Handle::new("<prelude>", "B"));
+    assert_eq!(
+        (1..=3)
+            .map(|i| cs.columns.get(&b, i, false).unwrap().to_string())
+            .collect::<Vec<_>>(),
+        ["2", "4", "510"]
+    );
+    Ok(())
+}
+
+#[test]
+fn packed_traces() -> Result<()> {
+    use crate::{compiler::ColumnRef, import::TraceFormat, structs::Handle};
+    use std::io::Write;
+
+    let tmp = temp_dir();
+
+    // {"A": [1, 2, 255], "B": [2, 4, 510]}, 510 being given as raw bytes
+    let cbor = &b"\xa2\x61A\x83\x01\x02\x18\xff\x61B\x83\x02\x04\xc2\x42\x01\xfe"[..];
+    let msgpack = &b"\x82\xa1A\x93\x01\x02\xcc\xff\xa1B\x93\x02\x04\xc4\x02\x01\xfe"[..];
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(cbor)?;
+    let gz_cbor = gz.finish()?;
+    let traces = [
+        ("cbor", TraceFormat::Cbor, cbor),
+        ("msgpack", TraceFormat::MessagePack, msgpack),
+        ("cbor.gz", TraceFormat::Cbor, &gz_cbor[..]),
+    ];
+    let load = || {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(
+            "(defcolumns (A :byte) B) (defconstraint double () (vanishes! (- B (* 2 A))))",
+        )?;
+        builder.expand_to(ExpansionLevel::top());
+        builder.into_constraint_set()
+    };
+    let b = ColumnRef::from_handle(Handle::new("<prelude>", "B"));
+    for (extension, format, bytes) in traces {
+        let trace = tmp.path().join(format!("packed-traces.{}", extension));
+        std::fs::write(&trace, bytes)?;
+        assert_eq!(TraceFormat::detect(trace.to_str().unwrap())?, format);
+
+        let mut cs = load()?;
+        crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, false)?;
+        crate::check::check(&cs, &None, &[], crate::check::CheckConfig::new())?;
+        assert_eq!(
+            (1..=3)
+                .map(|i| cs.columns.get(&b, i, false).unwrap().to_string())
+                .collect::<Vec<_>>(),
+            ["2", "4", "510"]
+        );
+    }
+
+    // the traces must be consumed whole
+    for (extension, bytes) in [("cbor", cbor), ("msgpack", msgpack)] {
+        let trace = tmp.path().join(format!("packed-traces.{}", extension));
+        std::fs::write(&trace, [bytes, b"\x00"].concat())?;
+        let err = crate::compute::compute_trace(trace.to_str().unwrap(), &mut load()?, false)
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("trailing data"), "{:#}", err);
+    }
+
+    // truncated or trailing data is rejected
+    assert!(crate::packed::decode_cbor(&b"\x82\x01"[..]).is_err());
+    assert!(crate::packed::decode_msgpack(&b"\x01\x02"[..]).is_err());
+    Ok(())
+}
+
+#[test]
+fn pipeline() -> Result<()> {
+    use crate::compiler::ColumnRef;
+    use crate::structs::Handle;
+    use crate::transformer::AutoConstraint;
+    use crate::Pipeline;
+
+    let tmp = temp_dir();
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(defcolumns (A :byte) B) (defconstraint double () (vanishes! (- B (* 2 A))))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    builder.auto_constraints(AutoConstraint::all());
+    let mut cs = builder.into_constraint_set()?;
+    let trace = tmp.path().join("pipeline.json");
+    std::fs::write(
+        &trace,
+        r#"{"<prelude>": {"A": [1, 2, 255], "B": [2, 4, 511]}}"#,
+    )?;
+    let out_dir = tmp.path().join("pipeline");
+    let pipeline = Pipeline {
+        out_dir: out_dir.clone(),
+        bin: true,
+        check: true,
+        trace: true,
+        #[cfg(feature = "exporters")]
+        export: None,
+    };
+
+    // a failing check leaves only the compiled constraint set behind
+    assert!(pipeline.run(&mut cs, trace.to_str().unwrap()).is_err());
+    assert!(out_dir.join(Pipeline::BIN).exists());
+    assert!(!out_dir.join(Pipeline::TRACE).exists());
+
+    // the compiled constraint set is reusable as is
+    let mut cs = SourceLoader::from_bin(out_dir.join(Pipeline::BIN).to_str().unwrap())?
+        .into_constraint_set()?;
+    std::fs::write(
+        &trace,
+        r#"{"<prelude>": {"A": [1, 2, 255], "B": [2, 4, 510]}}"#,
+    )?;
+    pipeline.run(&mut cs, trace.to_str().unwrap())?;
+    let mut written = SourceLoader::from_bin(out_dir.join(Pipeline::BIN).to_str().unwrap())?
+        .into_constraint_set()?;
+    // the computed trace holds the padding
+    crate::import::parse_trace(
+        out_dir.join(Pipeline::TRACE).to_str().unwrap(),
+        &mut written,
+        true,
+    )?;
+    let b = ColumnRef::from_handle(Handle::new("<prelude>", "B"));
+    assert_eq!(
+        (0..=3)
+            .map(|i| written.columns.get(&b, i, false).unwrap().to_string())
+            .collect::<Vec<_>>(),
+        ["0", "2", "4", "510"]
+    );
+    Ok(())
+}
+
+#[test]
+fn solidity_tables() -> Result<()> {
+    use crate::exporters::{solidity, Mangling};
+
+    let tmp = temp_dir();
+
+    let trace = tmp.path().join("solidity-tables.json");
+    std::fs::write(
+        &trace,
+        r#"{"ops": {"OPCODE": [1, 2, 255], "COST": [3, 5, 700]}, "m": {"X": [1]}}"#,
+    )?;
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module m) (defcolumns X)
+         (module ops :length 3) (defconst GAS 21000) (defcolumns (OPCODE :byte) (COST :i16))
+         (defcolumns (DOUBLE :i32 :comp (* 2 COST)))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, false)?;
+
+    let render = |packing| -> Result<String> {
+        let out = tmp.path().join("solidity-tables.sol");
+        solidity::render(
+            &cs,
+            &["ops".to_string()],
+            "Tables",
+            packing,
+            Some(&out.to_str().unwrap().to_string()),
+            &Mangling::default(),
+        )?;
+        Ok(std::fs::read_to_string(out)?)
+    };
+
+    let array = render(solidity::Packing::Array)?;
+    assert!(array.contains("library Tables {"));
+    assert!(array.contains("uint256 internal constant OPS_GAS = 21000;"));
+    assert!(array.contains("return [uint8(1), 2, 255];"));
+    assert!(array.contains("return [uint32(6), 10, 1400];"));
+    assert!(!array.contains("m__X"));
+
+    let bytes = render(solidity::Packing::Bytes)?;
+    assert!(bytes.contains(r#"OPS_COST_DATA = hex"0003000502bc";"#));
+    assert!(bytes.contains("function ops__COST(uint256 i) internal pure returns (uint16 r)"));
+    assert!(bytes.contains("r := shr(240, mload(add(add(data, 32), mul(i, 2))))"));
+    Ok(())
+}
+
+#[test]
+fn failures_per_module() -> Result<()> {
+    use crate::check::CheckConfig;
+
+    let tmp = temp_dir();
+
+    let trace = tmp.path().join("failures-per-module.json");
+    std::fs::write(
+        &trace,
+        r#"{"a": {"X": [1, 2], "Y": [1, 3]}, "b": {"Z": [0, 1]}, "c": {"W": [0, 0]}}"#,
+    )?;
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module a) (defcolumns X Y)
+         (defconstraint eq () (vanishes! (- X Y)))
+         (defconstraint small () (vanishes! (* X (- X 1))))
+         (defconstraint ok () (vanishes! (- X X)))
+         (module b) (defcolumns Z)
+         (defconstraint zero () (vanishes! Z))
+         (module c) (defcolumns W)
+         (defconstraint zero () (vanishes! W))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, false)?;
+
+    let err = crate::check::check(&cs, &None, &[], CheckConfig::new()).unwrap_err();
+    assert_eq!(
+        crate::check::strip_colors(&err.to_string()),
+        "constraints failed: a.eq, a.small, b.zero"
+    );
+    Ok(())
+}
+
+#[test]
+fn large_modules_checked_by_chunks() -> Result<()> {
+    use crate::check::{CheckConfig, FailureReport};
+    use std::sync::{Arc, Mutex};
+
+    let tmp = temp_dir();
+
+    // X = Y but on two rows, lying in different chunks of rows
+    let rows = 5 << 16;
+    let xs = (0..rows).map(|i| i % 7).collect::<Vec<_>>();
+    let mut ys = xs.clone();
+    ys[(3 << 16) + 5] += 1;
+    ys[(1 << 16) + 2] += 1;
+    let trace = tmp.path().join("large-modules-checked-by-chunks.json");
+    std::fs::write(
+        &trace,
+        serde_json::json!({"X": xs, "Y": ys, "Z": xs}).to_string(),
+    )?;
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(defcolumns X Y Z) (defconstraint eq () (vanishes! (- X Y))) (defconstraint ok () (vanishes! (- X Z)))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, false)?;
+
+    // the first failing row is reported, whatever the chunk checked first
+    let err = crate::check::check(&cs, &None, &[], CheckConfig::new()).unwrap_err();
+    assert_eq!(
+        crate::check::strip_colors(&err.to_string()),
+        "constraints failed: eq"
+    );
+    let rows = |config: CheckConfig| {
+        let report = Arc::new(Mutex::new(FailureReport::default()));
+        let _ = crate::check::check(&cs, &None, &[], config.record_failures(report.clone()));
+        let rows = report
+            .lock()
+            .unwrap()
+            .violations
+            .iter()
+            .map(|v| v.row)
+            .collect::<Vec<_>>();
+        rows
+    };
+    assert_eq!(rows(CheckConfig::new()), [(1 << 16) + 3]);
+    assert_eq!(
+        rows(CheckConfig::new().continue_on_error(true)),
+        [(1 << 16) + 3, (3 << 16) + 6]
+    );
+    Ok(())
+}
+
+#[test]
+fn differential_check() -> Result<()> {
+    use crate::compiler::ConstraintSet;
+
+    let tmp = temp_dir();
+
+    let trace = tmp.path().join("differential.json");
+    std::fs::write(
+        &trace,
+        r#"{"<prelude>": {"A": [0, 1, 0, 2], "B": [0, 3, 0, 1], "C": [5, 0, 0, 0], "D": [1, 0, 0, 1]}}"#,
+    )?;
+    let filled = |src: &str, level: ExpansionLevel| -> Result<ConstraintSet> {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(src)?;
+        builder.expand_to(level);
+        let mut cs = builder.into_constraint_set()?;
+        crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, false)?;
+        Ok(cs)
+    };
+
+    let src = "(defcolumns A B C (D :binary))
+               (defconstraint ifs () (if-zero A (vanishes! B) (vanishes! C)))
+               (defconstraint nested () (vanishes! (+ (if-not-zero A B C) (if-zero D B C))))
+               (defconstraint bin () (if-not-zero D (vanishes! (- B C)) (vanishes! C)))";
+    crate::check::differential(
+        &filled(src, ExpansionLevel::None)?,
+        &filled(src, ExpansionLevel::top())?,
+        &None,
+        &[],
+    )?;
+
+    // a lowering swapping the branches of a conditional is caught
+    let swapped = "(defcolumns A B C (D :binary))
+                   (defconstraint ifs () (if-zero A (vanishes! C) (vanishes! B)))";
+    let err = crate::check::differential(
+        &filled(src, ExpansionLevel::None)?,
+        &filled(swapped, ExpansionLevel::top())?,
+        &None,
+        &[],
+    )
+    .unwrap_err();
+    let err = crate::check::strip_colors(&err.to_string());
+    assert!(err.contains("ifs"));
+    assert!(!err.contains("nested"));
+    Ok(())
+}
+
+#[test]
+fn compilation_cache() -> Result<()> {
+    use crate::{cache::Cache, compiler::ConstraintSet};
+
+    let tmp = temp_dir();
+
+    let dir = tmp.path().join("compilation-cache");
+    let compile_with = |sources: &[&str], no_stdlib, promotion_gap| -> Result<ConstraintSet> {
+        let mut builder = SourceLoader::from_sources(no_stdlib, false);
+        for src in sources {
+            builder.add_source(src)?;
+        }
+        builder.promotion_gap(promotion_gap);
+        builder.cache(Cache::new(dir.to_str()));
+        builder.into_constraint_set()
+    };
+    let compile = |src: &str| compile_with(&[src], false, None);
+    let entries = |extension: &str| {
+        std::fs::read_dir(&dir)
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().path().extension().unwrap() == extension)
+            .count()
+    };
+
+    let src = "(defcolumns A B) (defconstraint eq () (vanishes! (- A B)))";
+    compile(src)?;
+    assert_eq!(entries("bin"), 1);
+    let cached = compile(src)?;
+    assert_eq!(entries("bin"), 1);
+    assert_eq!(cached.constraints.len(), 1);
+    assert_eq!(cached.columns.all().len(), 2);
+
+    // a corrupted entry is compiled again
+    for entry in std::fs::read_dir(&dir)? {
+        std::fs::write(entry?.path(), "garbage")?;
+    }
+    assert_eq!(compile(src)?.constraints.len(), 1);
+
+    compile("(defcolumns A B C) (defconstraint eq () (vanishes! (- A B C)))")?;
+    assert_eq!(entries("bin"), 2);
+
+    // all the compilation settings are part of the key
+    compile_with(&[src], false, Some(8))?;
+    assert_eq!(entries("bin"), 3);
+
+    // every module is parsed once, whatever the other ones; the standard
+    // library is left out, as only its parts used by the sources are included
+    let src = "(defcolumns A B) (defconstraint eq () (- A B))";
+    compile_with(&[src], true, None)?;
+    let asts = entries("ast");
+    let module = "(module m) (defcolumns X) (defconstraint x () X)";
+    compile_with(&[src, module], true, None)?;
+    assert_eq!(entries("ast"), asts + 1);
+    compile_with(&["(defcolumns A B)", module], true, None)?;
+    assert_eq!(entries("ast"), asts + 2);
+    Ok(())
+}
+
+#[test]
+fn cached_warnings() -> Result<()> {
+    use crate::{cache::Cache, check::strip_colors, compiler::record_warnings};
+
+    let tmp = temp_dir();
+
+    let dir = tmp.path().join("cached-warnings");
+    let compile = || -> Result<Vec<String>> {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(
+            "(defcolumns (B :byte) (W :i32))
+             (defun (first x y) x)
+             (defconstraint mixed () (vanishes! (+ (first B W) W)))",
+        )?;
+        builder.promotion_gap(Some(8));
+        builder.cache(Cache::new(dir.to_str()));
+        let (cs, warnings) = record_warnings(|| builder.into_constraint_set());
+        cs?;
+        Ok(warnings.iter().map(|w| strip_colors(w)).collect())
+    };
+
+    let compiled = compile()?;
+    assert!(compiled
+        .iter()
+        .any(|w| w.contains("is implicitly promoted")));
+    assert!(compiled.iter().any(|w| w.contains("is never used")));
+    // the warnings are emitted again when the compilation is reused, in which
+    // case the sources are not even parsed
+    let asts = || -> Result<Vec<std::path::PathBuf>> {
+        Ok(std::fs::read_dir(&dir)?
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.extension().unwrap() == "ast")
+            .collect())
+    };
+    for ast in asts()? {
+        std::fs::remove_file(ast)?;
+    }
+    assert_eq!(compile()?, compiled);
+    assert!(asts()?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn json_export() -> Result<()> {
+    use crate::{exporters::json, transformer::AutoConstraint};
+    use itertools::Itertools;
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module m) (defcolumns A (B :byte))
+         (defperspective p (= A 1) ((X :binary)))
+         (defconstraint shifted () (vanishes! (- (shift A 1) B)))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    builder.auto_constraints(AutoConstraint::all());
+    let cs = builder.into_constraint_set()?;
+
+    let mut out = Vec::new();
+    json::render(&cs, false, &mut out)?;
+    let json: serde_json::Value = serde_json::from_slice(&out)?;
+    assert_eq!(json["format"], "corset-constraint-set");
+    assert_eq!(json["version"], 2);
+
+    // the export must abide by its published schema
+    let schema: serde_json::Value =
+        serde_json::from_str(include_str!("exporters/json.schema.json"))?;
+    let schema = jsonschema::JSONSchema::compile(&schema).map_err(|e| anyhow!("{}", e))?;
+    if let Err(errors) = schema.validate(&json) {
+        bail!(
+            "the JSON export does not match its schema:\n{}",
+            errors
+                .map(|e| format!("{} at {}", e, e.instance_path))
+                .join("\n")
+        )
+    };
+
+    let columns = json["columns"].as_array().unwrap();
+    let id_of = |name: &str| {
+        columns
+            .iter()
+            .find(|c| c["name"] == name)
+            .map(|c| c["id"].clone())
+            .unwrap()
+    };
+    let b = columns.iter().find(|c| c["name"] == "B").unwrap();
+    assert_eq!(b["type"], "byte");
+    assert_eq!(b["kind"], "commitment");
+    let x = columns.iter().find(|c| c["name"] == "X").unwrap();
+    assert_eq!(x["perspective"], "p");
+
+    let shifted = json["constraints"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["name"] == "shifted")
+        .unwrap();
+    assert_eq!(shifted["kind"], "vanishes");
+    assert_eq!(shifted["expr"]["funcall"]["op"], "sub");
+    let a = &shifted["expr"]["funcall"]["args"][0]["column"];
+    assert_eq!(a["id"], id_of("A"));
+    assert_eq!(a["shift"], 1);
+
+    assert_eq!(json["perspectives"][0]["name"], "p");
+    assert_eq!(json["modules"][0]["spilling"], 1);
+
+    serde_json::from_str::<serde_json::Value>(json::SCHEMA)?;
+    Ok(())
+}
+
+#[test]
+fn list_columns() -> Result<()> {
+    use crate::exporters::debugger::{list_columns, ListingFormat};
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module m) (defcolumns (ACC_A :byte) (ACC_B :i16 :padding 3) X)
+         (definterleaved I (ACC_A ACC_B))
+         (module n) (defcolumns ACC_C)",
+    )?;
+    let cs = builder.into_constraint_set()?;
+    let list = |module: Option<&str>, filter: Option<&str>, format| -> Result<String> {
+        let filter = filter.map(regex_lite::Regex::new).transpose()?;
+        let mut out = Vec::new();
+        list_columns(&cs, module, filter.as_ref(), format, &mut out)?;
+        Ok(String::from_utf8(out)?)
+    };
+
+    let csv = list(Some("m"), Some("^ACC_"), ListingFormat::Csv)?;
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("id,module,name,type,multiplier,register,padding")
+    );
+    let rows = lines.collect::<Vec<_>>();
+    assert_eq!(rows.len(), 2);
+    assert!(rows
+        .iter()
+        .any(|r| r.contains(",m,ACC_B,i16,1,r") && r.ends_with("/m.ACC_Bι1,3")));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&list(None, Some("I|ACC_C"), ListingFormat::Json)?)?;
+    let columns = json.as_array().unwrap();
+    assert_eq!(columns.len(), 2);
+    assert!(columns
+        .iter()
+        .any(|c| c["name"] == "I" && c["multiplier"] == 2 && c["padding"].is_null()));
+
+    let table = list(Some("n"), None, ListingFormat::Table)?;
+    assert_eq!(table.lines().count(), 2);
+    assert!(table.starts_with("ID  Name     Type"));
+    assert!(list(Some("nope"), None, ListingFormat::Table).is_err());
+    Ok(())
+}
+
+#[test]
+fn spillings_after_expansion() -> Result<()> {
+    use crate::transformer::AutoConstraint;
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module m) (defcolumns A) (defpermutation (B) ((↓ A)))
+         (module n) (defcolumns X) (defconstraint c () (vanishes! (- X (shift X 3))))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    builder.auto_constraints(AutoConstraint::all());
+    let mut cs = builder.into_constraint_set()?;
+
+    // the sorting constraints only appear once expanded, and read the
+    // previous rows
+    assert!(cs.spilling_of("m").unwrap() >= 1);
+    assert_eq!(cs.spilling_of("n"), Some(3));
+    let cached = cs.columns.spilling.clone();
+    for module in cs.columns.modules() {
+        assert_eq!(cs.compute_spilling(&module), cached[&module]);
+    }
+    Ok(())
+}
+
+#[test]
+fn shift_windows() -> Result<()> {
+    use crate::compiler::ShiftWindow;
+    use crate::transformer::AutoConstraint;
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module m) (defcolumns A B) (defconstraint c () (vanishes! (- (shift A -2) (next B))))
+         (module n) (defcolumns X) (deflookup lk (m.A) ((shift X 3)))
+         (module o) (defcolumns Y)",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    builder.auto_constraints(AutoConstraint::all());
+    let cs = builder.into_constraint_set()?;
+
+    let window = |backward, forward| ShiftWindow { backward, forward };
+    assert_eq!(cs.shift_window_of("m"), window(2, 1));
+    // the shifts of a lookup count for the module of their column
+    assert_eq!(cs.shift_window_of("n"), window(0, 3));
+    assert_eq!(cs.shift_window_of("o"), window(0, 0));
+    assert_eq!(
+        crate::stats::module_stats(&cs)["n"].shift_window,
+        window(0, 3)
+    );
+    Ok(())
+}
+
+#[test]
+fn complexity_limits() -> Result<()> {
+    use crate::compiler::complexity::ComplexityLimits;
+
+    let source = "(module m) (defcolumns A B C)
+                  (defconstraint c () (vanishes! (* (+ A B) C (+ A B))))";
+    let build = |limits| -> Result<crate::ConstraintSet> {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(source)?;
+        builder.expand_to(ExpansionLevel::top());
+        builder.complexity_limits(limits);
+        builder.into_constraint_set()
+    };
+
+    assert!(build(ComplexityLimits {
+        max_nodes: None,
+        max_degree: Some(3),
+    })
+    .is_ok());
+    let err = crate::check::strip_colors(
+        &build(ComplexityLimits {
+            max_nodes: None,
+            max_degree: Some(2),
+        })
+        .err()
+        .unwrap()
+        .to_string(),
+    );
+    assert!(err.contains("m.c"));
+    assert!(err.contains("degree 3"));
+    // the shared sum is the best candidate to be committed to a column
+    assert!(err.contains("consider committing (Add m.A m.B)"), "{}", err);
+
+    assert!(build(ComplexityLimits {
+        max_nodes: Some(4),
+        max_degree: None,
+    })
+    .is_err());
+    Ok(())
+}
+
+#[cfg(feature = "exporters")]
+#[test]
+fn bundle() -> Result<()> {
+    use crate::transformer::AutoConstraint;
+    use sha2::{Digest, Sha256};
+
+    let tmp = temp_dir();
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        r#"(module m) (defcolumns (A :byte :group "input") B) (defconstraint double () (vanishes! (- B (* 2 A))))"#,
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    builder.auto_constraints(AutoConstraint::all());
+    let mut cs = builder.into_constraint_set()?;
+    let out_dir = tmp.path().join("bundle");
+    let manifest = crate::bundle::bundle(&mut cs, &out_dir)?;
+
+    // the manifest indexes every other file of the bundle
+    let mut names = std::fs::read_dir(&out_dir)?
+        .map(|e| Ok(e?.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<Vec<_>>>()?;
+    names.sort();
+    let mut indexed = manifest
+        .files
+        .iter()
+        .map(|f| f.name.clone())
+        .chain(std::iter::once(crate::bundle::MANIFEST.to_owned()))
+        .collect::<Vec<_>>();
+    indexed.sort();
+    assert_eq!(names, indexed);
+    for f in manifest.files.iter() {
+        let content = std::fs::read(out_dir.join(&f.name))?;
+        assert_eq!(content.len(), f.bytes);
+        assert_eq!(
+            Sha256::digest(&content)
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>(),
+            f.sha256
+        );
+    }
+
+    let docs = std::fs::read_to_string(out_dir.join("constraints.md"))?;
+    assert!(docs.contains("| `A` | byte | committed |"), "{}", docs);
+    assert!(docs.contains("| `double` | vanishes |"), "{}", docs);
+    assert!(docs.contains("### input"), "{}", docs);
+    let stats: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(out_dir.join("stats.json"))?)?;
+    assert_eq!(stats["m"]["columns"], 2);
+    assert_eq!(stats["m"]["lookups"], 0);
+    // the compiled constraint set is reusable as is
+    SourceLoader::from_bin(out_dir.join("constraints.bin").to_str().unwrap())?
+        .into_constraint_set()?;
+    Ok(())
+}
+
+#[test]
+fn simplification() -> Result<()> {
+    use crate::check::CheckConfig;
+
+    let tmp = temp_dir();
+
+    let source = "(module m) (defcolumns A B C)
+                  (defconstraint folded () (vanishes! (- (^ B 1) (* A 1) 0 (* 2 3 C))))
+                  (defconstraint factored () (vanishes! (- (* C A 2) (* 2 B C))))
+                  (defconstraint zero () (vanishes! (* A 0 B)))";
+    let build = || -> Result<crate::ConstraintSet> {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source(source)?;
+        builder.into_constraint_set()
+    };
+
+    let cs = build()?;
+    let exprs = cs
+        .constraints
+        .iter()
+        .map(|c| match c {
+            crate::compiler::Constraint::Vanishes { handle, expr, .. } => {
+                (handle.to_string(), expr.to_string())
+            }
+            _ => unreachable!(),
+        })
+        .collect::<std::collections::HashMap<_, _>>();
+    assert_eq!(exprs["m.folded"], "(- B A (* 6 C))");
+    assert_eq!(exprs["m.factored"], "(* C 2 (- A B))");
+    assert_eq!(exprs["m.zero"], "0");
+
+    // the simplified constraints keep their semantic
+    let trace = tmp.path().join("simplification.json");
+    for (columns, valid) in [
+        (r#""A": [1, 2], "B": [1, 2], "C": [0, 0]"#, true),
+        (r#""A": [1, 2], "B": [7, 8], "C": [1, 1]"#, false),
+    ] {
+        let mut cs = build()?;
+        std::fs::write(&trace, format!(r#"{{"m": {{{}}}}}"#, columns))?;
+        crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+        let r = crate::check::check(&cs, &None, &[], CheckConfig::new());
+        assert_eq!(r.is_ok(), valid, "{}", columns);
+    }
+    Ok(())
+}
+
+#[test]
+fn watched_files() -> Result<()> {
+    use crate::watch::Watched;
+
+    let tmp = temp_dir();
+
+    let root = tmp.path().join("watched-files");
+    std::fs::create_dir_all(root.join("alu"))?;
+    std::fs::write(root.join("constants.lisp"), "")?;
+    std::fs::write(root.join("trace.json"), "")?;
+    let root = std::fs::canonicalize(root)?;
+    let watched = Watched::new(
+        &[
+            root.join("constants.lisp").display().to_string(),
+            root.join("alu").display().to_string(),
+        ],
+        root.join("trace.json").to_str().unwrap(),
+    )?;
+
+    assert!(watched.concerns(&root.join("constants.lisp")));
+    assert!(watched.concerns(&root.join("trace.json")));
+    // the sources of a watched directory are found recursively
+    assert!(watched.concerns(&root.join("alu/add/add.lisp")));
+    assert!(!watched.concerns(&root.join("alu/notes.txt")));
+    // siblings of the watched files are ignored
+    assert!(!watched.concerns(&root.join("other.lisp")));
+    Ok(())
+}
+
+#[test]
+fn register_widths() -> Result<()> {
+    use crate::column::Column;
+    use crate::compiler::{builder::ConstraintSetBuilder, Intrinsic, Kind, Magma};
+    use crate::structs::Handle;
+
+    let mut builder = ConstraintSetBuilder::new();
+    let reg = builder.register(Handle::new("m", "packed"), Magma::integer(32));
+    let a = builder.commitment(Handle::new("m", "A"), Magma::byte())?;
+    let b = builder.commitment(Handle::new("m", "B"), Magma::byte())?;
+    let c = builder.commitment(Handle::new("m", "C"), Magma::binary())?;
+    builder.assign_register(&a, reg)?;
+    builder.assign_register(&b, reg)?;
+    // the range of a proven column is enforced rather than assumed
+    let proven = builder.register(Handle::new("m", "proven"), Magma::integer(32));
+    let d = builder.column(
+        Column::builder()
+            .handle(Handle::new("m", "D"))
+            .kind(Kind::Commitment)
+            .t(Magma::byte())
+            .must_prove(true)
+            .build(),
+    )?;
+    builder.assign_register(&d, proven)?;
+    let (a, b, c, d) = (
+        builder.node(&a, Kind::Commitment)?,
+        builder.node(&b, Kind::Commitment)?,
+        builder.node(&c, Kind::Commitment)?,
+        builder.node(&d, Kind::Commitment)?,
+    );
+    builder.vanishes(
+        Handle::new("m", "sum"),
+        None,
+        Intrinsic::Add.call(&[a, b, c, d])?,
+    );
+    let cs = builder.build()?;
+
+    let widths = cs.columns.register_bit_widths();
+    assert_eq!(widths[reg], 32);
+    assert_eq!(widths[proven], 32);
+    let c_reg = cs
+        .columns
+        .by_handle(&Handle::new("m", "C"))?
+        .register
+        .unwrap();
+    assert_eq!(widths[c_reg], 1);
+
+    // the register is 32 bits wide, but the constraints assume bytes
+    let widened = cs.columns.widened_registers();
+    assert_eq!(widened.len(), 1);
+    assert_eq!(widened[0].register, reg);
+    assert_eq!(widened[0].bits, 32);
+    assert_eq!(widened[0].assumed_bits, 8);
+    assert_eq!(widened[0].assumed_by, "m.A");
+
+    let mut out = Vec::new();
+    crate::exporters::json::render(&cs, false, &mut out)?;
+    let json: serde_json::Value = serde_json::from_slice(&out)?;
+    assert_eq!(json["registers"][reg]["bits"], 32);
+    Ok(())
+}
+
+#[test]
+fn padding_strategies() -> Result<()> {
+    use crate::compute::{Padding, PaddingStrategy};
+
+    let tmp = temp_dir();
+
+    let padding = Padding::parse(&["pow2".into(), "rom=2^4".into(), "ram=100".into()])?;
+    assert_eq!(padding.of("alu"), PaddingStrategy::PowerOfTwo);
+    assert_eq!(padding.of("rom"), PaddingStrategy::Length(16));
+    assert_eq!(padding.of("ram"), PaddingStrategy::Length(100));
+    assert!(Padding::parse(&["rom=2^".into()]).is_err());
+    assert_eq!(PaddingStrategy::PowerOfTwo.target(5)?, 8);
+    assert_eq!(PaddingStrategy::PowerOfTwo.target(8)?, 8);
+    assert!(PaddingStrategy::Length(4).target(5).is_err());
+
+    // other tests may run concurrently, and must not see this override
+    crate::PADDING
+        .write()
+        .unwrap()
+        .modules
+        .insert("padded".into(), PaddingStrategy::Length(16));
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module padded) (defcolumns A B) (defconstraint eq () (vanishes! (- A (prev B))))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    let trace = tmp.path().join("padding-strategies.json");
+    std::fs::write(&trace, r#"{"padded": {"A": [0, 1, 2], "B": [1, 2, 3]}}"#)?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+
+    let spilling = cs.spilling_of("padded").unwrap();
+    assert_eq!(spilling, 1);
+    let a = crate::compiler::ColumnRef::from_handle(crate::structs::Handle::new("padded", "A"));
+    assert_eq!(cs.columns.padded_len(&a), Some(16));
+    Ok(())
+}
+
+#[test]
+fn doctor_findings() -> Result<()> {
+    use std::io::Write;
+
+    let tmp = temp_dir();
+
+    const SOURCE: &str = "(defcolumns A B) (defconstraint double () (vanishes! (- B (* 2 A))))";
+    let json = br#"{"A": [1, 2], "B": [2, 4]}"#;
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(json)?;
+    let gz = gz.finish()?;
+
+    let doctor = |sources: &[&str], name: &str, trace: &[u8]| -> Result<()> {
+        let path = tmp.path().join(name);
+        std::fs::write(&path, trace)?;
+        let sources = sources.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        crate::doctor::doctor(&sources, Some(path.to_str().unwrap()), false)
+    };
+
+    // the environment checks can only warn
+    doctor(&[SOURCE], "corset-doctor.json", json)?;
+    doctor(&[SOURCE], "corset-doctor.json.gz", &gz)?;
+    doctor(&[], "corset-doctor.json", json)?;
+
+    for (sources, name, trace) in [
+        // truncated traces
+        (&[SOURCE][..], "corset-doctor.json", &json[..json.len() / 2]),
+        (&[SOURCE], "corset-doctor.json.gz", &gz[..gz.len() / 2]),
+        (&[], "corset-doctor.json", &json[..json.len() / 2]),
+        // a trace incompatible with the constraint system
+        (
+            &[SOURCE],
+            "corset-doctor.json",
+            br#"{"A": [1, 2], "B": [2]}"#,
+        ),
+        // a constraint system that does not compile
+        (
+            &["(defconstraint c () (vanishes! X))"],
+            "corset-doctor.json",
+            json,
+        ),
+    ] {
+        let err = doctor(sources, name, trace).unwrap_err().to_string();
+        assert!(err.starts_with("1 error(s)"), "{}: {}", name, err);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn remote_inputs() -> Result<()> {
+    use crate::input;
+    use std::io::{Read, Write};
+
+    // a server answering every request with the same trace, counting them
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}/trace.json", listener.local_addr()?);
+    let requests = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let served = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            served.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let body = r#"{"A": [1, 2, 3]}"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+    });
+    let requests = || requests.load(std::sync::atomic::Ordering::SeqCst);
+
+    // sniffing a remote input keeps it around for its parsing...
+    assert_eq!(input::head(&url, 6)?, b"{\"A\": ");
+    let mut content = String::new();
+    input::reader(&url)?.read_to_string(&mut content)?;
+    assert_eq!(content, r#"{"A": [1, 2, 3]}"#);
+    assert_eq!(requests(), 1);
+    // ...which then drops it
+    input::reader(&url)?.read_to_string(&mut String::new())?;
+    assert_eq!(requests(), 2);
+    Ok(())
+}
+
+#[test]
+fn compressed_inputs() -> Result<()> {
+    use crate::input::{self, Compression};
+    use std::io::{Read, Write};
+
+    let tmp = temp_dir();
+
+    let json = br#"{"A": [1, 2]}"#;
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(json)?;
+    let gz = gz.finish()?;
+    // a single-segment zstd frame, made of a single raw block
+    let mut zst = vec![0x28, 0xb5, 0x2f, 0xfd, 0x20, json.len() as u8];
+    zst.extend_from_slice(&((json.len() as u32) << 3 | 1).to_le_bytes()[..3]);
+    zst.extend_from_slice(json);
+
+    for (name, content, compression) in [
+        (
+            "corset-compressed-inputs.json",
+            &json[..],
+            Compression::None,
+        ),
+        ("corset-compressed-inputs.json.gz", &gz, Compression::Gzip),
+        ("corset-compressed-inputs.json.zst", &zst, Compression::Zstd),
+    ] {
+        let path = tmp.path().join(name);
+        std::fs::write(&path, content)?;
+        let path = path.to_str().unwrap();
+        assert_eq!(input::compression(path)?, compression);
+        let mut read = Vec::new();
+        input::reader(path)?.read_to_end(&mut read)?;
+        assert_eq!(read, json);
+        assert_eq!(input::head(path, 3)?, b"{\"A");
+        let mut decompressed = Vec::new();
+        input::decompress(content)?.read_to_end(&mut decompressed)?;
+        assert_eq!(decompressed, json);
+        assert!(!crate::import::is_expanded_trace(path)?);
+        assert_eq!(input::extension(path), Some("json"));
+
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source("(defcolumns A)")?;
+        let mut cs = builder.into_constraint_set()?;
+        crate::compute::compute_trace(path, &mut cs, true)?;
+        assert_eq!(cs.effective_len_for(crate::compiler::MAIN_MODULE), Some(3));
+    }
+
+    // LT traces and in-memory payloads are decompressed on the fly as well
+    let load = || -> Result<crate::compiler::ConstraintSet> {
+        let mut builder = SourceLoader::from_sources(false, false);
+        builder.add_source("(defcolumns A)")?;
+        builder.into_constraint_set()
+    };
+    let mut cs = load()?;
+    crate::compute::compute_trace_str(&gz, &mut cs, true)?;
+    assert_eq!(cs.effective_len_for(crate::compiler::MAIN_MODULE), Some(3));
+    let mut lt = Vec::new();
+    crate::import::write_binary_trace(&cs, &[], false, &mut lt)?;
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(&lt)?;
+    let path = tmp.path().join("compressed-inputs.lt.gz");
+    std::fs::write(&path, gz.finish()?)?;
+    let mut expected = load()?;
+    crate::import::read_binary_trace(&lt[..], &mut expected, false)?;
+    let mut cs = load()?;
+    crate::compute::compute_trace(path.to_str().unwrap(), &mut cs, true)?;
+    let a = crate::compiler::ColumnRef::from_handle(crate::structs::Handle::new(
+        crate::compiler::MAIN_MODULE,
+        "A",
+    ));
+    assert_eq!(cs.columns.len(&a), expected.columns.len(&a));
+
+    assert!(input::is_remote("https://example.com/constraints.bin"));
+    assert_eq!(
+        input::extension("https://example.com/constraints.bin.zst"),
+        Some("bin")
+    );
+    Ok(())
+}
+
+#[test]
+fn formatting_keeps_comments() -> Result<()> {
+    use crate::compiler::parser::parse_simple_ast;
+
+    let src = r#";; the columns
+(defcolumns A   B) ; two of them
+(defconstraint   foo () ;; A and B match
+  (vanishes! (- A
+   B)))
+;; corset:noformat
+(defconst X   1)"#;
+    let ast = &parse_simple_ast(&[("comments", src)])?[0].1;
+    let formatted = ast.format();
+    let reformatted = &parse_simple_ast(&[("comments", &formatted)])?[0].1;
+    assert_eq!(
+        ast.comments(),
+        [
+            ";; the columns",
+            "; two of them",
+            ";; A and B match",
+            ";; corset:noformat"
+        ]
+    );
+    assert_eq!(reformatted.comments(), ast.comments());
+    assert_eq!(reformatted.format(), formatted);
+    assert!(formatted.contains("(defconst X   1)"));
+    assert!(formatted.contains("(vanishes! (- A B))"));
+    Ok(())
+}
+
+#[test]
+fn strict_magma() -> Result<()> {
+    use crate::check::CheckConfig;
+
+    let tmp = temp_dir();
+
+    let trace = tmp.path().join("strict-magma.json");
+    std::fs::write(&trace, r#"{"A": [0, 1, 2, 0, 3]}"#)?;
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(defcolumns A (B :byte :comp (* A 200)))
+         (defconstraint b-doubles-a () (vanishes! (- B (* A 200))))",
+    )?;
+    builder.expand_to(ExpansionLevel::top());
+    let mut cs = builder.into_constraint_set()?;
+    crate::compute::compute_trace(trace.to_str().unwrap(), &mut cs, true)?;
+
+    // the constraints hold, but B overflows its type
+    crate::check::check(&cs, &None, &[], CheckConfig::new())?;
+    assert!(crate::check::check(&cs, &None, &[], CheckConfig::new().strict_magma(true)).is_err());
+    let violations = crate::check::magma_violations(&cs);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].column.name, "B");
+    // including the padding row
+    assert_eq!(violations[0].rows, [3, 5]);
+    assert_eq!(violations[0].count, 2);
+    Ok(())
+}
+
+#[test]
+fn lsp() -> Result<()> {
+    use crate::lsp::{path_to_uri, Server};
+    use serde_json::json;
+
+    let tmp = temp_dir();
+
+    let dir = tmp.path().join("corset lsp");
+    std::fs::create_dir_all(&dir)?;
+    let file = dir.join("m.lisp");
+    let src = "(module m)
+(defcolumns A (B :byte))
+(defalias C B)
+(defun (double x) (* 2 x))
+(defconst N 3)
+(defconstraint c () (vanishes! (- (double C) A N)))";
+    std::fs::write(&file, src)?;
+    let file = std::fs::canonicalize(file)?;
+    let uri = path_to_uri(&file);
+    assert!(uri.ends_with("/corset%20lsp/m.lisp"));
+
+    let mut server = Server::new(&[file.to_string_lossy().into_owned()], false);
+    let init =
+        server.handle(&json!({"jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {}}));
+    assert_eq!(init[0]["result"]["capabilities"]["hoverProvider"], true);
+    let open = json!({"jsonrpc": "2.0", "method": "textDocument/didOpen",
+                      "params": {"textDocument": {"uri": uri, "text": src}}});
+    assert!(server.handle(&open).is_empty());
+
+    let at = |method: &str, line: usize, character: usize| {
+        json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": {
+            "textDocument": {"uri": uri}, "position": {"line": line, "character": character}}})
+    };
+    // the alias is followed to the column it stands for
+    let definition = &server.handle(&at("textDocument/definition", 5, 42))[0]["result"];
+    assert_eq!(definition["uri"], uri);
+    assert_eq!(
+        definition["range"]["start"],
+        json!({"line": 1, "character": 14})
+    );
+    let hover =
+        server.handle(&at("textDocument/hover", 5, 42))[0]["result"]["contents"]["value"].clone();
+    assert_eq!(hover, "`B` : `[𝟠]`\n\ncolumn of module `m`");
+    let definition = &server.handle(&at("textDocument/definition", 5, 36))[0]["result"];
+    assert_eq!(definition["range"]["start"]["line"], 3);
+    let hover =
+        server.handle(&at("textDocument/hover", 5, 47))[0]["result"]["contents"]["value"].clone();
+    assert_eq!(hover, "`N` = `3`\n\nconstant of module `m`");
+
+    // errors are reported, then cleared once fixed
+    let change = |text: &str| {
+        json!({"jsonrpc": "2.0", "method": "textDocument/didChange", "params": {
+            "textDocument": {"uri": uri}, "contentChanges": [{"text": text}]}})
+    };
+    let broken = server.handle(&change(
+        &src.replace("(- (double C) A N)", "(- (double D) A N)"),
+    ));
+    assert_eq!(broken.len(), 1);
+    assert_eq!(broken[0]["params"]["uri"], uri);
+    let diagnostic = &broken[0]["params"]["diagnostics"][0];
+    assert_eq!(diagnostic["severity"], 1);
+    assert_eq!(diagnostic["range"]["start"]["line"], 5);
+    let fixed = server.handle(&change(src));
+    assert_eq!(fixed[0]["params"]["diagnostics"], json!([]));
+    Ok(())
+}
+
+#[test]
+fn diff_trace() -> Result<()> {
+    use crate::compiler::ConstraintSet;
+
+    let tmp = temp_dir();
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(defcolumns A B C)")?;
+    let constraints = builder.into_constraint_set()?.to_binary()?;
+    let dir = tmp.path();
+    let load = |name: &str, trace: &str| -> Result<ConstraintSet> {
+        let path = dir.join(name);
+        std::fs::write(&path, trace)?;
+        let mut cs = ConstraintSet::from_bytes(&constraints, name)?;
+        crate::compute::import_trace(path.to_str().unwrap(), &mut cs)?;
+        Ok(cs)
+    };
+    let reference = load(
+        "corset-diff-left.json",
+        r#"{"A": [1, 2, 3], "B": [4, 5, 6], "C": [7, 8, 9]}"#,
+    )?;
+    let other = load(
+        "corset-diff-right.json",
+        r#"{"A": [1, 2, 3, 4], "B": [4, 0, 6, 1]}"#,
+    )?;
+
+    assert!(crate::diff::diff(&reference, &reference).is_empty());
+    let diff = crate::diff::diff(&reference, &other);
+    assert_eq!(diff.identical, 0);
+    assert_eq!(diff.missing_right, ["C"]);
+    assert!(diff.missing_left.is_empty());
+    assert_eq!(diff.differing.len(), 2);
+    let b = &diff.differing[1];
+    assert_eq!(b.column, "B");
+    // the first row is the padding one
+    assert_eq!(b.first_row(), 2);
+    assert_eq!(b.count, 2);
+    assert_eq!(b.lengths, (4, 5));
+    assert_eq!(b.samples[1].left, None);
+    assert!(crate::diff::conclude(&diff).is_err());
+    Ok(())
+}
+
+#[test]
+fn source_spans() -> Result<()> {
+    use crate::structs::Handle;
+
+    let tmp = temp_dir();
+
+    let dir = tmp.path().join("spans");
+    std::fs::create_dir_all(&dir)?;
+    let file = dir.join("spans.lisp");
+    std::fs::write(
+        &file,
+        "(module m)
+(defcolumns A
+  (B :byte))
+(defcolumns (C :array [2]))
+
+(defconstraint a-is-b () (vanishes! (- A B)))
+(deflookup b-in-c (C_1) (B))",
+    )?;
+    let file = file.to_str().unwrap();
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(file)?;
+    builder.expand_to(ExpansionLevel::top());
+    let cs = builder.into_constraint_set()?;
+
+    let span = |h: &Handle| cs.spans.get(h).map(|s| s.to_string());
+    assert_eq!(
+        span(&Handle::new("m", "a-is-b")),
+        Some(format!("{file}:6:1"))
+    );
+    assert_eq!(
+        span(&Handle::new("m", "b-in-c")),
+        Some(format!("{file}:7:1"))
+    );
+    let column_span = |name: &str| {
+        cs.columns
+            .by_handle(&Handle::new("m", name))
+            .unwrap()
+            .span
+            .as_ref()
+            .map(|s| (s.line, s.column))
+    };
+    assert_eq!(column_span("A"), Some((2, 13)));
+    assert_eq!(column_span("B"), Some((3, 3)));
+    assert_eq!(column_span("C_1"), Some((4, 13)));
+
+    // failing constraints can then be traced back to their definition
+    #[cfg(feature = "exporters")]
+    {
+        let go = crate::exporters::wizardiop::render_constraints(
+            &cs,
+            &Default::default(),
+            &Default::default(),
+        );
+        assert!(go.contains(&format!("// defined at {file}:6:1")));
+        assert!(go.contains(&format!("// defined at {file}:7:1")));
+        let rust = crate::exporters::rust::render_constraints(
+            &cs,
+            &Default::default(),
+            &Default::default(),
+        )?;
+        assert!(rust.contains(&format!("// defined at {file}:6:1")));
+        let circom = crate::exporters::circom::render_constraints(
+            &cs,
+            &Default::default(),
+            &Default::default(),
+        )?;
+        assert!(circom.contains(&format!("// defined at {file}:7:1")));
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "postgres")]
+fn check_loop() -> Result<()> {
+    use crate::check::{check, CheckConfig, Checkpointer};
+    use crate::compiler::ConstraintSet;
+    use std::{sync::Arc, time::Duration};
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module m)
+         (defcolumns A B)
+         (defconstraint eq () (vanishes! (- A B)))
+         (defconstraint small () (vanishes! (* A (- A 1) (- A 2))))",
+    )?;
+    let constraints = crate::check_loop_constraints(builder)?;
+
+    // each block is checked against the constraints reloaded anew
+    let check_block = |payload: &str| -> Result<(Vec<String>, Vec<String>, Vec<i64>)> {
+        let mut cs = ConstraintSet::from_bytes(&constraints, "check-loop")?;
+        crate::compute::compute_trace_str(payload.as_bytes(), &mut cs, false)?;
+        let checkpointer = Arc::new(Checkpointer::new(
+            Default::default(),
+            Duration::from_secs(3600),
+            |_| Ok(()),
+        ));
+        let _ = check(
+            &cs,
+            &None,
+            &[],
+            CheckConfig::new().checkpoint(checkpointer.clone()),
+        );
+        Ok(crate::utils::constraint_stats(
+            &cs,
+            &checkpointer.progress(),
+        ))
+    };
+
+    let failing = check_block(r#"{"m": {"A": [1, 2], "B": [1, 3]}}"#)?;
+    assert_eq!(failing.0, ["m.small", "m.eq"]);
+    assert_eq!(failing.1, ["m", "m"]);
+    assert_eq!(failing.2, [0, 1]);
+    // a shorter block is not polluted by the columns of the previous one
+    let passing = check_block(r#"{"m": {"A": [2], "B": [2]}}"#)?;
+    assert_eq!(passing.0, ["m.eq", "m.small"]);
+    assert_eq!(passing.2, [0, 0]);
+    Ok(())
+}
+
+#[test]
+fn native_values_roundtrip() -> Result<()> {
+    use crate::column::Value;
+    use ark_bls12_377::fr::Fr;
+
+    let x = Value::Native(Fr::from(2));
+    let y: Value = serde_json::from_str(&serde_json::to_string(&x)?)?;
+    assert_eq!(y, x);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "inspector")]
+fn inspector_compat_mode() -> Result<()> {
+    use crate::inspect::{theme::Theme, Inspector};
+    use ratatui::{backend::TestBackend, Terminal};
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source("(module m1) (defcolumns A B) (module m2) (defcolumns C)")?;
+    let mut cs = builder.into_constraint_set()?;
+    crate::compute::compute_trace_str(
+        br#"{"m1": {"A": [1, 2], "B": [3, 4]}, "m2": {"C": [5]}}"#,
+        &mut cs,
+        true,
+    )?;
+    let render = |compat: bool| -> Result<String> {
+        let theme = Theme {
+            compat,
+            high_contrast: false,
+        };
+        let mut inspector = Inspector::from_cs(&cs, theme)?;
+        let mut terminal = Terminal::new(TestBackend::new(120, 20))?;
+        terminal.draw(|f| inspector.render(f))?;
+        Ok(terminal
+            .backend()
+            .buffer()
+            .content
+            .iter()
+            .map(|c| c.symbol())
+            .collect())
+    };
+
+    // Shift-Tab is replaced by brackets, and only ASCII glyphs are drawn
+    let compat = render(true)?;
+    assert!(compat.contains("< [[] Modules []] >"), "{}", compat);
+    assert!(compat.is_ascii(), "{}", compat);
+
+    let unicode = render(false)?;
+    assert!(unicode.contains("← [S-TAB] Modules [TAB] →"), "{}", unicode);
+    assert!(!unicode.is_ascii());
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "inspector")]
+fn inspector_histogram() {
+    use crate::{
+        column::Value,
+        inspect::{theme::Theme, widgets::histogram::Histogram},
+        pretty::Base,
+    };
+    use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+
+    let xs = [0usize, 1, 2, 2, 3, 3, 3];
+    let draw = |rows: std::ops::Range<isize>, k: usize| -> Vec<String> {
+        let histogram = Histogram::new(
+            "A",
+            |i| xs.get(i as usize).map(|x| Value::from(*x)),
+            rows,
+            Base::Dec,
+            k,
+            Theme::default(),
+        );
+        let area = Rect::new(0, 0, 80, 5);
+        let mut buffer = Buffer::empty(area);
+        histogram.chart().render(area, &mut buffer);
+        buffer
+            .content
+            .chunks(area.width as usize)
+            .map(|line| line.iter().map(|c| c.symbol()).collect())
+            .collect()
+    };
+
+    // the most frequent values come first, and only the top k are shown
+    let lines = draw(0..7, 2);
+    assert!(
+        lines[0].contains("A over [0; 6] -- 4 distinct values among 7"),
+        "{:?}",
+        lines
+    );
+    assert!(lines[1].starts_with("│3 3█"), "{:?}", lines);
+    assert!(lines[2].starts_with("│2 2█"), "{:?}", lines);
+    assert!(lines[3].trim_matches(['│', ' ']).is_empty(), "{:?}", lines);
+
+    // or only those over the displayed rows
+    let lines = draw(1..4, 2);
+    assert!(
+        lines[0].contains("A over [1; 3] -- 2 distinct values among 3"),
+        "{:?}",
+        lines
+    );
+    assert!(lines[1].starts_with("│2 2█"), "{:?}", lines);
+    assert!(lines[2].starts_with("│1 1█"), "{:?}", lines);
+}
+
+#[test]
+#[cfg(feature = "postgres")]
+fn backlog_progress() -> Result<()> {
+    use crate::{utils::BacklogReporter, Args, BlockOrder, Commands};
+    use clap::Parser;
+    use std::time::Duration;
+
+    let mut backlog = BacklogReporter::new(Duration::from_secs(60));
+    for _ in 0..3 {
+        backlog.processed();
+    }
+    assert_eq!(
+        backlog.report(30, Duration::from_secs(60)),
+        "30 blocks left; 3.00 blocks/min; ETA: 10m00s"
+    );
+    // every report starts a new period
+    assert_eq!(
+        backlog.report(30, Duration::from_secs(60)),
+        "30 blocks left; 0.00 blocks/min; ETA: unknown"
+    );
+    assert_eq!(
+        backlog.report(0, Duration::from_secs(60)),
+        "0 blocks left; 0.00 blocks/min; ETA: now"
+    );
+    backlog.processed();
+    assert_eq!(
+        backlog.report(7510, Duration::from_secs(1)),
+        "7510 blocks left; 60.00 blocks/min; ETA: 2h05m10s"
+    );
+
+    // the smallest blocks are processed first, unless told otherwise
+    let order = |args: &[&str]| -> Result<BlockOrder> {
+        let args = Args::try_parse_from(["corset", "check-loop"].iter().chain(args))?;
+        match args.command {
+            Some(Commands::CheckLoop { order, .. }) => Ok(order),
+            _ => unreachable!(),
+        }
+    };
+    assert!(matches!(order(&[])?, BlockOrder::Size));
+    assert!(matches!(order(&["--order", "id"])?, BlockOrder::Id));
+    Ok(())
+}
+
+#[test]
+fn library_api() -> Result<()> {
+    let tmp = temp_dir();
+    const SOURCE: &str =
+        "(defcolumns A (B :comp (* 2 A))) (defconstraint double () (vanishes! (- B (* 2 A))))";
+    let dir = tmp.path();
+    let trace = dir.join("library-api.json");
+    let out = dir.join("library-api-out.json");
+    let bin = dir.join("library-api.bin");
+    let sources = [("library-api".to_string(), SOURCE.to_string())];
+
+    // the constraint set may be compiled from its sources, or loaded from the
+    // output of `corset compile`
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(SOURCE)?;
+    std::fs::write(&bin, builder.into_constraint_set()?.to_binary()?)?;
+    let compiled = corset::compile(&sources, &Default::default(), false)?;
+    let loaded = corset::load(bin.to_str().unwrap())?;
+
+    std::fs::write(&trace, r#"{"A": [1, 2, 3]}"#)?;
+    for mut cs in [compiled, loaded] {
+        corset::import_trace(trace.to_str().unwrap(), &mut cs)?;
+        corset::compute(&mut cs, true)?;
+        corset::check(&cs, corset::CheckConfig::new())?;
+        corset::write_trace(
+            &mut cs,
+            out.to_str().unwrap(),
+            false,
+            corset::ValuesEncoding::Hex,
+        )?;
+        assert!(std::fs::read_to_string(&out)?.starts_with(r#"{"columns":{"#));
+    }
+
+    std::fs::write(&trace, r#"{"A": [1, 2, 3], "B": [2, 4, 7]}"#)?;
+    let mut cs = corset::compile(&sources, &Default::default(), false)?;
+    corset::import_trace(trace.to_str().unwrap(), &mut cs)?;
+    corset::compute(&mut cs, true)?;
+    assert!(corset::check(&cs, corset::CheckConfig::new()).is_err());
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "inspector")]
+fn inspector_constraints_pane() -> Result<()> {
+    use crate::inspect::{theme::Theme, Inspector};
+    use ratatui::{backend::TestBackend, Terminal};
+
+    let mut builder = SourceLoader::from_sources(false, false);
+    builder.add_source(
+        "(module m) (defcolumns A B C D E F G H)
+         (defconstraint first () (vanishes! (- G H)))
+         (defconstraint second () (vanishes! B))",
+    )?;
+    let mut cs = builder.into_constraint_set()?;
+    crate::compute::compute_trace_str(br#"{"m": {"A": [1], "B": [0], "C": [2], "D": [3], "E": [4], "F": [5], "G": [6], "H": [6]}}"#, &mut cs, true)?;
+
+    let draw = |inspector: &mut Inspector| -> Result<Vec<String>> {
+        let mut terminal = Terminal::new(TestBackend::new(100, 16))?;
+        terminal.draw(|f| inspector.render(f))?;
+        let buffer = terminal.backend().buffer();
+        Ok(buffer
+            .content
+            .chunks(buffer.area.width as usize)
+            .map(|line| line.iter().map(|c| c.symbol()).collect())
+            .collect())
+    };
+
+    for compat in [false, true] {
+        let theme = Theme {
+            compat,
+            high_contrast: false,
+        };
+        let mut inspector = Inspector::from_cs(&cs, theme)?;
+        let lines = draw(&mut inspector)?;
+        assert!(!lines.concat().contains(" Constraints "));
+
+        // the pane lists the constraints of the module, the first one selected
+        inspector.toggle_constraints();
+        let lines = draw(&mut inspector)?;
+        assert!(lines[4].starts_with(" A "), "{:#?}", lines);
+        assert!(lines[4].trim_end().ends_with(" vnsh first"), "{:#?}", lines);
+        assert!(
+            lines[5].trim_end().ends_with(" vnsh second"),
+            "{:#?}",
+            lines
+        );
+        assert!(lines[15].starts_with("first: 2 column(s) referenced"));
+        assert_eq!(lines.concat().is_ascii(), compat, "{:#?}", lines);
+
+        // the columns it references are brought to the top in turn
+        for column in [" G ", " H ", " G "] {
+            inspector.jump_to_reference();
+            let lines = draw(&mut inspector)?;
+            assert!(lines[4].starts_with(column), "{:#?}", lines);
+        }
+
+        // the selection wraps around
+        inspector.move_constraint(1);
+        inspector.jump_to_reference();
+        let lines = draw(&mut inspector)?;
+        assert!(lines[4].starts_with(" B "), "{:#?}", lines);
+        assert!(lines[15].starts_with("second: 1 column(s) referenced"));
+        inspector.move_constraint(1);
+        let lines = draw(&mut inspector)?;
+        assert!(lines[15].starts_with("first: 2 column(s) referenced"));
+
+        inspector.toggle_constraints();
+        let lines = draw(&mut inspector)?;
+        assert!(!lines.concat().contains(" Constraints "));
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "postgres")]
+fn block_events() -> Result<()> {
+    use crate::{utils::BlockEvent, Args, Commands};
+    use clap::Parser;
+
+    let mut out = Vec::new();
+    for (block, result, failures) in [("0x12", "done", 0), ("0x13", "failed", 2)] {
+        BlockEvent {
+            block,
+            result,
+            duration_ms: 1500,
+            failures,
+            payload_size: 42,
+        }
+        .emit_to(&mut out)?;
+    }
+
+    // one JSON object per line
+    let events = String::from_utf8(out)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<std::result::Result<Vec<serde_json::Value>, _>>()?;
+    assert_eq!(
+        events,
+        vec![
+            serde_json::json!({
+                "block": "0x12",
+                "result": "done",
+                "duration_ms": 1500,
+                "failures": 0,
+                "payload_size": 42
+            }),
+            serde_json::json!({
+                "block": "0x13",
+                "result": "failed",
+                "duration_ms": 1500,
+                "failures": 2,
+                "payload_size": 42
+            }),
+        ]
+    );
+
+    let json_events = |args: &[&str]| -> Result<bool> {
+        let args = Args::try_parse_from(["corset", "check-loop"].iter().chain(args))?;
+        match args.command {
+            Some(Commands::CheckLoop { json_events, .. }) => Ok(json_events),
+            _ => unreachable!(),
+        }
+    };
+    assert!(!json_events(&[])?);
+    assert!(json_events(&["--json-events"])?);
+    Ok(())
+}
@@ -1,8 +1,11 @@
+mod bits;
 mod concretize;
 mod ifs;
 mod inverses;
 mod nhood;
 mod selectors;
+mod signs;
+mod simplify;
 mod sort;
 mod splatter;
 mod statics;
@@ -10,11 +13,14 @@ mod statics;
 use anyhow::*;
 use log::*;
 
+use bits::expand_bits;
 pub use concretize::concretize;
 use ifs::expand_ifs;
 use inverses::expand_invs;
 use nhood::validate_nhood;
 use selectors::expand_constraints;
+use signs::expand_signs;
+use simplify::simplify;
 use sort::sorts;
 use splatter::splatter;
 pub use statics::precompute;
@@ -84,6 +90,8 @@ impl From<u8> for ExpansionLevel {
     }
 }
 impl ExpansionLevel {
+    // This is only used by the library
+    #[allow(dead_code)]
     pub fn all() -> u8 {
         5
     }
@@ -95,13 +103,22 @@ impl ExpansionLevel {
     pub fn apply(&self, cs: &mut ConstraintSet) -> Result<()> {
         if (cs.transformations & *self as u32) == 0 {
             info!("Applying {:?}", self);
-            match self {
-                ExpansionLevel::None => {}
-                ExpansionLevel::ExpandsIfs => expand_ifs(cs),
-                ExpansionLevel::Splatter => splatter(cs),
-                ExpansionLevel::ColumnizeExpressions => expand_constraints(cs)?,
-                ExpansionLevel::ExpandInvs => expand_invs(cs)?,
-            }
+            let transformation = |cs: &mut ConstraintSet| -> Result<()> {
+                match self {
+                    ExpansionLevel::None => {}
+                    ExpansionLevel::ExpandsIfs => expand_ifs(cs)?,
+                    ExpansionLevel::Splatter => splatter(cs),
+                    ExpansionLevel::ColumnizeExpressions => {
+                        expand_signs(cs)?;
+                        expand_bits(cs)?;
+                        expand_constraints(cs)?
+                    }
+                    ExpansionLevel::ExpandInvs => expand_invs(cs)?,
+                }
+                Ok(())
+            };
+            transformation(cs)?;
+            on_assertions(cs, transformation)?;
             cs.transformations |= *self as u32;
         }
 
@@ -113,26 +130,61 @@ pub(crate) fn expand_to(
     cs: &mut ConstraintSet,
     level: ExpansionLevel,
     auto_constraints: &[AutoConstraint],
+) -> Result<()> {
+    expand_to_with(cs, level, auto_constraints, |_, _| {})
+}
+
+/// Same as [`expand_to`], calling `on_stage` with the label of every step of
+/// the pipeline and the constraint set it produced
+pub(crate) fn expand_to_with(
+    cs: &mut ConstraintSet,
+    level: ExpansionLevel,
+    auto_constraints: &[AutoConstraint],
+    mut on_stage: impl FnMut(&'static str, &ConstraintSet),
 ) -> Result<()> {
     for c in auto_constraints.iter() {
         c.apply(cs)?;
     }
 
-    for transformation in [
-        ExpansionLevel::ExpandsIfs,
-        ExpansionLevel::Splatter,
-        ExpansionLevel::ColumnizeExpressions,
-        ExpansionLevel::ExpandInvs,
+    for (label, transformation) in [
+        ("if-expansion", ExpansionLevel::ExpandsIfs),
+        ("splatting", ExpansionLevel::Splatter),
+        ("columnization", ExpansionLevel::ColumnizeExpressions),
+        ("normalization expansion", ExpansionLevel::ExpandInvs),
     ] {
         if level >= transformation {
             transformation.apply(cs)?;
+            on_stage(label, cs);
         }
     }
 
+    simplify(cs);
+    on_assertions(cs, |cs| {
+        simplify(cs);
+        Ok(())
+    })?;
+    on_stage("simplification", cs);
     cs.convert_refs_to_ids()?;
+    // the auto-constraints and the expansions may have added shifted
+    // expressions
+    cs.compute_spillings();
+    cs.compute_shift_windows();
     cs.validate()
 }
 
+/// Apply `transformation` to the assertions rather than to the constraints;
+/// the constraints it introduces while lowering them are then assertions as
+/// well, and are never exported either.
+fn on_assertions(
+    cs: &mut ConstraintSet,
+    transformation: impl FnOnce(&mut ConstraintSet) -> Result<()>,
+) -> Result<()> {
+    std::mem::swap(&mut cs.constraints, &mut cs.assertions);
+    let r = transformation(cs);
+    std::mem::swap(&mut cs.constraints, &mut cs.assertions);
+    r
+}
+
 fn validate_computation(cs: &mut Vec<Node>, x_expr: &Node, x_col: &Handle) {
     cs.push(
         Intrinsic::Sub
@@ -0,0 +1,169 @@
+use anyhow::*;
+use num_bigint::BigInt;
+use num_traits::{One, ToPrimitive};
+use std::collections::HashSet;
+
+use crate::{
+    column::{Column, Computation},
+    compiler::{ColumnRef, Constraint, ConstraintSet, Expression, Intrinsic, Kind, Magma, Node},
+    structs::Handle,
+};
+
+use super::expression_to_name;
+
+/// A decomposition of `x` into `width` bits, stored in the columns prefixed by
+/// `handle`
+struct Decomposition {
+    handle: Handle,
+    x: Node,
+    width: usize,
+}
+impl Decomposition {
+    fn bit(&self, k: usize) -> Handle {
+        Handle::new(&self.handle.module, format!("{}#{}", self.handle.name, k))
+    }
+
+    fn bit_node(&self, k: usize) -> Node {
+        Node::column()
+            .handle(self.bit(k))
+            .kind(Kind::Computed)
+            .t(Magma::binary())
+            .build()
+    }
+}
+
+impl Node {
+    /// Replace all the `(bits-of x N k)` in this expression with the column
+    /// holding the k-th bit of x, and the `(bits-of x N)` with x; the
+    /// decompositions they require are pushed in `decompositions`.
+    fn do_expand_bits(
+        &mut self,
+        get_module: &dyn Fn(&HashSet<ColumnRef>) -> String,
+        decompositions: &mut Vec<Decomposition>,
+    ) {
+        match self.e_mut() {
+            Expression::List(es) => {
+                for e in es.iter_mut() {
+                    e.do_expand_bits(get_module, decompositions);
+                }
+            }
+            Expression::Funcall { func, args } => {
+                for e in args.iter_mut() {
+                    e.do_expand_bits(get_module, decompositions);
+                }
+                if matches!(func, Intrinsic::Bits) {
+                    // the arguments have been validated at compile-time
+                    let x = args[0].clone();
+                    let width = args[1].pure_eval().unwrap().to_usize().unwrap();
+                    let bit = args
+                        .get(2)
+                        .map(|k| k.pure_eval().unwrap().to_usize().unwrap());
+                    if let Result::Ok(x) = self.pure_eval() {
+                        *self = Node::from_bigint(x);
+                        return;
+                    }
+
+                    let module = get_module(&x.dependencies());
+                    let decomposition = Decomposition {
+                        handle: Handle::new(
+                            module,
+                            expression_to_name(&x, &format!("BITS{}", width)),
+                        ),
+                        x: x.clone(),
+                        width,
+                    };
+                    *self = match bit {
+                        Some(k) => decomposition.bit_node(k),
+                        None => x,
+                    };
+                    decompositions.push(decomposition);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `(bits-of x N)` is replaced by N computed binary columns B_k, that are
+/// proven to be the bits of x by:
+///   - B_k·(1 - B_k) = 0, i.e. every B_k is binary;
+///   - x = ∑ 2^k·B_k, i.e. they recompose x.
+pub fn expand_bits(cs: &mut ConstraintSet) -> Result<()> {
+    let mut decompositions = vec![];
+    for c in cs.constraints.iter_mut() {
+        if let Constraint::Vanishes { handle, expr, .. } = c {
+            let module = handle.module.clone();
+            let columns = &cs.columns;
+            let get_module = |rs: &HashSet<ColumnRef>| {
+                columns
+                    .module_for(rs.iter())
+                    .unwrap_or_else(|| module.clone())
+            };
+            expr.do_expand_bits(&get_module, &mut decompositions);
+        }
+    }
+
+    for decomposition in decompositions.into_iter() {
+        if cs.columns.by_handle(&decomposition.bit(0)).is_ok() {
+            continue;
+        }
+
+        for k in 0..decomposition.width {
+            let bit_id = cs.columns.insert_column_and_register(
+                Column::builder()
+                    .handle(decomposition.bit(k))
+                    .kind(Kind::Computed)
+                    .t(Magma::binary())
+                    .build(),
+            )?;
+            cs.computations.insert(
+                &bit_id,
+                Computation::Composite {
+                    target: bit_id.clone(),
+                    exp: Intrinsic::Bits.call(&[
+                        decomposition.x.clone(),
+                        Node::from_isize(decomposition.width as isize),
+                        Node::from_isize(k as isize),
+                    ])?,
+                },
+            )?;
+
+            cs.insert_constraint(Constraint::Vanishes {
+                handle: Handle::new(
+                    &decomposition.handle.module,
+                    format!("{}-is-binary", decomposition.bit(k).name),
+                ),
+                domain: None,
+                expr: Box::new(Intrinsic::Mul.call(&[
+                    decomposition.bit_node(k),
+                    Intrinsic::Sub.call(&[Node::from_isize(1), decomposition.bit_node(k)])?,
+                ])?),
+            });
+        }
+
+        cs.insert_constraint(Constraint::Vanishes {
+            handle: Handle::new(
+                &decomposition.handle.module,
+                format!("{}-decomposition", decomposition.handle.name),
+            ),
+            domain: None,
+            expr: Box::new(
+                Intrinsic::Sub.call(&[
+                    decomposition.x.clone(),
+                    Intrinsic::Add.call(
+                        &(0..decomposition.width)
+                            .map(|k| {
+                                Intrinsic::Mul.call(&[
+                                    Node::from_bigint(BigInt::one() << k),
+                                    decomposition.bit_node(k),
+                                ])
+                            })
+                            .collect::<Result<Vec<_>>>()?,
+                    )?,
+                ])?,
+            ),
+        });
+    }
+
+    Ok(())
+}
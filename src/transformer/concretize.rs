@@ -27,7 +27,11 @@ impl Node {
 
 impl ConstraintSet {
     fn make_constraints_native(&mut self) {
-        for c in self.constraints.iter_mut() {
+        for c in self
+            .constraints
+            .iter_mut()
+            .chain(self.assertions.iter_mut())
+        {
             match c {
                 Constraint::Vanishes { expr, .. } => expr.concretize(),
                 Constraint::Lookup { .. } => {}
@@ -43,9 +47,8 @@ impl ConstraintSet {
 
     fn make_computations_native(&mut self) {
         for c in self.computations.iter_mut() {
-            match c {
-                Computation::Composite { exp, .. } => exp.concretize(),
-                _ => {}
+            if let Computation::Composite { exp, .. } = c {
+                exp.concretize()
             }
         }
     }
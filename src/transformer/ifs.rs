@@ -1,10 +1,85 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use log::*;
 use num_traits::Zero;
 
-use crate::compiler::{Conditioning, Constraint, ConstraintSet, Expression, Intrinsic, Node};
+use crate::compiler::{Conditioning, Constraint, ConstraintSet, Expression, Intrinsic, Node, Type};
+use crate::pretty::Pretty;
 
 use super::{flatten_list, wrap};
 
+/// Ensure that `cond` may be used as the condition of the `func`
+/// conditional.
+fn validate_condition(func: &Intrinsic, cond: &Node) -> Result<()> {
+    if !(if matches!(func, Intrinsic::IfNotZero) {
+        matches!(cond.t().c(), Conditioning::Boolean | Conditioning::None)
+    } else {
+        matches!(cond.t().c(), Conditioning::Loobean | Conditioning::None)
+    }) {
+        bail!(
+            "condition {} of {:?} can not be of type {}",
+            cond.pretty(),
+            func,
+            cond.t()
+        )
+    }
+    Ok(())
+}
+
+/// Lower a conditional having both a then and an else branch into the
+/// value it takes, weighting each branch by its selector:
+///
+/// ```
+/// (if-not-zero A B C)
+/// ```
+///
+/// Becomes
+///
+/// ```
+/// NORM(A) * B + (1 - NORM(A)) * C
+/// ```
+///
+/// where `NORM(A)` is simplified to `A` if `A` is binary. Return `None` if
+/// `e` is not such a conditional, or if one of its branches is a list.
+fn factor_if(e: &Node) -> Option<Node> {
+    let Expression::Funcall {
+        func: func @ (Intrinsic::IfZero | Intrinsic::IfNotZero),
+        args,
+    } = e.e()
+    else {
+        return None;
+    };
+    if args.len() != 3
+        || args[1..].iter().any(|a| matches!(a.t(), Type::List(_)))
+        || validate_condition(func, &args[0]).is_err()
+    {
+        return None;
+    }
+
+    let cond = &args[0];
+    let cond_not_zero = if cond.t().is_binary() {
+        cond.clone()
+    } else {
+        Intrinsic::Normalize.call(std::slice::from_ref(cond)).ok()?
+    };
+    let cond_zero = Intrinsic::Sub
+        .call(&[Node::one(), cond_not_zero.clone()])
+        .ok()?;
+    let (then_sel, else_sel) = if matches!(func, Intrinsic::IfNotZero) {
+        (cond_not_zero, cond_zero)
+    } else {
+        (cond_zero, cond_not_zero)
+    };
+    Some(
+        Intrinsic::Add
+            .call(&[
+                Intrinsic::Mul.call(&[then_sel, args[1].clone()]).ok()?,
+                Intrinsic::Mul.call(&[else_sel, args[2].clone()]).ok()?,
+            ])
+            .ok()?
+            .with_type(e.t()),
+    )
+}
+
 /// Expand if conditions, assuming they are roughly in "top-most"
 /// positions.  That is, we can have arbitrary nested if `List` and
 /// `IfZero` / `IfNotZero` but nothing else.  The simplest example is
@@ -36,11 +111,7 @@ fn do_expand_ifs(e: &mut Node) -> Result<()> {
             if matches!(func, Intrinsic::IfZero | Intrinsic::IfNotZero) {
                 let cond = args[0].clone();
                 let if_not_zero = matches!(func, Intrinsic::IfNotZero);
-                assert!(if if_not_zero {
-                    matches!(cond.t().c(), Conditioning::Boolean | Conditioning::None)
-                } else {
-                    matches!(cond.t().c(), Conditioning::Loobean | Conditioning::None)
-                });
+                validate_condition(func, &cond)?;
 
                 // If the condition reduces to a constant, we can determine the result
                 if let Ok(constant_cond) = cond.pure_eval() {
@@ -60,8 +131,10 @@ fn do_expand_ifs(e: &mut Node) -> Result<()> {
                 } else {
                     let conds = {
                         let cond_not_zero = cond.clone();
-                        let cond_zero = Intrinsic::Sub
-                            .call(&[Node::one(), Intrinsic::Normalize.call(&[cond.clone()])?])?;
+                        let cond_zero = Intrinsic::Sub.call(&[
+                            Node::one(),
+                            Intrinsic::Normalize.call(std::slice::from_ref(&cond))?,
+                        ])?;
                         if if_not_zero {
                             [cond_not_zero, cond_zero]
                         } else {
@@ -109,6 +182,16 @@ fn do_expand_ifs(e: &mut Node) -> Result<()> {
     Ok(())
 }
 
+fn is_if(e: &Node) -> bool {
+    matches!(
+        e.e(),
+        Expression::Funcall {
+            func: Intrinsic::IfZero | Intrinsic::IfNotZero,
+            ..
+        }
+    )
+}
+
 /// Pull `if` conditionals out of nested positions and into top-most
 /// positions.  Specifically, something like this:
 ///
@@ -127,13 +210,15 @@ fn do_expand_ifs(e: &mut Node) -> Result<()> {
 ///
 /// **NOTE:** the algorithm implemented here is not particular
 /// efficient, and can result in unnecessary cloning of expressions.
+/// Conditionals sharing the same arguments list with other ones are
+/// factored in place, see [`factor_if`], rather than raised.
 fn raise_ifs(mut e: Node) -> Node {
     match e.e_mut() {
         Expression::Funcall { func, ref mut args } => {
             *args = args.iter_mut().map(|a| raise_ifs(a.clone())).collect();
             // This is a sanity check, though I'm not sure how it can
             // arise.
-            assert!(args.iter().fold(true, |b,e| b&&!matches!(e.e(), Expression::Void)));
+            assert!(args.iter().all(|e| !matches!(e.e(), Expression::Void)));
             //
             match func {
                 Intrinsic::Add
@@ -141,7 +226,18 @@ fn raise_ifs(mut e: Node) -> Node {
                     | Intrinsic::Mul
                     | Intrinsic::VectorAdd
                     | Intrinsic::VectorSub
-                    | Intrinsic::VectorMul => {    
+                    | Intrinsic::VectorMul => {
+                        // Raising a conditional duplicates all the other
+                        // arguments, which compounds exponentially if some
+                        // of them are conditionals too; those that can be
+                        // are then rather factored in place.
+                        if args.iter().filter(|a| is_if(a)).count() > 1 {
+                            for a in args.iter_mut() {
+                                if let Some(factored) = factor_if(a) {
+                                    *a = factored;
+                                }
+                            }
+                        }
                         for (i, a) in args.iter().enumerate() {
                             if let Expression::Funcall {
                                 func: func_if @ (Intrinsic::IfZero | Intrinsic::IfNotZero),
@@ -176,6 +272,8 @@ fn raise_ifs(mut e: Node) -> Node {
                     | Intrinsic::Neg
                     | Intrinsic::Inv
                     | Intrinsic::Normalize
+                    | Intrinsic::Sgn
+                    | Intrinsic::Bits
                     | Intrinsic::Exp
                     | Intrinsic::Begin => e,
             }
@@ -209,15 +307,22 @@ fn raise_ifs(mut e: Node) -> Node {
 /// **NOTE:** When the `if` condition is a constant expression, then
 /// it is evaluated at compile time and the entire `if` expression is
 /// eliminated.
-pub fn expand_ifs(cs: &mut ConstraintSet) {
+pub fn expand_ifs(cs: &mut ConstraintSet) -> Result<()> {
     for c in cs.constraints.iter_mut() {
-        if let Constraint::Vanishes { expr, .. } = c {
-            *expr = Box::new(raise_ifs(*expr.clone()));
-        }
-    }
-    for c in cs.constraints.iter_mut() {
-        if let Constraint::Vanishes { expr: e, .. } = c {
-            do_expand_ifs(e).unwrap();
+        if let Constraint::Vanishes { handle, expr, .. } = c {
+            let before = expr.size();
+            **expr = raise_ifs(*expr.clone());
+            do_expand_ifs(expr)?;
+            let after = expr.size();
+            if after != before {
+                debug!(
+                    "{}: expression size {} -> {}",
+                    handle.pretty(),
+                    before,
+                    after
+                );
+            }
         }
     }
+    Ok(())
 }
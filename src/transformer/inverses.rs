@@ -15,7 +15,6 @@ fn invert_expr(e: &Node) -> Node {
 
 /// For all Builtin::Inv encountered, create a new column and the associated constraints
 /// pre-computing and proving the inverted column.
-
 impl Node {
     pub(crate) fn do_normalize(
         &mut self,
@@ -23,7 +22,7 @@ impl Node {
         new_cols: &mut Vec<(Handle, Node)>,
     ) {
         if let Result::Ok(x) = self.pure_eval() {
-            *self = Node::from_value(crate::column::Value::try_from(x).unwrap().inverse());
+            *self = Node::from_bigint(x);
         } else {
             match self.e_mut() {
                 Expression::List(es) => {
@@ -1,9 +1,11 @@
 use anyhow::{bail, Result};
+use num_bigint::BigInt;
+use num_traits::One;
 use owo_colors::OwoColorize;
 use std::collections::HashMap;
 
 use crate::{
-    column::{Column, Computation},
+    column::{Column, Computation, Value},
     compiler::{
         ColumnRef, Constraint, ConstraintSet, Domain, Intrinsic, Kind, Magma, Node, RawMagma,
     },
@@ -84,6 +86,8 @@ fn process_nhood(
             froms: vec![_intrld_aux_xs_id.clone()],
             tos: vec![srt_intrld_aux_xs_id.clone()],
             signs: vec![true],
+            stable: true,
+            tiebreaks: vec![],
         },
     )?;
     cs.insert_constraint(Constraint::Permutation {
@@ -143,8 +147,33 @@ fn process_nhood(
     Ok(())
 }
 
+/// Signed integers are proven to lie in [-2^(n-1), 2^(n-1)) by ensuring that
+/// once shifted by 2^(n-1), they lie in [0, 2^n).
+fn process_signed(column_refs: &[(ColumnRef, usize)], cs: &mut ConstraintSet) -> Result<()> {
+    for (column_ref, bit_size) in column_refs {
+        let handle = cs.handle(column_ref);
+        let half = BigInt::one() << (bit_size - 1);
+        cs.insert_constraint(Constraint::InRange {
+            handle: Handle::new(
+                handle.module.clone(),
+                format!("{}-signed-range", handle.name),
+            ),
+            exp: Intrinsic::Add.call(&[
+                Node::column()
+                    .handle(handle.clone())
+                    .t(Magma::signed(*bit_size))
+                    .build(),
+                Node::from_bigint(half.clone()),
+            ])?,
+            max: Value::try_from(half << 1)?,
+        });
+    }
+    Ok(())
+}
+
 pub fn validate_nhood(cs: &mut ConstraintSet) -> Result<()> {
     let mut binary_columns = Vec::new();
+    let mut signed_columns = Vec::new();
     let mut constrained_columns = HashMap::<String, HashMap<u32, Vec<ColumnRef>>>::new();
 
     for (h, c) in cs.columns.iter() {
@@ -152,6 +181,7 @@ pub fn validate_nhood(cs: &mut ConstraintSet) -> Result<()> {
         if c.kind == Kind::Commitment && c.must_prove {
             match c.t.rm() {
                 RawMagma::Binary => binary_columns.push(h.clone()),
+                RawMagma::Signed(bit_size) => signed_columns.push((h.clone(), bit_size)),
                 _ => constrained_columns
                     .entry(c.handle.module.to_owned())
                     .or_default()
@@ -165,6 +195,7 @@ pub fn validate_nhood(cs: &mut ConstraintSet) -> Result<()> {
     // Binary columns are a special case. As they only generate a single
     // constraint and *do not create new columns* in their module.
     process_binarity(&binary_columns, cs);
+    process_signed(&signed_columns, cs)?;
 
     for (module, columns) in constrained_columns.iter() {
         for (&bit_size, handles) in columns.iter() {
@@ -0,0 +1,131 @@
+use anyhow::*;
+use num_bigint::BigInt;
+use num_traits::One;
+use std::collections::HashSet;
+
+use crate::{
+    column::{Column, Computation, Value},
+    compiler::{ColumnRef, Constraint, ConstraintSet, Expression, Intrinsic, Kind, Magma, Node},
+    pretty::{Base, Pretty},
+    structs::Handle,
+};
+
+use super::expression_to_name;
+
+/// The largest absolute value that `x` may take, if it is known
+fn magnitude_bound(x: &Node) -> Result<BigInt> {
+    use crate::compiler::RawMagma;
+    match x.t().rm() {
+        RawMagma::Signed(n) => Ok(BigInt::one() << (n - 1)),
+        RawMagma::Binary | RawMagma::Nibble | RawMagma::Byte | RawMagma::Integer(_) => {
+            Ok((BigInt::one() << x.t().m().bit_size()) - 1)
+        }
+        _ => bail!(
+            "unable to prove the sign of {}, as it is not a bounded integer",
+            x.pretty()
+        ),
+    }
+}
+
+impl Node {
+    /// Replace all the `(sgn x)` in this expression with computed columns,
+    /// whose handles and arguments are pushed in `new_cols`.
+    fn do_expand_signs(
+        &mut self,
+        get_module: &dyn Fn(&HashSet<ColumnRef>) -> String,
+        new_cols: &mut Vec<(Handle, Node)>,
+    ) {
+        match self.e_mut() {
+            Expression::List(es) => {
+                for e in es.iter_mut() {
+                    e.do_expand_signs(get_module, new_cols);
+                }
+            }
+            Expression::Funcall { func, args } => {
+                for e in args.iter_mut() {
+                    e.do_expand_signs(get_module, new_cols);
+                }
+                if matches!(func, Intrinsic::Sgn) {
+                    let arg = args[0].clone();
+                    if let Result::Ok(x) = self.pure_eval() {
+                        *self = Node::from_bigint(x);
+                    } else {
+                        let module = get_module(&arg.dependencies());
+                        let sign_handle = Handle::new(module, expression_to_name(&arg, "SGN"));
+                        new_cols.push((sign_handle.clone(), arg));
+                        *self = Node::column()
+                            .handle(sign_handle)
+                            .kind(Kind::Computed)
+                            .t(Magma::signed(2))
+                            .base(Base::Signed)
+                            .build();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `(sgn x)` is not a polynomial; so it is replaced by a computed column S,
+/// that is proven to be the sign of x by:
+///   - S² = (~ x), i.e. S is null iff x is null, and is ±1 otherwise;
+///   - S·x ∈ [0; M], M being the largest absolute value x may take.
+pub fn expand_signs(cs: &mut ConstraintSet) -> Result<()> {
+    let mut new_cols = vec![];
+    for c in cs.constraints.iter_mut() {
+        if let Constraint::Vanishes { handle, expr, .. } = c {
+            let module = handle.module.clone();
+            let columns = &cs.columns;
+            let get_module = |rs: &HashSet<ColumnRef>| {
+                columns
+                    .module_for(rs.iter())
+                    .unwrap_or_else(|| module.clone())
+            };
+            expr.do_expand_signs(&get_module, &mut new_cols);
+        }
+    }
+
+    for (sign_handle, x) in new_cols.into_iter() {
+        if cs.columns.by_handle(&sign_handle).is_ok() {
+            continue;
+        }
+        let max = magnitude_bound(&x)?;
+        let sign_id = cs.columns.insert_column_and_register(
+            Column::builder()
+                .handle(sign_handle.clone())
+                .kind(Kind::Computed)
+                .t(Magma::signed(2))
+                .base(Base::Signed)
+                .build(),
+        )?;
+        cs.computations.insert(
+            &sign_id,
+            Computation::Composite {
+                target: sign_id.clone(),
+                exp: Intrinsic::Sgn.call(std::slice::from_ref(&x))?,
+            },
+        )?;
+
+        let sign = Node::column()
+            .handle(sign_handle.clone())
+            .kind(Kind::Computed)
+            .t(Magma::signed(2))
+            .build();
+        cs.insert_constraint(Constraint::Vanishes {
+            handle: Handle::new(&sign_handle.module, format!("{}-values", sign_handle.name)),
+            domain: None,
+            expr: Box::new(Intrinsic::Sub.call(&[
+                Intrinsic::Mul.call(&[sign.clone(), sign.clone()])?,
+                Intrinsic::Normalize.call(std::slice::from_ref(&x))?,
+            ])?),
+        });
+        cs.insert_constraint(Constraint::InRange {
+            handle: Handle::new(&sign_handle.module, format!("{}-range", sign_handle.name)),
+            exp: Intrinsic::Mul.call(&[sign, x])?,
+            max: Value::try_from(max + 1)?,
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,266 @@
+use log::*;
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::{
+    column::Value,
+    compiler::{Constraint, ConstraintSet, Expression, Intrinsic, Node},
+    constants,
+    pretty::Pretty,
+};
+
+/// The value of `n` if it is a constant
+fn constant(n: &Node) -> Option<BigInt> {
+    if let Expression::Const(v) = n.e() {
+        Some(v.into())
+    } else {
+        None
+    }
+}
+
+/// Build the node holding the constant `x`; fail if `x` does not safely fit
+/// in a field element, so that the folding would not have to account for
+/// the modular reduction
+fn folded(x: BigInt) -> Option<Node> {
+    if x.bits() as usize >= constants::FIELD_BITSIZE {
+        return None;
+    }
+    let abs = Node::from_value(Value::try_from(x.abs()).ok()?);
+    if x.is_negative() {
+        Intrinsic::Neg.call(&[abs]).ok()
+    } else {
+        Some(abs)
+    }
+}
+
+/// Replace `n` by `by`, keeping the conditioning of `n` so that the
+/// constraints keep their semantic
+fn replace(n: &mut Node, by: Node) {
+    let t = n.t();
+    *n = if by.t().c() != t.c() {
+        let by_t = by.t().force_with_conditioning_of(&t);
+        by.with_type(by_t)
+    } else {
+        by
+    };
+}
+
+/// Extract the factors common to all the terms of a sum of products, e.g.
+/// `(+ (* a x) (* a y))` becomes `(* a (+ x y))`
+fn factorize(func: Intrinsic, args: &[Node]) -> Option<Node> {
+    // the factors of every term, along with their representation
+    let mut terms = args
+        .iter()
+        .map(|a| match a.e() {
+            Expression::Funcall {
+                func: Intrinsic::Mul,
+                args,
+            } => Some(
+                args.iter()
+                    .map(|f| (f.to_string(), f.clone()))
+                    .collect::<Vec<_>>(),
+            ),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if terms.len() < 2 {
+        return None;
+    }
+
+    let mut common = Vec::new();
+    while let Some((repr, factor)) = terms[0]
+        .iter()
+        .find(|(repr, _)| terms[1..].iter().all(|t| t.iter().any(|(r, _)| r == repr)))
+        .cloned()
+    {
+        for term in terms.iter_mut() {
+            let i = term.iter().position(|(r, _)| *r == repr).unwrap();
+            term.remove(i);
+        }
+        common.push(factor);
+    }
+    if common.is_empty() {
+        return None;
+    }
+
+    let rests = terms
+        .into_iter()
+        .map(|term| {
+            let mut factors = term.into_iter().map(|(_, f)| f).collect::<Vec<_>>();
+            match factors.len() {
+                0 => Some(Node::one()),
+                1 => factors.pop(),
+                _ => Intrinsic::Mul.call(&factors).ok(),
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+    common.push(simplified_call(func, rests)?);
+    simplified_call(Intrinsic::Mul, common)
+}
+
+/// Call `func` on the already simplified `args`, and simplify the result
+fn simplified_call(func: Intrinsic, args: Vec<Node>) -> Option<Node> {
+    simplify_funcall(func, &args).or_else(|| func.call(&args).ok())
+}
+
+/// Fold the constant sub-expressions of `n` and eliminate its neutral
+/// operands, bottom-up
+fn simplify_node(n: &mut Node) {
+    let replacement = match n.e_mut() {
+        Expression::List(xs) => {
+            xs.iter_mut().for_each(simplify_node);
+            None
+        }
+        Expression::Funcall { func, args } => {
+            args.iter_mut().for_each(simplify_node);
+            simplify_funcall(*func, args)
+        }
+        _ => None,
+    };
+    if let Some(r) = replacement {
+        replace(n, r);
+    }
+}
+
+/// The simplified form of the call of `func` on the already simplified
+/// `args`, if it can be simplified
+fn simplify_funcall(func: Intrinsic, args: &[Node]) -> Option<Node> {
+    let consts = args.iter().map(constant).collect::<Vec<_>>();
+    let n_consts = consts.iter().flatten().count();
+    let mut rest = args
+        .iter()
+        .filter(|a| !a.is_constant())
+        .cloned()
+        .collect::<Vec<_>>();
+    match func {
+        Intrinsic::Add | Intrinsic::VectorAdd => {
+            let sum = consts.iter().flatten().sum::<BigInt>();
+            if rest.is_empty() {
+                return folded(sum);
+            }
+            let changed = n_consts > 1 || (n_consts == 1 && sum.is_zero());
+            if !sum.is_zero() {
+                rest.push(folded(sum)?);
+            }
+            if rest.len() == 1 {
+                return rest.pop();
+            }
+            factorize(func, &rest).or_else(|| changed.then(|| func.call(&rest).ok()).flatten())
+        }
+        Intrinsic::Sub | Intrinsic::VectorSub => {
+            if rest.is_empty() {
+                let mut consts = consts.into_iter().flatten();
+                let first = consts.next()?;
+                return folded(consts.fold(first, |ax, x| ax - x));
+            }
+            let rest = std::iter::once(&args[0])
+                .chain(
+                    args.iter()
+                        .skip(1)
+                        .filter(|a| !constant(a).is_some_and(|x| x.is_zero())),
+                )
+                .cloned()
+                .collect::<Vec<_>>();
+            if rest.len() == 1 {
+                return rest.into_iter().next();
+            }
+            let changed = rest.len() < args.len();
+            factorize(func, &rest).or_else(|| changed.then(|| func.call(&rest).ok()).flatten())
+        }
+        Intrinsic::Mul | Intrinsic::VectorMul => {
+            if consts.iter().flatten().any(Zero::is_zero) {
+                return Some(Node::zero());
+            }
+            let product = consts.iter().flatten().product::<BigInt>();
+            if rest.is_empty() {
+                return folded(product);
+            }
+            if !(n_consts > 1 || (n_consts == 1 && product.is_one())) {
+                return None;
+            }
+            if !product.is_one() {
+                rest.insert(0, folded(product)?);
+            }
+            if rest.len() == 1 {
+                rest.pop()
+            } else {
+                func.call(&rest).ok()
+            }
+        }
+        Intrinsic::Exp => {
+            let exponent = consts[1].as_ref()?;
+            if exponent.is_zero() {
+                Some(Node::one())
+            } else if exponent.is_one() {
+                Some(args[0].clone())
+            } else {
+                let x = consts[0].as_ref()?;
+                let exponent = exponent.to_u32()?;
+                // do not compute powers that could not be folded anyway
+                if x.bits() * exponent as u64 > constants::FIELD_BITSIZE as u64 {
+                    return None;
+                }
+                folded(x.pow(exponent))
+            }
+        }
+        Intrinsic::Neg => match args[0].e() {
+            Expression::Funcall {
+                func: Intrinsic::Neg,
+                args,
+            } => Some(args[0].clone()),
+            Expression::Const(_) if consts[0].as_ref().is_some_and(Zero::is_zero) => {
+                Some(Node::zero())
+            }
+            _ => None,
+        },
+        Intrinsic::Normalize => consts[0].as_ref().map(|x| {
+            if x.is_zero() {
+                Node::zero()
+            } else {
+                Node::one()
+            }
+        }),
+        Intrinsic::IfZero | Intrinsic::IfNotZero => {
+            let cond = consts[0].as_ref()?;
+            let taken = if cond.is_zero() == matches!(func, Intrinsic::IfZero) {
+                1
+            } else {
+                2
+            };
+            args.get(taken).cloned()
+        }
+        Intrinsic::Inv | Intrinsic::Sgn | Intrinsic::Bits | Intrinsic::Begin => None,
+    }
+}
+
+/// Fold the constant sub-expressions and eliminate the neutral operands,
+/// e.g. `x*1`, `x+0` or `x^1`, of the constraint expressions, and extract
+/// the factors common to the terms of their sums of products.
+pub fn simplify(cs: &mut ConstraintSet) {
+    for c in cs.constraints.iter_mut() {
+        let handle = c.handle().to_owned();
+        let exprs: Vec<&mut Node> = match c {
+            Constraint::Vanishes { expr, .. } => vec![expr.as_mut()],
+            Constraint::Lookup {
+                including,
+                included,
+                ..
+            } => including.iter_mut().chain(included.iter_mut()).collect(),
+            Constraint::InRange { exp, .. } => vec![exp],
+            Constraint::Permutation { .. } | Constraint::Normalization { .. } => vec![],
+        };
+        for e in exprs {
+            let before = e.size();
+            simplify_node(e);
+            let after = e.size();
+            if after != before {
+                debug!(
+                    "{}: expression size {} -> {}",
+                    handle.pretty(),
+                    before,
+                    after
+                );
+            }
+        }
+    }
+}
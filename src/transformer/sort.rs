@@ -329,7 +329,10 @@ pub fn sorts(cs: &mut ConstraintSet) -> Result<()> {
         .collect::<Vec<_>>()
         .into_iter()
     {
-        if let Computation::Sorted { froms, tos, signs } = c {
+        if let Computation::Sorted {
+            froms, tos, signs, ..
+        } = c
+        {
             create_sort_constraint(cs, &froms, &tos, &signs)
                 .with_context(|| anyhow!("while creating sort constraints"))?;
         }
@@ -85,6 +85,7 @@ impl Node {
         *self = Node::column().handle(target).kind(Kind::Computed).build();
     }
 
+    #[allow(clippy::type_complexity)]
     fn do_splatter(
         &mut self,
         module: &str,
@@ -112,7 +113,11 @@ impl Node {
                             }
 
                             let op = (*func).into();
-                            let new_magma = args.iter().map(|a| a.t().m()).max().unwrap();
+                            let new_magma = args
+                                .iter()
+                                .map(|a| a.t().m())
+                                .reduce(|ax, m| ax.joined(&m))
+                                .unwrap();
                             ancillaries.update_width(op, new_magma.bit_size());
                             let new_handle =
                                 Handle::new(module, format!("{}{}{}", args[0], op, args[1]));
@@ -153,6 +158,7 @@ impl Node {
 }
 
 impl ConstraintSet {
+    #[allow(dead_code)]
     fn make_ancillaries(&mut self, ancillaries: ProtoAncillaries) {
         // TODO: should be inline lisp
         self.columns
@@ -249,7 +255,7 @@ impl ConstraintSet {
         for i in 0..self.constraints.len() {
             if let Constraint::Vanishes { expr: e, .. } = self.constraints.get_mut(i).unwrap() {
                 e.dyadize();
-                if let Some(module) = self.columns.module_for(&e.dependencies()).as_ref() {
+                if let Some(module) = self.columns.module_for(e.dependencies()).as_ref() {
                     e.do_splatter(
                         module,
                         &mut ancillaries,
@@ -263,8 +269,11 @@ impl ConstraintSet {
         // self.make_ancillaries(ancillaries);
 
         for (new_column, new_computation) in new_constants {
-            let id = self.columns.insert_column_and_register(new_column).unwrap();
-            self.computations.insert(&id, new_computation).unwrap();
+            // the constraints and the assertions are splattered separately
+            if self.columns.by_handle(&new_column.handle).is_err() {
+                let id = self.columns.insert_column_and_register(new_column).unwrap();
+                self.computations.insert(&id, new_computation).unwrap();
+            }
         }
 
         for (func, (new_handle, new_magma), args) in new_exo_columns.into_iter() {
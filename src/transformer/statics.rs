@@ -21,7 +21,7 @@ fn do_precompute(e: &mut Node) {
 }
 
 pub fn precompute(cs: &mut ConstraintSet) {
-    for c in cs.constraints.iter_mut() {
+    for c in cs.constraints.iter_mut().chain(cs.assertions.iter_mut()) {
         if let Constraint::Vanishes { expr: e, .. } = c {
             do_precompute(e);
         }
@@ -1,17 +1,16 @@
 use anyhow::*;
 use itertools::Itertools;
+use num_bigint::BigInt;
 #[cfg(feature = "postgres")]
 use postgres::Client;
-#[cfg(feature = "postgres")]
-use std::io::Read;
 
-use crate::{column::Value, compiler::Magma, pretty::Pretty, structs::Handle};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-pub fn is_file_empty(f: &str) -> Result<bool> {
-    std::fs::metadata(f)
-        .with_context(|| anyhow!("unable to read metadata of `{}`", f))
-        .map(|f| f.len() == 0)
-}
+use crate::{
+    column::Value, compiler::Magma, errors::RuntimeError, pretty::Pretty, structs::Handle,
+};
 
 #[cfg(feature = "postgres")]
 pub fn connect_to_db(
@@ -36,6 +35,182 @@ pub fn connect_to_db(
     .with_context(|| format!("while connecting to {}@{}/{}", user, host, database))
 }
 
+/// Create, if needed, the `constraint_stats` table accumulating how many times
+/// each constraint has been checked and has failed across the blocks, and the
+/// `module_stats` view aggregating it per module
+#[cfg(feature = "postgres")]
+pub fn create_constraint_stats(db: &mut Client) -> Result<()> {
+    db.batch_execute(
+        "CREATE TABLE IF NOT EXISTS constraint_stats (
+             name TEXT PRIMARY KEY,
+             module TEXT NOT NULL,
+             checks BIGINT NOT NULL DEFAULT 0,
+             failures BIGINT NOT NULL DEFAULT 0,
+             last_failure TIMESTAMPTZ
+         );
+         CREATE OR REPLACE VIEW module_stats AS
+             SELECT module, SUM(checks) AS checks, SUM(failures) AS failures,
+                    SUM(failures)::FLOAT / NULLIF(SUM(checks), 0) AS failure_rate
+             FROM constraint_stats GROUP BY module;",
+    )
+    .with_context(|| "while creating the constraint statistics table")
+}
+
+/// The name, module, and failure count of the constraints checked in
+/// `progress`
+#[cfg(feature = "postgres")]
+pub fn constraint_stats(
+    cs: &crate::compiler::ConstraintSet,
+    progress: &crate::check::Checkpoint,
+) -> (Vec<String>, Vec<String>, Vec<i64>) {
+    let modules = cs
+        .constraints
+        .iter()
+        .map(|c| (c.name(), c.handle().module.clone()))
+        .collect::<std::collections::HashMap<_, _>>();
+    let (mut names, mut owners, mut failures) = (Vec::new(), Vec::new(), Vec::new());
+    for (name, failed) in progress
+        .done
+        .iter()
+        .map(|n| (n, 0i64))
+        .chain(progress.failed.iter().map(|n| (n, 1i64)))
+    {
+        names.push(name.to_owned());
+        owners.push(modules.get(name).cloned().unwrap_or_default());
+        failures.push(failed);
+    }
+    (names, owners, failures)
+}
+
+/// Add the constraints checked in `progress` to the `constraint_stats` table
+#[cfg(feature = "postgres")]
+pub fn record_constraint_stats(
+    db: &mut impl postgres::GenericClient,
+    cs: &crate::compiler::ConstraintSet,
+    progress: &crate::check::Checkpoint,
+) -> Result<()> {
+    let (names, owners, failures) = constraint_stats(cs, progress);
+
+    db.execute(
+        "INSERT INTO constraint_stats (name, module, checks, failures, last_failure)
+         SELECT name, module, 1, failed, CASE WHEN failed > 0 THEN now() END
+         FROM UNNEST($1::TEXT[], $2::TEXT[], $3::BIGINT[]) AS s(name, module, failed)
+         ON CONFLICT (name) DO UPDATE SET
+             module = EXCLUDED.module,
+             checks = constraint_stats.checks + 1,
+             failures = constraint_stats.failures + EXCLUDED.failures,
+             last_failure = COALESCE(EXCLUDED.last_failure, constraint_stats.last_failure)",
+        &[&names, &owners, &failures],
+    )
+    .with_context(|| "while updating the constraint statistics")?;
+    Ok(())
+}
+
+/// Periodically log the number of blocks left to process, how fast they are
+/// processed, and when the backlog should be drained at that pace
+#[cfg(feature = "postgres")]
+pub struct BacklogReporter {
+    every: Duration,
+    last_report: Instant,
+    /// the blocks processed since the last report
+    processed: usize,
+}
+#[cfg(feature = "postgres")]
+impl BacklogReporter {
+    pub fn new(every: Duration) -> Self {
+        BacklogReporter {
+            every,
+            last_report: Instant::now(),
+            processed: 0,
+        }
+    }
+
+    /// Account for a processed block
+    pub fn processed(&mut self) {
+        self.processed += 1;
+    }
+
+    /// If a report is due, count the blocks of status `status` left in `db`
+    /// and log them along the processing rate and the ETA
+    pub fn maybe_report(
+        &mut self,
+        db: &mut impl postgres::GenericClient,
+        status: &str,
+    ) -> Result<()> {
+        let elapsed = self.last_report.elapsed();
+        if elapsed < self.every {
+            return Ok(());
+        }
+
+        let backlog: i64 = db
+            .query_one("SELECT COUNT(*) FROM blocks WHERE status=$1", &[&status])
+            .with_context(|| "while counting the blocks to process")?
+            .get(0);
+        log::info!("{}", self.report(backlog, elapsed));
+        self.last_report = Instant::now();
+        Ok(())
+    }
+
+    /// Describe the `backlog` along the processing rate over the `elapsed`
+    /// reporting period and the ETA, and start a new period
+    pub(crate) fn report(&mut self, backlog: i64, elapsed: Duration) -> String {
+        let rate = self.processed as f64 / elapsed.as_secs_f64();
+        self.processed = 0;
+        format!(
+            "{} blocks left; {:.2} blocks/min; ETA: {}",
+            backlog,
+            60. * rate,
+            if backlog == 0 {
+                "now".to_string()
+            } else if rate > 0. {
+                format_duration(Duration::from_secs_f64(backlog as f64 / rate))
+            } else {
+                "unknown".to_string()
+            }
+        )
+    }
+}
+
+/// Render `d` as hours, minutes and seconds, e.g. `2h05m10s`
+#[cfg(feature = "postgres")]
+fn format_duration(d: Duration) -> String {
+    let s = d.as_secs();
+    match (s / 3600, (s % 3600) / 60, s % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m{:02}s", m, s),
+        (h, m, s) => format!("{}h{:02}m{:02}s", h, m, s),
+    }
+}
+
+/// The outcome of the processing of a block by a loop worker, printed as a
+/// JSON line on stdout for the orchestrators
+#[cfg(feature = "postgres")]
+#[derive(serde::Serialize)]
+pub struct BlockEvent<'a> {
+    pub block: &'a str,
+    /// one of `done`, `failed` or `timeout`
+    pub result: &'static str,
+    /// the processing time, in milliseconds
+    pub duration_ms: u128,
+    /// how many constraints failed on this block
+    pub failures: usize,
+    /// the size of the block payload, in bytes
+    pub payload_size: usize,
+}
+#[cfg(feature = "postgres")]
+impl BlockEvent<'_> {
+    pub fn emit(&self) -> Result<()> {
+        self.emit_to(std::io::stdout().lock())
+    }
+
+    /// Write the event as a single JSON line to `out`
+    pub(crate) fn emit_to<W: std::io::Write>(&self, mut out: W) -> Result<()> {
+        writeln!(out, "{}", serde_json::to_string(self)?)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
 pub fn maybe_warn(t: Magma, xs: &[Value], h: &Handle) -> Result<()> {
     if !t.is_binary()
         && xs.iter().all(|x| x.is_zero() || x.is_one())
@@ -52,6 +227,33 @@ pub fn maybe_warn(t: Magma, xs: &[Value], h: &Handle) -> Result<()> {
 
 /// Remove all symbols in a symbol which are invalid in Go identifiers
 pub fn purify(s: &str) -> String {
+    replace_symbols(s).replace(|c: char| !c.is_ascii(), "_")
+}
+
+/// Remove all symbols in a symbol which are invalid in Go identifiers; the
+/// ASCII characters without a conventional replacement are replaced by their
+/// code point, e.g. `!` by `_21_`, and a leading digit is prefixed with `_`.
+pub fn purify_escaped(s: &str) -> String {
+    let r = replace_symbols(s)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c.to_string()
+            } else if !c.is_ascii() {
+                "_".to_owned()
+            } else {
+                format!("_{:x}_", c as u32)
+            }
+        })
+        .collect::<String>();
+    if r.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", r)
+    } else {
+        r
+    }
+}
+
+fn replace_symbols(s: &str) -> String {
     s.replace(
         [
             '(', ')', '{', '}', '[', ']', '<', '>', ':', '%', '.', '-', '#', ' ', '/',
@@ -85,7 +287,6 @@ pub fn purify(s: &str) -> String {
     .replace('₇', "7")
     .replace('₈', "8")
     .replace('₉', "9")
-    .replace(|c: char| !c.is_ascii(), "_")
 }
 
 pub fn hash_strings<S: ToString, I: Iterator<Item = S>>(xs: I) -> String {
@@ -96,3 +297,142 @@ pub fn hash_strings<S: ToString, I: Iterator<Item = S>>(xs: I) -> String {
     s.truncate(6);
     s
 }
+
+/// Convert a path written in a source file or a manifest to a native one,
+/// so that paths using either `/` or `\` as a separator are accepted on
+/// every platform
+pub fn portable_path(path: &str) -> PathBuf {
+    if std::path::MAIN_SEPARATOR == '\\' {
+        PathBuf::from(path)
+    } else {
+        PathBuf::from(path.replace('\\', "/"))
+    }
+}
+
+const SIZE_UNITS: [(&str, usize); 4] = [("G", 1 << 30), ("M", 1 << 20), ("K", 1 << 10), ("", 1)];
+
+/// Render a number of bytes in a human-readable way, e.g. `1.5GB`
+pub fn human_size(bytes: usize) -> String {
+    let (unit, factor) = SIZE_UNITS
+        .iter()
+        .find(|(_, factor)| bytes >= *factor)
+        .unwrap_or(&SIZE_UNITS[3]);
+    format!("{:.1}{}B", bytes as f64 / *factor as f64, unit)
+}
+
+/// Parse a size in bytes, expressed in MB by default or with an explicit
+/// `K`, `M` or `G` suffix, e.g. `512`, `256K` or `4G`
+pub fn parse_size(s: &str) -> Result<usize> {
+    let s = s.trim().trim_end_matches(['B', 'b']);
+    let (digits, factor) = SIZE_UNITS
+        .iter()
+        .filter(|(unit, _)| !unit.is_empty())
+        .find_map(|(unit, factor)| {
+            s.strip_suffix(unit)
+                .or_else(|| s.strip_suffix(&unit.to_lowercase()))
+                .map(|digits| (digits, *factor))
+        })
+        .unwrap_or((s, 1 << 20));
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|x| x.checked_mul(factor))
+        .ok_or_else(|| anyhow!("invalid size: `{}`", s))
+}
+
+/// Parse a duration, expressed in seconds by default or with an explicit `s`,
+/// `m` or `h` suffix, e.g. `90`, `30s` or `2h`
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let (digits, factor) = [("h", 3600), ("m", 60), ("s", 1)]
+        .iter()
+        .find_map(|(unit, factor)| s.strip_suffix(unit).map(|digits| (digits, *factor)))
+        .unwrap_or((s, 1));
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|x| x.checked_mul(factor))
+        .map(Duration::from_secs)
+        .ok_or_else(|| anyhow!("invalid duration: `{}`", s))
+}
+
+/// Parse a half-open range of rows, e.g. `1000..2000` or `1_000..2_000`
+pub fn parse_row_range(s: &str) -> Result<Range<isize>> {
+    let parse = |x: &str| {
+        x.trim()
+            .replace('_', "")
+            .parse::<isize>()
+            .map_err(|_| anyhow!("invalid row: `{}`", x))
+    };
+    let (start, end) = s
+        .split_once("..")
+        .ok_or_else(|| anyhow!("expected a range of rows A..B, found `{}`", s))?;
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start < 0 || end <= start {
+        bail!("invalid range of rows: `{}`", s)
+    }
+    Ok(start..end)
+}
+
+/// Parse the assumption of the value of a constant, e.g. `SIZE=256` or
+/// `hub.SIZE=0x100`
+pub fn parse_assumption(s: &str) -> Result<(String, BigInt)> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected NAME=VALUE, found `{}`", s))?;
+    let value = value.trim();
+    let parsed = if let Some(hex) = value.strip_prefix("0x") {
+        BigInt::parse_bytes(hex.as_bytes(), 16)
+    } else {
+        BigInt::parse_bytes(value.as_bytes(), 10)
+    };
+    Ok((
+        name.trim().to_owned(),
+        parsed.ok_or_else(|| anyhow!("invalid value for {}: `{}`", name.trim(), value))?,
+    ))
+}
+
+/// Start the countdown of [`crate::DEADLINE`], if `timeout` is set; otherwise,
+/// remove any previously set deadline.
+pub(crate) fn arm_deadline(timeout: Option<Duration>) {
+    *crate::DEADLINE.write().unwrap() = timeout.map(|t| (t, Instant::now()));
+}
+
+/// Fail if [`crate::DEADLINE`] is set and has passed. Long-running loops
+/// call it regularly, so that they can be interrupted.
+pub(crate) fn ensure_deadline(stage: &'static str) -> Result<()> {
+    if let Some((timeout, start)) = *crate::DEADLINE.read().unwrap() {
+        if start.elapsed() > timeout {
+            bail!(RuntimeError::DeadlineExceeded { stage, timeout })
+        }
+    }
+    Ok(())
+}
+
+/// Whether `err` has been caused by the expiration of [`crate::DEADLINE`]
+pub(crate) fn is_deadline_exceeded(err: &Error) -> bool {
+    err.chain().any(|e| {
+        matches!(
+            e.downcast_ref::<RuntimeError>(),
+            Some(RuntimeError::DeadlineExceeded { .. })
+        )
+    })
+}
+
+/// Fail if the user asked for the current operation to be cancelled
+pub(crate) fn ensure_not_cancelled(stage: &'static str) -> Result<()> {
+    if crate::CANCELLED.load(std::sync::atomic::Ordering::Relaxed) {
+        bail!(RuntimeError::Cancelled { stage })
+    }
+    Ok(())
+}
+
+/// Whether `err` has been caused by a cancellation request
+pub(crate) fn is_cancelled(err: &Error) -> bool {
+    err.chain().any(|e| {
+        matches!(
+            e.downcast_ref::<RuntimeError>(),
+            Some(RuntimeError::Cancelled { .. })
+        )
+    })
+}
@@ -0,0 +1,82 @@
+use log::*;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+
+/// The version of the constraint language; it must be bumped whenever its
+/// syntax or its semantics change.
+pub const LANGUAGE_VERSION: u32 = 2;
+
+/// The optional features this build of corset has been compiled with
+const FEATURES: &[(&str, bool)] = &[
+    ("cli", cfg!(feature = "cli")),
+    ("conflater", cfg!(feature = "conflater")),
+    ("exporters", cfg!(feature = "exporters")),
+    ("inspector", cfg!(feature = "inspector")),
+    ("json-bin", cfg!(feature = "json-bin")),
+    ("plugins", cfg!(feature = "plugins")),
+    ("postgres", cfg!(feature = "postgres")),
+    ("sqlite", cfg!(feature = "sqlite")),
+];
+
+/// Describes the build of corset that produced an artifact
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    pub version: String,
+    pub git_hash: String,
+    pub build_date: String,
+    pub features: Vec<String>,
+    pub language_version: u32,
+    /// the SHA-256 of the standard library embedded in this build
+    #[serde(default)]
+    pub stdlib_hash: Option<String>,
+}
+impl BuildInfo {
+    /// The build information of the running corset
+    pub fn current() -> Self {
+        BuildInfo {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            git_hash: env!("GIT_HASH").to_owned(),
+            build_date: env!("BUILD_DATE").to_owned(),
+            features: FEATURES
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            language_version: LANGUAGE_VERSION,
+            stdlib_hash: Some(crate::compiler::parser::stdlib::stdlib_hash()),
+        }
+    }
+
+    /// Warn if an artifact produced by the build `self` is loaded by a
+    /// different version of corset.
+    pub fn check_compatibility(&self, artifact: &str) {
+        let current = BuildInfo::current();
+        if self.language_version != current.language_version {
+            warn!(
+                "{} was compiled for version {} of the constraint language, but this corset implements version {}",
+                artifact.bold(),
+                self.language_version.to_string().red().bold(),
+                current.language_version.to_string().bold()
+            );
+        } else if self.version != current.version || self.git_hash != current.git_hash {
+            warn!(
+                "{} was compiled by corset {} ({}), but is loaded by corset {} ({})",
+                artifact.bold(),
+                self.version.yellow().bold(),
+                self.git_hash,
+                current.version.bold(),
+                current.git_hash
+            );
+        }
+        if let (Some(theirs), Some(ours)) = (&self.stdlib_hash, &current.stdlib_hash) {
+            if theirs != ours {
+                warn!(
+                    "{} was compiled against the stdlib {}, but this corset embeds the stdlib {}",
+                    artifact.bold(),
+                    theirs.yellow().bold(),
+                    ours.bold()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Re-check a trace every time it, or the sources of the constraint set, are
+//! modified, so that the constraints may be iterated upon without manually
+//! re-running a full compilation and check every time.
+//!
+//! Files are watched through their parent directory, as most editors save a
+//! file by replacing it, which would otherwise silently end its watch.
+use anyhow::*;
+use log::*;
+use notify::{RecursiveMode, Watcher};
+use owo_colors::OwoColorize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    check::{self, FailureReport},
+    compiler::ConstraintSet,
+    compute, utils,
+};
+
+/// How long to wait for a burst of modifications, e.g. a `git checkout`, to
+/// settle before re-checking
+const SETTLE: Duration = Duration::from_millis(250);
+
+pub(crate) struct WatchSettings {
+    pub only: Option<Vec<String>>,
+    pub skip: Vec<String>,
+    pub limit_rows: Option<usize>,
+    /// how to detail the failing constraints
+    pub report: check::ReportStyle,
+    /// how long every re-check may take
+    pub timeout: Option<Duration>,
+}
+
+/// The files and directories whose modification triggers a re-check
+#[derive(Debug, Default)]
+pub(crate) struct Watched {
+    files: Vec<PathBuf>,
+    /// the directories of Corset sources, whose `.lisp` files are watched
+    dirs: Vec<PathBuf>,
+}
+impl Watched {
+    pub(crate) fn new(sources: &[String], trace: &str) -> Result<Watched> {
+        let mut r = Watched::default();
+        for path in sources
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(trace))
+        {
+            let path = std::fs::canonicalize(path)
+                .with_context(|| anyhow!("while watching `{}`", path))?;
+            if path.is_dir() {
+                r.dirs.push(path);
+            } else {
+                r.files.push(path);
+            }
+        }
+        Ok(r)
+    }
+
+    /// Whether a modification of `path` concerns the watched files
+    pub(crate) fn concerns(&self, path: &Path) -> bool {
+        self.files.iter().any(|f| f == path)
+            || (path.extension().map(|e| e == "lisp").unwrap_or(false)
+                && self.dirs.iter().any(|d| path.starts_with(d)))
+    }
+
+    fn subscribe(&self, watcher: &mut impl Watcher) -> Result<()> {
+        for d in self.dirs.iter() {
+            watcher.watch(d, RecursiveMode::Recursive)?;
+        }
+        for parent in self.files.iter().filter_map(|f| f.parent()) {
+            if !self.dirs.iter().any(|d| parent.starts_with(d)) {
+                watcher.watch(parent, RecursiveMode::NonRecursive)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compile the constraint set with `build` and check `trace` against it, then
+/// do it again every time one of them is modified; only returns on error.
+pub(crate) fn watch<F>(
+    sources: &[String],
+    trace: &str,
+    settings: WatchSettings,
+    mut build: F,
+) -> Result<()>
+where
+    F: FnMut() -> Result<ConstraintSet>,
+{
+    let watched = Watched::new(sources, trace)?;
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watched.subscribe(&mut watcher)?;
+
+    // the first failing row of every failing constraint on the last run
+    let mut failing: Option<BTreeMap<String, isize>> = None;
+    loop {
+        let start = Instant::now();
+        match run_once(&mut build, trace, &settings) {
+            Result::Ok(now) => {
+                report(failing.as_ref(), &now);
+                info!(
+                    "{}: {} in {:.2}s",
+                    trace,
+                    if now.is_empty() {
+                        "SUCCESS".green().bold().to_string()
+                    } else {
+                        format!("{} failing constraints", now.len())
+                            .red()
+                            .bold()
+                            .to_string()
+                    },
+                    start.elapsed().as_secs_f64()
+                );
+                failing = Some(now);
+            }
+            Err(e) => error!("{:?}", e),
+        }
+
+        info!("watching for modifications...");
+        let changed = wait_for_modification(&rx, &watched)?;
+        info!(
+            "`{}` modified, re-checking",
+            changed.display().to_string().bold()
+        );
+    }
+}
+
+/// Block until a watched file is modified, then let the modifications settle
+/// and return the first modified file
+fn wait_for_modification(
+    rx: &mpsc::Receiver<notify::Result<notify::Event>>,
+    watched: &Watched,
+) -> Result<PathBuf> {
+    let changed = loop {
+        let event = rx.recv().context("file watcher disconnected")??;
+        if event.kind.is_access() {
+            continue;
+        }
+        if let Some(path) = event.paths.into_iter().find(|p| watched.concerns(p)) {
+            break path;
+        }
+    };
+    while rx.recv_timeout(SETTLE).is_ok() {}
+    Ok(changed)
+}
+
+/// Compile the constraint set, and return the first failing row of every
+/// failing constraint on `trace`
+fn run_once<F>(
+    build: &mut F,
+    trace: &str,
+    settings: &WatchSettings,
+) -> Result<BTreeMap<String, isize>>
+where
+    F: FnMut() -> Result<ConstraintSet>,
+{
+    utils::arm_deadline(settings.timeout);
+    let mut cs = build()?;
+    cs.set_row_limit(settings.limit_rows);
+    compute::compute_trace_for(trace, &mut cs, false, &settings.only, &settings.skip)
+        .with_context(|| format!("while expanding `{}`", trace))?;
+
+    let failures = Arc::new(Mutex::new(FailureReport::default()));
+    let result = check::check(
+        &cs,
+        &settings.only,
+        &settings.skip,
+        check::CheckConfig::new()
+            .report(settings.report)
+            .record_failures(failures.clone()),
+    );
+
+    let mut r = BTreeMap::new();
+    for v in failures.lock().unwrap().violations.iter() {
+        let row = r.entry(v.constraint.clone()).or_insert(v.row);
+        *row = (*row).min(v.row);
+    }
+    // failing constraints are expected, but not failing to check them
+    if r.is_empty() {
+        result.with_context(|| format!("while checking {}", trace.bright_white().bold()))?;
+    }
+    Ok(r)
+}
+
+/// Print how the failing constraints evolved since the `previous` run
+fn report(previous: Option<&BTreeMap<String, isize>>, now: &BTreeMap<String, isize>) {
+    for (constraint, row) in now.iter() {
+        let status = match previous.map(|p| p.get(constraint)) {
+            None => String::new(),
+            Some(None) => format!(" {}", "(new)".red().bold()),
+            Some(Some(before)) if before != row => format!(" (was row {})", before),
+            Some(Some(_)) => String::new(),
+        };
+        warn!("{} fails on row {}{}", constraint.red().bold(), row, status);
+    }
+    for constraint in previous
+        .into_iter()
+        .flat_map(|p| p.keys())
+        .filter(|c| !now.contains_key(*c))
+    {
+        info!("{} now passes", constraint.green().bold());
+    }
+}
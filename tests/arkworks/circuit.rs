@@ -0,0 +1,183 @@
+//! The constraint system exported by Corset, as an arkworks circuit.
+#![allow(non_snake_case, unused, clippy::all)]
+
+use ark_ff::PrimeField;
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use std::cmp::Ordering;
+
+/// The values of a column, allocated as witnesses of the constraint system
+#[derive(Clone)]
+pub struct Column<F: PrimeField>(Vec<FpVar<F>>);
+impl<F: PrimeField> Column<F> {
+    fn new_witness(cs: ConstraintSystemRef<F>, values: &[F]) -> Result<Self, SynthesisError> {
+        values
+            .iter()
+            .map(|x| FpVar::new_witness(cs.clone(), || Ok(*x)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Column)
+    }
+
+    fn interleave(columns: &[&Column<F>]) -> Self {
+        Column(
+            (0..columns[0].len())
+                .flat_map(|i| columns.iter().map(move |c| c.0[i].clone()))
+                .collect(),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn values(&self) -> &[FpVar<F>] {
+        &self.0
+    }
+
+    /// The cell at row `i`, or `None` if it lies outside of the column
+    fn at(&self, i: isize) -> Option<FpVar<F>> {
+        usize::try_from(i).ok().and_then(|i| self.0.get(i)).cloned()
+    }
+}
+
+/// The arguments that can not be expressed as R1CS constraints, and must be
+/// implemented by the prover, typically from a verifier challenge
+pub trait Arguments<F: PrimeField> {
+    /// Enforce that the rows of `included` all appear in `including`
+    fn inclusion(
+        &self,
+        cs: ConstraintSystemRef<F>,
+        name: &str,
+        including: &[&Column<F>],
+        included: &[&Column<F>],
+    ) -> Result<(), SynthesisError>;
+
+    /// Enforce that the rows of `to` are a permutation of the rows of `from`
+    fn permutation(
+        &self,
+        cs: ConstraintSystemRef<F>,
+        name: &str,
+        from: &[&Column<F>],
+        to: &[&Column<F>],
+    ) -> Result<(), SynthesisError>;
+}
+
+/// The values of the committed columns
+pub struct Trace<F: PrimeField> {
+    /// A
+    pub _prelude___A: Vec<F>,
+    /// B
+    pub _prelude___B: Vec<F>,
+}
+
+/// The circuit proving that a trace satisfies the constraints
+pub struct Circuit<F: PrimeField, A: Arguments<F>> {
+    pub trace: Trace<F>,
+    pub arguments: A,
+}
+
+fn constant<F: PrimeField>(x: &str) -> FpVar<F> {
+    let parse = |x: &str| F::from_str(x).unwrap_or_else(|_| panic!("invalid constant {}", x));
+    FpVar::constant(match x.strip_prefix('-') {
+        Some(x) => -parse(x),
+        None => parse(x),
+    })
+}
+
+/// `x` raised to the constant power `exp`, by square-and-multiply
+fn pow<F: PrimeField>(x: FpVar<F>, exp: u64) -> FpVar<F> {
+    let mut r = FpVar::one();
+    for k in (0..u64::BITS - exp.leading_zeros()).rev() {
+        r = &r * &r;
+        if (exp >> k) & 1 == 1 {
+            r = &r * &x;
+        }
+    }
+    r
+}
+
+/// Enforce that `body` vanishes on all the rows of a column of length `len`,
+/// except those where it reads outside of its columns
+fn vanishes_everywhere<F: PrimeField>(
+    len: usize,
+    body: impl Fn(isize) -> Option<FpVar<F>>,
+) -> Result<(), SynthesisError> {
+    for i in 0..len as isize {
+        if let Some(x) = body(i) {
+            x.enforce_equal(&FpVar::zero())?;
+        }
+    }
+    Ok(())
+}
+
+/// Enforce that `body` vanishes on row `i` of a column of length `len`;
+/// negative rows are counted from the end
+fn vanishes_at<F: PrimeField>(
+    len: usize,
+    i: isize,
+    body: impl Fn(isize) -> Option<FpVar<F>>,
+) -> Result<(), SynthesisError> {
+    let i = if i < 0 { len as isize + i } else { i };
+    if let Some(x) = body(i) {
+        x.enforce_equal(&FpVar::zero())?;
+    }
+    Ok(())
+}
+
+/// Enforce that `body` is strictly smaller than `max` on all the rows of a
+/// column of length `len`; `max` must not exceed (p - 1)/2
+fn in_range<F: PrimeField>(
+    len: usize,
+    max: &str,
+    body: impl Fn(isize) -> Option<FpVar<F>>,
+) -> Result<(), SynthesisError> {
+    let max = constant(max);
+    for i in 0..len as isize {
+        if let Some(x) = body(i) {
+            x.enforce_cmp(&max, Ordering::Less, false)?;
+        }
+    }
+    Ok(())
+}
+
+impl<F: PrimeField, A: Arguments<F>> ConstraintSynthesizer<F> for Circuit<F, A> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        //
+        // Committed columns
+        //
+        let _prelude___A = Column::new_witness(cs.clone(), &self.trace._prelude___A)?;
+        let _prelude___B = Column::new_witness(cs.clone(), &self.trace._prelude___B)?;
+
+        //
+        // Interleaved columns
+        //
+        let _prelude___I = Column::interleave(&[&_prelude___A, &_prelude___B]);
+
+        //
+        // Constraints
+        //
+        // defined at Immediate expression:4:1
+        // cube
+        vanishes_at(_prelude___A.len(), 0, |i| Some(pow(_prelude___A.at(i)?, 3)))?;
+        // defined at Immediate expression:3:1
+        // double
+        vanishes_everywhere(_prelude___A.len(), |i| {
+            Some((_prelude___B.at(i)? - (constant("2") * _prelude___A.at(i)?)))
+        })?;
+        // defined at Immediate expression:5:1
+        // interleaved
+        vanishes_at(_prelude___I.len(), 3, |i| {
+            Some((_prelude___I.at(i)? - constant("2")))
+        })?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,3 @@
+(include "cycle-b.lisp")
+(module m)
+(defcolumns A)
@@ -0,0 +1,3 @@
+(include "cycle-a.lisp")
+(module m)
+(defcolumns B)
@@ -0,0 +1,4 @@
+(include "columns.lisp")
+(module m)
+(defcolumns A)
+(defconstraint a-is-x () (eq! A X))
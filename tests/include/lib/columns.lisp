@@ -0,0 +1,2 @@
+(module m)
+(defcolumns X (B :binary))
@@ -0,0 +1,5 @@
+;; columns.lisp is included by both main.lisp and lib/a.lisp, but must only be
+;; compiled once
+(include "lib/a.lisp" "lib/columns.lisp")
+(module m)
+(defconstraint b-implies-a () (if-not-zero B (vanishes! A)))